@@ -27,8 +27,9 @@ pub const LAST_SYNC_META_KEY: &str = "last_sync_time";
 pub const GLOBAL_SYNCID_META_KEY: &str = "global_sync_id";
 pub const COLLECTION_SYNCID_META_KEY: &str = "sync_id";
 
-// A trait to abstract the broader sync processes.
-pub trait SyncEngineStorageImpl<T> {
+// A trait to abstract the broader sync processes. Send + Sync so the engine can be synced in
+// parallel with others.
+pub trait SyncEngineStorageImpl<T>: Send + Sync {
     fn get_incoming_impl(
         &self,
         enc_key: &Option<String>,
@@ -231,6 +232,12 @@ impl<T: SyncRecord + std::fmt::Debug> SyncEngine for ConfigSyncEngine<T> {
         log::warn!("not implemented as there isn't a valid use case for it");
         Ok(())
     }
+
+    // Addresses and credit cards share a database, but each locks it as needed and keeps its
+    // sync state separately, so they can be synced alongside each other.
+    fn as_parallel(&self) -> Option<&(dyn SyncEngine + Sync)> {
+        Some(self)
+    }
 }
 
 #[cfg(test)]
@@ -45,6 +45,13 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun undoDelete(id: String): Boolean {
+        return writeQueryCounters.measure {
+            store.undoDelete(id)
+        }
+    }
+
     @Throws(LoginsApiException::class)
     fun get(id: String): EncryptedLogin? {
         return readQueryCounters.measure {
@@ -59,6 +66,20 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun rotateKey(oldKey: String, newKey: String, canaryText: String): String {
+        return writeQueryCounters.measure {
+            store.rotateKey(oldKey, newKey, canaryText)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun deleteUndecryptableRecords(encryptionKey: String): KeyRecoveryReport {
+        return writeQueryCounters.measure {
+            store.deleteUndecryptableRecords(encryptionKey)
+        }
+    }
+
     @Throws(LoginsApiException::class)
     fun list(): List<EncryptedLogin> {
         return readQueryCounters.measure {
@@ -66,6 +87,20 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun getMostUsed(limit: UInt): List<EncryptedLogin> {
+        return readQueryCounters.measure {
+            store.getMostUsed(limit)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun getUnusedSince(timestamp: Long): List<EncryptedLogin> {
+        return readQueryCounters.measure {
+            store.getUnusedSince(timestamp)
+        }
+    }
+
     @Throws(LoginsApiException::class)
     fun getByBaseDomain(baseDomain: String): List<EncryptedLogin> {
         return readQueryCounters.measure {
@@ -73,6 +108,28 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun getByRelatedRealm(origin: String): List<EncryptedLogin> {
+        return readQueryCounters.measure {
+            store.getByRelatedRealm(origin)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun getSuggestions(origin: String): List<EncryptedLogin> {
+        return readQueryCounters.measure {
+            store.getSuggestions(origin)
+        }
+    }
+
+    fun setPublicSuffixList(list: String) {
+        store.setPublicSuffixList(list)
+    }
+
+    fun setRelatedRealms(groups: List<List<String>>) {
+        store.setRelatedRealms(groups)
+    }
+
     @Throws(LoginsApiException::class)
     fun findLoginToUpdate(look: LoginEntry, encryptionKey: String): Login? {
         return readQueryCounters.measure {
@@ -80,6 +137,41 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun importCsv(csv: String, encryptionKey: String): CsvImportResult {
+        return writeQueryCounters.measure {
+            store.importCsv(csv, encryptionKey)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun exportEncrypted(password: String, encryptionKey: String): String {
+        return readQueryCounters.measure {
+            store.exportEncrypted(password, encryptionKey)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun importEncrypted(archive: String, password: String, encryptionKey: String): UInt {
+        return writeQueryCounters.measure {
+            store.importEncrypted(archive, password, encryptionKey)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun findDuplicates(look: LoginEntry, encryptionKey: String): List<EncryptedLogin> {
+        return readQueryCounters.measure {
+            store.findDuplicates(look, encryptionKey)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun mergeLogins(ids: List<String>, encryptionKey: String): EncryptedLogin {
+        return writeQueryCounters.measure {
+            store.mergeLogins(ids, encryptionKey)
+        }
+    }
+
     @Throws(LoginsApiException::class)
     fun add(entry: LoginEntry, encryptionKey: String): EncryptedLogin {
         return writeQueryCounters.measure {
@@ -101,6 +193,55 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    @Throws(LoginsApiException::class)
+    fun addOrUpdateMany(entries: List<LoginEntry>, encryptionKey: String): List<BulkResultEntry> {
+        return writeQueryCounters.measure {
+            store.addOrUpdateMany(entries, encryptionKey)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun recordBreach(id: String, timestamp: Long) {
+        writeQueryCounters.measure {
+            store.recordBreach(id, timestamp)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun recordBreachAlertDismissal(id: String) {
+        writeQueryCounters.measure {
+            store.recordBreachAlertDismissal(id)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun recordVulnerablePassword(id: String, vulnerability: PasswordVulnerability) {
+        writeQueryCounters.measure {
+            store.recordVulnerablePassword(id, vulnerability)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun getAlerts(id: String): LoginAlerts? {
+        return readQueryCounters.measure {
+            store.getAlerts(id)
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun listAlerts(): List<LoginAlerts> {
+        return readQueryCounters.measure {
+            store.listAlerts()
+        }
+    }
+
+    @Throws(LoginsApiException::class)
+    fun resetAllAlerts() {
+        writeQueryCounters.measure {
+            store.resetAllAlerts()
+        }
+    }
+
     fun registerWithSyncManager() {
         return store.registerWithSyncManager()
     }
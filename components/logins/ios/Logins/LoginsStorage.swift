@@ -27,6 +27,25 @@ open class LoginsStorage {
         }
     }
 
+    /// Re-encrypt every login with `newKey`. Returns a canary for `canaryText` made with
+    /// `newKey`, to replace the canary made with `oldKey`.
+    ///
+    /// Throws `LoginStoreError.IncorrectKey`, without changing anything, if `oldKey` is wrong.
+    open func rotateKey(oldKey: String, newKey: String, canaryText: String) throws -> String {
+        return try queue.sync {
+            try self.store.rotateKey(oldKey: oldKey, newKey: newKey, canaryText: canaryText)
+        }
+    }
+
+    /// Recover from losing the encryption key: when `checkCanary` fails and a new key has been
+    /// made, delete every record which can't be decrypted with `encryptionKey`, without deleting
+    /// it on other devices. The next sync downloads the synced records again.
+    open func deleteUndecryptableRecords(encryptionKey: String) throws -> KeyRecoveryReport {
+        return try queue.sync {
+            try self.store.deleteUndecryptableRecords(encryptionKey: encryptionKey)
+        }
+    }
+
     /// Delete the record with the given ID. Returns false if no such record existed.
     open func delete(id: String) throws -> Bool {
         return try queue.sync {
@@ -34,6 +53,14 @@ open class LoginsStorage {
         }
     }
 
+    /// Restore a record deleted in the last couple of minutes, as it was before it was deleted.
+    /// Returns false if it can no longer be restored.
+    open func undoDelete(id: String) throws -> Bool {
+        return try queue.sync {
+            try self.store.undoDelete(id: id)
+        }
+    }
+
     /// Bump the usage count for the record with the given id.
     ///
     /// Throws `LoginStoreError.NoSuchRecord` if there was no such record.
@@ -61,6 +88,14 @@ open class LoginsStorage {
         }
     }
 
+    /// Add or update many logins in a single transaction, which is much faster than saving them one
+    /// at a time. Invalid logins are reported in the results, in the same order as `logins`.
+    open func addOrUpdateMany(logins: [LoginEntry], encryptionKey: String) throws -> [BulkResultEntry] {
+        return try queue.sync {
+            try self.store.addOrUpdateMany(logins: logins, encryptionKey: encryptionKey)
+        }
+    }
+
     /// Get the record with the given id. Returns nil if there is no such record.
     open func get(id: String) throws -> EncryptedLogin? {
         return try queue.sync {
@@ -75,6 +110,21 @@ open class LoginsStorage {
         }
     }
 
+    /// Get the `limit` most used records, most used first.
+    open func getMostUsed(limit: UInt32) throws -> [EncryptedLogin] {
+        return try queue.sync {
+            try self.store.getMostUsed(limit: limit)
+        }
+    }
+
+    /// Get the records which haven't been used, or created, since `timestamp` (in milliseconds),
+    /// least recently used first.
+    open func getUnusedSince(timestamp: Int64) throws -> [EncryptedLogin] {
+        return try queue.sync {
+            try self.store.getUnusedSince(timestamp: timestamp)
+        }
+    }
+
     /// Get the list of records for some base domain.
     open func getByBaseDomain(baseDomain: String) throws -> [EncryptedLogin] {
         return try queue.sync {
@@ -82,6 +132,127 @@ open class LoginsStorage {
         }
     }
 
+    /// Import the logins in a CSV file exported by Firefox, Chrome, LastPass or Bitwarden.
+    ///
+    /// Returns how many logins were added, and the rows which couldn't be imported.
+    open func importCsv(csv: String, encryptionKey: String) throws -> CsvImportResult {
+        return try queue.sync {
+            try self.store.importCsv(csv: csv, encryptionKey: encryptionKey)
+        }
+    }
+
+    /// Export every login as an archive encrypted with a key derived from `password`.
+    open func exportEncrypted(password: String, encryptionKey: String) throws -> String {
+        return try queue.sync {
+            try self.store.exportEncrypted(password: password, encryptionKey: encryptionKey)
+        }
+    }
+
+    /// Import the logins in an archive made by `exportEncrypted`. Returns the number of
+    /// logins which were added.
+    ///
+    /// Throws `LoginStoreError.IncorrectKey` if the password is wrong.
+    open func importEncrypted(archive: String, password: String, encryptionKey: String) throws -> UInt32 {
+        return try queue.sync {
+            try self.store.importEncrypted(archive: archive, password: password, encryptionKey: encryptionKey)
+        }
+    }
+
+    /// Get the saved logins with the same origin and username as `login`, whatever their
+    /// form fields. If `login` is saved, it is included.
+    open func findDuplicates(login: LoginEntry, encryptionKey: String) throws -> [EncryptedLogin] {
+        return try queue.sync {
+            try self.store.findDuplicates(look: login, encryptionKey: encryptionKey)
+        }
+    }
+
+    /// Merge the logins with the given ids, which must have the same origin and username,
+    /// into the first of them. The others are deleted.
+    ///
+    /// Throws `LoginStoreError.NoSuchRecord` if any of the logins don't exist.
+    open func mergeLogins(ids: [String], encryptionKey: String) throws -> EncryptedLogin {
+        return try queue.sync {
+            try self.store.mergeLogins(ids: ids, encryptionKey: encryptionKey)
+        }
+    }
+
+    /// Record that the site for the login with the given id was breached at `timestamp`,
+    /// in milliseconds.
+    ///
+    /// Throws `LoginStoreError.NoSuchRecord` if there was no such record.
+    open func recordBreach(id: String, timestamp: Int64) throws {
+        try queue.sync {
+            try self.store.recordBreach(id: id, timestamp: timestamp)
+        }
+    }
+
+    /// Record that the user dismissed the breach alert for the login with the given id.
+    open func recordBreachAlertDismissal(id: String) throws {
+        try queue.sync {
+            try self.store.recordBreachAlertDismissal(id: id)
+        }
+    }
+
+    /// Record that the password for the login with the given id is vulnerable.
+    open func recordVulnerablePassword(id: String, vulnerability: PasswordVulnerability) throws {
+        try queue.sync {
+            try self.store.recordVulnerablePassword(id: id, vulnerability: vulnerability)
+        }
+    }
+
+    /// Get the alerts for the login with the given id. Returns nil if there is no such record.
+    open func getAlerts(id: String) throws -> LoginAlerts? {
+        return try queue.sync {
+            try self.store.getAlerts(id: id)
+        }
+    }
+
+    /// Get the alerts for every login that's breached or has a vulnerable password.
+    open func listAlerts() throws -> [LoginAlerts] {
+        return try queue.sync {
+            try self.store.listAlerts()
+        }
+    }
+
+    /// Forget every recorded breach, dismissal and vulnerable password.
+    open func resetAllAlerts() throws {
+        try queue.sync {
+            try self.store.resetAllAlerts()
+        }
+    }
+
+    /// Get the logins to offer on a page with the given origin, best match first: the same
+    /// origin, then the same host, then the same site, then related sites.
+    open func getByRelatedRealm(origin: String) throws -> [EncryptedLogin] {
+        return try queue.sync {
+            try self.store.getByRelatedRealm(origin: origin)
+        }
+    }
+
+    /// Get the logins to suggest for autofill on a page with the given origin. Like
+    /// `getByRelatedRealm`, better matches come first, but logins which match equally well are
+    /// ranked by how often, and how recently, they were used.
+    open func getSuggestions(origin: String) throws -> [EncryptedLogin] {
+        return try queue.sync {
+            try self.store.getSuggestions(origin: origin)
+        }
+    }
+
+    /// Set the Public Suffix List used by `getByRelatedRealm`, in the format of
+    /// `public_suffix_list.dat`. Until it's set, each host is its own site.
+    open func setPublicSuffixList(list: String) {
+        queue.sync {
+            self.store.setPublicSuffixList(list: list)
+        }
+    }
+
+    /// Set the groups of sites, like `["apple.com", "icloud.com"]`, which share logins.
+    open func setRelatedRealms(groups: [[String]]) {
+        queue.sync {
+            self.store.setRelatedRealms(groups: groups)
+        }
+    }
+
     /// Register with the sync manager
     open func registerWithSyncManager() {
         return queue.sync {
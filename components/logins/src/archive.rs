@@ -0,0 +1,250 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Password-protected archives of logins, so users can back up their logins,
+//! or move them to another profile, without writing them out as plaintext.
+//!
+//! An archive is a JSON object holding the parameters used to derive a key
+//! from the user's password, and the logins, encrypted as a JWE with that key
+//! in the same way we encrypt `secFields` with the app's key:
+//!
+//! ```json
+//! {"version": 1, "salt": "...", "iterations": 600000, "logins": "<JWE>"}
+//! ```
+
+use crate::encryption::EncryptorDecryptor;
+use crate::error::*;
+use crate::login::{Login, LoginFields, RecordFields, SecureLoginFields};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jwcrypto::{Jwk, JwkKeyParameters};
+use rc_crypto::{pbkdf2, rand};
+use serde_derive::*;
+
+const ARCHIVE_VERSION: u32 = 1;
+// The OWASP recommendation for PBKDF2-HMAC-SHA256.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// The most iterations we'll run when opening an archive, so that a crafted
+// archive can't make us spin for minutes. This leaves room to raise
+// `PBKDF2_ITERATIONS` later, without older versions refusing the archives.
+const MAX_PBKDF2_ITERATIONS: u32 = 10 * PBKDF2_ITERATIONS;
+const SALT_LEN: usize = 16;
+// We only support AES256, which has a 32 byte key.
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    salt: String,
+    iterations: u32,
+    logins: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedLogin {
+    origin: String,
+    http_realm: Option<String>,
+    form_action_origin: Option<String>,
+    username_field: String,
+    password_field: String,
+    username: String,
+    password: String,
+    // Archives made before we had notes don't have them.
+    #[serde(default)]
+    notes: String,
+    time_created: i64,
+    time_last_used: i64,
+    time_password_changed: i64,
+    times_used: i64,
+}
+
+fn encryptor_for_password(
+    password: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<EncryptorDecryptor> {
+    let mut key = vec![0u8; KEY_LEN];
+    pbkdf2::derive(
+        password.as_bytes(),
+        salt,
+        iterations,
+        pbkdf2::HashAlgorithm::SHA256,
+        &mut key,
+    )?;
+    let jwk = Jwk {
+        kid: None,
+        key_parameters: JwkKeyParameters::Direct {
+            k: URL_SAFE_NO_PAD.encode(&key),
+        },
+    };
+    EncryptorDecryptor::new(&serde_json::to_string(&jwk)?)
+}
+
+/// Encrypts `logins` with a key derived from `password`.
+pub(crate) fn seal(logins: Vec<Login>, password: &str) -> Result<String> {
+    if password.is_empty() {
+        return Err(Error::ArchiveError("the password is empty".into()));
+    }
+    let mut salt = vec![0u8; SALT_LEN];
+    rand::fill(&mut salt)?;
+    let encdec = encryptor_for_password(password, &salt, PBKDF2_ITERATIONS)?;
+    let archived = logins
+        .into_iter()
+        .map(|login| ArchivedLogin {
+            origin: login.fields.origin,
+            http_realm: login.fields.http_realm,
+            form_action_origin: login.fields.form_action_origin,
+            username_field: login.fields.username_field,
+            password_field: login.fields.password_field,
+            username: login.sec_fields.username,
+            password: login.sec_fields.password,
+            notes: login.sec_fields.notes.unwrap_or_default(),
+            time_created: login.record.time_created,
+            time_last_used: login.record.time_last_used,
+            time_password_changed: login.record.time_password_changed,
+            times_used: login.record.times_used,
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string(&Archive {
+        version: ARCHIVE_VERSION,
+        salt: URL_SAFE_NO_PAD.encode(&salt),
+        iterations: PBKDF2_ITERATIONS,
+        logins: encdec.encrypt_struct(&archived, "seal logins archive")?,
+    })?)
+}
+
+/// Decrypts the logins in an archive made by `seal()`. The logins don't have
+/// ids.
+pub(crate) fn open(archive: &str, password: &str) -> Result<Vec<Login>> {
+    let archive: Archive = serde_json::from_str(archive)
+        .map_err(|_| Error::ArchiveError("not a logins archive".into()))?;
+    if archive.version != ARCHIVE_VERSION {
+        return Err(Error::ArchiveError(format!(
+            "unsupported version {}",
+            archive.version
+        )));
+    }
+    // We never make archives with fewer iterations, so one that has them was
+    // made by something else, and would be easier to brute force.
+    if !(PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS).contains(&archive.iterations) {
+        return Err(Error::ArchiveError(format!(
+            "unsupported iteration count {}",
+            archive.iterations
+        )));
+    }
+    let salt = URL_SAFE_NO_PAD
+        .decode(&archive.salt)
+        .map_err(|_| Error::ArchiveError("invalid salt".into()))?;
+    let encdec = encryptor_for_password(password, &salt, archive.iterations)?;
+    // A different key is the only reason we'd fail to decrypt an archive that
+    // we made.
+    let archived: Vec<ArchivedLogin> = encdec
+        .decrypt_struct(&archive.logins, "open logins archive")
+        .map_err(|_| Error::IncorrectArchivePassword)?;
+    Ok(archived
+        .into_iter()
+        .map(|login| Login {
+            record: RecordFields {
+                id: String::new(),
+                time_created: login.time_created,
+                time_last_used: login.time_last_used,
+                time_password_changed: login.time_password_changed,
+                times_used: login.times_used,
+            },
+            fields: LoginFields {
+                origin: login.origin,
+                http_realm: login.http_realm,
+                form_action_origin: login.form_action_origin,
+                username_field: login.username_field,
+                password_field: login.password_field,
+            },
+            sec_fields: SecureLoginFields {
+                username: login.username,
+                password: login.password,
+                notes: if login.notes.is_empty() {
+                    None
+                } else {
+                    Some(login.notes)
+                },
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let login = Login {
+            record: RecordFields {
+                id: "login".into(),
+                time_created: 1000,
+                time_last_used: 3000,
+                time_password_changed: 2000,
+                times_used: 5,
+            },
+            fields: LoginFields {
+                origin: "https://www.example.com".into(),
+                form_action_origin: Some("https://www.example.com".into()),
+                username_field: "user_input".into(),
+                password_field: "pass_input".into(),
+                ..Default::default()
+            },
+            sec_fields: SecureLoginFields {
+                username: "user".into(),
+                password: "password".into(),
+                ..Default::default()
+            },
+        };
+        let sealed = seal(vec![login.clone()], "hunter2").unwrap();
+        assert!(!sealed.contains("www.example.com"));
+        assert!(!sealed.contains("password"));
+
+        let opened = open(&sealed, "hunter2").unwrap();
+        assert_eq!(
+            opened,
+            vec![Login {
+                record: RecordFields {
+                    id: String::new(),
+                    ..login.record
+                },
+                ..login
+            }]
+        );
+
+        assert!(matches!(
+            open(&sealed, "hunter3"),
+            Err(Error::IncorrectArchivePassword)
+        ));
+        assert!(matches!(
+            open("not json", "hunter2"),
+            Err(Error::ArchiveError(_))
+        ));
+        assert!(matches!(seal(vec![], ""), Err(Error::ArchiveError(_))));
+    }
+
+    #[test]
+    fn test_open_checks_iterations() {
+        let sealed = seal(vec![], "hunter2").unwrap();
+        let with_iterations = |iterations: u32| {
+            let mut archive: Archive = serde_json::from_str(&sealed).unwrap();
+            archive.iterations = iterations;
+            serde_json::to_string(&archive).unwrap()
+        };
+        for iterations in [0, 1, PBKDF2_ITERATIONS - 1, MAX_PBKDF2_ITERATIONS + 1] {
+            assert!(matches!(
+                open(&with_iterations(iterations), "hunter2"),
+                Err(Error::ArchiveError(_))
+            ));
+        }
+        // More iterations than we use are fine, but change the key.
+        assert!(matches!(
+            open(&with_iterations(PBKDF2_ITERATIONS + 1), "hunter2"),
+            Err(Error::IncorrectArchivePassword)
+        ));
+        assert_eq!(open(&sealed, "hunter2").unwrap(), vec![]);
+    }
+}
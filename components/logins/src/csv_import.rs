@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Importing logins from the CSV files that password managers export.
+//!
+//! We understand the columns used by Firefox, Chrome, LastPass and Bitwarden,
+//! in any order, by their names in the header row. Only the origin and
+//! password are required.
+//!
+//! We parse the CSV ourselves rather than dragging in a CSV crate - the
+//! format these exports use is simple enough (RFC 4180, with quoted fields
+//! which can contain commas, quotes and newlines).
+
+use crate::error::*;
+use crate::login::{LoginEntry, LoginFields, SecureLoginFields};
+
+/// The outcome of importing a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CsvImportResult {
+    /// The number of logins which were added.
+    pub num_added: u32,
+    /// The number of logins which were already saved, with the same password.
+    pub num_unchanged: u32,
+    /// The rows which couldn't be imported.
+    pub failures: Vec<CsvImportFailure>,
+}
+
+/// A row of a CSV file which couldn't be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvImportFailure {
+    /// The line of the file the row starts on, counting from 1 for the header.
+    pub line: u32,
+    /// Why the row couldn't be imported. This never includes the row's data.
+    pub reason: String,
+}
+
+// The names each column goes by, lowercased.
+const ORIGIN_COLUMNS: &[&str] = &["url", "login_uri", "origin", "hostname"];
+const USERNAME_COLUMNS: &[&str] = &["username", "login_username", "login"];
+const PASSWORD_COLUMNS: &[&str] = &["password", "login_password"];
+// Chrome calls the notes "note", and LastPass calls them "extra".
+const NOTES_COLUMNS: &[&str] = &["notes", "note", "extra"];
+const HTTP_REALM_COLUMNS: &[&str] = &["httprealm"];
+const FORM_ACTION_ORIGIN_COLUMNS: &[&str] = &["formactionorigin"];
+// Bitwarden exports other kinds of items, like notes and cards, alongside
+// logins.
+const TYPE_COLUMNS: &[&str] = &["type"];
+
+// LastPass uses this URL for secure notes.
+const LASTPASS_SECURE_NOTE_URL: &str = "http://sn";
+
+/// A row of a CSV file, as a login to import, or why it isn't one.
+pub(crate) type CsvRow = (u32, std::result::Result<LoginEntry, String>);
+
+/// Parses the logins in a CSV file, without validating them.
+pub(crate) fn parse_logins(csv: &str) -> Result<Vec<CsvRow>> {
+    let mut records = parse_records(csv.trim_start_matches('\u{feff}'))?.into_iter();
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+    let find_column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|column| names.contains(&column.trim().to_lowercase().as_str()))
+    };
+    let origin_column = find_column(ORIGIN_COLUMNS)
+        .ok_or_else(|| Error::MalformedCsv("missing a URL column".into()))?;
+    let password_column = find_column(PASSWORD_COLUMNS)
+        .ok_or_else(|| Error::MalformedCsv("missing a password column".into()))?;
+    let username_column = find_column(USERNAME_COLUMNS);
+    let http_realm_column = find_column(HTTP_REALM_COLUMNS);
+    let form_action_origin_column = find_column(FORM_ACTION_ORIGIN_COLUMNS);
+    let notes_column = find_column(NOTES_COLUMNS);
+    let type_column = find_column(TYPE_COLUMNS);
+
+    Ok(records
+        // Skip blank lines.
+        .filter(|(_, record)| !(record.len() == 1 && record[0].is_empty()))
+        .map(|(line, record)| {
+            let get = |column: Option<usize>| {
+                column
+                    .and_then(|i| record.get(i))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            let origin = get(Some(origin_column));
+            let item_type = get(type_column);
+            if origin == LASTPASS_SECURE_NOTE_URL || !(item_type.is_empty() || item_type == "login")
+            {
+                return (line, Err("Not a login".to_string()));
+            }
+            let http_realm = Some(get(http_realm_column)).filter(|realm| !realm.is_empty());
+            // Most password managers don't know which form a login is for,
+            // so we use an empty `form_action_origin`, which matches any form.
+            let form_action_origin = match http_realm {
+                Some(_) => None,
+                None => Some(get(form_action_origin_column)),
+            };
+            let entry = LoginEntry {
+                fields: LoginFields {
+                    origin: with_scheme(origin),
+                    http_realm,
+                    form_action_origin,
+                    ..Default::default()
+                },
+                sec_fields: SecureLoginFields {
+                    username: get(username_column),
+                    // Passwords can legitimately start or end with spaces.
+                    password: record.get(password_column).cloned().unwrap_or_default(),
+                    notes: Some(get(notes_column)).filter(|notes| !notes.is_empty()),
+                },
+            };
+            (line, Ok(entry))
+        })
+        .collect())
+}
+
+// Some password managers save the host without a scheme, like `example.com`.
+fn with_scheme(origin: String) -> String {
+    if origin.is_empty() || origin.contains("://") {
+        origin
+    } else {
+        format!("https://{}", origin)
+    }
+}
+
+// Splits a CSV file into records, each with the line it starts on.
+fn parse_records(csv: &str) -> Result<Vec<(u32, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut in_quotes = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(Error::MalformedCsv(format!(
+            "unterminated quoted field on line {}",
+            record_line
+        )));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_records() {
+        assert_eq!(
+            parse_records("a,b\r\n\"c,\"\"d\"\"\",\"e\nf\"\ng,\n").unwrap(),
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (2, vec!["c,\"d\"".to_string(), "e\nf".to_string()]),
+                (4, vec!["g".to_string(), "".to_string()]),
+            ]
+        );
+        assert!(parse_records("a,\"b\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_logins() {
+        // Chrome's column conventions.
+        let rows = parse_logins(
+            "name,url,username,password,note\n\
+             example.com,https://example.com/login?next=1,user, p4ss ,Work account\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![(
+                2,
+                Ok(LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://example.com/login?next=1".into(),
+                        form_action_origin: Some("".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: " p4ss ".into(),
+                        notes: "Work account".into(),
+                    },
+                })
+            )]
+        );
+
+        // LastPass, with a secure note.
+        let rows = parse_logins(
+            "url,username,password,totp,extra,name,grouping,fav\n\
+             http://sn,,,,secret,note,,0\n\
+             example.com,user,pass,,\"PIN: 1234\nSecurity question: blue\",Example,,0\n",
+        )
+        .unwrap();
+        assert_eq!(rows[0], (2, Err("Not a login".to_string())));
+        let entry = rows[1].1.as_ref().unwrap();
+        assert_eq!(entry.fields.origin, "https://example.com");
+        assert_eq!(
+            entry.sec_fields.notes,
+            Some("PIN: 1234\nSecurity question: blue".into())
+        );
+
+        // Bitwarden, with a card.
+        let rows = parse_logins(
+            "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+             ,,card,Visa,,,0,,,,\n\
+             ,,login,Example,,,0,https://example.com,user,pass,\n",
+        )
+        .unwrap();
+        assert_eq!(rows[0], (2, Err("Not a login".to_string())));
+        let entry = rows[1].1.as_ref().unwrap();
+        assert_eq!(entry.sec_fields.username, "user");
+        assert_eq!(entry.sec_fields.password, "pass");
+
+        // Firefox, with an HTTP auth login.
+        let rows = parse_logins(
+            "\u{feff}\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\"\n\
+             \"https://example.com\",\"user\",\"pass\",\"My Realm\",\"\"\n",
+        )
+        .unwrap();
+        let entry = rows[0].1.as_ref().unwrap();
+        assert_eq!(entry.fields.http_realm, Some("My Realm".into()));
+        assert_eq!(entry.fields.form_action_origin, None);
+
+        assert!(parse_logins("name,username,password\nfoo,bar,baz\n").is_err());
+        assert_eq!(parse_logins("").unwrap(), vec![]);
+    }
+}
@@ -22,9 +22,11 @@
 ///     server.
 ///   - After we sync, we move all records from loginsL to loginsM, overwriting any previous data.
 ///     loginsL will be an empty table after this.  See mark_as_synchronized() for the details.
+use crate::csv_import::{self, CsvImportFailure, CsvImportResult};
 use crate::encryption::EncryptorDecryptor;
 use crate::error::*;
 use crate::login::*;
+use crate::realms::{RealmMatch, Realms};
 use crate::schema;
 use crate::sync::SyncStatus;
 use crate::util;
@@ -43,9 +45,34 @@ use std::time::SystemTime;
 use sync_guid::Guid;
 use url::{Host, Url};
 
+/// What `delete_undecryptable_records()` deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeyRecoveryReport {
+    /// Local logins and changes, which are lost unless they were synced.
+    pub num_local_deleted: u32,
+    /// Synced logins, which are downloaded again on the next sync.
+    pub num_mirror_deleted: u32,
+    /// Whether the next sync downloads every login, to replace the deleted ones.
+    pub full_resync: bool,
+}
+
+/// How long after a login is deleted `undo_delete()` can restore it.
+pub(crate) const UNDO_DELETE_GRACE_PERIOD_MS: i64 = 2 * 60 * 1000;
+
+// A login's usage score halves for each of these since it was last used, so a login used a few
+// times this week ranks above one used many times last year.
+const USAGE_HALF_LIFE_MS: f64 = 30.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+fn usage_score(login: &EncryptedLogin, now_ms: i64) -> f64 {
+    let last_used = login.record.time_last_used.max(login.record.time_created);
+    let age_ms = (now_ms - last_used).max(0) as f64;
+    login.record.times_used.max(1) as f64 * 0.5f64.powf(age_ms / USAGE_HALF_LIFE_MS)
+}
+
 pub struct LoginDb {
     pub db: Connection,
     interrupt_handle: Arc<SqlInterruptHandle>,
+    realms: Realms,
 }
 
 impl LoginDb {
@@ -64,6 +91,7 @@ impl LoginDb {
         let mut logins = Self {
             interrupt_handle: Arc::new(SqlInterruptHandle::new(&db)),
             db,
+            realms: Realms::default(),
         };
         let tx = logins.db.transaction()?;
         schema::init(&tx)?;
@@ -138,6 +166,25 @@ impl LoginDb {
         rows.collect::<Result<_>>()
     }
 
+    /// The `limit` most used logins, most used first.
+    pub fn get_most_used(&self, limit: u32) -> Result<Vec<EncryptedLogin>> {
+        let mut stmt = self.db.prepare_cached(&GET_MOST_USED_SQL)?;
+        let rows =
+            stmt.query_and_then(named_params! { ":limit": limit }, EncryptedLogin::from_row)?;
+        rows.collect::<Result<_>>()
+    }
+
+    /// The logins which haven't been used, or created, since `since_ms`, least recently used
+    /// first.
+    pub fn get_unused_since(&self, since_ms: i64) -> Result<Vec<EncryptedLogin>> {
+        let mut stmt = self.db.prepare_cached(&GET_UNUSED_SINCE_SQL)?;
+        let rows = stmt.query_and_then(
+            named_params! { ":since": since_ms },
+            EncryptedLogin::from_row,
+        )?;
+        rows.collect::<Result<_>>()
+    }
+
     pub fn get_by_base_domain(&self, base_domain: &str) -> Result<Vec<EncryptedLogin>> {
         // We first parse the input string as a host so it is normalized.
         let base_host = match Host::parse(base_domain) {
@@ -189,6 +236,67 @@ impl LoginDb {
         rows.collect::<Result<_>>()
     }
 
+    /// Set the Public Suffix List, in the format of `public_suffix_list.dat`, used by
+    /// `get_by_related_realm()` to decide which hosts are the same site.
+    pub fn set_public_suffix_list(&mut self, list: &str) {
+        self.realms.set_public_suffix_list(list);
+    }
+
+    /// Set the groups of sites, like `["apple.com", "icloud.com"]`, whose logins are shared
+    /// by `get_by_related_realm()`.
+    pub fn set_related_realms(&mut self, groups: Vec<Vec<String>>) {
+        self.realms.set_related_realms(groups);
+    }
+
+    /// Get the logins to offer on a page with the given origin, best match first: logins for
+    /// the same origin, then the same host, then the same site (by the Public Suffix List),
+    /// then related sites. Logins saved over http are offered on https, but not the other way
+    /// around.
+    pub fn get_by_related_realm(&self, origin: &str) -> Result<Vec<EncryptedLogin>> {
+        let mut matches = self.get_realm_matches(origin)?;
+        matches.sort_by_key(|(m, _)| *m);
+        Ok(matches.into_iter().map(|(_, login)| login).collect())
+    }
+
+    /// Get the logins to suggest for autofill on a page with the given origin. Like
+    /// `get_by_related_realm()`, better matches come first, but logins which match equally well
+    /// are ranked by how often, and how recently, they were used.
+    pub fn get_suggestions(&self, origin: &str) -> Result<Vec<EncryptedLogin>> {
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let mut matches = self
+            .get_realm_matches(origin)?
+            .into_iter()
+            .map(|(m, login)| (m, usage_score(&login, now_ms), login))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a_match, a_score, a), (b_match, b_score, b)| {
+            a_match
+                .cmp(b_match)
+                .then_with(|| b_score.total_cmp(a_score))
+                .then_with(|| b.record.time_last_used.cmp(&a.record.time_last_used))
+        });
+        Ok(matches.into_iter().map(|(_, _, login)| login).collect())
+    }
+
+    fn get_realm_matches(&self, origin: &str) -> Result<Vec<(RealmMatch, EncryptedLogin)>> {
+        let page = match Url::parse(origin) {
+            Ok(url) => url,
+            Err(e) => {
+                // don't log the input string as it's PII.
+                log::warn!("get_realm_matches was passed an invalid origin: {}", e);
+                return Ok(vec![]);
+            }
+        };
+        let mut stmt = self.db.prepare_cached(&GET_ALL_SQL)?;
+        let mut matches = Vec::new();
+        for login in stmt.query_and_then([], EncryptedLogin::from_row)? {
+            let login = login?;
+            if let Some(m) = self.realms.matches(&page, &login.fields.origin) {
+                matches.push((m, login));
+            }
+        }
+        Ok(matches)
+    }
+
     pub fn get_by_id(&self, id: &str) -> Result<Option<EncryptedLogin>> {
         self.try_query_row(
             &GET_BY_GUID_SQL,
@@ -234,6 +342,32 @@ impl LoginDb {
             .cloned())
     }
 
+    // Find saved logins which are near-duplicates of a `LoginEntry`
+    //
+    // These are logins for the same origin with the same username, regardless of their
+    // `form_action_origin`, `http_realm`, `username_field` and `password_field`. If `look` is a
+    // saved login, it will be included in the result. Use `merge_logins()` to consolidate them.
+    //
+    //  Returns an Err if the login is not valid and could not be fixed up
+    pub fn find_duplicates(
+        &self,
+        look: LoginEntry,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<Vec<EncryptedLogin>> {
+        let look = look.fixup()?;
+        let mut duplicates = vec![];
+        for login in self.db.prepare_cached(&GET_BY_ORIGIN_SQL)?.query_and_then(
+            named_params! { ":origin": &look.fields.origin },
+            EncryptedLogin::from_row,
+        )? {
+            let login = login?;
+            if login.decrypt_fields(encdec)?.username == look.sec_fields.username {
+                duplicates.push(login);
+            }
+        }
+        Ok(duplicates)
+    }
+
     pub fn touch(&self, id: &str) -> Result<()> {
         let tx = self.unchecked_transaction()?;
         self.ensure_local_overlay_exists(id)?;
@@ -356,11 +490,73 @@ impl LoginDb {
     }
 
     pub fn add(&self, entry: LoginEntry, encdec: &EncryptorDecryptor) -> Result<EncryptedLogin> {
-        let guid = Guid::random();
         let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let tx = self.unchecked_transaction()?;
+        let result = self.add_entry(entry, now_ms, encdec)?;
+        tx.commit()?;
+        Ok(result)
+    }
 
+    // Must be called in a transaction.
+    fn add_entry(
+        &self,
+        entry: LoginEntry,
+        now_ms: i64,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<EncryptedLogin> {
+        let guid = Guid::random();
         let new_entry = self.fixup_and_check_for_dupes(&guid, entry, encdec)?;
-        let result = EncryptedLogin {
+        let result = Self::new_login(guid, now_ms, new_entry, encdec)?;
+        self.insert_new_login(&result)?;
+        Ok(result)
+    }
+
+    /// Import the logins opened from an archive made by `LoginStore::export_encrypted()`,
+    /// keeping their timestamps and use counts. Logins which are already saved are skipped.
+    /// Returns the number of logins which were added.
+    pub fn import_archived_logins(
+        &self,
+        logins: Vec<Login>,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<u32> {
+        let scope = self.begin_interrupt_scope()?;
+        let mut num_added = 0;
+        let tx = self.unchecked_transaction()?;
+        for login in logins {
+            scope.err_if_interrupted()?;
+            let guid = Guid::random();
+            let entry = match login.entry().fixup() {
+                Ok(entry) => entry,
+                Err(Error::InvalidLogin(why)) => {
+                    log::warn!("Skipping invalid login in archive: {}", why);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if self.dupe_exists(&guid, &entry, encdec)? {
+                continue;
+            }
+            self.insert_new_login(&EncryptedLogin {
+                record: RecordFields {
+                    id: guid.to_string(),
+                    ..login.record
+                },
+                fields: entry.fields,
+                sec_fields: entry.sec_fields.encrypt(encdec)?,
+            })?;
+            num_added += 1;
+        }
+        tx.commit()?;
+        Ok(num_added)
+    }
+
+    fn new_login(
+        guid: Guid,
+        now_ms: i64,
+        entry: LoginEntry,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<EncryptedLogin> {
+        Ok(EncryptedLogin {
             record: RecordFields {
                 id: guid.to_string(),
                 time_created: now_ms,
@@ -368,11 +564,60 @@ impl LoginDb {
                 time_last_used: now_ms,
                 times_used: 1,
             },
-            fields: new_entry.fields,
-            sec_fields: new_entry.sec_fields.encrypt(encdec)?,
-        };
+            fields: entry.fields,
+            sec_fields: entry.sec_fields.encrypt(encdec)?,
+        })
+    }
+
+    /// Import the logins in a CSV file exported by another password manager.
+    ///
+    /// Rows which aren't valid logins, or which are already saved with a different password, are
+    /// reported as failures. Logins which are already saved with the same password are skipped.
+    /// Other errors, like a malformed file, fail the whole import.
+    pub fn import_csv(&self, csv: &str, encdec: &EncryptorDecryptor) -> Result<CsvImportResult> {
+        let rows = csv_import::parse_logins(csv)?;
+        let scope = self.begin_interrupt_scope()?;
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let mut result = CsvImportResult::default();
         let tx = self.unchecked_transaction()?;
-        self.insert_new_login(&result)?;
+        for (line, row) in rows {
+            scope.err_if_interrupted()?;
+            let entry = match row {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    result.failures.push(CsvImportFailure { line, reason });
+                    continue;
+                }
+            };
+            let guid = Guid::random();
+            let imported = entry.fixup().and_then(|entry| {
+                Ok(match self.find_dupe(&guid, &entry, encdec)? {
+                    None => {
+                        self.insert_new_login(&Self::new_login(guid, now_ms, entry, encdec)?)?;
+                        true
+                    }
+                    Some(existing) => {
+                        let existing = self
+                            .get_by_id(existing.as_str())?
+                            .ok_or_else(|| Error::NoSuchRecord(existing.to_string()))?;
+                        if existing.decrypt_fields(encdec)?.password != entry.sec_fields.password {
+                            return Err(InvalidLogin::DuplicateLogin.into());
+                        }
+                        false
+                    }
+                })
+            });
+            match imported {
+                Ok(true) => result.num_added += 1,
+                Ok(false) => result.num_unchanged += 1,
+                // Invalid logins are reported, and the import carries on.
+                Err(Error::InvalidLogin(why)) => result.failures.push(CsvImportFailure {
+                    line,
+                    reason: why.to_string(),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
         tx.commit()?;
         Ok(result)
     }
@@ -383,10 +628,22 @@ impl LoginDb {
         entry: LoginEntry,
         encdec: &EncryptorDecryptor,
     ) -> Result<EncryptedLogin> {
-        let guid = Guid::new(sguid);
         let now_ms = util::system_time_ms_i64(SystemTime::now());
         let tx = self.unchecked_transaction()?;
+        let result = self.update_entry(sguid, entry, now_ms, encdec)?;
+        tx.commit()?;
+        Ok(result)
+    }
 
+    // Must be called in a transaction.
+    fn update_entry(
+        &self,
+        sguid: &str,
+        entry: LoginEntry,
+        now_ms: i64,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<EncryptedLogin> {
+        let guid = Guid::new(sguid);
         let entry = entry.fixup()?;
 
         // Check if there's an existing login that's the dupe of this login.  That indicates that
@@ -414,12 +671,18 @@ impl LoginDb {
             Some(e) => e,
             None => return Err(Error::NoSuchRecord(sguid.to_owned())),
         };
-        let time_password_changed =
-            if existing.decrypt_fields(encdec)?.password == entry.sec_fields.password {
-                existing.record.time_password_changed
-            } else {
-                now_ms
-            };
+        let existing_sec_fields = existing.decrypt_fields(encdec)?;
+        let time_password_changed = if existing_sec_fields.password == entry.sec_fields.password {
+            existing.record.time_password_changed
+        } else {
+            now_ms
+        };
+        // Entries without notes leave the existing ones alone, so that updating a login from
+        // somewhere which doesn't know about notes, like a form submission, keeps them.
+        let mut sec_fields = entry.sec_fields;
+        if sec_fields.notes.is_none() {
+            sec_fields.notes = existing_sec_fields.notes;
+        }
 
         // Make the final object here - every column will be updated.
         let result = EncryptedLogin {
@@ -431,11 +694,10 @@ impl LoginDb {
                 times_used: existing.record.times_used + 1,
             },
             fields: entry.fields,
-            sec_fields: entry.sec_fields.encrypt(encdec)?,
+            sec_fields: sec_fields.encrypt(encdec)?,
         };
 
         self.update_existing_login(&result)?;
-        tx.commit()?;
         Ok(result)
     }
 
@@ -443,12 +705,53 @@ impl LoginDb {
         &self,
         entry: LoginEntry,
         encdec: &EncryptorDecryptor,
+    ) -> Result<EncryptedLogin> {
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let tx = self.unchecked_transaction()?;
+        let result = self.add_or_update_entry(entry, now_ms, encdec)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Like `add_or_update()`, but for many logins at once, in a single transaction, which is
+    /// much faster for importers and migrations. Invalid logins are reported in the results, in
+    /// the same order as `entries`, and don't stop the others from being saved. Any other error
+    /// fails the whole batch.
+    pub fn add_or_update_many(
+        &self,
+        entries: Vec<LoginEntry>,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<Vec<BulkResultEntry>> {
+        let scope = self.begin_interrupt_scope()?;
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let tx = self.unchecked_transaction()?;
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            scope.err_if_interrupted()?;
+            results.push(match self.add_or_update_entry(entry, now_ms, encdec) {
+                Ok(login) => BulkResultEntry::Success { login },
+                Err(Error::InvalidLogin(why)) => BulkResultEntry::Error {
+                    message: why.to_string(),
+                },
+                Err(e) => return Err(e),
+            });
+        }
+        tx.commit()?;
+        Ok(results)
+    }
+
+    // Must be called in a transaction.
+    fn add_or_update_entry(
+        &self,
+        entry: LoginEntry,
+        now_ms: i64,
+        encdec: &EncryptorDecryptor,
     ) -> Result<EncryptedLogin> {
         // Make sure to fixup the entry first, in case that changes the username
         let entry = entry.fixup()?;
         match self.find_login_to_update(entry.clone(), encdec)? {
-            Some(login) => self.update(&login.record.id, entry, encdec),
-            None => self.add(entry, encdec),
+            Some(login) => self.update_entry(&login.record.id, entry, now_ms, encdec),
+            None => self.add_entry(entry, now_ms, encdec),
         }
     }
 
@@ -586,12 +889,104 @@ impl LoginDb {
     }
 
     /// Delete the record with the provided id. Returns true if the record
-    /// existed already.
+    /// existed already. The deletion can be undone with `undo_delete()` for the next
+    /// `UNDO_DELETE_GRACE_PERIOD_MS`.
     pub fn delete(&self, id: &str) -> Result<bool> {
         let tx = self.unchecked_transaction_imm()?;
         let exists = self.exists(id)?;
         let now_ms = util::system_time_ms_i64(SystemTime::now());
+        self.purge_expired_deletions(now_ms)?;
+        self.execute_cached(
+            &SAVE_DELETED_SQL,
+            named_params! { ":guid": id, ":now_ms": now_ms },
+        )?;
+        self.mark_as_deleted(id, now_ms)?;
+        tx.commit()?;
+        Ok(exists)
+    }
+
+    /// Restore a login deleted by `delete()` in the last `UNDO_DELETE_GRACE_PERIOD_MS`, as it was
+    /// before it was deleted. If we haven't synced since, its tombstone is never uploaded;
+    /// otherwise the login is uploaded again, replacing the tombstone. Returns false if there's
+    /// nothing to restore, because the grace period is over, or a sync has since deleted the
+    /// login or brought it back.
+    pub fn undo_delete(&self, id: &str) -> Result<bool> {
+        let tx = self.unchecked_transaction_imm()?;
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        self.purge_expired_deletions(now_ms)?;
+        let saved_sync_status: Option<Option<u8>> = self.try_query_row(
+            "SELECT sync_status FROM loginsDeleted WHERE guid = :guid",
+            named_params! { ":guid": id },
+            |row| row.get(0),
+            true,
+        )?;
+        let saved_sync_status = match saved_sync_status {
+            Some(sync_status) => sync_status,
+            None => return Ok(false),
+        };
+        let local_is_deleted: Option<bool> = self.try_query_row(
+            "SELECT is_deleted FROM loginsL WHERE guid = :guid",
+            named_params! { ":guid": id },
+            |row| row.get(0),
+            true,
+        )?;
+        let in_mirror: bool = self.db.query_row(
+            "SELECT EXISTS(SELECT 1 FROM loginsM WHERE guid = :guid)",
+            named_params! { ":guid": id },
+            |row| row.get(0),
+        )?;
+        // Either the tombstone is waiting to be uploaded, or it was uploaded and the login is
+        // gone from both tables.
+        let can_restore = match local_is_deleted {
+            Some(is_deleted) => is_deleted,
+            None => !in_mirror,
+        };
+        if can_restore {
+            self.execute_cached(
+                "DELETE FROM loginsL WHERE guid = :guid",
+                named_params! { ":guid": id },
+            )?;
+            if saved_sync_status.is_none() && in_mirror {
+                // The login was only in the mirror, so without the tombstone it's as if the
+                // login was never deleted.
+                self.execute_cached(
+                    "UPDATE loginsM SET is_overridden = 0 WHERE guid = :guid",
+                    named_params! { ":guid": id },
+                )?;
+            } else {
+                // The mirror might have changed while the login was deleted, so if there is one,
+                // we reconcile with it on the next sync.
+                let sync_status = if in_mirror {
+                    SyncStatus::Changed
+                } else {
+                    SyncStatus::New
+                };
+                self.execute_cached(
+                    &RESTORE_DELETED_SQL,
+                    named_params! { ":guid": id, ":sync_status": sync_status as u8 },
+                )?;
+            }
+        }
+        self.execute_cached(
+            "DELETE FROM loginsDeleted WHERE guid = :guid",
+            named_params! { ":guid": id },
+        )?;
+        tx.commit()?;
+        Ok(can_restore)
+    }
+
+    // Forgets the logins deleted before the grace period, so we don't keep their data around.
+    fn purge_expired_deletions(&self, now_ms: i64) -> Result<()> {
+        self.execute_cached(
+            "DELETE FROM loginsDeleted WHERE timeDeleted < :cutoff",
+            named_params! { ":cutoff": now_ms - UNDO_DELETE_GRACE_PERIOD_MS },
+        )?;
+        Ok(())
+    }
 
+    // Turns the record into a tombstone, which will be uploaded on the next sync. Must be
+    // called in a transaction.
+    fn mark_as_deleted(&self, id: &str, now_ms: i64) -> Result<()> {
         // For IDs that have, mark is_deleted and clear sensitive fields
         self.execute(
             &format!(
@@ -625,8 +1020,109 @@ impl LoginDb {
             WHERE guid = :guid",
             changed = SyncStatus::Changed as u8),
             named_params! { ":now_ms": now_ms, ":guid": id })?;
+
+        // Alerts for deleted logins aren't useful any more.
+        self.execute(
+            "DELETE FROM loginsAlerts WHERE guid = :guid",
+            named_params! { ":guid": id },
+        )?;
+        Ok(())
+    }
+
+    /// Merge duplicate logins, as found by `find_duplicates()`, into the first one in `ids`.
+    ///
+    /// The first login keeps its password and form fields, and takes on the usage history of the
+    /// others: the earliest creation time, the latest use and the total use count. Their notes are
+    /// added to its notes, if they differ. The other logins are deleted, leaving tombstones so
+    /// they are deleted on other devices when we sync.
+    ///
+    /// All the logins must exist, and have the same origin and username.
+    pub fn merge_logins(
+        &self,
+        ids: &[String],
+        encdec: &EncryptorDecryptor,
+    ) -> Result<EncryptedLogin> {
+        let tx = self.unchecked_transaction_imm()?;
+        let mut logins = Vec::with_capacity(ids.len());
+        for id in ids {
+            let login = self
+                .get_by_id(id)?
+                .ok_or_else(|| Error::NoSuchRecord(id.to_owned()))?;
+            // The same login might be listed more than once.
+            if !logins
+                .iter()
+                .any(|l: &EncryptedLogin| l.record.id == login.record.id)
+            {
+                logins.push(login);
+            }
+        }
+        let (survivor, others) = match logins.split_first() {
+            Some(split) => split,
+            None => return Err(InvalidLogin::CannotMerge.into()),
+        };
+        let mut sec_fields = survivor.decrypt_fields(encdec)?;
+        // Keep everyone's notes, so merging never loses what the user wrote.
+        let mut notes = vec![sec_fields.notes.clone().unwrap_or_default()];
+        for other in others {
+            let other_sec_fields = other.decrypt_fields(encdec)?;
+            if other.fields.origin != survivor.fields.origin
+                || other_sec_fields.username != sec_fields.username
+            {
+                return Err(InvalidLogin::CannotMerge.into());
+            }
+            let other_notes = other_sec_fields.notes.unwrap_or_default();
+            if !notes.contains(&other_notes) {
+                notes.push(other_notes);
+            }
+        }
+        notes.retain(|n| !n.is_empty());
+        sec_fields.notes = Some(notes.join("\n\n"));
+        let sec_fields = if others.is_empty() {
+            survivor.sec_fields.clone()
+        } else {
+            sec_fields.encrypt(encdec)?
+        };
+
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let mut record = survivor.record.clone();
+        for other in others {
+            record.time_created = record.time_created.min(other.record.time_created);
+            record.time_last_used = record.time_last_used.max(other.record.time_last_used);
+            record.times_used += other.record.times_used;
+            self.mark_as_deleted(&other.record.id, now_ms)?;
+        }
+        if !others.is_empty() {
+            self.ensure_local_overlay_exists(&record.id)?;
+            self.mark_mirror_overridden(&record.id)?;
+            self.execute_cached(
+                &format!(
+                    "UPDATE loginsL
+                     SET local_modified = :now_ms,
+                         timeCreated    = :time_created,
+                         timeLastUsed   = :time_last_used,
+                         timesUsed      = :times_used,
+                         secFields      = :sec_fields,
+                         -- leave New records as they are, otherwise update them to `changed`
+                         sync_status    = max(sync_status, {changed})
+                     WHERE guid = :guid",
+                    changed = SyncStatus::Changed as u8
+                ),
+                named_params! {
+                    ":now_ms": now_ms,
+                    ":time_created": record.time_created,
+                    ":time_last_used": record.time_last_used,
+                    ":times_used": record.times_used,
+                    ":sec_fields": &sec_fields,
+                    ":guid": &record.id,
+                },
+            )?;
+        }
         tx.commit()?;
-        Ok(exists)
+        Ok(EncryptedLogin {
+            record,
+            fields: survivor.fields.clone(),
+            sec_fields,
+        })
     }
 
     fn mark_mirror_overridden(&self, guid: &str) -> Result<()> {
@@ -671,12 +1167,215 @@ impl LoginDb {
             "DELETE FROM loginsL",
             "DELETE FROM loginsM",
             "DELETE FROM loginsSyncMeta",
+            "DELETE FROM loginsAlerts",
+            "DELETE FROM loginsDeleted",
         ])?;
         tx.commit()?;
         Ok(())
     }
+
+    /// Re-encrypt the secure fields of every login, and the unknown fields we keep for sync,
+    /// with a new key. If anything can't be decrypted with the old key, nothing is changed.
+    pub fn rotate_key(&self, old: &EncryptorDecryptor, new: &EncryptorDecryptor) -> Result<()> {
+        let scope = self.begin_interrupt_scope()?;
+        let tx = self.unchecked_transaction_imm()?;
+        self.for_each_encrypted_field(
+            old,
+            "rotate key (decrypt)",
+            &scope,
+            |table, column, id, cleartext| {
+                self.execute_cached(
+                    &format!("UPDATE {table} SET {column} = :ciphertext WHERE id = :id"),
+                    named_params! {
+                        ":ciphertext": new.encrypt(&cleartext?, "rotate key (encrypt)")?,
+                        ":id": id,
+                    },
+                )?;
+                Ok(())
+            },
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete everything which can't be decrypted with `encdec`, for when the app has lost the
+    /// key the logins were encrypted with, and made a new one. Rather than failing to decrypt them
+    /// forever, we forget the logins, and make the next sync a full one, so that the synced ones
+    /// are downloaded again.
+    ///
+    /// The records are deleted without tombstones, so they aren't deleted on other devices.
+    pub fn delete_undecryptable_records(
+        &self,
+        encdec: &EncryptorDecryptor,
+    ) -> Result<KeyRecoveryReport> {
+        let scope = self.begin_interrupt_scope()?;
+        let tx = self.unchecked_transaction_imm()?;
+        let mut num_local_deleted = 0;
+        let mut num_mirror_deleted = 0;
+        self.for_each_encrypted_field(
+            encdec,
+            "delete undecryptable records",
+            &scope,
+            |table, _, id, cleartext| {
+                if cleartext.is_ok() {
+                    return Ok(());
+                }
+                self.execute_cached(
+                    &format!("DELETE FROM {table} WHERE id = :id"),
+                    named_params! { ":id": id },
+                )?;
+                match table {
+                    "loginsL" => num_local_deleted += 1,
+                    "loginsM" => num_mirror_deleted += 1,
+                    _ => (),
+                }
+                Ok(())
+            },
+        )?;
+        let full_resync = num_local_deleted > 0 || num_mirror_deleted > 0;
+        if full_resync {
+            self.execute_all(&[
+                // Mirror records are no longer overridden by the local records we deleted.
+                "UPDATE loginsM SET is_overridden = 0
+                 WHERE is_overridden = 1 AND guid NOT IN (SELECT guid FROM loginsL)",
+                "DELETE FROM loginsAlerts
+                 WHERE guid NOT IN (SELECT guid FROM loginsL UNION SELECT guid FROM loginsM)",
+            ])?;
+            // Synced logins we deleted will be downloaded again.
+            self.put_meta(schema::LAST_SYNC_META_KEY, &0)?;
+        }
+        tx.commit()?;
+        log::warn!(
+            "Deleted undecryptable logins: {} local, {} mirror",
+            num_local_deleted,
+            num_mirror_deleted
+        );
+        Ok(KeyRecoveryReport {
+            num_local_deleted,
+            num_mirror_deleted,
+            full_resync,
+        })
+    }
+
+    // Calls `action` with the table, column, row id and the result of decrypting it with
+    // `encdec`, for every encrypted column we store. Tombstones have an empty `secFields`, so
+    // they're skipped. Each column is read before `action` is called for any of its rows, so
+    // `action` can update or delete them.
+    fn for_each_encrypted_field(
+        &self,
+        encdec: &EncryptorDecryptor,
+        description: &str,
+        scope: &SqlInterruptScope,
+        mut action: impl FnMut(&str, &str, i64, Result<String>) -> Result<()>,
+    ) -> Result<()> {
+        for (table, column) in [
+            ("loginsL", "secFields"),
+            ("loginsM", "secFields"),
+            ("loginsM", "enc_unknown_fields"),
+            ("loginsDeleted", "secFields"),
+        ] {
+            let rows: Vec<(i64, String)> = self.query_rows_and_then(
+                &format!("SELECT id, {column} FROM {table} WHERE {column} != ''"),
+                [],
+                |row| Ok::<_, Error>((row.get(0)?, row.get(1)?)),
+            )?;
+            for (id, ciphertext) in rows {
+                scope.err_if_interrupted()?;
+                action(table, column, id, encdec.decrypt(&ciphertext, description))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record that the site for a login was breached at `timestamp`, in milliseconds.
+    pub fn record_breach(&self, id: &str, timestamp: i64) -> Result<()> {
+        self.upsert_alert(id, "timeBreached", timestamp)
+    }
+
+    /// Record that the user dismissed the breach alert for a login.
+    pub fn record_breach_alert_dismissal(&self, id: &str) -> Result<()> {
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        self.upsert_alert(id, "timeBreachAlertDismissed", now_ms)
+    }
+
+    /// Record that the password for a login is vulnerable, for example because it's reused for
+    /// other sites. This is cleared when the password changes.
+    pub fn record_vulnerable_password(
+        &self,
+        id: &str,
+        vulnerability: PasswordVulnerability,
+    ) -> Result<()> {
+        let tx = self.unchecked_transaction()?;
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        self.upsert_alert(id, "timeVulnerable", now_ms)?;
+        self.execute_cached(
+            "UPDATE loginsAlerts SET vulnerability = :vulnerability WHERE guid = :guid",
+            named_params! { ":vulnerability": vulnerability as u8, ":guid": id },
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Sets a single column of the alerts for a login, which must exist.
+    fn upsert_alert(&self, id: &str, column: &str, timestamp: i64) -> Result<()> {
+        if !self.exists(id)? {
+            return Err(Error::NoSuchRecord(id.to_owned()));
+        }
+        self.execute_cached(
+            &format!(
+                "INSERT INTO loginsAlerts (guid, {column}) VALUES (:guid, :timestamp)
+                 ON CONFLICT(guid) DO UPDATE SET {column} = excluded.{column}"
+            ),
+            named_params! { ":guid": id, ":timestamp": timestamp },
+        )?;
+        Ok(())
+    }
+
+    /// Get the alerts for a login, or `None` if there's no such login.
+    pub fn get_alerts(&self, id: &str) -> Result<Option<LoginAlerts>> {
+        self.try_query_row(
+            &format!("{GET_ALERTS_SQL} WHERE guid = :guid"),
+            named_params! { ":guid": id },
+            LoginAlerts::from_row,
+            true,
+        )
+    }
+
+    /// Get the alerts for every login that has been breached or has a vulnerable password.
+    pub fn list_alerts(&self) -> Result<Vec<LoginAlerts>> {
+        let mut stmt = self.db.prepare_cached(&format!(
+            "{GET_ALERTS_SQL} WHERE timeBreached IS NOT NULL OR vulnerability IS NOT NULL"
+        ))?;
+        let rows = stmt.query_and_then([], LoginAlerts::from_row)?;
+        rows.collect::<Result<_>>()
+    }
+
+    /// Forget every breach, dismissal and vulnerable password.
+    pub fn reset_all_alerts(&self) -> Result<()> {
+        self.execute_cached("DELETE FROM loginsAlerts", [])?;
+        Ok(())
+    }
 }
 
+// Breaches and vulnerabilities from before the password last changed don't apply to it.
+const GET_ALERTS_SQL: &str = "
+    SELECT * FROM (
+        SELECT logins.guid,
+               CASE WHEN alerts.timeBreached > logins.timePasswordChanged
+                    THEN alerts.timeBreached END AS timeBreached,
+               alerts.timeBreachAlertDismissed,
+               CASE WHEN alerts.timeVulnerable >= logins.timePasswordChanged
+                    THEN alerts.vulnerability END AS vulnerability,
+               CASE WHEN alerts.timeVulnerable >= logins.timePasswordChanged
+                    THEN alerts.timeVulnerable END AS timeVulnerable
+        FROM (
+            SELECT guid, timePasswordChanged FROM loginsL WHERE is_deleted = 0
+            UNION ALL
+            SELECT guid, timePasswordChanged FROM loginsM WHERE is_overridden = 0
+        ) logins
+        LEFT JOIN loginsAlerts alerts ON alerts.guid = logins.guid
+    )";
+
 lazy_static! {
     static ref GET_ALL_SQL: String = format!(
         "SELECT {common_cols} FROM loginsL WHERE is_deleted = 0
@@ -684,6 +1383,19 @@ lazy_static! {
          SELECT {common_cols} FROM loginsM WHERE is_overridden = 0",
         common_cols = schema::COMMON_COLS,
     );
+    static ref GET_MOST_USED_SQL: String = format!(
+        "SELECT * FROM ({get_all})
+         ORDER BY timesUsed DESC, timeLastUsed DESC
+         LIMIT :limit",
+        get_all = &*GET_ALL_SQL,
+    );
+    // `timeLastUsed` might be NULL, for logins which have never been used.
+    static ref GET_UNUSED_SINCE_SQL: String = format!(
+        "SELECT * FROM ({get_all})
+         WHERE MAX(IFNULL(timeLastUsed, 0), timeCreated) < :since
+         ORDER BY MAX(IFNULL(timeLastUsed, 0), timeCreated) ASC",
+        get_all = &*GET_ALL_SQL,
+    );
     static ref GET_BY_GUID_SQL: String = format!(
         "SELECT {common_cols}
          FROM loginsL
@@ -701,6 +1413,18 @@ lazy_static! {
          LIMIT 1",
         common_cols = schema::COMMON_COLS,
     );
+    static ref GET_BY_ORIGIN_SQL: String = format!(
+        "SELECT {common_cols} FROM loginsL
+         WHERE is_deleted = 0
+           AND origin = :origin
+
+         UNION ALL
+
+         SELECT {common_cols} FROM loginsM
+         WHERE is_overridden = 0
+           AND origin = :origin",
+        common_cols = schema::COMMON_COLS,
+    );
     pub static ref CLONE_ENTIRE_MIRROR_SQL: String = format!(
         "INSERT OR IGNORE INTO loginsL ({common_cols}, local_modified, is_deleted, sync_status)
          SELECT {common_cols}, NULL AS local_modified, 0 AS is_deleted, 0 AS sync_status
@@ -709,6 +1433,26 @@ lazy_static! {
     );
     static ref CLONE_SINGLE_MIRROR_SQL: String =
         format!("{} WHERE guid = :guid", &*CLONE_ENTIRE_MIRROR_SQL,);
+    static ref SAVE_DELETED_SQL: String = format!(
+        "INSERT OR REPLACE INTO loginsDeleted ({common_cols}, timeDeleted, local_modified, sync_status)
+         SELECT {common_cols}, :now_ms, local_modified, sync_status
+         FROM loginsL
+         WHERE is_deleted = 0 AND guid = :guid
+
+         UNION ALL
+
+         SELECT {common_cols}, :now_ms, NULL, NULL
+         FROM loginsM
+         WHERE is_overridden = 0 AND guid = :guid",
+        common_cols = schema::COMMON_COLS,
+    );
+    static ref RESTORE_DELETED_SQL: String = format!(
+        "INSERT INTO loginsL ({common_cols}, local_modified, is_deleted, sync_status)
+         SELECT {common_cols}, local_modified, 0, :sync_status
+         FROM loginsDeleted
+         WHERE guid = :guid",
+        common_cols = schema::COMMON_COLS,
+    );
 }
 
 #[cfg(test)]
@@ -879,10 +1623,12 @@ pub mod test_utils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::archive;
     use crate::encryption::test_utils::TEST_ENCRYPTOR;
     use crate::sync::merge::LocalLogin;
     use crate::SecureLoginFields;
     use std::{thread, time};
+    use sync15::ServerTimestamp;
 
     #[test]
     fn test_username_dupe_semantics() {
@@ -895,6 +1641,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "sekret".into(),
+                ..Default::default()
             },
         };
 
@@ -941,6 +1688,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "😍".into(),
                         password: "😍".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -978,6 +1726,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "😍".into(),
                         password: "😍".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1100,6 +1849,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_by_related_realm() {
+        let mut db = LoginDb::open_in_memory().unwrap();
+        for origin in [
+            "https://accounts.example.com",
+            "https://www.example.com",
+            "http://www.example.com",
+            "https://example.org",
+            "https://example.net",
+        ] {
+            db.add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: origin.into(),
+                        http_realm: Some(origin.into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        password: "test".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        }
+        let origins = |db: &LoginDb| {
+            db.get_by_related_realm("https://www.example.com")
+                .unwrap()
+                .into_iter()
+                .map(|l| l.fields.origin)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            origins(&db),
+            vec!["https://www.example.com", "http://www.example.com"]
+        );
+        db.set_public_suffix_list("com\norg\nnet\n");
+        db.set_related_realms(vec![vec!["example.com".into(), "example.org".into()]]);
+        assert_eq!(
+            origins(&db),
+            vec![
+                "https://www.example.com",
+                "http://www.example.com",
+                "https://accounts.example.com",
+                "https://example.org",
+            ]
+        );
+        assert!(db.get_by_related_realm("not a url").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_suggestions() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let now_ms = util::system_time_ms_i64(SystemTime::now());
+        let day_ms = 24 * 60 * 60 * 1000;
+        for (id, origin, times_used, days_since_used) in [
+            ("stale", "https://www.example.com", 50, 365),
+            ("recent", "https://www.example.com", 3, 1),
+            ("frequent", "https://www.example.com", 20, 7),
+            // Used the most, but only over http.
+            ("http", "http://www.example.com", 100, 0),
+        ] {
+            let mut login = crate::login::test_utils::enc_login(id, "password");
+            login.fields.origin = origin.into();
+            login.record.times_used = times_used;
+            login.record.time_last_used = now_ms - days_since_used * day_ms;
+            test_utils::add_mirror(&db, &login, &ServerTimestamp(1000), false).unwrap();
+        }
+        let ids = db
+            .get_suggestions("https://www.example.com")
+            .unwrap()
+            .into_iter()
+            .map(|login| login.record.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["frequent", "recent", "stale", "http"]);
+    }
+
     #[test]
     fn test_add() {
         let db = LoginDb::open_in_memory().unwrap();
@@ -1112,6 +1939,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test_user".into(),
                 password: "test_password".into(),
+                ..Default::default()
             },
         };
         let login = db.add(to_add, &TEST_ENCRYPTOR).unwrap();
@@ -1136,6 +1964,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "user1".into(),
                         password: "password1".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1152,6 +1981,7 @@ mod tests {
                 sec_fields: SecureLoginFields {
                     username: "user2".into(),
                     password: "password2".into(),
+                    ..Default::default()
                 },
             },
             &TEST_ENCRYPTOR,
@@ -1170,6 +2000,52 @@ mod tests {
         assert_eq!(sec_fields.password, "password2");
     }
 
+    #[test]
+    fn test_update_keeps_notes() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let entry = LoginEntry {
+            fields: LoginFields {
+                origin: "https://www.example.com".into(),
+                http_realm: Some("https://www.example.com".into()),
+                ..Default::default()
+            },
+            sec_fields: SecureLoginFields {
+                username: "user".into(),
+                password: "password1".into(),
+                notes: Some("PIN: 1234".into()),
+            },
+        };
+        let login = db.add(entry.clone(), &TEST_ENCRYPTOR).unwrap();
+        let get_notes = || {
+            db.get_by_id(&login.record.id)
+                .unwrap()
+                .unwrap()
+                .decrypt_fields(&TEST_ENCRYPTOR)
+                .unwrap()
+                .notes
+        };
+
+        // Updating the password, without saying anything about the notes, keeps them...
+        let mut update = entry.clone();
+        update.sec_fields.password = "password2".into();
+        update.sec_fields.notes = None;
+        db.update(&login.record.id, update.clone(), &TEST_ENCRYPTOR)
+            .unwrap();
+        assert_eq!(get_notes(), Some("PIN: 1234".into()));
+
+        // ...as does `add_or_update()`, which updates the same login.
+        update.sec_fields.password = "password3".into();
+        let updated = db.add_or_update(update.clone(), &TEST_ENCRYPTOR).unwrap();
+        assert_eq!(updated.record.id, login.record.id);
+        assert_eq!(get_notes(), Some("PIN: 1234".into()));
+
+        // Empty notes remove them.
+        update.sec_fields.notes = Some("".into());
+        db.update(&login.record.id, update, &TEST_ENCRYPTOR)
+            .unwrap();
+        assert_eq!(get_notes(), None);
+    }
+
     #[test]
     fn test_touch() {
         let db = LoginDb::open_in_memory().unwrap();
@@ -1184,6 +2060,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "user1".into(),
                         password: "password1".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1197,6 +2074,41 @@ mod tests {
         assert_eq!(login2.record.times_used, login.record.times_used + 1);
     }
 
+    #[test]
+    fn test_usage_stats() {
+        let db = LoginDb::open_in_memory().unwrap();
+        for (id, times_used, time_created, time_last_used) in [
+            ("old", 1, 1000, 2000),
+            ("never-used", 0, 1000, 0),
+            ("new", 3, 5000, 6000),
+            ("popular", 10, 1000, 7000),
+        ] {
+            let mut login = crate::login::test_utils::enc_login(id, "password");
+            login.record = RecordFields {
+                id: id.into(),
+                time_created,
+                time_password_changed: time_created,
+                time_last_used,
+                times_used,
+            };
+            test_utils::add_mirror(&db, &login, &ServerTimestamp(1000), false).unwrap();
+        }
+        let ids = |logins: Vec<EncryptedLogin>| {
+            logins
+                .into_iter()
+                .map(|login| login.record.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(db.get_most_used(2).unwrap()), vec!["popular", "new"]);
+        assert_eq!(
+            ids(db.get_unused_since(5000).unwrap()),
+            vec!["never-used", "old"]
+        );
+        // Autofilling a login counts as using it.
+        db.touch("old").unwrap();
+        assert_eq!(ids(db.get_unused_since(5000).unwrap()), vec!["never-used"]);
+    }
+
     #[test]
     fn test_delete() {
         let db = LoginDb::open_in_memory().unwrap();
@@ -1211,6 +2123,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "test_user".into(),
                         password: "test_password".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1232,6 +2145,527 @@ mod tests {
         assert!(!db.exists(login.guid_str()).unwrap());
     }
 
+    #[test]
+    fn test_undo_delete() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        http_realm: Some("https://www.example.com".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "test_user".into(),
+                        password: "test_password".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        assert!(db.delete(login.guid_str()).unwrap());
+        assert!(db.undo_delete(login.guid_str()).unwrap());
+        assert_eq!(db.get_by_id(login.guid_str()).unwrap(), Some(login.clone()));
+        let sync_status: u8 = db
+            .query_row(
+                "SELECT sync_status FROM loginsL WHERE guid = :guid",
+                named_params! { ":guid": login.guid_str() },
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(sync_status, SyncStatus::New as u8);
+        // There's nothing left to undo.
+        assert!(!db.undo_delete(login.guid_str()).unwrap());
+
+        // A synced login comes back without a local copy, so nothing is uploaded.
+        let synced = crate::login::test_utils::enc_login("synced", "password");
+        test_utils::add_mirror(&db, &synced, &ServerTimestamp(1000), false).unwrap();
+        assert!(db.delete("synced").unwrap());
+        assert!(db.undo_delete("synced").unwrap());
+        assert!(db.exists("synced").unwrap());
+        assert_eq!(test_utils::get_local_guids(&db), vec![login.guid_str()]);
+
+        // After a sync has uploaded the tombstone, the login is uploaded again.
+        assert!(db.delete("synced").unwrap());
+        db.execute_all(&[
+            "DELETE FROM loginsL WHERE guid = 'synced'",
+            "DELETE FROM loginsM WHERE guid = 'synced'",
+        ])
+        .unwrap();
+        assert!(db.undo_delete("synced").unwrap());
+        assert!(db.exists("synced").unwrap());
+
+        // Once the grace period is over, the deletion can't be undone.
+        assert!(db.delete(login.guid_str()).unwrap());
+        db.execute(
+            "UPDATE loginsDeleted SET timeDeleted = timeDeleted - :grace_period - 1",
+            named_params! { ":grace_period": UNDO_DELETE_GRACE_PERIOD_MS },
+        )
+        .unwrap();
+        assert!(!db.undo_delete(login.guid_str()).unwrap());
+        assert!(!db.exists(login.guid_str()).unwrap());
+    }
+
+    #[test]
+    fn test_find_duplicates_and_merge() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let form_login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        form_action_origin: Some("https://www.example.com".into()),
+                        username_field: "user_input".into(),
+                        password_field: "pass_input".into(),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: "password1".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        // A synced login for the same origin and username, with an http realm.
+        let realm_login = EncryptedLogin {
+            record: RecordFields {
+                id: "realm-login".into(),
+                time_created: 1000,
+                time_last_used: 2000,
+                time_password_changed: 1000,
+                times_used: 3,
+            },
+            fields: LoginFields {
+                origin: "https://www.example.com".into(),
+                http_realm: Some("the website".into()),
+                ..Default::default()
+            },
+            sec_fields: SecureLoginFields {
+                username: "user".into(),
+                password: "password2".into(),
+                notes: Some("PIN: 1234".into()),
+            }
+            .encrypt(&TEST_ENCRYPTOR)
+            .unwrap(),
+        };
+        test_utils::add_mirror(&db, &realm_login, &ServerTimestamp(1000), false).unwrap();
+        // Not a duplicate, because the username is different.
+        db.add(
+            LoginEntry {
+                fields: LoginFields {
+                    origin: "https://www.example.com".into(),
+                    form_action_origin: Some("https://www.example.com".into()),
+                    ..Default::default()
+                },
+                sec_fields: SecureLoginFields {
+                    username: "other-user".into(),
+                    password: "password3".into(),
+                    ..Default::default()
+                },
+            },
+            &TEST_ENCRYPTOR,
+        )
+        .unwrap();
+
+        let mut duplicates = db
+            .find_duplicates(
+                form_login.clone().decrypt(&TEST_ENCRYPTOR).unwrap().entry(),
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|l| l.record.id)
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        let mut expected = vec![form_login.record.id.clone(), realm_login.record.id.clone()];
+        expected.sort();
+        assert_eq!(duplicates, expected);
+
+        let merged = db
+            .merge_logins(
+                &[form_login.record.id.clone(), realm_login.record.id.clone()],
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        assert_eq!(merged.record.id, form_login.record.id);
+        assert_eq!(merged.fields, form_login.fields);
+        assert_eq!(merged.record.time_created, 1000);
+        assert_eq!(
+            merged.record.time_last_used,
+            form_login.record.time_last_used
+        );
+        assert_eq!(merged.record.times_used, 4);
+        assert_eq!(
+            merged.decrypt_fields(&TEST_ENCRYPTOR).unwrap().notes,
+            Some("PIN: 1234".into())
+        );
+        assert_eq!(db.get_by_id(&merged.record.id).unwrap().unwrap(), merged);
+
+        // The synced duplicate is now a tombstone, waiting to be uploaded.
+        assert!(!db.exists(realm_login.guid_str()).unwrap());
+        let (is_deleted, sync_status): (bool, u8) = db
+            .query_row(
+                "SELECT is_deleted, sync_status FROM loginsL WHERE guid = :guid",
+                named_params! { ":guid": realm_login.guid_str() },
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(is_deleted);
+        assert_eq!(sync_status, SyncStatus::Changed as u8);
+        assert_eq!(db.get_all().unwrap().len(), 2);
+
+        // Logins with different usernames can't be merged.
+        let other = db
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .find(|l| l.record.id != merged.record.id)
+            .unwrap();
+        assert!(db
+            .merge_logins(&[merged.record.id, other.record.id], &TEST_ENCRYPTOR)
+            .is_err());
+    }
+
+    #[test]
+    fn test_alerts() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        http_realm: Some("https://www.example.com".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user1".into(),
+                        password: "password1".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        let id = &login.record.id;
+        assert_eq!(
+            db.get_alerts(id).unwrap(),
+            Some(LoginAlerts {
+                id: id.clone(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(db.list_alerts().unwrap(), vec![]);
+        assert!(db.get_alerts("unknown").unwrap().is_none());
+        assert!(db.record_breach("unknown", 0).is_err());
+
+        // Breaches from before the password was set don't count.
+        db.record_breach(id, login.record.time_password_changed - 1)
+            .unwrap();
+        assert_eq!(db.get_alerts(id).unwrap().unwrap().time_breached, None);
+
+        let breach_time = login.record.time_password_changed + 1;
+        db.record_breach(id, breach_time).unwrap();
+        db.record_breach_alert_dismissal(id).unwrap();
+        db.record_vulnerable_password(id, PasswordVulnerability::Reused)
+            .unwrap();
+        let alerts = db.get_alerts(id).unwrap().unwrap();
+        assert_eq!(alerts.time_breached, Some(breach_time));
+        assert!(alerts.time_breach_alert_dismissed.is_some());
+        assert_eq!(alerts.vulnerability, Some(PasswordVulnerability::Reused));
+        assert!(alerts.time_vulnerable.is_some());
+        assert_eq!(db.list_alerts().unwrap(), vec![alerts]);
+
+        // Changing the password clears the vulnerability.
+        thread::sleep(time::Duration::from_millis(50));
+        db.update(
+            id,
+            LoginEntry {
+                fields: login.fields.clone(),
+                sec_fields: SecureLoginFields {
+                    username: "user1".into(),
+                    password: "password2".into(),
+                    ..Default::default()
+                },
+            },
+            &TEST_ENCRYPTOR,
+        )
+        .unwrap();
+        let alerts = db.get_alerts(id).unwrap().unwrap();
+        assert_eq!(alerts.vulnerability, None);
+        assert_eq!(alerts.time_vulnerable, None);
+
+        db.reset_all_alerts().unwrap();
+        db.record_breach(id, i64::MAX).unwrap();
+        assert_eq!(db.list_alerts().unwrap().len(), 1);
+        // Deleting the login deletes its alerts.
+        db.delete(id).unwrap();
+        assert_eq!(db.list_alerts().unwrap(), vec![]);
+        let count: i64 = db.query_one("SELECT COUNT(*) FROM loginsAlerts").unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let db = LoginDb::open_in_memory().unwrap();
+        db.add(
+            LoginEntry {
+                fields: LoginFields {
+                    origin: "https://www.example.com".into(),
+                    form_action_origin: Some("".into()),
+                    ..Default::default()
+                },
+                sec_fields: SecureLoginFields {
+                    username: "existing".into(),
+                    password: "password".into(),
+                    ..Default::default()
+                },
+            },
+            &TEST_ENCRYPTOR,
+        )
+        .unwrap();
+        let result = db
+            .import_csv(
+                "url,username,password
+                 https://www.example.com/login,existing,password
+                 https://www.example.com,existing,changed
+                 https://www.example.com,new,password
+                 example.org,,password
+                 https://www.example.com,empty-password,
+                 not a url,user,password
+",
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        assert_eq!(result.num_added, 2);
+        assert_eq!(result.num_unchanged, 1);
+        assert_eq!(
+            result.failures,
+            vec![
+                CsvImportFailure {
+                    line: 3,
+                    reason: "Login already exists".into(),
+                },
+                CsvImportFailure {
+                    line: 6,
+                    reason: "Password is empty".into(),
+                },
+                CsvImportFailure {
+                    line: 7,
+                    reason: "Login has illegal origin".into(),
+                },
+            ]
+        );
+        let mut origins = db
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|l| l.fields.origin)
+            .collect::<Vec<_>>();
+        origins.sort();
+        assert_eq!(
+            origins,
+            vec![
+                "https://example.org",
+                "https://www.example.com",
+                "https://www.example.com"
+            ]
+        );
+
+        assert!(db
+            .import_csv(
+                "name,url
+",
+                &TEST_ENCRYPTOR
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_export_import_encrypted() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        http_realm: Some("https://www.example.com".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: "password".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        let archive =
+            archive::seal(vec![login.decrypt(&TEST_ENCRYPTOR).unwrap()], "hunter2").unwrap();
+        let archived_logins = archive::open(&archive, "hunter2").unwrap();
+
+        // Importing into the same DB skips the login we already have.
+        assert_eq!(
+            db.import_archived_logins(archived_logins.clone(), &TEST_ENCRYPTOR)
+                .unwrap(),
+            0
+        );
+
+        // Importing into another DB, with a different key, keeps everything but the id.
+        let other_db = LoginDb::open_in_memory().unwrap();
+        let other_encdec = EncryptorDecryptor::new_with_random_key().unwrap();
+        assert_eq!(
+            other_db
+                .import_archived_logins(archived_logins, &other_encdec)
+                .unwrap(),
+            1
+        );
+        let imported = other_db.get_all().unwrap().pop().unwrap();
+        assert_ne!(imported.record.id, login.record.id);
+        assert_eq!(
+            imported.record,
+            RecordFields {
+                id: imported.record.id.clone(),
+                ..login.record
+            }
+        );
+        assert_eq!(imported.fields, login.fields);
+        assert_eq!(
+            imported.decrypt_fields(&other_encdec).unwrap(),
+            login.decrypt_fields(&TEST_ENCRYPTOR).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        http_realm: Some("https://www.example.com".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: "password".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        let deleted = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.org".into(),
+                        http_realm: Some("https://www.example.org".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: "password".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        db.delete(&deleted.record.id).unwrap();
+        // A synced login, with unknown fields.
+        let synced = crate::login::test_utils::enc_login("synced", "password");
+        test_utils::add_mirror(&db, &synced, &ServerTimestamp(1000), false).unwrap();
+        let unknown = TEST_ENCRYPTOR
+            .encrypt("{\"unknown\":1}", "test encrypt")
+            .unwrap();
+        db.execute(
+            "UPDATE loginsM SET enc_unknown_fields = :unknown",
+            named_params! { ":unknown": unknown },
+        )
+        .unwrap();
+
+        let new_encdec = EncryptorDecryptor::new_with_random_key().unwrap();
+        // The wrong key fails, without changing anything.
+        assert!(db.rotate_key(&new_encdec, &TEST_ENCRYPTOR).is_err());
+        assert_eq!(db.get_by_id(&login.record.id).unwrap().unwrap(), login);
+
+        db.rotate_key(&TEST_ENCRYPTOR, &new_encdec).unwrap();
+        for id in [&login.record.id, &synced.record.id] {
+            let rotated = db.get_by_id(id).unwrap().unwrap();
+            assert!(rotated.decrypt_fields(&TEST_ENCRYPTOR).is_err());
+            assert_eq!(
+                rotated.decrypt_fields(&new_encdec).unwrap().password,
+                "password"
+            );
+        }
+        let unknown: String = db
+            .query_one("SELECT enc_unknown_fields FROM loginsM")
+            .unwrap();
+        assert_eq!(
+            new_encdec.decrypt(&unknown, "test decrypt").unwrap(),
+            "{\"unknown\":1}"
+        );
+    }
+
+    #[test]
+    fn test_delete_undecryptable_records() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let login = db
+            .add(
+                LoginEntry {
+                    fields: LoginFields {
+                        origin: "https://www.example.com".into(),
+                        http_realm: Some("https://www.example.com".into()),
+                        ..Default::default()
+                    },
+                    sec_fields: SecureLoginFields {
+                        username: "user".into(),
+                        password: "password".into(),
+                        ..Default::default()
+                    },
+                },
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        db.record_breach(&login.record.id, 1000).unwrap();
+        let synced = crate::login::test_utils::enc_login("synced", "password");
+        test_utils::add_mirror(&db, &synced, &ServerTimestamp(1000), false).unwrap();
+        let deleted = crate::login::test_utils::enc_login("deleted", "password");
+        test_utils::add_mirror(&db, &deleted, &ServerTimestamp(1000), false).unwrap();
+        db.delete("deleted").unwrap();
+        db.put_meta(schema::LAST_SYNC_META_KEY, &1000).unwrap();
+
+        // The right key doesn't delete anything.
+        assert_eq!(
+            db.delete_undecryptable_records(&TEST_ENCRYPTOR).unwrap(),
+            KeyRecoveryReport::default()
+        );
+
+        let new_encdec = EncryptorDecryptor::new_with_random_key().unwrap();
+        assert_eq!(
+            db.delete_undecryptable_records(&new_encdec).unwrap(),
+            KeyRecoveryReport {
+                num_local_deleted: 1,
+                num_mirror_deleted: 2,
+                full_resync: true,
+            }
+        );
+        assert_eq!(db.get_all().unwrap(), vec![]);
+        assert_eq!(db.list_alerts().unwrap(), vec![]);
+        // The tombstone is still uploaded.
+        assert_eq!(test_utils::get_local_guids(&db), vec!["deleted"]);
+        assert_eq!(
+            db.get_meta::<i64>(schema::LAST_SYNC_META_KEY).unwrap(),
+            Some(0)
+        );
+    }
+
     mod test_find_login_to_update {
         use super::*;
 
@@ -1245,6 +2679,7 @@ mod tests {
                 sec_fields: SecureLoginFields {
                     username: username.into(),
                     password: password.into(),
+                    ..Default::default()
                 },
             }
         }
@@ -1283,6 +2718,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "user".into(),
                         password: "pass".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1299,6 +2735,7 @@ mod tests {
                     sec_fields: SecureLoginFields {
                         username: "user".into(),
                         password: "pass".into(),
+                        ..Default::default()
                     },
                 },
                 &TEST_ENCRYPTOR,
@@ -1370,5 +2807,44 @@ mod tests {
             entry.sec_fields.password = "pass3".to_string();
             db.add_or_update(entry, &TEST_ENCRYPTOR).unwrap();
         }
+
+        #[test]
+        fn test_add_or_update_many() {
+            let db = LoginDb::open_in_memory().unwrap();
+            let login = make_saved_login(&db, "user", "pass");
+            let results = db
+                .add_or_update_many(
+                    vec![
+                        make_entry("user", "pass2"),
+                        make_entry("new-user", "pass"),
+                        make_entry("invalid", ""),
+                        // Updates the login added earlier in the batch.
+                        make_entry("new-user", "pass3"),
+                    ],
+                    &TEST_ENCRYPTOR,
+                )
+                .unwrap();
+            assert_eq!(results.len(), 4);
+            let saved = results
+                .into_iter()
+                .map(|result| match result {
+                    BulkResultEntry::Success { login } => {
+                        Some(login.decrypt(&TEST_ENCRYPTOR).unwrap())
+                    }
+                    BulkResultEntry::Error { message } => {
+                        assert_eq!(message, "Password is empty");
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            let updated = saved[0].as_ref().unwrap();
+            assert_eq!(updated.record.id, login.record.id);
+            assert_eq!(updated.sec_fields.password, "pass2");
+            assert!(saved[2].is_none());
+            let added = saved[3].as_ref().unwrap();
+            assert_eq!(added.record.id, saved[1].as_ref().unwrap().record.id);
+            assert_eq!(added.sec_fields.password, "pass3");
+            assert_eq!(db.get_all().unwrap().len(), 2);
+        }
     }
 }
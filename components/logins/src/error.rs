@@ -84,6 +84,21 @@ pub enum Error {
 
     #[error("Migration Error: {0}")]
     MigrationError(String),
+
+    #[error("Malformed CSV: {0}")]
+    MalformedCsv(String),
+
+    #[error("Logins archive error: {0}")]
+    ArchiveError(String),
+
+    #[error("The password for the logins archive is incorrect")]
+    IncorrectArchivePassword,
+
+    #[error("Crypto error: {0}")]
+    RcCryptoError(#[from] rc_crypto::Error),
+
+    #[error("Invalid password rules: {0}")]
+    InvalidPasswordRules(String),
 }
 
 /// Error::InvalidLogin subtypes
@@ -96,6 +111,8 @@ pub enum InvalidLogin {
     EmptyPassword,
     #[error("Login already exists")]
     DuplicateLogin,
+    #[error("Only logins with the same origin and username can be merged")]
+    CannotMerge,
     #[error("Both `formActionOrigin` and `httpRealm` are present")]
     BothTargets,
     #[error("Neither `formActionOrigin` or `httpRealm` are present")]
@@ -118,6 +135,20 @@ impl GetErrorHandling for Error {
             Self::InvalidLogin(why) => ErrorHandling::convert(LoginsApiError::InvalidRecord {
                 reason: why.to_string(),
             }),
+            // The CSV file comes from the user, so there's nothing for us to fix.
+            Self::MalformedCsv(_) => ErrorHandling::convert(LoginsApiError::InvalidRecord {
+                reason: self.to_string(),
+            }),
+            Self::ArchiveError(_) => ErrorHandling::convert(LoginsApiError::InvalidRecord {
+                reason: self.to_string(),
+            }),
+            Self::InvalidPasswordRules(_) => {
+                ErrorHandling::convert(LoginsApiError::InvalidRecord {
+                    reason: self.to_string(),
+                })
+            }
+            // The user mistyped their password, which isn't something to report.
+            Self::IncorrectArchivePassword => ErrorHandling::convert(LoginsApiError::IncorrectKey),
             Self::MalformedIncomingRecord => {
                 ErrorHandling::convert(LoginsApiError::InvalidRecord {
                     reason: "invalid incoming record".to_string(),
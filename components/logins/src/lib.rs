@@ -9,8 +9,12 @@
 mod error;
 mod login;
 
+mod archive;
+mod csv_import;
 mod db;
 pub mod encryption;
+mod password_gen;
+mod realms;
 mod schema;
 mod store;
 mod sync;
@@ -18,10 +22,12 @@ mod util;
 
 uniffi::include_scaffolding!("logins");
 
-pub use crate::db::LoginDb;
+pub use crate::csv_import::{CsvImportFailure, CsvImportResult};
+pub use crate::db::{KeyRecoveryReport, LoginDb};
 use crate::encryption::{check_canary, create_canary, create_key};
 pub use crate::error::*;
 pub use crate::login::*;
+pub use crate::password_gen::PasswordRules;
 pub use crate::store::*;
 pub use crate::sync::LoginsSyncEngine;
 
@@ -50,3 +56,8 @@ fn decrypt_fields(sec_fields: String, enc_key: &str) -> ApiResult<SecureLoginFie
     let encdec = encryption::EncryptorDecryptor::new(enc_key)?;
     SecureLoginFields::decrypt(&sec_fields, &encdec)
 }
+
+#[handle_error(Error)]
+fn generate_password(rules: PasswordRules) -> ApiResult<String> {
+    password_gen::generate(&rules)
+}
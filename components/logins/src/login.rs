@@ -74,6 +74,9 @@
 //! The struct used to hold the fields which are stored encrypted. It contains:
 //! - username: A string.
 //! - password: A string.
+//! - notes: An optional string, which is `None` if the user hasn't written any notes. In a
+//!   `LoginEntry` passed to `update()` or `add_or_update()`, `None` leaves the login's notes
+//!   as they are, and an empty string removes them.
 //!
 //! # LoginFields
 //!
@@ -104,7 +107,7 @@
 //!   **XXX TODO:**
 //!   - Add a field with the original unicode versions of the URLs instead of punycode?
 //!
-//! - `sec_fields`: The `username` and `password` for the site, stored as a encrypted JSON
+//! - `sec_fields`: The `username`, `password` and `notes` for the site, stored as a encrypted JSON
 //!    representation of an `SecureLoginFields`.
 //!
 //!   This field is required and usually encrypted.  There are two different value types:
@@ -363,6 +366,15 @@ pub struct SecureLoginFields {
     pub username: String,
     #[serde(rename = "p")]
     pub password: String,
+    // Free-text notes from the user. Logins saved before we had notes don't have the field, and
+    // we don't store it when it's empty, so it costs nothing for logins without notes, and
+    // they're always `None` when read back.
+    #[serde(rename = "n", default, skip_serializing_if = "is_none_or_empty")]
+    pub notes: Option<String>,
+}
+
+fn is_none_or_empty(notes: &Option<String>) -> bool {
+    notes.as_deref().map_or(true, str::is_empty)
 }
 
 impl SecureLoginFields {
@@ -485,10 +497,73 @@ impl EncryptedLogin {
     }
 }
 
+/// The outcome of saving one of the logins passed to `add_or_update_many()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkResultEntry {
+    Success {
+        login: EncryptedLogin,
+    },
+    /// The login was invalid, so it wasn't saved.
+    Error {
+        message: String,
+    },
+}
+
 fn string_or_default(row: &Row<'_>, col: &str) -> Result<String> {
     Ok(row.get::<_, Option<String>>(col)?.unwrap_or_default())
 }
 
+/// Why a password is vulnerable, as decided by the app.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PasswordVulnerability {
+    /// The password is easy to guess.
+    Weak = 1,
+    /// The password is also used for other sites.
+    Reused = 2,
+}
+
+impl PasswordVulnerability {
+    pub(crate) fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::Weak),
+            2 => Some(Self::Reused),
+            _ => None,
+        }
+    }
+}
+
+/// The alerts to show the user for a login. These are stored locally and
+/// aren't synced.
+///
+/// Alerts are for the login's current password - once the password changes,
+/// earlier breaches and vulnerabilities are no longer reported.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct LoginAlerts {
+    pub id: String,
+    /// When the login's site was breached.
+    pub time_breached: Option<i64>,
+    /// When the user last dismissed the alert for a breach.
+    pub time_breach_alert_dismissed: Option<i64>,
+    pub vulnerability: Option<PasswordVulnerability>,
+    /// When the password was found to be vulnerable.
+    pub time_vulnerable: Option<i64>,
+}
+
+impl LoginAlerts {
+    pub(crate) fn from_row(row: &Row<'_>) -> Result<LoginAlerts> {
+        Ok(LoginAlerts {
+            id: row.get("guid")?,
+            time_breached: row.get("timeBreached")?,
+            time_breach_alert_dismissed: row.get("timeBreachAlertDismissed")?,
+            vulnerability: row
+                .get::<_, Option<u8>>("vulnerability")?
+                .and_then(PasswordVulnerability::from_u8),
+            time_vulnerable: row.get("timeVulnerable")?,
+        })
+    }
+}
+
 pub trait ValidateAndFixup {
     // Our validate and fixup functions.
     fn check_valid(&self) -> Result<()>
@@ -709,6 +784,7 @@ pub mod test_utils {
         let sec_fields = SecureLoginFields {
             username: "user".to_string(),
             password: password.to_string(),
+            ..Default::default()
         };
         EncryptedLogin {
             record: RecordFields {
@@ -799,6 +875,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -811,6 +888,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -823,6 +901,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "".into(),
+                ..Default::default()
             },
         };
 
@@ -836,6 +915,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -847,6 +927,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -859,6 +940,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -871,6 +953,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -883,6 +966,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "\0".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -895,6 +979,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "username".into(),
                 password: "test\0".into(),
+                ..Default::default()
             },
         };
 
@@ -907,6 +992,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -920,6 +1006,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -932,6 +1019,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -944,6 +1032,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test\n".into(),
+                ..Default::default()
             },
         };
 
@@ -957,6 +1046,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -969,6 +1059,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -981,6 +1072,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -993,6 +1085,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1005,6 +1098,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1017,6 +1111,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1029,6 +1124,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1041,6 +1137,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1197,6 +1294,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1209,6 +1307,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1221,6 +1320,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
         let login_with_empty_fsu = LoginEntry {
@@ -1232,6 +1332,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1249,6 +1350,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
 
@@ -1312,6 +1414,7 @@ mod tests {
         let sf = SecureLoginFields {
             username: "foo".into(),
             password: "pwd".into(),
+            ..Default::default()
         };
         assert_eq!(
             serde_json::to_string(&sf).unwrap(),
@@ -1321,6 +1424,7 @@ mod tests {
         let expected = SecureLoginFields {
             username: "user".into(),
             password: "p".into(),
+            ..Default::default()
         };
         assert_eq!(got, expected);
     }
@@ -0,0 +1,180 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Generating passwords for new accounts, so that every platform offers
+//! passwords of the same strength. The defaults, and the characters which
+//! are left out to avoid ambiguity, match Firefox desktop.
+
+use crate::error::*;
+use rc_crypto::rand;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+// Without the characters which are easily confused with each other, like
+// `l`, `I` and `1`.
+const UNAMBIGUOUS_LOWERCASE: &str = "abcdefghijkmnpqrstuvwxyz";
+const UNAMBIGUOUS_UPPERCASE: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ";
+const UNAMBIGUOUS_DIGITS: &str = "23456789";
+const SYMBOLS: &str = "-~!@#$%^&*_+=)}:;\"'>,.?]";
+
+// Sites often limit the length of passwords, so there's no point going
+// further than this.
+const MAX_LENGTH: u32 = 256;
+
+/// What a generated password should contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordRules {
+    pub length: u32,
+    /// Whether the password must contain at least one of each of these kinds
+    /// of character. It can only contain characters of these kinds.
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Whether to leave out characters which are easily confused.
+    pub avoid_ambiguous: bool,
+}
+
+impl Default for PasswordRules {
+    fn default() -> Self {
+        Self {
+            length: 15,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: false,
+            avoid_ambiguous: true,
+        }
+    }
+}
+
+/// Generates a random password following `rules`.
+pub(crate) fn generate(rules: &PasswordRules) -> Result<String> {
+    let classes = [
+        (rules.lowercase, LOWERCASE, UNAMBIGUOUS_LOWERCASE),
+        (rules.uppercase, UPPERCASE, UNAMBIGUOUS_UPPERCASE),
+        (rules.digits, DIGITS, UNAMBIGUOUS_DIGITS),
+        (rules.symbols, SYMBOLS, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(included, _, _)| *included)
+    .map(|(_, all, unambiguous)| {
+        let chars = if rules.avoid_ambiguous {
+            unambiguous
+        } else {
+            all
+        };
+        chars.chars().collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+    if classes.is_empty() {
+        return Err(Error::InvalidPasswordRules(
+            "no kinds of character are allowed".into(),
+        ));
+    }
+    if rules.length < classes.len() as u32 || rules.length > MAX_LENGTH {
+        return Err(Error::InvalidPasswordRules(format!(
+            "the length must be between {} and {}",
+            classes.len(),
+            MAX_LENGTH
+        )));
+    }
+
+    // One character of each kind, so they're all there...
+    let mut password = Vec::with_capacity(rules.length as usize);
+    for class in &classes {
+        password.push(class[random_index(class.len())?]);
+    }
+    // ...then the rest from any of them...
+    let all = classes.concat();
+    while password.len() < rules.length as usize {
+        password.push(all[random_index(all.len())?]);
+    }
+    // ...then shuffle, so the required characters aren't always first.
+    for i in (1..password.len()).rev() {
+        password.swap(i, random_index(i + 1)?);
+    }
+    Ok(password.into_iter().collect())
+}
+
+// Returns a uniformly random index below `len`.
+fn random_index(len: usize) -> Result<usize> {
+    let len = len as u32;
+    // Taking the remainder of any u32 would favor the lower indexes, so we
+    // throw away the values past the last multiple of `len`.
+    let limit = u32::MAX - u32::MAX % len;
+    loop {
+        let mut bytes = [0u8; 4];
+        rand::fill(&mut bytes)?;
+        let value = u32::from_le_bytes(bytes);
+        if value < limit {
+            return Ok((value % len) as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        for _ in 0..100 {
+            let password = generate(&PasswordRules::default()).unwrap();
+            assert_eq!(password.chars().count(), 15);
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+            assert!(!password.contains(['l', 'I', 'O', '0', '1']));
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = PasswordRules {
+            length: 40,
+            lowercase: false,
+            uppercase: false,
+            digits: true,
+            symbols: true,
+            avoid_ambiguous: false,
+        };
+        let password = generate(&rules).unwrap();
+        assert_eq!(password.chars().count(), 40);
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+        assert!(password.chars().any(|c| SYMBOLS.contains(c)));
+        assert!(password
+            .chars()
+            .all(|c| c.is_ascii_digit() || SYMBOLS.contains(c)));
+        assert_ne!(generate(&rules).unwrap(), password);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        for rules in [
+            PasswordRules {
+                length: 2,
+                ..Default::default()
+            },
+            PasswordRules {
+                length: 1000,
+                ..Default::default()
+            },
+            PasswordRules {
+                lowercase: false,
+                uppercase: false,
+                digits: false,
+                symbols: false,
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                generate(&rules),
+                Err(Error::InvalidPasswordRules(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Deciding which saved logins are for the same site as a page.
+//!
+//! Two hosts are the same "site" if they have the same registrable domain
+//! (eTLD+1) according to the Public Suffix List, e.g. `accounts.example.com`
+//! and `www.example.com`, but not `a.github.io` and `b.github.io`. Sites can
+//! also be related to each other, e.g. `apple.com` and `icloud.com`, which
+//! share an account backend.
+//!
+//! Both lists come from the app, which already ships (and updates) them. We
+//! don't want to bundle our own copy of the PSL, which would go out of date.
+//! Until the app gives us a PSL, each host is its own site - we never want to
+//! guess, because offering a login to the wrong site is much worse than not
+//! offering it at all.
+
+use std::collections::HashSet;
+use url::{Host, Url};
+
+/// The rules of the Public Suffix List, in the format of
+/// https://publicsuffix.org/list/public_suffix_list.dat
+#[derive(Debug, Default)]
+pub(crate) struct PublicSuffixList {
+    rules: HashSet<String>,
+    // Wildcard rules, like `*.ck`, without the `*.`
+    wildcards: HashSet<String>,
+    // Exception rules, like `!www.ck`, without the `!`
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    pub(crate) fn parse(list: &str) -> Self {
+        let mut psl = Self::default();
+        for line in list.lines() {
+            // Each rule is the first word on its line.
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule,
+                _ => continue,
+            };
+            let (set, rule) = if let Some(rule) = rule.strip_prefix("*.") {
+                (&mut psl.wildcards, rule)
+            } else if let Some(rule) = rule.strip_prefix('!') {
+                (&mut psl.exceptions, rule)
+            } else {
+                (&mut psl.rules, rule)
+            };
+            // The list is in Unicode, but the hosts we look up are punycoded.
+            if let Some(domain) = normalize_domain(rule) {
+                set.insert(domain);
+            }
+        }
+        psl
+    }
+
+    /// Returns the registrable domain (eTLD+1) of `host`, or `None` if `host`
+    /// is itself a public suffix.
+    pub(crate) fn registrable_domain(&self, host: &str) -> Option<String> {
+        let labels = host.split('.').collect::<Vec<_>>();
+        // When no rule matches, the public suffix is the last label.
+        let mut suffix_start = labels.len() - 1;
+        // The longest matching rule wins, so start with the whole host.
+        for i in 0..labels.len() {
+            let candidate = labels[i..].join(".");
+            if self.exceptions.contains(&candidate) {
+                suffix_start = i + 1;
+                break;
+            }
+            if self.rules.contains(&candidate)
+                || (i + 1 < labels.len() && self.wildcards.contains(&labels[i + 1..].join(".")))
+            {
+                suffix_start = i;
+                break;
+            }
+        }
+        if suffix_start == 0 {
+            return None;
+        }
+        Some(labels[suffix_start - 1..].join("."))
+    }
+}
+
+fn normalize_domain(domain: &str) -> Option<String> {
+    match Host::parse(domain) {
+        Ok(Host::Domain(domain)) => Some(domain),
+        _ => None,
+    }
+}
+
+/// How closely a login's origin matches a page's, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum RealmMatch {
+    Origin,
+    Host,
+    Site,
+    RelatedSite,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Realms {
+    psl: Option<PublicSuffixList>,
+    // Each group is a set of sites which share logins.
+    related: Vec<HashSet<String>>,
+}
+
+impl Realms {
+    pub(crate) fn set_public_suffix_list(&mut self, list: &str) {
+        self.psl = Some(PublicSuffixList::parse(list));
+    }
+
+    pub(crate) fn set_related_realms(&mut self, groups: Vec<Vec<String>>) {
+        self.related = groups
+            .into_iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter_map(|domain| normalize_domain(domain))
+                    .collect()
+            })
+            .collect();
+    }
+
+    fn site(&self, host: &str) -> String {
+        self.psl
+            .as_ref()
+            .and_then(|psl| psl.registrable_domain(host))
+            .unwrap_or_else(|| host.to_string())
+    }
+
+    /// Returns how closely a login for `login_origin` matches `page`, or
+    /// `None` if it shouldn't be offered there.
+    pub(crate) fn matches(&self, page: &Url, login_origin: &str) -> Option<RealmMatch> {
+        let login = Url::parse(login_origin).ok()?;
+        // Logins saved over http can be used on https, but never the other way
+        // around.
+        let scheme_ok = login.scheme() == page.scheme()
+            || (login.scheme() == "http" && page.scheme() == "https");
+        if !scheme_ok {
+            return None;
+        }
+        if login.origin() == page.origin() {
+            return Some(RealmMatch::Origin);
+        }
+        match (page.host(), login.host()) {
+            (Some(Host::Domain(page_host)), Some(Host::Domain(login_host))) => {
+                if page_host == login_host {
+                    return Some(RealmMatch::Host);
+                }
+                let page_site = self.site(page_host);
+                let login_site = self.site(login_host);
+                if page_site == login_site {
+                    Some(RealmMatch::Site)
+                } else if self
+                    .related
+                    .iter()
+                    .any(|group| group.contains(&page_site) && group.contains(&login_site))
+                {
+                    Some(RealmMatch::RelatedSite)
+                } else {
+                    None
+                }
+            }
+            // IP addresses must match exactly.
+            (Some(page_host), Some(login_host)) if page_host == login_host => {
+                Some(RealmMatch::Host)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PSL: &str = "
+// A cut-down version of the list.
+// ===BEGIN ICANN DOMAINS===
+com
+uk
+co.uk
+*.ck
+!www.ck
+// github.io is a private domain.
+github.io
+ελ
+";
+
+    #[test]
+    fn test_registrable_domain() {
+        let psl = PublicSuffixList::parse(TEST_PSL);
+        for (host, expected) in [
+            ("example.com", Some("example.com")),
+            ("accounts.example.com", Some("example.com")),
+            ("www.example.co.uk", Some("example.co.uk")),
+            ("co.uk", None),
+            ("a.github.io", Some("a.github.io")),
+            ("foo.bar.ck", Some("foo.bar.ck")),
+            ("bar.ck", None),
+            ("www.ck", Some("www.ck")),
+            ("a.www.ck", Some("www.ck")),
+            // Not in the list, so the public suffix is the last label.
+            ("www.example.test", Some("example.test")),
+            ("test", None),
+            ("xn--hxal.xn--qxam", Some("xn--hxal.xn--qxam")),
+        ] {
+            assert_eq!(
+                psl.registrable_domain(host).as_deref(),
+                expected,
+                "for {}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut realms = Realms::default();
+        let page = Url::parse("https://www.example.com/login").unwrap();
+        assert_eq!(
+            realms.matches(&page, "https://www.example.com"),
+            Some(RealmMatch::Origin)
+        );
+        assert_eq!(
+            realms.matches(&page, "http://www.example.com"),
+            Some(RealmMatch::Host)
+        );
+        // Without a PSL, we don't know that these are the same site.
+        assert_eq!(realms.matches(&page, "https://accounts.example.com"), None);
+
+        realms.set_public_suffix_list(TEST_PSL);
+        assert_eq!(
+            realms.matches(&page, "https://accounts.example.com"),
+            Some(RealmMatch::Site)
+        );
+        assert_eq!(realms.matches(&page, "https://example.org"), None);
+        realms.set_related_realms(vec![vec!["example.com".into(), "example.org".into()]]);
+        assert_eq!(
+            realms.matches(&page, "https://example.org"),
+            Some(RealmMatch::RelatedSite)
+        );
+
+        // https logins are never offered on http pages.
+        let http_page = Url::parse("http://www.example.com").unwrap();
+        assert_eq!(realms.matches(&http_page, "https://www.example.com"), None);
+        // Different sites under a public suffix don't match.
+        let github_page = Url::parse("https://a.github.io").unwrap();
+        assert_eq!(realms.matches(&github_page, "https://b.github.io"), None);
+    }
+}
@@ -6,11 +6,15 @@
 //! ================
 //!
 //! The schema we use is a evolution of the firefox-ios logins database format.
-//! There are three tables:
+//! There are five tables:
 //!
 //! - `loginsL`: The local table.
 //! - `loginsM`: The mirror table.
 //! - `loginsSyncMeta`: The table used to to store various sync metadata.
+//! - `loginsAlerts`: The table used to store breach and vulnerable password
+//!   alerts.
+//! - `loginsDeleted`: The table used to store recently deleted logins, so the
+//!   deletion can be undone.
 //!
 //! ## `loginsL`
 //!
@@ -87,6 +91,46 @@
 //!    [GLOBAL_STATE_META_KEY]. This is a `sync15::GlobalState` stored as
 //!    JSON.
 //!
+//! ## `loginsAlerts`
+//!
+//! This stores what the app has told us about logins which the user should
+//! be warned about, keyed by `guid`. It was added in version 3. Like
+//! `loginsSyncMeta`, it is local only and is never synced.
+//!
+//! ### `loginsAlerts` Columns
+//!
+//! - `timeBreached`: A millisecond timestamp of when the login's site was
+//!   breached, or NULL. Breaches before the login's `timePasswordChanged` are
+//!   ignored.
+//!
+//! - `timeBreachAlertDismissed`: A millisecond timestamp of when the user
+//!   last dismissed the breach alert, or NULL.
+//!
+//! - `vulnerability`: A `PasswordVulnerability` enum value, or NULL.
+//!
+//! - `timeVulnerable`: A millisecond timestamp of when the password was found
+//!   to be vulnerable, or NULL. Like breaches, this is ignored once the
+//!   password has changed.
+//!
+//! ## `loginsDeleted`
+//!
+//! This stores a copy of each login the user deleted in the last
+//! [crate::db::UNDO_DELETE_GRACE_PERIOD_MS], so that `undo_delete()` can
+//! restore it. It was added in version 4, and is local only. Rows are removed
+//! once the grace period is over, or when the login is deleted by a sync.
+//!
+//! ### `loginsDeleted` Columns
+//!
+//! Contains all fields in [COMMON_COLS], as they were before the login was
+//! deleted, as well as the following additional columns:
+//!
+//! - `timeDeleted`: A millisecond timestamp of when the login was deleted.
+//!
+//! - `local_modified`: The `loginsL.local_modified` of the login.
+//!
+//! - `sync_status`: The `loginsL.sync_status` of the login, or NULL if the
+//!   login was only in `loginsM`.
+//!
 
 use crate::error::*;
 use lazy_static::lazy_static;
@@ -95,7 +139,10 @@ use sql_support::ConnExt;
 
 /// Version 1: SQLCipher -> plaintext migration.
 /// Version 2: addition of `loginsM.enc_unknown_fields`.
-pub(super) const VERSION: i64 = 2;
+/// Version 3: addition of `loginsAlerts`.
+/// Version 4: addition of `loginsDeleted`.
+/// Version 5: support for notes, in `secFields` - see `upgrade()`.
+pub(super) const VERSION: i64 = 5;
 
 /// Every column shared by both tables except for `id`
 ///
@@ -169,6 +216,16 @@ lazy_static! {
         )",
         common_sql = COMMON_SQL
     );
+    static ref CREATE_DELETED_TABLE_SQL: String = format!(
+        "CREATE TABLE IF NOT EXISTS loginsDeleted (
+            {common_sql},
+            timeDeleted    INTEGER NOT NULL,
+            local_modified INTEGER,
+            -- NULL if the login was only in the mirror.
+            sync_status    TINYINT
+        )",
+        common_sql = COMMON_SQL
+    );
     static ref SET_VERSION_SQL: String =
         format!("PRAGMA user_version = {version}", version = VERSION);
 }
@@ -180,6 +237,16 @@ const CREATE_META_TABLE_SQL: &str = "
     )
 ";
 
+const CREATE_ALERTS_TABLE_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS loginsAlerts (
+        guid                     TEXT PRIMARY KEY,
+        timeBreached             INTEGER,
+        timeBreachAlertDismissed INTEGER,
+        vulnerability            TINYINT,
+        timeVulnerable           INTEGER
+    )
+";
+
 const CREATE_OVERRIDE_ORIGIN_INDEX_SQL: &str = "
     CREATE INDEX IF NOT EXISTS idx_loginsM_is_overridden_origin
     ON loginsM (is_overridden, origin)
@@ -218,7 +285,7 @@ pub(crate) fn init(db: &Connection) -> Result<()> {
 
 // Allow the redundant Ok() here.  It will make more sense once we have an actual upgrade function.
 #[allow(clippy::unnecessary_wraps)]
-fn upgrade(db: &Connection, from: i64) -> Result<()> {
+fn upgrade(db: &Connection, mut from: i64) -> Result<()> {
     log::debug!("Upgrading schema from {} to {}", from, VERSION);
     if from == VERSION {
         return Ok(());
@@ -232,10 +299,28 @@ fn upgrade(db: &Connection, from: i64) -> Result<()> {
     if from == 1 {
         // Just one new nullable column makes this fairly easy
         db.execute_batch("ALTER TABLE loginsM ADD enc_unknown_fields TEXT;")?;
+        from = 2;
+    }
+    if from == 2 {
+        db.execute_batch(CREATE_ALERTS_TABLE_SQL)?;
+        from = 3;
+    }
+    if from == 3 {
+        db.execute_batch(&CREATE_DELETED_TABLE_SQL)?;
+        from = 4;
+    }
+    if from == 4 {
+        // Until now, we kept the notes other devices synced as unknown fields, which would be
+        // dropped when we next uploaded a change to the login. Downloading every record again
+        // moves them into `secFields`, where they belong.
+        db.execute(
+            "UPDATE loginsSyncMeta SET value = 0 WHERE key = :key",
+            rusqlite::named_params! { ":key": LAST_SYNC_META_KEY },
+        )?;
     }
     // XXX - next migration, be sure to:
-    // from = 2;
-    // if from == 2 ...
+    // from = 5;
+    // if from == 5 ...
     db.execute_batch(&SET_VERSION_SQL)?;
     Ok(())
 }
@@ -248,6 +333,8 @@ pub(crate) fn create(db: &Connection) -> Result<()> {
         CREATE_OVERRIDE_ORIGIN_INDEX_SQL,
         CREATE_DELETED_ORIGIN_INDEX_SQL,
         CREATE_META_TABLE_SQL,
+        CREATE_ALERTS_TABLE_SQL,
+        &*CREATE_DELETED_TABLE_SQL,
         &*SET_VERSION_SQL,
     ])?;
     Ok(())
@@ -313,4 +400,58 @@ mod tests {
         db.execute_batch("SELECT enc_unknown_fields FROM loginsM")
             .unwrap();
     }
+
+    #[test]
+    fn test_upgrade_v2() {
+        let connection = Connection::open_in_memory().unwrap();
+        create(&connection).unwrap();
+        // Drop the new table and set the version back to 2 to simulate a v2 DB.
+        connection
+            .execute_batch(
+                "DROP TABLE loginsAlerts; DROP TABLE loginsDeleted; PRAGMA user_version = 2;",
+            )
+            .unwrap();
+
+        let db = LoginDb::with_connection(connection).unwrap();
+        let version = db.query_one::<i64>("PRAGMA user_version").unwrap();
+        assert_eq!(version, VERSION);
+        db.execute_batch("SELECT guid, timeBreached, vulnerability FROM loginsAlerts")
+            .unwrap();
+        db.execute_batch("SELECT guid, timeDeleted, sync_status FROM loginsDeleted")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_v3() {
+        let connection = Connection::open_in_memory().unwrap();
+        create(&connection).unwrap();
+        connection
+            .execute_batch("DROP TABLE loginsDeleted; PRAGMA user_version = 3;")
+            .unwrap();
+
+        let db = LoginDb::with_connection(connection).unwrap();
+        let version = db.query_one::<i64>("PRAGMA user_version").unwrap();
+        assert_eq!(version, VERSION);
+        db.execute_batch("SELECT guid, timeDeleted, sync_status FROM loginsDeleted")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_v4() {
+        let connection = Connection::open_in_memory().unwrap();
+        create(&connection).unwrap();
+        connection
+            .execute_batch(&format!(
+                "INSERT INTO loginsSyncMeta (key, value) VALUES ('{}', 1000);
+                 PRAGMA user_version = 4;",
+                LAST_SYNC_META_KEY
+            ))
+            .unwrap();
+
+        let db = LoginDb::with_connection(connection).unwrap();
+        let version = db.query_one::<i64>("PRAGMA user_version").unwrap();
+        assert_eq!(version, VERSION);
+        // The next sync downloads everything again.
+        assert_eq!(db.get_meta::<i64>(LAST_SYNC_META_KEY).unwrap(), Some(0));
+    }
 }
@@ -1,10 +1,14 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
-use crate::db::LoginDb;
+use crate::archive;
+use crate::csv_import::CsvImportResult;
+use crate::db::{KeyRecoveryReport, LoginDb};
 use crate::encryption::EncryptorDecryptor;
 use crate::error::*;
-use crate::login::{EncryptedLogin, Login, LoginEntry};
+use crate::login::{
+    BulkResultEntry, EncryptedLogin, Login, LoginAlerts, LoginEntry, PasswordVulnerability,
+};
 use crate::LoginsSyncEngine;
 use parking_lot::Mutex;
 use std::path::Path;
@@ -72,6 +76,16 @@ impl LoginStore {
         self.db.lock().get_all()
     }
 
+    #[handle_error(Error)]
+    pub fn get_most_used(&self, limit: u32) -> ApiResult<Vec<EncryptedLogin>> {
+        self.db.lock().get_most_used(limit)
+    }
+
+    #[handle_error(Error)]
+    pub fn get_unused_since(&self, timestamp: i64) -> ApiResult<Vec<EncryptedLogin>> {
+        self.db.lock().get_unused_since(timestamp)
+    }
+
     #[handle_error(Error)]
     pub fn get(&self, id: &str) -> ApiResult<Option<EncryptedLogin>> {
         self.db.lock().get_by_id(id)
@@ -82,6 +96,24 @@ impl LoginStore {
         self.db.lock().get_by_base_domain(base_domain)
     }
 
+    #[handle_error(Error)]
+    pub fn get_by_related_realm(&self, origin: &str) -> ApiResult<Vec<EncryptedLogin>> {
+        self.db.lock().get_by_related_realm(origin)
+    }
+
+    #[handle_error(Error)]
+    pub fn get_suggestions(&self, origin: &str) -> ApiResult<Vec<EncryptedLogin>> {
+        self.db.lock().get_suggestions(origin)
+    }
+
+    pub fn set_public_suffix_list(&self, list: &str) {
+        self.db.lock().set_public_suffix_list(list)
+    }
+
+    pub fn set_related_realms(&self, groups: Vec<Vec<String>>) {
+        self.db.lock().set_related_realms(groups)
+    }
+
     #[handle_error(Error)]
     pub fn find_login_to_update(
         &self,
@@ -92,6 +124,22 @@ impl LoginStore {
         self.db.lock().find_login_to_update(entry, &encdec)
     }
 
+    #[handle_error(Error)]
+    pub fn find_duplicates(
+        &self,
+        entry: LoginEntry,
+        enc_key: &str,
+    ) -> ApiResult<Vec<EncryptedLogin>> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        self.db.lock().find_duplicates(entry, &encdec)
+    }
+
+    #[handle_error(Error)]
+    pub fn merge_logins(&self, ids: Vec<String>, enc_key: &str) -> ApiResult<EncryptedLogin> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        self.db.lock().merge_logins(&ids, &encdec)
+    }
+
     #[handle_error(Error)]
     pub fn touch(&self, id: &str) -> ApiResult<()> {
         self.db.lock().touch(id)
@@ -102,6 +150,91 @@ impl LoginStore {
         self.db.lock().delete(id)
     }
 
+    #[handle_error(Error)]
+    pub fn undo_delete(&self, id: &str) -> ApiResult<bool> {
+        self.db.lock().undo_delete(id)
+    }
+
+    #[handle_error(Error)]
+    pub fn record_breach(&self, id: &str, timestamp: i64) -> ApiResult<()> {
+        self.db.lock().record_breach(id, timestamp)
+    }
+
+    #[handle_error(Error)]
+    pub fn record_breach_alert_dismissal(&self, id: &str) -> ApiResult<()> {
+        self.db.lock().record_breach_alert_dismissal(id)
+    }
+
+    #[handle_error(Error)]
+    pub fn record_vulnerable_password(
+        &self,
+        id: &str,
+        vulnerability: PasswordVulnerability,
+    ) -> ApiResult<()> {
+        self.db.lock().record_vulnerable_password(id, vulnerability)
+    }
+
+    #[handle_error(Error)]
+    pub fn get_alerts(&self, id: &str) -> ApiResult<Option<LoginAlerts>> {
+        self.db.lock().get_alerts(id)
+    }
+
+    #[handle_error(Error)]
+    pub fn list_alerts(&self) -> ApiResult<Vec<LoginAlerts>> {
+        self.db.lock().list_alerts()
+    }
+
+    #[handle_error(Error)]
+    pub fn reset_all_alerts(&self) -> ApiResult<()> {
+        self.db.lock().reset_all_alerts()
+    }
+
+    #[handle_error(Error)]
+    pub fn import_csv(&self, csv: &str, enc_key: &str) -> ApiResult<CsvImportResult> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        self.db.lock().import_csv(csv, &encdec)
+    }
+
+    #[handle_error(Error)]
+    pub fn export_encrypted(&self, password: &str, enc_key: &str) -> ApiResult<String> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        let logins = self
+            .db
+            .lock()
+            .get_all()?
+            .into_iter()
+            .map(|login| login.decrypt(&encdec))
+            .collect::<Result<Vec<_>>>()?;
+        // Deriving the archive's key is slow on purpose, so we do it without
+        // holding the lock.
+        archive::seal(logins, password)
+    }
+
+    #[handle_error(Error)]
+    pub fn import_encrypted(&self, archive: &str, password: &str, enc_key: &str) -> ApiResult<u32> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        let logins = archive::open(archive, password)?;
+        self.db.lock().import_archived_logins(logins, &encdec)
+    }
+
+    /// Re-encrypt every login with `new_key`, returning a new canary for `canary_text`, which
+    /// replaces the one the app made with `old_key`.
+    #[handle_error(Error)]
+    pub fn rotate_key(&self, old_key: &str, new_key: &str, canary_text: &str) -> ApiResult<String> {
+        let old_encdec = EncryptorDecryptor::new(old_key)?;
+        let new_encdec = EncryptorDecryptor::new(new_key)?;
+        self.db.lock().rotate_key(&old_encdec, &new_encdec)?;
+        new_encdec.create_canary(canary_text)
+    }
+
+    /// Delete the logins which can't be decrypted with `enc_key`, after `check_canary()` has
+    /// shown that the key the logins were encrypted with is lost, and the app has made a new one.
+    #[handle_error(Error)]
+    pub fn delete_undecryptable_records(&self, enc_key: &str) -> ApiResult<KeyRecoveryReport> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        self.db.lock().delete_undecryptable_records(&encdec)
+    }
+
     #[handle_error(Error)]
     pub fn wipe_local(&self) -> ApiResult<()> {
         self.db.lock().wipe_local()?;
@@ -136,6 +269,16 @@ impl LoginStore {
         self.db.lock().add_or_update(entry, &encdec)
     }
 
+    #[handle_error(Error)]
+    pub fn add_or_update_many(
+        &self,
+        entries: Vec<LoginEntry>,
+        enc_key: &str,
+    ) -> ApiResult<Vec<BulkResultEntry>> {
+        let encdec = EncryptorDecryptor::new(enc_key)?;
+        self.db.lock().add_or_update_many(entries, &encdec)
+    }
+
     // This allows the embedding app to say "make this instance available to
     // the sync manager". The implementation is more like "offer to sync mgr"
     // (thereby avoiding us needing to link with the sync manager) but
@@ -193,6 +336,7 @@ mod test {
             sec_fields: SecureLoginFields {
                 username: "coolperson21".into(),
                 password: "p4ssw0rd".into(),
+                ..Default::default()
             },
         };
 
@@ -205,6 +349,7 @@ mod test {
             sec_fields: SecureLoginFields {
                 username: "asdf".into(),
                 password: "fdsa".into(),
+                ..Default::default()
             },
         };
         let a_id = store
@@ -275,6 +420,7 @@ mod test {
             sec_fields: SecureLoginFields {
                 username: b.sec_fields.username.to_owned(),
                 password: "newpass".into(),
+                ..Default::default()
             },
             ..b
         };
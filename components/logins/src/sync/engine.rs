@@ -16,9 +16,8 @@ use crate::LoginStore;
 use interrupt_support::SqlInterruptScope;
 use rusqlite::named_params;
 use sql_support::ConnExt;
-use std::cell::RefCell;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, UNIX_EPOCH};
 use sync15::bso::{IncomingBso, OutgoingBso, OutgoingEnvelope};
 use sync15::engine::{CollSyncIds, CollectionRequest, EngineSyncAssociation, SyncEngine};
@@ -29,7 +28,7 @@ use sync_guid::Guid;
 pub struct LoginsSyncEngine {
     pub store: Arc<LoginStore>,
     pub scope: SqlInterruptScope,
-    pub staged: RefCell<Vec<IncomingBso>>,
+    pub staged: Mutex<Vec<IncomingBso>>,
     // It's unfortunate this is an Option<>, but tricky to change because sometimes we construct
     // an engine for, say, a `reset()` where this isn't needed or known.
     encdec: Option<EncryptorDecryptor>,
@@ -48,7 +47,7 @@ impl LoginsSyncEngine {
         Ok(Self {
             store,
             scope,
-            staged: RefCell::new(vec![]),
+            staged: Mutex::new(vec![]),
             encdec: None,
         })
     }
@@ -450,7 +449,7 @@ impl SyncEngine for LoginsSyncEngine {
     ) -> anyhow::Result<()> {
         // We don't have cross-item dependencies like bookmarks does, so we can
         // just apply now instead of "staging"
-        self.staged.borrow_mut().append(&mut inbound);
+        self.staged.lock().unwrap().append(&mut inbound);
         Ok(())
     }
 
@@ -459,7 +458,7 @@ impl SyncEngine for LoginsSyncEngine {
         timestamp: ServerTimestamp,
         telem: &mut telemetry::Engine,
     ) -> anyhow::Result<Vec<OutgoingBso>> {
-        let inbound = (*self.staged.borrow_mut()).drain(..).collect();
+        let inbound = self.staged.lock().unwrap().drain(..).collect();
         Ok(self.do_apply_incoming(inbound, timestamp, telem)?)
     }
 
@@ -502,6 +501,10 @@ impl SyncEngine for LoginsSyncEngine {
         self.do_reset(assoc)?;
         Ok(())
     }
+
+    fn as_parallel(&self) -> Option<&(dyn SyncEngine + Sync)> {
+        Some(self)
+    }
 }
 
 #[cfg(test)]
@@ -732,6 +735,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: username.into(),
                 password: password.into(),
+                ..Default::default()
             }
             .encrypt(&TEST_ENCRYPTOR)
             .unwrap(),
@@ -751,6 +755,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test".into(),
                 password: "test".into(),
+                ..Default::default()
             },
         };
         let first_id = store
@@ -768,6 +773,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test1".into(),
                 password: "test1".into(),
+                ..Default::default()
             },
         };
         let second_id = store
@@ -785,6 +791,7 @@ mod tests {
             sec_fields: SecureLoginFields {
                 username: "test1".into(),
                 password: "test1".into(),
+                ..Default::default()
             },
         };
         let no_form_origin_id = store
@@ -887,6 +894,7 @@ mod tests {
                         sec_fields: SecureLoginFields {
                             username: "test".into(),
                             password: "test".into(),
+                            ..Default::default()
                         },
                     },
                     &TEST_ENCRYPTION_KEY,
@@ -207,6 +207,7 @@ pub(crate) struct LoginDelta {
     pub username: Option<String>,
     pub http_realm: Option<String>,
     pub form_action_origin: Option<String>,
+    pub notes: Option<String>,
 
     pub time_created: Option<i64>,
     pub time_last_used: Option<i64>,
@@ -244,6 +245,7 @@ impl LoginDelta {
         merge_field!(merged, b, b_is_newer, username);
         merge_field!(merged, b, b_is_newer, http_realm);
         merge_field!(merged, b, b_is_newer, form_action_origin);
+        merge_field!(merged, b, b_is_newer, notes);
 
         merge_field!(merged, b, b_is_newer, time_created);
         merge_field!(merged, b, b_is_newer, time_last_used);
@@ -297,6 +299,10 @@ impl EncryptedLogin {
         if let Some(username) = delta.username.take() {
             sec_fields.username = username;
         }
+        // Like `http_realm` below, Some("") means the notes were removed.
+        if let Some(notes) = delta.notes.take() {
+            sec_fields.notes = if notes.is_empty() { None } else { Some(notes) };
+        }
         self.sec_fields = sec_fields.encrypt(encdec)?;
 
         // Use Some("") to indicate that it should be changed to be None (hacky...)
@@ -339,6 +345,9 @@ impl EncryptedLogin {
         if self_sec_fields.password != older_sec_fields.password {
             delta.password = Some(self_sec_fields.password);
         }
+        if self_sec_fields.notes != older_sec_fields.notes {
+            delta.notes = Some(self_sec_fields.notes.unwrap_or_default());
+        }
         if self.fields.password_field != older.fields.password_field {
             delta.password_field = Some(self.fields.password_field.clone());
         }
@@ -381,6 +390,8 @@ impl EncryptedLogin {
 mod tests {
     use super::*;
     use crate::encryption::test_utils::TEST_ENCRYPTOR;
+    use crate::login::test_utils::enc_login;
+    use crate::SecureLoginFields;
 
     #[test]
     fn test_invalid_payload_timestamps() {
@@ -427,4 +438,41 @@ mod tests {
         assert_eq!(login.record.time_last_used, now64 - 50);
         assert_eq!(login.record.time_password_changed, now64 - 25);
     }
+
+    #[test]
+    fn test_merge_notes() {
+        let shared = enc_login("1234", "password");
+        let changed = |update: fn(&mut SecureLoginFields)| {
+            let mut sec_fields = shared.decrypt_fields(&TEST_ENCRYPTOR).unwrap();
+            update(&mut sec_fields);
+            EncryptedLogin {
+                sec_fields: sec_fields.encrypt(&TEST_ENCRYPTOR).unwrap(),
+                ..shared.clone()
+            }
+        };
+        // The notes are merged like the other fields, so changing the password on one device
+        // and the notes on another keeps both.
+        let local = changed(|f| f.password = "new password".into());
+        let remote = changed(|f| f.notes = Some("PIN: 1234".into()));
+        let delta = local
+            .delta(&shared, &TEST_ENCRYPTOR)
+            .unwrap()
+            .merge(remote.delta(&shared, &TEST_ENCRYPTOR).unwrap(), true);
+        let mut merged = shared.clone();
+        merged.apply_delta(delta, &TEST_ENCRYPTOR).unwrap();
+        let sec_fields = merged.decrypt_fields(&TEST_ENCRYPTOR).unwrap();
+        assert_eq!(sec_fields.password, "new password");
+        assert_eq!(sec_fields.notes, Some("PIN: 1234".into()));
+
+        // Removing the notes is a change too.
+        let removed = changed(|f| f.notes = None);
+        let mut merged = remote.clone();
+        merged
+            .apply_delta(
+                removed.delta(&remote, &TEST_ENCRYPTOR).unwrap(),
+                &TEST_ENCRYPTOR,
+            )
+            .unwrap();
+        assert_eq!(merged.decrypt_fields(&TEST_ENCRYPTOR).unwrap().notes, None);
+    }
 }
@@ -63,6 +63,11 @@ impl IncomingLogin {
         let sec_fields = SecureLoginFields {
             username: p.username,
             password: p.password,
+            notes: if p.notes.is_empty() {
+                None
+            } else {
+                Some(p.notes)
+            },
         };
         // We handle NULL in the DB for migrated databases and it's wasteful
         // to encrypt the common case of an empty map, so...
@@ -139,6 +144,10 @@ pub struct LoginPayload {
     #[serde(default)]
     pub times_used: i64,
 
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+
     // Additional "unknown" round-tripped fields.
     #[serde(flatten)]
     unknown_fields: UnknownFields,
@@ -151,10 +160,13 @@ impl EncryptedLogin {
         encdec: &EncryptorDecryptor,
         enc_unknown_fields: Option<String>,
     ) -> Result<OutgoingBso> {
-        let unknown_fields = match enc_unknown_fields {
+        let mut unknown_fields = match enc_unknown_fields {
             Some(s) => UnknownFields::decrypt(&s, encdec)?,
             None => Default::default(),
         };
+        // Records we downloaded before we knew about notes have them as an unknown field, which
+        // would otherwise be serialized alongside the real one.
+        unknown_fields.remove("notes");
         let sec_fields = SecureLoginFields::decrypt(&self.sec_fields, encdec)?;
         Ok(OutgoingBso::from_content_with_id(
             crate::sync::LoginPayload {
@@ -170,6 +182,7 @@ impl EncryptedLogin {
                 time_password_changed: self.record.time_password_changed,
                 time_last_used: self.record.time_last_used,
                 times_used: self.record.times_used,
+                notes: sec_fields.notes.unwrap_or_default(),
                 unknown_fields,
             },
         )?)
@@ -303,6 +316,45 @@ mod tests {
         assert_eq!(json.get("foo").unwrap().as_str().unwrap(), "bar");
     }
 
+    #[test]
+    fn test_payload_notes() {
+        let bso = IncomingBso::from_test_content(serde_json::json!({
+            "id": "123412341234",
+            "httpRealm": "test",
+            "hostname": "https://www.example.com",
+            "username": "user",
+            "password": "password",
+            "notes": "PIN: 1234",
+        }));
+        let payload = bso.into_content::<LoginPayload>().content().unwrap();
+        assert!(payload.unknown_fields.is_empty());
+        let login = IncomingLogin::from_incoming_payload(payload, &TEST_ENCRYPTOR)
+            .unwrap()
+            .login;
+        assert_eq!(
+            login.decrypt_fields(&TEST_ENCRYPTOR).unwrap().notes,
+            Some("PIN: 1234".into())
+        );
+
+        // Logins we downloaded before we supported notes have them in their unknown fields, but
+        // the login's own notes win.
+        let unknown = Some(encrypt_struct(&serde_json::json!({ "notes": "stale" })));
+        let outgoing = login.clone().into_bso(&TEST_ENCRYPTOR, unknown).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&outgoing.payload).unwrap();
+        assert_eq!(json["notes"], "PIN: 1234");
+
+        // Empty notes aren't uploaded.
+        let mut sec_fields = login.decrypt_fields(&TEST_ENCRYPTOR).unwrap();
+        sec_fields.notes = Some(String::new());
+        let login = EncryptedLogin {
+            sec_fields: sec_fields.encrypt(&TEST_ENCRYPTOR).unwrap(),
+            ..login
+        };
+        let outgoing = login.into_bso(&TEST_ENCRYPTOR, None).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&outgoing.payload).unwrap();
+        assert!(json.get("notes").is_none());
+    }
+
     #[test]
     fn test_form_submit_payload_to_login() {
         let bso = IncomingBso::from_test_content(serde_json::json!({
@@ -347,6 +399,7 @@ mod tests {
             sec_fields: encrypt_struct(&SecureLoginFields {
                 username: "user".into(),
                 password: "password".into(),
+                ..Default::default()
             }),
         };
         let bso = login.into_bso(&TEST_ENCRYPTOR, None).unwrap();
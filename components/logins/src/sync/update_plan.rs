@@ -135,6 +135,18 @@ impl UpdatePlan {
                 ),
                 rusqlite::params_from_iter(chunk),
             )?;
+            // The login was deleted on another device, so its alerts can go too, and
+            // there's nothing left to undo.
+            for table in ["loginsAlerts", "loginsDeleted"] {
+                conn.execute(
+                    &format!(
+                        "DELETE FROM {table} WHERE guid IN ({vars})",
+                        table = table,
+                        vars = sql_support::repeat_sql_vars(chunk.len())
+                    ),
+                    rusqlite::params_from_iter(chunk),
+                )?;
+            }
             Ok(())
         })
     }
@@ -35,6 +35,7 @@ import org.mozilla.experiments.nimbus.internal.EnrollmentChangeEvent
 import org.mozilla.experiments.nimbus.internal.EnrollmentChangeEventType
 import org.mozilla.experiments.nimbus.internal.EnrollmentStatusExtraDef
 import org.mozilla.experiments.nimbus.internal.FeatureExposureExtraDef
+import org.mozilla.experiments.nimbus.internal.FetchErrorExtraDef
 import org.mozilla.experiments.nimbus.internal.MalformedFeatureConfigExtraDef
 import org.mozilla.experiments.nimbus.internal.MetricsHandler
 import org.mozilla.experiments.nimbus.internal.NimbusClient
@@ -69,6 +70,7 @@ open class Nimbus(
     deviceInfo: NimbusDeviceInfo,
     private val observer: NimbusInterface.Observer? = null,
     delegate: NimbusDelegate,
+    private val featureMinVersions: Map<String, String> = mapOf(),
 ) : NimbusInterface {
     // An I/O scope is used for reading or writing from the Nimbus's RKV database.
     private val dbScope: CoroutineScope = delegate.dbScope
@@ -93,6 +95,7 @@ open class Nimbus(
                         reason = extra.reason,
                         errorString = extra.errorString,
                         conflictSlug = extra.conflictSlug,
+                        isHoldback = extra.isHoldback,
                     ),
                 )
             }
@@ -128,6 +131,10 @@ open class Nimbus(
                 ),
             )
         }
+
+        override fun recordFetchError(event: FetchErrorExtraDef) {
+            // Not recorded until `nimbus_events.fetch_error` has a data review.
+        }
     }
 
     private val nimbusClient: NimbusClientInterface
@@ -460,10 +467,9 @@ open class Nimbus(
         nimbusClient.dumpStateToLog()
     }
 
-    override fun createMessageHelper(additionalContext: JSONObject?): NimbusMessagingHelper =
-        NimbusMessagingHelper(
-            nimbusClient.createTargetingHelper(additionalContext),
-            nimbusClient.createStringHelper(additionalContext),
+    override fun createMessageHelper(additionalContext: JSONObject?): NimbusMessagingHelperInterface =
+        RustMessagingHelperAdapter(
+            nimbusClient.createMessagingHelper(additionalContext),
         )
 
     @VisibleForTesting(otherwise = VisibleForTesting.PRIVATE)
@@ -574,6 +580,7 @@ open class Nimbus(
             osVersion = Build.VERSION.RELEASE,
             installationDate = packageInfo?.firstInstallTime,
             homeDirectory = context.applicationInfo?.dataDir,
+            featureMinVersions = featureMinVersions,
             customTargetingAttributes = appInfo.customTargetingAttributes,
         )
     }
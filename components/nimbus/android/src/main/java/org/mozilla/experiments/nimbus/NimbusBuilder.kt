@@ -194,6 +194,14 @@ abstract class AbstractNimbusBuilder<T : NimbusInterface>(val context: Context)
      */
     protected fun getCoenrollingFeatureIds(): List<String> =
         featureManifest?.getCoenrollingFeatureIds() ?: listOf()
+
+    /**
+     * Returns the earliest app version supporting each feature, keyed by feature id. Implementers
+     * of [newNimbus] should pass this into the [NimbusInterface] instance, so configurations for
+     * features this version of the app doesn't support are ignored.
+     */
+    protected fun getFeatureMinVersions(): Map<String, String> =
+        featureManifest?.getFeatureMinVersions() ?: mapOf()
 }
 
 private class Observer(
@@ -222,6 +230,7 @@ class DefaultNimbusBuilder(context: Context) : AbstractNimbusBuilder<NimbusInter
             deviceInfo = createDeviceInfo(),
             delegate = createDelegate(),
             observer = createObserver(),
+            featureMinVersions = getFeatureMinVersions(),
         )
 
     override fun newNimbusDisabled() = NullNimbus(context)
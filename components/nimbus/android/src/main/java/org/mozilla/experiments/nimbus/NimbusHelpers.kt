@@ -6,6 +6,7 @@ package org.mozilla.experiments.nimbus
 
 import org.json.JSONObject
 import org.mozilla.experiments.nimbus.internal.FFIObject
+import org.mozilla.experiments.nimbus.internal.MessagingHelper
 import org.mozilla.experiments.nimbus.internal.NimbusStringHelperInterface
 import org.mozilla.experiments.nimbus.internal.NimbusTargetingHelperInterface
 
@@ -73,6 +74,25 @@ class NimbusMessagingHelper(
     }
 }
 
+/**
+ * A [NimbusMessagingHelperInterface] backed by the messaging helper in the Rust component, which
+ * evaluates and caches JEXL, and formats strings, the same way on every platform.
+ */
+internal class RustMessagingHelperAdapter(
+    private val helper: MessagingHelper,
+) : NimbusMessagingHelperInterface {
+    override fun evalJexl(expression: String): Boolean = helper.evalJexl(expression)
+
+    override fun clearCache() = helper.clearCache()
+
+    override fun stringFormat(template: String, uuid: String?): String =
+        helper.stringFormat(template, uuid)
+
+    override fun getUuid(template: String): String? = helper.getUuid(template)
+
+    override fun destroy() = helper.destroy()
+}
+
 internal class AlwaysFalseTargetingHelper : NimbusTargetingHelperInterface {
     override fun evalJexl(expression: String): Boolean = false
 }
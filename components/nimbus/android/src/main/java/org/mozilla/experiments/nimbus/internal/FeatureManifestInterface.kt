@@ -43,4 +43,11 @@ interface FeatureManifestInterface<T> {
     fun getFeature(featureId: String): FeatureHolder<*>?
 
     fun getCoenrollingFeatureIds(): List<String>
+
+    /**
+     * Get the earliest app version which supports each feature, keyed by feature id.
+     *
+     * Configurations for a feature shouldn't be applied to app versions older than this.
+     */
+    fun getFeatureMinVersions(): Map<String, String> = mapOf()
 }
@@ -13,10 +13,10 @@ fn main() -> Result<()> {
     use env_logger::Env;
     use nimbus::{
         metrics::{
-            EnrollmentStatusExtraDef, FeatureExposureExtraDef, MalformedFeatureConfigExtraDef,
-            MetricsHandler,
+            EnrollmentStatusExtraDef, FeatureExposureExtraDef, FetchErrorExtraDef,
+            MalformedFeatureConfigExtraDef, MetricsHandler,
         },
-        AppContext, AvailableRandomizationUnits, EnrollmentStatus, NimbusClient,
+        AppContext, AvailableRandomizationUnits, EnrollmentStatus, Experiment, NimbusClient,
         NimbusTargetingHelper, RemoteSettingsConfig,
     };
     use std::collections::HashMap;
@@ -40,6 +40,10 @@ fn main() -> Result<()> {
         fn record_malformed_feature_config(&self, _event: MalformedFeatureConfigExtraDef) {
             // do nothing
         }
+
+        fn record_fetch_error(&self, _event: FetchErrorExtraDef) {
+            // do nothing
+        }
     }
 
     // We set the logging level to be `warn` here, meaning that only
@@ -172,6 +176,25 @@ fn main() -> Result<()> {
                 .help("The number of times to generate a UUID and attempt enrollment.")
             )
         )
+        .subcommand(
+            SubCommand::with_name("simulate")
+            .about("Shows how a recipe would be evaluated for this client, without enrolling: whether the app and channel match, the targeting result, the bucketing result and the chosen branch")
+            .arg(
+                Arg::with_name("recipe")
+                .long("recipe")
+                .value_name("FILE")
+                .help("A JSON file containing the experiment recipe")
+                .required(true)
+                .takes_value(true)
+            )
+            .arg(
+                Arg::with_name("nimbus-id")
+                .long("nimbus-id")
+                .value_name("UUID")
+                .help("The nimbus id to bucket with, instead of the one in the database")
+                .takes_value(true)
+            )
+        )
         .get_matches();
 
     // Read command line arguments, or set default values
@@ -233,9 +256,12 @@ fn main() -> Result<()> {
     )?;
     log::info!("Nimbus ID is {}", nimbus_client.nimbus_id()?);
 
-    // Explicitly update experiments at least once for init purposes
-    nimbus_client.fetch_experiments()?;
-    nimbus_client.apply_pending_experiments()?;
+    // Explicitly update experiments at least once for init purposes. Simulating
+    // a recipe doesn't need the server, so it can be used offline.
+    if matches.subcommand_name() != Some("simulate") {
+        nimbus_client.fetch_experiments()?;
+        nimbus_client.apply_pending_experiments()?;
+    }
 
     // We match against the subcommands
     match matches.subcommand() {
@@ -397,6 +423,75 @@ fn main() -> Result<()> {
             }
             println!("Results: {:#?}", results);
         }
+        // simulate explains why this client would or wouldn't be enrolled in a
+        // recipe, by evaluating each step of the enrollment separately.
+        ("simulate", Some(matches)) => {
+            let recipe = std::fs::read_to_string(matches.value_of("recipe").unwrap())
+                .expect("Recipe file does not exist");
+            let exp = serde_json::from_str::<Experiment>(&recipe)?;
+            let nimbus_id = match matches.value_of("nimbus-id") {
+                Some(id) => uuid::Uuid::parse_str(id).expect("the nimbus id should be a uuid"),
+                None => nimbus_client.nimbus_id()?,
+            };
+            let aru = AvailableRandomizationUnits::with_nimbus_id(&nimbus_id);
+            let th = nimbus_client.create_targeting_helper(None)?;
+
+            println!("======================================");
+            println!(
+                "Simulating enrollment in '{}' for nimbus id {}",
+                exp.slug, nimbus_id
+            );
+            println!(
+                "App name: {:?} (this app is '{}')",
+                exp.app_name, context.app_name
+            );
+            println!(
+                "Channel: {:?} (this app is '{}')",
+                exp.channel, context.channel
+            );
+            match &exp.targeting {
+                Some(expr) => match th.eval_jexl(expr.clone()) {
+                    Ok(result) => println!("Targeting: `{}` is {}", expr, result),
+                    Err(e) => println!("Targeting: `{}` failed: {}", expr, e),
+                },
+                None => println!("Targeting: none"),
+            }
+
+            // Bucketing is evaluated without the app, channel and targeting checks,
+            // so it can be seen even when one of those fails.
+            let bucketing_only = Experiment {
+                app_name: None,
+                channel: None,
+                targeting: None,
+                ..exp.clone()
+            };
+            let bucketing = nimbus::evaluate_enrollment(&aru, &bucketing_only, &th)?;
+            let bucket_config = &exp.bucket_config;
+            println!(
+                "Bucketing: {:?} on '{}', buckets {}..{} of {}: {}",
+                bucket_config.randomization_unit,
+                bucket_config.namespace,
+                bucket_config.start,
+                bucket_config.start + bucket_config.count,
+                bucket_config.total,
+                match bucketing.status {
+                    EnrollmentStatus::Enrolled { .. } => "selected".to_string(),
+                    EnrollmentStatus::NotEnrolled { .. } => "not selected".to_string(),
+                    status => format!("{:?}", status),
+                }
+            );
+
+            let enrollment = nimbus::evaluate_enrollment(&aru, &exp, &th)?;
+            match enrollment.status {
+                EnrollmentStatus::Enrolled { branch, .. } => {
+                    println!("Result: enrolled in branch '{}'", branch)
+                }
+                EnrollmentStatus::NotEnrolled { reason } => {
+                    println!("Result: not enrolled ({})", reason)
+                }
+                status => println!("Result: {:?}", status),
+            }
+        }
         (&_, _) => println!("Invalid subcommand"),
     };
     Ok(())
@@ -37,4 +37,15 @@ public protocol FeatureManifestInterface {
     func getFeature(featureId: String) -> FeatureHolderAny?
 
     func getCoenrollingFeatureIds() -> [String]
+
+    /// Get the earliest app version which supports each feature, keyed by feature id.
+    ///
+    /// Configurations for a feature shouldn't be applied to app versions older than this.
+    func getFeatureMinVersions() -> [String: String]
+}
+
+public extension FeatureManifestInterface {
+    func getFeatureMinVersions() -> [String: String] {
+        [:]
+    }
 }
@@ -398,16 +398,16 @@ extension Nimbus: NimbusBranchInterface {
 }
 
 extension Nimbus: NimbusMessagingProtocol {
-    public func createMessageHelper() throws -> NimbusMessagingHelperProtocol {
+    public func createMessageHelper() throws -> MessagingHelperProtocol {
         return try createMessageHelper(string: nil)
     }
 
-    public func createMessageHelper(additionalContext: [String: Any]) throws -> NimbusMessagingHelperProtocol {
+    public func createMessageHelper(additionalContext: [String: Any]) throws -> MessagingHelperProtocol {
         let string = try additionalContext.stringify()
         return try createMessageHelper(string: string)
     }
 
-    public func createMessageHelper<T: Encodable>(additionalContext: T) throws -> NimbusMessagingHelperProtocol {
+    public func createMessageHelper<T: Encodable>(additionalContext: T) throws -> MessagingHelperProtocol {
         let encoder = JSONEncoder()
         encoder.keyEncodingStrategy = .convertToSnakeCase
 
@@ -416,10 +416,8 @@ extension Nimbus: NimbusMessagingProtocol {
         return try createMessageHelper(string: string)
     }
 
-    private func createMessageHelper(string: String?) throws -> NimbusMessagingHelperProtocol {
-        let targetingHelper = try nimbusClient.createTargetingHelper(additionalContext: string)
-        let stringHelper = try nimbusClient.createStringHelper(additionalContext: string)
-        return NimbusMessagingHelper(targetingHelper: targetingHelper, stringHelper: stringHelper)
+    private func createMessageHelper(string: String?) throws -> MessagingHelperProtocol {
+        try nimbusClient.createMessagingHelper(additionalContext: string)
     }
 
     public var events: NimbusEventStore {
@@ -508,18 +506,15 @@ public extension NimbusDisabled {
 }
 
 extension NimbusDisabled: NimbusMessagingProtocol {
-    public func createMessageHelper() throws -> NimbusMessagingHelperProtocol {
-        NimbusMessagingHelper(
-            targetingHelper: AlwaysConstantTargetingHelper(),
-            stringHelper: EchoStringHelper()
-        )
+    public func createMessageHelper() throws -> MessagingHelperProtocol {
+        AlwaysConstantMessagingHelper()
     }
 
-    public func createMessageHelper(additionalContext _: [String: Any]) throws -> NimbusMessagingHelperProtocol {
+    public func createMessageHelper(additionalContext _: [String: Any]) throws -> MessagingHelperProtocol {
         try createMessageHelper()
     }
 
-    public func createMessageHelper<T: Encodable>(additionalContext _: T) throws -> NimbusMessagingHelperProtocol {
+    public func createMessageHelper<T: Encodable>(additionalContext _: T) throws -> MessagingHelperProtocol {
         try createMessageHelper()
     }
 
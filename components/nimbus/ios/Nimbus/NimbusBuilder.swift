@@ -245,12 +245,17 @@ public class NimbusBuilder {
         featureManifest?.getCoenrollingFeatureIds() ?? []
     }
 
+    func getFeatureMinVersions() -> [String: String] {
+        featureManifest?.getFeatureMinVersions() ?? [:]
+    }
+
     func newNimbus(_ appInfo: NimbusAppSettings, serverSettings: NimbusServerSettings?) throws -> NimbusInterface {
         try Nimbus.create(serverSettings,
                           appSettings: appInfo,
                           coenrollingFeatureIds: getCoenrollingFeatureIds(),
                           dbPath: dbFilePath,
                           resourceBundles: resourceBundles,
+                          featureMinVersions: getFeatureMinVersions(),
                           userDefaults: userDefaults,
                           errorReporter: errorReporter)
     }
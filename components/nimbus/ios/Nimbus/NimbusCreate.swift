@@ -27,6 +27,7 @@ class GleanMetricsHandler: MetricsHandler {
                     branch: extra.branch,
                     conflictSlug: extra.conflictSlug,
                     errorString: extra.errorString,
+                    isHoldback: extra.isHoldback,
                     reason: extra.reason,
                     slug: extra.slug,
                     status: extra.status
@@ -61,6 +62,10 @@ class GleanMetricsHandler: MetricsHandler {
                 partId: event.part
             ))
     }
+
+    func recordFetchError(event _: FetchErrorExtraDef) {
+        // Not recorded until `nimbus_events.fetch_error` has a data review.
+    }
 }
 
 public extension Nimbus {
@@ -71,6 +76,8 @@ public extension Nimbus {
     ///     - appSettings: the name and channel for the app
     ///     - dbPath: the path on disk for the database
     ///     - resourceBundles: an optional array of `Bundle` objects that are used to lookup text and images
+    ///     - featureMinVersions: the earliest app version supporting each feature, from the feature manifest.
+    ///       Configurations for features this version of the app doesn't support are ignored.
     ///     - enabled: intended for FeatureFlags. If false, then return a dummy `Nimbus` instance. Defaults to `true`.
     ///     - errorReporter: a closure capable of reporting errors. Defaults to using a logger.
     /// - Returns an implementation of `NimbusApi`.
@@ -82,6 +89,7 @@ public extension Nimbus {
         coenrollingFeatureIds: [String] = [],
         dbPath: String,
         resourceBundles: [Bundle] = [Bundle.main],
+        featureMinVersions: [String: String] = [:],
         enabled: Bool = true,
         userDefaults: UserDefaults? = nil,
         errorReporter: @escaping NimbusErrorReporter = defaultErrorReporter
@@ -90,7 +98,7 @@ public extension Nimbus {
             return NimbusDisabled.shared
         }
 
-        let context = Nimbus.buildExperimentContext(appSettings)
+        let context = Nimbus.buildExperimentContext(appSettings, featureMinVersions: featureMinVersions)
         let remoteSettings = server.map { server -> RemoteSettingsConfig in
             RemoteSettingsConfig(
                 collectionName: server.collection,
@@ -116,7 +124,8 @@ public extension Nimbus {
     static func buildExperimentContext(
         _ appSettings: NimbusAppSettings,
         bundle: Bundle = Bundle.main,
-        device: UIDevice = .current
+        device: UIDevice = .current,
+        featureMinVersions: [String: String] = [:]
     ) -> AppContext {
         let info = bundle.infoDictionary ?? [:]
         var inferredDateInstalledOn: Date? {
@@ -146,6 +155,7 @@ public extension Nimbus {
             debugTag: "Nimbus.rs",
             installationDate: installationDateSinceEpoch,
             homeDirectory: nil,
+            featureMinVersions: featureMinVersions,
             customTargetingAttributes: try? appSettings.customTargetingAttributes.stringify()
         )
     }
@@ -18,30 +18,31 @@ import Glean
  * over time, the message helper should not be stored for long periods.
  */
 public protocol NimbusMessagingProtocol {
-    func createMessageHelper() throws -> NimbusMessagingHelperProtocol
-    func createMessageHelper(additionalContext: [String: Any]) throws -> NimbusMessagingHelperProtocol
-    func createMessageHelper<T: Encodable>(additionalContext: T) throws -> NimbusMessagingHelperProtocol
+    func createMessageHelper() throws -> MessagingHelperProtocol
+    func createMessageHelper(additionalContext: [String: Any]) throws -> MessagingHelperProtocol
+    func createMessageHelper<T: Encodable>(additionalContext: T) throws -> MessagingHelperProtocol
 
     var events: NimbusEventStore { get }
 }
 
-public protocol NimbusMessagingHelperProtocol: NimbusStringHelperProtocol, NimbusTargetingHelperProtocol {
-    /**
-     * Clear the JEXL cache
-     */
-    func clearCache()
-}
+// `MessagingHelper` and `MessagingHelperProtocol` are generated from the Rust component, so the JEXL cache
+// and string formatting are shared with Android. The helper evaluates JEXL against the same context as Nimbus
+// targeting, caching the results until `clearCache()` is called.
+
+extension MessagingHelper: NimbusTargetingHelperProtocol, NimbusStringHelperProtocol {}
+
+@available(*, deprecated, renamed: "MessagingHelperProtocol")
+public typealias NimbusMessagingHelperProtocol = MessagingHelperProtocol
 
 /**
- * A helper object to make working with Strings uniform across multiple implementations of the messaging
- * system.
+ * A messaging helper built from a separate targeting helper and string helper.
  *
- * This object provides access to a JEXL evaluator which runs against the same context as provided by
- * Nimbus targeting.
- *
- * It should also provide a similar function for String substitution, though this scheduled for EXP-2159.
+ * Messaging helpers are now created by `NimbusMessagingProtocol.createMessageHelper`, which returns a
+ * `MessagingHelper` from the Rust component; this class is kept so apps which build their own helpers still
+ * compile.
  */
-public class NimbusMessagingHelper: NimbusMessagingHelperProtocol {
+@available(*, deprecated, message: "Use the MessagingHelperProtocol returned by createMessageHelper() instead")
+public class NimbusMessagingHelper: MessagingHelperProtocol, NimbusTargetingHelperProtocol, NimbusStringHelperProtocol {
     private let targetingHelper: NimbusTargetingHelperProtocol
     private let stringHelper: NimbusStringHelperProtocol
     private var cache: [String: Bool]
@@ -80,7 +81,7 @@ public class NimbusMessagingHelper: NimbusMessagingHelperProtocol {
 
 // MARK: Dummy implementations
 
-class AlwaysConstantTargetingHelper: NimbusTargetingHelperProtocol {
+class AlwaysConstantMessagingHelper: MessagingHelperProtocol {
     private let constant: Bool
 
     public init(constant: Bool = false) {
@@ -90,9 +91,9 @@ class AlwaysConstantTargetingHelper: NimbusTargetingHelperProtocol {
     public func evalJexl(expression _: String) throws -> Bool {
         constant
     }
-}
 
-class EchoStringHelper: NimbusStringHelperProtocol {
+    public func clearCache() {}
+
     public func getUuid(template _: String) -> String? {
         nil
     }
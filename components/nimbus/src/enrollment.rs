@@ -9,6 +9,7 @@ use crate::{
     SLUG_REPLACEMENT_PATTERN,
 };
 use serde_derive::*;
+use serde_json::{Map, Value};
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Display, Formatter, Result as FmtResult},
@@ -116,7 +117,7 @@ pub struct ExperimentEnrollment {
 impl ExperimentEnrollment {
     /// Evaluate an experiment enrollment for an experiment
     /// we are seeing for the first time.
-    fn from_new_experiment(
+    pub(crate) fn from_new_experiment(
         is_user_participating: bool,
         available_randomization_units: &AvailableRandomizationUnits,
         experiment: &Experiment,
@@ -226,7 +227,11 @@ impl ExperimentEnrollment {
                     let updated_enrollment =
                         self.disqualify_from_enrolled(DisqualifiedReason::OptOut);
                     out_enrollment_events.push(updated_enrollment.get_change_event());
-                    updated_enrollment
+                    if updated_experiment.is_rollout {
+                        self.rollout_opted_out()
+                    } else {
+                        updated_enrollment
+                    }
                 } else if !updated_experiment.has_branch(branch) {
                     // The branch we were in disappeared!
                     let updated_enrollment =
@@ -279,7 +284,16 @@ impl ExperimentEnrollment {
             EnrollmentStatus::Disqualified {
                 ref branch, reason, ..
             } => {
-                if !is_user_participating {
+                if !is_user_participating
+                    && updated_experiment.is_rollout
+                    && matches!(
+                        reason,
+                        DisqualifiedReason::NotSelected | DisqualifiedReason::NotTargeted,
+                    )
+                {
+                    // This rollout will be evaluated again once the user is participating.
+                    self.clone()
+                } else if !is_user_participating {
                     log::debug!(
                         "Disqualified experiment enrollment '{}' has been reset to not-enrolled (global opt-out)",
                         &self.slug
@@ -379,16 +393,23 @@ impl ExperimentEnrollment {
     /// We move any enrolled experiments to the "disqualified" state, since their further
     /// partipation would submit partial data that could skew analysis.
     ///
+    /// Rollouts aren't analysed by branch, so they are instead evaluated again, with the new
+    /// identifiers, the next time enrollments are evolved.
     #[cfg_attr(not(feature = "stateful"), allow(unused))]
     pub fn reset_telemetry_identifiers(
         &self,
+        is_rollout: bool,
         out_enrollment_events: &mut Vec<EnrollmentChangeEvent>,
     ) -> Self {
         let updated = match self.status {
             EnrollmentStatus::Enrolled { .. } => {
                 let disqualified = self.disqualify_from_enrolled(DisqualifiedReason::OptOut);
                 out_enrollment_events.push(disqualified.get_change_event());
-                disqualified
+                if is_rollout {
+                    self.rollout_opted_out()
+                } else {
+                    disqualified
+                }
             }
             EnrollmentStatus::NotEnrolled { .. }
             | EnrollmentStatus::Disqualified { .. }
@@ -451,7 +472,43 @@ impl ExperimentEnrollment {
         }
     }
 
+    /// Rollouts which the user hasn't opted out of explicitly are left not-enrolled, rather than
+    /// disqualified, so that they can be enrolled in again once the user is participating.
+    ///
+    /// Explicitly opting out of a rollout still disqualifies it, as it does an experiment.
+    fn rollout_opted_out(&self) -> Self {
+        Self {
+            slug: self.slug.clone(),
+            status: EnrollmentStatus::NotEnrolled {
+                reason: NotEnrolledReason::OptOut,
+            },
+        }
+    }
+
     /// If the current state is `Enrolled`, move to `Disqualified` with the given reason.
+    /// Update our enrollment to an experiment for a feature this version of the app doesn't
+    /// support. As with a targeting change, we're disqualified unless we explicitly opted in.
+    fn on_experiment_unsupported(
+        &self,
+        out_enrollment_events: &mut Vec<EnrollmentChangeEvent>,
+    ) -> Self {
+        match &self.status {
+            EnrollmentStatus::Enrolled { reason, .. }
+                if !matches!(reason, EnrolledReason::OptIn) =>
+            {
+                log::debug!(
+                    "Existing experiment enrollment '{}' is now disqualified (unsupported feature)",
+                    &self.slug
+                );
+                let updated_enrollment =
+                    self.disqualify_from_enrolled(DisqualifiedReason::NotTargeted);
+                out_enrollment_events.push(updated_enrollment.get_change_event());
+                updated_enrollment
+            }
+            _ => self.clone(),
+        }
+    }
+
     fn disqualify_from_enrolled(&self, reason: DisqualifiedReason) -> Self {
         match self.status {
             EnrollmentStatus::Enrolled { ref branch, .. } => ExperimentEnrollment {
@@ -534,8 +591,9 @@ pub(crate) trait ExperimentMetadata {
 
 pub(crate) struct EnrollmentsEvolver<'a> {
     available_randomization_units: &'a AvailableRandomizationUnits,
-    targeting_helper: &'a NimbusTargetingHelper,
+    pub(crate) targeting_helper: &'a NimbusTargetingHelper,
     coenrolling_feature_ids: &'a HashSet<&'a str>,
+    unsupported_feature_ids: HashSet<String>,
 }
 
 impl<'a> EnrollmentsEvolver<'a> {
@@ -548,9 +606,28 @@ impl<'a> EnrollmentsEvolver<'a> {
             available_randomization_units,
             targeting_helper,
             coenrolling_feature_ids,
+            unsupported_feature_ids: Default::default(),
         }
     }
 
+    /// Features which this version of the app doesn't support. Experiments using any of them are
+    /// treated as not targeted: they are never enrolled, and existing enrollments are disqualified.
+    #[cfg_attr(not(feature = "stateful"), allow(unused))]
+    pub(crate) fn with_unsupported_feature_ids(
+        mut self,
+        unsupported_feature_ids: HashSet<String>,
+    ) -> Self {
+        self.unsupported_feature_ids = unsupported_feature_ids;
+        self
+    }
+
+    fn is_supported(&self, experiment: &Experiment) -> bool {
+        experiment
+            .get_feature_ids()
+            .iter()
+            .all(|id| !self.unsupported_feature_ids.contains(id))
+    }
+
     pub(crate) fn evolve_enrollments<E>(
         &self,
         is_user_participating: bool,
@@ -852,6 +929,15 @@ impl<'a> EnrollmentsEvolver<'a> {
             .put("is_already_enrolled", is_already_enrolled);
 
         Ok(match (prev_experiment, next_experiment, prev_enrollment) {
+            // New experiment for a feature this version of the app doesn't support.
+            (None, Some(experiment), None) if !self.is_supported(experiment) => {
+                Some(ExperimentEnrollment {
+                    slug: experiment.slug.clone(),
+                    status: EnrollmentStatus::NotEnrolled {
+                        reason: NotEnrolledReason::NotTargeted,
+                    },
+                })
+            }
             // New experiment.
             (None, Some(experiment), None) => Some(ExperimentEnrollment::from_new_experiment(
                 is_user_participating,
@@ -864,6 +950,10 @@ impl<'a> EnrollmentsEvolver<'a> {
             (Some(_), None, Some(enrollment)) => {
                 enrollment.on_experiment_ended(out_enrollment_events)
             }
+            // Known experiment, now for a feature this version of the app doesn't support.
+            (Some(_), Some(experiment), Some(enrollment)) if !self.is_supported(experiment) => {
+                Some(enrollment.on_experiment_unsupported(out_enrollment_events))
+            }
             // Known experiment.
             (Some(_), Some(experiment), Some(enrollment)) => {
                 Some(enrollment.on_experiment_updated(
@@ -990,21 +1080,59 @@ pub fn map_features_by_feature_id(
     );
     let (experiments, exp_enrollments) =
         filter_experiments_and_enrollments(experiments, enrollments, |exp| !exp.is_rollout());
+    let experiments = map_experiments(&experiments);
 
     let features_under_rollout = map_features(
         &ro_enrollments,
         &map_experiments(&rollouts),
         coenrolling_ids,
     );
-    let features_under_experiment = map_features(
-        &exp_enrollments,
-        &map_experiments(&experiments),
-        coenrolling_ids,
-    );
+    let features_under_experiment = map_features(&exp_enrollments, &experiments, coenrolling_ids);
 
-    features_under_experiment
+    let mut features = features_under_experiment
         .defaults(&features_under_rollout)
-        .unwrap()
+        .unwrap();
+
+    // Features held back by an experiment get only the app's defaults, so rollouts
+    // don't apply to them either.
+    for (feature_id, feature) in features_under_experiment {
+        let is_held_back = match (experiments.get(&feature.slug), &feature.branch) {
+            (Some(experiment), Some(branch)) => experiment.is_holdback_branch(branch),
+            _ => false,
+        };
+        if is_held_back {
+            features.insert(feature_id, feature);
+        }
+    }
+
+    features
+}
+
+/// For each coenrolling feature, the configuration from each of the experiments and rollouts it is
+/// enrolled in, unmerged, with the experiments first.
+#[cfg_attr(not(feature = "stateful"), allow(unused))]
+pub(crate) fn map_coenrolled_features_by_feature_id(
+    enrollments: &[ExperimentEnrollment],
+    experiments: &[Experiment],
+    coenrolling_ids: &HashSet<&str>,
+) -> HashMap<String, Vec<CoenrolledFeatureConfig>> {
+    let experiments = map_experiments(experiments);
+    let mut coenrolled_features: HashMap<String, Vec<CoenrolledFeatureConfig>> = HashMap::new();
+    for enrolled_feature_config in enrollments
+        .iter()
+        .flat_map(|e| get_enrolled_feature_configs(e, &experiments))
+        .filter(|f| coenrolling_ids.contains(f.feature_id.as_str()))
+    {
+        coenrolled_features
+            .entry(enrolled_feature_config.feature_id.clone())
+            .or_default()
+            .push(enrolled_feature_config.into());
+    }
+    for configs in coenrolled_features.values_mut() {
+        // Rollouts have no branch.
+        configs.sort_by_key(|f| f.branch.is_none());
+    }
+    coenrolled_features
 }
 
 pub(crate) fn populate_feature_maps(
@@ -1059,8 +1187,10 @@ fn get_enrolled_feature_configs(
     };
 
     // Get the branch from the experiment, and then get the feature configs
-    // from there.
+    // from there. Clients in the holdback branch get none of them, so all the
+    // experiment's features are left with their defaults below.
     let mut branch_features = match &experiment.get_branch(branch_slug) {
+        Some(_) if experiment.is_holdback_branch(branch_slug) => Default::default(),
         Some(branch) => branch.get_feature_configs(),
         _ => Default::default(),
     };
@@ -1106,8 +1236,9 @@ fn get_enrolled_feature_configs(
 }
 
 /// Small transitory struct to contain all the information needed to configure a feature with the Feature API.
-/// By design, we don't want to store it on the disk. Instead we calculate it from experiments
-/// and enrollments.
+/// By design, we don't want to store it in the database. Instead we calculate it from experiments
+/// and enrollments. The stateful client keeps a snapshot of them, to serve at startup until the
+/// database is opened.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct EnrolledFeatureConfig {
@@ -1166,6 +1297,27 @@ impl From<&EnrolledFeatureConfig> for EnrolledFeature {
     }
 }
 
+/// The configuration of a coenrolling feature from one of the experiments or rollouts it is
+/// enrolled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoenrolledFeatureConfig {
+    pub slug: String,
+    pub branch: Option<String>,
+    pub feature_id: String,
+    pub value: Map<String, Value>,
+}
+
+impl From<EnrolledFeatureConfig> for CoenrolledFeatureConfig {
+    fn from(value: EnrolledFeatureConfig) -> Self {
+        Self {
+            slug: value.slug,
+            branch: value.branch,
+            feature_id: value.feature_id,
+            value: value.feature.value,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EnrollmentChangeEvent {
     pub experiment_slug: String,
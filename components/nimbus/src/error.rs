@@ -17,6 +17,9 @@ pub enum NimbusError {
     #[cfg(feature = "stateful")]
     #[error("Rkv error: {0}")]
     RkvError(#[from] rkv::StoreError),
+    #[cfg(feature = "stateful")]
+    #[error("SQL error: {0}")]
+    SqlError(String),
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
     #[error("JSON Error: {0}")]
@@ -70,6 +73,20 @@ pub enum NimbusError {
     UniFFICallbackError(#[from] uniffi::UnexpectedUniFFICallbackError),
 }
 
+#[cfg(feature = "sqlite-storage")]
+impl From<rusqlite::Error> for NimbusError {
+    fn from(e: rusqlite::Error) -> Self {
+        NimbusError::SqlError(e.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite-storage")]
+impl From<sql_support::open_database::Error> for NimbusError {
+    fn from(e: sql_support::open_database::Error) -> Self {
+        NimbusError::SqlError(e.to_string())
+    }
+}
+
 #[cfg(feature = "stateful")]
 #[derive(Debug, thiserror::Error)]
 pub enum BehaviorError {
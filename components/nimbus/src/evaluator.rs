@@ -6,7 +6,9 @@
 use crate::{
     enrollment::{EnrolledReason, EnrollmentStatus, ExperimentEnrollment, NotEnrolledReason},
     error::{NimbusError, Result},
-    sampling, AvailableRandomizationUnits, Branch, Experiment, NimbusTargetingHelper,
+    sampling,
+    versioning::Version,
+    AvailableRandomizationUnits, Branch, Experiment, NimbusTargetingHelper,
 };
 use serde_derive::*;
 use serde_json::Value;
@@ -29,27 +31,6 @@ impl Bucket {
     }
 }
 
-fn prefer_none_to_empty(s: Option<&str>) -> Option<String> {
-    let s = s?;
-    if s.is_empty() {
-        None
-    } else {
-        Some(s.to_string())
-    }
-}
-
-pub fn split_locale(locale: String) -> (Option<String>, Option<String>) {
-    if locale.contains('-') {
-        let mut parts = locale.split('-');
-        (
-            prefer_none_to_empty(parts.next()),
-            prefer_none_to_empty(parts.next()),
-        )
-    } else {
-        (Some(locale), None)
-    }
-}
-
 /// Determine the enrolment status for an experiment.
 ///
 /// # Arguments:
@@ -171,7 +152,46 @@ pub fn is_experiment_available(
         }
         (_, _) => log::debug!("Experiment missing channel, skipping it as a targeting parameter"),
     }
-    true
+
+    // Verify the app's version is within the range targeted by the experiment.
+    match th.context.get("app_version".to_string()) {
+        Some(Value::String(mine)) => is_app_version_in_range(exp, mine),
+        _ => {
+            log::debug!("App version unknown, skipping it as a targeting parameter");
+            true
+        }
+    }
+}
+
+/// Whether `app_version` is between the experiment's `min_app_version` and `max_app_version`,
+/// both inclusive, comparing them as Firefox versions, so `120.0b2 < 120.0 < 120.1 < 120.*`.
+/// If any of the versions can't be parsed, the experiment is not available.
+fn is_app_version_in_range(exp: &Experiment, app_version: &str) -> bool {
+    if exp.min_app_version.is_none() && exp.max_app_version.is_none() {
+        return true;
+    }
+    let parse = |version: &str| match Version::try_from(version) {
+        Ok(version) => Some(version),
+        Err(e) => {
+            log::warn!("Invalid version {} for {}: {}", version, exp.slug, e);
+            None
+        }
+    };
+    let app_version = match parse(app_version) {
+        Some(app_version) => app_version,
+        None => return false,
+    };
+    let is_above_min = match exp.min_app_version.as_deref().map(parse) {
+        Some(Some(min)) => app_version >= min,
+        Some(None) => false,
+        None => true,
+    };
+    let is_below_max = match exp.max_app_version.as_deref().map(parse) {
+        Some(Some(max)) => app_version <= max,
+        Some(None) => false,
+        None => true,
+    };
+    is_above_min && is_below_max
 }
 
 /// Chooses a branch randomly from a set of branches
@@ -244,26 +264,3 @@ pub(crate) fn targeting(
         }),
     }
 }
-
-#[cfg(test)]
-mod unit_tests {
-    use super::*;
-
-    #[test]
-    fn test_splitting_locale() -> Result<()> {
-        assert_eq!(
-            split_locale("en-US".to_string()),
-            (Some("en".to_string()), Some("US".to_string()))
-        );
-        assert_eq!(
-            split_locale("es".to_string()),
-            (Some("es".to_string()), None)
-        );
-
-        assert_eq!(
-            split_locale("-unknown".to_string()),
-            (None, Some("unknown".to_string()))
-        );
-        Ok(())
-    }
-}
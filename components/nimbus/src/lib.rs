@@ -8,6 +8,7 @@ mod defaults;
 mod enrollment;
 mod evaluator;
 mod json;
+mod locale;
 mod sampling;
 mod strings;
 mod targeting;
@@ -17,7 +18,7 @@ pub mod metrics;
 pub mod schema;
 pub mod versioning;
 
-pub use enrollment::{EnrolledFeature, EnrollmentStatus};
+pub use enrollment::{CoenrolledFeatureConfig, EnrolledFeature, EnrollmentStatus};
 pub use error::{NimbusError, Result};
 #[cfg(debug_assertions)]
 pub use evaluator::evaluate_enrollment;
@@ -0,0 +1,159 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Canonicalization of the locales supplied by apps, so that targeting on `locale`,
+//! `language` and `region` behaves the same whichever platform the locale came from.
+//!
+//! Android gives locales like `en-US`, iOS like `en_US` or `zh-Hant_TW`, and POSIX
+//! environments like `en_US.UTF-8`; these are all canonicalized to BCP 47 style, e.g.
+//! `en-US` and `zh-Hant-TW`.
+
+/// A locale, split into its language, script and region.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Locale {
+    /// The lowercase language, e.g. `en`.
+    pub(crate) language: Option<String>,
+    /// The titlecase script, e.g. `Hant`.
+    pub(crate) script: Option<String>,
+    /// The uppercase region, e.g. `US`.
+    pub(crate) region: Option<String>,
+}
+
+impl Locale {
+    /// Parses a locale, leniently: variants, extensions and POSIX encodings and modifiers
+    /// are ignored, and missing parts are `None`.
+    pub(crate) fn parse(locale: &str) -> Self {
+        // Drop a POSIX encoding or modifier, e.g. `en_US.UTF-8` or `de_DE@euro`.
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let mut subtags = locale.split(['-', '_']).peekable();
+
+        let language = subtags.next().and_then(|s| non_empty(s.to_lowercase()));
+        let script = subtags
+            .next_if(|s| s.len() == 4 && s.chars().all(|c| c.is_ascii_alphabetic()))
+            .map(titlecase);
+        let region = subtags
+            .next_if(|s| (2..=3).contains(&s.len()))
+            .map(str::to_uppercase);
+
+        Self {
+            language,
+            script,
+            region,
+        }
+    }
+
+    /// The canonical form of the locale, e.g. `zh-Hant-TW`.
+    pub(crate) fn canonical(&self) -> Option<String> {
+        let language = self.language.as_ref()?;
+        Some(
+            std::iter::once(language)
+                .chain(&self.script)
+                .chain(&self.region)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join("-"),
+        )
+    }
+
+    /// The locales to try, most specific first, e.g. `zh-Hant-TW`, `zh-Hant`, `zh`.
+    pub(crate) fn fallbacks(&self) -> Vec<String> {
+        let Some(language) = &self.language else {
+            return Vec::new();
+        };
+        let mut fallbacks = Vec::new();
+        if let Some(region) = &self.region {
+            fallbacks.push(match &self.script {
+                Some(script) => format!("{language}-{script}-{region}"),
+                None => format!("{language}-{region}"),
+            });
+        }
+        if let Some(script) = &self.script {
+            fallbacks.push(format!("{language}-{script}"));
+        }
+        fallbacks.push(language.clone());
+        fallbacks
+    }
+}
+
+/// Canonicalizes a locale, e.g. `en_us` to `en-US`. Locales without a language are left
+/// as they are.
+pub(crate) fn canonicalize_locale(locale: &str) -> String {
+    Locale::parse(locale)
+        .canonical()
+        .unwrap_or_else(|| locale.to_string())
+}
+
+/// Canonicalizes a region, e.g. `us` to `US`, or `None` if it is empty.
+#[cfg_attr(not(feature = "stateful"), allow(unused))]
+pub(crate) fn canonicalize_region(region: &str) -> Option<String> {
+    non_empty(region.trim().to_uppercase())
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+fn titlecase(s: &str) -> String {
+    let lower = s.to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => lower,
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn assert_split(locale: &str, language: Option<&str>, region: Option<&str>) {
+        let parsed = Locale::parse(locale);
+        assert_eq!(parsed.language.as_deref(), language, "language of {locale}");
+        assert_eq!(parsed.region.as_deref(), region, "region of {locale}");
+    }
+
+    #[test]
+    fn test_splitting_locale() {
+        assert_split("en-US", Some("en"), Some("US"));
+        assert_split("es", Some("es"), None);
+        assert_split("-us", None, Some("US"));
+        // Subtags which can't be a region aren't taken as one.
+        assert_split("-unknown", None, None);
+        // Locales from other platforms are split the same way.
+        assert_split("en_us", Some("en"), Some("US"));
+        assert_split("en_US.UTF-8", Some("en"), Some("US"));
+        assert_split("de_DE@euro", Some("de"), Some("DE"));
+        assert_split("es-419", Some("es"), Some("419"));
+    }
+
+    #[test]
+    fn test_canonical_locale() {
+        assert_eq!(canonicalize_locale("en_us"), "en-US");
+        assert_eq!(canonicalize_locale("zh-hant_tw"), "zh-Hant-TW");
+        assert_eq!(canonicalize_locale("sr-Latn"), "sr-Latn");
+        assert_eq!(canonicalize_locale("en-US-u-ca-gregory"), "en-US");
+        assert_eq!(canonicalize_locale("-US"), "-US");
+    }
+
+    #[test]
+    fn test_locale_fallbacks() {
+        assert_eq!(
+            Locale::parse("zh-Hant-TW").fallbacks(),
+            vec!["zh-Hant-TW", "zh-Hant", "zh"]
+        );
+        assert_eq!(Locale::parse("en_US").fallbacks(), vec!["en-US", "en"]);
+        assert_eq!(Locale::parse("fr").fallbacks(), vec!["fr"]);
+        assert!(Locale::parse("").fallbacks().is_empty());
+    }
+
+    #[test]
+    fn test_canonical_region() {
+        assert_eq!(canonicalize_region(" gb "), Some("GB".to_string()));
+        assert_eq!(canonicalize_region(""), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{enrollment::ExperimentEnrollment, EnrolledFeature, EnrollmentStatus};
+use crate::{enrollment::ExperimentEnrollment, EnrolledFeature, EnrollmentStatus, Experiment};
 use serde_derive::{Deserialize, Serialize};
 
 pub trait MetricsHandler: Send + Sync {
@@ -12,6 +12,9 @@ pub trait MetricsHandler: Send + Sync {
 
     #[cfg(feature = "stateful")]
     fn record_malformed_feature_config(&self, event: MalformedFeatureConfigExtraDef);
+
+    #[cfg(feature = "stateful")]
+    fn record_fetch_error(&self, event: FetchErrorExtraDef);
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -19,6 +22,7 @@ pub struct EnrollmentStatusExtraDef {
     pub branch: Option<String>,
     pub conflict_slug: Option<String>,
     pub error_string: Option<String>,
+    pub is_holdback: Option<bool>,
     pub reason: Option<String>,
     pub slug: Option<String>,
     pub status: Option<String>,
@@ -40,6 +44,10 @@ impl EnrollmentStatusExtraDef {
         self.error_string.as_ref().unwrap()
     }
 
+    pub fn is_holdback(&self) -> bool {
+        self.is_holdback.unwrap()
+    }
+
     pub fn reason(&self) -> &str {
         self.reason.as_ref().unwrap()
     }
@@ -84,6 +92,7 @@ impl From<ExperimentEnrollment> for EnrollmentStatusExtraDef {
             branch: branch_value,
             conflict_slug: None,
             error_string: error_value,
+            is_holdback: None,
             reason: reason_value,
             slug: Some(enrollment.slug),
             status: Some(enrollment.status.name()),
@@ -93,6 +102,20 @@ impl From<ExperimentEnrollment> for EnrollmentStatusExtraDef {
     }
 }
 
+impl EnrollmentStatusExtraDef {
+    /// Records whether the client's branch, if it has one, is the experiment's holdback branch.
+    pub(crate) fn with_holdback(self, experiment: &Experiment) -> Self {
+        let is_holdback = self
+            .branch
+            .as_deref()
+            .map(|branch| experiment.is_holdback_branch(branch));
+        Self {
+            is_holdback,
+            ..self
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct FeatureExposureExtraDef {
     pub branch: Option<String>,
@@ -137,3 +160,10 @@ impl MalformedFeatureConfigExtraDef {
         }
     }
 }
+
+/// Recorded when fetching the experiments fails.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FetchErrorExtraDef {
+    /// The kind of failure: `network`, `backoff`, `server`, `signature` or `other`.
+    pub reason: String,
+}
@@ -19,6 +19,23 @@ pub struct EnrolledExperiment {
     pub branch_slug: String,
 }
 
+/// An experiment or rollout this client is enrolled in, as shown by debugging UIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveEnrollment {
+    pub slug: String,
+    pub branch_slug: String,
+    pub feature_ids: Vec<String>,
+    pub is_rollout: bool,
+    /// Whether this client is in the experiment's holdback branch, so gets only the app's
+    /// defaults for its features.
+    pub is_holdback: bool,
+    /// Whether the experiment has stopped enrolling new clients. Existing enrollments carry on.
+    pub is_enrollment_paused: bool,
+    /// When this client enrolled, in milliseconds since the Unix epoch. This is `None` for
+    /// enrollments made before enrollment dates were recorded.
+    pub enrollment_date: Option<i64>,
+}
+
 // ⚠️ Attention : Changes to this type should be accompanied by a new test  ⚠️
 // ⚠️ in `test_lib_bw_compat.rs`, and may require a DB migration. ⚠️
 #[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
@@ -46,6 +63,21 @@ pub struct Experiment {
     pub reference_branch: Option<String>,
     #[serde(default)]
     pub is_rollout: bool,
+    /// Once this client has matched the `targeting`, it stays eligible for the experiment even if
+    /// the attributes it was targeted on change, e.g. `is_first_run`.
+    #[serde(default)]
+    pub is_sticky: bool,
+    /// The branch whose clients are held back from the experiment's features: they get only
+    /// the app's defaults, rather than the configuration of a branch or of any rollout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holdback_branch: Option<String>,
+    /// The lowest version of the app the recipe is available to, inclusive, compared as a
+    /// Firefox version, e.g. `120.0b2`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_app_version: Option<String>,
+    /// The highest version of the app the recipe is available to, inclusive, e.g. `121.*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_app_version: Option<String>,
     pub published_date: Option<chrono::DateTime<chrono::Utc>>,
     // N.B. records in RemoteSettings will have `id` and `filter_expression` fields,
     // but we ignore them because they're for internal use by RemoteSettings.
@@ -63,6 +95,10 @@ impl Experiment {
         self.branches.iter().find(|b| b.slug == branch_slug)
     }
 
+    pub(crate) fn is_holdback_branch(&self, branch_slug: &str) -> bool {
+        self.holdback_branch.as_deref() == Some(branch_slug)
+    }
+
     pub(crate) fn get_feature_ids(&self) -> Vec<String> {
         let branches = &self.branches;
         let feature_ids = branches
@@ -102,7 +138,74 @@ impl ExperimentMetadata for Experiment {
     }
 }
 
+/// A record in the experiments collection which remotely turns off a feature, replacing the
+/// configuration from experiments and rollouts, and the app's defaults, with `value`.
+///
+/// ```json
+/// { "id": "disable-homescreen", "killSwitch": { "featureId": "homescreen" } }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureKillSwitch {
+    pub feature_id: String,
+    /// The configuration the feature is given while it is disabled. This defaults to
+    /// `{"enabled": false}`, which turns off features following the usual convention.
+    #[serde(default = "FeatureKillSwitch::default_value")]
+    pub value: Map<String, Value>,
+}
+
+impl FeatureKillSwitch {
+    fn default_value() -> Map<String, Value> {
+        let mut value = Map::new();
+        value.insert("enabled".to_string(), Value::Bool(false));
+        value
+    }
+}
+
+/// The records of the experiments collection: the experiments and rollouts, and the
+/// kill switches for features.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Recipes {
+    pub experiments: Vec<Experiment>,
+    pub kill_switches: Vec<FeatureKillSwitch>,
+}
+
+impl Recipes {
+    /// Adds a record from the collection, omitting it if it is malformed.
+    pub(crate) fn push_record(&mut self, record: &Value) {
+        let id = record
+            .get("id")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!("ID_NOT_FOUND"));
+        if let Some(kill_switch) = record.get("killSwitch") {
+            match serde_json::from_value::<FeatureKillSwitch>(kill_switch.clone()) {
+                Ok(kill_switch) => self.kill_switches.push(kill_switch),
+                Err(e) => log::warn!("Malformed kill switch found! Record {},  Error: {}", id, e),
+            }
+            return;
+        }
+        // XXX: In the future it would be nice if this lived in its own versioned crate so that
+        // the schema could be decoupled from the sdk so that it can be iterated on while the
+        // sdk depends on a particular version of the schema through the Cargo.toml.
+        match serde_json::from_value::<Experiment>(record.clone()) {
+            Ok(exp) => self.experiments.push(exp),
+            Err(e) => {
+                log::trace!("Malformed experiment data: {:#?}", record);
+                log::warn!(
+                    "Malformed experiment found! Experiment {},  Error: {}",
+                    id,
+                    e
+                );
+            }
+        }
+    }
+}
+
 pub fn parse_experiments(payload: &str) -> Result<Vec<Experiment>> {
+    Ok(parse_recipes(payload)?.experiments)
+}
+
+pub fn parse_recipes(payload: &str) -> Result<Recipes> {
     // We first encode the response into a `serde_json::Value`
     // to allow us to deserialize each experiment individually,
     // omitting any malformed experiments
@@ -110,25 +213,12 @@ pub fn parse_experiments(payload: &str) -> Result<Vec<Experiment>> {
     let data = value
         .get("data")
         .ok_or(NimbusError::InvalidExperimentFormat)?;
-    let mut res = Vec::new();
+    let mut res = Recipes::default();
     for exp in data
         .as_array()
         .ok_or(NimbusError::InvalidExperimentFormat)?
     {
-        // XXX: In the future it would be nice if this lived in its own versioned crate so that
-        // the schema could be decoupled from the sdk so that it can be iterated on while the
-        // sdk depends on a particular version of the schema through the Cargo.toml.
-        match serde_json::from_value::<Experiment>(exp.clone()) {
-            Ok(exp) => res.push(exp),
-            Err(e) => {
-                log::trace!("Malformed experiment data: {:#?}", exp);
-                log::warn!(
-                    "Malformed experiment found! Experiment {},  Error: {}",
-                    exp.get("id").unwrap_or(&serde_json::json!("ID_NOT_FOUND")),
-                    e
-                );
-            }
-        }
+        res.push_record(exp);
     }
     Ok(res)
 }
@@ -255,11 +345,13 @@ impl From<Branch> for ExperimentBranch {
 
 // ⚠️ Attention : Changes to this type should be accompanied by a new test  ⚠️
 // ⚠️ in `test_lib_bw_compat`, and may require a DB migration. ⚠️
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Hash, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum RandomizationUnit {
     NimbusId,
     UserId,
+    /// An identifier shared by a group of clients, e.g. a household, supplied by the app.
+    GroupId,
 }
 
 impl Default for RandomizationUnit {
@@ -272,6 +364,10 @@ impl Default for RandomizationUnit {
 pub struct AvailableRandomizationUnits {
     pub user_id: Option<String>,
     pub nimbus_id: Option<String>,
+    pub group_id: Option<String>,
+    /// When set, this is bucketed on in place of every randomization unit, so tests can
+    /// choose which branches the client is enrolled in.
+    pub bucketing_seed: Option<String>,
 }
 
 impl AvailableRandomizationUnits {
@@ -280,14 +376,14 @@ impl AvailableRandomizationUnits {
     pub fn with_user_id(user_id: &str) -> Self {
         Self {
             user_id: Some(user_id.to_string()),
-            nimbus_id: None,
+            ..Default::default()
         }
     }
 
     pub fn with_nimbus_id(nimbus_id: &Uuid) -> Self {
         Self {
-            user_id: None,
             nimbus_id: Some(nimbus_id.to_string()),
+            ..Default::default()
         }
     }
 
@@ -295,13 +391,19 @@ impl AvailableRandomizationUnits {
         Self {
             user_id: self.user_id.clone(),
             nimbus_id: Some(nimbus_id.to_string()),
+            group_id: self.group_id.clone(),
+            bucketing_seed: self.bucketing_seed.clone(),
         }
     }
 
     pub fn get_value<'a>(&'a self, wanted: &'a RandomizationUnit) -> Option<&'a str> {
+        if let Some(seed) = &self.bucketing_seed {
+            return Some(seed);
+        }
         match wanted {
             RandomizationUnit::NimbusId => self.nimbus_id.as_deref(),
             RandomizationUnit::UserId => self.user_id.as_deref(),
+            RandomizationUnit::GroupId => self.group_id.as_deref(),
         }
     }
 }
@@ -541,7 +541,10 @@ impl EventStore {
     pub fn clear(&mut self, db: &Database) -> Result<()> {
         self.events = HashMap::<String, MultiIntervalCounter>::new();
         self.datum = None;
-        self.persist_data(db)?;
+        // Persisting the now empty store wouldn't remove the counts already in the database.
+        let mut writer = db.write()?;
+        db.clear_event_count_data(&mut writer)?;
+        writer.commit()?;
         Ok(())
     }
 
@@ -8,7 +8,8 @@
 
 use crate::error::Result;
 use crate::stateful::client::SettingsClient;
-use crate::Experiment;
+use crate::Recipes;
+use serde_json::Value;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::BufReader;
@@ -31,9 +32,9 @@ impl SettingsClient for FileSystemClient {
         unimplemented!();
     }
 
-    fn fetch_experiments(&self) -> Result<Vec<Experiment>> {
+    fn fetch_experiments(&self, _verify_signatures: bool) -> Result<Recipes> {
         log::info!("reading experiments in {}", self.path.display());
-        let mut res = Vec::new();
+        let mut res = Recipes::default();
         // Skip directories and non .json files (eg, READMEs)
         let json_ext = Some(OsStr::new("json"));
         let filenames = self
@@ -45,8 +46,9 @@ impl SettingsClient for FileSystemClient {
         for child_path in filenames {
             let file = File::open(child_path.clone())?;
             let reader = BufReader::new(file);
-            match serde_json::from_reader::<_, Experiment>(reader) {
-                Ok(exp) => res.push(exp),
+            // Each file holds one record, which is either an experiment or a kill switch.
+            match serde_json::from_reader::<_, Value>(reader) {
+                Ok(record) => res.push_record(&record),
                 Err(e) => {
                     log::warn!(
                         "Malformed experiment found! File {},  Error: {}",
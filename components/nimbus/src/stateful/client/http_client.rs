@@ -14,17 +14,47 @@
 //! But the simple subset implemented here meets our needs for now.
 
 use crate::error::Result;
-use crate::schema::parse_experiments;
-use crate::stateful::client::{Experiment, SettingsClient};
-use remote_settings::Client;
+use crate::schema::{parse_recipes, Recipes};
+use crate::stateful::client::SettingsClient;
+#[cfg(feature = "signatures")]
+use remote_settings::signatures::PROD_ROOT_HASH;
+use remote_settings::{CacheValidators, Client};
+
+// Signatures are only verified with the `signatures` feature; without it,
+// `NimbusClient::should_verify_signatures` is always false.
 
 impl SettingsClient for Client {
     fn get_experiments_metadata(&self) -> Result<String> {
         unimplemented!();
     }
 
-    fn fetch_experiments(&self) -> Result<Vec<Experiment>> {
-        let resp = self.get_records_raw()?;
-        parse_experiments(&resp.text())
+    #[cfg_attr(not(feature = "signatures"), allow(unused_variables))]
+    fn fetch_experiments(&self, verify_signatures: bool) -> Result<Recipes> {
+        #[cfg(feature = "signatures")]
+        if verify_signatures {
+            return parse_recipes(&self.get_records_raw_verified(PROD_ROOT_HASH)?.text());
+        }
+        parse_recipes(&self.get_records_raw()?.text())
+    }
+
+    #[cfg_attr(not(feature = "signatures"), allow(unused_variables))]
+    fn fetch_experiments_if_modified(
+        &self,
+        verify_signatures: bool,
+        validators: &CacheValidators,
+    ) -> Result<Option<(Recipes, CacheValidators)>> {
+        #[cfg(feature = "signatures")]
+        let resp = if verify_signatures {
+            self.get_records_raw_verified_if_modified(PROD_ROOT_HASH, validators)?
+        } else {
+            self.get_records_raw_if_modified(validators)?
+        };
+        #[cfg(not(feature = "signatures"))]
+        let resp = self.get_records_raw_if_modified(validators)?;
+        resp.map(|resp| {
+            let recipes = parse_recipes(&resp.text())?;
+            Ok((recipes, CacheValidators::from_response(&resp)))
+        })
+        .transpose()
     }
 }
@@ -6,11 +6,11 @@ mod fs_client;
 pub(crate) mod http_client;
 pub(crate) mod null_client;
 use crate::error::{NimbusError, Result};
-use crate::Experiment;
+use crate::Recipes;
 use fs_client::FileSystemClient;
 use null_client::NullClient;
 use remote_settings::Client;
-use remote_settings::RemoteSettingsConfig;
+use remote_settings::{CacheValidators, RemoteSettingsConfig};
 use url::Url;
 
 pub(crate) fn create_client(
@@ -43,8 +43,26 @@ pub(crate) fn create_client(
     })
 }
 
-// The trait used to fetch experiments.
+// The trait used to fetch experiments, along with the kill switches in the same collection.
 pub(crate) trait SettingsClient {
     fn get_experiments_metadata(&self) -> Result<String>;
-    fn fetch_experiments(&self) -> Result<Vec<Experiment>>;
+    // `verify_signatures` is only meaningful for clients which fetch signed collections
+    // from a server; the others ignore it.
+    fn fetch_experiments(&self, verify_signatures: bool) -> Result<Recipes>;
+
+    // Fetches the experiments, unless they haven't changed since the response the `validators`
+    // were taken from, in which case `None` is returned. The experiments are returned with the
+    // validators of this response, to be used for the next fetch.
+    //
+    // Clients which can't tell whether the experiments have changed always return them.
+    fn fetch_experiments_if_modified(
+        &self,
+        verify_signatures: bool,
+        _validators: &CacheValidators,
+    ) -> Result<Option<(Recipes, CacheValidators)>> {
+        Ok(Some((
+            self.fetch_experiments(verify_signatures)?,
+            Default::default(),
+        )))
+    }
 }
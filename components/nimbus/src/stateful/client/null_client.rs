@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::error::Result;
-use crate::stateful::client::{Experiment, SettingsClient};
+use crate::stateful::client::{Recipes, SettingsClient};
 
 /// This is a client for use when no server is provided.
 /// Its primary use is for non-Mozilla forks of apps that are not using their
@@ -20,7 +20,7 @@ impl SettingsClient for NullClient {
     fn get_experiments_metadata(&self) -> Result<String> {
         unimplemented!();
     }
-    fn fetch_experiments(&self) -> Result<Vec<Experiment>> {
+    fn fetch_experiments(&self, _verify_signatures: bool) -> Result<Recipes> {
         Ok(Default::default())
     }
 }
@@ -4,22 +4,39 @@
 
 use crate::{
     enrollment::{
-        map_features_by_feature_id, EnrolledFeature, EnrolledFeatureConfig, ExperimentEnrollment,
+        map_coenrolled_features_by_feature_id, map_features_by_feature_id, CoenrolledFeatureConfig,
+        EnrolledFeature, EnrolledFeatureConfig, ExperimentEnrollment,
     },
     error::{NimbusError, Result},
     stateful::{
-        enrollment::get_enrollments,
+        enrollment::{
+            get_enrollments, get_feature_config_overrides, get_kill_switches,
+            FEATURE_CONFIG_OVERRIDE_SLUG, KILL_SWITCH_SLUG,
+        },
         persistence::{Database, StoreId, Writer},
     },
-    EnrolledExperiment, Experiment,
+    EnrolledExperiment, Experiment, FeatureConfig,
 };
+use once_cell::sync::OnceCell;
+use serde_derive::*;
+use serde_json::{Map, Value};
 use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 
 // This module manages an in-memory cache of the database, so that some
 // functions exposed by nimbus can return results without blocking on any
 // IO. Consumers are expected to call our public `update()` function whenever
 // the database might have changed.
+//
+// Until the cache is first updated, which needs the database to be opened, the
+// feature configurations are served from a snapshot written at the last update,
+// so that apps can configure features at startup without waiting on the database.
+
+/// The name of the file, in the database's directory, holding the snapshot of the
+/// feature configurations.
+pub(crate) const FEATURE_CONFIG_SNAPSHOT_FILE: &str = "feature-configs.json";
 
 // This struct is the cached data. This is never mutated, but instead
 // recreated every time the cache is updated.
@@ -28,6 +45,17 @@ struct CachedData {
     pub enrollments: Vec<ExperimentEnrollment>,
     pub experiments_by_slug: HashMap<String, EnrolledExperiment>,
     pub features_by_feature_id: HashMap<String, EnrolledFeatureConfig>,
+    pub coenrolled_features_by_feature_id: HashMap<String, Vec<CoenrolledFeatureConfig>>,
+    pub disabled_feature_ids: HashSet<String>,
+}
+
+// The feature configurations as of the last update of the cache. These are derived from
+// the database, so the snapshot is rewritten rather than migrated when this changes.
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct FeatureConfigSnapshot {
+    features_by_feature_id: HashMap<String, EnrolledFeatureConfig>,
+    disabled_feature_ids: HashSet<String>,
 }
 
 // This is the public cache API. Each NimbusClient can create one of these and
@@ -36,9 +64,20 @@ struct CachedData {
 #[derive(Default)]
 pub struct DatabaseCache {
     data: RwLock<Option<CachedData>>,
+    // Where the snapshot is kept; without it, nothing is served before the first update.
+    snapshot_path: Option<PathBuf>,
+    // The snapshot, read the first time it is needed, or `None` if there isn't one.
+    snapshot: OnceCell<Option<FeatureConfigSnapshot>>,
 }
 
 impl DatabaseCache {
+    pub fn new<P: AsRef<Path>>(db_path: P) -> Self {
+        Self {
+            snapshot_path: Some(db_path.as_ref().join(FEATURE_CONFIG_SNAPSHOT_FILE)),
+            ..Default::default()
+        }
+    }
+
     // Call this function whenever it's possible that anything cached by this
     // struct (eg, our enrollments) might have changed.
     //
@@ -55,7 +94,7 @@ impl DatabaseCache {
         db: &Database,
         writer: Writer,
         coenrolling_ids: &HashSet<&str>,
-    ) -> Result<()> {
+    ) -> Result<Vec<String>> {
         // By passing in the active `writer` we read the state of enrollments
         // as written by the calling code, before it's committed to the db.
         let enrollments = get_enrollments(db, &writer)?;
@@ -72,22 +111,65 @@ impl DatabaseCache {
         let experiments: Vec<Experiment> =
             db.get_store(StoreId::Experiments).collect_all(&writer)?;
 
-        let features_by_feature_id =
+        let mut features_by_feature_id =
             map_features_by_feature_id(&enrollments, &experiments, coenrolling_ids);
+        let mut coenrolled_features_by_feature_id =
+            map_coenrolled_features_by_feature_id(&enrollments, &experiments, coenrolling_ids);
+
+        // Kill switches replace the configuration from experiments and rollouts, including
+        // those of coenrolling features. Like overrides, they have no branch, so no activation
+        // or exposure events are recorded for them.
+        let mut disabled_feature_ids = HashSet::new();
+        for kill_switch in get_kill_switches(db, &writer)? {
+            let feature_id = kill_switch.feature_id;
+            let feature = EnrolledFeatureConfig {
+                feature: FeatureConfig {
+                    feature_id: feature_id.clone(),
+                    value: kill_switch.value,
+                },
+                slug: KILL_SWITCH_SLUG.to_string(),
+                branch: None,
+                feature_id: feature_id.clone(),
+            };
+            coenrolled_features_by_feature_id.remove(&feature_id);
+            features_by_feature_id.insert(feature_id.clone(), feature);
+            disabled_feature_ids.insert(feature_id);
+        }
 
-        // This is where testing tools would override i.e. replace experimental feature configurations.
-        // i.e. testing tools would cause custom feature configs to be stored in a Store.
-        // Here, we get those overrides out of the store, and merge it with this map.
+        // Feature configurations set by testing tools replace those from experiments, and
+        // from kill switches.
+        // They have no branch, so no activation or exposure events are recorded for them.
+        for (feature_id, value) in get_feature_config_overrides(db, &writer)? {
+            let feature = EnrolledFeatureConfig {
+                feature: FeatureConfig {
+                    feature_id: feature_id.clone(),
+                    value,
+                },
+                slug: FEATURE_CONFIG_OVERRIDE_SLUG.to_string(),
+                branch: None,
+                feature_id: feature_id.clone(),
+            };
+            features_by_feature_id.insert(feature_id, feature);
+        }
 
         // This is where rollouts (promoted experiments on a given feature) will be merged in to the feature variables.
 
+        let snapshot = FeatureConfigSnapshot {
+            features_by_feature_id: features_by_feature_id.clone(),
+            disabled_feature_ids: disabled_feature_ids.clone(),
+        };
         let data = CachedData {
             experiments,
             enrollments,
             experiments_by_slug,
             features_by_feature_id,
+            coenrolled_features_by_feature_id,
+            disabled_feature_ids,
         };
 
+        let changed_feature_ids = self.changed_feature_ids(&data);
+        let is_snapshot_stale = self.is_snapshot_stale(&snapshot);
+
         // Try to commit the change to disk and update the cache as close
         // together in time as possible. This leaves a small window where another
         // thread could read new data from disk but see old data in the cache,
@@ -95,9 +177,97 @@ impl DatabaseCache {
         // The alternative would be to lock the cache while we commit to disk,
         // and we don't want to risk blocking the main thread.
         writer.commit()?;
-        let mut cached = self.data.write().unwrap();
-        cached.replace(data);
-        Ok(())
+        self.data.write().unwrap().replace(data);
+        if is_snapshot_stale {
+            self.write_snapshot(&snapshot);
+        }
+        Ok(changed_feature_ids)
+    }
+
+    // Whether the snapshot on disk differs from `snapshot`, so it has to be rewritten. Most
+    // updates, e.g. recording an event, don't change the feature configurations.
+    fn is_snapshot_stale(&self, snapshot: &FeatureConfigSnapshot) -> bool {
+        match *self.data.read().unwrap() {
+            Some(ref previous) => {
+                previous.features_by_feature_id != snapshot.features_by_feature_id
+                    || previous.disabled_feature_ids != snapshot.disabled_feature_ids
+            }
+            None => self.read_snapshot() != Some(snapshot),
+        }
+    }
+
+    // The ids of the features whose configuration differs between the cached data, or the
+    // snapshot it was served from before initialization, and the new data.
+    fn changed_feature_ids(&self, data: &CachedData) -> Vec<String> {
+        fn changed<V: PartialEq>(
+            previous: &HashMap<String, V>,
+            current: &HashMap<String, V>,
+        ) -> HashSet<String> {
+            previous
+                .keys()
+                .chain(current.keys())
+                .filter(|id| previous.get(*id) != current.get(*id))
+                .cloned()
+                .collect()
+        }
+
+        let no_features = HashMap::new();
+        let no_coenrolled_features = HashMap::new();
+        let cached = self.data.read().unwrap();
+        let (previous_features, previous_coenrolled_features) = match *cached {
+            Some(ref previous) => (
+                &previous.features_by_feature_id,
+                &previous.coenrolled_features_by_feature_id,
+            ),
+            None => (
+                self.read_snapshot()
+                    .map(|snapshot| &snapshot.features_by_feature_id)
+                    .unwrap_or(&no_features),
+                &no_coenrolled_features,
+            ),
+        };
+        let mut feature_ids: Vec<String> = changed(previous_features, &data.features_by_feature_id)
+            .union(&changed(
+                previous_coenrolled_features,
+                &data.coenrolled_features_by_feature_id,
+            ))
+            .cloned()
+            .collect();
+        feature_ids.sort();
+        feature_ids
+    }
+
+    // Failing to write the snapshot only slows down the next startup, so it isn't an error.
+    fn write_snapshot(&self, snapshot: &FeatureConfigSnapshot) {
+        let Some(path) = &self.snapshot_path else {
+            return;
+        };
+        // Write to a temporary file first, so a crash can't leave a partial snapshot.
+        let tmp_path = path.with_extension("json.tmp");
+        let write = || -> Result<()> {
+            fs::write(&tmp_path, serde_json::to_vec(snapshot)?)?;
+            fs::rename(&tmp_path, path)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            log::warn!("Failed to write the feature config snapshot: {}", e);
+        }
+    }
+
+    fn read_snapshot(&self) -> Option<&FeatureConfigSnapshot> {
+        let path = self.snapshot_path.as_ref()?;
+        self.snapshot
+            .get_or_init(|| {
+                let bytes = fs::read(path).ok()?;
+                match serde_json::from_slice(&bytes) {
+                    Ok(snapshot) => Some(snapshot),
+                    Err(e) => {
+                        log::warn!("Ignoring malformed feature config snapshot: {}", e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
     }
 
     // Abstracts safely referencing our cached data.
@@ -121,6 +291,32 @@ impl DatabaseCache {
         }
     }
 
+    // Like `get_data`, but for the feature configurations, which are served from the
+    // snapshot until the cache is first updated.
+    fn get_features<T, F>(&self, func: F) -> Result<T>
+    where
+        F: FnOnce(&HashMap<String, EnrolledFeatureConfig>, &HashSet<String>) -> T,
+    {
+        if let Some(ref data) = *self.data.read().unwrap() {
+            return Ok(func(
+                &data.features_by_feature_id,
+                &data.disabled_feature_ids,
+            ));
+        }
+        match self.read_snapshot() {
+            Some(snapshot) => Ok(func(
+                &snapshot.features_by_feature_id,
+                &snapshot.disabled_feature_ids,
+            )),
+            None => {
+                log::warn!(
+                    "DatabaseCache attempting to read data before initialization is completed"
+                );
+                Err(NimbusError::DatabaseNotReady)
+            }
+        }
+    }
+
     pub fn get_experiment_branch(&self, id: &str) -> Result<Option<String>> {
         self.get_data(|data| -> Option<String> {
             data.experiments_by_slug
@@ -132,21 +328,45 @@ impl DatabaseCache {
     // This gives access to the feature JSON. We pass it as a string because uniffi doesn't
     // support JSON yet.
     pub fn get_feature_config_variables(&self, feature_id: &str) -> Result<Option<String>> {
-        self.get_data(|data| {
-            let enrolled_feature = data.features_by_feature_id.get(feature_id)?;
+        self.get_features(|features_by_feature_id, _| {
+            let enrolled_feature = features_by_feature_id.get(feature_id)?;
             let string = serde_json::to_string(&enrolled_feature.feature.value).unwrap();
             Some(string)
         })
     }
 
-    pub fn get_enrollment_by_feature(&self, feature_id: &str) -> Result<Option<EnrolledFeature>> {
+    pub fn get_feature_config_value(&self, feature_id: &str) -> Result<Option<Map<String, Value>>> {
+        self.get_features(|features_by_feature_id, _| {
+            features_by_feature_id
+                .get(feature_id)
+                .map(|enrolled_feature| enrolled_feature.feature.value.clone())
+        })
+    }
+
+    pub fn get_coenrolled_feature_configs(
+        &self,
+        feature_id: &str,
+    ) -> Result<Vec<CoenrolledFeatureConfig>> {
         self.get_data(|data| {
-            data.features_by_feature_id
+            data.coenrolled_features_by_feature_id
+                .get(feature_id)
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn get_enrollment_by_feature(&self, feature_id: &str) -> Result<Option<EnrolledFeature>> {
+        self.get_features(|features_by_feature_id, _| {
+            features_by_feature_id
                 .get(feature_id)
                 .map(|feature| feature.into())
         })
     }
 
+    pub fn is_feature_disabled(&self, feature_id: &str) -> Result<bool> {
+        self.get_features(|_, disabled_feature_ids| disabled_feature_ids.contains(feature_id))
+    }
+
     pub fn get_active_experiments(&self) -> Result<Vec<EnrolledExperiment>> {
         self.get_data(|data| {
             data.experiments_by_slug
@@ -8,11 +8,25 @@ use crate::{
     },
     error::Result,
     stateful::persistence::{Database, Readable, StoreId, Writer},
-    EnrolledExperiment, EnrollmentStatus, Experiment,
+    ActiveEnrollment, EnrolledExperiment, EnrollmentStatus, Experiment, FeatureKillSwitch,
 };
+use chrono::{DateTime, Utc};
+use serde_derive::*;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
 
 const DB_KEY_GLOBAL_USER_PARTICIPATION: &str = "user-opt-in";
 const DEFAULT_GLOBAL_USER_PARTICIPATION: bool = true;
+const DB_KEY_FEATURE_CONFIG_OVERRIDES: &str = "feature-config-overrides";
+pub(crate) const DB_KEY_ENROLLMENT_DATES: &str = "enrollment-dates";
+pub(crate) const DB_KEY_KILL_SWITCHES: &str = "kill-switches";
+const DB_KEY_COMPLETED_EXPERIMENTS: &str = "completed-experiments";
+
+/// The slug reported for features whose configuration has been set for testing.
+pub(crate) const FEATURE_CONFIG_OVERRIDE_SLUG: &str = "nimbus-testing-override";
+
+/// The slug reported for features which have been disabled by a kill switch.
+pub(crate) const KILL_SWITCH_SLUG: &str = "nimbus-kill-switch";
 
 impl<'a> EnrollmentsEvolver<'a> {
     /// Convenient wrapper around `evolve_enrollments` that fetches the current state of experiments,
@@ -29,11 +43,25 @@ impl<'a> EnrollmentsEvolver<'a> {
         let enrollments_store = db.get_store(StoreId::Enrollments);
         let prev_experiments: Vec<Experiment> = experiments_store.collect_all(writer)?;
         let prev_enrollments: Vec<ExperimentEnrollment> = enrollments_store.collect_all(writer)?;
+        let sticky_matches = self.update_sticky_targeting(db, writer, next_experiments)?;
+        // Sticky experiments whose targeting we've matched before are evaluated without it.
+        let targeted_experiments: Vec<Experiment> = next_experiments
+            .iter()
+            .map(|exp| match sticky_matches.get(&exp.slug) {
+                Some(targeting) if exp.is_sticky && exp.targeting.as_ref() == Some(targeting) => {
+                    Experiment {
+                        targeting: None,
+                        ..exp.clone()
+                    }
+                }
+                _ => exp.clone(),
+            })
+            .collect();
         // Calculate the changes.
         let (next_enrollments, enrollments_change_events) = self.evolve_enrollments(
             is_user_participating,
             &prev_experiments,
-            next_experiments,
+            &targeted_experiments,
             &prev_enrollments,
         )?;
         let next_enrollments = map_enrollments(&next_enrollments);
@@ -53,14 +81,59 @@ impl<'a> EnrollmentsEvolver<'a> {
         }
         Ok(enrollments_change_events)
     }
+
+    /// Records the targeting of any sticky experiments this client now matches, and forgets
+    /// matches for experiments which have gone away or changed their targeting.
+    ///
+    /// Returns the targeting matched for each experiment, keyed by slug.
+    fn update_sticky_targeting(
+        &self,
+        db: &Database,
+        writer: &mut Writer,
+        next_experiments: &[Experiment],
+    ) -> Result<HashMap<String, String>> {
+        let store = db.get_store(StoreId::StickyTargeting);
+        let prev_matches: Vec<StickyTargetingMatch> = store.collect_all(writer)?;
+        let prev_matches: HashMap<String, String> = prev_matches
+            .into_iter()
+            .map(|m| (m.slug, m.targeting))
+            .collect();
+
+        let mut next_matches = HashMap::new();
+        for exp in next_experiments.iter().filter(|exp| exp.is_sticky) {
+            let targeting = match &exp.targeting {
+                Some(targeting) => targeting,
+                None => continue,
+            };
+            let is_match = prev_matches.get(&exp.slug) == Some(targeting)
+                || matches!(self.targeting_helper.eval_jexl(targeting.clone()), Ok(true));
+            if is_match {
+                next_matches.insert(exp.slug.clone(), targeting.clone());
+            }
+        }
+
+        store.clear(writer)?;
+        for (slug, targeting) in &next_matches {
+            let sticky_match = StickyTargetingMatch {
+                slug: slug.clone(),
+                targeting: targeting.clone(),
+            };
+            store.put(writer, slug, &sticky_match)?;
+        }
+        Ok(next_matches)
+    }
+}
+
+/// The targeting of a sticky experiment, recorded when this client first matched it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StickyTargetingMatch {
+    pub(crate) slug: String,
+    pub(crate) targeting: String,
 }
 
 /// Return information about all enrolled experiments.
 /// Note this does not include rollouts
-pub fn get_enrollments<'r>(
-    db: &Database,
-    reader: &'r impl Readable<'r>,
-) -> Result<Vec<EnrolledExperiment>> {
+pub fn get_enrollments(db: &Database, reader: &impl Readable) -> Result<Vec<EnrolledExperiment>> {
     let enrollments: Vec<ExperimentEnrollment> =
         db.get_store(StoreId::Enrollments).collect_all(reader)?;
     let mut result = Vec::with_capacity(enrollments.len());
@@ -92,6 +165,94 @@ pub fn get_enrollments<'r>(
     Ok(result)
 }
 
+/// Record the date of each current enrollment, keeping the dates of enrollments which are
+/// already known and forgetting those of enrollments which have ended.
+pub fn update_enrollment_dates(
+    db: &Database,
+    writer: &mut Writer,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let store = db.get_store(StoreId::Meta);
+    let previous: HashMap<String, DateTime<Utc>> = store
+        .get(writer, DB_KEY_ENROLLMENT_DATES)?
+        .unwrap_or_default();
+    let enrollments: Vec<ExperimentEnrollment> =
+        db.get_store(StoreId::Enrollments).collect_all(writer)?;
+    let dates: HashMap<String, DateTime<Utc>> = enrollments
+        .into_iter()
+        .filter(|enrollment| matches!(enrollment.status, EnrollmentStatus::Enrolled { .. }))
+        .map(|enrollment| {
+            let date = previous.get(&enrollment.slug).copied().unwrap_or(now);
+            (enrollment.slug, date)
+        })
+        .collect();
+    store.put(writer, DB_KEY_ENROLLMENT_DATES, &dates)
+}
+
+/// Record the slugs of the experiments and rollouts which have ended while this client was
+/// enrolled, and return all those recorded so far. The slugs are kept after the `WasEnrolled`
+/// enrollments are garbage collected, so recipes can go on excluding past participants.
+pub fn update_completed_experiments(db: &Database, writer: &mut Writer) -> Result<HashSet<String>> {
+    let store = db.get_store(StoreId::Meta);
+    let mut completed: HashSet<String> = store
+        .get(writer, DB_KEY_COMPLETED_EXPERIMENTS)?
+        .unwrap_or_default();
+    let enrollments: Vec<ExperimentEnrollment> =
+        db.get_store(StoreId::Enrollments).collect_all(writer)?;
+    let previous_len = completed.len();
+    completed.extend(
+        enrollments
+            .into_iter()
+            .filter(|enrollment| matches!(enrollment.status, EnrollmentStatus::WasEnrolled { .. }))
+            .map(|enrollment| enrollment.slug),
+    );
+    if completed.len() != previous_len {
+        store.put(writer, DB_KEY_COMPLETED_EXPERIMENTS, &completed)?;
+    }
+    Ok(completed)
+}
+
+/// Return the branch, features and enrollment date of every experiment and rollout this client
+/// is enrolled in, ordered by slug.
+pub fn get_active_enrollments(
+    db: &Database,
+    reader: &impl Readable,
+) -> Result<Vec<ActiveEnrollment>> {
+    let dates: HashMap<String, DateTime<Utc>> = db
+        .get_store(StoreId::Meta)
+        .get(reader, DB_KEY_ENROLLMENT_DATES)?
+        .unwrap_or_default();
+    let enrollments: Vec<ExperimentEnrollment> =
+        db.get_store(StoreId::Enrollments).collect_all(reader)?;
+    let mut result = Vec::with_capacity(enrollments.len());
+    for enrollment in enrollments {
+        if let EnrollmentStatus::Enrolled { branch, .. } = &enrollment.status {
+            match db
+                .get_store(StoreId::Experiments)
+                .get::<Experiment, _>(reader, &enrollment.slug)?
+            {
+                Some(experiment) => result.push(ActiveEnrollment {
+                    feature_ids: experiment.get_feature_ids(),
+                    enrollment_date: dates
+                        .get(&experiment.slug)
+                        .map(|date| date.timestamp_millis()),
+                    slug: experiment.slug,
+                    branch_slug: branch.to_string(),
+                    is_rollout: experiment.is_rollout,
+                    is_holdback: experiment.is_holdback_branch(branch),
+                    is_enrollment_paused: experiment.is_enrollment_paused,
+                }),
+                None => log::warn!(
+                    "Have enrollment {:?} but no matching experiment!",
+                    enrollment
+                ),
+            };
+        }
+    }
+    result.sort_by(|a, b| a.slug.cmp(&b.slug));
+    Ok(result)
+}
+
 pub fn opt_in_with_branch(
     db: &Database,
     writer: &mut Writer,
@@ -142,10 +303,57 @@ pub fn opt_out(
     Ok(events)
 }
 
-pub fn get_global_user_participation<'r>(
-    db: &Database,
-    reader: &'r impl Readable<'r>,
-) -> Result<bool> {
+/// The change events for replacing `prev_enrollments` with `next_enrollments` wholesale, as
+/// `NimbusClient::rollback_last_apply` does, rather than by evolving them: experiments which
+/// are no longer enrolled, or are enrolled in another branch, are unenrolled with the reason
+/// "rollback", and those which are enrolled again are enrolled.
+pub(crate) fn get_rollback_change_events(
+    prev_enrollments: &[ExperimentEnrollment],
+    next_enrollments: &[ExperimentEnrollment],
+) -> Vec<EnrollmentChangeEvent> {
+    fn enrolled_branches(enrollments: &[ExperimentEnrollment]) -> HashMap<&str, &str> {
+        enrollments
+            .iter()
+            .filter_map(|e| match &e.status {
+                EnrollmentStatus::Enrolled { branch, .. } => {
+                    Some((e.slug.as_str(), branch.as_str()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+    let prev_branches = enrolled_branches(prev_enrollments);
+    let next_branches = enrolled_branches(next_enrollments);
+
+    let mut events = vec![];
+    for enrollment in prev_enrollments {
+        let slug = enrollment.slug.as_str();
+        match (prev_branches.get(slug), next_branches.get(slug)) {
+            (Some(prev), next) if next != Some(prev) => events.push(EnrollmentChangeEvent::new(
+                slug,
+                prev,
+                Some("rollback"),
+                EnrollmentChangeEventType::Unenrollment,
+            )),
+            _ => {}
+        }
+    }
+    for enrollment in next_enrollments {
+        let slug = enrollment.slug.as_str();
+        match (prev_branches.get(slug), next_branches.get(slug)) {
+            (prev, Some(next)) if prev != Some(next) => events.push(EnrollmentChangeEvent::new(
+                slug,
+                next,
+                None,
+                EnrollmentChangeEventType::Enrollment,
+            )),
+            _ => {}
+        }
+    }
+    events
+}
+
+pub fn get_global_user_participation(db: &Database, reader: &impl Readable) -> Result<bool> {
     let store = db.get_store(StoreId::Meta);
     let opted_in = store.get::<bool, _>(reader, DB_KEY_GLOBAL_USER_PARTICIPATION)?;
     if let Some(opted_in) = opted_in {
@@ -164,6 +372,49 @@ pub fn set_global_user_participation(
     store.put(writer, DB_KEY_GLOBAL_USER_PARTICIPATION, &opt_in)
 }
 
+/// Return the feature configurations set for testing, keyed by feature id.
+pub fn get_feature_config_overrides(
+    db: &Database,
+    reader: &impl Readable,
+) -> Result<HashMap<String, Map<String, Value>>> {
+    let store = db.get_store(StoreId::Meta);
+    Ok(store
+        .get(reader, DB_KEY_FEATURE_CONFIG_OVERRIDES)?
+        .unwrap_or_default())
+}
+
+/// Set, or with `None` unset, the configuration of a feature for testing.
+pub fn set_feature_config_override(
+    db: &Database,
+    writer: &mut Writer,
+    feature_id: &str,
+    value: Option<Map<String, Value>>,
+) -> Result<()> {
+    let mut overrides = get_feature_config_overrides(db, writer)?;
+    match value {
+        Some(value) => overrides.insert(feature_id.to_string(), value),
+        None => overrides.remove(feature_id),
+    };
+    let store = db.get_store(StoreId::Meta);
+    store.put(writer, DB_KEY_FEATURE_CONFIG_OVERRIDES, &overrides)
+}
+
+/// Return the kill switches from the experiments collection, as of the last time the
+/// pending experiments were applied.
+pub fn get_kill_switches(db: &Database, reader: &impl Readable) -> Result<Vec<FeatureKillSwitch>> {
+    let store = db.get_store(StoreId::Meta);
+    Ok(store.get(reader, DB_KEY_KILL_SWITCHES)?.unwrap_or_default())
+}
+
+pub fn set_kill_switches(
+    db: &Database,
+    writer: &mut Writer,
+    kill_switches: &[FeatureKillSwitch],
+) -> Result<()> {
+    let store = db.get_store(StoreId::Meta);
+    store.put(writer, DB_KEY_KILL_SWITCHES, &kill_switches)
+}
+
 /// Reset unique identifiers in response to application-level telemetry reset.
 ///
 pub fn reset_telemetry_identifiers(
@@ -171,11 +422,17 @@ pub fn reset_telemetry_identifiers(
     writer: &mut Writer,
 ) -> Result<Vec<EnrollmentChangeEvent>> {
     let mut events = vec![];
+    let experiments: Vec<Experiment> = db.get_store(StoreId::Experiments).collect_all(writer)?;
+    let rollouts: HashSet<String> = experiments
+        .into_iter()
+        .filter(|e| e.is_rollout)
+        .map(|e| e.slug)
+        .collect();
     let store = db.get_store(StoreId::Enrollments);
     let enrollments: Vec<ExperimentEnrollment> = store.collect_all(writer)?;
-    let updated_enrollments = enrollments
-        .iter()
-        .map(|enrollment| enrollment.reset_telemetry_identifiers(&mut events));
+    let updated_enrollments = enrollments.iter().map(|enrollment| {
+        enrollment.reset_telemetry_identifiers(rollouts.contains(&enrollment.slug), &mut events)
+    });
     store.clear(writer)?;
     for enrollment in updated_enrollments {
         store.put(writer, &enrollment.slug, &enrollment)?;
@@ -2,7 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::{evaluator::split_locale, stateful::matcher::AppContext};
+use crate::{
+    locale::{canonicalize_locale, canonicalize_region, Locale},
+    stateful::matcher::AppContext,
+};
 use chrono::{DateTime, Utc};
 use serde_derive::*;
 use std::collections::{HashMap, HashSet};
@@ -12,13 +15,24 @@ pub struct TargetingAttributes {
     #[serde(flatten)]
     pub app_context: AppContext,
     pub language: Option<String>,
+    pub script: Option<String>,
+    /// The region set with `set_region`, or else the one from the locale.
     pub region: Option<String>,
+    /// The canonical locale, then the less specific locales it falls back to, e.g.
+    /// `["zh-Hant-TW", "zh-Hant", "zh"]`.
+    pub locale_fallbacks: Vec<String>,
+    // The region supplied by the app, which takes precedence over the locale's.
+    #[serde(skip)]
+    pub(crate) region_override: Option<String>,
     pub is_already_enrolled: bool,
     pub days_since_install: Option<i32>,
     pub days_since_update: Option<i32>,
     pub active_experiments: HashSet<String>,
     pub enrollments: HashSet<String>,
     pub enrollments_map: HashMap<String, String>,
+    /// The slugs of the experiments and rollouts which ended while the client was enrolled,
+    /// including those which ended too long ago to be in `enrollments`.
+    pub completed_experiments: HashSet<String>,
     #[serde(with = "chrono::serde::ts_seconds")]
     pub current_date: DateTime<Utc>,
     pub nimbus_id: Option<String>,
@@ -27,18 +41,12 @@ pub struct TargetingAttributes {
 #[cfg(feature = "stateful")]
 impl From<AppContext> for TargetingAttributes {
     fn from(app_context: AppContext) -> Self {
-        let (language, region) = app_context
-            .locale
-            .clone()
-            .map(split_locale)
-            .unwrap_or_else(|| (None, None));
-
-        Self {
+        let mut attributes = Self {
             app_context,
-            language,
-            region,
             ..Default::default()
-        }
+        };
+        attributes.update_locale_attributes();
+        attributes
     }
 }
 
@@ -53,4 +61,31 @@ impl TargetingAttributes {
         self.days_since_update = update_date.map(|then| (now - then).num_days() as i32);
         self.current_date = now;
     }
+
+    pub(crate) fn set_locale(&mut self, locale: String) {
+        self.app_context.locale = Some(locale);
+        self.update_locale_attributes();
+    }
+
+    /// Sets the region, e.g. the home region from geolocation, in place of the locale's.
+    /// With `None`, the locale's region is used again.
+    pub(crate) fn set_region(&mut self, region: Option<String>) {
+        self.region_override = region.as_deref().and_then(canonicalize_region);
+        self.update_locale_attributes();
+    }
+
+    // Canonicalizes the locale, and derives the language, script and region from it.
+    fn update_locale_attributes(&mut self) {
+        let locale = self
+            .app_context
+            .locale
+            .as_deref()
+            .map(Locale::parse)
+            .unwrap_or_default();
+        self.app_context.locale = self.app_context.locale.as_deref().map(canonicalize_locale);
+        self.language = locale.language.clone();
+        self.script = locale.script.clone();
+        self.region = self.region_override.clone().or(locale.region.clone());
+        self.locale_fallbacks = locale.fallbacks();
+    }
 }
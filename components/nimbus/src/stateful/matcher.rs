@@ -10,6 +10,7 @@
 //!
 use serde_derive::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 
 /// The `AppContext` object represents the parameters and characteristics of the
 /// consuming application that we are interested in for targeting purposes. The
@@ -34,6 +35,10 @@ use serde_json::{Map, Value};
 /// - `debug_tag`: Used for debug purposes as a way to match only developer builds, etc.
 /// - `installation_date`: The date the application installed the app
 /// - `home_directory`: The application's home directory
+/// - `profile_id`: Identifies the profile, for apps with more than one, so each profile has its own
+///   database
+/// - `feature_min_versions`: The earliest app version which supports each feature, keyed by feature
+///   id, from the feature manifest. This isn't available for targeting.
 /// - `custom_targeting_attributes`: Contains attributes specific to the application, derived by the application
 #[cfg(feature = "stateful")]
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -53,6 +58,9 @@ pub struct AppContext {
     pub debug_tag: Option<String>,
     pub installation_date: Option<i64>,
     pub home_directory: Option<String>,
+    pub profile_id: Option<String>,
+    #[serde(skip)]
+    pub feature_min_versions: Option<HashMap<String, String>>,
     #[serde(flatten)]
     pub custom_targeting_attributes: Option<Map<String, Value>>,
 }
@@ -5,40 +5,50 @@
 use crate::{
     defaults::Defaults,
     enrollment::{
-        EnrolledFeature, EnrollmentChangeEvent, EnrollmentChangeEventType, EnrollmentStatus,
-        EnrollmentsEvolver, ExperimentEnrollment,
+        CoenrolledFeatureConfig, EnrolledFeature, EnrollmentChangeEvent, EnrollmentChangeEventType,
+        EnrollmentStatus, EnrollmentsEvolver, ExperimentEnrollment, NotEnrolledReason,
     },
     error::BehaviorError,
     evaluator::{is_experiment_available, TargetingAttributes},
     metrics::{
-        EnrollmentStatusExtraDef, FeatureExposureExtraDef, MalformedFeatureConfigExtraDef,
-        MetricsHandler,
+        EnrollmentStatusExtraDef, FeatureExposureExtraDef, FetchErrorExtraDef,
+        MalformedFeatureConfigExtraDef, MetricsHandler,
     },
-    schema::parse_experiments,
+    schema::parse_recipes,
     stateful::{
         behavior::EventStore,
         client::{create_client, SettingsClient},
         dbcache::DatabaseCache,
         enrollment::{
-            get_global_user_participation, opt_in_with_branch, opt_out,
-            reset_telemetry_identifiers, set_global_user_participation,
+            get_active_enrollments, get_global_user_participation, get_rollback_change_events,
+            opt_in_with_branch, opt_out, reset_telemetry_identifiers, set_feature_config_override,
+            set_global_user_participation, set_kill_switches, update_completed_experiments,
+            update_enrollment_dates,
         },
         matcher::AppContext,
-        persistence::{Database, StoreId, Writer},
-        updating::{read_and_remove_pending_experiments, write_pending_experiments},
+        persistence::{Database, DatabaseState, StoreId, Writer},
+        updating::{
+            read_and_remove_pending_experiments, read_pending_kill_switches,
+            restore_apply_snapshot, write_apply_snapshot, write_pending_recipes,
+        },
     },
     strings::fmt_with_map,
-    AvailableExperiment, AvailableRandomizationUnits, EnrolledExperiment, Experiment,
-    ExperimentBranch, NimbusError, NimbusTargetingHelper, Result,
+    versioning::Version,
+    ActiveEnrollment, AvailableExperiment, AvailableRandomizationUnits, EnrolledExperiment,
+    Experiment, ExperimentBranch, NimbusError, NimbusTargetingHelper, RandomizationUnit, Result,
 };
 use chrono::{DateTime, NaiveDateTime, Utc};
 use once_cell::sync::OnceCell;
-use remote_settings::RemoteSettingsConfig;
+use remote_settings::{CacheValidators, RemoteSettingsConfig, RemoteSettingsError};
 use serde_json::{Map, Value};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{
+    mpsc::{channel, Sender},
+    Arc, Mutex, MutexGuard,
+};
+use url::Url;
 use uuid::Uuid;
 
 const DB_KEY_NIMBUS_ID: &str = "nimbus-id";
@@ -46,6 +56,24 @@ pub const DB_KEY_INSTALLATION_DATE: &str = "installation-date";
 pub const DB_KEY_UPDATE_DATE: &str = "update-date";
 pub const DB_KEY_APP_VERSION: &str = "app-version";
 pub const DB_KEY_FETCH_ENABLED: &str = "fetch-enabled";
+pub const DB_KEY_SIGNATURE_VERIFICATION_ENABLED: &str = "signature-verification-enabled";
+pub const DB_KEY_CACHE_VALIDATORS: &str = "experiments-cache-validators";
+pub const DB_KEY_CUSTOM_TARGETING_ATTRIBUTES: &str = "custom-targeting-attributes";
+pub const DB_KEY_RANDOMIZATION_UNITS: &str = "randomization-units";
+
+/// The keys of the identifiers which could link the client's telemetry to that of another
+/// client, so aren't exported by `NimbusClient::export_enrollment_state`.
+const IDENTIFIER_DB_KEYS: [&str; 2] = [DB_KEY_NIMBUS_ID, DB_KEY_RANDOMIZATION_UNITS];
+
+/// The directory, under the database path, which holds the database of each profile.
+pub const PROFILES_DIR_NAME: &str = "profiles";
+
+/// The Remote Settings collection of experiments which are staged, but not yet live.
+pub const PREVIEW_COLLECTION_NAME: &str = "nimbus-preview";
+
+/// The host of the production Remote Settings server, whose collections are signed by the
+/// production root certificate.
+const PRODUCTION_SERVER_HOST: &str = "firefox.settings.services.mozilla.com";
 
 // The main `NimbusClient` struct must not expose any methods that make an `&mut self`,
 // in order to be compatible with the uniffi's requirements on objects. This is a helper
@@ -67,11 +95,40 @@ impl InternalMutableState {
     }
 }
 
+/// The path of the database of the given profile, under the database path given by the app.
+fn profile_db_path(db_path: PathBuf, profile_id: &str) -> Result<PathBuf> {
+    let is_valid = !profile_id.is_empty()
+        && profile_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid {
+        return Err(NimbusError::InvalidPath(format!(
+            "{profile_id} is not a valid profile id"
+        )));
+    }
+    Ok(db_path.join(PROFILES_DIR_NAME).join(profile_id))
+}
+
+/// Notified on a background thread when `apply_pending_experiments_in_background` has finished.
+pub trait ApplyPendingExperimentsCallback: Send + Sync {
+    fn on_applied(&self, events: Vec<EnrollmentChangeEvent>);
+    fn on_error(&self, message: String);
+}
+
+/// Notified on a background thread when enrollments change, e.g. after experiments are applied
+/// or the user opts in or out, with the ids of the features whose configuration changed.
+pub trait EnrollmentChangeObserver: Send + Sync {
+    fn on_enrollments_changed(&self, feature_ids: Vec<String>);
+}
+
 /// Nimbus is the main struct representing the experiments state
 /// It should hold all the information needed to communicate a specific user's
 /// experimentation status
 pub struct NimbusClient {
     settings_client: Mutex<Box<dyn SettingsClient + Send>>,
+    // The config the settings client was created with, so it can be switched to the preview
+    // collection and back.
+    settings_config: Option<RemoteSettingsConfig>,
     pub(crate) mutable_state: Mutex<InternalMutableState>,
     app_context: AppContext,
     pub(crate) db: OnceCell<Database>,
@@ -82,6 +139,10 @@ pub struct NimbusClient {
     coenrolling_feature_ids: Vec<String>,
     event_store: Arc<Mutex<EventStore>>,
     metrics_handler: Arc<Box<dyn MetricsHandler>>,
+    // The feature id, experiment slug and branch of the exposures recorded by `record_exposure`.
+    recorded_exposures: Mutex<HashSet<(String, String, Option<String>)>>,
+    // Sends the changed feature ids to the thread which notifies the registered observer.
+    enrollment_change_observer: Mutex<Option<Sender<Vec<String>>>>,
 }
 
 impl NimbusClient {
@@ -94,7 +155,11 @@ impl NimbusClient {
         config: Option<RemoteSettingsConfig>,
         metrics_handler: Box<dyn MetricsHandler>,
     ) -> Result<Self> {
-        let settings_client = Mutex::new(create_client(config)?);
+        let db_path = match &app_context.profile_id {
+            Some(profile_id) => profile_db_path(db_path.into(), profile_id)?,
+            None => db_path.into(),
+        };
+        let settings_client = Mutex::new(create_client(config.clone())?);
 
         let mutable_state = Mutex::new(InternalMutableState {
             available_randomization_units: Default::default(),
@@ -105,17 +170,47 @@ impl NimbusClient {
 
         Ok(Self {
             settings_client,
+            settings_config: config,
             mutable_state,
             app_context,
-            database_cache: Default::default(),
-            db_path: db_path.into(),
+            database_cache: DatabaseCache::new(&db_path),
+            db_path,
             coenrolling_feature_ids,
             db: OnceCell::default(),
             event_store: Arc::default(),
             metrics_handler: Arc::new(metrics_handler),
+            recorded_exposures: Default::default(),
+            enrollment_change_observer: Default::default(),
         })
     }
 
+    /// A test-only constructor for a client which buckets on `bucketing_seed` in place of its
+    /// `nimbus_id` and any other randomization unit. The same seed always enrolls in the same
+    /// branches of a recipe, so integration tests can choose a branch without patching recipes.
+    pub fn new_with_bucketing_seed<P: Into<PathBuf>>(
+        app_context: AppContext,
+        coenrolling_feature_ids: Vec<String>,
+        db_path: P,
+        config: Option<RemoteSettingsConfig>,
+        metrics_handler: Box<dyn MetricsHandler>,
+        bucketing_seed: String,
+    ) -> Result<Self> {
+        let client = Self::new(
+            app_context,
+            coenrolling_feature_ids,
+            db_path,
+            config,
+            metrics_handler,
+        )?;
+        client
+            .mutable_state
+            .lock()
+            .unwrap()
+            .available_randomization_units
+            .bucketing_seed = Some(bucketing_seed);
+        Ok(client)
+    }
+
     pub fn with_targeting_attributes(&mut self, targeting_attributes: TargetingAttributes) {
         let mut state = self.mutable_state.lock().unwrap();
         state.targeting_attributes = targeting_attributes;
@@ -148,7 +243,9 @@ impl NimbusClient {
         state: &mut MutexGuard<InternalMutableState>,
     ) -> Result<()> {
         self.read_or_create_nimbus_id(db, writer, state)?;
+        self.update_randomization_units(db, writer, state)?;
         self.update_ta_install_dates(db, writer, state)?;
+        self.update_ta_custom_targeting_attributes(db, writer, state)?;
         self.event_store.lock().unwrap().read_from_db(db)?;
         Ok(())
     }
@@ -158,21 +255,63 @@ impl NimbusClient {
     fn end_initialize(
         &self,
         db: &Database,
-        writer: Writer,
+        mut writer: Writer,
         state: &mut MutexGuard<InternalMutableState>,
     ) -> Result<()> {
-        self.update_ta_active_experiments(db, &writer, state)?;
+        self.update_ta_active_experiments(db, &mut writer, state)?;
+        update_enrollment_dates(db, &mut writer, Utc::now())?;
         let coenrolling_ids = self
             .coenrolling_feature_ids
             .iter()
             .map(|s| s.as_str())
             .collect();
-        self.database_cache
-            .commit_and_update(db, writer, &coenrolling_ids)?;
+        let changed_feature_ids =
+            self.database_cache
+                .commit_and_update(db, writer, &coenrolling_ids)?;
         self.record_enrollment_status_telemetry(state)?;
+        self.notify_enrollment_change_observer(changed_feature_ids);
         Ok(())
     }
 
+    /// Registers the observer notified when enrollments change, replacing any registered
+    /// before, so UI can refresh the screens which depend on the changed features.
+    ///
+    /// The observer is notified on a thread of its own, as changes are found with the client's
+    /// state locked, and observers may well ask for the changed feature configurations. The
+    /// thread exits once the observer is unregistered or replaced.
+    pub fn register_enrollment_change_observer(&self, observer: Box<dyn EnrollmentChangeObserver>) {
+        let (sender, receiver) = channel::<Vec<String>>();
+        std::thread::spawn(move || {
+            for feature_ids in receiver {
+                observer.on_enrollments_changed(feature_ids);
+            }
+        });
+        *self.enrollment_change_observer.lock().unwrap() = Some(sender);
+    }
+
+    pub fn unregister_enrollment_change_observer(&self) {
+        *self.enrollment_change_observer.lock().unwrap() = None;
+    }
+
+    fn notify_enrollment_change_observer(&self, feature_ids: Vec<String>) {
+        if feature_ids.is_empty() {
+            return;
+        }
+        if let Some(sender) = self.enrollment_change_observer.lock().unwrap().as_ref() {
+            // The thread only stops once the sender is dropped, so this can't fail.
+            let _ = sender.send(feature_ids);
+        }
+    }
+
+    // Note: the contract for this function is that it never blocks on IO.
+    pub fn get_coenrolled_feature_configs(
+        &self,
+        feature_id: String,
+    ) -> Result<Vec<CoenrolledFeatureConfig>> {
+        self.database_cache
+            .get_coenrolled_feature_configs(&feature_id)
+    }
+
     pub fn get_enrollment_by_feature(&self, feature_id: String) -> Result<Option<EnrolledFeature>> {
         self.database_cache.get_enrollment_by_feature(&feature_id)
     }
@@ -182,7 +321,13 @@ impl NimbusClient {
         self.database_cache.get_experiment_branch(&slug)
     }
 
+    // Note: the contract for this function is that it never blocks on opening the database.
+    // Until `initialize` is called, the configurations are served from the snapshot written
+    // when they last changed, which is read the first time it is needed.
     pub fn get_feature_config_variables(&self, feature_id: String) -> Result<Option<String>> {
+        if !self.supports_feature(&feature_id) {
+            return Ok(None);
+        }
         Ok(
             if let Some(s) = self
                 .database_cache
@@ -196,6 +341,62 @@ impl NimbusClient {
         )
     }
 
+    /// Returns the variables of the feature as a single JSON object: the configuration from
+    /// the experiment or rollout the feature is enrolled in, merged over the `defaults` given
+    /// by the feature manifest. An experiment's configuration already takes precedence over a
+    /// rollout's, so callers no longer need to merge the layers themselves.
+    ///
+    /// Note: the contract for this function is that it never blocks on opening the database.
+    pub fn get_feature_variables_json(
+        &self,
+        feature_id: String,
+        defaults: Option<JsonObject>,
+    ) -> Result<JsonObject> {
+        let defaults = defaults.unwrap_or_default();
+        if !self.supports_feature(&feature_id) {
+            return Ok(defaults);
+        }
+        Ok(
+            match self.database_cache.get_feature_config_value(&feature_id)? {
+                Some(value) => {
+                    self.record_feature_activation_if_needed(&feature_id);
+                    value.defaults(&defaults)?
+                }
+                None => defaults,
+            },
+        )
+    }
+
+    /// Whether this version of the app supports the feature, according to the minimum versions
+    /// given by the feature manifest. Configurations for unsupported features are ignored, so
+    /// the app uses its defaults. If either version can't be parsed, the feature is supported.
+    fn supports_feature(&self, feature_id: &str) -> bool {
+        let min_version = self
+            .app_context
+            .feature_min_versions
+            .as_ref()
+            .and_then(|versions| versions.get(feature_id));
+        let (Some(min_version), Some(app_version)) = (min_version, &self.app_context.app_version)
+        else {
+            return true;
+        };
+        match (
+            Version::try_from(app_version.as_str()),
+            Version::try_from(min_version.as_str()),
+        ) {
+            (Ok(app_version), Ok(min_version)) => app_version >= min_version,
+            _ => true,
+        }
+    }
+
+    /// Whether the feature has been disabled by a kill switch in the experiments collection,
+    /// in which case its configuration is the one given by the kill switch.
+    ///
+    /// Note: the contract for this function is that it never blocks on IO.
+    pub fn is_feature_disabled(&self, feature_id: String) -> Result<bool> {
+        self.database_cache.is_feature_disabled(&feature_id)
+    }
+
     pub fn get_experiment_branches(&self, slug: String) -> Result<Vec<ExperimentBranch>> {
         self.get_all_experiments()?
             .into_iter()
@@ -228,10 +429,132 @@ impl NimbusClient {
         Ok(events)
     }
 
+    /// Updates the targeting attributes which can change while the app is running, then
+    /// re-evaluates enrollments against them. The locale is canonicalized, e.g. `en_us` to
+    /// `en-US`, and the `language`, `script`, `region` and `locale_fallbacks` attributes are
+    /// derived from it. Attributes passed as `None` are left unchanged.
+    ///
+    /// Experiments and rollouts whose targeting no longer matches are disqualified, as they
+    /// would be when their recipes change.
+    pub fn update_targeting_context(
+        &self,
+        locale: Option<String>,
+        app_version: Option<String>,
+    ) -> Result<Vec<EnrollmentChangeEvent>> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let mut state = self.mutable_state.lock().unwrap();
+        if let Some(locale) = locale {
+            state.targeting_attributes.set_locale(locale);
+        }
+        if app_version.is_some() {
+            state.targeting_attributes.app_context.app_version = app_version;
+        }
+
+        let existing_experiments: Vec<Experiment> =
+            db.get_store(StoreId::Experiments).collect_all(&writer)?;
+        let events = self.evolve_experiments(db, &mut writer, &mut state, &existing_experiments)?;
+        self.end_initialize(db, writer, &mut state)?;
+        Ok(events)
+    }
+
+    /// Sets the `region` targeting attribute, e.g. to the home region from geolocation, in
+    /// place of the region from the locale, then re-evaluates enrollments. With `None`, the
+    /// locale's region is used again.
+    pub fn set_region(&self, region: Option<String>) -> Result<Vec<EnrollmentChangeEvent>> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let mut state = self.mutable_state.lock().unwrap();
+        state.targeting_attributes.set_region(region);
+
+        let existing_experiments: Vec<Experiment> =
+            db.get_store(StoreId::Experiments).collect_all(&writer)?;
+        let events = self.evolve_experiments(db, &mut writer, &mut state, &existing_experiments)?;
+        self.end_initialize(db, writer, &mut state)?;
+        Ok(events)
+    }
+
     pub fn get_active_experiments(&self) -> Result<Vec<EnrolledExperiment>> {
         self.database_cache.get_active_experiments()
     }
 
+    pub fn get_active_enrollments(&self) -> Result<Vec<ActiveEnrollment>> {
+        let db = self.db()?;
+        let reader = db.read()?;
+        get_active_enrollments(db, &reader)
+    }
+
+    /// Evaluates whether this client would enroll in the given recipe, and why, as if it was
+    /// seeing it for the first time, without changing any enrollments. `additional_context` is
+    /// added to the targeting attributes, as for `create_targeting_helper`.
+    pub fn evaluate_enrollment_dry_run(
+        &self,
+        recipe_json: String,
+        additional_context: Option<JsonObject>,
+    ) -> Result<EnrollmentStatusExtraDef> {
+        let experiment: Experiment = serde_json::from_str(&recipe_json)?;
+        let is_user_participating = self.get_global_user_participation()?;
+        let targeting_helper = self.create_targeting_helper(additional_context)?;
+        let state = self.mutable_state.lock().unwrap();
+        let enrollment = ExperimentEnrollment::from_new_experiment(
+            is_user_participating,
+            &state.available_randomization_units,
+            &experiment,
+            &targeting_helper,
+            &mut vec![],
+        )?;
+        Ok(EnrollmentStatusExtraDef::from(enrollment).with_holdback(&experiment))
+    }
+
+    /// Returns whether, and why, this client is enrolled in each of the experiments and
+    /// rollouts from the last fetch, e.g. `NotTargeted`, `NotSelected`, `OptOut`,
+    /// `EnrollmentsPaused` or `FeatureConflict`, for debugging. For feature conflicts,
+    /// `conflict_slug` is the recipe already using the feature.
+    ///
+    /// Note: the contract for this function is that it never blocks on IO.
+    pub fn get_enrollment_statuses(&self) -> Result<Vec<EnrollmentStatusExtraDef>> {
+        let experiments: HashMap<String, Experiment> = self
+            .database_cache
+            .get_experiments()?
+            .into_iter()
+            .map(|e| (e.slug.clone(), e))
+            .collect();
+        let active_experiments = self.database_cache.get_active_experiments()?;
+        let mut statuses: Vec<EnrollmentStatusExtraDef> = self
+            .database_cache
+            .get_enrollments()?
+            .into_iter()
+            .filter_map(|enrollment| {
+                let experiment = experiments.get(&enrollment.slug)?;
+                let conflict_slug = match enrollment.status {
+                    EnrollmentStatus::NotEnrolled {
+                        reason: NotEnrolledReason::FeatureConflict,
+                    } => {
+                        let feature_ids = experiment.get_feature_ids();
+                        active_experiments
+                            .iter()
+                            .find(|active| {
+                                experiments.get(&active.slug).map(|e| e.is_rollout)
+                                    == Some(experiment.is_rollout)
+                                    && active.feature_ids.iter().any(|f| feature_ids.contains(f))
+                            })
+                            .map(|active| active.slug.clone())
+                    }
+                    _ => None,
+                };
+                Some(
+                    EnrollmentStatusExtraDef {
+                        conflict_slug,
+                        ..enrollment.into()
+                    }
+                    .with_holdback(experiment),
+                )
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.slug.cmp(&b.slug));
+        Ok(statuses)
+    }
+
     pub fn get_all_experiments(&self) -> Result<Vec<Experiment>> {
         let db = self.db()?;
         let reader = db.read()?;
@@ -271,20 +594,100 @@ impl NimbusClient {
         Ok(result)
     }
 
+    /// Sets the configuration of a feature, replacing any from experiments or rollouts, so test
+    /// automation can exercise a configuration without a recipe.
+    ///
+    /// This should only be used in testing.
+    pub fn set_feature_config_for_testing(
+        &self,
+        feature_id: String,
+        config: JsonObject,
+    ) -> Result<()> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        set_feature_config_override(db, &mut writer, &feature_id, Some(config))?;
+        let mut state = self.mutable_state.lock().unwrap();
+        self.end_initialize(db, writer, &mut state)?;
+        Ok(())
+    }
+
+    /// Removes the configuration set with `set_feature_config_for_testing`.
+    pub fn unset_feature_config_for_testing(&self, feature_id: String) -> Result<()> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        set_feature_config_override(db, &mut writer, &feature_id, None)?;
+        let mut state = self.mutable_state.lock().unwrap();
+        self.end_initialize(db, writer, &mut state)?;
+        Ok(())
+    }
+
     pub fn fetch_experiments(&self) -> Result<()> {
         if !self.is_fetch_enabled()? {
             return Ok(());
         }
         log::info!("fetching experiments");
-        let settings_client = self.settings_client.lock().unwrap();
-        let new_experiments = settings_client.fetch_experiments()?;
+        let verify_signatures = self.should_verify_signatures()?;
         let db = self.db()?;
+        let validators: CacheValidators = db
+            .get(StoreId::Meta, DB_KEY_CACHE_VALIDATORS)?
+            .unwrap_or_default();
+        let settings_client = self.settings_client.lock().unwrap();
+        let fetched =
+            match settings_client.fetch_experiments_if_modified(verify_signatures, &validators) {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    self.record_fetch_error(&e);
+                    return Err(e);
+                }
+            };
+        // The experiments haven't changed since they were last fetched, so there is nothing
+        // to write.
+        let Some((recipes, validators)) = fetched else {
+            log::info!("experiments not modified");
+            return Ok(());
+        };
         let mut writer = db.write()?;
-        write_pending_experiments(db, &mut writer, new_experiments)?;
+        write_pending_recipes(db, &mut writer, recipes)?;
+        db.get_store(StoreId::Meta)
+            .put(&mut writer, DB_KEY_CACHE_VALIDATORS, &validators)?;
         writer.commit()?;
         Ok(())
     }
 
+    /// Forgets the validators of the last fetch, so the next fetch gets every experiment,
+    /// e.g. when they come from a different collection.
+    fn clear_cache_validators(&self, db: &Database, writer: &mut Writer) -> Result<()> {
+        db.get_store(StoreId::Meta).put(
+            writer,
+            DB_KEY_CACHE_VALIDATORS,
+            &CacheValidators::default(),
+        )
+    }
+
+    /// Switches between the preview collection of experiments and the collection the client was
+    /// created with, then fetches and applies the experiments from it.
+    ///
+    /// This allows QA to test staged experiments without rebuilding the app.
+    pub fn set_preview_collection(&self, use_preview: bool) -> Result<Vec<EnrollmentChangeEvent>> {
+        let config = self.settings_config.clone().map(|config| {
+            if use_preview {
+                RemoteSettingsConfig {
+                    collection_name: PREVIEW_COLLECTION_NAME.to_string(),
+                    ..config
+                }
+            } else {
+                config
+            }
+        });
+        *self.settings_client.lock().unwrap() = create_client(config)?;
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        self.clear_cache_validators(db, &mut writer)?;
+        writer.commit()?;
+        self.fetch_experiments()?;
+        self.apply_pending_experiments()
+    }
+
     pub fn set_fetch_enabled(&self, allow: bool) -> Result<()> {
         let db = self.db()?;
         let mut writer = db.write()?;
@@ -304,6 +707,52 @@ impl NimbusClient {
         Ok(enabled)
     }
 
+    /// Turns the verification of the content signature of fetched experiments on or off.
+    ///
+    /// Verification is on by default. This is a kill switch, in case a problem with the
+    /// signatures would otherwise stop every client from fetching experiments.
+    pub fn set_signature_verification_enabled(&self, enabled: bool) -> Result<()> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        db.get_store(StoreId::Meta).put(
+            &mut writer,
+            DB_KEY_SIGNATURE_VERIFICATION_ENABLED,
+            &enabled,
+        )?;
+        writer.commit()?;
+        Ok(())
+    }
+
+    pub fn is_signature_verification_enabled(&self) -> Result<bool> {
+        let db = self.db()?;
+        let reader = db.read()?;
+        let enabled = db
+            .get_store(StoreId::Meta)
+            .get(&reader, DB_KEY_SIGNATURE_VERIFICATION_ENABLED)?
+            .unwrap_or(true);
+        Ok(enabled)
+    }
+
+    /// Whether the content signature of fetched experiments should be verified. Only the
+    /// collections on the production server are signed by the production root certificate,
+    /// so experiments from other servers, e.g. stage or a local server, aren't verified.
+    /// Nothing is verified unless the crate is built with the `signatures` feature.
+    pub(crate) fn should_verify_signatures(&self) -> Result<bool> {
+        if !cfg!(feature = "signatures") {
+            return Ok(false);
+        }
+        let is_production = match &self.settings_config {
+            Some(RemoteSettingsConfig {
+                server_url: Some(server_url),
+                ..
+            }) => Url::parse(server_url)
+                .map(|url| url.host_str() == Some(PRODUCTION_SERVER_HOST))
+                .unwrap_or(false),
+            _ => true,
+        };
+        Ok(is_production && self.is_signature_verification_enabled()?)
+    }
+
     /**
      * Calculate the days since install and days since update on the targeting_attributes.
      */
@@ -330,13 +779,41 @@ impl NimbusClient {
         Ok(())
     }
 
+    /**
+     * Adds the custom targeting attributes set by `set_custom_targeting_attributes` to those
+     * from the app context.
+     */
+    fn update_ta_custom_targeting_attributes(
+        &self,
+        db: &Database,
+        writer: &Writer,
+        state: &mut MutexGuard<InternalMutableState>,
+    ) -> Result<()> {
+        let persisted: Option<JsonObject> = db
+            .get_store(StoreId::Meta)
+            .get(writer, DB_KEY_CUSTOM_TARGETING_ATTRIBUTES)?;
+        if let Some(persisted) = persisted {
+            let mut attributes = self
+                .app_context
+                .custom_targeting_attributes
+                .clone()
+                .unwrap_or_default();
+            attributes.extend(persisted);
+            state
+                .targeting_attributes
+                .app_context
+                .custom_targeting_attributes = Some(attributes);
+        }
+        Ok(())
+    }
+
     /**
      * Calculates the active_experiments based on current enrollments for the targeting attributes.
      */
     fn update_ta_active_experiments(
         &self,
         db: &Database,
-        writer: &Writer,
+        writer: &mut Writer,
         state: &mut MutexGuard<InternalMutableState>,
     ) -> Result<()> {
         let enrollments_store = db.get_store(StoreId::Enrollments);
@@ -364,6 +841,8 @@ impl NimbusClient {
         state.targeting_attributes.active_experiments = is_enrolled_set;
         state.targeting_attributes.enrollments = all_enrolled_set;
         state.targeting_attributes.enrollments_map = enrollments_map;
+        state.targeting_attributes.completed_experiments =
+            update_completed_experiments(db, writer)?;
 
         Ok(())
     }
@@ -382,11 +861,20 @@ impl NimbusClient {
             .iter()
             .map(|s| s.as_str())
             .collect();
+        let unsupported_feature_ids = self
+            .app_context
+            .feature_min_versions
+            .iter()
+            .flat_map(|versions| versions.keys())
+            .filter(|feature_id| !self.supports_feature(feature_id))
+            .cloned()
+            .collect();
         let evolver = EnrollmentsEvolver::new(
             &state.available_randomization_units,
             &targeting_helper,
             &coenrolling_feature_ids,
-        );
+        )
+        .with_unsupported_feature_ids(unsupported_feature_ids);
         evolver.evolve_enrollments_in_db(db, writer, experiments)
     }
 
@@ -397,12 +885,15 @@ impl NimbusClient {
 
         // We'll get the pending experiments which were stored for us, either by fetch_experiments
         // or by set_experiments_locally.
+        let kill_switches = read_pending_kill_switches(db, &writer)?;
         let pending_updates = read_and_remove_pending_experiments(db, &mut writer)?;
         let mut state = self.mutable_state.lock().unwrap();
         self.begin_initialize(db, &mut writer, &mut state)?;
 
         let res = match pending_updates {
             Some(new_experiments) => {
+                write_apply_snapshot(db, &mut writer)?;
+                set_kill_switches(db, &mut writer, &kill_switches)?;
                 self.update_ta_active_experiments(db, &writer, &mut state)?;
                 // Perform the enrollment calculations if there are pending experiments.
                 self.evolve_experiments(db, &mut writer, &mut state, &new_experiments)?
@@ -415,6 +906,47 @@ impl NimbusClient {
         Ok(res)
     }
 
+    /// Rolls back the last `apply_pending_experiments` which had pending experiments, restoring
+    /// the experiments and enrollments from before it, e.g. when a bad batch of recipes breaks
+    /// the app. Only the last apply can be rolled back.
+    ///
+    /// Returns the enrollment changes, as `apply_pending_experiments` does: experiments the apply
+    /// enrolled in are unenrolled, with the reason "rollback", and those it unenrolled from are
+    /// enrolled again. Nothing is returned if there is nothing to roll back.
+    ///
+    /// The cache validators are not rolled back, so the batch is not fetched again until the
+    /// collection next changes.
+    pub fn rollback_last_apply(&self) -> Result<Vec<EnrollmentChangeEvent>> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let enrollments_store = db.get_store(StoreId::Enrollments);
+        let prev_enrollments: Vec<ExperimentEnrollment> = enrollments_store.collect_all(&writer)?;
+        if !restore_apply_snapshot(db, &mut writer)? {
+            return Ok(vec![]);
+        }
+        let mut state = self.mutable_state.lock().unwrap();
+        self.begin_initialize(db, &mut writer, &mut state)?;
+        self.update_ta_active_experiments(db, &mut writer, &mut state)?;
+        let next_enrollments: Vec<ExperimentEnrollment> = enrollments_store.collect_all(&writer)?;
+        let res = get_rollback_change_events(&prev_enrollments, &next_enrollments);
+
+        self.end_initialize(db, writer, &mut state)?;
+        Ok(res)
+    }
+
+    pub fn apply_pending_experiments_in_background(
+        self: Arc<Self>,
+        callback: Box<dyn ApplyPendingExperimentsCallback>,
+    ) {
+        std::thread::spawn(move || match self.apply_pending_experiments() {
+            Ok(events) => callback.on_applied(events),
+            Err(e) => {
+                log::warn!("Failed to apply pending experiments: {}", e);
+                callback.on_error(e.to_string());
+            }
+        });
+    }
+
     fn get_installation_date(&self, db: &Database, writer: &mut Writer) -> Result<DateTime<Utc>> {
         // we first check our context
         if let Some(context_installation_date) = self.app_context.installation_date {
@@ -443,7 +975,11 @@ impl NimbusClient {
                 store.put(writer, DB_KEY_INSTALLATION_DATE, &installation_date)?;
                 installation_date
             } else {
-                Utc::now()
+                // Without any other source, the first time Nimbus runs is the best guess for
+                // when the app was installed.
+                let now = Utc::now();
+                store.put(writer, DB_KEY_INSTALLATION_DATE, &now)?;
+                now
             },
         )
     }
@@ -477,8 +1013,13 @@ impl NimbusClient {
                 }
                 // The current version is not available, or the persisted date is not available.
                 (_, _, Some(date)) => date,
-                // Either way, this doesn't appear to be a good production environment.
-                _ => Utc::now(),
+                // Without the app's version, updates can't be detected, so the first time
+                // Nimbus runs is used, as for the installation date.
+                _ => {
+                    let now = Utc::now();
+                    store.put(writer, DB_KEY_UPDATE_DATE, &now)?;
+                    now
+                }
             },
         )
     }
@@ -509,14 +1050,43 @@ impl NimbusClient {
     }
 
     pub fn set_experiments_locally(&self, experiments_json: String) -> Result<()> {
-        let new_experiments = parse_experiments(&experiments_json)?;
+        let recipes = parse_recipes(&experiments_json)?;
         let db = self.db()?;
         let mut writer = db.write()?;
-        write_pending_experiments(db, &mut writer, new_experiments)?;
+        write_pending_recipes(db, &mut writer, recipes)?;
+        // The next fetch should replace these experiments, even if the server's haven't changed.
+        self.clear_cache_validators(db, &mut writer)?;
         writer.commit()?;
         Ok(())
     }
 
+    /// Applies the experiments in the file at `path`, e.g. the `initial_experiments.json`
+    /// bundled with the app, on the first run, before any experiments have been fetched or set.
+    /// This lets first-run experiments enroll without waiting for the network. On later runs,
+    /// the file is ignored and no events are returned.
+    pub fn apply_initial_experiments(&self, path: String) -> Result<Vec<EnrollmentChangeEvent>> {
+        if !self.is_before_first_fetch()? {
+            log::info!("Experiments have already been fetched, ignoring {}", path);
+            return Ok(vec![]);
+        }
+        let experiments_json = std::fs::read_to_string(&path)?;
+        self.set_experiments_locally(experiments_json)?;
+        self.apply_pending_experiments()
+    }
+
+    /// Whether experiments have never been fetched or set locally, both of which record the
+    /// cache validators. Databases from before they were recorded have experiments instead.
+    fn is_before_first_fetch(&self) -> Result<bool> {
+        let db = self.db()?;
+        let reader = db.read()?;
+        let validators: Option<CacheValidators> = db
+            .get_store(StoreId::Meta)
+            .get(&reader, DB_KEY_CACHE_VALIDATORS)?;
+        let experiments: Vec<Experiment> =
+            db.get_store(StoreId::Experiments).collect_all(&reader)?;
+        Ok(validators.is_none() && experiments.is_empty())
+    }
+
     /// Reset all enrollments and experiments in the database.
     ///
     /// This should only be used in testing.
@@ -529,6 +1099,42 @@ impl NimbusClient {
         Ok(())
     }
 
+    /// Exports the experiments, enrollments and the rest of the database as JSON, e.g. to attach
+    /// to a bug report, or to restore on another device with `import_enrollment_state`. The
+    /// `nimbus_id` and randomization units are left out.
+    pub fn export_enrollment_state(&self) -> Result<String> {
+        let db = self.db()?;
+        let reader = db.read()?;
+        let mut exported = db.export_state(&reader)?;
+        for key in IDENTIFIER_DB_KEYS {
+            exported.meta.remove(key);
+        }
+        Ok(serde_json::to_string(&exported)?)
+    }
+
+    /// Replaces the experiments, enrollments and the rest of the database with the JSON from
+    /// `export_enrollment_state`, keeping this client's `nimbus_id` and randomization units.
+    /// Enrollments are not re-evaluated until experiments are next applied.
+    pub fn import_enrollment_state(&self, state_json: String) -> Result<()> {
+        let mut imported: DatabaseState = serde_json::from_str(&state_json)?;
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let mut state = self.mutable_state.lock().unwrap();
+        let meta = db.get_store(StoreId::Meta);
+        for key in IDENTIFIER_DB_KEYS {
+            match meta.get::<Value, _>(&writer, key)? {
+                Some(value) => imported.meta.insert(key.to_string(), value),
+                None => imported.meta.remove(key),
+            };
+        }
+        db.import_state(&mut writer, &imported)?;
+        self.begin_initialize(db, &mut writer, &mut state)?;
+        self.end_initialize(db, writer, &mut state)?;
+        // The event store reads what has been committed, so only sees the imported counts now.
+        self.event_store.lock().unwrap().read_from_db(db)?;
+        Ok(())
+    }
+
     /// Reset internal state in response to application-level telemetry reset.
     ///
     /// When the user resets their telemetry state in the consuming application, we need learn
@@ -554,12 +1160,20 @@ impl NimbusClient {
 
             // The `nimbus_id` itself is a unique identifier.
             // N.B. we do this last, as a signal that all data has been reset.
+            store.put(
+                &mut writer,
+                DB_KEY_RANDOMIZATION_UNITS,
+                &HashMap::<RandomizationUnit, String>::new(),
+            )?;
             store.delete(&mut writer, DB_KEY_NIMBUS_ID)?;
             self.end_initialize(db, writer, &mut state)?;
         }
 
         // (No need to commit `writer` if the above check was false, since we didn't change anything)
-        state.available_randomization_units = Default::default();
+        state.available_randomization_units = AvailableRandomizationUnits {
+            bucketing_seed: state.available_randomization_units.bucketing_seed.take(),
+            ..Default::default()
+        };
         state.targeting_attributes.nimbus_id = None;
 
         Ok(events)
@@ -631,6 +1245,79 @@ impl NimbusClient {
         Ok(context)
     }
 
+    /// Adds attributes to the targeting context, alongside the `custom_targeting_attributes`
+    /// from the app context, e.g. whether this is the default browser.
+    ///
+    /// The attributes are persisted, so are available on later runs before the app sets them
+    /// again. Attributes set to `null` are removed. They are used from the next time enrollments
+    /// are evaluated.
+    pub fn set_custom_targeting_attributes(&self, attributes: JsonObject) -> Result<()> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let store = db.get_store(StoreId::Meta);
+        let mut persisted: JsonObject = store
+            .get(&writer, DB_KEY_CUSTOM_TARGETING_ATTRIBUTES)?
+            .unwrap_or_default();
+        for (key, value) in attributes {
+            if value.is_null() {
+                persisted.remove(&key);
+            } else {
+                persisted.insert(key, value);
+            }
+        }
+        store.put(&mut writer, DB_KEY_CUSTOM_TARGETING_ATTRIBUTES, &persisted)?;
+
+        let mut state = self.mutable_state.lock().unwrap();
+        self.update_ta_custom_targeting_attributes(db, &writer, &mut state)?;
+        writer.commit()?;
+        Ok(())
+    }
+
+    /// Sets the identifiers of the randomization units supplied by the app, replacing any set
+    /// before, so that experiments randomized by account or by group, e.g. a household, bucket
+    /// the same way on every client with the same identifier.
+    ///
+    /// The identifiers are persisted, and removed by `reset_telemetry_identifiers`. They are used
+    /// from the next time enrollments are evaluated.
+    pub fn set_randomization_units(
+        &self,
+        user_id: Option<String>,
+        group_id: Option<String>,
+    ) -> Result<()> {
+        let db = self.db()?;
+        let mut writer = db.write()?;
+        let units: HashMap<RandomizationUnit, String> = [
+            (RandomizationUnit::UserId, user_id),
+            (RandomizationUnit::GroupId, group_id),
+        ]
+        .into_iter()
+        .filter_map(|(unit, id)| Some((unit, id?)))
+        .collect();
+        db.get_store(StoreId::Meta)
+            .put(&mut writer, DB_KEY_RANDOMIZATION_UNITS, &units)?;
+
+        let mut state = self.mutable_state.lock().unwrap();
+        self.update_randomization_units(db, &writer, &mut state)?;
+        writer.commit()?;
+        Ok(())
+    }
+
+    fn update_randomization_units(
+        &self,
+        db: &Database,
+        writer: &Writer,
+        state: &mut MutexGuard<InternalMutableState>,
+    ) -> Result<()> {
+        let units: HashMap<RandomizationUnit, String> = db
+            .get_store(StoreId::Meta)
+            .get(writer, DB_KEY_RANDOMIZATION_UNITS)?
+            .unwrap_or_default();
+        let available = &mut state.available_randomization_units;
+        available.user_id = units.get(&RandomizationUnit::UserId).cloned();
+        available.group_id = units.get(&RandomizationUnit::GroupId).cloned();
+        Ok(())
+    }
+
     pub fn create_targeting_helper(
         &self,
         additional_context: Option<JsonObject>,
@@ -649,6 +1336,22 @@ impl NimbusClient {
         Ok(Arc::new(helper))
     }
 
+    /// Creates a helper for messaging, which evaluates trigger expressions and formats
+    /// message strings against the same context, built from the targeting attributes and
+    /// `additional_context`.
+    pub fn create_messaging_helper(
+        &self,
+        additional_context: Option<JsonObject>,
+    ) -> Result<Arc<MessagingHelper>> {
+        let context = self.merge_additional_context(additional_context)?;
+        let string_helper = NimbusStringHelper::new(context.as_object().unwrap().to_owned());
+        let targeting_helper = NimbusTargetingHelper::new(context, self.event_store.clone());
+        Ok(Arc::new(MessagingHelper::new(
+            targeting_helper,
+            string_helper,
+        )))
+    }
+
     /// Records an event for the purposes of behavioral targeting.
     ///
     /// This function is used to record and persist data used for the behavioral
@@ -749,7 +1452,33 @@ impl NimbusClient {
     }
 
     pub fn record_feature_exposure(&self, feature_id: String, slug: Option<String>) {
-        let event = if let Some(slug) = slug {
+        if let Some(event) = self.feature_exposure_event(feature_id, slug) {
+            self.metrics_handler.record_feature_exposure(event);
+        }
+    }
+
+    /// Records the exposure event for the experiment this feature is involved in, at most once
+    /// per enrollment for the lifetime of this client, so apps can call it whenever the
+    /// feature is shown.
+    pub fn record_exposure(&self, feature_id: String) {
+        if let Some(event) = self.feature_exposure_event(feature_id, None) {
+            let key = (
+                event.feature_id.clone(),
+                event.slug.clone(),
+                event.branch.clone(),
+            );
+            if self.recorded_exposures.lock().unwrap().insert(key) {
+                self.metrics_handler.record_feature_exposure(event);
+            }
+        }
+    }
+
+    fn feature_exposure_event(
+        &self,
+        feature_id: String,
+        slug: Option<String>,
+    ) -> Option<FeatureExposureExtraDef> {
+        if let Some(slug) = slug {
             if let Ok(Some(branch)) = self.database_cache.get_experiment_branch(&slug) {
                 Some(FeatureExposureExtraDef {
                     feature_id,
@@ -767,11 +1496,34 @@ impl NimbusClient {
             }
         } else {
             None
-        };
+        }
+    }
 
-        if let Some(event) = event {
-            self.metrics_handler.record_feature_exposure(event);
+    /// Records the fetch error event. Network errors and backoff are expected while the device
+    /// is offline or the server is busy, so they are only logged. Anything else, e.g. a
+    /// malformed response, is also reported.
+    fn record_fetch_error(&self, e: &NimbusError) {
+        let (reason, is_expected) = match e {
+            NimbusError::ClientError(RemoteSettingsError::RequestError(_)) => ("network", true),
+            NimbusError::ClientError(RemoteSettingsError::BackoffError(_)) => ("backoff", true),
+            NimbusError::ClientError(RemoteSettingsError::ResponseError(_)) => ("server", false),
+            NimbusError::ClientError(RemoteSettingsError::SignatureError(_)) => {
+                ("signature", false)
+            }
+            _ => ("other", false),
+        };
+        if is_expected {
+            log::warn!("Failed to fetch experiments: {}", e);
+        } else {
+            error_support::report_error!(
+                "nimbus-fetch-experiments",
+                "Failed to fetch experiments: {}",
+                e
+            );
         }
+        self.metrics_handler.record_fetch_error(FetchErrorExtraDef {
+            reason: reason.to_string(),
+        });
     }
 
     pub fn record_malformed_feature_config(&self, feature_id: String, part_id: String) {
@@ -795,21 +1547,21 @@ impl NimbusClient {
         let experiments = self
             .database_cache
             .get_experiments()?
-            .iter()
+            .into_iter()
             .filter_map(
-                |exp| match is_experiment_available(&targeting_helper, exp, true) {
-                    true => Some(exp.slug.clone()),
+                |exp| match is_experiment_available(&targeting_helper, &exp, true) {
+                    true => Some((exp.slug.clone(), exp)),
                     false => None,
                 },
             )
-            .collect::<HashSet<String>>();
+            .collect::<HashMap<String, Experiment>>();
         self.metrics_handler.record_enrollment_statuses(
             self.database_cache
                 .get_enrollments()?
                 .into_iter()
-                .filter_map(|e| match experiments.contains(&e.slug) {
-                    true => Some(e.into()),
-                    false => None,
+                .filter_map(|e| {
+                    let experiment = experiments.get(&e.slug)?;
+                    Some(EnrollmentStatusExtraDef::from(e).with_holdback(experiment))
                 })
                 .collect(),
         );
@@ -847,6 +1599,50 @@ impl NimbusStringHelper {
     }
 }
 
+/// Evaluates message triggers and formats message strings, so the messaging frameworks
+/// on each platform share one implementation.
+///
+/// The results of trigger expressions are cached, since the same triggers are evaluated
+/// for many messages. The context doesn't change once the helper is created, so the
+/// helper shouldn't be kept for long; `clear_cache` forgets the results, e.g. after
+/// events are recorded.
+pub struct MessagingHelper {
+    targeting_helper: NimbusTargetingHelper,
+    string_helper: NimbusStringHelper,
+    cache: Mutex<HashMap<String, bool>>,
+}
+
+impl MessagingHelper {
+    fn new(targeting_helper: NimbusTargetingHelper, string_helper: NimbusStringHelper) -> Self {
+        Self {
+            targeting_helper,
+            string_helper,
+            cache: Default::default(),
+        }
+    }
+
+    pub fn eval_jexl(&self, expression: String) -> Result<bool> {
+        if let Some(result) = self.cache.lock().unwrap().get(&expression) {
+            return Ok(*result);
+        }
+        let result = self.targeting_helper.eval_jexl(expression.clone())?;
+        self.cache.lock().unwrap().insert(expression, result);
+        Ok(result)
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    pub fn get_uuid(&self, template: String) -> Option<String> {
+        self.string_helper.get_uuid(template)
+    }
+
+    pub fn string_format(&self, template: String, uuid: Option<String>) -> String {
+        self.string_helper.string_format(template, uuid)
+    }
+}
+
 type JsonObject = Map<String, Value>;
 
 #[cfg(feature = "stateful-uniffi-bindings")]
@@ -2,54 +2,63 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Our storage abstraction, currently backed by Rkv.
+//! Our storage abstraction, backed by Rkv or, with the `sqlite-storage` feature, SQLite.
 
 use crate::error::{NimbusError, Result};
-// This uses the lmdb backend for rkv, which is unstable.
-// We use it for now since glean didn't seem to have trouble with it (although
-// it must be noted that the rkv documentation explicitly says "To use rkv in
-// production/release environments at Mozilla, you may do so with the "SafeMode"
-// backend", so we really should get more guidance here.)
+// The rkv documentation says "To use rkv in production/release environments at
+// Mozilla, you may do so with the "SafeMode" backend", so the `stateful` feature
+// enables `rkv-safe-mode`. The lmdb backend is still available without it.
 use crate::enrollment::ExperimentEnrollment;
 use crate::Experiment;
 use core::iter::Iterator;
 use rkv::{StoreError, StoreOptions};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
 // We use an incrementing integer to manage database migrations.
 // If you need to make a backwards-incompatible change to the data schema,
-// increment `DB_VERSION` and implement some migration logic in `maybe_upgrade`.
+// write a `Database::migrate_vN_to_vN+1` method, add it to the end of `MIGRATIONS`
+// and increment `DB_VERSION`. `maybe_upgrade` runs every migration a database needs,
+// in order, and tests in `test_persistence.rs` should cover upgrading from each
+// older version.
 //
 // ⚠️ Warning : Altering the type of `DB_VERSION` would itself require a DB migration. ⚠️
 pub(crate) const DB_KEY_DB_VERSION: &str = "db_version";
 pub(crate) const DB_VERSION: u16 = 2;
+
+/// A migration of the database from one version to the next, within the upgrade
+/// transaction.
+type Migration = fn(&Database, &mut Writer) -> Result<()>;
+
+/// The migrations, in order: `MIGRATIONS[n]` upgrades a database from version `n + 1`
+/// to version `n + 2`.
+const MIGRATIONS: &[Migration] = &[Database::migrate_v1_to_v2];
+
+// Every version before `DB_VERSION` needs a migration.
+const _: () = assert!(MIGRATIONS.len() == DB_VERSION as usize - 1);
 const RKV_MAX_DBS: u32 = 6;
+/// The directory of the Rkv database.
+pub(crate) const RKV_DB_DIR: &str = "db";
+/// The directory, next to the database, where a corrupt database is moved to before it is
+/// recreated.
+pub(crate) const CORRUPT_DB_BACKUP_DIR: &str = "db.corrupt";
 
 // Inspired by Glean - use a feature to choose between the backends.
 // Select the LMDB-powered storage backend when the feature is not activated.
 #[cfg(not(feature = "rkv-safe-mode"))]
 mod backend {
-    use rkv::backend::{
-        Lmdb, LmdbDatabase, LmdbEnvironment, LmdbRoCursor, LmdbRoTransaction, LmdbRwTransaction,
-    };
+    use rkv::backend::{Lmdb, LmdbDatabase, LmdbEnvironment, LmdbRoTransaction, LmdbRwTransaction};
     use std::path::Path;
 
     use super::RKV_MAX_DBS;
 
     pub type Rkv = rkv::Rkv<LmdbEnvironment>;
     pub type RkvSingleStore = rkv::SingleStore<LmdbDatabase>;
-    pub type Reader<'t> = rkv::Reader<LmdbRoTransaction<'t>>;
-    pub type Writer<'t> = rkv::Writer<LmdbRwTransaction<'t>>;
-    pub trait Readable<'r>:
-        rkv::Readable<'r, Database = LmdbDatabase, RoCursor = LmdbRoCursor<'r>>
-    {
-    }
-    impl<'r, T: rkv::Readable<'r, Database = LmdbDatabase, RoCursor = LmdbRoCursor<'r>>>
-        Readable<'r> for T
-    {
-    }
+    pub type RkvReader<'t> = rkv::Reader<LmdbRoTransaction<'t>>;
+    pub type RkvWriter<'t> = rkv::Writer<LmdbRwTransaction<'t>>;
 
     pub fn rkv_new(path: &Path) -> Result<Rkv, rkv::StoreError> {
         Rkv::with_capacity::<Lmdb>(path, RKV_MAX_DBS)
@@ -60,7 +69,7 @@ mod backend {
 #[cfg(feature = "rkv-safe-mode")]
 mod backend {
     use rkv::backend::{
-        SafeMode, SafeModeDatabase, SafeModeEnvironment, SafeModeRoCursor, SafeModeRoTransaction,
+        SafeMode, SafeModeDatabase, SafeModeEnvironment, SafeModeRoTransaction,
         SafeModeRwTransaction,
     };
     use std::path::Path;
@@ -69,31 +78,26 @@ mod backend {
 
     pub type Rkv = rkv::Rkv<SafeModeEnvironment>;
     pub type RkvSingleStore = rkv::SingleStore<SafeModeDatabase>;
-    pub type Reader<'t> = rkv::Reader<SafeModeRoTransaction<'t>>;
-    pub type Writer<'t> = rkv::Writer<SafeModeRwTransaction<'t>>;
-    pub trait Readable<'r>:
-        rkv::Readable<'r, Database = SafeModeDatabase, RoCursor = SafeModeRoCursor<'r>>
-    {
-    }
-    impl<
-            'r,
-            T: rkv::Readable<'r, Database = SafeModeDatabase, RoCursor = SafeModeRoCursor<'r>>,
-        > Readable<'r> for T
-    {
-    }
+    pub type RkvReader<'t> = rkv::Reader<SafeModeRoTransaction<'t>>;
+    pub type RkvWriter<'t> = rkv::Writer<SafeModeRwTransaction<'t>>;
 
     pub fn rkv_new(path: &Path) -> Result<Rkv, rkv::StoreError> {
         Rkv::with_capacity::<SafeMode>(path, RKV_MAX_DBS)
     }
 }
 
+#[cfg(feature = "sqlite-storage")]
+mod sqlite;
+
 use backend::*;
-pub use backend::{Readable, Writer};
+#[cfg(feature = "sqlite-storage")]
+pub use sqlite::SqliteStorage;
 
 /// Enumeration of the different stores within our database.
 ///
-/// Our rkv database contains a number of different "stores", and the items
+/// Our database contains a number of different "stores", and the items
 /// in each store correspond to a particular type of object at the Rust level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StoreId {
     /// Store containing the set of known experiments, as read from the server.
     ///
@@ -124,12 +128,29 @@ pub enum StoreId {
     ///   * "update-date": a UTC DateTime string, defining the date the consuming app was
     ///                     last updated
     ///   * "app-version": String, the version of the app last persisted
+    ///   * "custom-targeting-attributes": a JSON object, the targeting attributes set by
+    ///                     `set_custom_targeting_attributes`
+    ///   * "feature-config-overrides": a map of feature ids to the feature configurations set
+    ///                     by `set_feature_config_for_testing`
+    ///   * "randomization-units": a map of randomization units to the identifiers supplied by
+    ///                     the app with `set_randomization_units`
+    ///   * "signature-verification-enabled": bool, whether the content signature of fetched
+    ///                     experiments is verified; see `set_signature_verification_enabled`
+    ///   * "experiments-cache-validators": the `ETag` and `Last-Modified` headers of the last
+    ///                     fetch of experiments, to make the next fetch conditional
+    ///   * "enrollment-dates": a map of the slugs of current enrollments to the UTC DateTime
+    ///                     at which this client enrolled
+    ///   * "kill-switches": a list of `FeatureKillSwitch`es, the features remotely disabled by
+    ///                     the experiments collection
     Meta,
     /// Store containing pending updates to experiment data.
     ///
-    /// The `Updates` store contains a single key "pending-experiment-updates", whose
+    /// The `Updates` store contains a key "pending-experiment-updates", whose
     /// corresponding value is a serialized `Vec<Experiment>` of new experiment data
-    /// that has been received from the server but not yet processed by the application.
+    /// that has been received from the server but not yet processed by the application,
+    /// and a key "pending-kill-switches" with the `Vec<FeatureKillSwitch>` received with it.
+    /// The key "last-apply-snapshot" holds the experiments and enrollments from before the
+    /// pending experiments were last applied, for `NimbusClient::rollback_last_apply`.
     Updates,
     /// Store containing collected counts of behavior events for targeting purposes.
     ///
@@ -138,17 +159,128 @@ pub enum StoreId {
     /// [`MultiIntervalCounter`] struct that contains a set of configurations and data
     /// for the different time periods that the data will be aggregated on.
     EventCounts,
+    /// Store containing the targeting of sticky experiments which this client has matched.
+    ///
+    /// Keys in the `StickyTargeting` store are experiment identifier slugs, and their
+    /// corresponding values are serialized instances of the [`StickyTargetingMatch`]
+    /// struct recording the targeting expression which was matched.
+    StickyTargeting,
+}
+
+impl StoreId {
+    /// Every store, in declaration order.
+    pub const ALL: [StoreId; 6] = [
+        StoreId::Experiments,
+        StoreId::Enrollments,
+        StoreId::Meta,
+        StoreId::Updates,
+        StoreId::EventCounts,
+        StoreId::StickyTargeting,
+    ];
+
+    /// The name of the store within the storage backend.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoreId::Meta => "meta",
+            StoreId::Experiments => "experiments",
+            StoreId::Enrollments => "enrollments",
+            StoreId::Updates => "updates",
+            StoreId::EventCounts => "event_counts",
+            StoreId::StickyTargeting => "sticky_targeting",
+        }
+    }
 }
 
-/// A wrapper for an Rkv store. Implemented to allow any value which supports
+/// A storage backend for the database: a transactional key-value store holding the
+/// records of each [`StoreId`] as JSON strings.
+///
+/// The records written by a [`Writer`] are visible to reads through that writer, and to
+/// other transactions once it is committed. A writer which is dropped without being
+/// committed discards its changes.
+pub trait Storage: Send + Sync {
+    /// Begins a read-only transaction.
+    fn read(&self) -> Result<Reader<'_>>;
+
+    /// Begins a read-write transaction. Only one is open at a time, so this blocks while
+    /// another writer is open.
+    fn write(&self) -> Result<Writer<'_>>;
+}
+
+/// The reads of a transaction on a [`Storage`].
+pub trait ReadTransaction {
+    /// Returns the JSON value of `key` in `store`.
+    fn get(&self, store: StoreId, key: &str) -> Result<Option<String>>;
+
+    /// Returns every key in `store` with its JSON value, ordered by key.
+    fn get_all(&self, store: StoreId) -> Result<Vec<(String, String)>>;
+}
+
+/// The writes of a read-write transaction on a [`Storage`].
+pub trait WriteTransaction: ReadTransaction {
+    fn put(&mut self, store: StoreId, key: &str, value: &str) -> Result<()>;
+
+    fn delete(&mut self, store: StoreId, key: &str) -> Result<()>;
+
+    fn clear(&mut self, store: StoreId) -> Result<()>;
+
+    /// Returns this transaction as a [`ReadTransaction`], for reading what has been
+    /// written to it.
+    fn as_read(&self) -> &dyn ReadTransaction;
+
+    fn commit(self: Box<Self>) -> Result<()>;
+}
+
+/// A read-only transaction, as returned by `Database::read`.
+pub struct Reader<'t>(Box<dyn ReadTransaction + 't>);
+
+impl<'t> Reader<'t> {
+    pub fn new(transaction: impl ReadTransaction + 't) -> Self {
+        Reader(Box::new(transaction))
+    }
+}
+
+/// A read-write transaction, as returned by `Database::write`.
+/// The `writer.commit();` must be called to commit data added via the
+/// writer.
+pub struct Writer<'t>(Box<dyn WriteTransaction + 't>);
+
+impl<'t> Writer<'t> {
+    pub fn new(transaction: impl WriteTransaction + 't) -> Self {
+        Writer(Box::new(transaction))
+    }
+
+    pub fn commit(self) -> Result<()> {
+        self.0.commit()
+    }
+}
+
+/// A transaction which records can be read through: either a [`Reader`], or a [`Writer`]
+/// to read what has been written to it before it's committed.
+pub trait Readable {
+    fn transaction(&self) -> &dyn ReadTransaction;
+}
+
+impl<'t> Readable for Reader<'t> {
+    fn transaction(&self) -> &dyn ReadTransaction {
+        &*self.0
+    }
+}
+
+impl<'t> Readable for Writer<'t> {
+    fn transaction(&self) -> &dyn ReadTransaction {
+        self.0.as_read()
+    }
+}
+
+/// A store within the database. Implemented to allow any value which supports
 /// serde to be used.
 pub struct SingleStore {
-    store: RkvSingleStore,
+    id: StoreId,
 }
 
 impl SingleStore {
-    pub fn new(store: RkvSingleStore) -> Self {
-        SingleStore { store }
+    pub fn new(id: StoreId) -> Self {
+        SingleStore { id }
     }
 
     pub fn put<T: serde::Serialize + for<'de> serde::Deserialize<'de>>(
@@ -158,40 +290,27 @@ impl SingleStore {
         persisted_data: &T,
     ) -> Result<()> {
         let persisted_json = serde_json::to_string(persisted_data)?;
-        self.store
-            .put(writer, key, &rkv::Value::Json(&persisted_json))?;
-        Ok(())
+        writer.0.put(self.id, key, &persisted_json)
     }
 
     #[allow(dead_code)]
     pub fn delete(&self, writer: &mut Writer, key: &str) -> Result<()> {
-        self.store.delete(writer, key)?;
-        Ok(())
+        writer.0.delete(self.id, key)
     }
 
     pub fn clear(&self, writer: &mut Writer) -> Result<()> {
-        self.store.clear(writer)?;
-        Ok(())
+        writer.0.clear(self.id)
     }
 
     // Some "get" functions that cooperate with transactions (ie, so we can
     // get what we've written to the transaction before it's committed).
-    // It's unfortunate that these are duplicated with the DB itself, but the
-    // traits used by rkv make this tricky.
-    pub fn get<'r, T, R>(&self, reader: &'r R, key: &str) -> Result<Option<T>>
+    pub fn get<T, R>(&self, reader: &R, key: &str) -> Result<Option<T>>
     where
-        R: Readable<'r>,
+        R: Readable,
         T: serde::Serialize + for<'de> serde::Deserialize<'de>,
     {
-        let persisted_data = self.store.get(reader, key)?;
-        match persisted_data {
-            Some(data) => {
-                if let rkv::Value::Json(data) = data {
-                    Ok(Some(serde_json::from_str::<T>(data)?))
-                } else {
-                    Err(NimbusError::InvalidPersistedData)
-                }
-            }
+        match reader.transaction().get(self.id, key)? {
+            Some(data) => Ok(Some(serde_json::from_str::<T>(&data)?)),
             None => Ok(None),
         }
     }
@@ -199,85 +318,227 @@ impl SingleStore {
     /// Fork of collect_all that simply drops records that fail to read
     /// rather than simply returning an error up the stack.  This likely
     /// wants to be just a parameter to collect_all, but for now....
-    pub fn try_collect_all<'r, T, R>(&self, reader: &'r R) -> Result<Vec<T>>
+    pub fn try_collect_all<T, R>(&self, reader: &R) -> Result<Vec<T>>
     where
-        R: Readable<'r>,
+        R: Readable,
         T: serde::Serialize + for<'de> serde::Deserialize<'de>,
     {
         let mut result = Vec::new();
-        let mut iter = self.store.iter_start(reader)?;
-        while let Some(Ok((_, data))) = iter.next() {
-            if let rkv::Value::Json(data) = data {
-                let unserialized = serde_json::from_str::<T>(data);
-                match unserialized {
-                    Ok(value) => result.push(value),
-                    Err(e) => {
-                        // If there is an error, we won't push this onto the
-                        // result Vec, but we won't blow up the entire
-                        // deserialization either.
-                        log::warn!(
-                            "try_collect_all: discarded a record while deserializing with: {:?}",
-                            e
-                        );
-                        log::warn!(
-                            "try_collect_all:   data that failed to deserialize: {:?}",
-                            data
-                        );
-                    }
-                };
-            }
+        for (_, data) in reader.transaction().get_all(self.id)? {
+            let unserialized = serde_json::from_str::<T>(&data);
+            match unserialized {
+                Ok(value) => result.push(value),
+                Err(e) => {
+                    // If there is an error, we won't push this onto the
+                    // result Vec, but we won't blow up the entire
+                    // deserialization either.
+                    log::warn!(
+                        "try_collect_all: discarded a record while deserializing with: {:?}",
+                        e
+                    );
+                    log::warn!(
+                        "try_collect_all:   data that failed to deserialize: {:?}",
+                        data
+                    );
+                }
+            };
         }
         Ok(result)
     }
 
-    pub fn collect_all<'r, T, R>(&self, reader: &'r R) -> Result<Vec<T>>
+    pub fn collect_all<T, R>(&self, reader: &R) -> Result<Vec<T>>
     where
-        R: Readable<'r>,
+        R: Readable,
         T: serde::Serialize + for<'de> serde::Deserialize<'de>,
     {
         let mut result = Vec::new();
-        let mut iter = self.store.iter_start(reader)?;
-        while let Some(Ok((_, data))) = iter.next() {
-            if let rkv::Value::Json(data) = data {
-                result.push(serde_json::from_str::<T>(data)?);
-            }
+        for (_, data) in reader.transaction().get_all(self.id)? {
+            result.push(serde_json::from_str::<T>(&data)?);
+        }
+        Ok(result)
+    }
+
+    /// Collects every record in the store as untyped JSON, keyed by its key.
+    pub fn collect_all_by_key<R>(&self, reader: &R) -> Result<Map<String, Value>>
+    where
+        R: Readable,
+    {
+        let mut result = Map::new();
+        for (key, data) in reader.transaction().get_all(self.id)? {
+            result.insert(key, serde_json::from_str(&data)?);
         }
         Ok(result)
     }
+
+    /// Replaces every record in the store with `records`, as collected by `collect_all_by_key`.
+    pub fn replace_all(&self, writer: &mut Writer, records: &Map<String, Value>) -> Result<()> {
+        self.clear(writer)?;
+        for (key, value) in records {
+            self.put(writer, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`Storage`] backed by an Rkv database, in the `db` directory.
+pub struct RkvStorage {
+    rkv: Rkv,
+    stores: Vec<RkvSingleStore>,
+}
+
+impl RkvStorage {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let rkv = Database::open_rkv(path)?;
+        let stores = StoreId::ALL
+            .iter()
+            .map(|id| rkv.open_single(id.name(), StoreOptions::create()))
+            .collect::<Result<_, StoreError>>()?;
+        Ok(Self { rkv, stores })
+    }
+
+    fn store(&self, id: StoreId) -> &RkvSingleStore {
+        &self.stores[id as usize]
+    }
+}
+
+impl Storage for RkvStorage {
+    fn read(&self) -> Result<Reader<'_>> {
+        Ok(Reader::new(RkvReadTransaction {
+            storage: self,
+            reader: self.rkv.read()?,
+        }))
+    }
+
+    fn write(&self) -> Result<Writer<'_>> {
+        Ok(Writer::new(RkvWriteTransaction {
+            storage: self,
+            writer: self.rkv.write()?,
+        }))
+    }
+}
+
+// The reads of the Rkv transactions are the same, but the traits used by rkv make it
+// tricky to share them between readers and writers.
+macro_rules! impl_rkv_read_transaction {
+    ($ty:ident, $field:ident) => {
+        impl<'t> ReadTransaction for $ty<'t> {
+            fn get(&self, store: StoreId, key: &str) -> Result<Option<String>> {
+                match self.storage.store(store).get(&self.$field, key)? {
+                    Some(rkv::Value::Json(data)) => Ok(Some(data.to_string())),
+                    Some(_) => Err(NimbusError::InvalidPersistedData),
+                    None => Ok(None),
+                }
+            }
+
+            fn get_all(&self, store: StoreId) -> Result<Vec<(String, String)>> {
+                let mut result = Vec::new();
+                for entry in self.storage.store(store).iter_start(&self.$field)? {
+                    let (key, data) = entry?;
+                    if let rkv::Value::Json(data) = data {
+                        let key = String::from_utf8_lossy(key).into_owned();
+                        result.push((key, data.to_string()));
+                    }
+                }
+                Ok(result)
+            }
+        }
+    };
+}
+
+struct RkvReadTransaction<'t> {
+    storage: &'t RkvStorage,
+    reader: RkvReader<'t>,
+}
+
+impl_rkv_read_transaction!(RkvReadTransaction, reader);
+
+struct RkvWriteTransaction<'t> {
+    storage: &'t RkvStorage,
+    writer: RkvWriter<'t>,
+}
+
+impl_rkv_read_transaction!(RkvWriteTransaction, writer);
+
+impl<'t> WriteTransaction for RkvWriteTransaction<'t> {
+    fn put(&mut self, store: StoreId, key: &str, value: &str) -> Result<()> {
+        self.storage
+            .store(store)
+            .put(&mut self.writer, key, &rkv::Value::Json(value))?;
+        Ok(())
+    }
+
+    fn delete(&mut self, store: StoreId, key: &str) -> Result<()> {
+        self.storage.store(store).delete(&mut self.writer, key)?;
+        Ok(())
+    }
+
+    fn clear(&mut self, store: StoreId) -> Result<()> {
+        self.storage.store(store).clear(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn as_read(&self) -> &dyn ReadTransaction {
+        self
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.writer.commit()?;
+        Ok(())
+    }
+}
+
+/// The contents of a database, as exported by `NimbusClient::export_enrollment_state`.
+///
+/// Each store is a map of its keys to their JSON values. The pending updates aren't
+/// included, since they are replaced by the next fetch.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseState {
+    pub meta: Map<String, Value>,
+    pub experiments: Map<String, Value>,
+    pub enrollments: Map<String, Value>,
+    pub event_counts: Map<String, Value>,
+    pub sticky_targeting: Map<String, Value>,
 }
 
 /// Database used to access persisted data
-/// This an abstraction around an Rkv database
+/// This an abstraction around a [`Storage`], which is an Rkv database or, with the
+/// `sqlite-storage` feature, an SQLite database
 /// An instance on this database is created each time the component is loaded
 /// if there is persisted data, the `get` functions should retrieve it
 pub struct Database {
-    rkv: Rkv,
+    storage: Box<dyn Storage>,
     meta_store: SingleStore,
     experiment_store: SingleStore,
     enrollment_store: SingleStore,
     updates_store: SingleStore,
     event_count_store: SingleStore,
+    sticky_targeting_store: SingleStore,
 }
 
 impl Database {
     /// Main constructor for a database
-    /// Initiates the Rkv database to be used to retreive persisted data
+    /// Initiates the database to be used to retreive persisted data
     /// # Arguments
     /// - `path`: A path to the persisted data, this is provided by the consuming application
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let rkv = Self::open_rkv(path)?;
-        let meta_store = rkv.open_single("meta", StoreOptions::create())?;
-        let experiment_store = rkv.open_single("experiments", StoreOptions::create())?;
-        let enrollment_store = rkv.open_single("enrollments", StoreOptions::create())?;
-        let updates_store = rkv.open_single("updates", StoreOptions::create())?;
-        let event_count_store = rkv.open_single("event_counts", StoreOptions::create())?;
+        #[cfg(feature = "sqlite-storage")]
+        let storage = SqliteStorage::open(path)?;
+        #[cfg(not(feature = "sqlite-storage"))]
+        let storage = RkvStorage::open(path)?;
+        Self::with_storage(Box::new(storage))
+    }
+
+    /// Constructs a database persisted in `storage`, upgrading its data to `DB_VERSION`.
+    pub fn with_storage(storage: Box<dyn Storage>) -> Result<Self> {
         let db = Self {
-            rkv,
-            meta_store: SingleStore::new(meta_store),
-            experiment_store: SingleStore::new(experiment_store),
-            enrollment_store: SingleStore::new(enrollment_store),
-            updates_store: SingleStore::new(updates_store),
-            event_count_store: SingleStore::new(event_count_store),
+            storage,
+            meta_store: SingleStore::new(StoreId::Meta),
+            experiment_store: SingleStore::new(StoreId::Experiments),
+            enrollment_store: SingleStore::new(StoreId::Enrollments),
+            updates_store: SingleStore::new(StoreId::Updates),
+            event_count_store: SingleStore::new(StoreId::EventCounts),
+            sticky_targeting_store: SingleStore::new(StoreId::StickyTargeting),
         };
         db.maybe_upgrade()?;
         Ok(db)
@@ -285,7 +546,7 @@ impl Database {
 
     fn maybe_upgrade(&self) -> Result<()> {
         log::debug!("entered maybe upgrade");
-        let mut writer = self.rkv.write()?;
+        let mut writer = self.write()?;
         let db_version = self.meta_store.get::<u16, _>(&writer, DB_KEY_DB_VERSION)?;
         match db_version {
             Some(DB_VERSION) => {
@@ -293,25 +554,23 @@ impl Database {
                 log::info!("Already at version {}, no upgrade needed", DB_VERSION);
                 return Ok(());
             }
-            Some(1) => {
-                log::info!("Migrating database from v1 to v2");
-                match self.migrate_v1_to_v2(&mut writer) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        // The idea here is that it's better to leave an
-                        // individual install with a clean empty database
-                        // than in an unknown inconsistent state, because it
-                        // allows them to start participating in experiments
-                        // again, rather than potentially repeating the upgrade
-                        // over and over at each embedding client restart.
-                        error_support::report_error!(
-                            "nimbus-database-migration",
-                            "Error migrating database v1 to v2: {:?}.  Wiping experiments and enrollments",
-                            e
-                        );
-                        self.clear_experiments_and_enrollments(&mut writer)?;
-                    }
-                };
+            Some(version) if (1..DB_VERSION).contains(&version) => {
+                if let Err(e) = self.run_migrations(&mut writer, version) {
+                    // The idea here is that it's better to leave an
+                    // individual install with a clean empty database
+                    // than in an unknown inconsistent state, because it
+                    // allows them to start participating in experiments
+                    // again, rather than potentially repeating the upgrade
+                    // over and over at each embedding client restart.
+                    error_support::report_error!(
+                        "nimbus-database-migration",
+                        "Error migrating database v{} to v{}: {:?}.  Wiping experiments and enrollments",
+                        version,
+                        DB_VERSION,
+                        e
+                    );
+                    self.clear_experiments_and_enrollments(&mut writer)?;
+                }
             }
             None => {
                 log::info!("maybe_upgrade: no version number; wiping most stores");
@@ -342,6 +601,17 @@ impl Database {
         Ok(())
     }
 
+    /// Runs the migrations from `from_version` up to `DB_VERSION`, stopping at the first
+    /// which fails.
+    fn run_migrations(&self, writer: &mut Writer, from_version: u16) -> Result<()> {
+        let start = from_version as usize - 1;
+        for (i, migrate) in MIGRATIONS.iter().enumerate().skip(start) {
+            log::info!("Migrating database from v{} to v{}", i + 1, i + 2);
+            migrate(self, writer)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn clear_experiments_and_enrollments(
         &self,
         writer: &mut Writer,
@@ -356,16 +626,49 @@ impl Database {
         Ok(())
     }
 
+    /// Exports every store, except the pending updates.
+    pub(crate) fn export_state<R>(&self, reader: &R) -> Result<DatabaseState>
+    where
+        R: Readable,
+    {
+        Ok(DatabaseState {
+            meta: self.meta_store.collect_all_by_key(reader)?,
+            experiments: self.experiment_store.collect_all_by_key(reader)?,
+            enrollments: self.enrollment_store.collect_all_by_key(reader)?,
+            event_counts: self.event_count_store.collect_all_by_key(reader)?,
+            sticky_targeting: self.sticky_targeting_store.collect_all_by_key(reader)?,
+        })
+    }
+
+    /// Replaces the contents of every store with those exported by `export_state`, and
+    /// discards the pending updates. The state must have been exported from a database at
+    /// the current `DB_VERSION`, since it isn't migrated.
+    pub(crate) fn import_state(&self, writer: &mut Writer, state: &DatabaseState) -> Result<()> {
+        let db_version = state.meta.get(DB_KEY_DB_VERSION).and_then(Value::as_u64);
+        if db_version != Some(DB_VERSION as u64) {
+            return Err(NimbusError::InvalidPersistedData);
+        }
+        self.meta_store.replace_all(writer, &state.meta)?;
+        self.experiment_store
+            .replace_all(writer, &state.experiments)?;
+        self.enrollment_store
+            .replace_all(writer, &state.enrollments)?;
+        self.event_count_store
+            .replace_all(writer, &state.event_counts)?;
+        self.sticky_targeting_store
+            .replace_all(writer, &state.sticky_targeting)?;
+        self.updates_store.clear(writer)?;
+        Ok(())
+    }
+
     /// Migrates a v1 database to v2
     ///
     /// Note that any Err returns from this function (including stuff
-    /// propagated up via the ? operator) will cause maybe_update (our caller)
+    /// propagated up via the ? operator) will cause maybe_upgrade
     /// to assume that this is unrecoverable and wipe the database, removing
     /// people from any existing enrollments and blowing away their experiment
     /// history, so that they don't get left in an inconsistent state.
     fn migrate_v1_to_v2(&self, writer: &mut Writer) -> Result<()> {
-        log::info!("Upgrading from version 1 to version 2");
-
         // use try_collect_all to read everything except records that serde
         // returns deserialization errors on.  Some logging of those errors
         // happens, but it's not ideal.
@@ -444,11 +747,12 @@ impl Database {
             StoreId::Enrollments => &self.enrollment_store,
             StoreId::Updates => &self.updates_store,
             StoreId::EventCounts => &self.event_count_store,
+            StoreId::StickyTargeting => &self.sticky_targeting_store,
         }
     }
 
     pub fn open_rkv<P: AsRef<Path>>(path: P) -> Result<Rkv> {
-        let path = std::path::Path::new(path.as_ref()).join("db");
+        let path = std::path::Path::new(path.as_ref()).join(RKV_DB_DIR);
         log::debug!("open_rkv: path =  {:?}", path.display());
         fs::create_dir_all(&path)?;
         let rkv = match rkv_new(&path) {
@@ -461,14 +765,24 @@ impl Database {
                         // the other hand avoids us knowing about the
                         // underlying implementation (ie, how do we know what
                         // files might exist in all cases?)
-                        log::warn!(
-                            "Database at '{}' appears corrupt - removing and recreating",
-                            path.display()
+                        // The corrupt database is kept, replacing any earlier backup, so
+                        // that it can be looked at later. The new database is empty
+                        // until the experiments are next fetched.
+                        error_support::report_error!(
+                            "nimbus-database-corrupt",
+                            "Database at '{}' appears corrupt ({:?}) - backing up and recreating",
+                            path.display(),
+                            rkv_error
                         );
-                        fs::remove_dir_all(&path)?;
+                        let backup = path.with_file_name(CORRUPT_DB_BACKUP_DIR);
+                        if backup.exists() {
+                            fs::remove_dir_all(&backup)?;
+                        }
+                        if let Err(e) = fs::rename(&path, &backup) {
+                            log::warn!("Failed to back up the corrupt database: {}", e);
+                            fs::remove_dir_all(&path)?;
+                        }
                         fs::create_dir_all(&path)?;
-                        // TODO: Once we have glean integration we want to
-                        // record telemetry here.
                         rkv_new(&path)
                     }
                     // All other errors are fatal.
@@ -482,14 +796,14 @@ impl Database {
 
     /// Function used to obtain a "reader" which is used for read-only transactions.
     pub fn read(&self) -> Result<Reader> {
-        Ok(self.rkv.read()?)
+        self.storage.read()
     }
 
     /// Function used to obtain a "writer" which is used for transactions.
     /// The `writer.commit();` must be called to commit data added via the
     /// writer.
     pub fn write(&self) -> Result<Writer> {
-        Ok(self.rkv.write()?)
+        self.storage.write()
     }
 
     /// Function used to retrieve persisted data outside of a transaction.
@@ -505,37 +819,16 @@ impl Database {
         store_id: StoreId,
         key: &str,
     ) -> Result<Option<T>> {
-        let reader = self.rkv.read()?;
-        let persisted_data = self.get_store(store_id).store.get(&reader, key)?;
-        match persisted_data {
-            Some(data) => {
-                if let rkv::Value::Json(data) = data {
-                    Ok(Some(serde_json::from_str::<T>(data)?))
-                } else {
-                    Err(NimbusError::InvalidPersistedData)
-                }
-            }
-            None => Ok(None),
-        }
+        self.get_store(store_id).get(&self.read()?, key)
     }
 
     // Function for collecting all items in a store outside of a transaction.
     // Only available for tests; product code should always be using transactions.
-    // Iters are a bit tricky - would be nice to make them generic, but this will
-    // do for our use-case.
     #[cfg(test)]
     pub fn collect_all<T: serde::Serialize + for<'de> serde::Deserialize<'de>>(
         &self,
         store_id: StoreId,
     ) -> Result<Vec<T>> {
-        let mut result = Vec::new();
-        let reader = self.rkv.read()?;
-        let mut iter = self.get_store(store_id).store.iter_start(&reader)?;
-        while let Some(Ok((_, data))) = iter.next() {
-            if let rkv::Value::Json(data) = data {
-                result.push(serde_json::from_str::<T>(data)?);
-            }
-        }
-        Ok(result)
+        self.get_store(store_id).collect_all(&self.read()?)
     }
 }
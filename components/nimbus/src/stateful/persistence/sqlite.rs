@@ -0,0 +1,290 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The [`Storage`] backed by an SQLite database, used with the `sqlite-storage` feature.
+
+use super::{
+    ReadTransaction, Readable, Reader, RkvStorage, Storage, StoreId, WriteTransaction, Writer,
+    RKV_DB_DIR,
+};
+use crate::error::Result;
+use rusqlite::{params, Connection, OpenFlags, Transaction};
+use sql_support::{
+    open_database::{self, open_database_with_flags, ConnectionInitializer},
+    ConnExt,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// The name of the SQLite database file, in the directory given to `Database::new`.
+pub(crate) const SQLITE_DB_FILE: &str = "nimbus.sqlite";
+
+const SCHEMA_VERSION: u32 = 1;
+
+// Every store is kept in the one table, as JSON values keyed by the store's name and the
+// record's key.
+const CREATE_SCHEMA_SQL: &str = "
+    CREATE TABLE records (
+        store TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (store, key)
+    ) WITHOUT ROWID;
+";
+
+struct NimbusConnectionInitializer;
+
+impl ConnectionInitializer for NimbusConnectionInitializer {
+    const NAME: &'static str = "nimbus db";
+    const END_VERSION: u32 = SCHEMA_VERSION;
+
+    fn prepare(&self, conn: &Connection, _db_empty: bool) -> open_database::Result<()> {
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        Ok(())
+    }
+
+    fn init(&self, tx: &Transaction<'_>) -> open_database::Result<()> {
+        tx.execute_batch(CREATE_SCHEMA_SQL)?;
+        Ok(())
+    }
+
+    fn upgrade_from(&self, _tx: &Transaction<'_>, version: u32) -> open_database::Result<()> {
+        Err(open_database::Error::IncompatibleVersion(version))
+    }
+}
+
+/// The [`Storage`] backed by an SQLite database.
+///
+/// Each reader is a transaction on a read-only connection of its own, so that, as with Rkv,
+/// it sees the records committed when it first reads, even while a writer is open. Idle
+/// read-only connections are kept for the next reader.
+pub struct SqliteStorage {
+    db_file: PathBuf,
+    write_conn: Mutex<Connection>,
+    read_conns: Mutex<Vec<Connection>>,
+}
+
+impl SqliteStorage {
+    /// Opens the SQLite database in the directory `path`. The records of an Rkv database
+    /// in the same directory are moved into it the first time it is opened. If they can't
+    /// be, opening fails, and the Rkv database is kept to be imported the next time.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        fs::create_dir_all(path)?;
+        let db_file = path.join(SQLITE_DB_FILE);
+        let write_conn = open_database_with_flags(
+            &db_file,
+            OpenFlags::SQLITE_OPEN_NO_MUTEX
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_READ_WRITE,
+            &NimbusConnectionInitializer,
+        )?;
+        let storage = Self {
+            db_file,
+            write_conn: Mutex::new(write_conn),
+            read_conns: Mutex::new(Vec::new()),
+        };
+        // The read-only connections have to be opened after the writable one has created
+        // the database.
+        let read_conn = storage.open_read_conn()?;
+        storage.read_conns.lock().unwrap().push(read_conn);
+        // Nothing else can be written to the database until the import has been committed,
+        // since we'd otherwise take the records written as a sign the import was done, and
+        // remove the Rkv database without importing it.
+        if let Err(e) = storage.import_rkv(path) {
+            error_support::report_error!(
+                "nimbus-rkv-import",
+                "Error importing the Rkv database into SQLite: {:?}",
+                e
+            );
+            return Err(e);
+        }
+        Ok(storage)
+    }
+
+    fn open_read_conn(&self) -> Result<Connection> {
+        Ok(open_database_with_flags(
+            &self.db_file,
+            OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_READ_ONLY,
+            &NimbusConnectionInitializer,
+        )?)
+    }
+
+    /// Copies every record of the Rkv database in `path` into this database, which must be
+    /// empty, in a single transaction, then removes the Rkv database once it's committed.
+    fn import_rkv(&self, path: &Path) -> Result<()> {
+        let rkv_dir = path.join(RKV_DB_DIR);
+        if !rkv_dir.exists() {
+            return Ok(());
+        }
+        let mut transaction = SqliteWriteTransaction::begin(self.write_conn.lock().unwrap())?;
+        // A database with records has already been imported into, and is newer than the
+        // Rkv database, which we failed to remove.
+        if !transaction.conn.exists("SELECT 1 FROM records", [])? {
+            log::info!("Importing the Rkv database at {:?}", rkv_dir.display());
+            let rkv = RkvStorage::open(path)?;
+            let reader = rkv.read()?;
+            for store in StoreId::ALL {
+                for (key, value) in reader.transaction().get_all(store)? {
+                    transaction.put(store, &key, &value)?;
+                }
+            }
+        }
+        Box::new(transaction).commit()?;
+        if let Err(e) = fs::remove_dir_all(&rkv_dir) {
+            log::warn!("Failed to remove the imported Rkv database: {}", e);
+        }
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self) -> Result<Reader<'_>> {
+        // A thread can have several readers open at once, so each takes a connection
+        // rather than waiting for another reader's.
+        let idle_conn = self.read_conns.lock().unwrap().pop();
+        let conn = match idle_conn {
+            Some(conn) => conn,
+            None => self.open_read_conn()?,
+        };
+        Ok(Reader::new(SqliteReadTransaction::begin(self, conn)?))
+    }
+
+    fn write(&self) -> Result<Writer<'_>> {
+        Ok(Writer::new(SqliteWriteTransaction::begin(
+            self.write_conn.lock().unwrap(),
+        )?))
+    }
+}
+
+fn get(conn: &Connection, store: StoreId, key: &str) -> Result<Option<String>> {
+    Ok(conn.try_query_one(
+        "SELECT value FROM records WHERE store = ? AND key = ?",
+        params![store.name(), key],
+        true,
+    )?)
+}
+
+fn get_all(conn: &Connection, store: StoreId) -> Result<Vec<(String, String)>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT key, value FROM records WHERE store = ? ORDER BY key")?;
+    let rows = stmt.query_map(params![store.name()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// A transaction on a read-only connection, which is returned to the storage's idle
+/// connections when the transaction is dropped.
+struct SqliteReadTransaction<'t> {
+    storage: &'t SqliteStorage,
+    // Only taken when dropped.
+    conn: Option<Connection>,
+}
+
+impl<'t> SqliteReadTransaction<'t> {
+    fn begin(storage: &'t SqliteStorage, conn: Connection) -> Result<Self> {
+        conn.execute_batch("BEGIN")?;
+        Ok(Self {
+            storage,
+            conn: Some(conn),
+        })
+    }
+
+    fn conn(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl<'t> ReadTransaction for SqliteReadTransaction<'t> {
+    fn get(&self, store: StoreId, key: &str) -> Result<Option<String>> {
+        get(self.conn(), store, key)
+    }
+
+    fn get_all(&self, store: StoreId) -> Result<Vec<(String, String)>> {
+        get_all(self.conn(), store)
+    }
+}
+
+impl<'t> Drop for SqliteReadTransaction<'t> {
+    fn drop(&mut self) {
+        let conn = self.conn.take().unwrap();
+        match conn.execute_batch("ROLLBACK") {
+            Ok(()) => self.storage.read_conns.lock().unwrap().push(conn),
+            Err(e) => log::warn!("Error ending a read transaction: {}", e),
+        }
+    }
+}
+
+/// A transaction on the writable connection, which holds its lock until the transaction
+/// is committed or, when dropped, rolled back.
+struct SqliteWriteTransaction<'t> {
+    conn: MutexGuard<'t, Connection>,
+    finished: bool,
+}
+
+impl<'t> SqliteWriteTransaction<'t> {
+    fn begin(conn: MutexGuard<'t, Connection>) -> Result<Self> {
+        conn.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(Self {
+            conn,
+            finished: false,
+        })
+    }
+}
+
+impl<'t> ReadTransaction for SqliteWriteTransaction<'t> {
+    fn get(&self, store: StoreId, key: &str) -> Result<Option<String>> {
+        get(&self.conn, store, key)
+    }
+
+    fn get_all(&self, store: StoreId) -> Result<Vec<(String, String)>> {
+        get_all(&self.conn, store)
+    }
+}
+
+impl<'t> WriteTransaction for SqliteWriteTransaction<'t> {
+    fn put(&mut self, store: StoreId, key: &str, value: &str) -> Result<()> {
+        self.conn.execute_cached(
+            "INSERT OR REPLACE INTO records(store, key, value) VALUES (?, ?, ?)",
+            params![store.name(), key, value],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&mut self, store: StoreId, key: &str) -> Result<()> {
+        self.conn.execute_cached(
+            "DELETE FROM records WHERE store = ? AND key = ?",
+            params![store.name(), key],
+        )?;
+        Ok(())
+    }
+
+    fn clear(&mut self, store: StoreId) -> Result<()> {
+        self.conn
+            .execute_cached("DELETE FROM records WHERE store = ?", params![store.name()])?;
+        Ok(())
+    }
+
+    fn as_read(&self) -> &dyn ReadTransaction {
+        self
+    }
+
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        self.conn.execute_batch("COMMIT")?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<'t> Drop for SqliteWriteTransaction<'t> {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+                log::warn!("Error rolling back a transaction: {}", e);
+            }
+        }
+    }
+}
@@ -6,10 +6,27 @@
 //! safe updating from the server.
 
 use crate::error::Result;
+use crate::stateful::enrollment::{DB_KEY_ENROLLMENT_DATES, DB_KEY_KILL_SWITCHES};
 use crate::stateful::persistence::{Database, StoreId, Writer};
-use crate::Experiment;
+use crate::{Experiment, FeatureKillSwitch, Recipes};
+use serde_derive::*;
+use serde_json::{Map, Value};
 
 const KEY_PENDING_UPDATES: &str = "pending-experiment-updates";
+const KEY_PENDING_KILL_SWITCHES: &str = "pending-kill-switches";
+const KEY_LAST_APPLY_SNAPSHOT: &str = "last-apply-snapshot";
+
+/// The experiments, enrollments, and the state derived from them, from before pending
+/// experiments were last applied, so that the apply can be rolled back.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ApplySnapshot {
+    experiments: Map<String, Value>,
+    enrollments: Map<String, Value>,
+    sticky_targeting: Map<String, Value>,
+    enrollment_dates: Option<Value>,
+    kill_switches: Option<Value>,
+}
 
 pub fn write_pending_experiments(
     db: &Database,
@@ -20,6 +37,25 @@ pub fn write_pending_experiments(
         .put(writer, KEY_PENDING_UPDATES, &experiments)
 }
 
+/// Writes the experiments and the kill switches received together, to be applied together.
+pub fn write_pending_recipes(db: &Database, writer: &mut Writer, recipes: Recipes) -> Result<()> {
+    write_pending_experiments(db, writer, recipes.experiments)?;
+    db.get_store(StoreId::Updates)
+        .put(writer, KEY_PENDING_KILL_SWITCHES, &recipes.kill_switches)
+}
+
+/// Reads the kill switches which arrived with the pending experiments. This must be called
+/// before `read_and_remove_pending_experiments`, which clears them.
+pub fn read_pending_kill_switches(
+    db: &Database,
+    writer: &Writer,
+) -> Result<Vec<FeatureKillSwitch>> {
+    Ok(db
+        .get_store(StoreId::Updates)
+        .get(writer, KEY_PENDING_KILL_SWITCHES)?
+        .unwrap_or_default())
+}
+
 pub fn read_and_remove_pending_experiments(
     db: &Database,
     writer: &mut Writer,
@@ -38,3 +74,55 @@ pub fn read_and_remove_pending_experiments(
     // None is "there are no pending updates".
     Ok(experiments)
 }
+
+/// Snapshots the experiments and enrollments before the pending experiments are applied,
+/// replacing the snapshot from any earlier apply. This must be called after
+/// `read_and_remove_pending_experiments`, which clears the store it's kept in.
+pub fn write_apply_snapshot(db: &Database, writer: &mut Writer) -> Result<()> {
+    let meta = db.get_store(StoreId::Meta);
+    let snapshot = ApplySnapshot {
+        experiments: db
+            .get_store(StoreId::Experiments)
+            .collect_all_by_key(writer)?,
+        enrollments: db
+            .get_store(StoreId::Enrollments)
+            .collect_all_by_key(writer)?,
+        sticky_targeting: db
+            .get_store(StoreId::StickyTargeting)
+            .collect_all_by_key(writer)?,
+        enrollment_dates: meta.get(writer, DB_KEY_ENROLLMENT_DATES)?,
+        kill_switches: meta.get(writer, DB_KEY_KILL_SWITCHES)?,
+    };
+    db.get_store(StoreId::Updates)
+        .put(writer, KEY_LAST_APPLY_SNAPSHOT, &snapshot)
+}
+
+/// Restores the snapshot written by `write_apply_snapshot`, then removes it, so an apply can
+/// only be rolled back once. Returns `false` if there is no snapshot.
+pub fn restore_apply_snapshot(db: &Database, writer: &mut Writer) -> Result<bool> {
+    let updates = db.get_store(StoreId::Updates);
+    let snapshot: ApplySnapshot = match updates.get(writer, KEY_LAST_APPLY_SNAPSHOT)? {
+        Some(snapshot) => snapshot,
+        None => return Ok(false),
+    };
+    db.get_store(StoreId::Experiments)
+        .replace_all(writer, &snapshot.experiments)?;
+    db.get_store(StoreId::Enrollments)
+        .replace_all(writer, &snapshot.enrollments)?;
+    db.get_store(StoreId::StickyTargeting)
+        .replace_all(writer, &snapshot.sticky_targeting)?;
+    let meta = db.get_store(StoreId::Meta);
+    for (key, value) in [
+        (DB_KEY_ENROLLMENT_DATES, snapshot.enrollment_dates),
+        (DB_KEY_KILL_SWITCHES, snapshot.kill_switches),
+    ] {
+        match value {
+            Some(value) => meta.put(writer, key, &value)?,
+            // Deleting a missing key is an error.
+            None if meta.get::<Value, _>(writer, key)?.is_some() => meta.delete(writer, key)?,
+            None => {}
+        }
+    }
+    updates.delete(writer, KEY_LAST_APPLY_SNAPSHOT)?;
+    Ok(true)
+}
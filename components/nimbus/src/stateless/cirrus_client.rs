@@ -162,7 +162,11 @@ impl CirrusClient {
                 .iter()
                 .cloned()
                 .map(|e| {
+                    let experiment = state.experiments.iter().find(|exp| exp.slug == e.slug);
                     let mut extra: EnrollmentStatusExtraDef = e.into();
+                    if let Some(experiment) = experiment {
+                        extra = extra.with_holdback(experiment);
+                    }
                     extra.user_id = Some(user_id.clone());
                     extra
                 })
@@ -2,7 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::{evaluator::split_locale, stateless::matcher::AppContext};
+use crate::{
+    locale::{canonicalize_locale, Locale},
+    stateless::matcher::AppContext,
+};
 use serde_derive::*;
 use serde_json::Map;
 use serde_json::Value;
@@ -14,25 +17,31 @@ pub struct TargetingAttributes {
     #[serde(flatten)]
     pub request_context: Map<String, Value>,
     pub language: Option<String>,
+    pub script: Option<String>,
     pub region: Option<String>,
+    /// The canonical locale, then the less specific locales it falls back to, e.g.
+    /// `["zh-Hant-TW", "zh-Hant", "zh"]`.
+    pub locale_fallbacks: Vec<String>,
 }
 
 impl TargetingAttributes {
-    pub fn new(app_context: AppContext, request_context: Map<String, Value>) -> Self {
-        let (language, region) = match request_context
-            .get("locale")
-            .unwrap_or(&Value::Null)
-            .as_str()
-        {
-            Some(locale) => split_locale(locale.to_string()),
-            _ => (None, None),
+    pub fn new(app_context: AppContext, mut request_context: Map<String, Value>) -> Self {
+        let locale = match request_context.get_mut("locale") {
+            Some(Value::String(locale)) => {
+                let parsed = Locale::parse(locale);
+                *locale = canonicalize_locale(locale);
+                parsed
+            }
+            _ => Locale::default(),
         };
 
         Self {
             app_context,
             request_context,
-            language,
-            region,
+            locale_fallbacks: locale.fallbacks(),
+            language: locale.language,
+            script: locale.script,
+            region: locale.region,
         }
     }
 }
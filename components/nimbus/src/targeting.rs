@@ -120,17 +120,27 @@ pub fn jexl_eval<Context: serde::Serialize>(
     }
 }
 
+// As in Firefox, numbers are compared as if they were strings, so `versionCompare(100)` is the
+// same as `versionCompare('100')`.
+fn version_arg(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
 fn version_compare(args: &[Value]) -> Result<Value> {
     let curr_version = args.first().ok_or_else(|| {
         NimbusError::VersionParsingError("current version doesn't exist in jexl transform".into())
     })?;
-    let curr_version = curr_version.as_str().ok_or_else(|| {
+    let curr_version = version_arg(curr_version).ok_or_else(|| {
         NimbusError::VersionParsingError("current version in jexl transform is not a string".into())
     })?;
     let min_version = args.get(1).ok_or_else(|| {
         NimbusError::VersionParsingError("minimum version doesn't exist in jexl transform".into())
     })?;
-    let min_version = min_version.as_str().ok_or_else(|| {
+    let min_version = version_arg(min_version).ok_or_else(|| {
         NimbusError::VersionParsingError("minium version is not a string in jexl transform".into())
     })?;
     let min_version = Version::try_from(min_version)?;
@@ -10,7 +10,7 @@ use crate::{
 };
 
 #[cfg(feature = "stateful")]
-use crate::metrics::{FeatureExposureExtraDef, MalformedFeatureConfigExtraDef};
+use crate::metrics::{FeatureExposureExtraDef, FetchErrorExtraDef, MalformedFeatureConfigExtraDef};
 
 use serde::Serialize;
 use serde_json::{json, Value};
@@ -60,6 +60,8 @@ struct MetricState {
     exposures: Vec<FeatureExposureExtraDef>,
     #[cfg(feature = "stateful")]
     malformeds: Vec<MalformedFeatureConfigExtraDef>,
+    #[cfg(feature = "stateful")]
+    fetch_errors: Vec<FetchErrorExtraDef>,
 }
 
 /// A Rust implementation of the MetricsHandler trait
@@ -89,16 +91,26 @@ impl TestMetrics {
         let mut state = self.state.lock().unwrap();
         state.activations.clear();
         state.enrollment_statuses.clear();
+        state.exposures.clear();
         state.malformeds.clear();
+        state.fetch_errors.clear();
     }
 
     pub fn get_activations(&self) -> Vec<FeatureExposureExtraDef> {
         self.state.lock().unwrap().activations.clone()
     }
 
+    pub fn get_exposures(&self) -> Vec<FeatureExposureExtraDef> {
+        self.state.lock().unwrap().exposures.clone()
+    }
+
     pub fn get_malformeds(&self) -> Vec<MalformedFeatureConfigExtraDef> {
         self.state.lock().unwrap().malformeds.clone()
     }
+
+    pub fn get_fetch_errors(&self) -> Vec<FetchErrorExtraDef> {
+        self.state.lock().unwrap().fetch_errors.clone()
+    }
 }
 
 impl MetricsHandler for TestMetrics {
@@ -124,6 +136,12 @@ impl MetricsHandler for TestMetrics {
         let mut state = self.state.lock().unwrap();
         state.malformeds.push(event);
     }
+
+    #[cfg(feature = "stateful")]
+    fn record_fetch_error(&self, event: FetchErrorExtraDef) {
+        let mut state = self.state.lock().unwrap();
+        state.fetch_errors.push(event);
+    }
 }
 
 pub(crate) fn get_test_experiments() -> Vec<Experiment> {
@@ -1443,4 +1443,28 @@ mod event_store_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn clear_should_remove_persisted_events() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let db = Database::new(&tmp_dir)?;
+
+        let mut store = EventStore::new();
+        store.record_event(1, "event-1", None)?;
+        store.persist_data(&db)?;
+        assert_eq!(EventStore::try_from(&db)?.events.len(), 1);
+
+        store.clear(&db)?;
+        assert!(store.events.is_empty());
+
+        // Once cleared, the events shouldn't come back when the store is read again.
+        let mut store = EventStore::try_from(&db)?;
+        assert!(store.events.is_empty());
+        assert_eq!(
+            0f64,
+            store.query("event-1", Interval::Days, 56, 0, EventQueryType::Sum)?
+        );
+
+        Ok(())
+    }
 }
@@ -14,19 +14,23 @@ use crate::{
         behavior::EventStore,
         enrollment::{
             get_enrollments, opt_in_with_branch, opt_out, reset_telemetry_identifiers,
-            set_global_user_participation,
+            set_global_user_participation, StickyTargetingMatch,
         },
         persistence::{Database, Readable, StoreId},
     },
-    tests::helpers::{get_test_experiments, no_coenrolling_features},
-    AppContext, AvailableRandomizationUnits, NimbusTargetingHelper, Result,
+    tests::helpers::{
+        get_bucketed_rollout, get_single_feature_rollout, get_test_experiments,
+        no_coenrolling_features,
+    },
+    AppContext, AvailableRandomizationUnits, Experiment, NimbusTargetingHelper, Result,
 };
+use serde_json::json;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
-fn get_experiment_enrollments<'r>(
+fn get_experiment_enrollments(
     db: &Database,
-    reader: &'r impl Readable<'r>,
+    reader: &impl Readable,
 ) -> Result<Vec<ExperimentEnrollment>> {
     db.get_store(StoreId::Enrollments).collect_all(reader)
 }
@@ -168,6 +172,72 @@ fn test_updates() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sticky_targeting() -> Result<()> {
+    let _ = env_logger::try_init();
+    let tmp_dir = tempfile::tempdir()?;
+    let db = Database::new(&tmp_dir)?;
+    let mut writer = db.write()?;
+    let nimbus_id = Uuid::new_v4();
+    let aru = AvailableRandomizationUnits::with_nimbus_id(&nimbus_id);
+    let app_context = |is_first_run: bool| AppContext {
+        app_name: "fenix".to_string(),
+        app_id: "org.mozilla.fenix".to_string(),
+        channel: "nightly".to_string(),
+        custom_targeting_attributes: json!({ "is_first_run": is_first_run }).as_object().cloned(),
+        ..Default::default()
+    };
+    let recipe = |slug: &str, count: i64, is_sticky: bool| Experiment {
+        targeting: Some("is_first_run".to_string()),
+        is_sticky,
+        ..get_bucketed_rollout(slug, count)
+    };
+    let ids = no_coenrolling_features();
+
+    // On the first run, we match the targeting of both, but aren't bucketed into either.
+    let th = app_context(true).into();
+    let evolver = EnrollmentsEvolver::new(&aru, &th, &ids);
+    let exps = [recipe("sticky", 0, true), recipe("not-sticky", 0, false)];
+    evolver.evolve_enrollments_in_db(&db, &mut writer, &exps)?;
+    assert_eq!(get_enrollments(&db, &writer)?.len(), 0);
+
+    // Later, we no longer match, but the sticky experiment remembers that we did.
+    let th = app_context(false).into();
+    let evolver = EnrollmentsEvolver::new(&aru, &th, &ids);
+    let exps = [
+        recipe("sticky", 10_000, true),
+        recipe("not-sticky", 10_000, false),
+    ];
+    evolver.evolve_enrollments_in_db(&db, &mut writer, &exps)?;
+    let enrollments = get_enrollments(&db, &writer)?;
+    assert_eq!(enrollments.len(), 1);
+    assert_eq!(enrollments[0].slug, "sticky");
+
+    let enrollments = get_experiment_enrollments(&db, &writer)?;
+    let not_sticky = enrollments.iter().find(|e| e.slug == "not-sticky").unwrap();
+    assert!(matches!(
+        not_sticky.status,
+        EnrollmentStatus::NotEnrolled {
+            reason: NotEnrolledReason::NotTargeted
+        }
+    ));
+
+    // Changing the targeting forgets the match.
+    let exps = [Experiment {
+        targeting: Some("is_first_run && true".to_string()),
+        ..recipe("sticky", 10_000, true)
+    }];
+    evolver.evolve_enrollments_in_db(&db, &mut writer, &exps)?;
+    assert_eq!(get_enrollments(&db, &writer)?.len(), 0);
+    let matches: Vec<StickyTargetingMatch> = db
+        .get_store(StoreId::StickyTargeting)
+        .collect_all(&writer)?;
+    assert!(matches.is_empty());
+
+    writer.commit()?;
+    Ok(())
+}
+
 #[test]
 fn test_global_opt_out() -> Result<()> {
     let _ = env_logger::try_init();
@@ -383,3 +453,45 @@ fn test_telemetry_reset() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_telemetry_reset_reevaluates_rollouts() -> Result<()> {
+    let _ = env_logger::try_init();
+    let tmp_dir = tempfile::tempdir()?;
+    let db = Database::new(&tmp_dir)?;
+    let mut writer = db.write()?;
+
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({}));
+    db.get_store(StoreId::Experiments)
+        .put(&mut writer, &rollout.slug, &rollout)?;
+    db.get_store(StoreId::Enrollments).put(
+        &mut writer,
+        &rollout.slug,
+        &ExperimentEnrollment {
+            slug: rollout.slug.clone(),
+            status: EnrollmentStatus::new_enrolled(EnrolledReason::Qualified, "control"),
+        },
+    )?;
+    writer.commit()?;
+
+    let mut writer = db.write()?;
+    let events = reset_telemetry_identifiers(&db, &mut writer)?;
+    writer.commit()?;
+
+    // The rollout is left not-enrolled, so it is bucketed again with the new identifiers.
+    let enrollments = db.collect_all::<ExperimentEnrollment>(StoreId::Enrollments)?;
+    assert_eq!(enrollments.len(), 1);
+    assert!(matches!(
+        &enrollments[0].status,
+        EnrollmentStatus::NotEnrolled {
+            reason: NotEnrolledReason::OptOut
+        }
+    ));
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].change,
+        EnrollmentChangeEventType::Disqualification
+    );
+    Ok(())
+}
@@ -3,7 +3,10 @@
 * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::{
-    enrollment::{DisqualifiedReason, EnrolledReason, EnrollmentStatus, ExperimentEnrollment},
+    enrollment::{
+        DisqualifiedReason, EnrolledReason, EnrollmentChangeEvent, EnrollmentChangeEventType,
+        EnrollmentStatus, ExperimentEnrollment,
+    },
     error::Result,
     metrics::MalformedFeatureConfigExtraDef,
     stateful::{
@@ -11,6 +14,7 @@ use crate::{
             EventStore, Interval, IntervalConfig, IntervalData, MultiIntervalCounter,
             SingleIntervalCounter,
         },
+        dbcache::FEATURE_CONFIG_SNAPSHOT_FILE,
         persistence::{Database, StoreId},
     },
     tests::helpers::{
@@ -18,12 +22,19 @@ use crate::{
         get_single_feature_rollout, get_targeted_experiment, to_local_experiments_string,
         TestMetrics,
     },
-    AppContext, Experiment, NimbusClient, TargetingAttributes, DB_KEY_APP_VERSION,
-    DB_KEY_UPDATE_DATE,
+    AppContext, ApplyPendingExperimentsCallback, EnrollmentChangeObserver, Experiment,
+    NimbusClient, NimbusError, RemoteSettingsConfig, TargetingAttributes, DB_KEY_APP_VERSION,
+    DB_KEY_CACHE_VALIDATORS, DB_KEY_INSTALLATION_DATE, DB_KEY_UPDATE_DATE,
 };
 use chrono::{DateTime, Duration, Utc};
-use serde_json::json;
+use remote_settings::CacheValidators;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::{
+    mpsc::{channel, Sender},
+    Arc, Mutex,
+};
 use std::{io::Write, str::FromStr};
 use tempfile::TempDir;
 use uuid::Uuid;
@@ -965,6 +976,183 @@ fn test_fetch_enabled() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_set_preview_collection() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let ctx = AppContext {
+        app_name: "fenix".to_string(),
+        app_id: "org.mozilla.fenix".to_string(),
+        channel: "nightly".to_string(),
+        ..Default::default()
+    };
+    let experiments_dir = TempDir::new()?;
+    let exp = get_targeted_experiment("staged-experiment", "true");
+    std::fs::write(
+        experiments_dir.path().join("staged-experiment.json"),
+        exp.to_string(),
+    )?;
+    // The file system client ignores the collection, but lets us check that switching
+    // collection fetches and applies the experiments.
+    let config = RemoteSettingsConfig {
+        server_url: Some(
+            url::Url::from_directory_path(experiments_dir.path())
+                .unwrap()
+                .to_string(),
+        ),
+        bucket_name: None,
+        collection_name: "nimbus-mobile-experiments".to_string(),
+    };
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        ctx,
+        Default::default(),
+        tmp_dir.path(),
+        Some(config),
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+    assert!(client.get_active_experiments()?.is_empty());
+
+    let events = client.set_preview_collection(true)?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+
+    let events = client.set_preview_collection(false)?;
+    assert!(events.is_empty());
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_targeting_attributes() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let ctx = AppContext {
+        app_name: "fenix".to_string(),
+        app_id: "org.mozilla.fenix".to_string(),
+        channel: "nightly".to_string(),
+        custom_targeting_attributes: json!({ "is_first_run": false }).as_object().cloned(),
+        ..Default::default()
+    };
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        ctx.clone(),
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics.clone()),
+    )?;
+    client.initialize()?;
+    client.set_custom_targeting_attributes(
+        json!({ "is_default_browser": true, "referrer": "campaign" })
+            .as_object()
+            .cloned()
+            .unwrap(),
+    )?;
+    client.set_custom_targeting_attributes(
+        json!({ "referrer": null }).as_object().cloned().unwrap(),
+    )?;
+
+    let th = client.create_targeting_helper(None)?;
+    assert!(th.eval_jexl("is_default_browser && !is_first_run".to_string())?);
+
+    let exp = get_targeted_experiment("default-browser", "is_default_browser");
+    client.set_experiments_locally(to_local_experiments_string(&[exp])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+    drop(client);
+
+    // The attributes are still there after a restart.
+    let client = NimbusClient::new(
+        ctx,
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+    let attributes = client
+        .get_targeting_attributes()
+        .app_context
+        .custom_targeting_attributes
+        .unwrap();
+    assert_eq!(
+        Value::Object(attributes),
+        json!({ "is_first_run": false, "is_default_browser": true })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_set_feature_config_for_testing() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics.clone()),
+    )?;
+    client.initialize()?;
+
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 1 }));
+    client.set_experiments_locally(to_local_experiments_string(&[rollout.clone()])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+
+    client.set_feature_config_for_testing(
+        "a-feature".to_string(),
+        json!({ "a": 2 }).as_object().cloned().unwrap(),
+    )?;
+    client.set_feature_config_for_testing(
+        "b-feature".to_string(),
+        json!({ "b": true }).as_object().cloned().unwrap(),
+    )?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 2 }).to_string())
+    );
+    assert_eq!(
+        client.get_feature_config_variables("b-feature".to_string())?,
+        Some(json!({ "b": true }).to_string())
+    );
+    let enrollment = client
+        .get_enrollment_by_feature("b-feature".to_string())?
+        .unwrap();
+    assert_eq!(enrollment.branch, None);
+
+    // Overrides survive the experiments being updated.
+    client.set_experiments_locally(to_local_experiments_string(&[rollout])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 2 }).to_string())
+    );
+
+    client.unset_feature_config_for_testing("a-feature".to_string())?;
+    client.unset_feature_config_for_testing("b-feature".to_string())?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+    assert_eq!(
+        client.get_feature_config_variables("b-feature".to_string())?,
+        None
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_active_enrollment_in_targeting() -> Result<()> {
     let metrics = TestMetrics::new();
@@ -1022,6 +1210,55 @@ fn test_active_enrollment_in_targeting() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_completed_experiments_in_targeting() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        temp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let exp = get_targeted_experiment("test-1", "true");
+    client.set_experiments_locally(to_local_experiments_string(&[exp])?)?;
+    client.apply_pending_experiments()?;
+
+    let targeting_helper = client.create_targeting_helper(None)?;
+    assert!(targeting_helper.eval_jexl("'test-1' in active_experiments".to_string())?);
+    assert!(!targeting_helper.eval_jexl("'test-1' in completed_experiments".to_string())?);
+
+    // The experiment ends.
+    client.set_experiments_locally(to_local_experiments_string(&[])?)?;
+    client.apply_pending_experiments()?;
+
+    let targeting_helper = client.create_targeting_helper(None)?;
+    assert!(!targeting_helper.eval_jexl("'test-1' in active_experiments".to_string())?);
+    assert!(targeting_helper.eval_jexl("'test-1' in enrollments".to_string())?);
+    assert!(targeting_helper.eval_jexl("'test-1' in completed_experiments".to_string())?);
+
+    // The experiment is still completed once its enrollment has been garbage collected.
+    let db = client.db()?;
+    let mut writer = db.write()?;
+    db.get_store(StoreId::Enrollments)
+        .delete(&mut writer, "test-1")?;
+    writer.commit()?;
+    client.initialize()?;
+
+    let targeting_helper = client.create_targeting_helper(None)?;
+    assert!(!targeting_helper.eval_jexl("'test-1' in enrollments".to_string())?);
+    assert!(targeting_helper.eval_jexl("'test-1' in completed_experiments".to_string())?);
+
+    Ok(())
+}
+
 #[test]
 fn test_previous_enrollments_in_targeting() -> Result<()> {
     let metrics = TestMetrics::new();
@@ -1480,6 +1717,45 @@ fn test_feature_activation_events() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_record_exposure_once_per_enrollment() -> Result<()> {
+    let feature_id = "experimental-feature";
+    let rec_exp = get_single_feature_experiment("my-experiment", feature_id, json!({}));
+    let rec_ro = get_single_feature_rollout("my-rollout", "rollout-feature", json!({}));
+
+    let metrics = TestMetrics::new();
+    let client = with_metrics(&metrics, "coenrolling-feature")?;
+    client.set_experiments_locally(to_local_experiments_string(&[rec_exp, rec_ro])?)?;
+    client.apply_pending_experiments()?;
+
+    client.record_exposure(feature_id.to_string());
+    client.record_exposure(feature_id.to_string());
+    let exposures = metrics.get_exposures();
+    assert_eq!(1, exposures.len());
+    assert_eq!("my-experiment", &exposures[0].slug);
+    assert_eq!(Some("control"), exposures[0].branch.as_deref());
+
+    // Rollouts don't fire exposure events.
+    client.record_exposure("rollout-feature".to_string());
+    assert_eq!(1, metrics.get_exposures().len());
+
+    // A new enrollment in the same feature is recorded again.
+    let rec_exp = get_single_feature_experiment("my-experiment-2", feature_id, json!({}));
+    client.set_experiments_locally(to_local_experiments_string(&[rec_exp])?)?;
+    client.apply_pending_experiments()?;
+    client.record_exposure(feature_id.to_string());
+    client.record_exposure(feature_id.to_string());
+    let exposures = metrics.get_exposures();
+    assert_eq!(2, exposures.len());
+    assert_eq!("my-experiment-2", &exposures[1].slug);
+
+    // record_feature_exposure isn't deduplicated.
+    client.record_feature_exposure(feature_id.to_string(), None);
+    assert_eq!(3, metrics.get_exposures().len());
+
+    Ok(())
+}
+
 #[test]
 fn test_malformed_feature_events() -> Result<()> {
     let slug_exp = "my-experiment";
@@ -1565,3 +1841,1168 @@ fn test_malformed_feature_events() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_get_active_enrollments() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+    assert!(client.get_active_enrollments()?.is_empty());
+
+    let before = Utc::now().timestamp_millis();
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({}));
+    let rollout = get_single_feature_rollout("a-rollout", "b-feature", json!({}));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment, rollout.clone()])?)?;
+    client.apply_pending_experiments()?;
+    let after = Utc::now().timestamp_millis();
+
+    let enrollments = client.get_active_enrollments()?;
+    assert_eq!(enrollments.len(), 2);
+    assert_eq!(enrollments[0].slug, "a-rollout");
+    assert_eq!(enrollments[0].branch_slug, "control");
+    assert_eq!(enrollments[0].feature_ids, vec!["b-feature".to_string()]);
+    assert!(enrollments[0].is_rollout);
+    assert_eq!(enrollments[1].slug, "an-experiment");
+    assert_eq!(enrollments[1].feature_ids, vec!["a-feature".to_string()]);
+    assert!(!enrollments[1].is_rollout);
+    for enrollment in &enrollments {
+        let date = enrollment.enrollment_date.unwrap();
+        assert!(before <= date && date <= after);
+    }
+
+    // The enrollment date is kept while the client stays enrolled.
+    client.set_experiments_locally(to_local_experiments_string(&[rollout.clone()])?)?;
+    client.apply_pending_experiments()?;
+    let remaining = client.get_active_enrollments()?;
+    assert_eq!(remaining, vec![enrollments[0].clone()]);
+
+    // Pausing enrollment doesn't unenroll existing clients.
+    let paused = Experiment {
+        is_enrollment_paused: true,
+        ..rollout
+    };
+    client.set_experiments_locally(to_local_experiments_string(&[paused])?)?;
+    client.apply_pending_experiments()?;
+    let remaining = client.get_active_enrollments()?;
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].is_enrollment_paused);
+    assert_eq!(remaining[0].enrollment_date, enrollments[0].enrollment_date);
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_pending_experiments_in_background() -> Result<()> {
+    struct Callback(Mutex<Sender<Option<Vec<EnrollmentChangeEvent>>>>);
+
+    impl ApplyPendingExperimentsCallback for Callback {
+        fn on_applied(&self, events: Vec<EnrollmentChangeEvent>) {
+            self.0.lock().unwrap().send(Some(events)).unwrap();
+        }
+
+        fn on_error(&self, _message: String) {
+            self.0.lock().unwrap().send(None).unwrap();
+        }
+    }
+
+    let metrics = TestMetrics::new();
+    let tmp_dir = TempDir::new()?;
+    let client = Arc::new(NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?);
+    client.initialize()?;
+
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({}));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment])?)?;
+
+    let (sender, receiver) = channel();
+    client
+        .clone()
+        .apply_pending_experiments_in_background(Box::new(Callback(Mutex::new(sender))));
+    let events = receiver
+        .recv()
+        .unwrap()
+        .expect("experiments should be applied");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].experiment_slug, "an-experiment");
+    assert_eq!(
+        client.get_experiment_branch("an-experiment".to_string())?,
+        Some("control".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_profiles_have_separate_databases() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client_for_profile = |profile_id: Option<&str>| {
+        NimbusClient::new(
+            AppContext {
+                app_name: "fenix".to_string(),
+                app_id: "org.mozilla.fenix".to_string(),
+                channel: "nightly".to_string(),
+                profile_id: profile_id.map(str::to_string),
+                ..Default::default()
+            },
+            Default::default(),
+            tmp_dir.path(),
+            None,
+            Box::new(TestMetrics::new()),
+        )
+    };
+
+    let client = client_for_profile(Some("work"))?;
+    client.initialize()?;
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({}));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+    assert!(tmp_dir.path().join("profiles").join("work").exists());
+
+    for profile_id in [Some("personal"), None] {
+        let client = client_for_profile(profile_id)?;
+        client.initialize()?;
+        assert!(client.get_active_experiments()?.is_empty());
+    }
+
+    assert!(matches!(
+        client_for_profile(Some("../work")),
+        Err(NimbusError::InvalidPath(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_fetch_error_is_recorded() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let metrics = TestMetrics::new();
+    // The file system client fails to read a directory which doesn't exist.
+    let config = RemoteSettingsConfig {
+        server_url: Some(
+            url::Url::from_directory_path(tmp_dir.path().join("missing"))
+                .unwrap()
+                .to_string(),
+        ),
+        bucket_name: None,
+        collection_name: "nimbus-mobile-experiments".to_string(),
+    };
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        Some(config),
+        Box::new(metrics.clone()),
+    )?;
+    client.initialize()?;
+
+    assert!(client.fetch_experiments().is_err());
+    let events = metrics.get_fetch_errors();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].reason, "other");
+
+    Ok(())
+}
+
+#[test]
+fn test_feature_config_below_min_version_is_ignored() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            app_version: Some("118.0".to_string()),
+            feature_min_versions: Some(HashMap::from([
+                ("new-feature".to_string(), "119.0".to_string()),
+                ("old-feature".to_string(), "117.1".to_string()),
+            ])),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+    let experiments = [
+        get_single_feature_experiment("new-experiment", "new-feature", json!({ "a": 1 })),
+        get_single_feature_experiment("old-experiment", "old-feature", json!({ "b": 2 })),
+    ];
+    client.set_experiments_locally(to_local_experiments_string(&experiments)?)?;
+    let events = client.apply_pending_experiments()?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].experiment_slug, "old-experiment");
+    let active = client.get_active_experiments()?;
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].slug, "old-experiment");
+
+    assert_eq!(
+        client.get_feature_config_variables("new-feature".to_string())?,
+        None
+    );
+    let defaults = json!({ "a": 0 }).as_object().unwrap().clone();
+    assert_eq!(
+        client.get_feature_variables_json("new-feature".to_string(), Some(defaults.clone()))?,
+        defaults
+    );
+
+    let config = client
+        .get_feature_config_variables("old-feature".to_string())?
+        .expect("old-feature is supported");
+    assert_eq!(serde_json::from_str::<Value>(&config)?, json!({ "b": 2 }));
+
+    Ok(())
+}
+
+#[test]
+fn test_update_targeting_context() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            locale: Some("en-US".to_string()),
+            app_version: Some("120.0".to_string()),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+
+    let exp = get_targeted_experiment("us-only", "region == 'US'");
+    client.set_experiments_locally(to_local_experiments_string(&[exp])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+
+    // Unchanged attributes keep the client enrolled.
+    let events = client.update_targeting_context(None, Some("121.0".to_string()))?;
+    assert!(events.is_empty());
+    let th = client.create_targeting_helper(None)?;
+    assert!(th.eval_jexl("app_version == '121.0' && language == 'en'".to_string())?);
+
+    let events = client.update_targeting_context(Some("de-DE".to_string()), None)?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].experiment_slug, "us-only");
+    assert_eq!(events[0].reason.as_deref(), Some("targeting"));
+    assert!(client.get_active_experiments()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_enrollment_dry_run() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+
+    let exp = get_targeted_experiment("test-1", "true");
+    let result = client.evaluate_enrollment_dry_run(exp.to_string(), None)?;
+    assert_eq!(result.slug(), "test-1");
+    assert_eq!(result.status(), "Enrolled");
+    assert_eq!(result.reason(), "Qualified");
+    assert!(result.branch.is_some());
+
+    let exp = get_targeted_experiment("test-2", "is_test_user");
+    let result = client.evaluate_enrollment_dry_run(exp.to_string(), None)?;
+    assert_eq!(result.status(), "NotEnrolled");
+    assert_eq!(result.reason(), "NotTargeted");
+
+    let context = json!({ "is_test_user": true }).as_object().cloned();
+    let result = client.evaluate_enrollment_dry_run(exp.to_string(), context)?;
+    assert_eq!(result.status(), "Enrolled");
+
+    // Nothing is enrolled.
+    assert!(client.get_active_experiments()?.is_empty());
+    assert!(client.get_all_experiments()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_group_id_randomization_unit() -> Result<()> {
+    let mut exp = get_targeted_experiment("household-experiment", "true");
+    exp["bucketConfig"]["randomizationUnit"] = json!("group_id");
+
+    let mut branches = HashSet::new();
+    for _ in 0..5 {
+        let tmp_dir = TempDir::new()?;
+        let client = NimbusClient::new(
+            AppContext {
+                app_name: "fenix".to_string(),
+                app_id: "org.mozilla.fenix".to_string(),
+                channel: "nightly".to_string(),
+                ..Default::default()
+            },
+            Default::default(),
+            tmp_dir.path(),
+            None,
+            Box::new(TestMetrics::new()),
+        )?;
+        client.initialize()?;
+
+        let result = client.evaluate_enrollment_dry_run(exp.to_string(), None)?;
+        assert_eq!(result.status(), "Error");
+
+        client.set_randomization_units(None, Some("household".to_string()))?;
+        client.set_experiments_locally(to_local_experiments_string(&[&exp])?)?;
+        client.apply_pending_experiments()?;
+        let branch = client.get_experiment_branch("household-experiment".to_string())?;
+        branches.insert(branch.expect("should be enrolled"));
+
+        // The group id is forgotten on a telemetry reset.
+        client.reset_telemetry_identifiers()?;
+        let result = client.evaluate_enrollment_dry_run(exp.to_string(), None)?;
+        assert_eq!(result.status(), "Error");
+    }
+    // Clients with the same group id are always given the same branch.
+    assert_eq!(branches.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_messaging_helper() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = tempfile::tempdir()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+
+    let additional_context = json!({ "page": "home" });
+    let helper = client.create_messaging_helper(additional_context.as_object().cloned())?;
+    assert!(helper.eval_jexl("app_name == 'fenix' && page == 'home'".to_string())?);
+    assert!(!helper.eval_jexl("page == 'settings'".to_string())?);
+    assert!(helper.eval_jexl("unknown_identifier".to_string()).is_err());
+
+    assert_eq!(
+        helper.string_format("{app_name}://{page}".to_string(), None),
+        "fenix://home"
+    );
+    let uuid = helper.get_uuid("{uuid}".to_string());
+    assert!(uuid.is_some());
+    assert_eq!(
+        helper.string_format("{uuid}".to_string(), uuid.clone()),
+        uuid.unwrap()
+    );
+    assert_eq!(helper.get_uuid("{page}".to_string()), None);
+
+    // Results are cached until the cache is cleared.
+    let trigger = "'app.foregrounded'|eventSum('Days', 1, 0) > 0".to_string();
+    assert!(!helper.eval_jexl(trigger.clone())?);
+    client.record_event("app.foregrounded".to_string(), 1)?;
+    assert!(!helper.eval_jexl(trigger.clone())?);
+    helper.clear_cache();
+    assert!(helper.eval_jexl(trigger)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_signature_verification() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let app_context = AppContext {
+        app_name: "fenix".to_string(),
+        app_id: "org.mozilla.fenix".to_string(),
+        channel: "nightly".to_string(),
+        ..Default::default()
+    };
+    let create_client = |server_url: Option<&str>| {
+        NimbusClient::new(
+            app_context.clone(),
+            Default::default(),
+            tmp_dir.path(),
+            Some(RemoteSettingsConfig {
+                server_url: server_url.map(str::to_string),
+                bucket_name: None,
+                collection_name: "nimbus-mobile-experiments".to_string(),
+            }),
+            Box::new(TestMetrics::new()),
+        )
+    };
+
+    // Only the production server's collections are verified, and only with the `signatures`
+    // feature.
+    let client = create_client(None)?;
+    client.initialize()?;
+    assert!(client.is_signature_verification_enabled()?);
+    assert_eq!(
+        client.should_verify_signatures()?,
+        cfg!(feature = "signatures")
+    );
+    let client = create_client(Some("https://firefox.settings.services.mozilla.com"))?;
+    assert_eq!(
+        client.should_verify_signatures()?,
+        cfg!(feature = "signatures")
+    );
+    let client = create_client(Some("https://settings-cdn.stage.mozaws.net"))?;
+    assert!(!client.should_verify_signatures()?);
+
+    // The kill switch is persisted.
+    let client = create_client(None)?;
+    client.set_signature_verification_enabled(false)?;
+    let client = create_client(None)?;
+    assert!(!client.is_signature_verification_enabled()?);
+    assert!(!client.should_verify_signatures()?);
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_validators_are_cleared() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = tempfile::tempdir()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+
+    let db = client.db()?;
+    let validators = CacheValidators {
+        etag: Some("\"1000\"".to_string()),
+        last_modified: None,
+    };
+    let set_validators = || -> Result<()> {
+        let mut writer = db.write()?;
+        db.get_store(StoreId::Meta)
+            .put(&mut writer, DB_KEY_CACHE_VALIDATORS, &validators)?;
+        writer.commit()?;
+        Ok(())
+    };
+    let get_validators = || -> Result<CacheValidators> {
+        Ok(db
+            .get(StoreId::Meta, DB_KEY_CACHE_VALIDATORS)?
+            .unwrap_or_default())
+    };
+
+    // Experiments set locally are replaced by the next fetch, even if the server's haven't
+    // changed.
+    set_validators()?;
+    let exp = get_single_feature_experiment("exp", "feature", json!({}));
+    client.set_experiments_locally(to_local_experiments_string(&[exp])?)?;
+    assert_eq!(get_validators()?, CacheValidators::default());
+
+    // The validators of one collection don't apply to another.
+    set_validators()?;
+    client.set_preview_collection(true)?;
+    assert_eq!(get_validators()?, CacheValidators::default());
+
+    Ok(())
+}
+
+#[test]
+fn test_kill_switches() -> Result<()> {
+    let metrics = TestMetrics::new();
+    let tmp_dir = tempfile::tempdir()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(metrics),
+    )?;
+    client.initialize()?;
+
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 1 }));
+    let kill_switch = json!({
+        "id": "disable-a-feature",
+        "killSwitch": { "featureId": "a-feature" },
+    });
+    let recipes = serde_json::to_string(&json!({ "data": [rollout, kill_switch] }))?;
+    client.set_experiments_locally(recipes)?;
+
+    // Kill switches take effect when the experiments are applied.
+    assert!(!client.is_feature_disabled("a-feature".to_string())?);
+    client.apply_pending_experiments()?;
+    assert!(client.is_feature_disabled("a-feature".to_string())?);
+    assert!(!client.is_feature_disabled("b-feature".to_string())?);
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "enabled": false }).to_string())
+    );
+    let enrollment = client
+        .get_enrollment_by_feature("a-feature".to_string())?
+        .unwrap();
+    assert_eq!(enrollment.branch, None);
+
+    // The kill switches are persisted.
+    drop(client);
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+    assert!(client.is_feature_disabled("a-feature".to_string())?);
+
+    // Removing the kill switch from the collection restores the rollout's configuration.
+    client.set_experiments_locally(to_local_experiments_string(&[rollout])?)?;
+    client.apply_pending_experiments()?;
+    assert!(!client.is_feature_disabled("a-feature".to_string())?);
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_feature_configs_are_served_before_initialize() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let create_client = || {
+        NimbusClient::new(
+            AppContext {
+                app_name: "fenix".to_string(),
+                app_id: "org.mozilla.fenix".to_string(),
+                channel: "nightly".to_string(),
+                ..Default::default()
+            },
+            Default::default(),
+            tmp_dir.path(),
+            None,
+            Box::new(TestMetrics::new()),
+        )
+    };
+
+    // Before the first run, there is nothing to serve until the database is ready.
+    let client = create_client()?;
+    assert!(matches!(
+        client.get_feature_config_variables("a-feature".to_string()),
+        Err(NimbusError::DatabaseNotReady)
+    ));
+    client.initialize()?;
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 1 }));
+    client.set_experiments_locally(to_local_experiments_string(&[rollout])?)?;
+    client.apply_pending_experiments()?;
+    drop(client);
+
+    // On the next run, the configurations are served without opening the database.
+    let client = create_client()?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+    assert_eq!(
+        client.get_feature_config_variables("b-feature".to_string())?,
+        None
+    );
+    assert!(client.db.get().is_none());
+
+    // Once initialized, they come from the database. The snapshot is only rewritten when
+    // they change.
+    client.initialize()?;
+    let snapshot_path = tmp_dir.path().join(FEATURE_CONFIG_SNAPSHOT_FILE);
+    std::fs::remove_file(&snapshot_path)?;
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 1 }));
+    client.set_experiments_locally(to_local_experiments_string(&[rollout])?)?;
+    client.apply_pending_experiments()?;
+    assert!(!snapshot_path.exists());
+
+    client.set_feature_config_for_testing(
+        "a-feature".to_string(),
+        json!({ "a": 2 }).as_object().cloned().unwrap(),
+    )?;
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 2 }).to_string())
+    );
+    assert!(snapshot_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_install_and_update_dates_without_app_plumbing() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    // The app supplies neither an installation date, a home directory nor a version.
+    let create_client = || {
+        NimbusClient::new(
+            AppContext::default(),
+            Default::default(),
+            tmp_dir.path(),
+            None,
+            Box::new(TestMetrics::new()),
+        )
+    };
+
+    let client = create_client()?;
+    client.initialize()?;
+    let targeting_attributes = client.get_targeting_attributes();
+    assert_eq!(targeting_attributes.days_since_install, Some(0));
+    assert_eq!(targeting_attributes.days_since_update, Some(0));
+
+    // The first run is persisted as the installation and update dates.
+    let db = client.db()?;
+    let installation_date: Option<DateTime<Utc>> =
+        db.get(StoreId::Meta, DB_KEY_INSTALLATION_DATE)?;
+    let update_date: Option<DateTime<Utc>> = db.get(StoreId::Meta, DB_KEY_UPDATE_DATE)?;
+    assert!(installation_date.is_some());
+    assert!(update_date.is_some());
+
+    let mut writer = db.write()?;
+    let store = db.get_store(StoreId::Meta);
+    store.put(
+        &mut writer,
+        DB_KEY_INSTALLATION_DATE,
+        &(Utc::now() - Duration::days(5)),
+    )?;
+    store.put(
+        &mut writer,
+        DB_KEY_UPDATE_DATE,
+        &(Utc::now() - Duration::days(2)),
+    )?;
+    writer.commit()?;
+    drop(client);
+
+    // So later runs count the days from it.
+    let client = create_client()?;
+    client.initialize()?;
+    let targeting_attributes = client.get_targeting_attributes();
+    assert_eq!(targeting_attributes.days_since_install, Some(5));
+    assert_eq!(targeting_attributes.days_since_update, Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_locale_canonicalization_and_region() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            locale: Some("zh-hant_tw".to_string()),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let th = client.create_targeting_helper(None)?;
+    assert!(th.eval_jexl("locale == 'zh-Hant-TW'".to_string())?);
+    assert!(th.eval_jexl("language == 'zh' && script == 'Hant' && region == 'TW'".to_string())?);
+    assert!(th.eval_jexl("'zh-Hant' in locale_fallbacks".to_string())?);
+
+    let exp = get_targeted_experiment("us-only", "region == 'US'");
+    client.set_experiments_locally(to_local_experiments_string(&[exp])?)?;
+    client.apply_pending_experiments()?;
+    assert!(client.get_active_experiments()?.is_empty());
+
+    // The region set by the app takes precedence over the locale's, even when the locale
+    // changes.
+    let events = client.set_region(Some("us".to_string()))?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+    client.update_targeting_context(Some("en-GB".to_string()), None)?;
+    assert_eq!(client.get_active_experiments()?.len(), 1);
+
+    // Without it, the locale's region is used again.
+    let events = client.set_region(None)?;
+    assert_eq!(events.len(), 1);
+    assert!(client.get_active_experiments()?.is_empty());
+    let th = client.create_targeting_helper(None)?;
+    assert!(th.eval_jexl("region == 'GB'".to_string())?);
+
+    Ok(())
+}
+
+#[test]
+fn test_get_enrollment_statuses() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let experiments = [
+        serde_json::to_value(get_single_feature_experiment(
+            "exp-a",
+            "about_welcome",
+            json!({ "text": "a" }),
+        ))?,
+        serde_json::to_value(get_single_feature_experiment(
+            "exp-b",
+            "about_welcome",
+            json!({ "text": "b" }),
+        ))?,
+        get_targeted_experiment("targeted-out", "false"),
+    ];
+    client.set_experiments_locally(to_local_experiments_string(&experiments)?)?;
+    client.apply_pending_experiments()?;
+
+    let statuses = client.get_enrollment_statuses()?;
+    let slugs: Vec<_> = statuses
+        .iter()
+        .map(|s| s.slug.as_deref().unwrap())
+        .collect();
+    assert_eq!(slugs, vec!["exp-a", "exp-b", "targeted-out"]);
+
+    // Only one of the experiments on the same feature is enrolled, and the other says which.
+    let (enrolled, conflicted) = if statuses[0].status.as_deref() == Some("Enrolled") {
+        (&statuses[0], &statuses[1])
+    } else {
+        (&statuses[1], &statuses[0])
+    };
+    assert_eq!(enrolled.status.as_deref(), Some("Enrolled"));
+    assert_eq!(enrolled.branch.as_deref(), Some("control"));
+    assert_eq!(conflicted.status.as_deref(), Some("NotEnrolled"));
+    assert_eq!(conflicted.reason.as_deref(), Some("FeatureConflict"));
+    assert_eq!(conflicted.conflict_slug, enrolled.slug);
+
+    assert_eq!(statuses[2].status.as_deref(), Some("NotEnrolled"));
+    assert_eq!(statuses[2].reason.as_deref(), Some("NotTargeted"));
+    assert_eq!(statuses[2].conflict_slug, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_rollback_last_apply() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+    assert!(client.rollback_last_apply()?.is_empty());
+
+    let good = get_single_feature_experiment("good", "a-feature", json!({ "a": 1 }));
+    client.set_experiments_locally(to_local_experiments_string(&[good])?)?;
+    client.apply_pending_experiments()?;
+
+    let bad = get_single_feature_experiment("bad", "a-feature", json!({ "a": 2 }));
+    client.set_experiments_locally(to_local_experiments_string(&[bad])?)?;
+    client.apply_pending_experiments()?;
+    assert_eq!(client.get_experiment_branch("good".to_string())?, None);
+    assert_eq!(
+        client.get_experiment_branch("bad".to_string())?,
+        Some("control".to_string())
+    );
+
+    // Applying without pending experiments doesn't replace the snapshot.
+    client.apply_pending_experiments()?;
+
+    let events = client.rollback_last_apply()?;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].experiment_slug, "bad");
+    assert_eq!(events[0].reason.as_deref(), Some("rollback"));
+    assert_eq!(events[0].change, EnrollmentChangeEventType::Unenrollment);
+    assert_eq!(events[1].experiment_slug, "good");
+    assert_eq!(events[1].change, EnrollmentChangeEventType::Enrollment);
+    assert_eq!(
+        client.get_experiment_branch("good".to_string())?,
+        Some("control".to_string())
+    );
+    assert_eq!(client.get_experiment_branch("bad".to_string())?, None);
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+
+    // The active experiments used for targeting are rolled back too.
+    let targeting_helper = client.create_targeting_helper(None)?;
+    assert!(targeting_helper.eval_jexl("'good' in active_experiments".to_string())?);
+    assert!(!targeting_helper.eval_jexl("'bad' in active_experiments".to_string())?);
+
+    // Only the last apply can be rolled back.
+    assert!(client.rollback_last_apply()?.is_empty());
+    assert_eq!(
+        client.get_experiment_branch("good".to_string())?,
+        Some("control".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_initial_experiments() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let write_file = |name: &str, experiment: Experiment| -> Result<String> {
+        let path = tmp_dir.path().join(name);
+        std::fs::write(&path, to_local_experiments_string(&[experiment])?)?;
+        Ok(path.to_string_lossy().into_owned())
+    };
+
+    let initial = write_file(
+        "initial_experiments.json",
+        get_single_feature_experiment("initial", "a-feature", json!({ "a": 1 })),
+    )?;
+    let events = client.apply_initial_experiments(initial)?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        client.get_experiment_branch("initial".to_string())?,
+        Some("control".to_string())
+    );
+
+    // The file is only used on the first run.
+    let later = write_file(
+        "later_experiments.json",
+        get_single_feature_experiment("later", "b-feature", json!({ "b": 1 })),
+    )?;
+    assert!(client.apply_initial_experiments(later)?.is_empty());
+    assert_eq!(client.get_experiment_branch("later".to_string())?, None);
+    assert_eq!(
+        client.get_experiment_branch("initial".to_string())?,
+        Some("control".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_new_with_bucketing_seed() -> Result<()> {
+    // The recipe buckets on the user_id, which the app hasn't set, so the client would
+    // otherwise fail to enroll.
+    let mut experiment = get_targeted_experiment("seeded", "true");
+    experiment["bucketConfig"]["randomizationUnit"] = json!("user_id");
+
+    for (seed, branch) in [("seed-a", "treatment"), ("seed-d", "control")] {
+        for _ in 0..2 {
+            let tmp_dir = TempDir::new()?;
+            let client = NimbusClient::new_with_bucketing_seed(
+                AppContext {
+                    app_name: "fenix".to_string(),
+                    app_id: "org.mozilla.fenix".to_string(),
+                    channel: "nightly".to_string(),
+                    ..Default::default()
+                },
+                Default::default(),
+                tmp_dir.path(),
+                None,
+                Box::new(TestMetrics::new()),
+                seed.to_string(),
+            )?;
+            client.initialize()?;
+            client.set_experiments_locally(to_local_experiments_string(&[&experiment])?)?;
+            client.apply_pending_experiments()?;
+
+            // Every client with the same seed lands in the same branch, whatever its nimbus_id.
+            assert_eq!(
+                client.get_experiment_branch("seeded".to_string())?,
+                Some(branch.to_string())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_export_and_import_enrollment_state() -> Result<()> {
+    let app_context = AppContext {
+        app_name: "fenix".to_string(),
+        app_id: "org.mozilla.fenix".to_string(),
+        channel: "nightly".to_string(),
+        ..Default::default()
+    };
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        app_context.clone(),
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({ "a": 1 }));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment])?)?;
+    client.apply_pending_experiments()?;
+    let exported = client.export_enrollment_state()?;
+
+    let other_dir = TempDir::new()?;
+    let other = NimbusClient::new(
+        app_context,
+        Default::default(),
+        other_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    other.initialize()?;
+    assert_ne!(other.nimbus_id()?, client.nimbus_id()?);
+    assert!(other.get_active_enrollments()?.is_empty());
+
+    // The identifiers aren't exported, so the other client keeps its own.
+    assert!(!exported.contains(&client.nimbus_id()?.to_string()));
+    let other_nimbus_id = other.nimbus_id()?;
+    other.import_enrollment_state(exported.clone())?;
+    assert_eq!(other.nimbus_id()?, other_nimbus_id);
+    assert_eq!(
+        other.get_active_enrollments()?,
+        client.get_active_enrollments()?
+    );
+    assert_eq!(
+        other.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({ "a": 1 }).to_string())
+    );
+    assert_eq!(other.export_enrollment_state()?, exported);
+
+    assert!(other.import_enrollment_state("{}".to_string()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_holdback_branch() -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let experiment = Experiment {
+        holdback_branch: Some("control".to_string()),
+        ..get_single_feature_experiment("an-experiment", "a-feature", json!({ "a": 1 }))
+    };
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 2 }));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment, rollout])?)?;
+    client.apply_pending_experiments()?;
+
+    // Neither the experiment's branch nor the rollout configure the feature.
+    assert_eq!(
+        client.get_feature_config_variables("a-feature".to_string())?,
+        Some(json!({}).to_string())
+    );
+
+    let enrollments = client.get_active_enrollments()?;
+    assert_eq!(enrollments.len(), 2);
+    assert_eq!(enrollments[0].slug, "a-rollout");
+    assert!(!enrollments[0].is_holdback);
+    assert_eq!(enrollments[1].slug, "an-experiment");
+    assert!(enrollments[1].is_holdback);
+
+    let statuses = client.get_enrollment_statuses()?;
+    assert_eq!(statuses[0].slug.as_deref(), Some("a-rollout"));
+    assert_eq!(statuses[0].is_holdback, Some(false));
+    assert_eq!(statuses[1].slug.as_deref(), Some("an-experiment"));
+    assert_eq!(statuses[1].is_holdback, Some(true));
+
+    Ok(())
+}
+
+#[test]
+fn test_get_feature_variables_json_merges_in_precedence_order() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({ "a": 1 }));
+    let rollout = get_single_feature_rollout("a-rollout", "a-feature", json!({ "a": 2, "b": 2 }));
+    client.set_experiments_locally(to_local_experiments_string(&[experiment, rollout])?)?;
+    client.apply_pending_experiments()?;
+
+    let defaults = json!({ "a": 0, "b": 0, "c": 0 }).as_object().cloned();
+    assert_eq!(
+        Value::Object(
+            client.get_feature_variables_json("a-feature".to_string(), defaults.clone())?
+        ),
+        json!({ "a": 1, "b": 2, "c": 0 })
+    );
+
+    // Features which are not enrolled get the defaults.
+    assert_eq!(
+        Value::Object(client.get_feature_variables_json("b-feature".to_string(), defaults)?),
+        json!({ "a": 0, "b": 0, "c": 0 })
+    );
+    assert_eq!(
+        Value::Object(client.get_feature_variables_json("b-feature".to_string(), None)?),
+        json!({})
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_enrollment_change_observer() -> Result<()> {
+    struct Observer(Mutex<Sender<Vec<String>>>);
+
+    impl EnrollmentChangeObserver for Observer {
+        fn on_enrollments_changed(&self, feature_ids: Vec<String>) {
+            self.0.lock().unwrap().send(feature_ids).unwrap();
+        }
+    }
+
+    let tmp_dir = TempDir::new()?;
+    let client = NimbusClient::new(
+        AppContext {
+            app_name: "fenix".to_string(),
+            app_id: "org.mozilla.fenix".to_string(),
+            channel: "nightly".to_string(),
+            ..Default::default()
+        },
+        Default::default(),
+        tmp_dir.path(),
+        None,
+        Box::new(TestMetrics::new()),
+    )?;
+    client.initialize()?;
+
+    let (sender, receiver) = channel();
+    client.register_enrollment_change_observer(Box::new(Observer(Mutex::new(sender))));
+    let timeout = std::time::Duration::from_secs(5);
+
+    let experiment = get_single_feature_experiment("an-experiment", "a-feature", json!({}));
+    let rollout = get_single_feature_rollout("a-rollout", "b-feature", json!({ "b": 1 }));
+    let experiments = to_local_experiments_string(&[experiment, rollout])?;
+    client.set_experiments_locally(experiments.clone())?;
+    client.apply_pending_experiments()?;
+    assert_eq!(
+        receiver.recv_timeout(timeout).unwrap(),
+        vec!["a-feature".to_string(), "b-feature".to_string()]
+    );
+
+    // Only the features affected by a change are reported.
+    client.opt_out("an-experiment".to_string())?;
+    assert_eq!(
+        receiver.recv_timeout(timeout).unwrap(),
+        vec!["a-feature".to_string()]
+    );
+
+    // Nothing is reported when no feature changes.
+    client.set_experiments_locally(experiments)?;
+    client.apply_pending_experiments()?;
+    assert!(receiver
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .is_err());
+
+    client.unregister_enrollment_change_observer();
+    client.set_feature_config_for_testing(
+        "a-feature".to_string(),
+        json!({ "a": 1 }).as_object().cloned().unwrap(),
+    )?;
+    assert!(receiver
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .is_err());
+
+    Ok(())
+}
@@ -5,7 +5,6 @@
 use crate::{
     enrollment::ExperimentEnrollment, error::Result, stateful::persistence::*, Experiment,
 };
-use rkv::StoreOptions;
 use serde_json::json;
 use std::fs;
 
@@ -13,12 +12,9 @@ use std::fs;
 fn test_db_upgrade_no_version() -> Result<()> {
     let tmp_dir = tempfile::tempdir()?;
 
-    let rkv = Database::open_rkv(&tmp_dir)?;
-    let _meta_store = rkv.open_single("meta", StoreOptions::create())?;
-    let experiment_store =
-        SingleStore::new(rkv.open_single("experiments", StoreOptions::create())?);
-    let enrollment_store =
-        SingleStore::new(rkv.open_single("enrollments", StoreOptions::create())?);
+    let rkv = RkvStorage::open(&tmp_dir)?;
+    let experiment_store = SingleStore::new(StoreId::Experiments);
+    let enrollment_store = SingleStore::new(StoreId::Enrollments);
     let mut writer = rkv.write()?;
     enrollment_store.put(&mut writer, "foo", &"bar".to_owned())?;
     experiment_store.put(&mut writer, "bobo", &"tron".to_owned())?;
@@ -36,12 +32,10 @@ fn test_db_upgrade_no_version() -> Result<()> {
 fn test_db_upgrade_unknown_version() -> Result<()> {
     let tmp_dir = tempfile::tempdir()?;
 
-    let rkv = Database::open_rkv(&tmp_dir)?;
-    let meta_store = SingleStore::new(rkv.open_single("meta", StoreOptions::create())?);
-    let experiment_store =
-        SingleStore::new(rkv.open_single("experiments", StoreOptions::create())?);
-    let enrollment_store =
-        SingleStore::new(rkv.open_single("enrollments", StoreOptions::create())?);
+    let rkv = RkvStorage::open(&tmp_dir)?;
+    let meta_store = SingleStore::new(StoreId::Meta);
+    let experiment_store = SingleStore::new(StoreId::Experiments);
+    let enrollment_store = SingleStore::new(StoreId::Enrollments);
     let mut writer = rkv.write()?;
     meta_store.put(&mut writer, DB_KEY_DB_VERSION, &u16::MAX)?;
     enrollment_store.put(&mut writer, "foo", &"bar".to_owned())?;
@@ -55,6 +49,145 @@ fn test_db_upgrade_unknown_version() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_export_and_import_state() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let db = Database::new(&tmp_dir)?;
+    let mut writer = db.write()?;
+    db.get_store(StoreId::Experiments)
+        .put(&mut writer, "bobo", &"tron".to_owned())?;
+    db.get_store(StoreId::Enrollments)
+        .put(&mut writer, "foo", &"bar".to_owned())?;
+    db.get_store(StoreId::Updates)
+        .put(&mut writer, "pending", &"update".to_owned())?;
+    writer.commit()?;
+
+    let state = db.export_state(&db.read()?)?;
+    assert_eq!(state.meta.get(DB_KEY_DB_VERSION), Some(&json!(DB_VERSION)));
+    assert_eq!(state.experiments.get("bobo"), Some(&json!("tron")));
+    assert_eq!(state.enrollments.get("foo"), Some(&json!("bar")));
+
+    let other_dir = tempfile::tempdir()?;
+    let other = Database::new(&other_dir)?;
+    let mut writer = other.write()?;
+    other
+        .get_store(StoreId::Enrollments)
+        .put(&mut writer, "old", &"enrollment".to_owned())?;
+    other.import_state(&mut writer, &state)?;
+    writer.commit()?;
+    assert_eq!(other.export_state(&other.read()?)?, state);
+    assert!(other.collect_all::<String>(StoreId::Updates)?.is_empty());
+
+    // State from another version of the database can't be imported.
+    let mut old_state = state;
+    old_state
+        .meta
+        .insert(DB_KEY_DB_VERSION.to_string(), json!(DB_VERSION - 1));
+    let mut writer = other.write()?;
+    assert!(other.import_state(&mut writer, &old_state).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dropped_writer_discards_its_changes() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let db = Database::new(&tmp_dir)?;
+    let store = db.get_store(StoreId::Experiments);
+
+    let mut writer = db.write()?;
+    store.put(&mut writer, "bobo", &"tron".to_owned())?;
+    assert_eq!(store.get(&writer, "bobo")?, Some("tron".to_owned()));
+    // The changes aren't visible to readers until they are committed.
+    assert_eq!(db.get::<String>(StoreId::Experiments, "bobo")?, None);
+    drop(writer);
+    assert_eq!(db.get::<String>(StoreId::Experiments, "bobo")?, None);
+
+    let mut writer = db.write()?;
+    store.put(&mut writer, "bobo", &"tron".to_owned())?;
+    writer.commit()?;
+    assert_eq!(
+        db.get::<String>(StoreId::Experiments, "bobo")?,
+        Some("tron".to_owned())
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn test_sqlite_storage_imports_rkv_database() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let enrollment_store = SingleStore::new(StoreId::Enrollments);
+
+    let rkv = RkvStorage::open(&tmp_dir)?;
+    let mut writer = rkv.write()?;
+    SingleStore::new(StoreId::Meta).put(&mut writer, DB_KEY_DB_VERSION, &DB_VERSION)?;
+    enrollment_store.put(&mut writer, "foo", &"bar".to_owned())?;
+    writer.commit()?;
+    drop(rkv);
+
+    let db = Database::with_storage(Box::new(SqliteStorage::open(&tmp_dir)?))?;
+    assert_eq!(db.get(StoreId::Meta, DB_KEY_DB_VERSION)?, Some(DB_VERSION));
+    assert_eq!(
+        db.get::<String>(StoreId::Enrollments, "foo")?,
+        Some("bar".to_owned())
+    );
+    assert!(!tmp_dir.path().join(RKV_DB_DIR).exists());
+    drop(db);
+
+    // An Rkv database left behind isn't imported over the newer records.
+    let rkv = RkvStorage::open(&tmp_dir)?;
+    let mut writer = rkv.write()?;
+    enrollment_store.put(&mut writer, "foo", &"stale".to_owned())?;
+    writer.commit()?;
+    drop(rkv);
+
+    let db = Database::new(&tmp_dir)?;
+    assert_eq!(
+        db.get::<String>(StoreId::Enrollments, "foo")?,
+        Some("bar".to_owned())
+    );
+    assert!(!tmp_dir.path().join(RKV_DB_DIR).exists());
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn test_sqlite_storage_readers_are_transactions() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let storage = SqliteStorage::open(&tmp_dir)?;
+    let store = SingleStore::new(StoreId::Experiments);
+
+    let mut writer = storage.write()?;
+    store.put(&mut writer, "bobo", &"tron".to_owned())?;
+    writer.commit()?;
+
+    let reader = storage.read()?;
+    assert_eq!(store.get(&reader, "bobo")?, Some("tron".to_owned()));
+    // Another reader can be begun while the first is open.
+    let other_reader = storage.read()?;
+
+    let mut writer = storage.write()?;
+    store.put(&mut writer, "bobo", &"tronic".to_owned())?;
+    writer.commit()?;
+
+    // The first reader still sees the records as they were when it first read them.
+    assert_eq!(store.get(&reader, "bobo")?, Some("tron".to_owned()));
+    assert_eq!(store.get(&other_reader, "bobo")?, Some("tronic".to_owned()));
+    drop(reader);
+    drop(other_reader);
+    assert_eq!(
+        store.get(&storage.read()?, "bobo")?,
+        Some("tronic".to_owned())
+    );
+
+    Ok(())
+}
+
+// The SQLite database replaces a corrupt database itself, without a backup.
+#[cfg(not(feature = "sqlite-storage"))]
 #[test]
 fn test_corrupt_db() -> Result<()> {
     let tmp_dir = tempfile::tempdir()?;
@@ -72,10 +205,26 @@ fn test_corrupt_db() -> Result<()> {
     let garbage_len = garbage.len() as u64;
     fs::write(&db_file, garbage)?;
     assert_eq!(fs::metadata(&db_file)?.len(), garbage_len);
-    // Opening the DB should delete the corrupt file and replace it.
+    // Opening the DB should back up the corrupt file and replace it.
     Database::new(&tmp_dir)?;
     // Old contents should be removed and replaced with actual data.
     assert_ne!(fs::metadata(&db_file)?.len(), garbage_len);
+    let backup_file = tmp_dir
+        .path()
+        .join(CORRUPT_DB_BACKUP_DIR)
+        .join(db_file.file_name().unwrap());
+    assert_eq!(fs::read(backup_file)?, garbage);
+
+    // A database which is corrupted again replaces the earlier backup.
+    let garbage = b"Still not a database!";
+    fs::write(&db_file, garbage)?;
+    Database::new(&tmp_dir)?;
+    assert_ne!(fs::read(&db_file)?, garbage);
+    let backup_file = tmp_dir
+        .path()
+        .join(CORRUPT_DB_BACKUP_DIR)
+        .join(db_file.file_name().unwrap());
+    assert_eq!(fs::read(backup_file)?, garbage);
     Ok(())
 }
 
@@ -525,12 +674,10 @@ fn create_old_database(
 ) -> Result<()> {
     let _ = env_logger::try_init();
 
-    let rkv = Database::open_rkv(tmp_dir)?;
-    let meta_store = SingleStore::new(rkv.open_single("meta", StoreOptions::create())?);
-    let experiment_store =
-        SingleStore::new(rkv.open_single("experiments", StoreOptions::create())?);
-    let enrollment_store =
-        SingleStore::new(rkv.open_single("enrollments", StoreOptions::create())?);
+    let rkv = RkvStorage::open(tmp_dir)?;
+    let meta_store = SingleStore::new(StoreId::Meta);
+    let experiment_store = SingleStore::new(StoreId::Experiments);
+    let enrollment_store = SingleStore::new(StoreId::Enrollments);
     let mut writer = rkv.write()?;
 
     meta_store.put(&mut writer, "db_version", &old_version)?;
@@ -768,3 +915,40 @@ fn test_migrate_db_v1_with_valid_and_invalid_records_to_db_v2() -> Result<()> {
 
     Ok(())
 }
+
+/// Every older version of the database should be upgraded to the current version,
+/// running each migration in turn, and keep the records which are still valid.
+#[test]
+fn test_upgrade_from_every_old_version() -> Result<()> {
+    let _ = env_logger::try_init();
+    let experiments = db_v1_experiments_with_non_empty_features();
+    let enrollments = get_db_v1_enrollments_with_non_empty_features();
+
+    for old_version in 1..DB_VERSION {
+        let tmp_dir = tempfile::tempdir()?;
+        create_old_database(&tmp_dir, old_version, &experiments, &enrollments)?;
+
+        let db = Database::new(&tmp_dir)?;
+        assert_eq!(db.get(StoreId::Meta, DB_KEY_DB_VERSION)?, Some(DB_VERSION));
+        assert_eq!(
+            db.collect_all::<Experiment>(StoreId::Experiments)?.len(),
+            experiments.len()
+        );
+        assert_eq!(
+            db.collect_all::<ExperimentEnrollment>(StoreId::Enrollments)?
+                .len(),
+            enrollments.len()
+        );
+        drop(db);
+
+        // Opening the upgraded database again shouldn't change anything.
+        let db = Database::new(&tmp_dir)?;
+        assert_eq!(db.get(StoreId::Meta, DB_KEY_DB_VERSION)?, Some(DB_VERSION));
+        assert_eq!(
+            db.collect_all::<Experiment>(StoreId::Experiments)?.len(),
+            experiments.len()
+        );
+    }
+
+    Ok(())
+}
@@ -11,7 +11,7 @@ use crate::{
     error::Result,
     tests::helpers::{
         get_ios_rollout_experiment, get_multi_feature_experiment, get_single_feature_experiment,
-        get_test_experiments, no_coenrolling_features,
+        get_single_feature_rollout, get_test_experiments, no_coenrolling_features,
     },
     AppContext, AvailableRandomizationUnits, Branch, BucketConfig, Experiment, FeatureConfig,
     NimbusTargetingHelper, TargetingAttributes,
@@ -953,6 +953,89 @@ fn test_rollout_unenrolls_then_reenrolls_when_bucketing_changes() -> Result<()>
     Ok(())
 }
 
+#[test]
+fn test_rollout_reenrolls_after_global_opt_out_and_opt_in() -> Result<()> {
+    let (_, app_ctx, aru) = local_ctx();
+    let th = app_ctx.into();
+    let ids = no_coenrolling_features();
+    let evolver = enrollment_evolver(&th, &aru, &ids);
+
+    let slug = "my-rollout";
+    let recipes = [get_bucketed_rollout(slug, 10_000)];
+
+    let (enrollments, _) = evolver.evolve_enrollments::<Experiment>(true, &[], &recipes, &[])?;
+    assert!(matches!(
+        &enrollments[0].status,
+        EnrollmentStatus::Enrolled { .. }
+    ));
+
+    // Opting out of everything unenrolls us from the rollout…
+    let (enrollments, events) =
+        evolver.evolve_enrollments::<Experiment>(false, &recipes, &recipes, &enrollments)?;
+    assert!(matches!(
+        &enrollments[0].status,
+        EnrollmentStatus::NotEnrolled {
+            reason: NotEnrolledReason::OptOut
+        }
+    ));
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].change,
+        EnrollmentChangeEventType::Disqualification
+    );
+
+    // … and it stays that way while we're opted out.
+    let (enrollments, events) =
+        evolver.evolve_enrollments::<Experiment>(false, &recipes, &recipes, &enrollments)?;
+    assert!(matches!(
+        &enrollments[0].status,
+        EnrollmentStatus::NotEnrolled { .. }
+    ));
+    assert!(events.is_empty());
+
+    // Unlike an experiment, we enroll again when we opt back in.
+    let (enrollments, events) =
+        evolver.evolve_enrollments::<Experiment>(true, &recipes, &recipes, &enrollments)?;
+    assert!(matches!(
+        &enrollments[0].status,
+        EnrollmentStatus::Enrolled { .. }
+    ));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].change, EnrollmentChangeEventType::Enrollment);
+
+    Ok(())
+}
+
+#[test]
+fn test_rollout_stays_disqualified_after_explicit_opt_out() -> Result<()> {
+    let (_, app_ctx, aru) = local_ctx();
+    let th = app_ctx.into();
+    let ids = no_coenrolling_features();
+    let evolver = enrollment_evolver(&th, &aru, &ids);
+
+    let recipes = [get_bucketed_rollout("my-rollout", 10_000)];
+    let (enrollments, _) = evolver.evolve_enrollments::<Experiment>(true, &[], &recipes, &[])?;
+
+    let enrollments = [enrollments[0].on_explicit_opt_out(&mut vec![])];
+    for is_user_participating in [false, true] {
+        let (next, _) = evolver.evolve_enrollments::<Experiment>(
+            is_user_participating,
+            &recipes,
+            &recipes,
+            &enrollments,
+        )?;
+        assert!(matches!(
+            &next[0].status,
+            EnrollmentStatus::Disqualified {
+                reason: DisqualifiedReason::OptOut,
+                ..
+            }
+        ));
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_experiment_does_not_reenroll_from_disqualified_not_selected_or_not_targeted() -> Result<()>
 {
@@ -1892,6 +1975,40 @@ fn test_map_features_by_feature_id_with_coenrollment() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_map_coenrolled_features_by_feature_id() -> Result<()> {
+    let ro1 = get_single_feature_rollout("ro1", "coenrolling", json!({ "a": 1 }));
+    let exp1 = get_single_feature_experiment("exp1", "colliding", json!({"x": 1 }));
+    let exp2 = get_single_feature_experiment("exp2", "coenrolling", json!({ "a": 2, "b": 2 }));
+    let exp3 = get_single_feature_experiment("exp3", "coenrolling", json!({ "b": 3 }));
+
+    let ids = HashSet::from(["coenrolling"]);
+    let exps = [ro1, exp1, exp2, exp3];
+    let enrollments = [
+        ExperimentEnrollment::enrolled("ro1"),
+        ExperimentEnrollment::enrolled("exp1"),
+        ExperimentEnrollment::enrolled("exp2"),
+        ExperimentEnrollment::not_enrolled("exp3"),
+    ];
+
+    let observed = map_coenrolled_features_by_feature_id(&enrollments, &exps, &ids);
+    let config = |slug: &str, branch: Option<&str>, value: Value| CoenrolledFeatureConfig {
+        slug: slug.to_string(),
+        branch: branch.map(str::to_string),
+        feature_id: "coenrolling".to_string(),
+        value: value.as_object().cloned().unwrap(),
+    };
+    let expected = HashMap::from([(
+        "coenrolling".to_string(),
+        vec![
+            config("exp2", Some("control"), json!({ "a": 2, "b": 2 })),
+            config("ro1", None, json!({ "a": 1 })),
+        ],
+    )]);
+    assert_eq!(observed, expected);
+    Ok(())
+}
+
 #[test]
 fn test_map_features_by_feature_id_with_coenrolling_multifeature() -> Result<()> {
     let exp1 = get_multi_feature_experiment(
@@ -2917,6 +3034,50 @@ fn test_rollouts_end_to_end() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_holdback_branch_gets_only_defaults() -> Result<()> {
+    let (rollout, experiment) = get_rollout_and_experiment();
+    let experiment = Experiment {
+        holdback_branch: Some(experiment.branches[0].slug.clone()),
+        ..experiment
+    };
+    let recipes = &[rollout, experiment];
+
+    let (_, app_ctx, aru) = local_ctx();
+    let th = app_ctx.into();
+    let ids = no_coenrolling_features();
+    let evolver = enrollment_evolver(&th, &aru, &ids);
+
+    let (enrollments, _events) =
+        evolver.evolve_enrollments::<Experiment>(true, &[], recipes, &[])?;
+    assert!(enrollments.iter().all(|e| e.status.is_enrolled()));
+
+    let features = map_features_by_feature_id(&enrollments, recipes, &no_coenrolling_features());
+    assert_eq!(features.len(), 3);
+
+    // The experiment's features are held back, even from the rollout.
+    let alice = &features["alice"];
+    assert_eq!(alice.branch.as_deref(), Some("experiment1"));
+    assert!(alice.feature.value.is_empty());
+
+    let bob = &features["bob"];
+    assert_eq!(bob.slug, "experiment1");
+    assert!(bob.feature.value.is_empty());
+
+    // Features not in the experiment still get the rollout.
+    let charlie = &features["charlie"];
+    assert!(charlie.is_rollout());
+    assert_eq!(
+        Value::Object(charlie.feature.value.clone()),
+        json!({
+            "name": "Charlie".to_string(),
+            "specified": "Rollout".to_string(),
+        })
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_enrollment_explicit_opt_in() -> Result<()> {
     let exp = get_test_experiments()[0].clone();
@@ -7,8 +7,8 @@ use crate::{
     enrollment::{EnrolledReason, EnrollmentStatus, NotEnrolledReason},
     evaluate_enrollment,
     evaluator::{choose_branch, is_experiment_available, targeting},
-    AppContext, AvailableRandomizationUnits, Branch, BucketConfig, Experiment, RandomizationUnit,
-    Result, TargetingAttributes,
+    AppContext, AvailableRandomizationUnits, Branch, BucketConfig, Experiment,
+    NimbusTargetingHelper, RandomizationUnit, Result, TargetingAttributes,
 };
 use serde_json::{json, Map, Value};
 
@@ -70,6 +70,11 @@ fn test_language_region_from_locale() {
 
     test("nim-BUS", Some("nim"), Some("BUS"));
 
+    // Locales are canonicalized, whichever platform they come from.
+    test("en_us", Some("en"), Some("US"));
+    test("zh-Hant_TW", Some("zh"), Some("TW"));
+    test("de_DE.UTF-8", Some("de"), Some("DE"));
+
     // Not sure these are useful.
     test("nim-", Some("nim"), None);
     test("-BUS", None, Some("BUS"));
@@ -138,6 +143,28 @@ fn test_minimum_version_targeting_fails() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_minimum_version_targeting_with_a_number() -> Result<()> {
+    let expression_statement = "app_version|versionCompare(96) >= 0";
+    let ctx = AppContext {
+        app_version: Some("96.1".into()),
+        ..Default::default()
+    };
+    assert_eq!(targeting(expression_statement, &ctx.into()), None);
+
+    let ctx = AppContext {
+        app_version: Some("95.0".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        targeting(expression_statement, &ctx.into()),
+        Some(EnrollmentStatus::NotEnrolled {
+            reason: NotEnrolledReason::NotTargeted
+        })
+    );
+    Ok(())
+}
+
 #[test]
 fn test_targeting_specific_verision() -> Result<()> {
     // Here's our valid jexl statement that targets **only** 96 versions
@@ -429,6 +456,68 @@ fn test_is_experiment_available() {
     assert!(!is_experiment_available(&th, &experiment, false));
 }
 
+#[test]
+fn test_is_experiment_available_app_version_range() {
+    let experiment = Experiment {
+        app_name: Some("NimbusTest".to_string()),
+        channel: Some("nightly".to_string()),
+        slug: "TEST_EXP".to_string(),
+        min_app_version: Some("120.0".to_string()),
+        max_app_version: Some("121.*".to_string()),
+        ..Default::default()
+    };
+    let th_with_version = |app_version: Option<&str>| -> NimbusTargetingHelper {
+        AppContext {
+            app_name: "NimbusTest".to_string(),
+            channel: "nightly".to_string(),
+            app_version: app_version.map(str::to_string),
+            ..Default::default()
+        }
+        .into()
+    };
+
+    // Versions are compared as Firefox versions, not as strings.
+    for app_version in ["120.0", "120.1.2", "121.0", "121.5.1"] {
+        let th = th_with_version(Some(app_version));
+        assert!(
+            is_experiment_available(&th, &experiment, true),
+            "{app_version}"
+        );
+    }
+    for app_version in [
+        "119.9",
+        "120.0b2",
+        "122.0",
+        "99.0",
+        "1000.0",
+        "not a version!",
+    ] {
+        let th = th_with_version(Some(app_version));
+        assert!(
+            !is_experiment_available(&th, &experiment, true),
+            "{app_version}"
+        );
+    }
+
+    // Without a version, the range can't be checked.
+    assert!(is_experiment_available(
+        &th_with_version(None),
+        &experiment,
+        true
+    ));
+
+    // Either end of the range can be open.
+    let experiment = Experiment {
+        max_app_version: None,
+        ..experiment
+    };
+    assert!(is_experiment_available(
+        &th_with_version(Some("1000.0")),
+        &experiment,
+        true
+    ));
+}
+
 #[test]
 fn test_qualified_enrollment() {
     let experiment = Experiment {
@@ -68,3 +68,33 @@ fn test_deserialize_untyped_json() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_parse_recipes_with_kill_switches() -> Result<()> {
+    let payload = json!({
+        "data": [
+            { "id": "disable-a", "killSwitch": { "featureId": "a-feature" } },
+            {
+                "id": "disable-b",
+                "killSwitch": { "featureId": "b-feature", "value": { "mode": "off" } },
+            },
+            { "id": "malformed", "killSwitch": { "value": {} } },
+        ]
+    });
+    let recipes = crate::parse_recipes(&payload.to_string())?;
+    assert!(recipes.experiments.is_empty());
+    assert_eq!(
+        recipes.kill_switches,
+        vec![
+            crate::FeatureKillSwitch {
+                feature_id: "a-feature".to_string(),
+                value: json!({ "enabled": false }).as_object().cloned().unwrap(),
+            },
+            crate::FeatureKillSwitch {
+                feature_id: "b-feature".to_string(),
+                value: json!({ "mode": "off" }).as_object().cloned().unwrap(),
+            },
+        ]
+    );
+    Ok(())
+}
@@ -3,8 +3,6 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 #![cfg(feature = "rkv-safe-mode")]
 
-use rkv::StoreOptions;
-
 // utilities shared between tests
 
 use nimbus::{
@@ -34,6 +32,11 @@ impl MetricsHandler for NoopMetricsHandler {
     fn record_malformed_feature_config(&self, _event: MalformedFeatureConfigExtraDef) {
         // do nothing
     }
+
+    #[cfg(feature = "stateful")]
+    fn record_fetch_error(&self, _event: FetchErrorExtraDef) {
+        // do nothing
+    }
 }
 
 #[allow(dead_code)] // work around https://github.com/rust-lang/rust/issues/46379
@@ -78,8 +81,10 @@ fn new_test_client_internal(
     )
 }
 
-use nimbus::metrics::{FeatureExposureExtraDef, MalformedFeatureConfigExtraDef};
-use nimbus::stateful::persistence::{Database, SingleStore};
+use nimbus::metrics::{
+    FeatureExposureExtraDef, FetchErrorExtraDef, MalformedFeatureConfigExtraDef,
+};
+use nimbus::stateful::persistence::{RkvStorage, SingleStore, Storage, StoreId};
 use std::path::Path;
 
 #[allow(dead_code)] //  work around https://github.com/rust-lang/rust/issues/46379
@@ -92,12 +97,10 @@ pub fn create_database<P: AsRef<Path>>(
     let _ = env_logger::try_init();
     log::debug!("create_database(): old_version = {:?}", old_version);
     log::debug!("create_database(): path = {:?}", path.as_ref());
-    let rkv = Database::open_rkv(path)?;
-    let meta_store = SingleStore::new(rkv.open_single("meta", StoreOptions::create())?);
-    let experiment_store =
-        SingleStore::new(rkv.open_single("experiments", StoreOptions::create())?);
-    let enrollment_store =
-        SingleStore::new(rkv.open_single("enrollments", StoreOptions::create())?);
+    let rkv = RkvStorage::open(path)?;
+    let meta_store = SingleStore::new(StoreId::Meta);
+    let experiment_store = SingleStore::new(StoreId::Experiments);
+    let enrollment_store = SingleStore::new(StoreId::Enrollments);
     let mut writer = rkv.write()?;
 
     meta_store.put(&mut writer, "db_version", &old_version)?;
@@ -91,6 +91,36 @@ interface ReadableBookmarksConnection : InterruptibleConnection {
      */
     fun getBookmarkUrlForKeyword(keyword: String): Url?
 
+    /**
+     * Returns the bookmarks for the URL with the provided search keyword.
+     *
+     * Like on desktop, keywords belong to URLs, so every bookmark of a URL has the same
+     * keyword. Keywords are matched case-insensitively.
+     *
+     * @param keyword The search keyword.
+     * @return The bookmarks with the keyword, empty if there are none.
+     */
+    fun getBookmarksWithKeyword(keyword: String): List<BookmarkItem>
+
+    /**
+     * Returns the URL to load for URL bar input that starts with a search keyword, like
+     * "wiki firefox". `%s` in the keyword's URL is replaced with the escaped search terms,
+     * and `%S` with the search terms as-is.
+     *
+     * @param input The URL bar input.
+     * @return The URL to load, or null if the input doesn't start with a keyword, or has
+     * search terms but the keyword's URL doesn't have a placeholder for them.
+     */
+    fun getKeywordSearchUrl(input: String): Url?
+
+    /**
+     * Returns the search keyword of the provided URL.
+     *
+     * @param url The URL to get the keyword of.
+     * @return The keyword, or null if the URL doesn't have one.
+     */
+    fun getKeywordForUrl(url: Url): String?
+
     /**
      * Returns the list of bookmarks that match the provided search string.
      *
@@ -132,6 +162,26 @@ interface ReadableBookmarksConnection : InterruptibleConnection {
      * has its `interrupt()` method called on another thread.
      */
     fun countBookmarksInTrees(guids: List<Guid>): UInt
+
+    /**
+     * Returns the tags of the provided URL, most recently used first.
+     *
+     * Like on desktop, tags belong to URLs, so every bookmark of a URL has the same tags.
+     *
+     * @param url The URL to get the tags of.
+     * @return The tags of the URL, empty if it has none.
+     */
+    fun getTagsForUrl(url: Url): List<String>
+
+    /**
+     * Returns the URLs with the provided tag.
+     *
+     * @param tag The tag to look for.
+     * @return The URLs with the tag, empty if there are none.
+     *
+     * @throws InvalidBookmarkOperation If `tag` is empty or too long.
+     */
+    fun getUrlsWithTag(tag: String): List<Url>
 }
 
 /**
@@ -158,6 +208,63 @@ interface WritableBookmarksConnection : ReadableBookmarksConnection {
      */
     fun deleteAllBookmarks()
 
+    /**
+     * Tags the provided URL. The tag is synced with the bookmarks of the URL.
+     *
+     * @param url The URL to tag.
+     * @param tag The tag to add. Leading and trailing whitespace is removed.
+     *
+     * @throws UnknownBookmarkItem If `url` has never been bookmarked or visited.
+     * @throws InvalidBookmarkOperation If `tag` is empty or too long.
+     */
+    fun tagUrl(url: Url, tag: String)
+
+    /**
+     * Sets the search keyword of the provided URL, replacing its existing keyword. If another
+     * URL has the keyword, it's removed from that URL. The keyword is synced with the
+     * bookmarks of the URL.
+     *
+     * @param url The URL to set the keyword of.
+     * @param keyword The keyword. Leading and trailing whitespace is removed, and it's lowercased.
+     *
+     * @throws UnknownBookmarkItem If `url` isn't bookmarked.
+     * @throws InvalidBookmarkOperation If `keyword` is empty or contains whitespace.
+     */
+    fun setKeywordForUrl(url: Url, keyword: String)
+
+    /**
+     * Removes the provided search keyword. Does nothing if no URL has the keyword.
+     *
+     * @param keyword The keyword to remove.
+     *
+     * @throws InvalidBookmarkOperation If `keyword` is empty or contains whitespace.
+     */
+    fun removeKeyword(keyword: String)
+
+    /**
+     * Removes the provided tag from the provided URL. Does nothing if the URL doesn't have the tag.
+     *
+     * @param url The URL to untag.
+     * @param tag The tag to remove.
+     *
+     * @throws InvalidBookmarkOperation If `tag` is empty or too long.
+     */
+    fun untagUrl(url: Url, tag: String)
+
+    /**
+     * Removes every tag from the provided URL.
+     *
+     * @param url The URL to untag.
+     */
+    fun removeAllTagsFromUrl(url: Url)
+
+    /**
+     * Removes the provided tag from every URL.
+     *
+     * @param tag The tag to remove.
+     */
+    fun removeTag(tag: String)
+
     /**
      * Create a bookmark folder, returning its guid.
      *
@@ -242,4 +349,20 @@ interface WritableBookmarksConnection : ReadableBookmarksConnection {
      * folder node.
      */
     fun updateBookmark(guid: Guid, parentGuid: Guid?, position: UInt?, title: String?, url: Url?)
+
+    /**
+     * Reorder the children of a folder, in a single transaction. This is faster than
+     * moving each child with [updateBookmark], and only marks the folder as changed
+     * for Sync.
+     *
+     * @param parentGuid The GUID of the folder.
+     * @param orderedChildGuids The GUIDs of the children, in their new order. GUIDs that
+     * aren't children of the folder are ignored, and children that aren't listed keep
+     * their order, after the listed ones.
+     *
+     * @throws CannotUpdateRoot If `parentGuid` is [BookmarkRoot.Root] (e.g. "root________")
+     * @throws UnknownBookmarkItem If `parentGuid` does not refer to a known bookmark.
+     * @throws InvalidParent If `parentGuid` does not refer to a folder node.
+     */
+    fun setBookmarkChildrenOrder(parentGuid: Guid, orderedChildGuids: List<Guid>)
 }
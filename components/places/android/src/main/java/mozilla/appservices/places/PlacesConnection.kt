@@ -4,29 +4,50 @@
 
 package mozilla.appservices.places
 
+import mozilla.appservices.places.uniffi.AnnotationExpiration
+import mozilla.appservices.places.uniffi.AnnotationValue
 import mozilla.appservices.places.uniffi.BookmarkItem
 import mozilla.appservices.places.uniffi.BookmarkPosition
 import mozilla.appservices.places.uniffi.BookmarkUpdateInfo
+import mozilla.appservices.places.uniffi.BookmarksMigrationResult
 import mozilla.appservices.places.uniffi.ConnectionType
 import mozilla.appservices.places.uniffi.DocumentType
+import mozilla.appservices.places.uniffi.Favicon
+import mozilla.appservices.places.uniffi.FennecMigrationResult
 import mozilla.appservices.places.uniffi.FrecencyThresholdOption
 import mozilla.appservices.places.uniffi.HistoryHighlight
 import mozilla.appservices.places.uniffi.HistoryHighlightWeights
 import mozilla.appservices.places.uniffi.HistoryMetadata
 import mozilla.appservices.places.uniffi.HistoryMetadataObservation
+import mozilla.appservices.places.uniffi.HistoryMigrationResult
+import mozilla.appservices.places.uniffi.HistorySyncConfig
 import mozilla.appservices.places.uniffi.HistoryVisitInfo
+import mozilla.appservices.places.uniffi.HistoryVisitInfosPage
 import mozilla.appservices.places.uniffi.HistoryVisitInfosWithBound
 import mozilla.appservices.places.uniffi.InsertableBookmark
 import mozilla.appservices.places.uniffi.InsertableBookmarkFolder
 import mozilla.appservices.places.uniffi.InsertableBookmarkItem
 import mozilla.appservices.places.uniffi.InsertableBookmarkSeparator
+import mozilla.appservices.places.uniffi.MigrationProgressListener
+import mozilla.appservices.places.uniffi.OriginAutofill
+import mozilla.appservices.places.uniffi.PageAnnotation
+import mozilla.appservices.places.uniffi.PagePreview
+import mozilla.appservices.places.uniffi.PageVisitCounts
 import mozilla.appservices.places.uniffi.PlacesApiException
+import mozilla.appservices.places.uniffi.PlacesConnectionInterruptHandle
+import mozilla.appservices.places.uniffi.PlacesInterruptHandle
+import mozilla.appservices.places.uniffi.PlacesMetricsHandler
+import mozilla.appservices.places.uniffi.PlacesOperation
 import mozilla.appservices.places.uniffi.SearchResult
-import mozilla.appservices.places.uniffi.SqlInterruptHandle
+import mozilla.appservices.places.uniffi.ShutdownPhase
 import mozilla.appservices.places.uniffi.TopFrecentSiteInfo
+import mozilla.appservices.places.uniffi.TopSitesExclusions
 import mozilla.appservices.places.uniffi.VisitObservation
+import mozilla.appservices.places.uniffi.VisitSource
 import mozilla.appservices.places.uniffi.VisitType
 import mozilla.appservices.places.uniffi.placesApiNew
+import mozilla.appservices.places.uniffi.placesShutdown
+import mozilla.appservices.places.uniffi.setMetricsHandler
 import mozilla.appservices.sync15.SyncTelemetryPing
 import mozilla.telemetry.glean.private.CounterMetricType
 import mozilla.telemetry.glean.private.LabeledMetricType
@@ -56,6 +77,7 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
         // pushback on allowing this to actually be a constructor, so it's a global
         // function instead :(
         api = placesApiNew(path)
+        setMetricsHandler(PlacesGleanMetricsHandler)
 
         val uniffiConnection = api.newConnection(ConnectionType.READ_WRITE)
         writeConn = PlacesWriterConnection(uniffiConnection, this)
@@ -76,10 +98,27 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
         return PlacesReaderConnection(conn)
     }
 
+    override fun openReaderPool(size: UInt): PlacesReaderConnection {
+        val conn = api.newReaderPool(size)
+        return PlacesReaderConnection(conn)
+    }
+
     override fun getWriter(): PlacesWriterConnection {
         return writeConn
     }
 
+    override fun newReadInterruptHandle(): PlacesInterruptHandle {
+        return api.newReadInterruptHandle()
+    }
+
+    override fun newWriteInterruptHandle(): PlacesInterruptHandle {
+        return api.newWriteInterruptHandle()
+    }
+
+    override fun shutdown(phase: ShutdownPhase) {
+        placesShutdown(phase)
+    }
+
     @Synchronized
     override fun close() {
         this.writeConn.apiRef.clear()
@@ -109,6 +148,10 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
         this.api.resetHistory()
     }
 
+    override fun setHistorySyncConfig(config: HistorySyncConfig) {
+        this.api.setHistorySyncConfig(config)
+    }
+
     override fun resetBookmarkSyncMetadata() {
         return this.api.bookmarksReset()
     }
@@ -117,7 +160,7 @@ class PlacesApi(path: String) : PlacesManager, AutoCloseable {
 @Suppress("TooGenericExceptionCaught")
 open class PlacesConnection internal constructor(uniffiConn: UniffiPlacesConnection) : InterruptibleConnection, AutoCloseable {
     protected var conn: UniffiPlacesConnection
-    protected var interruptHandle: SqlInterruptHandle
+    protected var interruptHandle: PlacesConnectionInterruptHandle
 
     init {
         interruptHandle = uniffiConn.newInterruptHandle()
@@ -136,7 +179,7 @@ open class PlacesConnection internal constructor(uniffiConn: UniffiPlacesConnect
     }
 
     override fun interrupt() {
-        this.interruptHandle.interrupt()
+        this.conn.interrupt()
     }
 }
 
@@ -155,14 +198,60 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         return this.conn.queryAutocomplete(query, limit)
     }
 
+    override fun queryInputHistory(query: String, limit: Int): List<SearchResult> {
+        return this.conn.queryInputHistory(query, limit)
+    }
+
+    override fun searchHistory(query: String, limit: Int): List<SearchResult> {
+        return readQueryCounters.measure {
+            this.conn.searchHistory(query, limit)
+        }
+    }
+
     override fun matchUrl(query: String): Url? {
         return this.conn.matchUrl(query)
     }
 
+    override fun matchOriginForAutofill(prefix: String): OriginAutofill? {
+        return this.conn.matchOriginForAutofill(prefix)
+    }
+
     override fun getTopFrecentSiteInfos(numItems: Int, frecencyThreshold: FrecencyThresholdOption): List<TopFrecentSiteInfo> {
         return this.conn.getTopFrecentSiteInfos(numItems, frecencyThreshold)
     }
 
+    override fun getTopFrecentSites(limit: Int, exclusions: TopSitesExclusions): List<TopFrecentSiteInfo> {
+        return this.conn.getTopFrecentSites(limit, exclusions)
+    }
+
+    override fun getFaviconForPage(pageUrl: Url, preferredWidth: UInt): Favicon? {
+        return readQueryCounters.measure {
+            this.conn.getFaviconForPage(pageUrl, preferredWidth)
+        }
+    }
+
+    override fun getPagePreviews(pageUrls: List<Url>): List<PagePreview> {
+        return readQueryCounters.measure {
+            this.conn.getPagePreviews(pageUrls)
+        }
+    }
+
+    override fun getPageGuid(pageUrl: Url): Guid? {
+        return this.conn.getPageGuid(pageUrl)
+    }
+
+    override fun getPageAnnotation(pageGuid: Guid, name: String): AnnotationValue? {
+        return this.conn.getPageAnnotation(pageGuid, name)
+    }
+
+    override fun getPageAnnotations(pageGuid: Guid): List<PageAnnotation> {
+        return this.conn.getPageAnnotations(pageGuid)
+    }
+
+    override fun getPagesWithAnnotation(name: String): List<Guid> {
+        return this.conn.getPagesWithAnnotation(name)
+    }
+
     override fun getVisited(urls: List<String>): List<Boolean> {
         return this.conn.getVisited(urls)
     }
@@ -171,12 +260,28 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         return this.conn.getVisitedUrlsInRange(start, end, includeRemote)
     }
 
-    override fun getVisitInfos(start: Long, end: Long, excludeTypes: List<VisitType>): List<HistoryVisitInfo> {
-        readQueryCounters.measure {
-            return this.conn.getVisitInfos(start, end, visitTransitionSet(excludeTypes))
+    override fun getVisitInfos(
+        start: Long,
+        end: Long,
+        excludeTypes: List<VisitType>,
+        source: VisitSource,
+    ): List<HistoryVisitInfo> {
+        return readQueryCounters.measure {
+            this.conn.getVisitInfos(start, end, visitTransitionSet(excludeTypes), source)
         }
     }
 
+    override fun getVisitInfosPage(
+        start: Long,
+        end: Long,
+        count: UInt,
+        excludeTypes: List<VisitType>,
+        continuation: String?,
+        source: VisitSource,
+    ): HistoryVisitInfosPage {
+        return this.conn.getVisitInfosPage(start, end, visitTransitionSet(excludeTypes), source, count, continuation)
+    }
+
     override fun getVisitPage(offset: Long, count: Long, excludeTypes: List<VisitType>): List<HistoryVisitInfo> {
         return this.conn.getVisitPage(offset, count, visitTransitionSet(excludeTypes))
     }
@@ -190,8 +295,12 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         return this.conn.getVisitPageWithBound(offset, bound, count, visitTransitionSet(excludeTypes))
     }
 
-    override fun getVisitCount(excludeTypes: List<VisitType>): Long {
-        return this.conn.getVisitCount(visitTransitionSet(excludeTypes))
+    override fun getVisitCount(excludeTypes: List<VisitType>, source: VisitSource): Long {
+        return this.conn.getVisitCount(visitTransitionSet(excludeTypes), source)
+    }
+
+    override fun getPageVisitCounts(pageUrls: List<Url>): List<PageVisitCounts> {
+        return this.conn.getPageVisitCounts(pageUrls)
     }
 
     override suspend fun getLatestHistoryMetadataForUrl(url: Url): HistoryMetadata? {
@@ -218,6 +327,12 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         }
     }
 
+    override suspend fun getRecentSearches(limit: Int): List<HistoryMetadata> {
+        return readQueryCounters.measure {
+            this.conn.getRecentSearches(limit)
+        }
+    }
+
     override suspend fun getHighlights(
         weights: HistoryHighlightWeights,
         limit: Int,
@@ -251,6 +366,36 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         return this.conn.bookmarksGetUrlForKeyword(keyword)
     }
 
+    override fun getBookmarksWithKeyword(keyword: String): List<BookmarkItem> {
+        return readQueryCounters.measure {
+            this.conn.bookmarksGetByKeyword(keyword)
+        }
+    }
+
+    override fun getKeywordSearchUrl(input: String): Url? {
+        return readQueryCounters.measure {
+            this.conn.bookmarksGetKeywordSearchUrl(input)
+        }
+    }
+
+    override fun getKeywordForUrl(url: Url): String? {
+        return readQueryCounters.measure {
+            this.conn.getKeywordForUrl(url)
+        }
+    }
+
+    override fun getTagsForUrl(url: Url): List<String> {
+        return readQueryCounters.measure {
+            this.conn.getTagsForUrl(url)
+        }
+    }
+
+    override fun getUrlsWithTag(tag: String): List<Url> {
+        return readQueryCounters.measure {
+            this.conn.getUrlsWithTag(tag)
+        }
+    }
+
     override fun searchBookmarks(query: String, limit: Int): List<BookmarkItem> {
         return readQueryCounters.measure {
             this.conn.bookmarksSearch(query, limit)
@@ -269,6 +414,25 @@ open class PlacesReaderConnection internal constructor(conn: UniffiPlacesConnect
         }
     }
 
+    /**
+     * Writes all bookmarks to a Netscape-format HTML file, which most browsers can import.
+     *
+     * @param path the path to write the file to. It's replaced if it already exists.
+     */
+    fun exportBookmarksToHtml(path: String) {
+        this.conn.bookmarksExportToHtml(path)
+    }
+
+    /**
+     * Writes all bookmarks to a file in the format of desktop Firefox's JSON backups, which keeps
+     * their GUIDs.
+     *
+     * @param path the path to write the file to. It's replaced if it already exists.
+     */
+    fun exportBookmarksToJson(path: String) {
+        this.conn.bookmarksExportToJson(path)
+    }
+
     private val readQueryCounters: PlacesManagerCounterMetrics by lazy {
         PlacesManagerCounterMetrics(
             PlacesManagerMetrics.readQueryCount,
@@ -325,12 +489,43 @@ class PlacesWriterConnection internal constructor(conn: UniffiPlacesConnection,
         }
     }
 
+    override fun deleteVisitsForHost(host: String) {
+        return writeQueryCounters.measure {
+            this.conn.deleteVisitsForHost(host)
+        }
+    }
+
     override fun deleteVisit(url: String, visitTimestamp: Long) {
         return writeQueryCounters.measure {
             this.conn.deleteVisit(url, visitTimestamp)
         }
     }
 
+    override fun setFaviconForPage(pageUrl: Url, favicon: Favicon) {
+        return writeQueryCounters.measure {
+            this.conn.setFaviconForPage(pageUrl, favicon)
+        }
+    }
+
+    override fun setPagePreview(preview: PagePreview) {
+        return writeQueryCounters.measure {
+            this.conn.setPagePreview(preview)
+        }
+    }
+
+    override fun setPageAnnotation(
+        pageGuid: Guid,
+        name: String,
+        value: AnnotationValue,
+        expiration: AnnotationExpiration,
+    ) {
+        return this.conn.setPageAnnotation(pageGuid, name, value, expiration)
+    }
+
+    override fun removePageAnnotation(pageGuid: Guid, name: String) {
+        return this.conn.removePageAnnotation(pageGuid, name)
+    }
+
     override fun deleteVisitsSince(since: Long) {
         deleteVisitsBetween(since, Long.MAX_VALUE)
     }
@@ -347,6 +542,8 @@ class PlacesWriterConnection internal constructor(conn: UniffiPlacesConnection,
                 this.conn.runMaintenancePrune(dbSizeLimit, 12U)
             }
 
+            this.conn.runMaintenanceFrecency(400U)
+
             PlacesManagerMetrics.runMaintenanceVacuumTime.measure {
                 this.conn.runMaintenanceVacuum()
             }
@@ -375,6 +572,42 @@ class PlacesWriterConnection internal constructor(conn: UniffiPlacesConnection,
         }
     }
 
+    override fun tagUrl(url: Url, tag: String) {
+        return writeQueryCounters.measure {
+            this.conn.tagUrl(url, tag)
+        }
+    }
+
+    override fun setKeywordForUrl(url: Url, keyword: String) {
+        return writeQueryCounters.measure {
+            this.conn.setKeywordForUrl(url, keyword)
+        }
+    }
+
+    override fun removeKeyword(keyword: String) {
+        return writeQueryCounters.measure {
+            this.conn.removeKeyword(keyword)
+        }
+    }
+
+    override fun untagUrl(url: Url, tag: String) {
+        return writeQueryCounters.measure {
+            this.conn.untagUrl(url, tag)
+        }
+    }
+
+    override fun removeAllTagsFromUrl(url: Url) {
+        return writeQueryCounters.measure {
+            this.conn.removeAllTagsFromUrl(url)
+        }
+    }
+
+    override fun removeTag(tag: String) {
+        return writeQueryCounters.measure {
+            this.conn.removeTag(tag)
+        }
+    }
+
     override fun deleteBookmarkNode(guid: Guid): Boolean {
         return writeQueryCounters.measure {
             this.conn.bookmarksDelete(guid)
@@ -387,9 +620,7 @@ class PlacesWriterConnection internal constructor(conn: UniffiPlacesConnection,
         // passing them along here.
         // NB: Even though `MsgTypes.HistoryMetadataObservation` has an optional title field, we ignore it here.
         // That's used by consumers which aren't already using the history observation APIs.
-        return writeQueryCounters.measure {
-            this.conn.noteHistoryMetadataObservation(observation)
-        }
+        return this.conn.noteHistoryMetadataObservation(observation)
     }
 
     override suspend fun noteHistoryMetadataObservationViewTime(key: HistoryMetadataKey, viewTime: Int) {
@@ -471,16 +702,98 @@ class PlacesWriterConnection internal constructor(conn: UniffiPlacesConnection,
         } else {
             position.toUInt()
         }
-        return writeQueryCounters.measure {
-            val info = BookmarkUpdateInfo(guid = guid, title = title, url = url, parentGuid = parentGuid, position = p)
-            return this.conn.bookmarksUpdate(info)
-        }
+        val info = BookmarkUpdateInfo(guid = guid, title = title, url = url, parentGuid = parentGuid, position = p)
+        return this.conn.bookmarksUpdate(info)
+    }
+
+    override fun setBookmarkChildrenOrder(parentGuid: Guid, orderedChildGuids: List<Guid>) {
+        return this.conn.setBookmarkChildrenOrder(parentGuid, orderedChildGuids)
     }
 
     override fun acceptResult(searchString: String, url: String) {
         return this.conn.acceptResult(searchString, url)
     }
 
+    /**
+     * Imports history, then bookmarks, from Fennec's `browser.db`. This is meant to be run once,
+     * when a legacy user first starts the app.
+     *
+     * Records that can't be imported are skipped, and counted in the `numFailed` of the result,
+     * rather than failing the whole import.
+     *
+     * @param path the path to Fennec's `browser.db`.
+     * @param listener notified as each phase of the import makes progress.
+     * @return the number of visits and bookmarks that were, and weren't, imported.
+     */
+    fun importFromFennec(path: String, listener: MigrationProgressListener): FennecMigrationResult {
+        return writeQueryCounters.measure {
+            this.conn.placesImportFromFennec(path, listener)
+        }
+    }
+
+    /**
+     * Imports history from Chrome's `History` database, for users switching from Chrome. Chrome
+     * locks the database while it's running, so this should be given a copy.
+     *
+     * Visits that can't be imported are skipped, and counted in the `numFailed` of the result,
+     * rather than failing the whole import.
+     *
+     * @param path the path to a copy of Chrome's `History` database.
+     * @return the number of visits that were, and weren't, imported.
+     */
+    fun importHistoryFromChrome(path: String): HistoryMigrationResult {
+        return writeQueryCounters.measure {
+            this.conn.placesHistoryImportFromChrome(path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from Chrome's JSON `Bookmarks` file, for users switching from Chrome.
+     * They're added to the existing bookmarks: Chrome's bookmarks bar, other bookmarks and mobile
+     * bookmarks go in our toolbar, unfiled and mobile roots.
+     *
+     * Bookmarks that can't be imported are skipped, and counted in the `numFailed` of the result,
+     * rather than failing the whole import.
+     *
+     * @param path the path to Chrome's `Bookmarks` file.
+     * @return the number of bookmarks that were, and weren't, imported.
+     */
+    fun importBookmarksFromChrome(path: String): BookmarksMigrationResult {
+        return writeQueryCounters.measure {
+            this.conn.placesBookmarksImportFromChrome(path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from a Netscape-format HTML file, as exported by most browsers. They're
+     * added to the existing bookmarks: the file's top-level bookmarks go in the menu, and its
+     * toolbar and other bookmarks folders go in those roots.
+     *
+     * Bookmarks that can't be imported are skipped, and counted in the `numFailed` of the result,
+     * rather than failing the whole import.
+     *
+     * @param path the path to the HTML file.
+     * @return the number of bookmarks that were, and weren't, imported.
+     */
+    fun importBookmarksFromHtml(path: String): BookmarksMigrationResult {
+        return this.conn.placesBookmarksImportFromHtml(path)
+    }
+
+    /**
+     * Imports bookmarks from one of desktop Firefox's JSON backups. They're added to the existing
+     * bookmarks, and keep their GUIDs unless they're already in use, so that syncing them doesn't
+     * create duplicates.
+     *
+     * Bookmarks that can't be imported are skipped, and counted in the `numFailed` of the result,
+     * rather than failing the whole import.
+     *
+     * @param path the path to the JSON backup.
+     * @return the number of bookmarks that were, and weren't, imported.
+     */
+    fun importBookmarksFromJson(path: String): BookmarksMigrationResult {
+        return this.conn.placesBookmarksImportFromJson(path)
+    }
+
     @Synchronized
     override fun close() {
         // If our API is still around, do nothing.
@@ -529,6 +842,15 @@ interface PlacesManager {
      */
     fun openReader(): ReadableHistoryConnection
 
+    /**
+     * Open a reader connection backed by a pool of connections, so that queries
+     * made from different threads, like autocomplete and the history panel, can
+     * run at the same time instead of waiting for each other.
+     *
+     * @param size the number of connections in the pool.
+     */
+    fun openReaderPool(size: UInt): ReadableHistoryConnection
+
     /**
      * Get a reference to the writer connection.
      *
@@ -536,6 +858,28 @@ interface PlacesManager {
      */
     fun getWriter(): WritableHistoryConnection
 
+    /**
+     * Returns a handle that interrupts the operations running on all reader
+     * connections, including ones opened later. Use this to cancel long
+     * queries when the user navigates away.
+     */
+    fun newReadInterruptHandle(): PlacesInterruptHandle
+
+    /**
+     * Returns a handle that interrupts the operations running on the writer
+     * connection.
+     */
+    fun newWriteInterruptHandle(): PlacesInterruptHandle
+
+    /**
+     * Starts a phase of the app's shutdown, and any phases before it, so the
+     * app doesn't wait on us. Syncs are interrupted in the `BACKGROUND` phase,
+     * reader connections in `READ` and the writer connection in `WRITE`.
+     * Interrupted connections can't start anything else, and opening one
+     * after its phase has started throws.
+     */
+    fun shutdown(phase: ShutdownPhase = ShutdownPhase.WRITE)
+
     /**
      * Syncs the places history store, returning a telemetry ping.
      *
@@ -568,6 +912,16 @@ interface PlacesManager {
      */
     fun resetHistorySyncMetadata()
 
+    /**
+     * Sets how many visits are uploaded for each page, how many records are
+     * downloaded or uploaded in each history sync, and how many downloaded
+     * records are applied at a time. Low-end devices can use smaller limits
+     * to bound memory use during large first syncs.
+     *
+     * This applies to history syncs that start after it's called.
+     */
+    fun setHistorySyncConfig(config: HistorySyncConfig)
+
     /**
      * Resets all sync metadata for bookmarks, including change flags,
      * sync statuses, and last sync time. The next sync after reset
@@ -627,6 +981,15 @@ interface ReadableHistoryMetadataConnection : InterruptibleConnection {
      */
     suspend fun queryHistoryMetadata(query: String, limit: Int): List<HistoryMetadata>
 
+    /**
+     * Returns the latest [HistoryMetadata] for each [HistoryMetadata.searchTerm], most recent first,
+     * e.g. to show the user's recent searches.
+     *
+     * @param limit A maximum number of records to return.
+     * @return A `List` of [HistoryMetadata] with search terms, empty if there are none.
+     */
+    suspend fun getRecentSearches(limit: Int): List<HistoryMetadata>
+
     /**
      * Returns an ordered list of [HistoryHighlight], ranked by their "highlight score".
      * A highlight score takes into account factors listed in [HistoryHighlightWeights].
@@ -678,6 +1041,27 @@ interface ReadableHistoryConnection : InterruptibleConnection {
      */
     fun queryAutocomplete(query: String, limit: Int): List<SearchResult>
 
+    /**
+     * An autocomplete source that only returns the pages previously chosen, with
+     * [WritableHistoryConnection.acceptResult], for search strings starting with [query].
+     * Like desktop's adaptive history, the pages don't have to contain [query].
+     *
+     * @param query a string to match results against, ignoring case.
+     * @param limit a maximum number of results to retrieve.
+     * @return a list of [SearchResult], most often chosen first.
+     */
+    fun queryInputHistory(query: String, limit: Int): List<SearchResult>
+
+    /**
+     * Searches the titles and URLs of visited pages for all the words in [query]. Each word
+     * can match the start of a word in the title or URL, e.g. "moz fire" matches "Mozilla Firefox".
+     *
+     * @param query a string to match results against.
+     * @param limit a maximum number of results to retrieve.
+     * @return a list of [SearchResult] matching the [query], best matches first.
+     */
+    fun searchHistory(query: String, limit: Int): List<SearchResult>
+
     /**
      * See if a url that's sufficiently close to `search` exists in
      * the database.
@@ -689,6 +1073,17 @@ interface ReadableHistoryConnection : InterruptibleConnection {
      */
     fun matchUrl(query: String): String?
 
+    /**
+     * Returns the origin to autofill in the URL bar for the text typed so far, like
+     * "youtube.com/" for "you". Only bookmarked origins, and origins that are much more
+     * frecent than average, are autofilled.
+     *
+     * @param prefix the typed text. It can start with `http://` or `https://`, to only
+     * autofill origins with that scheme, and end with a `/`, to only autofill that exact host.
+     * @return the origin, whose `completion` starts with [prefix], ignoring case, or null.
+     */
+    fun matchOriginForAutofill(prefix: String): OriginAutofill?
+
     /**
      * Returns a list of the top frecent site infos limited by the given number of items
      * and frecency threshold sorted by most to least frecent.
@@ -700,6 +1095,74 @@ interface ReadableHistoryConnection : InterruptibleConnection {
      */
     fun getTopFrecentSiteInfos(numItems: Int, frecencyThreshold: FrecencyThresholdOption): List<TopFrecentSiteInfo>
 
+    /**
+     * Returns the most frecent sites, sorted by most to least frecent. The http, https
+     * and "www." origins for a host are combined into one site, which uses https if
+     * the site has been visited over https.
+     *
+     * @param limit the number of top sites to return in the list.
+     * @param exclusions sites to leave out, like those the app already shows as pinned,
+     * or that the user has removed. Any site with the same host is excluded.
+     * @return a list of the top sites, with their origin URLs.
+     */
+    fun getTopFrecentSites(limit: Int, exclusions: TopSitesExclusions): List<TopFrecentSiteInfo>
+
+    /**
+     * Returns the best icon for a page: the smallest one that's at least [preferredWidth]
+     * wide, or the largest one if they're all smaller. If the page doesn't have any icons
+     * of its own, the root icon (`/favicon.ico`) of its origin is used instead.
+     *
+     * Expired icons are still returned; the app should fetch them again, and store them
+     * with [WritableHistoryConnection.setFaviconForPage].
+     *
+     * @param pageUrl the URL of the page.
+     * @param preferredWidth the width the icon will be shown at, in pixels.
+     * @return the icon, or null if there isn't one.
+     */
+    fun getFaviconForPage(pageUrl: Url, preferredWidth: UInt): Favicon?
+
+    /**
+     * Returns the preview image URLs and descriptions of pages, for showing them as
+     * rich cards.
+     *
+     * @param pageUrls the URLs of the pages.
+     * @return the previews, in the same order as [pageUrls]. Pages that aren't known,
+     * or don't have a preview, are left out.
+     */
+    fun getPagePreviews(pageUrls: List<Url>): List<PagePreview>
+
+    /**
+     * Returns the GUID of a page, for reading or setting its annotations.
+     *
+     * @param pageUrl the URL of the page.
+     * @return the page's GUID, or null if it isn't in history or bookmarked.
+     */
+    fun getPageGuid(pageUrl: Url): Guid?
+
+    /**
+     * Returns the value of a page's annotation.
+     *
+     * @param pageGuid the GUID of the page.
+     * @param name the name of the annotation.
+     * @return the value, or null if the page doesn't have an annotation with that name.
+     */
+    fun getPageAnnotation(pageGuid: Guid, name: String): AnnotationValue?
+
+    /**
+     * Returns all of a page's annotations, ordered by name.
+     *
+     * @param pageGuid the GUID of the page.
+     */
+    fun getPageAnnotations(pageGuid: Guid): List<PageAnnotation>
+
+    /**
+     * Returns the pages with an annotation, most recently set first.
+     *
+     * @param name the name of the annotation.
+     * @return the GUIDs of the pages.
+     */
+    fun getPagesWithAnnotation(name: String): List<Guid>
+
     /**
      * Maps a list of page URLs to a list of booleans indicating if each URL was visited.
      *
@@ -725,13 +1188,41 @@ interface ReadableHistoryConnection : InterruptibleConnection {
      *
      * @param start The (inclusive) start time to bound the query.
      * @param end The (inclusive) end time to bound the query.
+     * @param excludeTypes List of visit types to exclude.
+     * @param source Whether to include all visits, or only local or remote ones.
      */
     fun getVisitInfos(
         start: Long,
         end: Long = Long.MAX_VALUE,
         excludeTypes: List<VisitType> = listOf(),
+        source: VisitSource = VisitSource.ALL,
     ): List<HistoryVisitInfo>
 
+    /**
+     * Get a page of the visits that occurred in the given time range, most recent first,
+     * for history UIs that load more visits as the user scrolls.
+     *
+     * Unlike [getVisitPage], pages don't skip or repeat visits if history changes while
+     * you're paging through it.
+     *
+     * @param start The (inclusive) start time to bound the query.
+     * @param end The (inclusive) end time to bound the query.
+     * @param count The maximum number of visits to return in the page.
+     * @param excludeTypes List of visit types to exclude.
+     * @param continuation Null for the first page, or the [HistoryVisitInfosPage.continuation]
+     *  of the previous page.
+     * @param source Whether to include all visits, or only local or remote ones.
+     * @return The page, whose `continuation` is null if it's the last one.
+     */
+    fun getVisitInfosPage(
+        start: Long,
+        end: Long,
+        count: UInt,
+        excludeTypes: List<VisitType> = listOf(),
+        continuation: String? = null,
+        source: VisitSource = VisitSource.ALL,
+    ): HistoryVisitInfosPage
+
     /**
      * Return a "page" of history results. Each page will have visits in descending order
      * with respect to their visit timestamps. In the case of ties, their row id will
@@ -775,8 +1266,19 @@ interface ReadableHistoryConnection : InterruptibleConnection {
      *
      *
      * @param excludeTypes List of visit types to exclude.
+     * @param source Whether to count all visits, or only local or remote ones.
+     */
+    fun getVisitCount(excludeTypes: List<VisitType> = listOf(), source: VisitSource = VisitSource.ALL): Long
+
+    /**
+     * Returns how many times pages were visited on this device, and on other
+     * devices, and when they were last visited on each.
+     *
+     * @param pageUrls the URLs of the pages.
+     * @return the counts, in the same order as [pageUrls]. Pages that aren't known
+     * are left out.
      */
-    fun getVisitCount(excludeTypes: List<VisitType> = listOf()): Long
+    fun getPageVisitCounts(pageUrls: List<Url>): List<PageVisitCounts>
 }
 
 interface WritableHistoryConnection : ReadableHistoryConnection {
@@ -794,6 +1296,7 @@ interface WritableHistoryConnection : ReadableHistoryConnection {
      * - Expiring irrelevant history visits.
      * - Periodic repair or deletion of corrupted records.
      * - Deleting older visits when the database exceeds dbSizeLimit
+     * - Recalculating stale frecencies, such as those of synced history.
      * - etc.
      *
      * Maintanance in performed in small chunks at a time to avoid blocking the
@@ -841,6 +1344,19 @@ interface WritableHistoryConnection : ReadableHistoryConnection {
      */
     fun deleteVisitsFor(url: String)
 
+    /**
+     * Deletes all information about the pages on a host, including their
+     * visits. Pages that are bookmarked are kept, but their visits are still
+     * removed. If a page or its visits have been synced, the deletion will
+     * also be synced.
+     *
+     * The host must match exactly, so deleting `example.com` won't delete
+     * visits to `www.example.com`.
+     *
+     * @param host the host whose pages should be removed.
+     */
+    fun deleteVisitsForHost(host: String)
+
     /**
      * Deletes all visits which occurred since the specified time. If the
      * deletion removes the last visit for a place, the place itself will also
@@ -876,9 +1392,60 @@ interface WritableHistoryConnection : ReadableHistoryConnection {
      */
     fun deleteVisit(url: String, visitTimestamp: Long)
 
+    /**
+     * Stores an icon for a page, replacing any icons it had at other URLs. Each size
+     * of an icon is stored separately. Icons no pages use are deleted automatically.
+     *
+     * This is ignored if the page isn't in history or bookmarked.
+     *
+     * @param pageUrl the URL of the page.
+     * @param favicon the icon, whose data may be null if it hasn't been fetched yet.
+     */
+    fun setFaviconForPage(pageUrl: Url, favicon: Favicon)
+
+    /**
+     * Stores the preview image URL and description of a page, usually from its
+     * `og:image` and `og:description` metadata, replacing any it had. Descriptions
+     * longer than 256 bytes are truncated.
+     *
+     * This is ignored if the page isn't in history or bookmarked.
+     *
+     * @param preview the page's preview.
+     */
+    fun setPagePreview(preview: PagePreview)
+
+    /**
+     * Sets an annotation on a page, replacing any value it had with the same name.
+     * Annotations let features store their own metadata about pages, like reader view
+     * state. They're local to this device, and removed along with their page.
+     *
+     * @param pageGuid the GUID of the page, from [getPageGuid].
+     * @param name the name of the annotation. Features should namespace their names,
+     * like `reader/scrollPosition`, so they don't clash.
+     * @param value the value of the annotation.
+     * @param expiration when the annotation should be removed. Setting an annotation
+     * again resets its expiration.
+     * @throws UnknownBookmarkItem if there's no page with the GUID.
+     */
+    fun setPageAnnotation(
+        pageGuid: Guid,
+        name: String,
+        value: AnnotationValue,
+        expiration: AnnotationExpiration,
+    )
+
+    /**
+     * Removes a page's annotation. Does nothing if the page doesn't have one with that name.
+     *
+     * @param pageGuid the GUID of the page.
+     * @param name the name of the annotation.
+     */
+    fun removePageAnnotation(pageGuid: Guid, name: String)
+
     /**
      * Records an accepted autocomplete match, recording the query string,
-     * and chosen URL for subsequent matches.
+     * and chosen URL for subsequent matches. The query string is trimmed and
+     * lowercased.
      *
      * @param searchString The query string
      * @param url The chosen URL string
@@ -945,3 +1512,25 @@ class PlacesManagerCounterMetrics(
         }
     }
 }
+
+/**
+ * Records the timings that the places library measures for each operation, and the errors hit by
+ * sync steps, into Glean.
+ *
+ * The library times the same operations that [PlacesManagerCounterMetrics] counts, so that the
+ * timings are consistent across platforms. The counts stay here, because calls with arguments
+ * that can't be passed to the library, like invalid URLs, fail before it sees them.
+ */
+internal object PlacesGleanMetricsHandler : PlacesMetricsHandler {
+    override fun recordOperation(operation: PlacesOperation, durationMs: ULong, error: String?) {
+        val samples = listOf(durationMs.toLong())
+        when (operation) {
+            PlacesOperation.READ -> PlacesManagerMetrics.readQueryTime.accumulateSamples(samples)
+            PlacesOperation.WRITE -> PlacesManagerMetrics.writeQueryTime.accumulateSamples(samples)
+            PlacesOperation.SYNC -> {
+                PlacesManagerMetrics.syncTime.accumulateSamples(samples)
+                error?.let { PlacesManagerMetrics.syncErrorCount[it].add() }
+            }
+        }
+    }
+}
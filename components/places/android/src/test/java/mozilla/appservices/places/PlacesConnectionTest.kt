@@ -418,6 +418,41 @@ class PlacesConnectionTest {
         assertEquals(db.countBookmarksInTrees(listOf(BookmarkRoot.Unfiled.id)), 2U)
     }
 
+    @Test
+    fun testBookmarkTags() {
+        val url = "https://www.example.com/"
+        db.createBookmarkItem(
+            parentGUID = BookmarkRoot.Unfiled.id,
+            url = url,
+            title = "example",
+        )
+
+        db.tagUrl(url, "news")
+        db.tagUrl(url, " reading ")
+        assertEquals(listOf("news", "reading"), db.getTagsForUrl(url).sorted())
+        assertEquals(listOf(url), db.getUrlsWithTag("news"))
+
+        db.untagUrl(url, "news")
+        assertEquals(listOf("reading"), db.getTagsForUrl(url))
+        assertEquals(listOf<String>(), db.getUrlsWithTag("news"))
+
+        db.removeTag("reading")
+        assertEquals(listOf<String>(), db.getTagsForUrl(url))
+
+        try {
+            db.tagUrl("https://www.example.com/never-visited", "news")
+            fail("Should have thrown")
+        } catch (e: PlacesApiException.UnknownBookmarkItem) {
+            // nothing to do here
+        }
+        try {
+            db.tagUrl(url, " ")
+            fail("Should have thrown")
+        } catch (e: PlacesApiException.InvalidBookmarkOperation) {
+            // nothing to do here
+        }
+    }
+
     @Test
     fun testHistoryMetricsGathering() {
         assertNull(PlacesManagerMetrics.writeQueryCount.testGetValue())
@@ -428,6 +463,7 @@ class PlacesConnectionTest {
         db.noteObservation(VisitObservation(url = "https://www.example.com/3", visitType = VisitType.LINK, at = 200000))
 
         assertEquals(3, PlacesManagerMetrics.writeQueryCount.testGetValue())
+        assertEquals(3, PlacesManagerMetrics.writeQueryTime.testGetValue()!!.values.values.sum())
         assertNull(PlacesManagerMetrics.writeQueryErrorCount["__other__"].testGetValue())
 
         try {
@@ -454,6 +490,7 @@ class PlacesConnectionTest {
         assertEquals(2, infos.size)
 
         assertEquals(5, PlacesManagerMetrics.writeQueryCount.testGetValue())
+        assertEquals(2, PlacesManagerMetrics.readQueryTime.testGetValue()!!.values.values.sum())
         assertNull(PlacesManagerMetrics.writeQueryErrorCount["_other_"].testGetValue())
     }
 
@@ -38,6 +38,7 @@ public class PlacesAPI {
      */
     public init(path: String) throws {
         try api = placesApiNew(dbPath: path)
+        setMetricsHandler(handler: PlacesGleanMetricsHandler())
 
         let uniffiConn = try api.newConnection(connType: ConnectionType.readWrite)
         writeConn = try PlacesWriteConnection(conn: uniffiConn)
@@ -75,6 +76,36 @@ public class PlacesAPI {
             self.api.registerWithSyncManager()
         }
     }
+
+    /**
+     * Returns a handle that interrupts the operations running on all reader
+     * connections, including ones opened later. Use this to cancel long
+     * queries when the user navigates away.
+     */
+    open func newReadInterruptHandle() -> PlacesInterruptHandle {
+        return api.newReadInterruptHandle()
+    }
+
+    /**
+     * Returns a handle that interrupts the operations running on the writer
+     * connection.
+     */
+    open func newWriteInterruptHandle() -> PlacesInterruptHandle {
+        return api.newWriteInterruptHandle()
+    }
+
+    /**
+     * Start a phase of the app's shutdown, and any phases before it, so the
+     * app doesn't wait on us. Syncs are interrupted in the `.background`
+     * phase, reader connections in `.read` and the writer connection in
+     * `.write`.
+     *
+     * - Note: Interrupted connections can't start anything else, and opening
+     *         one after its phase has started throws.
+     */
+    open func shutdown(phase: ShutdownPhase = .write) {
+        placesShutdown(phase: phase)
+    }
 }
 
 /**
@@ -84,7 +115,7 @@ public class PlacesReadConnection {
     fileprivate let queue = DispatchQueue(label: "com.mozilla.places.conn")
     fileprivate var conn: UniffiPlacesConnection
     fileprivate weak var api: PlacesAPI?
-    private let interruptHandle: SqlInterruptHandle
+    private let interruptHandle: PlacesConnectionInterruptHandle
 
     fileprivate init(conn: UniffiPlacesConnection, api: PlacesAPI? = nil) throws {
         self.conn = conn
@@ -234,6 +265,37 @@ public class PlacesReadConnection {
         }
     }
 
+    /**
+     * Returns the bookmarks for the URL with the provided search keyword. Keywords are
+     * matched case-insensitively.
+     */
+    open func getBookmarksWithKeyword(keyword: String) throws -> [BookmarkItemData] {
+        return try queue.sync {
+            try self.checkApi()
+            let items = try self.conn.bookmarksGetByKeyword(keyword: keyword)
+            return toBookmarkItemDataList(items: items)
+        }
+    }
+
+    /**
+     * Returns the URL to load for input that starts with a search keyword, like
+     * "wiki firefox", with the search terms substituted for `%s` in the keyword's URL,
+     * or nil if the input doesn't start with a keyword.
+     */
+    open func getKeywordSearchURL(input: String) throws -> Url? {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.bookmarksGetKeywordSearchUrl(input: input)
+        }
+    }
+
+    open func getKeywordForURL(url: Url) throws -> String? {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.getKeywordForUrl(url: url)
+        }
+    }
+
     /**
      * Returns the list of bookmarks that match the provided search string.
      *
@@ -299,6 +361,28 @@ public class PlacesReadConnection {
         }
     }
 
+    /**
+     * Writes all bookmarks to `path` as a Netscape-format HTML file, which most browsers can
+     * import.
+     */
+    open func exportBookmarksToHTML(path: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try self.conn.bookmarksExportToHtml(path: path)
+        }
+    }
+
+    /**
+     * Writes all bookmarks to `path` in the format of desktop Firefox's JSON backups, which
+     * keeps their GUIDs.
+     */
+    open func exportBookmarksToJSON(path: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try self.conn.bookmarksExportToJson(path: path)
+        }
+    }
+
     open func getLatestHistoryMetadataForUrl(url: Url) throws -> HistoryMetadata? {
         return try queue.sync {
             try self.checkApi()
@@ -343,6 +427,18 @@ public class PlacesReadConnection {
         }
     }
 
+    /**
+     * Returns the origin to autofill in the URL bar for the text typed so far,
+     * like "youtube.com/" for "you", or `nil` if there isn't a bookmarked or
+     * frecent enough one.
+     */
+    open func matchOriginForAutofill(prefix: String) throws -> OriginAutofill? {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.matchOriginForAutofill(prefix: prefix)
+        }
+    }
+
     open func queryAutocomplete(search: String, limit: Int32) throws -> [SearchResult] {
         return try queue.sync {
             try self.checkApi()
@@ -350,6 +446,18 @@ public class PlacesReadConnection {
         }
     }
 
+    /**
+     * Returns only the pages previously chosen, with `acceptResult`, for search
+     * strings starting with `search`, most often chosen first. The pages don't
+     * have to contain `search`.
+     */
+    open func queryInputHistory(search: String, limit: Int32) throws -> [SearchResult] {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.queryInputHistory(search: search, limit: limit)
+        }
+    }
+
     open func getVisitUrlsInRange(start: PlacesTimestamp, end: PlacesTimestamp, includeRemote: Bool)
         throws -> [Url]
     {
@@ -359,19 +467,52 @@ public class PlacesReadConnection {
         }
     }
 
-    open func getVisitInfos(start: PlacesTimestamp, end: PlacesTimestamp, excludeTypes: VisitTransitionSet)
+    open func getVisitInfos(
+        start: PlacesTimestamp,
+        end: PlacesTimestamp,
+        excludeTypes: VisitTransitionSet,
+        source: VisitSource = .all
+    )
         throws -> [HistoryVisitInfo]
     {
         return try queue.sync {
             try self.checkApi()
-            return try self.conn.getVisitInfos(startDate: start, endDate: end, excludeTypes: excludeTypes)
+            return try self.conn.getVisitInfos(
+                startDate: start, endDate: end, excludeTypes: excludeTypes, source: source
+            )
+        }
+    }
+
+    /**
+     * Get a page of the visits in the given time range, most recent first.
+     *
+     * Pass `nil` as the `continuation` for the first page, then the `continuation`
+     * of the previous page, until it's `nil`. Unlike offsets, pages don't skip or
+     * repeat visits if history changes between calls.
+     */
+    open func getVisitInfosPage(
+        start: PlacesTimestamp,
+        end: PlacesTimestamp,
+        excludeTypes: VisitTransitionSet,
+        count: UInt32,
+        continuation: String?,
+        source: VisitSource = .all
+    )
+        throws -> HistoryVisitInfosPage
+    {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.getVisitInfosPage(
+                startDate: start, endDate: end, excludeTypes: excludeTypes,
+                source: source, count: count, continuation: continuation
+            )
         }
     }
 
-    open func getVisitCount(excludedTypes: VisitTransitionSet) throws -> Int64 {
+    open func getVisitCount(excludedTypes: VisitTransitionSet, source: VisitSource = .all) throws -> Int64 {
         return try queue.sync {
             try self.checkApi()
-            return try self.conn.getVisitCount(excludeTypes: excludedTypes)
+            return try self.conn.getVisitCount(excludeTypes: excludedTypes, source: source)
         }
     }
 
@@ -436,6 +577,7 @@ public class PlacesWriteConnection: PlacesReadConnection {
      * - Requesting that the indices in our tables be optimized.
      * - Periodic repair or deletion of corrupted records.
      * - Deleting older visits when the database exceeds dbSizeLimit
+     * - Recalculating stale frecencies, such as those of synced history.
      * - etc.
      *
      * Maintanance in performed in small chunks at a time to avoid blocking the
@@ -462,13 +604,10 @@ public class PlacesWriteConnection: PlacesReadConnection {
     open func runMaintenance(dbSizeLimit: UInt32 = 0) throws {
         return try queue.sync {
             try self.checkApi()
-            // The Kotlin code uses a higher pruneLimit, while Swift is extra conservative.  The
-            // main reason for this is the v119 places incident.  Once we figure that one out more,
-            // let's increase the prune limit here as well.
-            _ = try self.conn.runMaintenancePrune(dbSizeLimit: dbSizeLimit, pruneLimit: 6)
-            try self.conn.runMaintenanceVacuum()
-            try self.conn.runMaintenanceOptimize()
-            try self.conn.runMaintenanceCheckpoint()
+            // The Kotlin code uses a higher pruneLimit, while `runMaintenance` is extra
+            // conservative.  The main reason for this is the v119 places incident.  Once we figure
+            // that one out more, let's increase the prune limit here as well.
+            _ = try self.conn.runMaintenance(dbSizeLimit: dbSizeLimit)
         }
     }
 
@@ -691,6 +830,33 @@ public class PlacesWriteConnection: PlacesReadConnection {
         }
     }
 
+    /**
+     * Reorder the children of a folder, in a single transaction. This is faster than
+     * moving each child with `updateBookmarkNode`, and only marks the folder as
+     * changed for Sync.
+     *
+     * - Parameters:
+     *     - parentGUID: The GUID of the folder.
+     *     - orderedChildGUIDs: The GUIDs of the children, in their new order. GUIDs that
+     *                          aren't children of the folder are ignored, and children
+     *                          that aren't listed keep their order, after the listed ones.
+     *
+     * - Throws:
+     *     - `PlacesApiError.cannotUpdateRoot`: If `parentGUID` is `BookmarkRoots.RootGUID`.
+     *     - `PlacesApiError.noSuchItem`: If `parentGUID` does not refer to a known bookmark.
+     *     - `PlacesApiError.invalidParent`: If `parentGUID` refers to a bookmark which is
+     *                                       not a folder.
+     *     - `PlacesConnectionError.connUseAfterAPIClosed`: if the PlacesAPI that returned this connection
+     *                                                      object has been closed. This indicates API
+     *                                                      misuse.
+     */
+    open func setBookmarkChildrenOrder(parentGUID: Guid, orderedChildGUIDs: [Guid]) throws {
+        try queue.sync {
+            try self.checkApi()
+            try self.conn.setBookmarkChildrenOrder(parentGuid: parentGUID, orderedChildGuids: orderedChildGUIDs)
+        }
+    }
+
     // Helper for the various creation functions.
     // Note: Caller synchronizes
     private func doInsert(item: InsertableBookmarkItem) throws -> Guid {
@@ -788,6 +954,24 @@ public class PlacesWriteConnection: PlacesReadConnection {
         }
     }
 
+    /**
+     * Sets the search keyword of a bookmarked URL, replacing its existing keyword, and
+     * removing the keyword from any other URL.
+     */
+    open func setKeywordForURL(url: Url, keyword: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try self.conn.setKeywordForUrl(url: url, keyword: keyword)
+        }
+    }
+
+    open func removeKeyword(keyword: String) throws {
+        try queue.sync {
+            try self.checkApi()
+            try self.conn.removeKeyword(keyword: keyword)
+        }
+    }
+
     open func acceptResult(searchString: String, url: String) throws {
         return try queue.sync {
             try self.checkApi()
@@ -808,4 +992,72 @@ public class PlacesWriteConnection: PlacesReadConnection {
             return try self.conn.placesHistoryImportFromIos(dbPath: path, lastSyncTimestamp: lastSyncTimestamp)
         }
     }
+
+    /**
+     * Imports history from a copy of Safari's `History.db`, for users switching from Safari.
+     * Visits that can't be imported are counted as failures in the result.
+     */
+    open func migrateHistoryFromSafari(path: String) throws -> HistoryMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesHistoryImportFromSafari(dbPath: path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from Safari's `Bookmarks.plist`, for users switching from Safari.
+     * Bookmarks that can't be imported are counted as failures in the result.
+     */
+    open func migrateBookmarksFromSafari(path: String) throws -> BookmarksMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesBookmarksImportFromSafari(plistPath: path)
+        }
+    }
+
+    /**
+     * Imports history from a copy of Chrome's `History` database, for users switching from
+     * Chrome. Visits that can't be imported are counted as failures in the result.
+     */
+    open func migrateHistoryFromChrome(path: String) throws -> HistoryMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesHistoryImportFromChrome(dbPath: path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from Chrome's JSON `Bookmarks` file, for users switching from Chrome.
+     * Bookmarks that can't be imported are counted as failures in the result.
+     */
+    open func migrateBookmarksFromChrome(path: String) throws -> BookmarksMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesBookmarksImportFromChrome(jsonPath: path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from a Netscape-format HTML file, as exported by most browsers. They're
+     * added to the existing bookmarks. Bookmarks that can't be imported are counted as failures
+     * in the result.
+     */
+    open func importBookmarksFromHTML(path: String) throws -> BookmarksMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesBookmarksImportFromHtml(path: path)
+        }
+    }
+
+    /**
+     * Imports bookmarks from one of desktop Firefox's JSON backups. They're added to the
+     * existing bookmarks, and keep their GUIDs unless they're already in use. Bookmarks that
+     * can't be imported are counted as failures in the result.
+     */
+    open func importBookmarksFromJSON(path: String) throws -> BookmarksMigrationResult {
+        return try queue.sync {
+            try self.checkApi()
+            return try self.conn.placesBookmarksImportFromJson(path: path)
+        }
+    }
 }
@@ -0,0 +1,32 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import Foundation
+import Glean
+
+typealias PlacesManagerMetrics = GleanMetrics.PlacesManager
+
+/**
+ * Records the timings that the places library measures for each operation, and the errors hit by
+ * sync steps, into Glean.
+ *
+ * Unlike on Android, the read and write operations aren't counted here, because calls with
+ * arguments that can't be passed to the library, like invalid URLs, fail before it sees them.
+ */
+class PlacesGleanMetricsHandler: PlacesMetricsHandler {
+    func recordOperation(operation: PlacesOperation, durationMs: UInt64, error: String?) {
+        let samples = [Int64(durationMs)]
+        switch operation {
+        case .read:
+            PlacesManagerMetrics.readQueryTime.accumulateSamples(samples)
+        case .write:
+            PlacesManagerMetrics.writeQueryTime.accumulateSamples(samples)
+        case .sync:
+            PlacesManagerMetrics.syncTime.accumulateSamples(samples)
+            if let error = error {
+                PlacesManagerMetrics.syncErrorCount[error].add()
+            }
+        }
+    }
+}
@@ -273,3 +273,55 @@ CREATE TABLE IF NOT EXISTS moz_places_metadata_search_queries (
     id INTEGER PRIMARY KEY,
     term TEXT NOT NULL UNIQUE
 );
+
+-- A full-text index over the titles and URLs of places, used by `search_history`.
+-- It's an external content table, so it doesn't store copies of them; the
+-- moz_places_*_trigger_fts triggers keep it in sync with moz_places.
+CREATE VIRTUAL TABLE IF NOT EXISTS moz_places_fts USING fts5(
+    title,
+    url,
+    content='moz_places',
+    content_rowid='id',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+-- Favicons, and the pages that use them. Like Desktop, each size of an icon is
+-- its own row. "Root" icons, at `/favicon.ico`, are also used for the other
+-- pages on their origin that don't have icons of their own.
+CREATE TABLE IF NOT EXISTS moz_icons (
+    id INTEGER PRIMARY KEY,
+    icon_url TEXT NOT NULL,
+    width INTEGER NOT NULL DEFAULT 0, -- 0 if the size isn't known.
+    root INTEGER NOT NULL DEFAULT 0,
+    expire_ms INTEGER NOT NULL DEFAULT 0,
+    data BLOB, -- NULL if the icon hasn't been fetched.
+
+    UNIQUE(icon_url, width)
+);
+
+CREATE TABLE IF NOT EXISTS moz_icons_to_pages (
+    page_id INTEGER NOT NULL,
+    icon_id INTEGER NOT NULL,
+
+    PRIMARY KEY(page_id, icon_id),
+    FOREIGN KEY(page_id) REFERENCES moz_places(id) ON DELETE CASCADE,
+    FOREIGN KEY(icon_id) REFERENCES moz_icons(id) ON DELETE CASCADE
+) WITHOUT ROWID;
+
+-- Annotations that features attach to pages, like desktop's page annotations.
+-- Each is a typed value, stored in `content`, with an expiration policy; see
+-- `storage::annotations`.
+CREATE TABLE IF NOT EXISTS moz_annos (
+    place_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    type INTEGER NOT NULL,
+    content NOT NULL,
+    expiration INTEGER NOT NULL DEFAULT 0,
+    date_added INTEGER NOT NULL,
+    last_modified INTEGER NOT NULL,
+
+    PRIMARY KEY(place_id, name),
+    FOREIGN KEY(place_id) REFERENCES moz_places(id) ON DELETE CASCADE
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS moz_annos_nameindex ON moz_annos(name);
@@ -350,3 +350,59 @@ BEGIN
         SELECT id FROM moz_places_metadata pm WHERE pm.search_query_id = OLD.search_query_id
     );
 END;
+
+-- These triggers keep the full-text index of titles and URLs in sync with moz_places.
+-- The index is an external content table, so entries are removed with its special
+-- 'delete' command, which must be given the values that were indexed.
+CREATE TEMP TRIGGER moz_places_afterinsert_trigger_fts
+AFTER INSERT ON moz_places FOR EACH ROW
+BEGIN
+    INSERT INTO moz_places_fts(rowid, title, url)
+    VALUES (NEW.id, NEW.title, NEW.url);
+END;
+
+CREATE TEMP TRIGGER moz_places_afterdelete_trigger_fts
+AFTER DELETE ON moz_places FOR EACH ROW
+BEGIN
+    INSERT INTO moz_places_fts(moz_places_fts, rowid, title, url)
+    VALUES ('delete', OLD.id, OLD.title, OLD.url);
+END;
+
+CREATE TEMP TRIGGER moz_places_afterupdate_trigger_fts
+AFTER UPDATE OF title, url ON moz_places FOR EACH ROW
+BEGIN
+    INSERT INTO moz_places_fts(moz_places_fts, rowid, title, url)
+    VALUES ('delete', OLD.id, OLD.title, OLD.url);
+    INSERT INTO moz_places_fts(rowid, title, url)
+    VALUES (NEW.id, NEW.title, NEW.url);
+END;
+
+-- Icons are deleted once no pages use them, except for root icons, which are
+-- deleted along with their origin.
+CREATE TEMP TRIGGER moz_icons_to_pages_afterdelete_trigger
+AFTER DELETE ON moz_icons_to_pages FOR EACH ROW
+BEGIN
+    DELETE FROM moz_icons
+    WHERE id = OLD.icon_id AND
+          NOT root AND
+          NOT EXISTS(SELECT 1 FROM moz_icons_to_pages WHERE icon_id = OLD.icon_id);
+END;
+
+CREATE TEMP TRIGGER moz_origins_afterdelete_trigger_icons
+AFTER DELETE ON moz_origins FOR EACH ROW
+BEGIN
+    DELETE FROM moz_icons
+    WHERE root AND icon_url = OLD.prefix || OLD.host || '/favicon.ico';
+END;
+
+-- Annotations that expire with history are removed along with the last visit
+-- to their page, even if the page is kept because it's bookmarked. The `1` is
+-- `AnnotationExpiration::WithHistory`.
+CREATE TEMP TRIGGER moz_historyvisits_afterdelete_trigger_annos
+AFTER DELETE ON moz_historyvisits FOR EACH ROW
+BEGIN
+    DELETE FROM moz_annos
+    WHERE place_id = OLD.place_id AND
+          expiration = 1 AND
+          NOT EXISTS(SELECT 1 FROM moz_historyvisits WHERE place_id = OLD.place_id);
+END;
@@ -2,10 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::db::PlacesDb;
+use crate::db::{
+    PlacesDb, MOZ_META_KEY_ORIGIN_FRECENCY_COUNT, MOZ_META_KEY_ORIGIN_FRECENCY_SUM,
+    MOZ_META_KEY_ORIGIN_FRECENCY_SUM_OF_SQUARES,
+};
 use crate::error::Result;
 use crate::ffi::SearchResult as FfiSearchResult;
+use crate::match_impl::find_all_in_string;
 pub use crate::match_impl::{MatchBehavior, SearchBehavior};
+use crate::storage::bookmarks::keywords;
+use crate::storage::get_meta;
 use rusqlite::Row;
 use serde_derive::*;
 use sql_support::ConnExt;
@@ -64,6 +70,8 @@ pub fn search_frecent(conn: &PlacesDb, params: SearchParams) -> Result<Vec<Searc
         &[
             // Try to match on the origin, or the full URL.
             &OriginOrUrl::new(&params.search_string),
+            // Try to match a keyword exactly.
+            &Keyword::new(&params.search_string),
             // query adaptive matches and suggestions, matching Anywhere.
             &Adaptive::with_behavior(
                 &params.search_string,
@@ -80,11 +88,34 @@ pub fn search_frecent(conn: &PlacesDb, params: SearchParams) -> Result<Vec<Searc
     )?;
 
     matches.sort_unstable_by(|a, b| a.url.cmp(&b.url));
-    matches.dedup_by(|a, b| a.url == b.url);
+    // If more than one matcher found the same URL, keep the first match, but
+    // with the reasons from all of them.
+    matches.dedup_by(|dupe, first| {
+        if dupe.url != first.url {
+            return false;
+        }
+        first.reasons.append(&mut dupe.reasons);
+        first.reasons.sort_unstable();
+        first.reasons.dedup();
+        true
+    });
 
     Ok(matches)
 }
 
+/// Queries only the pages that the user previously chose for search strings
+/// starting with this one, like desktop's input history provider. Pages
+/// chosen more often come first, and pages chosen for exactly this search
+/// string count double. The pages don't have to match the search string
+/// themselves.
+pub fn search_input_history(conn: &PlacesDb, params: SearchParams) -> Result<Vec<SearchResult>> {
+    match_with_limit(
+        conn,
+        &[&Adaptive::for_input_history(&params.search_string)],
+        params.limit,
+    )
+}
+
 pub fn match_url(conn: &PlacesDb, query: impl AsRef<str>) -> Result<Option<Url>> {
     let scope = conn.begin_interrupt_scope()?;
     let matcher = OriginOrUrl::new(query.as_ref());
@@ -100,6 +131,121 @@ pub fn match_url(conn: &PlacesDb, query: impl AsRef<str>) -> Result<Option<Url>>
     }
 }
 
+/// An origin to autofill in the URL bar, found by `match_origin_for_autofill`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginAutofill {
+    /// The URL of the origin, with its scheme and a trailing slash, like
+    /// `https://www.youtube.com/`.
+    pub url: Url,
+    /// What to complete the typed text to. This starts with the typed text,
+    /// ignoring case, so it only has a scheme or `www.` if they were typed,
+    /// like `youtube.com/` for "you".
+    pub completion: String,
+    /// The origin's frecency.
+    pub frecency: i64,
+}
+
+// Finds the most frecent origin for a host that starts with the typed one, or
+// with `www.` and the typed one. Hosts need to be bookmarked, or at least as
+// frecent as `:frecencyThreshold`, counting all their origins, but only
+// origins with the typed prefix, if any, are returned.
+const ORIGIN_AUTOFILL_SQL: &str = "
+    WITH hosts(host, host_frecency, bookmarked) AS (
+        SELECT o.host,
+               TOTAL(MAX(o.frecency, 0)),
+               EXISTS(SELECT 1 FROM moz_places h
+                      JOIN moz_origins bo ON bo.id = h.origin_id
+                      WHERE bo.host = o.host AND
+                            h.foreign_count > 0)
+        FROM moz_origins o
+        WHERE o.host BETWEEN :host AND
+                             :host || (CASE WHEN :exact THEN '' ELSE X'FFFF' END)
+           OR (:matchWww AND
+               o.host BETWEEN 'www.' || :host AND
+                              'www.' || :host || (CASE WHEN :exact THEN '' ELSE X'FFFF' END))
+        GROUP BY o.host
+    )
+    SELECT o.prefix || o.host || '/' AS url,
+           o.host AS host,
+           o.frecency AS frecency
+    FROM moz_origins o
+    JOIN hosts ON hosts.host = o.host
+    WHERE (:prefix IS NULL OR o.prefix = :prefix)
+      AND (hosts.bookmarked OR
+           (hosts.host_frecency > 0 AND hosts.host_frecency >= :frecencyThreshold))
+    ORDER BY o.frecency DESC, o.id DESC
+    LIMIT 1
+";
+
+/// Returns the origin to autofill for the text typed in the URL bar, like
+/// desktop's origin autofill: typing "you" completes to "youtube.com/". Only
+/// origins that are bookmarked, or whose frecency is at least one standard
+/// deviation above the average for all origins, are autofilled, so that
+/// rarely visited sites don't get in the way.
+///
+/// The typed text can start with `http://` or `https://`, to only autofill
+/// origins with that scheme, and end with a `/`, to only autofill that exact
+/// host. If it doesn't start with `www.`, origins whose host does are
+/// completed without it. Anything else, like a path, doesn't autofill.
+pub fn match_origin_for_autofill(conn: &PlacesDb, text: &str) -> Result<Option<OriginAutofill>> {
+    let text = text.trim().to_lowercase();
+    let (prefix, remainder) = match split_after_prefix(&text) {
+        (prefix @ ("http://" | "https://"), remainder) => (Some(prefix), remainder),
+        // Something like `localhost:8080`, or an unsupported scheme.
+        _ => (None, text.as_str()),
+    };
+    let (host, exact) = match remainder.strip_suffix('/') {
+        Some(host) => (host, true),
+        None => (remainder, false),
+    };
+    if !looks_like_origin(host) {
+        return Ok(None);
+    }
+    let match_www = !host.starts_with("www.");
+    let mut stmt = conn.prepare_maybe_cached(ORIGIN_AUTOFILL_SQL, true)?;
+    let mut rows = stmt.query(rusqlite::named_params! {
+        ":host": host,
+        ":exact": exact,
+        ":matchWww": match_www,
+        ":prefix": prefix,
+        ":frecencyThreshold": origin_frecency_threshold(conn)?,
+    })?;
+    let row = match rows.next()? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let url = Url::parse(&row.get::<_, String>("url")?)?;
+    let origin_host = row.get::<_, String>("host")?;
+    let origin_host = if match_www {
+        origin_host
+            .strip_prefix("www.")
+            .unwrap_or(&origin_host)
+            .to_owned()
+    } else {
+        origin_host
+    };
+    Ok(Some(OriginAutofill {
+        url,
+        completion: format!("{}{}/", prefix.unwrap_or_default(), origin_host),
+        frecency: row.get("frecency")?,
+    }))
+}
+
+/// Returns the average frecency of all the origins with a positive frecency,
+/// plus one standard deviation, from the stats that the origin triggers keep.
+fn origin_frecency_threshold(conn: &PlacesDb) -> Result<f64> {
+    // The sum of squares can overflow into a real, so we read them all as
+    // reals.
+    let stat = |key: &str| -> Result<f64> { Ok(get_meta::<f64>(conn, key)?.unwrap_or(0.0)) };
+    let count = stat(MOZ_META_KEY_ORIGIN_FRECENCY_COUNT)?;
+    if count <= 0.0 {
+        return Ok(0.0);
+    }
+    let mean = stat(MOZ_META_KEY_ORIGIN_FRECENCY_SUM)? / count;
+    let variance = stat(MOZ_META_KEY_ORIGIN_FRECENCY_SUM_OF_SQUARES)? / count - mean * mean;
+    Ok(mean + variance.max(0.0).sqrt())
+}
+
 fn match_with_limit(
     conn: &PlacesDb,
     matchers: &[&dyn Matcher],
@@ -121,8 +267,13 @@ fn match_with_limit(
 }
 
 /// Records an accepted autocomplete match, recording the query string,
-/// and chosen URL for subsequent matches.
+/// and chosen URL for subsequent matches. Like desktop, the query string is
+/// trimmed and lowercased, so that input history matches ignore case.
 pub fn accept_result(conn: &PlacesDb, search_string: &str, url: &Url) -> Result<()> {
+    let search_string = normalize_input(search_string);
+    if search_string.is_empty() {
+        return Ok(());
+    }
     // See `nsNavHistory::AutoCompleteFeedback`.
     conn.execute(
         "INSERT OR REPLACE INTO moz_inputhistory(place_id, input, use_count)
@@ -139,6 +290,12 @@ pub fn accept_result(conn: &PlacesDb, search_string: &str, url: &Url) -> Result<
     Ok(())
 }
 
+/// Returns the form of a search string that's stored in, and matched against,
+/// the input history.
+pub(crate) fn normalize_input(search_string: &str) -> String {
+    search_string.trim().to_lowercase()
+}
+
 pub fn split_after_prefix(href: &str) -> (&str, &str) {
     // Only search up to 64 bytes (matches desktop behavior)
     let haystack = &href.as_bytes()[..href.len().min(64)];
@@ -179,6 +336,54 @@ fn looks_like_origin(string: &str) -> bool {
         })
 }
 
+/// Why a search result matched the search string.
+#[derive(Debug, Clone, Copy, Serialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum MatchReason {
+    /// The search string is the start of the result's origin.
+    Origin,
+    /// The search string is the start of the result's URL, or some of its
+    /// words appear in the URL.
+    Url,
+    /// Some of the words in the search string appear in the title.
+    Title,
+    /// The result is bookmarked.
+    Bookmark,
+    /// The search string is a keyword for the result.
+    Keyword,
+    /// The user previously chose this result for a similar search string.
+    InputHistory,
+}
+
+/// A range of bytes in a string, used to highlight the parts of a search
+/// result that matched the search string.
+#[derive(Debug, Clone, Copy, Serialize, Eq, PartialEq)]
+pub struct MatchRange {
+    /// The offset of the first byte of the match.
+    pub start: u32,
+    /// The offset of the byte after the last byte of the match.
+    pub end: u32,
+}
+
+/// Finds the parts of `text` that match any of the words in `search_string`,
+/// ignoring case, for highlighting. Overlapping and adjacent matches are
+/// merged, and the ranges are returned in order.
+pub fn find_highlights(search_string: &str, text: &str) -> Vec<MatchRange> {
+    let mut ranges = search_string
+        .split_ascii_whitespace()
+        .flat_map(|token| find_all_in_string(token, text))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+    let mut highlights: Vec<MatchRange> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        let (start, end) = (start as u32, end as u32);
+        match highlights.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => highlights.push(MatchRange { start, end }),
+        }
+    }
+    highlights
+}
+
 #[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct SearchResult {
     /// The search string for this match.
@@ -198,49 +403,92 @@ pub struct SearchResult {
 
     /// A frecency score for this match.
     pub frecency: i64,
+
+    /// Why this result matched, in a stable order.
+    pub reasons: Vec<MatchReason>,
+
+    /// The parts of `title` that matched the search string.
+    pub title_highlights: Vec<MatchRange>,
+
+    /// The parts of `url` that matched the search string.
+    pub url_highlights: Vec<MatchRange>,
 }
 
 impl SearchResult {
-    /// Default search behaviors from Desktop: HISTORY, BOOKMARK, OPENPAGE, SEARCHES.
-    /// Default match behavior: MATCH_BOUNDARY_ANYWHERE.
-    pub fn from_adaptive_row(row: &rusqlite::Row<'_>) -> Result<Self> {
-        let search_string = row.get::<_, String>("searchString")?;
-        let _place_id = row.get::<_, i64>("id")?;
-        let url = row.get::<_, String>("url")?;
-        let history_title = row.get::<_, Option<String>>("title")?;
-        let bookmark_title = row.get::<_, Option<String>>("btitle")?;
-        let frecency = row.get::<_, i64>("frecency")?;
-        let title = bookmark_title.or(history_title).unwrap_or_default();
-        let url = Url::parse(&url)?;
-
-        Ok(Self {
+    fn new(
+        search_string: String,
+        url: Url,
+        title: String,
+        frecency: i64,
+        mut reasons: Vec<MatchReason>,
+    ) -> Self {
+        let title_highlights = find_highlights(&search_string, &title);
+        let url_highlights = find_highlights(&search_string, url.as_str());
+        reasons.sort_unstable();
+        reasons.dedup();
+        Self {
             search_string,
             url,
             title,
             icon_url: None,
             frecency,
-        })
+            reasons,
+            title_highlights,
+            url_highlights,
+        }
     }
 
-    pub fn from_suggestion_row(row: &rusqlite::Row<'_>) -> Result<Self> {
+    /// Builds a result for a page found by matching the words in the search
+    /// string against its title and URL.
+    fn from_page_row(row: &rusqlite::Row<'_>, mut reasons: Vec<MatchReason>) -> Result<Self> {
         let search_string = row.get::<_, String>("searchString")?;
         let url = row.get::<_, String>("url")?;
-
         let history_title = row.get::<_, Option<String>>("title")?;
         let bookmark_title = row.get::<_, Option<String>>("btitle")?;
+        let bookmarked = row.get::<_, bool>("bookmarked")?;
+        let frecency = row.get::<_, i64>("frecency")?;
         let title = bookmark_title.or(history_title).unwrap_or_default();
-
         let url = Url::parse(&url)?;
 
+        if bookmarked {
+            reasons.push(MatchReason::Bookmark);
+        }
+        let mut result = Self::new(search_string, url, title, frecency, reasons);
+        if !result.title_highlights.is_empty() {
+            result.reasons.push(MatchReason::Title);
+        }
+        if !result.url_highlights.is_empty() {
+            result.reasons.push(MatchReason::Url);
+        }
+        result.reasons.sort_unstable();
+        Ok(result)
+    }
+
+    /// Default search behaviors from Desktop: HISTORY, BOOKMARK, OPENPAGE, SEARCHES.
+    /// Default match behavior: MATCH_BOUNDARY_ANYWHERE.
+    pub fn from_adaptive_row(row: &rusqlite::Row<'_>) -> Result<Self> {
+        Self::from_page_row(row, vec![MatchReason::InputHistory])
+    }
+
+    pub fn from_suggestion_row(row: &rusqlite::Row<'_>) -> Result<Self> {
+        Self::from_page_row(row, vec![])
+    }
+
+    pub fn from_keyword_row(row: &rusqlite::Row<'_>) -> Result<Self> {
+        let search_string = row.get::<_, String>("searchString")?;
+        let url = row.get::<_, String>("url")?;
+        let title = row.get::<_, Option<String>>("title")?;
         let frecency = row.get::<_, i64>("frecency")?;
 
-        Ok(Self {
+        let url = Url::parse(&url)?;
+
+        Ok(Self::new(
             search_string,
             url,
-            title,
-            icon_url: None,
+            title.unwrap_or_default(),
             frecency,
-        })
+            vec![MatchReason::Keyword],
+        ))
     }
 
     pub fn from_origin_row(row: &rusqlite::Row<'_>) -> Result<Self> {
@@ -251,13 +499,13 @@ impl SearchResult {
 
         let url = Url::parse(&url)?;
 
-        Ok(Self {
+        Ok(Self::new(
             search_string,
             url,
-            title: display_url,
-            icon_url: None,
+            display_url,
             frecency,
-        })
+            vec![MatchReason::Origin],
+        ))
     }
 
     pub fn from_url_row(row: &rusqlite::Row<'_>) -> Result<Self> {
@@ -285,13 +533,13 @@ impl SearchResult {
             }
         };
 
-        Ok(Self {
+        Ok(Self::new(
             search_string,
             url,
-            title: display_url,
-            icon_url: None,
+            display_url,
             frecency,
-        })
+            vec![MatchReason::Url],
+        ))
     }
 }
 
@@ -301,6 +549,9 @@ impl From<SearchResult> for FfiSearchResult {
             url: res.url,
             title: res.title,
             frecency: res.frecency,
+            reasons: res.reasons,
+            title_highlights: res.title_highlights,
+            url_highlights: res.url_highlights,
         }
     }
 }
@@ -422,10 +673,63 @@ impl<'query> Matcher for OriginOrUrl<'query> {
     }
 }
 
+struct Keyword<'query> {
+    query: &'query str,
+}
+
+impl<'query> Keyword<'query> {
+    pub fn new(query: &'query str) -> Keyword<'query> {
+        Keyword { query }
+    }
+}
+
+impl<'query> Matcher for Keyword<'query> {
+    fn search(&self, conn: &PlacesDb, _: u32) -> Result<Vec<SearchResult>> {
+        // Like desktop, the first word is the keyword, and the rest are the
+        // search terms to substitute into its URL.
+        let (keyword, param) = keywords::split_keyword_input(self.query);
+        if keyword.is_empty() {
+            return Ok(vec![]);
+        }
+        let keyword = keyword.to_lowercase();
+        let results = query_flat_rows_and_then(
+            conn,
+            "
+            SELECT h.url AS url,
+                   IFNULL((SELECT title FROM moz_bookmarks
+                           WHERE fk = h.id AND
+                                 title NOT NULL
+                           ORDER BY lastModified DESC
+                           LIMIT 1), h.title) AS title,
+                   h.frecency AS frecency,
+                   :searchString AS searchString
+            FROM moz_keywords k
+            JOIN moz_places h ON h.id = k.place_id
+            WHERE k.keyword = :keyword",
+            &[
+                (":searchString", &self.query as &dyn rusqlite::ToSql),
+                (":keyword", &keyword),
+            ],
+            SearchResult::from_keyword_row,
+        )?;
+        Ok(results
+            .into_iter()
+            .filter_map(|mut result| {
+                let href = keywords::substitute_keyword_param(result.url.as_str(), param)?;
+                result.url = Url::parse(&href).ok()?;
+                Some(result)
+            })
+            .collect())
+    }
+}
+
 struct Adaptive<'query> {
     query: &'query str,
     match_behavior: MatchBehavior,
     search_behavior: SearchBehavior,
+    // Whether the pages must match the query, as well as having been chosen
+    // for it.
+    match_pages: bool,
 }
 
 impl<'query> Adaptive<'query> {
@@ -438,12 +742,29 @@ impl<'query> Adaptive<'query> {
             query,
             match_behavior,
             search_behavior,
+            match_pages: true,
+        }
+    }
+
+    /// Matches every page chosen for the query, or a longer one that starts
+    /// with it, even if the page doesn't contain the query.
+    pub fn for_input_history(query: &'query str) -> Adaptive<'query> {
+        Adaptive {
+            match_pages: false,
+            ..Adaptive::with_behavior(query, MatchBehavior::Anywhere, SearchBehavior::default())
         }
     }
 }
 
 impl<'query> Matcher for Adaptive<'query> {
     fn search(&self, conn: &PlacesDb, max_results: u32) -> Result<Vec<SearchResult>> {
+        let input = normalize_input(self.query);
+        if input.is_empty() {
+            return Ok(vec![]);
+        }
+        // An empty match string matches every page, so `AUTOCOMPLETE_MATCH`
+        // only filters the pages by the search behavior.
+        let match_string = if self.match_pages { self.query } else { "" };
         query_flat_rows_and_then(
             conn,
             "
@@ -464,14 +785,14 @@ impl<'query> Matcher for Adaptive<'query> {
                    h.frecency as frecency,
                    :searchString AS searchString
             FROM (
-              SELECT ROUND(MAX(use_count) * (1 + (input = :searchString)), 1) AS rank,
+              SELECT ROUND(MAX(use_count) * (1 + (input = :input)), 1) AS rank,
                      place_id
               FROM moz_inputhistory
-              WHERE input BETWEEN :searchString AND :searchString || X'FFFF'
+              WHERE input BETWEEN :input AND :input || X'FFFF'
               GROUP BY place_id
             ) AS i
             JOIN moz_places h ON h.id = i.place_id
-            WHERE AUTOCOMPLETE_MATCH(:searchString, h.url,
+            WHERE AUTOCOMPLETE_MATCH(:matchString, h.url,
                                      IFNULL(btitle, h.title), tags,
                                      visit_count, h.typed, bookmarked,
                                      NULL, :matchBehavior, :searchBehavior)
@@ -479,6 +800,8 @@ impl<'query> Matcher for Adaptive<'query> {
             LIMIT :maxResults",
             &[
                 (":searchString", &self.query as &dyn rusqlite::ToSql),
+                (":input", &input),
+                (":matchString", &match_string),
                 (":matchBehavior", &self.match_behavior),
                 (":searchBehavior", &self.search_behavior),
                 (":maxResults", &max_results),
@@ -683,9 +1006,270 @@ mod tests {
                 title: "example.com/".into(),
                 icon_url: None,
                 frecency: 2000,
+                reasons: vec![MatchReason::Origin],
+                title_highlights: vec![MatchRange { start: 0, end: 7 }],
+                url_highlights: vec![MatchRange { start: 7, end: 14 }],
             }]
         );
     }
+    #[test]
+    fn search_match_reasons() -> Result<()> {
+        use crate::storage::bookmarks::{
+            insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableItem,
+        };
+
+        let conn = new_mem_connection();
+
+        let url = Url::parse("https://www.mozilla.org/firefox/")?;
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone())
+                .with_title("Get Firefox Browser".to_string())
+                .with_visit_type(VisitType::Typed)
+                .with_at(Timestamp::now()),
+        )?;
+        insert_bookmark(
+            &conn,
+            InsertableItem::Bookmark {
+                b: InsertableBookmark {
+                    parent_guid: BookmarkRootGuid::Unfiled.into(),
+                    position: BookmarkPosition::Append,
+                    date_added: None,
+                    last_modified: None,
+                    guid: None,
+                    url: url.clone(),
+                    title: Some("Firefox".into()),
+                },
+            },
+        )?;
+        conn.execute(
+            "INSERT INTO moz_keywords(keyword, place_id)
+             SELECT 'ff', id FROM moz_places WHERE url = :url",
+            &[(":url", &url.as_str())],
+        )?;
+
+        let search = |search_string: &str| {
+            search_frecent(
+                &conn,
+                SearchParams {
+                    search_string: search_string.into(),
+                    limit: 10,
+                },
+            )
+        };
+
+        let results = search("moz fire")?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, url);
+        assert_eq!(results[0].title, "Firefox");
+        assert_eq!(
+            results[0].reasons,
+            vec![MatchReason::Url, MatchReason::Title, MatchReason::Bookmark]
+        );
+        assert_eq!(
+            results[0].title_highlights,
+            vec![MatchRange { start: 0, end: 4 }]
+        );
+        assert_eq!(
+            results[0].url_highlights,
+            vec![
+                MatchRange { start: 12, end: 15 },
+                MatchRange { start: 24, end: 28 }
+            ]
+        );
+
+        let results = search("ff")?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, url);
+        assert_eq!(results[0].reasons, vec![MatchReason::Keyword]);
+        assert!(results[0].title_highlights.is_empty());
+
+        // The keyword's URL doesn't have a placeholder for search terms.
+        assert!(search("FF nightly")?
+            .iter()
+            .all(|result| !result.reasons.contains(&MatchReason::Keyword)));
+
+        // Results found by more than one matcher have all their reasons.
+        accept_result(&conn, "fire", &url)?;
+        let results = search("fire")?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].reasons,
+            vec![
+                MatchReason::Url,
+                MatchReason::Title,
+                MatchReason::Bookmark,
+                MatchReason::InputHistory
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn search_input_history_prefers_chosen_pages() -> Result<()> {
+        let conn = new_mem_connection();
+        let nytimes = Url::parse("https://www.nytimes.com/")?;
+        let example = Url::parse("https://news.example.com/")?;
+        for (url, title) in [(&nytimes, "The New York Times"), (&example, "Example")] {
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone())
+                    .with_title(title.to_string())
+                    .with_visit_type(VisitType::Link)
+                    .with_at(Timestamp::now()),
+            )?;
+        }
+        let search = |search_string: &str| {
+            search_input_history(
+                &conn,
+                SearchParams {
+                    search_string: search_string.into(),
+                    limit: 10,
+                },
+            )
+        };
+        assert!(search("news")?.is_empty());
+
+        // The input is trimmed and lowercased, and the page doesn't have to
+        // contain it.
+        accept_result(&conn, " NEWS ", &nytimes)?;
+        accept_result(&conn, "news", &nytimes)?;
+        accept_result(&conn, "new", &example)?;
+        accept_result(&conn, "  ", &example)?;
+        assert_eq!(
+            conn.query_one::<i64>("SELECT COUNT(*) FROM moz_inputhistory")?,
+            2
+        );
+
+        let results = search("News")?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, nytimes);
+        assert_eq!(results[0].reasons, vec![MatchReason::InputHistory]);
+        // The full search only includes chosen pages that match the search
+        // string.
+        let has_input_history_match = |search_string: &str| -> Result<bool> {
+            Ok(search_frecent(
+                &conn,
+                SearchParams {
+                    search_string: search_string.into(),
+                    limit: 10,
+                },
+            )?
+            .iter()
+            .any(|result| {
+                result.url == nytimes && result.reasons.contains(&MatchReason::InputHistory)
+            }))
+        };
+        assert!(!has_input_history_match("news")?);
+        assert!(has_input_history_match("new")?);
+
+        // A page chosen for exactly the search string beats one chosen more
+        // often for a longer one.
+        let results = search("new")?;
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.url.clone())
+                .collect::<Vec<_>>(),
+            vec![example, nytimes]
+        );
+        assert!(search("")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn match_origin_for_autofill_completes_frecent_origins() -> Result<()> {
+        use crate::storage::bookmarks::{
+            insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableItem,
+        };
+
+        let conn = new_mem_connection();
+        let now = Timestamp::now();
+        for i in 0..10 {
+            apply_observation(
+                &conn,
+                VisitObservation::new(Url::parse(&format!(
+                    "https://www.youtube.com/watch?v={}",
+                    i
+                ))?)
+                .with_visit_type(VisitType::Typed)
+                .with_at(Timestamp(now.0 - i)),
+            )?;
+        }
+        for url in ["https://yourdomain.example/", "https://www.example.com/"] {
+            apply_observation(
+                &conn,
+                VisitObservation::new(Url::parse(url)?)
+                    .with_visit_type(VisitType::Link)
+                    .with_at(now),
+            )?;
+        }
+        insert_bookmark(
+            &conn,
+            InsertableItem::Bookmark {
+                b: InsertableBookmark {
+                    parent_guid: BookmarkRootGuid::Unfiled.into(),
+                    position: BookmarkPosition::Append,
+                    date_added: None,
+                    last_modified: None,
+                    guid: None,
+                    url: Url::parse("https://yodel.example/")?,
+                    title: None,
+                },
+            },
+        )?;
+
+        let completion = |text: &str| -> Result<Option<String>> {
+            Ok(match_origin_for_autofill(&conn, text)?.map(|autofill| autofill.completion))
+        };
+
+        let autofill = match_origin_for_autofill(&conn, "you")?.expect("Should autofill");
+        assert_eq!(autofill.url.as_str(), "https://www.youtube.com/");
+        assert_eq!(autofill.completion, "youtube.com/");
+        assert!(autofill.frecency > 0);
+
+        assert_eq!(completion(" YOU ")?.as_deref(), Some("youtube.com/"));
+        assert_eq!(completion("www.you")?.as_deref(), Some("www.youtube.com/"));
+        assert_eq!(
+            completion("https://you")?.as_deref(),
+            Some("https://youtube.com/")
+        );
+        assert_eq!(completion("youtube.com/")?.as_deref(), Some("youtube.com/"));
+        // Bookmarked origins are autofilled even if they aren't frecent.
+        assert_eq!(completion("yod")?.as_deref(), Some("yodel.example/"));
+
+        // The only origin for this host is below the frecency threshold.
+        assert_eq!(completion("your")?, None);
+        // There's no `http://` origin for this host.
+        assert_eq!(completion("http://you")?, None);
+        for text in [
+            "",
+            "youtube.co/",
+            "youtube.com/watch",
+            "you tube",
+            "ftp://you",
+        ] {
+            assert_eq!(completion(text)?, None, "{:?} shouldn't autofill", text);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn find_highlights_merges_overlapping_matches() {
+        assert_eq!(
+            find_highlights("fire Firefox ox", "Get Firefox Browser"),
+            vec![MatchRange { start: 4, end: 11 }]
+        );
+        assert_eq!(
+            find_highlights("get  ow", "Get Firefox Browser"),
+            vec![
+                MatchRange { start: 0, end: 3 },
+                MatchRange { start: 14, end: 16 }
+            ]
+        );
+        assert!(find_highlights("", "Get Firefox Browser").is_empty());
+    }
+
     #[test]
     fn search_unicode() {
         let conn = new_mem_connection();
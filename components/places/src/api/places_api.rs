@@ -5,13 +5,17 @@
 use crate::bookmark_sync::BookmarksSyncEngine;
 use crate::db::db::{PlacesDb, SharedPlacesDb};
 use crate::error::*;
-use crate::history_sync::HistorySyncEngine;
+use crate::history_sync::{HistorySyncConfig, HistorySyncEngine};
 use crate::storage::{
     self, bookmarks::bookmark_sync, delete_meta, get_meta, history::history_sync, put_meta,
 };
 use crate::util::normalize_path;
 use error_support::handle_error;
-use interrupt_support::register_interrupt;
+pub use interrupt_support::ShutdownPhase;
+use interrupt_support::{
+    in_shutdown_phase, register_interrupt_for_phase, shutdown_phase, Interrupted,
+    SqlInterruptHandle,
+};
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
 use rusqlite::OpenFlags;
@@ -77,7 +81,10 @@ fn create_sync_engine(
     let conn = places_api.get_sync_connection()?;
     match engine_id {
         SyncEngineId::Bookmarks => Ok(Box::new(BookmarksSyncEngine::new(conn)?)),
-        SyncEngineId::History => Ok(Box::new(HistorySyncEngine::new(conn)?)),
+        SyncEngineId::History => Ok(Box::new(HistorySyncEngine::with_config(
+            conn,
+            places_api.history_sync_config(),
+        )?)),
         _ => unreachable!("can't provide unknown engine: {}", engine_id),
     }
 }
@@ -112,6 +119,16 @@ impl ConnectionType {
             ConnectionType::Sync => common_flags | OpenFlags::SQLITE_OPEN_READ_WRITE,
         }
     }
+
+    /// The shutdown phase in which connections of this type are interrupted:
+    /// syncs are abandoned first, then queries, and writes last.
+    pub fn shutdown_phase(self) -> ShutdownPhase {
+        match self {
+            ConnectionType::Sync => ShutdownPhase::Background,
+            ConnectionType::ReadOnly => ShutdownPhase::Read,
+            ConnectionType::ReadWrite => ShutdownPhase::Write,
+        }
+    }
 }
 
 // We only allow a single PlacesApi per filename.
@@ -121,6 +138,62 @@ lazy_static! {
 
 static ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
+fn err_if_shut_down(conn_type: ConnectionType) -> Result<()> {
+    if in_shutdown_phase(conn_type.shutdown_phase()) {
+        return Err(Interrupted.into());
+    }
+    Ok(())
+}
+
+/// The interrupt handles of the connections opened by a `PlacesApi`, tagged
+/// with their connection type. Each handle is also registered for the
+/// shutdown phase of its connection type, so `places_shutdown` interrupts it.
+#[derive(Default)]
+struct ConnectionInterrupts {
+    handles: Mutex<Vec<(ConnectionType, Weak<SqlInterruptHandle>)>>,
+}
+
+impl ConnectionInterrupts {
+    fn register(&self, db: &PlacesDb) {
+        let handle = db.new_interrupt_handle();
+        register_interrupt_for_phase(
+            Arc::<SqlInterruptHandle>::downgrade(&handle),
+            db.conn_type().shutdown_phase(),
+        );
+        let mut handles = self.handles.lock();
+        // Forget the handles of connections that have been closed.
+        handles.retain(|(_, handle)| handle.strong_count() > 0);
+        handles.push((db.conn_type(), Arc::downgrade(&handle)));
+    }
+
+    fn interrupt(&self, should_interrupt: impl Fn(ConnectionType) -> bool) {
+        for (conn_type, handle) in self.handles.lock().iter() {
+            if should_interrupt(*conn_type) {
+                if let Some(handle) = handle.upgrade() {
+                    handle.interrupt();
+                }
+            }
+        }
+    }
+}
+
+/// Interrupts the operations running on all of a `PlacesApi`'s reader
+/// connections, or on its writer connection. Unlike a connection's own
+/// interrupt handle, this also covers connections opened after the handle
+/// was created, so apps can hold one handle for, say, cancelling queries when
+/// the user navigates away.
+pub struct PlacesInterruptHandle {
+    interrupts: Arc<ConnectionInterrupts>,
+    conn_type: ConnectionType,
+}
+
+impl PlacesInterruptHandle {
+    pub fn interrupt(&self) {
+        self.interrupts
+            .interrupt(|conn_type| conn_type == self.conn_type);
+    }
+}
+
 pub struct SyncState {
     pub mem_cached_state: Cell<MemoryCachedState>,
     pub disk_cached_state: Cell<Option<String>>,
@@ -134,6 +207,15 @@ pub fn places_api_new(db_name: impl AsRef<Path>) -> ApiResult<Arc<PlacesApi>> {
     PlacesApi::new(db_name)
 }
 
+/// Starts a phase of the app's shutdown, and any phases before it. The
+/// operations running on connections whose type belongs to those phases are
+/// interrupted, and those connections can't start new ones, nor can new
+/// connections of those types be opened. Other components' connections that
+/// are registered for shutdown are interrupted in the `Write` phase.
+pub fn places_shutdown(phase: ShutdownPhase) {
+    shutdown_phase(phase)
+}
+
 /// The entry-point to the places API. This object gives access to database
 /// connections and other helpers. It enforces that only 1 write connection
 /// can exist to the database at once.
@@ -151,6 +233,8 @@ pub struct PlacesApi {
     // - The outer mutex synchronizes the `get_sync_connection()` operation.  If multiple threads
     //   ran that at the same time there would be issues.
     sync_connection: Mutex<Weak<SharedPlacesDb>>,
+    history_sync_config: Mutex<HistorySyncConfig>,
+    interrupts: Arc<ConnectionInterrupts>,
     id: usize,
 }
 
@@ -185,11 +269,15 @@ impl PlacesApi {
                     id,
                     coop_tx_lock.clone(),
                 )?;
+                let interrupts = Arc::new(ConnectionInterrupts::default());
+                interrupts.register(&connection);
                 let new = PlacesApi {
                     db_name: db_name.clone(),
                     write_connection: Mutex::new(Some(connection)),
                     sync_state: Mutex::new(None),
                     sync_connection: Mutex::new(Weak::new()),
+                    history_sync_config: Mutex::new(HistorySyncConfig::default()),
+                    interrupts,
                     id,
                     coop_tx_lock,
                 };
@@ -207,15 +295,18 @@ impl PlacesApi {
 
     /// Open a connection to the database.
     pub fn open_connection(&self, conn_type: ConnectionType) -> Result<PlacesDb> {
+        err_if_shut_down(conn_type)?;
         match conn_type {
             ConnectionType::ReadOnly => {
                 // make a new one - we can have as many of these as we want.
-                PlacesDb::open(
+                let db = PlacesDb::open(
                     self.db_name.clone(),
                     ConnectionType::ReadOnly,
                     self.id,
                     self.coop_tx_lock.clone(),
-                )
+                )?;
+                self.interrupts.register(&db);
+                Ok(db)
             }
             ConnectionType::ReadWrite => {
                 // We only allow one of these.
@@ -239,6 +330,7 @@ impl PlacesApi {
     //   - The mutex is then wrapped in an Arc<>.  If the last Arc<> returned is still alive, then
     //     get_sync_connection() will reuse it.
     pub fn get_sync_connection(&self) -> Result<Arc<SharedPlacesDb>> {
+        err_if_shut_down(ConnectionType::Sync)?;
         // First step: lock the outer mutex
         let mut conn = self.sync_connection.lock();
         match conn.upgrade() {
@@ -246,13 +338,14 @@ impl PlacesApi {
             Some(db) => Ok(db),
             // If not, create a new connection
             None => {
-                let db = Arc::new(SharedPlacesDb::new(PlacesDb::open(
+                let db = PlacesDb::open(
                     self.db_name.clone(),
                     ConnectionType::Sync,
                     self.id,
                     self.coop_tx_lock.clone(),
-                )?));
-                register_interrupt(Arc::<SharedPlacesDb>::downgrade(&db));
+                )?;
+                self.interrupts.register(&db);
+                let db = Arc::new(SharedPlacesDb::new(db));
                 // Store a weakref for next time
                 *conn = Arc::downgrade(&db);
                 Ok(db)
@@ -295,6 +388,32 @@ impl PlacesApi {
         *PLACES_API_FOR_SYNC_MANAGER.lock() = Arc::downgrade(&self);
     }
 
+    /// Sets the limits used by history syncs that start after this call,
+    /// including those started by the sync manager.
+    pub fn set_history_sync_config(&self, config: HistorySyncConfig) {
+        *self.history_sync_config.lock() = config;
+    }
+
+    pub fn history_sync_config(&self) -> HistorySyncConfig {
+        *self.history_sync_config.lock()
+    }
+
+    /// Returns a handle that interrupts all our reader connections.
+    pub fn new_read_interrupt_handle(&self) -> Arc<PlacesInterruptHandle> {
+        Arc::new(PlacesInterruptHandle {
+            interrupts: Arc::clone(&self.interrupts),
+            conn_type: ConnectionType::ReadOnly,
+        })
+    }
+
+    /// Returns a handle that interrupts our writer connection.
+    pub fn new_write_interrupt_handle(&self) -> Arc<PlacesInterruptHandle> {
+        Arc::new(PlacesInterruptHandle {
+            interrupts: Arc::clone(&self.interrupts),
+            conn_type: ConnectionType::ReadWrite,
+        })
+    }
+
     // NOTE: These should be deprecated as soon as possible - that will be once
     // all consumers have been updated to use the .sync() method below, and/or
     // we have implemented the sync manager and migrated consumers to that.
@@ -306,7 +425,7 @@ impl PlacesApi {
         self.do_sync_one(
             "history",
             move |conn, mem_cached_state, disk_cached_state| {
-                let engine = HistorySyncEngine::new(conn)?;
+                let engine = HistorySyncEngine::with_config(conn, self.history_sync_config())?;
                 Ok(sync_multiple(
                     &[&engine],
                     disk_cached_state,
@@ -409,7 +528,8 @@ impl PlacesApi {
         let sync_state = guard.as_ref().unwrap();
 
         let bm_engine = BookmarksSyncEngine::new(conn.clone())?;
-        let history_engine = HistorySyncEngine::new(conn.clone())?;
+        let history_engine =
+            HistorySyncEngine::with_config(conn.clone(), self.history_sync_config())?;
         let mut mem_cached_state = sync_state.mem_cached_state.take();
         let mut disk_cached_state = sync_state.disk_cached_state.take();
 
@@ -603,4 +723,34 @@ mod tests {
         // Make sure we can open it again.
         assert!(api.open_connection(ConnectionType::ReadWrite).is_ok());
     }
+
+    #[test]
+    fn test_interrupt_handles() {
+        let api = new_mem_api();
+        let writer = api
+            .open_connection(ConnectionType::ReadWrite)
+            .expect("should get writer");
+        let reader = api
+            .open_connection(ConnectionType::ReadOnly)
+            .expect("should get reader");
+        let read_handle = api.new_read_interrupt_handle();
+        let write_handle = api.new_write_interrupt_handle();
+
+        let reader_scope = reader.begin_interrupt_scope().unwrap();
+        let writer_scope = writer.begin_interrupt_scope().unwrap();
+        read_handle.interrupt();
+        assert!(reader_scope.was_interrupted());
+        assert!(!writer_scope.was_interrupted());
+
+        // Readers opened after the handle was created are interrupted too.
+        let reader2 = api
+            .open_connection(ConnectionType::ReadOnly)
+            .expect("should get another reader");
+        let reader2_scope = reader2.begin_interrupt_scope().unwrap();
+        write_handle.interrupt();
+        assert!(writer_scope.was_interrupted());
+        assert!(!reader2_scope.was_interrupted());
+        read_handle.interrupt();
+        assert!(reader2_scope.was_interrupted());
+    }
 }
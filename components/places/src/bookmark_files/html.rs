@@ -0,0 +1,627 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Netscape-format bookmarks HTML, as exported and imported by every
+//! browser. The format is a `<DL>` list per folder, with a `<DT>` for each
+//! item: `<A>` for a bookmark, `<H3>` for a folder, followed by a `<DL>` of
+//! its children, and `<HR>` for a separator.
+//!
+//! The file doesn't have GUIDs, so imported items always get new ones.
+
+use super::{fetch_roots, import_items, FileItem, ImportParent};
+use crate::error::Result;
+use crate::import::common::BookmarksMigrationResult;
+use crate::storage::bookmarks::json_tree::BookmarkTreeNode;
+use crate::storage::bookmarks::{keywords, BookmarkRootGuid};
+use crate::PlacesDb;
+use std::collections::HashMap;
+use std::fmt::Write;
+use types::Timestamp;
+
+const HEADER: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<meta http-equiv="Content-Security-Policy"
+      content="default-src 'self'; script-src 'none'; img-src data: *; object-src 'none'"></meta>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+
+"#;
+
+/// The attributes that mark a folder as one of our roots, rather than a
+/// regular folder. Desktop exports the menu as the top-level list, and the
+/// toolbar and unfiled roots as folders in it. It doesn't export the mobile
+/// root, so we mark it the same way.
+const ROOT_FOLDERS: &[(BookmarkRootGuid, &str, &str)] = &[
+    (
+        BookmarkRootGuid::Toolbar,
+        "PERSONAL_TOOLBAR_FOLDER",
+        "Bookmarks Toolbar",
+    ),
+    (
+        BookmarkRootGuid::Unfiled,
+        "UNFILED_BOOKMARKS_FOLDER",
+        "Other Bookmarks",
+    ),
+    (
+        BookmarkRootGuid::Mobile,
+        "MOBILE_BOOKMARKS_FOLDER",
+        "Mobile Bookmarks",
+    ),
+];
+
+/// Writes all bookmarks to `path` as Netscape-format HTML.
+pub fn export(conn: &PlacesDb, path: impl AsRef<std::path::Path>) -> Result<()> {
+    let html = export_to_string(conn)?;
+    std::fs::write(path, html)?;
+    Ok(())
+}
+
+fn export_to_string(conn: &PlacesDb) -> Result<String> {
+    let mut writer = HtmlWriter {
+        out: HEADER.to_string(),
+        keywords: keywords::get_keywords_by_url(conn)?,
+    };
+    let mut roots = fetch_roots(conn)?;
+    writer.out.push_str("<DL><p>\n");
+    if let Some(index) = roots
+        .iter()
+        .position(|(root, _)| *root == BookmarkRootGuid::Menu)
+    {
+        let (_, children) = roots.remove(index);
+        writer.write_items(&children, 1);
+    }
+    for (root, attribute, title) in ROOT_FOLDERS {
+        let children = roots
+            .iter()
+            .find(|(r, _)| r == root)
+            .map(|(_, children)| children.as_slice())
+            .unwrap_or_default();
+        writeln!(
+            writer.out,
+            r#"    <DT><H3 {}="true">{}</H3>"#,
+            attribute,
+            escape(title)
+        )
+        .unwrap();
+        writer.write_list(children, 1);
+    }
+    writer.out.push_str("</DL>\n");
+    Ok(writer.out)
+}
+
+struct HtmlWriter {
+    out: String,
+    keywords: HashMap<String, String>,
+}
+
+impl HtmlWriter {
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn write_list(&mut self, children: &[BookmarkTreeNode], depth: usize) {
+        self.indent(depth);
+        self.out.push_str("<DL><p>\n");
+        self.write_items(children, depth + 1);
+        self.indent(depth);
+        self.out.push_str("</DL><p>\n");
+    }
+
+    fn write_items(&mut self, children: &[BookmarkTreeNode], depth: usize) {
+        for child in children {
+            self.indent(depth);
+            let (date_added, last_modified) = child.created_modified();
+            let dates = format!(
+                r#"ADD_DATE="{}" LAST_MODIFIED="{}""#,
+                date_added.as_millis() / 1000,
+                last_modified.as_millis() / 1000
+            );
+            match child {
+                BookmarkTreeNode::Bookmark { b } => {
+                    write!(
+                        self.out,
+                        r#"<DT><A HREF="{}" {}"#,
+                        escape(b.url.as_str()),
+                        dates
+                    )
+                    .unwrap();
+                    if let Some(keyword) = self.keywords.get(b.url.as_str()) {
+                        write!(self.out, r#" SHORTCUTURL="{}""#, escape(keyword)).unwrap();
+                    }
+                    writeln!(
+                        self.out,
+                        ">{}</A>",
+                        escape(b.title.as_deref().unwrap_or_default())
+                    )
+                    .unwrap();
+                }
+                BookmarkTreeNode::Separator { .. } => self.out.push_str("<HR>\n"),
+                BookmarkTreeNode::Folder { f } => {
+                    writeln!(
+                        self.out,
+                        "<DT><H3 {}>{}</H3>",
+                        dates,
+                        escape(f.title.as_deref().unwrap_or_default())
+                    )
+                    .unwrap();
+                    self.write_list(&f.children, depth);
+                }
+            }
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Imports the bookmarks in a Netscape-format HTML file, exported by us or
+/// another browser. Items in the top-level list go in the menu, and folders
+/// marked as the toolbar or other bookmarks go in those roots.
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<BookmarksMigrationResult> {
+    let bytes = std::fs::read(path)?;
+    do_import(conn, &String::from_utf8_lossy(&bytes))
+}
+
+fn do_import(conn: &PlacesDb, html: &str) -> Result<BookmarksMigrationResult> {
+    import_items(conn, find_items_to_import(html))
+}
+
+/// Returns the items to import in the order to insert them, along with where
+/// to insert them. Like browsers, this is lenient about the structure, and
+/// only looks at the tags it cares about.
+fn find_items_to_import(html: &str) -> Vec<(FileItem, ImportParent)> {
+    let mut items = Vec::new();
+    // The folders of the lists we're in.
+    let mut parents: Vec<ImportParent> = Vec::new();
+    // The folder whose children the next list holds, if the last item was a
+    // folder.
+    let mut next_list_parent = None;
+    let mut tokens = tokenize(html).into_iter();
+    while let Some(token) = tokens.next() {
+        let current_parent = parents
+            .last()
+            .copied()
+            .unwrap_or(ImportParent::Root(BookmarkRootGuid::Menu));
+        let (name, attributes) = match token {
+            Token::StartTag { name, attributes } => (name, attributes),
+            Token::EndTag { name } => {
+                if name == "DL" {
+                    parents.pop();
+                    next_list_parent = None;
+                }
+                continue;
+            }
+            Token::Text(_) => continue,
+        };
+        let date = |name: &str| {
+            attributes
+                .get(name)
+                .and_then(|seconds: &String| seconds.trim().parse::<u64>().ok())
+                .and_then(|seconds| seconds.checked_mul(1000))
+                .map(Timestamp)
+        };
+        match name.as_str() {
+            "DL" => parents.push(next_list_parent.take().unwrap_or(current_parent)),
+            "H3" => {
+                let title = read_text(&mut tokens, "H3");
+                let root = ROOT_FOLDERS
+                    .iter()
+                    .find(|(_, attribute, _)| attributes.contains_key(*attribute))
+                    .map(|(root, _, _)| *root);
+                next_list_parent = Some(match root {
+                    Some(root) => ImportParent::Root(root),
+                    None => {
+                        items.push((
+                            FileItem::Folder {
+                                guid: None,
+                                date_added: date("ADD_DATE"),
+                                last_modified: date("LAST_MODIFIED"),
+                                title,
+                            },
+                            current_parent,
+                        ));
+                        ImportParent::Folder(items.len() - 1)
+                    }
+                });
+            }
+            "A" => {
+                let title = read_text(&mut tokens, "A");
+                next_list_parent = None;
+                let Some(url) = attributes.get("HREF") else {
+                    continue;
+                };
+                items.push((
+                    FileItem::Bookmark {
+                        guid: None,
+                        date_added: date("ADD_DATE"),
+                        last_modified: date("LAST_MODIFIED"),
+                        title,
+                        url: url.clone(),
+                        keyword: attributes.get("SHORTCUTURL").cloned(),
+                    },
+                    current_parent,
+                ));
+            }
+            "HR" => {
+                next_list_parent = None;
+                items.push((
+                    FileItem::Separator {
+                        guid: None,
+                        date_added: None,
+                        last_modified: None,
+                    },
+                    current_parent,
+                ));
+            }
+            _ => {}
+        }
+    }
+    items
+}
+
+/// Returns the text up to the end of the `name` element, or `None` if it's
+/// empty.
+fn read_text(tokens: &mut impl Iterator<Item = Token>, name: &str) -> Option<String> {
+    let mut text = String::new();
+    for token in tokens {
+        match token {
+            Token::Text(t) => text.push_str(&t),
+            Token::EndTag { name: end } if end == name => break,
+            _ => {}
+        }
+    }
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// A start tag. Names are uppercased, and attribute values are decoded.
+    StartTag {
+        name: String,
+        attributes: HashMap<String, String>,
+    },
+    EndTag {
+        name: String,
+    },
+    /// Decoded text between tags.
+    Text(String),
+}
+
+/// Splits bookmarks HTML into tags and text, skipping comments and
+/// declarations like `<!DOCTYPE>`.
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..start])));
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", move |end| &rest[end + 1..]);
+            continue;
+        }
+        let (token, after) = parse_tag(&rest[1..]);
+        tokens.extend(token);
+        rest = after;
+    }
+    tokens
+}
+
+/// Parses the tag after a `<`, returning it, or `None` if it isn't a tag, and
+/// the rest of the input.
+fn parse_tag(input: &str) -> (Option<Token>, &str) {
+    let (closing, input) = match input.strip_prefix('/') {
+        Some(input) => (true, input),
+        None => (false, input),
+    };
+    let name_end = input
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(input.len());
+    if name_end == 0 {
+        // Not a tag, like a `<` in text.
+        return (Some(Token::Text("<".to_string())), input);
+    }
+    let name = input[..name_end].to_ascii_uppercase();
+    let mut rest = &input[name_end..];
+    let mut attributes = HashMap::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        let attribute_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let attribute = rest[..attribute_end].to_ascii_uppercase();
+        rest = rest[attribute_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let after = &after[1..];
+                        let end = after.find(quote).unwrap_or(after.len());
+                        (&after[..end], after.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        after.split_at(end)
+                    }
+                };
+                rest = after;
+                decode_entities(value)
+            }
+            None => String::new(),
+        };
+        attributes.insert(attribute, value);
+    }
+    let token = if closing {
+        Token::EndTag { name }
+    } else {
+        Token::StartTag { name, attributes }
+    };
+    (Some(token), rest)
+}
+
+/// Decodes the character references in `s`. Unknown or malformed references
+/// are left as they are.
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..=end]).map(|c| (c, end + 2)));
+        match reference {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::storage::bookmarks::keywords::get_keyword_for_url;
+    use crate::tests::{assert_json_tree, insert_json_tree};
+    use serde_json::json;
+    use url::Url;
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("a &amp; b"), "a & b");
+        assert_eq!(decode_entities("&lt;&#39;&#x41;&gt;"), "<'A>");
+        assert_eq!(
+            decode_entities("fish & chips &bogus;"),
+            "fish & chips &bogus;"
+        );
+    }
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file. -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1600000100" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/" ADD_DATE="1600000000" SHORTCUTURL="ex">Fish &amp; Chips</A>
+        <DT><H3>Folder</H3>
+        <DL><p>
+            <DT><A HREF="https://example.org/">Nested</A>
+            <DT><A HREF="not a url">Invalid</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF='https://example.net/'>Menu</A>
+    <HR>
+    <DT><A HREF=https://example.com/unquoted>
+    </A>
+</DL><p>
+"#;
+        let result = do_import(&conn, html)?;
+        assert_eq!(result.num_total, 7);
+        assert_eq!(result.num_succeeded, 6);
+        assert_eq!(result.num_failed, 1);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Fish & Chips",
+                        "url": "https://example.com/",
+                        "date_added": 1_600_000_000_000u64,
+                    },
+                    {
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "title": "Nested",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    }
+                ]
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Menu",
+                        "url": "https://example.net/"
+                    },
+                    {
+                        "type": 3
+                    },
+                    {
+                        "url": "https://example.com/unquoted"
+                    }
+                ]
+            }),
+        );
+        assert_eq!(
+            get_keyword_for_url(&conn, &Url::parse("https://example.com/")?)?,
+            Some("ex".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_round_trip() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": String::from(BookmarkRootGuid::Menu.as_str()),
+                "children": [
+                    {
+                        "title": "<Menu> \"bookmark\"",
+                        "url": "https://example.com/?a=1&b=2",
+                        "date_added": 1_600_000_000_000u64,
+                    },
+                    {
+                        "type": 3
+                    },
+                ]
+            }),
+        );
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": String::from(BookmarkRootGuid::Mobile.as_str()),
+                "children": [
+                    {
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "title": "Mobile",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    },
+                ]
+            }),
+        );
+        keywords::set_keyword_for_url(&conn, &Url::parse("https://example.org/")?, "kw")?;
+
+        let html = export_to_string(&conn)?;
+        let imported = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let result = do_import(&imported, &html)?;
+        assert_eq!((result.num_total, result.num_succeeded), (4, 4));
+
+        // The GUIDs aren't exported, so they're left out here.
+        assert_json_tree(
+            &imported,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "<Menu> \"bookmark\"",
+                        "url": "https://example.com/?a=1&b=2",
+                        "date_added": 1_600_000_000_000u64,
+                    },
+                    {
+                        "type": 3
+                    },
+                ]
+            }),
+        );
+        assert_json_tree(
+            &imported,
+            &BookmarkRootGuid::Mobile.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "title": "Mobile",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    },
+                ]
+            }),
+        );
+        assert_eq!(
+            get_keyword_for_url(&imported, &Url::parse("https://example.org/")?)?,
+            Some("kw".to_string())
+        );
+        Ok(())
+    }
+}
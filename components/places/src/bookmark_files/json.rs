@@ -0,0 +1,477 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The JSON bookmark backups made by desktop Firefox. Unlike HTML, these
+//! have GUIDs, so importing a backup made by a profile that syncs with the
+//! same account matches up with the bookmarks already on the server.
+//!
+//! This isn't the same as the JSON in `json_tree`, which is our own format
+//! for tests and tools. Desktop's uses camelCase names, a `typeCode` and a
+//! MIME-like `type` for each item, and microsecond timestamps.
+
+use super::{fetch_roots, import_items, FileItem, ImportParent};
+use crate::error::Result;
+use crate::import::common::BookmarksMigrationResult;
+use crate::storage::bookmarks::json_tree::BookmarkTreeNode;
+use crate::storage::bookmarks::{keywords, BookmarkRootGuid};
+use crate::PlacesDb;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+
+const TYPE_BOOKMARK: &str = "text/x-moz-place";
+const TYPE_FOLDER: &str = "text/x-moz-place-container";
+const TYPE_SEPARATOR: &str = "text/x-moz-place-separator";
+
+const TYPE_CODE_BOOKMARK: u64 = 1;
+const TYPE_CODE_FOLDER: u64 = 2;
+const TYPE_CODE_SEPARATOR: u64 = 3;
+
+/// Desktop's names for the roots, in the `root` property of each root folder.
+const ROOT_NAMES: &[(BookmarkRootGuid, &str, &str)] = &[
+    (BookmarkRootGuid::Root, "placesRoot", ""),
+    (BookmarkRootGuid::Menu, "bookmarksMenuFolder", "menu"),
+    (BookmarkRootGuid::Toolbar, "toolbarFolder", "toolbar"),
+    (
+        BookmarkRootGuid::Unfiled,
+        "unfiledBookmarksFolder",
+        "unfiled",
+    ),
+    (BookmarkRootGuid::Mobile, "mobileFolder", "mobile"),
+];
+
+fn root_for_name(name: &str) -> Option<BookmarkRootGuid> {
+    ROOT_NAMES
+        .iter()
+        .find(|(_, root_name, _)| *root_name == name)
+        .map(|(root, _, _)| *root)
+}
+
+/// Writes all bookmarks to `path` in the format of desktop's JSON backups.
+pub fn export(conn: &PlacesDb, path: impl AsRef<std::path::Path>) -> Result<()> {
+    let json = export_to_string(conn)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+fn export_to_string(conn: &PlacesDb) -> Result<String> {
+    let keywords = keywords::get_keywords_by_url(conn)?;
+    let roots: Vec<Value> = fetch_roots(conn)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, (root, children))| root_to_json(root, index, &children, &keywords))
+        .collect();
+    let mut tree = root_to_json(BookmarkRootGuid::Root, 0, &[], &keywords);
+    tree["children"] = Value::Array(roots);
+    Ok(serde_json::to_string(&tree)?)
+}
+
+fn root_to_json(
+    root: BookmarkRootGuid,
+    index: usize,
+    children: &[BookmarkTreeNode],
+    keywords: &HashMap<String, String>,
+) -> Value {
+    let (_, name, title) = ROOT_NAMES
+        .iter()
+        .find(|(r, _, _)| *r == root)
+        .expect("all roots have names");
+    json!({
+        "guid": root.as_str(),
+        "title": title,
+        "index": index,
+        "typeCode": TYPE_CODE_FOLDER,
+        "type": TYPE_FOLDER,
+        "root": name,
+        "children": children_to_json(children, keywords),
+    })
+}
+
+fn children_to_json(children: &[BookmarkTreeNode], keywords: &HashMap<String, String>) -> Value {
+    Value::Array(
+        children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| node_to_json(child, index, keywords))
+            .collect(),
+    )
+}
+
+fn node_to_json(
+    node: &BookmarkTreeNode,
+    index: usize,
+    keywords: &HashMap<String, String>,
+) -> Value {
+    let (date_added, last_modified) = node.created_modified();
+    let mut map = Map::new();
+    map.insert("guid".into(), json!(node.guid()));
+    map.insert("index".into(), json!(index));
+    map.insert("dateAdded".into(), json!(date_added.as_millis() * 1000));
+    map.insert(
+        "lastModified".into(),
+        json!(last_modified.as_millis() * 1000),
+    );
+    match node {
+        BookmarkTreeNode::Bookmark { b } => {
+            map.insert(
+                "title".into(),
+                json!(b.title.as_deref().unwrap_or_default()),
+            );
+            map.insert("typeCode".into(), json!(TYPE_CODE_BOOKMARK));
+            map.insert("type".into(), json!(TYPE_BOOKMARK));
+            map.insert("uri".into(), json!(b.url.as_str()));
+            if let Some(keyword) = keywords.get(b.url.as_str()) {
+                map.insert("keyword".into(), json!(keyword));
+            }
+        }
+        BookmarkTreeNode::Separator { .. } => {
+            map.insert("typeCode".into(), json!(TYPE_CODE_SEPARATOR));
+            map.insert("type".into(), json!(TYPE_SEPARATOR));
+        }
+        BookmarkTreeNode::Folder { f } => {
+            map.insert(
+                "title".into(),
+                json!(f.title.as_deref().unwrap_or_default()),
+            );
+            map.insert("typeCode".into(), json!(TYPE_CODE_FOLDER));
+            map.insert("type".into(), json!(TYPE_FOLDER));
+            map.insert("children".into(), children_to_json(&f.children, keywords));
+        }
+    }
+    Value::Object(map)
+}
+
+/// Imports the bookmarks in one of desktop's JSON backups. The children of
+/// each of desktop's roots go in the matching root here. Tags, which desktop
+/// stores in a root of their own, aren't imported.
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<BookmarksMigrationResult> {
+    let bytes = std::fs::read(path)?;
+    let tree: Value = serde_json::from_slice(&bytes)?;
+    do_import(conn, &tree)
+}
+
+fn do_import(conn: &PlacesDb, tree: &Value) -> Result<BookmarksMigrationResult> {
+    import_items(conn, find_items_to_import(tree))
+}
+
+fn root_for_node(node: &Value) -> Option<BookmarkRootGuid> {
+    node.get("root")
+        .and_then(Value::as_str)
+        .and_then(root_for_name)
+        .or_else(|| {
+            node.get("guid")
+                .and_then(Value::as_str)
+                .and_then(BookmarkRootGuid::well_known)
+        })
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("children")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Returns the items to import in the order to insert them, along with where
+/// to insert them. A backup of a single folder, rather than the whole tree,
+/// is imported into the menu.
+fn find_items_to_import(tree: &Value) -> Vec<(FileItem, ImportParent)> {
+    let mut items = Vec::new();
+    if root_for_node(tree) != Some(BookmarkRootGuid::Root) {
+        add_item(tree, ImportParent::Root(BookmarkRootGuid::Menu), &mut items);
+        return items;
+    }
+    for child in children(tree) {
+        match root_for_node(child) {
+            Some(root) if root != BookmarkRootGuid::Root => {
+                add_children(child, ImportParent::Root(root), &mut items)
+            }
+            // Anything else at the top level, including the tags root, isn't
+            // a bookmark.
+            _ => log::warn!("Skipping unknown root {:?}", child.get("root")),
+        }
+    }
+    items
+}
+
+fn add_children(folder: &Value, parent: ImportParent, items: &mut Vec<(FileItem, ImportParent)>) {
+    for child in children(folder) {
+        add_item(child, parent, items);
+    }
+}
+
+/// Adds `node` and, if it's a folder, its descendants. Desktop writes
+/// `typeCode`, but older backups only have `type`.
+fn add_item(node: &Value, parent: ImportParent, items: &mut Vec<(FileItem, ImportParent)>) {
+    let guid = node.get("guid").and_then(Value::as_str).map(SyncGuid::from);
+    let date = |name: &str| {
+        node.get(name)
+            .and_then(Value::as_u64)
+            .map(|micros| Timestamp(micros / 1000))
+    };
+    let date_added = date("dateAdded");
+    let last_modified = date("lastModified");
+    let title = node
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.is_empty())
+        .map(str::to_owned);
+    let type_code = node.get("typeCode").and_then(Value::as_u64).or_else(|| {
+        match node.get("type").and_then(Value::as_str) {
+            Some(TYPE_BOOKMARK) => Some(TYPE_CODE_BOOKMARK),
+            Some(TYPE_FOLDER) => Some(TYPE_CODE_FOLDER),
+            Some(TYPE_SEPARATOR) => Some(TYPE_CODE_SEPARATOR),
+            _ => None,
+        }
+    });
+    match type_code {
+        Some(TYPE_CODE_BOOKMARK) => items.push((
+            FileItem::Bookmark {
+                guid,
+                date_added,
+                last_modified,
+                title,
+                url: node
+                    .get("uri")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+                keyword: node
+                    .get("keyword")
+                    .and_then(Value::as_str)
+                    .map(str::to_owned),
+            },
+            parent,
+        )),
+        Some(TYPE_CODE_FOLDER) => {
+            let index = items.len();
+            items.push((
+                FileItem::Folder {
+                    guid,
+                    date_added,
+                    last_modified,
+                    title,
+                },
+                parent,
+            ));
+            add_children(node, ImportParent::Folder(index), items);
+        }
+        Some(TYPE_CODE_SEPARATOR) => items.push((
+            FileItem::Separator {
+                guid,
+                date_added,
+                last_modified,
+            },
+            parent,
+        )),
+        kind => log::warn!("Skipping bookmark of unsupported type {:?}", kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::storage::bookmarks::keywords::{get_keyword_for_url, set_keyword_for_url};
+    use crate::tests::{assert_json_tree, insert_json_tree};
+    use url::Url;
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        // An existing bookmark whose GUID is used by the backup.
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": String::from(BookmarkRootGuid::Unfiled.as_str()),
+                "children": [
+                    {
+                        "guid": "bookmarkAAAA",
+                        "title": "Existing",
+                        "url": "https://example.com/existing"
+                    },
+                ]
+            }),
+        );
+        let tree = json!({
+            "guid": "root________",
+            "title": "",
+            "typeCode": 2,
+            "root": "placesRoot",
+            "children": [
+                {
+                    "guid": "menu________",
+                    "title": "menu",
+                    "typeCode": 2,
+                    "root": "bookmarksMenuFolder",
+                    "children": [
+                        {
+                            "guid": "bookmarkBBBB",
+                            "title": "Example",
+                            "dateAdded": 1_600_000_000_000_000u64,
+                            "lastModified": 1_600_000_100_000_000u64,
+                            "typeCode": 1,
+                            "type": "text/x-moz-place",
+                            "uri": "https://example.com/",
+                            "keyword": "ex"
+                        },
+                        {
+                            "guid": "bookmarkAAAA",
+                            "title": "Duplicate GUID",
+                            "typeCode": 1,
+                            "uri": "https://example.com/dupe"
+                        },
+                        {
+                            "guid": "separatorAA1",
+                            "type": "text/x-moz-place-separator"
+                        },
+                        {
+                            "guid": "folderAAAAAA",
+                            "title": "Folder",
+                            "typeCode": 2,
+                            "children": [
+                                {
+                                    "title": "Invalid",
+                                    "typeCode": 1,
+                                    "uri": "not a url"
+                                },
+                                {
+                                    "guid": "bookmarkCCCC",
+                                    "title": "Nested",
+                                    "typeCode": 1,
+                                    "uri": "https://example.org/"
+                                }
+                            ]
+                        }
+                    ]
+                },
+                {
+                    "guid": "tags________",
+                    "title": "tags",
+                    "typeCode": 2,
+                    "root": "tagsFolder",
+                    "children": [
+                        {
+                            "title": "tag",
+                            "typeCode": 2,
+                            "children": []
+                        }
+                    ]
+                }
+            ]
+        });
+        let result = do_import(&conn, &tree)?;
+        assert_eq!(result.num_total, 6);
+        assert_eq!(result.num_succeeded, 5);
+        assert_eq!(result.num_failed, 1);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "children": [
+                    {
+                        "guid": "bookmarkBBBB",
+                        "title": "Example",
+                        "url": "https://example.com/",
+                        "date_added": 1_600_000_000_000u64,
+                        "last_modified": 1_600_000_100_000u64,
+                    },
+                    {
+                        "title": "Duplicate GUID",
+                        "url": "https://example.com/dupe"
+                    },
+                    {
+                        "guid": "separatorAA1",
+                        "type": 3
+                    },
+                    {
+                        "guid": "folderAAAAAA",
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "guid": "bookmarkCCCC",
+                                "title": "Nested",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    }
+                ]
+            }),
+        );
+        // The GUID in use wasn't reused.
+        let dupe = crate::storage::bookmarks::fetch::fetch_bookmarks_by_url(
+            &conn,
+            &Url::parse("https://example.com/dupe")?,
+        )?;
+        assert_ne!(dupe[0].guid, "bookmarkAAAA");
+        assert_eq!(
+            get_keyword_for_url(&conn, &Url::parse("https://example.com/")?)?,
+            Some("ex".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_round_trip() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let menu = json!({
+            "guid": String::from(BookmarkRootGuid::Menu.as_str()),
+            "children": [
+                {
+                    "guid": "bookmarkAAAA",
+                    "title": "Example",
+                    "url": "https://example.com/",
+                },
+                {
+                    "guid": "separatorAA1",
+                    "type": 3
+                },
+                {
+                    "guid": "folderAAAAAA",
+                    "title": "Folder",
+                    "children": [
+                        {
+                            "guid": "bookmarkBBBB",
+                            "title": "Nested",
+                            "url": "https://example.org/"
+                        }
+                    ]
+                }
+            ]
+        });
+        insert_json_tree(&conn, menu.clone());
+        let toolbar = json!({
+            "guid": String::from(BookmarkRootGuid::Toolbar.as_str()),
+            "children": [
+                {
+                    "guid": "bookmarkCCCC",
+                    "title": "Toolbar",
+                    "url": "https://example.net/",
+                },
+            ]
+        });
+        insert_json_tree(&conn, toolbar.clone());
+        set_keyword_for_url(&conn, &Url::parse("https://example.com/")?, "ex")?;
+
+        let exported: Value = serde_json::from_str(&export_to_string(&conn)?)?;
+        assert_eq!(exported["root"], "placesRoot");
+        assert_eq!(exported["children"][1]["root"], "toolbarFolder");
+
+        let imported = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let result = do_import(&imported, &exported)?;
+        assert_eq!((result.num_total, result.num_succeeded), (5, 5));
+        assert_json_tree(&imported, &BookmarkRootGuid::Menu.into(), menu);
+        assert_json_tree(&imported, &BookmarkRootGuid::Toolbar.into(), toolbar);
+        assert_eq!(
+            get_keyword_for_url(&imported, &Url::parse("https://example.com/")?)?,
+            Some("ex".to_string())
+        );
+        Ok(())
+    }
+}
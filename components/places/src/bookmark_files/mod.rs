@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Exporting and importing bookmarks as files that other browsers understand:
+//! Netscape-format HTML, which every browser can import and export, and the
+//! JSON backups made by desktop Firefox.
+//!
+//! Both importers add to the existing bookmarks, rather than replacing them.
+//! Like the Safari importer, items are inserted one at a time, so an invalid
+//! item only fails itself (and, for a folder, its descendants). Items keep
+//! their GUIDs when the file has them and they aren't already in use, so
+//! that importing a file exported from a synced profile doesn't create
+//! duplicates on other devices.
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{
+    guid_to_keep, import_bookmark_items, BookmarksMigrationResult, ImportParent,
+};
+use crate::storage::bookmarks::json_tree::{fetch_tree, BookmarkTreeNode, FetchDepth};
+use crate::storage::bookmarks::{
+    insert_bookmark, keywords, BookmarkPosition, BookmarkRootGuid, InsertableBookmark,
+    InsertableFolder, InsertableItem, InsertableSeparator,
+};
+use crate::PlacesDb;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+pub mod html;
+pub mod json;
+pub use html::{export as export_html, import as import_html};
+pub use json::{export as export_json, import as import_json};
+
+/// An item read from a bookmarks file.
+#[derive(Debug)]
+enum FileItem {
+    Folder {
+        guid: Option<SyncGuid>,
+        date_added: Option<Timestamp>,
+        last_modified: Option<Timestamp>,
+        title: Option<String>,
+    },
+    Bookmark {
+        guid: Option<SyncGuid>,
+        date_added: Option<Timestamp>,
+        last_modified: Option<Timestamp>,
+        title: Option<String>,
+        url: String,
+        keyword: Option<String>,
+    },
+    Separator {
+        guid: Option<SyncGuid>,
+        date_added: Option<Timestamp>,
+        last_modified: Option<Timestamp>,
+    },
+}
+
+/// Inserts the items read from a file, parents before children.
+fn import_items(
+    conn: &PlacesDb,
+    items: Vec<(FileItem, ImportParent)>,
+) -> Result<BookmarksMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    let import_start = Instant::now();
+
+    let num_total = items.len() as u32;
+    log::info!("The number of bookmarks is: {:?}", num_total);
+
+    let num_succeeded = import_bookmark_items(
+        &scope,
+        items,
+        |item, parent_guid| import_item(conn, item, parent_guid).map(Some),
+        |_| (),
+    )?;
+    log::info!("Successfully imported {} bookmarks!", num_succeeded);
+    Ok(BookmarksMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total - num_succeeded,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Inserts a single item, returning its GUID.
+fn import_item(conn: &PlacesDb, item: FileItem, parent_guid: SyncGuid) -> Result<SyncGuid> {
+    let mut keyword_for_url = None;
+    let insertable: InsertableItem = match item {
+        FileItem::Folder {
+            guid,
+            date_added,
+            last_modified,
+            title,
+        } => InsertableFolder {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added,
+            last_modified,
+            guid: guid_to_keep(conn, guid)?,
+            title,
+            children: Vec::new(),
+        }
+        .into(),
+        FileItem::Bookmark {
+            guid,
+            date_added,
+            last_modified,
+            title,
+            url,
+            keyword,
+        } => {
+            let url = Url::parse(&url)?;
+            keyword_for_url = keyword.map(|keyword| (keyword, url.clone()));
+            InsertableBookmark {
+                parent_guid,
+                position: BookmarkPosition::Append,
+                date_added,
+                last_modified,
+                guid: guid_to_keep(conn, guid)?,
+                url,
+                title,
+            }
+            .into()
+        }
+        FileItem::Separator {
+            guid,
+            date_added,
+            last_modified,
+        } => InsertableSeparator {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added,
+            last_modified,
+            guid: guid_to_keep(conn, guid)?,
+        }
+        .into(),
+    };
+    let guid = insert_bookmark(conn, insertable)?;
+    if let Some((keyword, url)) = keyword_for_url {
+        // The bookmark is still worth keeping without its keyword.
+        if let Err(e) = keywords::set_keyword_for_url(conn, &url, &keyword) {
+            log::warn!("Failed to import keyword for bookmark {}: {}", guid, e);
+        }
+    }
+    Ok(guid)
+}
+
+/// Returns each of the roots that hold the user's bookmarks, with its
+/// children.
+fn fetch_roots(conn: &PlacesDb) -> Result<Vec<(BookmarkRootGuid, Vec<BookmarkTreeNode>)>> {
+    let root = fetch_tree(conn, BookmarkRootGuid::Root.guid(), &FetchDepth::Deepest)?;
+    let children = match root {
+        Some((BookmarkTreeNode::Folder { f }, _, _)) => f.children,
+        _ => Vec::new(),
+    };
+    Ok(children
+        .into_iter()
+        .filter_map(|child| match child {
+            BookmarkTreeNode::Folder { f } => {
+                let root = BookmarkRootGuid::from_guid(f.guid.as_ref()?)?;
+                Some((root, f.children))
+            }
+            _ => None,
+        })
+        .collect())
+}
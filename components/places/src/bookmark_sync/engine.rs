@@ -11,6 +11,7 @@ use super::{SyncedBookmarkKind, SyncedBookmarkValidity};
 use crate::db::{GlobalChangeCounterTracker, PlacesDb, SharedPlacesDb};
 use crate::error::*;
 use crate::frecency::{calculate_frecency, DEFAULT_FRECENCY_SETTINGS};
+use crate::metrics::{self, PlacesOperation};
 use crate::storage::{
     bookmarks::{
         bookmark_sync::{create_synced_bookmark_roots, reset},
@@ -809,8 +810,9 @@ fn push_synced_items(
     // records we just uploaded on the next sync.
     put_meta(db, LAST_SYNC_META_KEY, &uploaded_at.as_millis())?;
 
-    // Clean up.
-    db.execute_batch("DELETE FROM itemsToUpload")?;
+    // Clean up the items we just flagged. We're called once for each batch the server
+    // commits, so items in later batches need to stay around until they're uploaded too.
+    db.execute_batch("DELETE FROM itemsToUpload WHERE uploadedAt > -1")?;
     tx.commit()?;
 
     Ok(())
@@ -916,7 +918,9 @@ impl SyncEngine for BookmarksSyncEngine {
         let conn = self.db.lock();
         // Stage all incoming items.
         let mut incoming_telemetry = telemetry::EngineIncoming::new();
-        stage_incoming(&conn, &self.scope, inbound, &mut incoming_telemetry)?;
+        metrics::measure(PlacesOperation::Sync, || {
+            stage_incoming(&conn, &self.scope, inbound, &mut incoming_telemetry)
+        })?;
         telem.incoming(incoming_telemetry);
         Ok(())
     }
@@ -927,15 +931,17 @@ impl SyncEngine for BookmarksSyncEngine {
         telem: &mut telemetry::Engine,
     ) -> anyhow::Result<Vec<OutgoingBso>> {
         let conn = self.db.lock();
-        // write the timestamp now, so if we are interrupted merging or
-        // creating outgoing changesets we don't need to re-apply the same
-        // records.
-        put_meta(&conn, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
-
-        // Merge.
-        let mut merger = Merger::with_telemetry(&conn, &self.scope, timestamp, telem);
-        merger.merge()?;
-        Ok(fetch_outgoing_records(&conn, &self.scope)?)
+        Ok(metrics::measure(PlacesOperation::Sync, || {
+            // write the timestamp now, so if we are interrupted merging or
+            // creating outgoing changesets we don't need to re-apply the same
+            // records.
+            put_meta(&conn, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
+
+            // Merge.
+            let mut merger = Merger::with_telemetry(&conn, &self.scope, timestamp, telem);
+            merger.merge()?;
+            fetch_outgoing_records(&conn, &self.scope)
+        })?)
     }
 
     fn set_uploaded(
@@ -944,12 +950,18 @@ impl SyncEngine for BookmarksSyncEngine {
         ids: Vec<SyncGuid>,
     ) -> anyhow::Result<()> {
         let conn = self.db.lock();
-        push_synced_items(&conn, &self.scope, new_timestamp, ids)?;
-        Ok(update_frecencies(&conn, &self.scope)?)
+        Ok(metrics::measure(PlacesOperation::Sync, || {
+            push_synced_items(&conn, &self.scope, new_timestamp, ids)
+        })?)
     }
 
     fn sync_finished(&self) -> anyhow::Result<()> {
         let conn = self.db.lock();
+        // We're called once, after every batch is uploaded, so we only recalculate
+        // frecencies once per sync, rather than for each batch.
+        metrics::measure(PlacesOperation::Sync, || {
+            update_frecencies(&conn, &self.scope)
+        })?;
         conn.pragma_update(None, "wal_checkpoint", "PASSIVE")?;
         Ok(())
     }
@@ -135,7 +135,10 @@ impl PlacesDb {
         coop_tx_lock: Arc<Mutex<()>>,
     ) -> Self {
         Self {
-            interrupt_handle: Arc::new(SqlInterruptHandle::new(&db)),
+            interrupt_handle: Arc::new(SqlInterruptHandle::new_for_phase(
+                &db,
+                conn_type.shutdown_phase(),
+            )),
             db,
             conn_type,
             // The API sets this explicitly.
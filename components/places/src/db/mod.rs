@@ -7,6 +7,10 @@
 pub mod db;
 mod schema;
 mod tx;
+pub(crate) use self::schema::{
+    MOZ_META_KEY_ORIGIN_FRECENCY_COUNT, MOZ_META_KEY_ORIGIN_FRECENCY_SUM,
+    MOZ_META_KEY_ORIGIN_FRECENCY_SUM_OF_SQUARES,
+};
 pub use self::tx::PlacesTransaction;
 
 pub use crate::db::db::{GlobalChangeCounterTracker, PlacesDb, SharedPlacesDb};
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use crate::api::matcher::normalize_input;
 use crate::api::places_api::ConnectionType;
 use crate::bookmark_sync::engine::LAST_SYNC_META_KEY;
 use crate::storage::bookmarks::{
@@ -10,8 +11,9 @@ use crate::storage::bookmarks::{
 use crate::types::SyncStatus;
 use rusqlite::Connection;
 use sql_support::ConnExt;
+use std::collections::HashMap;
 
-pub const VERSION: u32 = 17;
+pub const VERSION: u32 = 21;
 
 // Shared schema and temp tables for the read-write and Sync connections.
 const CREATE_SHARED_SCHEMA_SQL: &str = include_str!("../../sql/create_shared_schema.sql");
@@ -288,6 +290,27 @@ pub fn upgrade_from(db: &Connection, from: u32) -> rusqlite::Result<()> {
                 (),
             )?;
         }
+        17 => {
+            // Add the full-text index of titles and URLs, and index the existing places.
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+            db.execute(
+                "INSERT INTO moz_places_fts(moz_places_fts) VALUES('rebuild')",
+                (),
+            )?;
+        }
+        18 => {
+            // Add the favicon tables.
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+        }
+        19 => {
+            // Add the page annotations table.
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+        }
+        20 => {
+            // Input history is now matched ignoring case, so normalize the
+            // existing inputs like `accept_result` does.
+            normalize_input_history(db)?;
+        }
         // Add more migrations here...
 
         // Any other from value indicates that something very wrong happened
@@ -301,6 +324,39 @@ pub fn upgrade_from(db: &Connection, from: u32) -> rusqlite::Result<()> {
     Ok(())
 }
 
+/// Trims and lowercases the inputs in `moz_inputhistory`, merging the rows
+/// for a page whose inputs only differed by case or surrounding whitespace,
+/// and summing their use counts. Rows whose inputs were only whitespace are
+/// dropped. We do this in Rust because SQLite's `LOWER` only handles ASCII.
+fn normalize_input_history(db: &Connection) -> rusqlite::Result<()> {
+    // `use_count` is a REAL, since it decays over time.
+    let mut use_counts: HashMap<(i64, String), f64> = HashMap::new();
+    let rows: Vec<(i64, String, Option<f64>)> = db.query_rows_and_then(
+        "SELECT place_id, input, use_count FROM moz_inputhistory",
+        [],
+        |row| -> rusqlite::Result<_> { Ok((row.get(0)?, row.get(1)?, row.get(2)?)) },
+    )?;
+    for (place_id, input, use_count) in rows {
+        let input = normalize_input(&input);
+        if !input.is_empty() {
+            *use_counts.entry((place_id, input)).or_default() += use_count.unwrap_or(0.0);
+        }
+    }
+    db.execute("DELETE FROM moz_inputhistory", [])?;
+    let mut stmt = db.prepare(
+        "INSERT INTO moz_inputhistory(place_id, input, use_count)
+         VALUES(:place_id, :input, :use_count)",
+    )?;
+    for ((place_id, input), use_count) in use_counts {
+        stmt.execute(rusqlite::named_params! {
+            ":place_id": place_id,
+            ":input": input,
+            ":use_count": use_count,
+        })?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -881,6 +937,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_upgrade_schema_17_18() {
+        let db_file = MigratedDatabaseFile::new(PlacesInitializer::new_for_test(), CREATE_V15_DB);
+
+        db_file.upgrade_to(17);
+        db_file
+            .open()
+            .execute(
+                "INSERT INTO moz_places(url, title, guid)
+                 VALUES('https://www.mozilla.org/firefox/', 'Get Firefox', 'place_guid_1')",
+                [],
+            )
+            .unwrap();
+        db_file.upgrade_to(18);
+        let db = db_file.open();
+
+        // Test the existing places were indexed
+        assert_eq!(
+            db.query_one::<i64>(
+                "SELECT count(*) FROM moz_places_fts WHERE moz_places_fts MATCH 'firefox'"
+            )
+            .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_upgrade_schema_20_21() {
+        let db_file = MigratedDatabaseFile::new(PlacesInitializer::new_for_test(), CREATE_V15_DB);
+
+        db_file.upgrade_to(20);
+        db_file
+            .open()
+            .execute_batch(
+                "INSERT INTO moz_places(id, url, guid)
+                 VALUES(1, 'https://www.mozilla.org/', 'place_guid_1'),
+                       (2, 'https://www.example.com/', 'place_guid_2');
+                 INSERT INTO moz_inputhistory(place_id, input, use_count)
+                 VALUES(1, 'Moz', 1.9),
+                       (1, ' moz', 0.6),
+                       (1, 'mozilla', 1),
+                       (2, 'MOZ', 0.5),
+                       (2, '  ', 4)",
+            )
+            .unwrap();
+        db_file.upgrade_to(21);
+        let db = db_file.open();
+
+        // Test the inputs were normalized, and the duplicates merged, without
+        // truncating the decayed use counts.
+        let rows: Vec<(i64, String, f64)> = db
+            .query_rows_and_then(
+                "SELECT place_id, input, use_count FROM moz_inputhistory
+                 ORDER BY place_id, input",
+                [],
+                |row| -> rusqlite::Result<_> { Ok((row.get(0)?, row.get(1)?, row.get(2)?)) },
+            )
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (1, "moz".to_string(), 2.5),
+                (1, "mozilla".to_string(), 1.0),
+                (2, "moz".to_string(), 0.5),
+            ]
+        );
+    }
+
     #[test]
     fn test_gh5464() {
         // Test the gh-5464 error case: A user with the `v16` schema, but with `user_version` set
@@ -926,6 +1050,10 @@ mod tests {
             "moz_keywords",
             "moz_places_metadata",
             "moz_places_metadata_search_queries",
+            "moz_places_fts",
+            "moz_icons",
+            "moz_icons_to_pages",
+            "moz_annos",
         ];
         #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
         struct ColumnInfo {
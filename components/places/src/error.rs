@@ -108,11 +108,17 @@ pub enum Error {
     #[error("Can not import from database version {0}")]
     UnsupportedDatabaseVersion(i64),
 
+    #[error("Invalid property list: {0}")]
+    InvalidPropertyList(&'static str),
+
     #[error("Error opening database: {0}")]
     OpenDatabaseError(#[from] sql_support::open_database::Error),
 
     #[error("Invalid metadata observation: {0}")]
     InvalidMetadataObservation(#[from] InvalidMetadataObservation),
+
+    #[error("Invalid continuation")]
+    InvalidContinuation,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -147,6 +153,9 @@ pub enum InvalidPlaceInfo {
     // Like Urls, a tag is considered private info, so the value isn't in the error.
     #[error("The tag value is invalid")]
     InvalidTag,
+    // Like tags, keywords are considered private info.
+    #[error("The keyword is invalid")]
+    InvalidKeyword,
     #[error("Cannot change the '{0}' property of a bookmark of type {1:?}")]
     IllegalChange(&'static str, BookmarkType),
 
@@ -204,6 +213,15 @@ impl GetErrorHandling for Error {
                     InvalidPlaceInfo::CannotUpdateRoot(..) => {
                         PlacesApiError::InvalidBookmarkOperation { reason: label }
                     }
+                    InvalidPlaceInfo::NoSuchUrl => {
+                        PlacesApiError::UnknownBookmarkItem { reason: label }
+                    }
+                    InvalidPlaceInfo::InvalidTag => {
+                        PlacesApiError::InvalidBookmarkOperation { reason: label }
+                    }
+                    InvalidPlaceInfo::InvalidKeyword => {
+                        PlacesApiError::InvalidBookmarkOperation { reason: label }
+                    }
                     _ => PlacesApiError::UnexpectedPlacesException { reason: label },
                 })
                 .report_error("places-invalid-place-info")
@@ -274,6 +292,14 @@ impl GetErrorHandling for Error {
                 })
                 .log_warning()
             }
+            Error::InvalidContinuation => {
+                // The app passed a continuation that we didn't give it. That's a mistake in
+                // the app, not a bug here, so there's nothing for us to report.
+                ErrorHandling::convert(PlacesApiError::UnexpectedPlacesException {
+                    reason: self.to_string(),
+                })
+                .log_warning()
+            }
             _ => ErrorHandling::convert(PlacesApiError::UnexpectedPlacesException {
                 reason: self.to_string(),
             })
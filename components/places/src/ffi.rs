@@ -5,30 +5,43 @@
 // This module implement the traits that make the FFI code easier to manage.
 
 use crate::api::matcher::{self, search_frecent, SearchParams};
-pub use crate::api::places_api::places_api_new;
+pub use crate::api::matcher::{MatchRange, MatchReason, OriginAutofill};
+pub use crate::api::places_api::{
+    places_api_new, places_shutdown, PlacesInterruptHandle, ShutdownPhase,
+};
+use crate::bookmark_files;
 pub use crate::error::Result;
 pub use crate::error::{ApiResult, PlacesApiError};
-pub use crate::import::common::HistoryMigrationResult;
-use crate::import::import_ios_history;
+pub use crate::history_sync::HistorySyncConfig;
+pub use crate::import::common::{
+    BookmarksMigrationResult, HistoryMigrationResult, MigrationPhase, MigrationProgressListener,
+};
+pub use crate::import::fennec::FennecMigrationResult;
+use crate::import::{
+    import_chrome_bookmarks, import_chrome_history, import_fennec, import_ios_history,
+    import_safari_bookmarks, import_safari_history,
+};
+use crate::metrics::{self, PlacesOperation};
 use crate::storage;
 use crate::storage::bookmarks;
+use crate::storage::bookmarks::keywords;
 pub use crate::storage::bookmarks::BookmarkPosition;
 pub use crate::storage::history_metadata::{
     DocumentType, HistoryHighlight, HistoryHighlightWeights, HistoryMetadata,
     HistoryMetadataObservation,
 };
-pub use crate::storage::RunMaintenanceMetrics;
-use crate::storage::{history, history_metadata};
-use crate::types::VisitTransitionSet;
+use crate::storage::{annotations, favicons, history, history_metadata, previews, tags};
+pub use crate::storage::{RunMaintenanceFrecencyMetrics, RunMaintenanceMetrics};
+use crate::types::{VisitSource, VisitTransitionSet};
 use crate::ConnectionType;
 use crate::UniffiCustomTypeConverter;
 use crate::VisitObservation;
 use crate::VisitType;
 use crate::{PlacesApi, PlacesDb};
 use error_support::handle_error;
-use interrupt_support::register_interrupt;
 pub use interrupt_support::SqlInterruptHandle;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use sync15::client::Sync15StorageClientInit;
 pub use sync_guid::Guid;
@@ -38,6 +51,10 @@ pub use url::Url;
 // From https://searchfox.org/mozilla-central/rev/1674b86019a96f076e0f98f1d0f5f3ab9d4e9020/browser/components/newtab/lib/TopSitesFeed.jsm#87
 const SKIP_ONE_PAGE_FRECENCY_THRESHOLD: i64 = 101 + 1;
 
+// The most connections a reader pool opens. Each one has its own page cache, so larger pools use
+// more memory without making queries any faster once there are more of them than cores.
+const MAX_READER_POOL_SIZE: u32 = 8;
+
 // `bookmarks::InsertableItem` is clear for Rust code, but just `InsertableItem` is less
 // clear in the UDL - so change some of the type names.
 pub type InsertableBookmarkItem = crate::storage::bookmarks::InsertableItem;
@@ -120,9 +137,15 @@ impl PlacesApi {
     #[handle_error(crate::Error)]
     pub fn new_connection(&self, conn_type: ConnectionType) -> ApiResult<Arc<PlacesConnection>> {
         let db = self.open_connection(conn_type)?;
-        let connection = Arc::new(PlacesConnection::new(db));
-        register_interrupt(Arc::<PlacesConnection>::downgrade(&connection));
-        Ok(connection)
+        Ok(Arc::new(PlacesConnection::new(db)))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn new_reader_pool(&self, size: u32) -> ApiResult<Arc<PlacesConnection>> {
+        let dbs = (0..size.clamp(1, MAX_READER_POOL_SIZE))
+            .map(|_| self.open_connection(ConnectionType::ReadOnly))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Arc::new(PlacesConnection::new_pool(dbs)))
     }
 
     // NOTE: These methods are unused on Android but will remain needed for
@@ -176,38 +199,98 @@ impl PlacesApi {
 }
 
 pub struct PlacesConnection {
-    db: Mutex<PlacesDb>,
-    interrupt_handle: Arc<SqlInterruptHandle>,
+    // A single connection, or, for a reader pool, several read-only
+    // connections that can run queries at the same time.
+    dbs: Vec<Mutex<PlacesDb>>,
+    // Where to start looking for an idle connection in the pool, so that
+    // callers are spread across it.
+    next_db: AtomicUsize,
+    interrupt_handle: Arc<PlacesConnectionInterruptHandle>,
+}
+
+// Interrupts the operations running on a `PlacesConnection`, or on all the connections in a
+// reader pool.
+pub struct PlacesConnectionInterruptHandle {
+    handles: Vec<Arc<SqlInterruptHandle>>,
+}
+
+impl PlacesConnectionInterruptHandle {
+    pub fn interrupt(&self) {
+        for handle in &self.handles {
+            handle.interrupt();
+        }
+    }
 }
 
 impl PlacesConnection {
     pub fn new(db: PlacesDb) -> Self {
+        Self::new_pool(vec![db])
+    }
+
+    pub fn new_pool(dbs: Vec<PlacesDb>) -> Self {
+        assert!(!dbs.is_empty(), "A pool needs at least one connection");
         Self {
-            interrupt_handle: db.new_interrupt_handle(),
-            db: Mutex::new(db),
+            interrupt_handle: Arc::new(PlacesConnectionInterruptHandle {
+                handles: dbs.iter().map(PlacesDb::new_interrupt_handle).collect(),
+            }),
+            dbs: dbs.into_iter().map(Mutex::new).collect(),
+            next_db: AtomicUsize::new(0),
         }
     }
 
+    // Returns an idle connection from the pool, or waits for one if they're
+    // all busy.
+    fn lock_db(&self) -> MutexGuard<'_, PlacesDb> {
+        let start = self.next_db.fetch_add(1, Ordering::Relaxed) % self.dbs.len();
+        for offset in 0..self.dbs.len() {
+            if let Some(db) = self.dbs[(start + offset) % self.dbs.len()].try_lock() {
+                return db;
+            }
+        }
+        self.dbs[start].lock()
+    }
+
     // A helper that gets the connection from the mutex and converts errors.
     fn with_conn<F, T>(&self, f: F) -> Result<T>
     where
         F: FnOnce(&PlacesDb) -> crate::error::Result<T>,
     {
-        let conn = self.db.lock();
-        f(&conn)
+        f(&self.lock_db())
+    }
+
+    // Like `with_conn`, but also records how long the operation took as a
+    // read. Only the operations that the Android wrappers count in
+    // `read_query_count` are measured, so the timings line up with the counts.
+    fn measure_read<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&PlacesDb) -> crate::error::Result<T>,
+    {
+        metrics::measure(PlacesOperation::Read, || self.with_conn(f))
     }
 
-    // pass the SqlInterruptHandle as an object through Uniffi
-    pub fn new_interrupt_handle(&self) -> Arc<SqlInterruptHandle> {
+    // Like `measure_read`, but for the operations counted in `write_query_count`.
+    fn measure_write<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&PlacesDb) -> crate::error::Result<T>,
+    {
+        metrics::measure(PlacesOperation::Write, || self.with_conn(f))
+    }
+
+    // pass the interrupt handle as an object through Uniffi
+    pub fn new_interrupt_handle(&self) -> Arc<PlacesConnectionInterruptHandle> {
         Arc::clone(&self.interrupt_handle)
     }
 
+    pub fn interrupt(&self) {
+        self.interrupt_handle.interrupt();
+    }
+
     #[handle_error(crate::Error)]
     pub fn get_latest_history_metadata_for_url(
         &self,
         url: Url,
     ) -> ApiResult<Option<HistoryMetadata>> {
-        self.with_conn(|conn| history_metadata::get_latest_for_url(conn, &url))
+        self.measure_read(|conn| history_metadata::get_latest_for_url(conn, &url))
     }
 
     #[handle_error(crate::Error)]
@@ -216,7 +299,7 @@ impl PlacesConnection {
         start: PlacesTimestamp,
         end: PlacesTimestamp,
     ) -> ApiResult<Vec<HistoryMetadata>> {
-        self.with_conn(|conn| {
+        self.measure_read(|conn| {
             history_metadata::get_between(conn, start.as_millis_i64(), end.as_millis_i64())
         })
     }
@@ -226,7 +309,7 @@ impl PlacesConnection {
         &self,
         start: PlacesTimestamp,
     ) -> ApiResult<Vec<HistoryMetadata>> {
-        self.with_conn(|conn| history_metadata::get_since(conn, start.as_millis_i64()))
+        self.measure_read(|conn| history_metadata::get_since(conn, start.as_millis_i64()))
     }
 
     #[handle_error(crate::Error)]
@@ -235,7 +318,12 @@ impl PlacesConnection {
         query: String,
         limit: i32,
     ) -> ApiResult<Vec<HistoryMetadata>> {
-        self.with_conn(|conn| history_metadata::query(conn, query.as_str(), limit))
+        self.measure_read(|conn| history_metadata::query(conn, query.as_str(), limit))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_recent_searches(&self, limit: i32) -> ApiResult<Vec<HistoryMetadata>> {
+        self.measure_read(|conn| history_metadata::get_recent_searches(conn, limit))
     }
 
     #[handle_error(crate::Error)]
@@ -244,7 +332,7 @@ impl PlacesConnection {
         weights: HistoryHighlightWeights,
         limit: i32,
     ) -> ApiResult<Vec<HistoryHighlight>> {
-        self.with_conn(|conn| history_metadata::get_highlights(conn, weights, limit))
+        self.measure_read(|conn| history_metadata::get_highlights(conn, weights, limit))
     }
 
     #[handle_error(crate::Error)]
@@ -258,7 +346,9 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn metadata_delete_older_than(&self, older_than: PlacesTimestamp) -> ApiResult<()> {
-        self.with_conn(|conn| history_metadata::delete_older_than(conn, older_than.as_millis_i64()))
+        self.measure_write(|conn| {
+            history_metadata::delete_older_than(conn, older_than.as_millis_i64())
+        })
     }
 
     #[handle_error(crate::Error)]
@@ -268,7 +358,7 @@ impl PlacesConnection {
         referrer_url: Option<Url>,
         search_term: Option<String>,
     ) -> ApiResult<()> {
-        self.with_conn(|conn| {
+        self.measure_write(|conn| {
             history_metadata::delete_metadata(
                 conn,
                 &url,
@@ -281,7 +371,7 @@ impl PlacesConnection {
     /// Add an observation to the database.
     #[handle_error(crate::Error)]
     pub fn apply_observation(&self, visit: VisitObservation) -> ApiResult<()> {
-        self.with_conn(|conn| history::apply_observation(conn, visit))?;
+        self.measure_write(|conn| history::apply_observation(conn, visit))?;
         Ok(())
     }
 
@@ -308,13 +398,48 @@ impl PlacesConnection {
         start_date: PlacesTimestamp,
         end_date: PlacesTimestamp,
         exclude_types: VisitTransitionSet,
+        source: VisitSource,
     ) -> ApiResult<Vec<HistoryVisitInfo>> {
-        self.with_conn(|conn| history::get_visit_infos(conn, start_date, end_date, exclude_types))
+        self.measure_read(|conn| {
+            history::get_visit_infos(conn, start_date, end_date, exclude_types, source)
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_visit_infos_page(
+        &self,
+        start_date: PlacesTimestamp,
+        end_date: PlacesTimestamp,
+        exclude_types: VisitTransitionSet,
+        source: VisitSource,
+        count: u32,
+        continuation: Option<String>,
+    ) -> ApiResult<HistoryVisitInfosPage> {
+        self.with_conn(|conn| {
+            history::get_visit_infos_page(
+                conn,
+                start_date,
+                end_date,
+                exclude_types,
+                source,
+                count,
+                continuation.as_deref(),
+            )
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_visit_count(
+        &self,
+        exclude_types: VisitTransitionSet,
+        source: VisitSource,
+    ) -> ApiResult<i64> {
+        self.with_conn(|conn| history::get_visit_count(conn, exclude_types, source))
     }
 
     #[handle_error(crate::Error)]
-    pub fn get_visit_count(&self, exclude_types: VisitTransitionSet) -> ApiResult<i64> {
-        self.with_conn(|conn| history::get_visit_count(conn, exclude_types))
+    pub fn get_page_visit_counts(&self, page_urls: Vec<Url>) -> ApiResult<Vec<PageVisitCounts>> {
+        self.with_conn(|conn| history::get_page_visit_counts(conn, &page_urls))
     }
 
     #[handle_error(crate::Error)]
@@ -357,7 +482,7 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn delete_visits_for(&self, url: String) -> ApiResult<()> {
-        self.with_conn(|conn| {
+        self.measure_write(|conn| {
             let guid = match Url::parse(&url) {
                 Ok(url) => history::url_to_guid(conn, &url)?,
                 Err(e) => {
@@ -372,18 +497,23 @@ impl PlacesConnection {
         })
     }
 
+    #[handle_error(crate::Error)]
+    pub fn delete_visits_for_host(&self, host: String) -> ApiResult<()> {
+        self.measure_write(|conn| history::delete_visits_for_host(conn, &host))
+    }
+
     #[handle_error(crate::Error)]
     pub fn delete_visits_between(
         &self,
         start: PlacesTimestamp,
         end: PlacesTimestamp,
     ) -> ApiResult<()> {
-        self.with_conn(|conn| history::delete_visits_between(conn, start, end))
+        self.measure_write(|conn| history::delete_visits_between(conn, start, end))
     }
 
     #[handle_error(crate::Error)]
     pub fn delete_visit(&self, url: String, timestamp: PlacesTimestamp) -> ApiResult<()> {
-        self.with_conn(|conn| {
+        self.measure_write(|conn| {
             match Url::parse(&url) {
                 Ok(url) => {
                     history::delete_place_visit_at_time(conn, &url, timestamp)?;
@@ -411,11 +541,92 @@ impl PlacesConnection {
             )
         })
     }
+
+    #[handle_error(crate::Error)]
+    pub fn get_top_frecent_sites(
+        &self,
+        limit: i32,
+        exclusions: TopSitesExclusions,
+    ) -> ApiResult<Vec<TopFrecentSiteInfo>> {
+        self.with_conn(|conn| history::get_top_frecent_sites(conn, limit, &exclusions))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn set_favicon_for_page(&self, page_url: Url, favicon: Favicon) -> ApiResult<()> {
+        self.measure_write(|conn| favicons::set_favicon_for_page(conn, &page_url, &favicon))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_favicon_for_page(
+        &self,
+        page_url: Url,
+        preferred_width: u32,
+    ) -> ApiResult<Option<Favicon>> {
+        self.measure_read(|conn| favicons::get_favicon_for_page(conn, &page_url, preferred_width))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn set_page_preview(&self, preview: PagePreview) -> ApiResult<()> {
+        self.measure_write(|conn| previews::set_page_preview(conn, &preview))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_page_previews(&self, page_urls: Vec<Url>) -> ApiResult<Vec<PagePreview>> {
+        self.measure_read(|conn| previews::get_page_previews(conn, &page_urls))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_page_guid(&self, page_url: Url) -> ApiResult<Option<Guid>> {
+        self.with_conn(|conn| annotations::get_page_guid(conn, &page_url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn set_page_annotation(
+        &self,
+        page_guid: Guid,
+        name: String,
+        value: AnnotationValue,
+        expiration: AnnotationExpiration,
+    ) -> ApiResult<()> {
+        self.with_conn(|conn| {
+            annotations::set_page_annotation(conn, &page_guid, &name, &value, expiration)
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_page_annotation(
+        &self,
+        page_guid: Guid,
+        name: String,
+    ) -> ApiResult<Option<AnnotationValue>> {
+        self.with_conn(|conn| annotations::get_page_annotation(conn, &page_guid, &name))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_page_annotations(&self, page_guid: Guid) -> ApiResult<Vec<PageAnnotation>> {
+        self.with_conn(|conn| annotations::get_page_annotations(conn, &page_guid))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_pages_with_annotation(&self, name: String) -> ApiResult<Vec<Guid>> {
+        self.with_conn(|conn| annotations::get_pages_with_annotation(conn, &name))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn remove_page_annotation(&self, page_guid: Guid, name: String) -> ApiResult<()> {
+        self.with_conn(|conn| annotations::remove_page_annotation(conn, &page_guid, &name))
+    }
+
     // deletes all history and updates the sync metadata to only sync after
     // most recent visit to prevent further syncing of older data
     #[handle_error(crate::Error)]
     pub fn delete_everything_history(&self) -> ApiResult<()> {
-        history::delete_everything(&self.db.lock())
+        self.measure_write(history::delete_everything)
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn run_maintenance(&self, db_size_limit: u32) -> ApiResult<RunMaintenanceMetrics> {
+        self.with_conn(|conn| storage::run_maintenance(conn, db_size_limit))
     }
 
     #[handle_error(crate::Error)]
@@ -427,6 +638,14 @@ impl PlacesConnection {
         self.with_conn(|conn| storage::run_maintenance_prune(conn, db_size_limit, prune_limit))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn run_maintenance_frecency(
+        &self,
+        budget: u32,
+    ) -> ApiResult<RunMaintenanceFrecencyMetrics> {
+        self.with_conn(|conn| storage::run_maintenance_frecency(conn, budget))
+    }
+
     #[handle_error(crate::Error)]
     pub fn run_maintenance_vacuum(&self) -> ApiResult<()> {
         self.with_conn(storage::run_maintenance_vacuum)
@@ -456,6 +675,25 @@ impl PlacesConnection {
         })
     }
 
+    #[handle_error(crate::Error)]
+    pub fn query_input_history(&self, search: String, limit: i32) -> ApiResult<Vec<SearchResult>> {
+        self.with_conn(|conn| {
+            matcher::search_input_history(
+                conn,
+                SearchParams {
+                    search_string: search,
+                    limit: limit as u32,
+                },
+            )
+            .map(|search_results| search_results.into_iter().map(Into::into).collect())
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn search_history(&self, query: String, limit: i32) -> ApiResult<Vec<SearchResult>> {
+        self.measure_read(|conn| history::search_history(conn, &query, limit as u32))
+    }
+
     #[handle_error(crate::Error)]
     pub fn accept_result(&self, search_string: String, url: String) -> ApiResult<()> {
         self.with_conn(|conn| {
@@ -477,6 +715,11 @@ impl PlacesConnection {
         self.with_conn(|conn| matcher::match_url(conn, query))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn match_origin_for_autofill(&self, prefix: String) -> ApiResult<Option<OriginAutofill>> {
+        self.with_conn(|conn| matcher::match_origin_for_autofill(conn, &prefix))
+    }
+
     #[handle_error(crate::Error)]
     pub fn bookmarks_get_tree(&self, item_guid: &Guid) -> ApiResult<Option<BookmarkItem>> {
         self.with_conn(|conn| bookmarks::fetch::fetch_tree(conn, item_guid))
@@ -488,7 +731,7 @@ impl PlacesConnection {
         guid: &Guid,
         get_direct_children: bool,
     ) -> ApiResult<Option<BookmarkItem>> {
-        self.with_conn(|conn| {
+        self.measure_read(|conn| {
             let bookmark = bookmarks::fetch::fetch_bookmark(conn, guid, get_direct_children)?;
             Ok(bookmark.map(BookmarkItem::from))
         })
@@ -496,7 +739,7 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn bookmarks_get_all_with_url(&self, url: String) -> ApiResult<Vec<BookmarkItem>> {
-        self.with_conn(|conn| {
+        self.measure_read(|conn| {
             // XXX - We should return the exact type - ie, BookmarkData rather than BookmarkItem.
             match Url::parse(&url) {
                 Ok(url) => Ok(bookmarks::fetch::fetch_bookmarks_by_url(conn, &url)?
@@ -514,7 +757,7 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn bookmarks_search(&self, query: String, limit: i32) -> ApiResult<Vec<BookmarkItem>> {
-        self.with_conn(|conn| {
+        self.measure_read(|conn| {
             // XXX - We should return the exact type - ie, BookmarkData rather than BookmarkItem.
             Ok(
                 bookmarks::fetch::search_bookmarks(conn, query.as_str(), limit as u32)?
@@ -527,7 +770,7 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn bookmarks_get_recent(&self, limit: i32) -> ApiResult<Vec<BookmarkItem>> {
-        self.with_conn(|conn| {
+        self.measure_read(|conn| {
             // XXX - We should return the exact type - ie, BookmarkData rather than BookmarkItem.
             Ok(bookmarks::fetch::recent_bookmarks(conn, limit as u32)?
                 .into_iter()
@@ -538,12 +781,12 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn bookmarks_delete(&self, id: Guid) -> ApiResult<bool> {
-        self.with_conn(|conn| bookmarks::delete_bookmark(conn, &id))
+        self.measure_write(|conn| bookmarks::delete_bookmark(conn, &id))
     }
 
     #[handle_error(crate::Error)]
     pub fn bookmarks_delete_everything(&self) -> ApiResult<()> {
-        self.with_conn(bookmarks::delete_everything)
+        self.measure_write(bookmarks::delete_everything)
     }
 
     #[handle_error(crate::Error)]
@@ -551,9 +794,70 @@ impl PlacesConnection {
         self.with_conn(|conn| bookmarks::bookmarks_get_url_for_keyword(conn, keyword.as_str()))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_get_by_keyword(&self, keyword: String) -> ApiResult<Vec<BookmarkItem>> {
+        self.measure_read(|conn| {
+            // XXX - We should return the exact type - ie, BookmarkData rather than BookmarkItem.
+            Ok(keywords::fetch_bookmarks_by_keyword(conn, &keyword)?
+                .into_iter()
+                .map(|b| BookmarkItem::Bookmark { b })
+                .collect())
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_get_keyword_search_url(&self, input: String) -> ApiResult<Option<Url>> {
+        self.measure_read(|conn| keywords::get_keyword_search_url(conn, &input))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_keyword_for_url(&self, url: Url) -> ApiResult<Option<String>> {
+        self.measure_read(|conn| keywords::get_keyword_for_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn set_keyword_for_url(&self, url: Url, keyword: String) -> ApiResult<()> {
+        self.measure_write(|conn| keywords::set_keyword_for_url(conn, &url, &keyword))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn remove_keyword(&self, keyword: String) -> ApiResult<()> {
+        self.measure_write(|conn| keywords::remove_keyword(conn, &keyword))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tag_url(&self, url: Url, tag: String) -> ApiResult<()> {
+        self.measure_write(|conn| tags::tag_url(conn, &url, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn untag_url(&self, url: Url, tag: String) -> ApiResult<()> {
+        self.measure_write(|conn| tags::untag_url(conn, &url, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn remove_all_tags_from_url(&self, url: Url) -> ApiResult<()> {
+        self.measure_write(|conn| tags::remove_all_tags_from_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn remove_tag(&self, tag: String) -> ApiResult<()> {
+        self.measure_write(|conn| tags::remove_tag(conn, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_tags_for_url(&self, url: Url) -> ApiResult<Vec<String>> {
+        self.measure_read(|conn| tags::get_tags_for_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_urls_with_tag(&self, tag: String) -> ApiResult<Vec<Url>> {
+        self.measure_read(|conn| tags::get_urls_with_tag(conn, &tag))
+    }
+
     #[handle_error(crate::Error)]
     pub fn bookmarks_insert(&self, data: InsertableBookmarkItem) -> ApiResult<Guid> {
-        self.with_conn(|conn| bookmarks::insert_bookmark(conn, data))
+        self.measure_write(|conn| bookmarks::insert_bookmark(conn, data))
     }
 
     #[handle_error(crate::Error)]
@@ -561,9 +865,20 @@ impl PlacesConnection {
         self.with_conn(|conn| bookmarks::update_bookmark_from_info(conn, item))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn set_bookmark_children_order(
+        &self,
+        parent_guid: Guid,
+        ordered_child_guids: Vec<Guid>,
+    ) -> ApiResult<()> {
+        self.with_conn(|conn| {
+            bookmarks::set_bookmark_children_order(conn, &parent_guid, &ordered_child_guids)
+        })
+    }
+
     #[handle_error(crate::Error)]
     pub fn bookmarks_count_bookmarks_in_trees(&self, guids: &[Guid]) -> ApiResult<u32> {
-        self.with_conn(|conn| bookmarks::count_bookmarks_in_trees(conn, guids))
+        self.measure_read(|conn| bookmarks::count_bookmarks_in_trees(conn, guids))
     }
 
     #[handle_error(crate::Error)]
@@ -574,11 +889,72 @@ impl PlacesConnection {
     ) -> ApiResult<HistoryMigrationResult> {
         self.with_conn(|conn| import_ios_history(conn, &db_path, last_sync_timestamp))
     }
-}
 
-impl AsRef<SqlInterruptHandle> for PlacesConnection {
-    fn as_ref(&self) -> &SqlInterruptHandle {
-        &self.interrupt_handle
+    #[handle_error(crate::Error)]
+    pub fn places_import_from_fennec(
+        &self,
+        db_path: String,
+        progress: Box<dyn MigrationProgressListener>,
+    ) -> ApiResult<FennecMigrationResult> {
+        self.measure_write(|conn| import_fennec(conn, &db_path, progress.as_ref()))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_history_import_from_chrome(
+        &self,
+        db_path: String,
+    ) -> ApiResult<HistoryMigrationResult> {
+        self.measure_write(|conn| import_chrome_history(conn, &db_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_chrome(
+        &self,
+        json_path: String,
+    ) -> ApiResult<BookmarksMigrationResult> {
+        self.measure_write(|conn| import_chrome_bookmarks(conn, &json_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_history_import_from_safari(
+        &self,
+        db_path: String,
+    ) -> ApiResult<HistoryMigrationResult> {
+        self.with_conn(|conn| import_safari_history(conn, &db_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_safari(
+        &self,
+        plist_path: String,
+    ) -> ApiResult<BookmarksMigrationResult> {
+        self.with_conn(|conn| import_safari_bookmarks(conn, &plist_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_html(
+        &self,
+        path: String,
+    ) -> ApiResult<BookmarksMigrationResult> {
+        self.with_conn(|conn| bookmark_files::import_html(conn, &path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_json(
+        &self,
+        path: String,
+    ) -> ApiResult<BookmarksMigrationResult> {
+        self.with_conn(|conn| bookmark_files::import_json(conn, &path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_export_to_html(&self, path: String) -> ApiResult<()> {
+        self.with_conn(|conn| bookmark_files::export_html(conn, &path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_export_to_json(&self, path: String) -> ApiResult<()> {
+        self.with_conn(|conn| bookmark_files::export_json(conn, &path))
     }
 }
 
@@ -599,11 +975,73 @@ pub struct HistoryVisitInfosWithBound {
     pub offset: i64,
 }
 
+#[derive(Clone, PartialEq, Eq)]
+pub struct HistoryVisitInfosPage {
+    pub infos: Vec<HistoryVisitInfo>,
+    pub continuation: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageVisitCounts {
+    pub url: Url,
+    pub local: i32,
+    pub remote: i32,
+    pub last_visit_date_local: PlacesTimestamp,
+    pub last_visit_date_remote: PlacesTimestamp,
+}
+
 pub struct TopFrecentSiteInfo {
     pub url: Url,
     pub title: Option<String>,
 }
 
+pub struct TopSitesExclusions {
+    pub pinned: Vec<Url>,
+    pub blocked: Vec<Url>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Favicon {
+    pub icon_url: Url,
+    pub width: u32,
+    pub data: Option<Vec<u8>>,
+    pub expires_at: PlacesTimestamp,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PagePreview {
+    pub url: Url,
+    pub preview_image_url: Option<Url>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnnotationValue {
+    Integer { value: i64 },
+    Real { value: f64 },
+    Text { value: String },
+    Bool { value: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AnnotationExpiration {
+    Never = 0,
+    WithHistory = 1,
+    Days = 2,
+    Weeks = 3,
+    Months = 4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageAnnotation {
+    pub name: String,
+    pub value: AnnotationValue,
+    pub expiration: AnnotationExpiration,
+    pub date_added: PlacesTimestamp,
+    pub last_modified: PlacesTimestamp,
+}
+
 pub enum FrecencyThresholdOption {
     None,
     SkipOneTimePages,
@@ -622,6 +1060,9 @@ pub struct SearchResult {
     pub url: Url,
     pub title: String,
     pub frecency: i64,
+    pub reasons: Vec<MatchReason>,
+    pub title_highlights: Vec<MatchRange>,
+    pub url_highlights: Vec<MatchRange>,
 }
 
 // Exists just to convince uniffi to generate `liftSequence*` helpers!
@@ -647,4 +1088,42 @@ mod tests {
         let invalid_url = "http://1234.56.78.90".to_string();
         assert!(PlacesConnection::bookmarks_get_all_with_url(&conn, invalid_url).is_ok());
     }
+
+    #[test]
+    fn test_reader_pool() {
+        let api = PlacesApi::new_memory("test_reader_pool").expect("should open api");
+        let pool = api.new_reader_pool(2).expect("should open pool");
+        assert_eq!(pool.dbs.len(), 2);
+        assert!(pool
+            .dbs
+            .iter()
+            .all(|db| db.lock().conn_type() == ConnectionType::ReadOnly));
+
+        // While one of the connections is busy, reads use the other one,
+        // instead of waiting.
+        let busy = pool.lock_db();
+        for _ in 0..4 {
+            assert_eq!(
+                pool.get_visit_count(VisitTransitionSet::empty(), VisitSource::All)
+                    .unwrap(),
+                0
+            );
+        }
+        drop(busy);
+
+        // The interrupt handle interrupts every connection in the pool.
+        let scopes = pool
+            .dbs
+            .iter()
+            .map(|db| db.lock().begin_interrupt_scope().unwrap())
+            .collect::<Vec<_>>();
+        pool.new_interrupt_handle().interrupt();
+        assert!(scopes.iter().all(|scope| scope.was_interrupted()));
+
+        assert_eq!(api.new_reader_pool(0).unwrap().dbs.len(), 1);
+        assert_eq!(
+            api.new_reader_pool(u32::MAX).unwrap().dbs.len(),
+            MAX_READER_POOL_SIZE as usize
+        );
+    }
 }
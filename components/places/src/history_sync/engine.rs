@@ -4,6 +4,7 @@
 
 use crate::db::{PlacesDb, SharedPlacesDb};
 use crate::error::*;
+use crate::metrics::{self, PlacesOperation};
 use crate::storage::history::{delete_everything, history_sync::reset};
 use crate::storage::{get_meta, put_meta};
 use interrupt_support::SqlInterruptScope;
@@ -14,8 +15,8 @@ use sync15::engine::{
 };
 use sync15::{telemetry, Guid, ServerTimestamp};
 
-use super::plan::{apply_plan, finish_plan, get_planned_outgoing};
-use super::MAX_INCOMING_PLACES;
+use super::plan::{apply_plan, finish_plan, finish_uploaded, get_planned_outgoing};
+use super::HistorySyncConfig;
 
 pub const LAST_SYNC_META_KEY: &str = "history_last_sync_time";
 // Note that all engines in this crate should use a *different* meta key
@@ -28,31 +29,17 @@ fn do_apply_incoming(
     scope: &SqlInterruptScope,
     inbound: Vec<IncomingBso>,
     telem: &mut telemetry::Engine,
+    config: &HistorySyncConfig,
 ) -> Result<()> {
     let mut incoming_telemetry = telemetry::EngineIncoming::new();
-    apply_plan(db, inbound, &mut incoming_telemetry, scope)?;
+    apply_plan(db, inbound, &mut incoming_telemetry, scope, config)?;
     telem.incoming(incoming_telemetry);
     Ok(())
 }
 
-fn do_sync_finished(
-    db: &PlacesDb,
-    new_timestamp: ServerTimestamp,
-    records_synced: Vec<Guid>,
-) -> Result<()> {
-    log::info!(
-        "sync completed after uploading {} records",
-        records_synced.len()
-    );
+fn do_sync_finished(db: &PlacesDb) -> Result<()> {
     finish_plan(db)?;
-
-    // write timestamp to reflect what we just wrote.
-    // XXX - should clean up transactions, but we *are not* in a transaction
-    // here, so this value applies immediately.
-    put_meta(db, LAST_SYNC_META_KEY, &new_timestamp.as_millis())?;
-
     db.pragma_update(None, "wal_checkpoint", "PASSIVE")?;
-
     Ok(())
 }
 
@@ -64,13 +51,19 @@ pub struct HistorySyncEngine {
     // Public because we use it in the [PlacesApi] sync methods.  We can probably make this private
     // once all syncing goes through the sync manager.
     pub(crate) scope: SqlInterruptScope,
+    config: HistorySyncConfig,
 }
 
 impl HistorySyncEngine {
     pub fn new(db: Arc<SharedPlacesDb>) -> Result<Self> {
+        Self::with_config(db, HistorySyncConfig::default())
+    }
+
+    pub fn with_config(db: Arc<SharedPlacesDb>, config: HistorySyncConfig) -> Result<Self> {
         Ok(Self {
             scope: db.begin_interrupt_scope()?,
             db,
+            config,
         })
     }
 }
@@ -89,7 +82,9 @@ impl SyncEngine for HistorySyncEngine {
         // just apply it directly. We can't advance our timestamp, which means if we are
         // interrupted we'll re-download and re-apply them, but that will be fine in practice.
         let conn = self.db.lock();
-        do_apply_incoming(&conn, &self.scope, inbound, telem)?;
+        metrics::measure(PlacesOperation::Sync, || {
+            do_apply_incoming(&conn, &self.scope, inbound, telem, &self.config)
+        })?;
         Ok(())
     }
 
@@ -99,18 +94,33 @@ impl SyncEngine for HistorySyncEngine {
         _telem: &mut telemetry::Engine,
     ) -> anyhow::Result<Vec<OutgoingBso>> {
         let conn = self.db.lock();
-        // We know we've seen everything incoming, so it's safe to write the timestamp now.
-        // If we are interrupted creating outgoing BSOs we won't re-apply what we just did.
-        put_meta(&conn, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
-        Ok(get_planned_outgoing(&conn)?)
+        Ok(metrics::measure(PlacesOperation::Sync, || {
+            // We know we've seen everything incoming, so it's safe to write the timestamp now.
+            // If we are interrupted creating outgoing BSOs we won't re-apply what we just did.
+            put_meta(&conn, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
+            get_planned_outgoing(&conn, &self.config)
+        })?)
     }
 
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> anyhow::Result<()> {
-        Ok(do_sync_finished(&self.db.lock(), new_timestamp, ids)?)
+        log::info!("uploaded a batch of {} records", ids.len());
+        let conn = self.db.lock();
+        // We're called for each batch the server commits, so mark these records
+        // as synced now, in case a later batch fails. Pages we didn't upload are
+        // only updated in `sync_finished()`, once everything is uploaded.
+        metrics::measure(PlacesOperation::Sync, || finish_uploaded(&conn, &ids))?;
+        // write timestamp to reflect what we just wrote.
+        // XXX - should clean up transactions, but we *are not* in a transaction
+        // here, so this value applies immediately.
+        put_meta(&conn, LAST_SYNC_META_KEY, &new_timestamp.as_millis())?;
+        Ok(())
     }
 
     fn sync_finished(&self) -> anyhow::Result<()> {
-        Ok(())
+        log::info!("sync completed");
+        Ok(metrics::measure(PlacesOperation::Sync, || {
+            do_sync_finished(&self.db.lock())
+        })?)
     }
 
     fn get_collection_request(
@@ -127,7 +137,7 @@ impl SyncEngine for HistorySyncEngine {
                 CollectionRequest::new("history".into())
                     .full()
                     .newer_than(since)
-                    .limit(MAX_INCOMING_PLACES, RequestOrder::Newest),
+                    .limit(self.config.max_records(), RequestOrder::Newest),
             )
         })
     }
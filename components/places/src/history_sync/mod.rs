@@ -15,11 +15,52 @@ pub mod record;
 
 pub use engine::HistorySyncEngine;
 
-const MAX_INCOMING_PLACES: usize = 5000;
-const MAX_OUTGOING_PLACES: usize = 5000;
-const MAX_VISITS: usize = 20;
+const DEFAULT_MAX_RECORDS_PER_BATCH: u32 = 5000;
+const DEFAULT_MAX_VISITS_PER_RECORD: u32 = 20;
+const DEFAULT_INCOMING_CHUNK_SIZE: u32 = 500;
 pub const HISTORY_TTL: u32 = 5_184_000; // 60 days in milliseconds
 
+/// Limits on how much history we download, upload and hold in memory while
+/// syncing. The defaults suit most devices; low-end devices may want smaller
+/// chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistorySyncConfig {
+    /// The most visits we upload for each page.
+    pub max_visits_per_record: u32,
+    /// The most records we download or upload in one sync. The newest
+    /// records are downloaded, and the most frecent pages are uploaded.
+    pub max_records_per_batch: u32,
+    /// The number of downloaded records we plan and apply at a time, so that
+    /// a large first sync doesn't hold the plans for all of them in memory.
+    pub incoming_chunk_size: u32,
+}
+
+impl Default for HistorySyncConfig {
+    fn default() -> Self {
+        Self {
+            max_visits_per_record: DEFAULT_MAX_VISITS_PER_RECORD,
+            max_records_per_batch: DEFAULT_MAX_RECORDS_PER_BATCH,
+            incoming_chunk_size: DEFAULT_INCOMING_CHUNK_SIZE,
+        }
+    }
+}
+
+impl HistorySyncConfig {
+    // A limit of zero would make us sync nothing, or never finish applying
+    // incoming records, so we treat it as one.
+    fn max_visits(&self) -> usize {
+        self.max_visits_per_record.max(1) as usize
+    }
+
+    fn max_records(&self) -> usize {
+        self.max_records_per_batch.max(1) as usize
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.incoming_chunk_size.max(1) as usize
+    }
+}
+
 /// Visit timestamps on the server are *microseconds* since the epoch.
 #[derive(
     Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize, Default,
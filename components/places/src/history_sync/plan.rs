@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use super::record::{HistoryRecord, HistoryRecordVisit};
-use super::{MAX_OUTGOING_PLACES, MAX_VISITS};
+use super::HistorySyncConfig;
 use crate::api::history::can_add_url;
 use crate::db::PlacesDb;
 use crate::error::*;
@@ -11,7 +11,7 @@ use crate::storage::{
     delete_pending_temp_tables,
     history::history_sync::{
         apply_synced_deletion, apply_synced_reconciliation, apply_synced_visits, fetch_outgoing,
-        fetch_visits, finish_outgoing, FetchedVisit, FetchedVisitPage,
+        fetch_visits, finish_outgoing, mark_uploaded, FetchedVisit, FetchedVisitPage,
     },
 };
 use crate::types::{UnknownFields, VisitType};
@@ -181,15 +181,36 @@ pub fn apply_plan(
     inbound: Vec<IncomingBso>,
     telem: &mut telemetry::EngineIncoming,
     interruptee: &impl Interruptee,
+    config: &HistorySyncConfig,
 ) -> Result<()> {
+    // We plan and apply the incoming records a chunk at a time, so that we
+    // only hold the plans for one chunk in memory. Each record is consumed
+    // as it's planned, so its payload is freed as we go.
+    let mut inbound = inbound.into_iter().peekable();
+    while inbound.peek().is_some() {
+        let chunk = inbound.by_ref().take(config.chunk_size());
+        let plans = plan_incoming_chunk(db, chunk, telem, interruptee, config.max_visits())?;
+        apply_incoming_chunk(db, plans, telem, interruptee)?;
+    }
+    log::info!("incoming: {}", serde_json::to_string(&telem).unwrap());
+    Ok(())
+}
+
+fn plan_incoming_chunk(
+    db: &PlacesDb,
+    chunk: impl Iterator<Item = IncomingBso>,
+    telem: &mut telemetry::EngineIncoming,
+    interruptee: &impl Interruptee,
+    max_visits: usize,
+) -> Result<Vec<(SyncGuid, IncomingPlan)>> {
     // for a first-cut, let's do this in the most naive way possible...
-    let mut plans: Vec<(SyncGuid, IncomingPlan)> = Vec::with_capacity(inbound.len());
-    for incoming in inbound {
+    let mut plans: Vec<(SyncGuid, IncomingPlan)> = Vec::with_capacity(chunk.size_hint().0);
+    for incoming in chunk {
         interruptee.err_if_interrupted()?;
         let content = incoming.into_content::<HistoryRecord>();
         let plan = match content.kind {
             IncomingKind::Tombstone => IncomingPlan::Delete,
-            IncomingKind::Content(record) => plan_incoming_record(db, record, MAX_VISITS),
+            IncomingKind::Content(record) => plan_incoming_record(db, record, max_visits),
             IncomingKind::Malformed => {
                 // We could push IncomingPlan::Invalid here, but the code before the IncomingKind
                 // refactor didn't know what `id` to use, so skipped it - so we do too.
@@ -203,9 +224,16 @@ pub fn apply_plan(
         };
         plans.push((content.envelope.id.clone(), plan));
     }
+    Ok(plans)
+}
 
+fn apply_incoming_chunk(
+    db: &PlacesDb,
+    plans: Vec<(SyncGuid, IncomingPlan)>,
+    telem: &mut telemetry::EngineIncoming,
+    interruptee: &impl Interruptee,
+) -> Result<()> {
     let mut tx = db.begin_transaction()?;
-
     for (guid, plan) in plans {
         interruptee.err_if_interrupted()?;
         match &plan {
@@ -265,21 +293,27 @@ pub fn apply_plan(
     // frecency and origin updates.
     delete_pending_temp_tables(db)?;
     tx.commit()?;
-    log::info!("incoming: {}", serde_json::to_string(&telem).unwrap());
     Ok(())
 }
 
-pub fn get_planned_outgoing(db: &PlacesDb) -> Result<Vec<OutgoingBso>> {
+pub fn get_planned_outgoing(db: &PlacesDb, config: &HistorySyncConfig) -> Result<Vec<OutgoingBso>> {
     // It might make sense for fetch_outgoing to manage its own
     // begin_transaction - even though doesn't seem a large bottleneck
     // at this time, the fact we hold a single transaction for the entire call
     // really is used only for performance, so it's certainly a candidate.
     let tx = db.begin_transaction()?;
-    let outgoing = fetch_outgoing(db, MAX_OUTGOING_PLACES, MAX_VISITS)?;
+    let outgoing = fetch_outgoing(db, config.max_records(), config.max_visits())?;
     tx.commit()?;
     Ok(outgoing)
 }
 
+pub fn finish_uploaded(db: &PlacesDb, ids: &[SyncGuid]) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    mark_uploaded(db, ids)?;
+    tx.commit()?;
+    Ok(())
+}
+
 pub fn finish_plan(db: &PlacesDb) -> Result<()> {
     let tx = db.begin_transaction()?;
     finish_outgoing(db)?;
@@ -350,9 +384,10 @@ mod tests {
             incoming,
             &mut telemetry::EngineIncoming::new(),
             &NeverInterrupts,
+            &HistorySyncConfig::default(),
         )
         .expect("should apply");
-        get_planned_outgoing(db).expect("should get outgoing")
+        get_planned_outgoing(db, &HistorySyncConfig::default()).expect("should get outgoing")
     }
 
     #[test]
@@ -733,7 +768,9 @@ mod tests {
         let visit = visits.into_iter().next().unwrap();
         assert_eq!(visit.visit_date, now);
 
-        // page should have frecency (going through a public api to get this is a pain)
+        // page should have frecency once it's recalculated (going through a
+        // public api to get this is a pain)
+        crate::storage::run_maintenance_frecency(&db, 10)?;
         // XXX - FIXME - searching for "title" here fails to find a result?
         // But above, we've checked title is in the record.
         let found = search_frecent(
@@ -769,6 +806,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_apply_plan_with_config() -> Result<()> {
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::Sync)?;
+        let now: Timestamp = SystemTime::now().into();
+        let config = HistorySyncConfig {
+            max_visits_per_record: 1,
+            max_records_per_batch: 2,
+            incoming_chunk_size: 2,
+        };
+
+        // Five incoming records, applied in chunks of two.
+        let incoming = (0..5)
+            .map(|i| {
+                IncomingBso::from_test_content(json!({
+                    "id": format!("incoming{:04}", i),
+                    "title": "title",
+                    "histUri": format!("http://example.com/{}", i),
+                    "visits": [ {"date": ServerVisitTimestamp::from(now), "type": 1}]
+                }))
+            })
+            .collect();
+        apply_plan(
+            &db,
+            incoming,
+            &mut telemetry::EngineIncoming::new(),
+            &NeverInterrupts,
+            &config,
+        )?;
+        for i in 0..5 {
+            let url = Url::parse(&format!("http://example.com/{}", i))?;
+            let (page, visits) = fetch_visits(&db, &url, 2)?.expect("page exists");
+            assert_eq!(page.guid.as_str(), format!("incoming{:04}", i));
+            assert_eq!(visits.len(), 1);
+        }
+
+        // Three local pages with two visits each, but we only upload two
+        // records, with one visit each.
+        for i in 0..3 {
+            let url = Url::parse(&format!("https://example.org/{}", i))?;
+            for visit_date in [now, Timestamp(now.0 - 1000)] {
+                apply_observation(
+                    &db,
+                    VisitObservation::new(url.clone())
+                        .with_visit_type(VisitType::Link)
+                        .with_at(Some(visit_date)),
+                )?;
+            }
+        }
+        let outgoing = get_planned_outgoing(&db, &config)?;
+        assert_eq!(outgoing.len(), 2);
+        for record in outgoing {
+            let payload: serde_json::Value = serde_json::from_str(&record.payload)?;
+            assert_eq!(payload["visits"].as_array().map(Vec::len), Some(1));
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_simple_visit_reconciliation() -> Result<()> {
         let _ = env_logger::try_init();
@@ -935,6 +1030,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_finish_uploaded() -> Result<()> {
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::Sync)?;
+        let url1 = Url::parse("https://example.com/1")?;
+        let url2 = Url::parse("https://example.com/2")?;
+        for url in [&url1, &url2] {
+            apply_observation(
+                &db,
+                VisitObservation::new(url.clone())
+                    .with_visit_type(VisitType::Link)
+                    .with_at(Some(SystemTime::now().into())),
+            )?;
+        }
+        let outgoing = apply_and_get_outgoing(&db, vec![]);
+        assert_eq!(outgoing.len(), 2);
+
+        // The server committed the batch with the first page, then failed.
+        finish_uploaded(&db, &[get_existing_guid(&db, &url1)])?;
+        assert_eq!(get_sync(&db, &url1), (SyncStatus::Normal, 0));
+        assert_eq!(get_sync(&db, &url2), (SyncStatus::Normal, 1));
+
+        // So next time, we only upload the second.
+        let outgoing = apply_and_get_outgoing(&db, vec![]);
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].envelope.id, get_existing_guid(&db, &url2));
+
+        // And once everything is uploaded, the first isn't counted twice.
+        finish_uploaded(&db, &[get_existing_guid(&db, &url2)])?;
+        finish_plan(&db)?;
+        assert_eq!(get_sync(&db, &url1), (SyncStatus::Normal, 0));
+        assert_eq!(get_sync(&db, &url2), (SyncStatus::Normal, 0));
+        Ok(())
+    }
+
     #[test]
     fn test_clamp_visit_date() {
         let ts = Timestamp::from(727_747_199_999);
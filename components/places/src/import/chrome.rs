@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+pub mod bookmarks;
+pub mod history;
+pub use bookmarks::import as import_bookmarks;
+pub use history::import as import_history;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::error::Result;
+    use crate::storage::history::history_sync::fetch_visits;
+    use crate::types::VisitType;
+    use crate::PlacesDb;
+    use types::Timestamp;
+    use url::Url;
+
+    /// The difference between Chrome's epoch, 1601-01-01, and the Unix
+    /// epoch, in microseconds.
+    const CHROME_EPOCH_OFFSET: u64 = 11_644_473_600_000_000;
+
+    fn create_chrome_db(path: &std::path::Path) -> Result<()> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE urls(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url LONGVARCHAR,
+                title LONGVARCHAR,
+                visit_count INTEGER DEFAULT 0 NOT NULL,
+                last_visit_time INTEGER NOT NULL
+            );
+            CREATE TABLE visits(
+                id INTEGER PRIMARY KEY,
+                url INTEGER NOT NULL,
+                visit_time INTEGER NOT NULL,
+                from_visit INTEGER,
+                transition INTEGER DEFAULT 0 NOT NULL
+            );",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_chrome_history() -> Result<()> {
+        let _ = env_logger::try_init();
+        let tmpdir = tempfile::tempdir().unwrap();
+        let chrome_path = tmpdir.path().join("History");
+        create_chrome_db(&chrome_path)?;
+        let chrome = rusqlite::Connection::open(&chrome_path)?;
+        let visit_date = Timestamp::now().as_millis() - 1000;
+        chrome.execute_batch(&format!(
+            "INSERT INTO urls(id, url, title, last_visit_time) VALUES
+                (1, 'https://example.com/', 'Example', {time}),
+                (2, 'https://example.org/', '', {time}),
+                (3, 'chrome://settings/', 'Settings', {time});
+            INSERT INTO visits(url, visit_time, transition) VALUES
+                (1, {time}, 1),                    -- TYPED
+                (1, {time} - 1000, 805306376),     -- RELOAD | CHAIN_START | CHAIN_END
+                (2, {time}, 2147483648),           -- LINK | SERVER_REDIRECT
+                (2, {time}, 3),                    -- AUTO_SUBFRAME
+                (3, {time}, 1);",
+            time = visit_date * 1000 + CHROME_EPOCH_OFFSET,
+        ))?;
+        drop(chrome);
+
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let result = import_history(&conn, &chrome_path)?;
+        // The subframe visit isn't counted, and the visit to the settings page
+        // fails.
+        assert_eq!(
+            (result.num_total, result.num_succeeded, result.num_failed),
+            (4, 3, 1)
+        );
+
+        let (info, visits) = fetch_visits(&conn, &Url::parse("https://example.com/")?, 10)?
+            .expect("should have imported the page");
+        assert_eq!(info.title, "Example");
+        let mut visits: Vec<_> = visits
+            .into_iter()
+            .map(|v| (v.visit_date, v.visit_type, v.is_local))
+            .collect();
+        visits.sort_by_key(|(visit_date, _, _)| *visit_date);
+        assert_eq!(
+            visits,
+            vec![
+                (Timestamp(visit_date - 1), Some(VisitType::Reload), true),
+                (Timestamp(visit_date), Some(VisitType::Typed), true),
+            ]
+        );
+
+        let (_, visits) = fetch_visits(&conn, &Url::parse("https://example.org/")?, 10)?
+            .expect("should have imported the page");
+        assert_eq!(visits.len(), 1);
+        assert_eq!(visits[0].visit_type, Some(VisitType::RedirectTemporary));
+
+        assert!(fetch_visits(&conn, &Url::parse("chrome://settings/")?, 10)?.is_none());
+        Ok(())
+    }
+}
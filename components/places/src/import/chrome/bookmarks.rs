@@ -0,0 +1,379 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{import_bookmark_items, BookmarksMigrationResult, ImportParent};
+use crate::storage::bookmarks::{
+    insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableFolder,
+    InsertableItem,
+};
+use crate::PlacesDb;
+use serde_derive::Deserialize;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+/// The difference between Chrome's epoch, 1601-01-01, and the Unix epoch, in
+/// microseconds.
+const CHROME_EPOCH_OFFSET: u64 = 11_644_473_600_000_000;
+
+#[derive(Deserialize)]
+struct ChromeBookmarks {
+    roots: ChromeRoots,
+}
+
+/// Chrome's roots. Each is a folder, but only its children are imported.
+#[derive(Deserialize)]
+struct ChromeRoots {
+    bookmark_bar: Option<ChromeNode>,
+    other: Option<ChromeNode>,
+    synced: Option<ChromeNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ChromeNode {
+    Folder {
+        #[serde(default)]
+        name: String,
+        date_added: Option<String>,
+        date_modified: Option<String>,
+        #[serde(default)]
+        children: Vec<ChromeNode>,
+    },
+    Url {
+        #[serde(default)]
+        name: String,
+        date_added: Option<String>,
+        url: String,
+    },
+}
+
+#[derive(Debug)]
+enum ChromeItem {
+    Folder {
+        title: Option<String>,
+        date_added: Option<Timestamp>,
+        last_modified: Option<Timestamp>,
+    },
+    Bookmark {
+        title: Option<String>,
+        date_added: Option<Timestamp>,
+        url: String,
+    },
+}
+
+/// This import is used for users switching from Chrome, and reads the
+/// `Bookmarks` JSON file from their Chrome profile.
+///
+/// Like the Safari import, bookmarks are inserted one at a time, so that an
+/// invalid bookmark only fails itself (and, for a folder, its children)
+/// instead of the whole import.
+///
+/// ### Basic process
+///
+/// - Read the JSON file.
+/// - Walk the trees under Chrome's bookmarks bar, other bookmarks and mobile
+///   bookmarks, parents before children, to find the items to import.
+/// - Insert each item into our toolbar, unfiled or mobile root, converting
+///   Chrome's timestamps, which are in microseconds since 1601. Chrome's GUIDs
+///   aren't valid for us, so every item gets a new one.
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<BookmarksMigrationResult> {
+    let bytes = std::fs::read(path)?;
+    let bookmarks: ChromeBookmarks = serde_json::from_slice(&bytes)?;
+    do_import(conn, bookmarks)
+}
+
+fn do_import(conn: &PlacesDb, bookmarks: ChromeBookmarks) -> Result<BookmarksMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    let import_start = Instant::now();
+
+    let items = find_items_to_import(bookmarks);
+    let num_total = items.len() as u32;
+    log::info!("The number of bookmarks is: {:?}", num_total);
+
+    let num_succeeded = import_bookmark_items(
+        &scope,
+        items,
+        |item, parent_guid| import_item(conn, item, parent_guid).map(Some),
+        |_| (),
+    )?;
+    log::info!("Successfully imported {} bookmarks!", num_succeeded);
+    Ok(BookmarksMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total - num_succeeded,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Converts one of Chrome's timestamps. Chrome uses 0 when it doesn't know.
+fn chrome_timestamp(value: Option<&str>) -> Option<Timestamp> {
+    let micros = value?.parse::<u64>().ok()?;
+    Some(Timestamp(micros.checked_sub(CHROME_EPOCH_OFFSET)? / 1000))
+}
+
+fn title(name: String) -> Option<String> {
+    (!name.is_empty()).then_some(name)
+}
+
+/// Returns the items to import in the order to insert them, along with where
+/// to insert them.
+fn find_items_to_import(bookmarks: ChromeBookmarks) -> Vec<(ChromeItem, ImportParent)> {
+    let roots = [
+        (bookmarks.roots.bookmark_bar, BookmarkRootGuid::Toolbar),
+        (bookmarks.roots.other, BookmarkRootGuid::Unfiled),
+        (bookmarks.roots.synced, BookmarkRootGuid::Mobile),
+    ];
+    let mut items = Vec::new();
+    for (node, root) in roots {
+        match node {
+            Some(ChromeNode::Folder { children, .. }) => {
+                add_children(children, ImportParent::Root(root), &mut items)
+            }
+            Some(ChromeNode::Url { .. }) => {
+                log::warn!("Skipping Chrome root {:?}, which isn't a folder", root)
+            }
+            None => (),
+        }
+    }
+    items
+}
+
+fn add_children(
+    children: Vec<ChromeNode>,
+    parent: ImportParent,
+    items: &mut Vec<(ChromeItem, ImportParent)>,
+) {
+    for child in children {
+        add_item(child, parent, items);
+    }
+}
+
+/// Adds `node` and, if it's a folder, its descendants. Chrome's files are
+/// parsed into a tree, so they can't contain cycles.
+fn add_item(node: ChromeNode, parent: ImportParent, items: &mut Vec<(ChromeItem, ImportParent)>) {
+    match node {
+        ChromeNode::Folder {
+            name,
+            date_added,
+            date_modified,
+            children,
+        } => {
+            let index = items.len();
+            items.push((
+                ChromeItem::Folder {
+                    title: title(name),
+                    date_added: chrome_timestamp(date_added.as_deref()),
+                    last_modified: chrome_timestamp(date_modified.as_deref()),
+                },
+                parent,
+            ));
+            add_children(children, ImportParent::Folder(index), items);
+        }
+        ChromeNode::Url {
+            name,
+            date_added,
+            url,
+        } => items.push((
+            ChromeItem::Bookmark {
+                title: title(name),
+                date_added: chrome_timestamp(date_added.as_deref()),
+                url,
+            },
+            parent,
+        )),
+    }
+}
+
+/// Inserts a single Chrome item, returning its new GUID.
+fn import_item(conn: &PlacesDb, item: ChromeItem, parent_guid: SyncGuid) -> Result<SyncGuid> {
+    let insertable: InsertableItem = match item {
+        ChromeItem::Folder {
+            title,
+            date_added,
+            last_modified,
+        } => InsertableFolder {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added,
+            last_modified,
+            guid: None,
+            title,
+            children: Vec::new(),
+        }
+        .into(),
+        ChromeItem::Bookmark {
+            title,
+            date_added,
+            url,
+        } => InsertableBookmark {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added,
+            last_modified: date_added,
+            guid: None,
+            url: Url::parse(&url)?,
+            title,
+        }
+        .into(),
+    };
+    insert_bookmark(conn, insertable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::error::Error;
+    use crate::storage::bookmarks::fetch::{fetch_bookmark, Item};
+    use crate::tests::assert_json_tree;
+    use serde_json::json;
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let date_added = 1_600_000_000_000u64;
+        let chrome_date_added = (date_added * 1000 + CHROME_EPOCH_OFFSET).to_string();
+        let bookmarks = json!({
+            "checksum": "00000000000000000000000000000000",
+            "roots": {
+                "bookmark_bar": {
+                    "type": "folder",
+                    "name": "Bookmarks bar",
+                    "date_added": chrome_date_added,
+                    "children": [
+                        {
+                            "type": "url",
+                            "name": "Example",
+                            "date_added": chrome_date_added,
+                            "url": "https://example.com/",
+                        },
+                        {
+                            "type": "folder",
+                            "name": "Folder",
+                            "date_added": "0",
+                            "children": [
+                                {
+                                    "type": "url",
+                                    "name": "Nested",
+                                    "url": "https://example.org/",
+                                },
+                                {
+                                    "type": "url",
+                                    "name": "Invalid",
+                                    "url": "not a url",
+                                },
+                            ],
+                        },
+                    ],
+                },
+                "other": {
+                    "type": "folder",
+                    "name": "Other bookmarks",
+                    "children": [
+                        {
+                            "type": "url",
+                            "name": "",
+                            "url": "https://example.net/",
+                        },
+                    ],
+                },
+                "synced": {
+                    "type": "folder",
+                    "name": "Mobile bookmarks",
+                    "children": [
+                        {
+                            "type": "url",
+                            "name": "Mobile",
+                            "url": "https://example.com/mobile",
+                        },
+                    ],
+                },
+            },
+            "version": 1,
+        });
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Bookmarks");
+        std::fs::write(&path, bookmarks.to_string())?;
+
+        let result = import(&conn, &path)?;
+        assert_eq!(result.num_total, 6);
+        assert_eq!(result.num_succeeded, 5);
+        assert_eq!(result.num_failed, 1);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Example",
+                        "url": "https://example.com/"
+                    },
+                    {
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "title": "Nested",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    }
+                ]
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.into(),
+            json!({
+                "children": [
+                    {
+                        "url": "https://example.net/"
+                    }
+                ]
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Mobile.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Mobile",
+                        "url": "https://example.com/mobile"
+                    }
+                ]
+            }),
+        );
+
+        let Some(Item::Folder { f: toolbar }) =
+            fetch_bookmark(&conn, &BookmarkRootGuid::Toolbar.into(), false)?
+        else {
+            panic!("the toolbar should be a folder");
+        };
+        let Some(Item::Bookmark { b }) =
+            fetch_bookmark(&conn, &toolbar.child_guids.unwrap()[0], false)?
+        else {
+            panic!("should have imported the bookmark");
+        };
+        assert_eq!(b.date_added, Timestamp(date_added));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_invalid() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Bookmarks");
+        std::fs::write(&path, "[]")?;
+        assert!(matches!(import(&conn, &path), Err(Error::JsonError(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, select_count, HistoryMigrationResult,
+};
+use crate::storage::update_all_frecencies_at_once;
+use crate::PlacesDb;
+use types::Timestamp;
+use url::Url;
+
+/// This import is used for users switching from Chrome, and reads the
+/// `History` SQLite database from their Chrome profile. Chrome keeps the
+/// database locked while it's running, so apps should pass a copy.
+///
+/// ### Basic process
+///
+/// - Attach the Chrome database.
+/// - Slurp http(s) pages into a temp table "chromeHistoryStaging" from the
+///   Chrome database, normalizing (and specifically, punycoding) their URLs.
+/// - Add any entries to moz_places that are needed.
+/// - Insert the visits, converting Chrome's timestamps, which are in
+///   microseconds since 1601, and page transitions into ours. Visits to
+///   subframes that the user didn't navigate, which we don't record either,
+///   aren't imported.
+/// - Update frecency for new items.
+/// - Cleanup (detach Chrome database, etc).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url)
+}
+
+fn do_import(conn: &PlacesDb, chrome_db_file_url: Url) -> Result<HistoryMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", chrome_db_file_url);
+    let auto_detach = attached_database(conn, &chrome_db_file_url, "chrome")?;
+    let tx = conn.begin_transaction()?;
+    let num_total = select_count(conn, &COUNT_CHROME_HISTORY_VISITS)?;
+    log::info!("The number of visits is: {:?}", num_total);
+
+    log::info!("Creating and populating staging table");
+    tx.execute_batch(&CREATE_STAGING_TABLE)?;
+    tx.execute_batch(&FILL_STAGING)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Updating old titles that may be missing, but now are available");
+    tx.execute_batch(&UPDATE_PLACES_TITLES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Populating missing entries in moz_places");
+    tx.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Inserting the history visits");
+    let num_succeeded = tx.execute(&INSERT_HISTORY_VISITS, [])? as u32;
+    scope.err_if_interrupted()?;
+
+    log::info!("Insert all new entries into stale frecencies");
+    let now = Timestamp::now().as_millis();
+    tx.execute(&ADD_TO_STALE_FRECENCIES, &[(":now", &now)])?;
+    scope.err_if_interrupted()?;
+
+    tx.commit()?;
+    log::info!("Successfully imported history visits!");
+
+    log::info!("Updating all frecencies");
+    update_all_frecencies_at_once(conn, &scope)?;
+    log::info!("Frecencies updated!");
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total.saturating_sub(num_succeeded),
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+lazy_static::lazy_static! {
+    // Count Chrome history visits, except for automatic subframe visits,
+    // which we skip.
+    static ref COUNT_CHROME_HISTORY_VISITS: &'static str =
+        "SELECT COUNT(*) FROM chrome.visits
+         WHERE (transition & 0xFF) <> 3"
+    ;
+
+    // We use a staging table purely so that we can normalize URLs (and
+    // specifically, punycode them)
+    static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE IF NOT EXISTS temp.chromeHistoryStaging(
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        ) WITHOUT ROWID;"
+    ;
+
+    static ref FILL_STAGING: &'static str = "
+        INSERT OR IGNORE INTO temp.chromeHistoryStaging(id, url, url_hash, title)
+            SELECT
+                id,
+                validate_url(url),
+                hash(validate_url(url)),
+                NULLIF(sanitize_utf8(title), '')
+            FROM chrome.urls
+            WHERE (url LIKE 'http://%' OR url LIKE 'https://%')
+            AND validate_url(url) IS NOT NULL"
+    ;
+
+    // UPDATE FROM isn't available until sqlite 3.33, so we use a subquery.
+    static ref UPDATE_PLACES_TITLES: &'static str =
+    "UPDATE main.moz_places
+        SET title = IFNULL((SELECT t.title
+                            FROM temp.chromeHistoryStaging t
+                            WHERE t.url_hash = main.moz_places.url_hash AND t.url = main.moz_places.url), title)"
+    ;
+
+    // Insert any missing entries into moz_places that we'll need for this.
+    static ref FILL_MOZ_PLACES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+        SELECT
+            IFNULL(
+                (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                generate_guid()
+            ),
+            t.url,
+            t.url_hash,
+            t.title,
+            -1,
+            1
+        FROM temp.chromeHistoryStaging t"
+    ;
+
+    // Insert history visits. Chrome's core transition types are in the low
+    // byte of `transition`, and its qualifiers in the high bits. Server
+    // redirects are flagged with 0x80000000, but Chrome doesn't say whether
+    // they were permanent.
+    static ref INSERT_HISTORY_VISITS: &'static str =
+    "INSERT OR IGNORE INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+        SELECT
+            NULL, -- Chrome's redirect chains refer to its own visit IDs.
+            (SELECT p.id FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+            sanitize_timestamp(v.visit_time / 1000 - 11644473600000),
+            CASE
+                WHEN (v.transition & 0x80000000) <> 0 THEN 6 -- RedirectTemporary
+                ELSE CASE (v.transition & 0xFF)
+                    WHEN 1 THEN 2 -- TYPED => Typed
+                    WHEN 2 THEN 3 -- AUTO_BOOKMARK => Bookmark
+                    WHEN 4 THEN 8 -- MANUAL_SUBFRAME => FramedLink
+                    WHEN 5 THEN 2 -- GENERATED => Typed
+                    WHEN 8 THEN 9 -- RELOAD => Reload
+                    WHEN 9 THEN 2 -- KEYWORD => Typed
+                    WHEN 10 THEN 2 -- KEYWORD_GENERATED => Typed
+                    ELSE 1 -- LINK, AUTO_TOPLEVEL, FORM_SUBMIT => Link
+                END
+            END,
+            1
+        FROM chrome.visits v
+        JOIN temp.chromeHistoryStaging t ON t.id = v.url
+        WHERE (v.transition & 0xFF) <> 3 -- AUTO_SUBFRAME"
+    ;
+
+    // Adds newly modified places entries into the stale frecencies table
+    static ref ADD_TO_STALE_FRECENCIES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places_stale_frecencies(place_id, stale_at)
+    SELECT
+        p.id,
+        :now
+    FROM main.moz_places p
+    WHERE p.frecency = -1"
+    ;
+}
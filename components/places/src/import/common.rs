@@ -4,9 +4,12 @@
 
 use crate::db::PlacesDb;
 use crate::error::*;
+use crate::storage::bookmarks::{get_raw_bookmark, BookmarkRootGuid};
+use interrupt_support::SqlInterruptScope;
 use rusqlite::{named_params, Connection};
 use serde::Serialize;
 use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
 use types::Timestamp;
 use url::Url;
 
@@ -171,6 +174,95 @@ pub struct HistoryMigrationResult {
     pub total_duration: u64,
 }
 
+#[derive(Serialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct BookmarksMigrationResult {
+    pub num_total: u32,
+    pub num_succeeded: u32,
+    pub num_failed: u32,
+    pub total_duration: u64,
+}
+
+/// The step of a migration that's being reported to a
+/// `MigrationProgressListener`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationPhase {
+    History,
+    Bookmarks,
+}
+
+/// Notified as a migration makes progress, so that the app can show it to
+/// the user. `total` is the number of records the phase will process, and
+/// `processed` counts both the records that were imported and those that
+/// were skipped because they were invalid.
+pub trait MigrationProgressListener: Send + Sync {
+    fn on_progress(&self, phase: MigrationPhase, processed: u32, total: u32);
+}
+
+/// Where an imported bookmark goes: either directly in one of our roots, or
+/// in a folder that's imported before it, by its index in the import order.
+#[derive(Debug, Clone, Copy)]
+pub enum ImportParent {
+    Root(BookmarkRootGuid),
+    Folder(usize),
+}
+
+/// Inserts bookmarks read by an importer, in the order they're given, which
+/// must put parents before their children.
+///
+/// Items are inserted one at a time with `insert_item`, which is given the
+/// GUID of the item's parent, and returns the item's new GUID, or `None` if
+/// it was skipped. An item that fails or is skipped only fails itself and,
+/// for a folder, its descendants, instead of the whole import.
+/// `on_processed` is called with the number of items processed so far after
+/// each one.
+///
+/// Returns the number of items that were inserted.
+pub fn import_bookmark_items<T>(
+    scope: &SqlInterruptScope,
+    items: Vec<(T, ImportParent)>,
+    mut insert_item: impl FnMut(T, SyncGuid) -> Result<Option<SyncGuid>>,
+    mut on_processed: impl FnMut(usize),
+) -> Result<u32> {
+    // The GUIDs of the items we've inserted, by their index in `items`.
+    let mut inserted_guids: Vec<Option<SyncGuid>> = Vec::with_capacity(items.len());
+    for (index, (item, parent)) in items.into_iter().enumerate() {
+        scope.err_if_interrupted()?;
+        let parent_guid = match parent {
+            ImportParent::Root(root) => Some(root.as_guid()),
+            ImportParent::Folder(parent_index) => inserted_guids[parent_index].clone(),
+        };
+        let inserted_guid = match parent_guid {
+            Some(parent_guid) => match insert_item(item, parent_guid) {
+                Ok(guid) => guid,
+                Err(e) => {
+                    log::warn!("Failed to import bookmark {}: {}", index, e);
+                    None
+                }
+            },
+            // Its parent failed, so we can't import it either.
+            None => None,
+        };
+        inserted_guids.push(inserted_guid);
+        on_processed(index + 1);
+    }
+    Ok(inserted_guids.iter().filter(|guid| guid.is_some()).count() as u32)
+}
+
+/// Returns `guid` if we can give it to an imported item, or `None` if the
+/// item needs a new one because `guid` is invalid or already used here.
+pub fn guid_to_keep(conn: &PlacesDb, guid: Option<SyncGuid>) -> Result<Option<SyncGuid>> {
+    Ok(match guid {
+        Some(guid)
+            if guid.is_valid_for_places()
+                && guid.is_valid_for_sync_server()
+                && get_raw_bookmark(conn, &guid)?.is_none() =>
+        {
+            Some(guid)
+        }
+        _ => None,
+    })
+}
+
 pub fn define_history_migration_functions(c: &Connection) -> Result<()> {
     use rusqlite::functions::FunctionFlags;
     c.create_scalar_function(
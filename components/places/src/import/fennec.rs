@@ -0,0 +1,211 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+pub mod bookmarks;
+pub mod history;
+pub use bookmarks::import as import_bookmarks;
+pub use history::import as import_history;
+
+use crate::error::{Error, Result};
+use crate::import::common::{
+    BookmarksMigrationResult, HistoryMigrationResult, MigrationProgressListener,
+};
+use crate::PlacesDb;
+use serde::Serialize;
+use sql_support::ConnExt;
+
+/// The oldest version of Fennec's `browser.db` we can import from. This is the
+/// version that added the `visits` table.
+const FENNEC_DB_VERSION: i64 = 34;
+
+#[derive(Serialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct FennecMigrationResult {
+    pub history: HistoryMigrationResult,
+    pub bookmarks: BookmarksMigrationResult,
+}
+
+/// Imports history, then bookmarks, from Fennec's `browser.db` at `path`.
+///
+/// This is meant to be run once, when a legacy user first starts the new app.
+/// Records that can't be imported are skipped and counted as failures in the
+/// result, rather than failing the whole import.
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+    progress: &dyn MigrationProgressListener,
+) -> Result<FennecMigrationResult> {
+    let path = path.as_ref();
+    let history = import_history(conn, path, progress)?;
+    let bookmarks = import_bookmarks(conn, path, progress)?;
+    Ok(FennecMigrationResult { history, bookmarks })
+}
+
+/// Checks that the attached `fennec` database is one we can import from.
+fn check_fennec_db_version(conn: &PlacesDb) -> Result<()> {
+    let db_version = conn.query_one::<i64>("PRAGMA fennec.user_version")?;
+    if db_version < FENNEC_DB_VERSION {
+        return Err(Error::UnsupportedDatabaseVersion(db_version));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::import::common::MigrationPhase;
+    use crate::storage::bookmarks::BookmarkRootGuid;
+    use crate::storage::history::history_sync::fetch_visits;
+    use std::sync::Mutex;
+    use types::Timestamp;
+    use url::Url;
+
+    #[derive(Default)]
+    struct RecordingListener(Mutex<Vec<(MigrationPhase, u32, u32)>>);
+
+    impl MigrationProgressListener for RecordingListener {
+        fn on_progress(&self, phase: MigrationPhase, processed: u32, total: u32) {
+            self.0.lock().unwrap().push((phase, processed, total));
+        }
+    }
+
+    fn create_fennec_db(path: &std::path::Path, version: i64) -> Result<()> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(&format!(
+            "CREATE TABLE history(
+                _id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT,
+                url TEXT NOT NULL,
+                guid TEXT NOT NULL,
+                deleted INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE visits(
+                _id INTEGER PRIMARY KEY AUTOINCREMENT,
+                history_guid TEXT NOT NULL,
+                visit_type TINYINT NOT NULL DEFAULT 1,
+                date INTEGER NOT NULL,
+                is_local TINYINT NOT NULL DEFAULT 1
+            );
+            CREATE TABLE bookmarks(
+                _id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT,
+                url TEXT,
+                type INTEGER NOT NULL DEFAULT 1,
+                parent INTEGER,
+                position INTEGER NOT NULL,
+                created INTEGER,
+                modified INTEGER,
+                guid TEXT NOT NULL,
+                deleted INTEGER NOT NULL DEFAULT 0
+            );
+            PRAGMA user_version = {version};"
+        ))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_fennec() -> Result<()> {
+        let _ = env_logger::try_init();
+        let tmpdir = tempfile::tempdir().unwrap();
+        let fennec_path = tmpdir.path().join("browser.db");
+        create_fennec_db(&fennec_path, 39)?;
+        let fennec = rusqlite::Connection::open(&fennec_path)?;
+        // Fennec stores visit dates in microseconds.
+        let visit_date = Timestamp::now().as_millis() - 1000;
+        fennec.execute_batch(&format!(
+            "INSERT INTO history(_id, title, url, guid, deleted) VALUES
+                (1, 'Example', 'https://example.com/', 'fennecpage01', 0),
+                (2, 'Invalid', 'not a url', 'fennecpage02', 0),
+                (3, 'Deleted', 'https://example.org/', 'fennecpage03', 1);
+            INSERT INTO visits(history_guid, visit_type, date, is_local) VALUES
+                ('fennecpage01', 2, {date}, 1),
+                ('fennecpage01', 0, {date} - 1000, 0),
+                ('fennecpage02', 1, {date}, 1);
+            INSERT INTO bookmarks(_id, title, url, type, parent, position, created, modified, guid, deleted) VALUES
+                (0, NULL, NULL, 0, 0, 0, 0, 0, 'places', 0),
+                (1, 'Mobile', NULL, 0, 0, 0, 0, 0, 'mobile', 0),
+                (2, 'Pinned', NULL, 0, 0, 1, 0, 0, 'pinned', 0),
+                (3, 'Folder', NULL, 0, 1, 0, 0, 0, 'fennecfold01', 0),
+                (4, 'Example', 'https://example.com/', 1, 3, 0, 0, 0, 'fennecbook01', 0),
+                (5, NULL, NULL, 2, 3, 1, 0, 0, 'fennecsep001', 0),
+                (6, 'Invalid', 'not a url', 1, 1, 1, 0, 0, 'fennecbook02', 0),
+                (7, 'Deleted', 'https://example.org/', 1, 1, 2, 0, 0, 'fennecbook03', 1),
+                (8, 'Pinned site', 'https://example.net/', 1, 2, 0, 0, 0, 'fennecbook04', 0);",
+            date = visit_date * 1000,
+        ))?;
+        drop(fennec);
+
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let listener = RecordingListener::default();
+        let result = import(&conn, &fennec_path, &listener)?;
+
+        assert_eq!(
+            (
+                result.history.num_total,
+                result.history.num_succeeded,
+                result.history.num_failed
+            ),
+            (3, 2, 1)
+        );
+        let (info, visits) = fetch_visits(&conn, &Url::parse("https://example.com/")?, 10)?
+            .expect("should have imported the page");
+        assert_eq!(info.guid.as_str(), "fennecpage01");
+        assert_eq!(info.title, "Example");
+        assert_eq!(visits.len(), 2);
+        assert!(visits.iter().any(|v| v.visit_date == Timestamp(visit_date)));
+
+        // The folder, its bookmark and separator, and the invalid bookmark,
+        // but not deleted or pinned items.
+        assert_eq!(
+            (
+                result.bookmarks.num_total,
+                result.bookmarks.num_succeeded,
+                result.bookmarks.num_failed
+            ),
+            (4, 3, 1)
+        );
+        let children_of = |parent: &str| -> Result<Vec<(String, u8)>> {
+            conn.query_rows_and_then(
+                "SELECT b.guid, b.type FROM moz_bookmarks b
+                 JOIN moz_bookmarks p ON p.id = b.parent
+                 WHERE p.guid = :parent
+                 ORDER BY b.position",
+                &[(":parent", &parent)],
+                |row| -> Result<_> { Ok((row.get(0)?, row.get(1)?)) },
+            )
+        };
+        assert_eq!(
+            children_of(BookmarkRootGuid::Mobile.as_str())?,
+            vec![("fennecfold01".to_string(), 2)]
+        );
+        let folder_children = children_of("fennecfold01")?;
+        assert_eq!(folder_children.len(), 2);
+        assert_eq!(folder_children[0], ("fennecbook01".to_string(), 1));
+        assert_eq!(folder_children[1].1, 3);
+
+        assert_eq!(
+            *listener.0.lock().unwrap(),
+            vec![
+                (MigrationPhase::History, 0, 3),
+                (MigrationPhase::History, 3, 3),
+                (MigrationPhase::Bookmarks, 0, 4),
+                (MigrationPhase::Bookmarks, 4, 4),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_fennec_old_version() -> Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let fennec_path = tmpdir.path().join("browser.db");
+        create_fennec_db(&fennec_path, FENNEC_DB_VERSION - 1)?;
+
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let err = import(&conn, &fennec_path, &RecordingListener::default())
+            .expect_err("should refuse to import");
+        assert!(matches!(err, Error::UnsupportedDatabaseVersion(33)));
+        Ok(())
+    }
+}
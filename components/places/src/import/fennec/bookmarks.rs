@@ -0,0 +1,246 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use super::check_fennec_db_version;
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, guid_to_keep, import_bookmark_items,
+    BookmarksMigrationResult, ImportParent, MigrationPhase, MigrationProgressListener,
+};
+use crate::storage::bookmarks::{
+    insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableFolder,
+    InsertableItem, InsertableSeparator,
+};
+use crate::PlacesDb;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+/// The number of bookmarks inserted between progress notifications.
+const PROGRESS_INTERVAL: usize = 100;
+
+// Fennec's bookmark types. Livemarks and queries aren't imported.
+const FENNEC_TYPE_FOLDER: i64 = 0;
+const FENNEC_TYPE_BOOKMARK: i64 = 1;
+const FENNEC_TYPE_SEPARATOR: i64 = 2;
+
+/// Maps the GUIDs of Fennec's roots to ours. Fennec's other roots, like
+/// "pinned", "tags" and "readinglist", aren't real bookmark folders, so their
+/// contents aren't imported.
+fn root_for_fennec_guid(guid: &str) -> Option<BookmarkRootGuid> {
+    match guid {
+        "mobile" => Some(BookmarkRootGuid::Mobile),
+        "menu" => Some(BookmarkRootGuid::Menu),
+        "toolbar" => Some(BookmarkRootGuid::Toolbar),
+        "unfiled" => Some(BookmarkRootGuid::Unfiled),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+struct FennecBookmark {
+    id: i64,
+    guid: String,
+    parent: i64,
+    kind: i64,
+    title: Option<String>,
+    url: Option<String>,
+    date_added: Timestamp,
+    last_modified: Timestamp,
+}
+
+impl FennecBookmark {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            id: row.get("_id")?,
+            guid: row.get("guid")?,
+            parent: row.get("parent")?,
+            kind: row.get("type")?,
+            title: row.get("title")?,
+            url: row.get("url")?,
+            date_added: row.get("created")?,
+            last_modified: row.get("modified")?,
+        })
+    }
+}
+
+/// This import is used for Android users migrating from Fennec's `browser.db`
+/// to the new rust-places store.
+///
+/// Unlike history, bookmarks are inserted one at a time, using the same code
+/// as `bookmarks_insert`, so that an invalid bookmark only fails itself (and,
+/// for a folder, its children) instead of the whole import.
+///
+/// ### Basic process
+///
+/// - Attach the Fennec database, and check that it's a version we know.
+/// - Read all the bookmarks that haven't been deleted.
+/// - Walk the trees under Fennec's mobile, menu, toolbar and unfiled roots,
+///   parents before children and children in order, to find the items to
+///   import.
+/// - Insert each item into the matching folder, keeping Fennec's GUIDs where
+///   we can so that Sync recognizes them, and notifying `progress` as we go.
+/// - Cleanup (detach Fennec database).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+    progress: &dyn MigrationProgressListener,
+) -> Result<BookmarksMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url, progress)
+}
+
+fn do_import(
+    conn: &PlacesDb,
+    fennec_db_file_url: Url,
+    progress: &dyn MigrationProgressListener,
+) -> Result<BookmarksMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", fennec_db_file_url);
+    let auto_detach = attached_database(conn, &fennec_db_file_url, "fennec")?;
+    check_fennec_db_version(conn)?;
+
+    let items = find_items_to_import(conn.query_rows_and_then(
+        &FETCH_FENNEC_BOOKMARKS,
+        [],
+        FennecBookmark::from_row,
+    )?);
+    let num_total = items.len() as u32;
+    log::info!("The number of bookmarks is: {:?}", num_total);
+    progress.on_progress(MigrationPhase::Bookmarks, 0, num_total);
+
+    let num_succeeded = import_bookmark_items(
+        &scope,
+        items,
+        |item, parent_guid| import_item(conn, &item, parent_guid),
+        |processed| {
+            if processed % PROGRESS_INTERVAL == 0 {
+                progress.on_progress(MigrationPhase::Bookmarks, processed as u32, num_total);
+            }
+        },
+    )?;
+    progress.on_progress(MigrationPhase::Bookmarks, num_total, num_total);
+    auto_detach.execute_now()?;
+
+    log::info!("Successfully imported {} bookmarks!", num_succeeded);
+    Ok(BookmarksMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total - num_succeeded,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Returns the items to import in the order to insert them, along with where
+/// to insert them.
+fn find_items_to_import(bookmarks: Vec<FennecBookmark>) -> Vec<(FennecBookmark, ImportParent)> {
+    let mut roots = Vec::new();
+    let mut children_by_parent: HashMap<i64, Vec<FennecBookmark>> = HashMap::new();
+    for bookmark in bookmarks {
+        match root_for_fennec_guid(&bookmark.guid) {
+            Some(root) => roots.push((bookmark.id, ImportParent::Root(root))),
+            None => children_by_parent
+                .entry(bookmark.parent)
+                .or_default()
+                .push(bookmark),
+        }
+    }
+
+    let mut items = Vec::new();
+    // A queue of folders whose children we need to add, and where to put
+    // those children.
+    let mut folders = VecDeque::from(roots);
+    while let Some((folder_id, import_parent)) = folders.pop_front() {
+        for child in children_by_parent.remove(&folder_id).unwrap_or_default() {
+            if child.kind == FENNEC_TYPE_FOLDER {
+                folders.push_back((child.id, ImportParent::Folder(items.len())));
+            }
+            items.push((child, import_parent));
+        }
+    }
+    items
+}
+
+/// Inserts a single Fennec item, returning its new GUID, or `None` if it's
+/// a kind of item we don't import.
+fn import_item(
+    conn: &PlacesDb,
+    item: &FennecBookmark,
+    parent_guid: SyncGuid,
+) -> Result<Option<SyncGuid>> {
+    // Keep Fennec's GUID, unless it's invalid or already used here, in which
+    // case we'll make a new one.
+    let guid = guid_to_keep(conn, Some(SyncGuid::from(item.guid.as_str())))?;
+    let position = BookmarkPosition::Append;
+    let date_added = Some(item.date_added);
+    let last_modified = Some(item.last_modified);
+    let insertable: InsertableItem = match item.kind {
+        FENNEC_TYPE_BOOKMARK => match &item.url {
+            Some(url) => InsertableBookmark {
+                parent_guid,
+                position,
+                date_added,
+                last_modified,
+                guid,
+                url: Url::parse(url)?,
+                title: item.title.clone(),
+            }
+            .into(),
+            None => {
+                log::warn!("Skipping bookmark {} with an invalid URL", item.id);
+                return Ok(None);
+            }
+        },
+        FENNEC_TYPE_FOLDER => InsertableFolder {
+            parent_guid,
+            position,
+            date_added,
+            last_modified,
+            guid,
+            title: item.title.clone(),
+            children: Vec::new(),
+        }
+        .into(),
+        FENNEC_TYPE_SEPARATOR => InsertableSeparator {
+            parent_guid,
+            position,
+            date_added,
+            last_modified,
+            guid,
+        }
+        .into(),
+        kind => {
+            log::warn!("Skipping bookmark {} of unsupported type {}", item.id, kind);
+            return Ok(None);
+        }
+    };
+    Ok(Some(insert_bookmark(conn, insertable)?))
+}
+
+lazy_static::lazy_static! {
+    // Fetch all the Fennec bookmarks which haven't been deleted, with each
+    // folder's children in order.
+    static ref FETCH_FENNEC_BOOKMARKS: &'static str =
+    "SELECT
+        _id,
+        guid,
+        IFNULL(parent, -1) AS parent,
+        type,
+        sanitize_utf8(title) AS title,
+        validate_url(url) AS url,
+        sanitize_timestamp(created) AS created,
+        sanitize_timestamp(modified) AS modified
+    FROM fennec.bookmarks
+    WHERE deleted = 0
+    ORDER BY parent, position, _id"
+    ;
+}
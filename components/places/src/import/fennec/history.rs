@@ -0,0 +1,222 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use super::check_fennec_db_version;
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, select_count, HistoryMigrationResult,
+    MigrationPhase, MigrationProgressListener,
+};
+use crate::storage::update_all_frecencies_at_once;
+use crate::PlacesDb;
+use rusqlite::named_params;
+use types::Timestamp;
+use url::Url;
+
+/// The number of Fennec visits inserted between progress notifications.
+const VISIT_CHUNK_SIZE: u32 = 5000;
+
+/// This import is used for Android users migrating from Fennec's `browser.db`
+/// to the new rust-places store.
+///
+/// ### Basic process
+///
+/// - Attach the Fennec database, and check that it's a version we know.
+/// - Slurp pages into a temp table "fennecHistoryStaging" from the Fennec
+///   database, normalizing (and specifically, punycoding) their URLs. Pages
+///   with invalid URLs are skipped.
+/// - Add any entries to moz_places that are needed, keeping Fennec's GUIDs
+///   where we can so that Sync recognizes them.
+/// - Insert the visits in chunks, notifying `progress` after each one. Visits
+///   to skipped pages are counted as failures.
+/// - Update frecency for new items.
+/// - Cleanup (detach Fennec database, etc).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+    progress: &dyn MigrationProgressListener,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url, progress)
+}
+
+fn do_import(
+    conn: &PlacesDb,
+    fennec_db_file_url: Url,
+    progress: &dyn MigrationProgressListener,
+) -> Result<HistoryMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", fennec_db_file_url);
+    let auto_detach = attached_database(conn, &fennec_db_file_url, "fennec")?;
+    check_fennec_db_version(conn)?;
+
+    let tx = conn.begin_transaction()?;
+    let num_total = select_count(conn, &COUNT_FENNEC_HISTORY_VISITS)?;
+    log::info!("The number of visits is: {:?}", num_total);
+    progress.on_progress(MigrationPhase::History, 0, num_total);
+
+    log::info!("Creating and populating staging table");
+    tx.execute_batch(&CREATE_STAGING_TABLE)?;
+    tx.execute_batch(&FILL_STAGING)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Updating old titles that may be missing, but now are available");
+    tx.execute_batch(&UPDATE_PLACES_TITLES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Populating missing entries in moz_places");
+    tx.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Inserting the history visits");
+    let mut num_processed = 0;
+    let mut num_succeeded = 0;
+    let mut last_id = 0;
+    loop {
+        let (chunk_len, chunk_end) = tx.query_row_and_then(
+            &NEXT_VISIT_CHUNK,
+            named_params! {
+                ":last_id": last_id,
+                ":chunk_size": VISIT_CHUNK_SIZE,
+            },
+            |row| -> rusqlite::Result<_> {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, Option<i64>>(1)?))
+            },
+        )?;
+        let chunk_end = match chunk_end {
+            Some(chunk_end) => chunk_end,
+            None => break,
+        };
+        num_succeeded += tx.execute(
+            &INSERT_HISTORY_VISITS,
+            named_params! {
+                ":last_id": last_id,
+                ":chunk_end": chunk_end,
+            },
+        )? as u32;
+        scope.err_if_interrupted()?;
+        last_id = chunk_end;
+        num_processed += chunk_len;
+        progress.on_progress(MigrationPhase::History, num_processed, num_total);
+    }
+
+    log::info!("Insert all new entries into stale frecencies");
+    let now = Timestamp::now().as_millis();
+    tx.execute(&ADD_TO_STALE_FRECENCIES, &[(":now", &now)])?;
+    scope.err_if_interrupted()?;
+
+    tx.commit()?;
+    log::info!("Successfully imported history visits!");
+
+    // We now update the frecencies as its own transaction, so that reader
+    // connections can read the migrated data without waiting for the
+    // frecencies to be up to date.
+    log::info!("Updating all frecencies");
+    update_all_frecencies_at_once(conn, &scope)?;
+    log::info!("Frecencies updated!");
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total.saturating_sub(num_succeeded),
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+lazy_static::lazy_static! {
+    // Count Fennec history visits
+    static ref COUNT_FENNEC_HISTORY_VISITS: &'static str =
+        "SELECT COUNT(*) FROM fennec.visits"
+    ;
+
+    // We use a staging table purely so that we can normalize URLs (and
+    // specifically, punycode them)
+    static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE IF NOT EXISTS temp.fennecHistoryStaging(
+            guid TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        ) WITHOUT ROWID;"
+    ;
+
+    static ref FILL_STAGING: &'static str = "
+        INSERT OR IGNORE INTO temp.fennecHistoryStaging(guid, url, url_hash, title)
+            SELECT
+                guid,
+                validate_url(url),
+                hash(validate_url(url)),
+                sanitize_utf8(title)
+            FROM fennec.history
+            WHERE deleted = 0
+            AND validate_url(url) IS NOT NULL"
+    ;
+
+    // UPDATE FROM isn't available until sqlite 3.33, so we use a subquery.
+    static ref UPDATE_PLACES_TITLES: &'static str =
+    "UPDATE main.moz_places
+        SET title = IFNULL((SELECT t.title
+                            FROM temp.fennecHistoryStaging t
+                            WHERE t.url_hash = main.moz_places.url_hash AND t.url = main.moz_places.url), title)"
+    ;
+
+    // Insert any missing entries into moz_places that we'll need for this.
+    // Fennec's GUIDs are Sync GUIDs, so we keep them if they're well-formed.
+    static ref FILL_MOZ_PLACES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+        SELECT
+            IFNULL(
+                (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                CASE WHEN LENGTH(t.guid) = 12 THEN t.guid ELSE generate_guid() END
+            ),
+            t.url,
+            t.url_hash,
+            t.title,
+            -1,
+            1
+        FROM temp.fennecHistoryStaging t"
+    ;
+
+    // Find the number of visits in the next chunk, and the last ID in it.
+    static ref NEXT_VISIT_CHUNK: &'static str =
+    "SELECT COUNT(*), MAX(_id) FROM (
+        SELECT _id FROM fennec.visits
+        WHERE _id > :last_id
+        ORDER BY _id
+        LIMIT :chunk_size
+    )"
+    ;
+
+    // Insert a chunk of history visits. Visits to pages we skipped, or that
+    // we couldn't add to moz_places, are dropped by the join.
+    static ref INSERT_HISTORY_VISITS: &'static str =
+    "INSERT OR IGNORE INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+        SELECT
+            NULL, -- Fennec does not store enough information to rebuild redirect chains.
+            p.id,
+            sanitize_timestamp(v.date), -- Fennec stores visit dates in microseconds.
+            CASE WHEN v.visit_type BETWEEN 1 AND 9 THEN v.visit_type ELSE 1 END,
+            v.is_local
+        FROM fennec.visits v
+        JOIN temp.fennecHistoryStaging t ON t.guid = v.history_guid
+        JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url
+        WHERE v._id > :last_id AND v._id <= :chunk_end"
+    ;
+
+    // Adds newly modified places entries into the stale frecencies table
+    static ref ADD_TO_STALE_FRECENCIES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places_stale_frecencies(place_id, stale_at)
+    SELECT
+        p.id,
+        :now
+    FROM main.moz_places p
+    WHERE p.frecency = -1"
+    ;
+}
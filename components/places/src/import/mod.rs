@@ -2,6 +2,14 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+pub mod chrome;
 pub mod common;
+pub mod fennec;
 pub mod ios;
+pub mod safari;
+pub use chrome::import_bookmarks as import_chrome_bookmarks;
+pub use chrome::import_history as import_chrome_history;
+pub use fennec::import as import_fennec;
 pub use ios::import_history as import_ios_history;
+pub use safari::import_bookmarks as import_safari_bookmarks;
+pub use safari::import_history as import_safari_history;
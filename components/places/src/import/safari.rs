@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+pub mod bookmarks;
+pub mod history;
+mod plist;
+pub use bookmarks::import as import_bookmarks;
+pub use history::import as import_history;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::error::Result;
+    use crate::storage::history::history_sync::fetch_visits;
+    use crate::types::VisitType;
+    use crate::PlacesDb;
+    use types::Timestamp;
+    use url::Url;
+
+    /// Seconds between the Unix epoch and Safari's, 2001-01-01.
+    const SAFARI_EPOCH_OFFSET: f64 = 978_307_200.0;
+
+    fn create_safari_db(path: &std::path::Path) -> Result<()> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE history_items(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                domain_expansion TEXT NULL,
+                visit_count INTEGER NOT NULL
+            );
+            CREATE TABLE history_visits(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                history_item INTEGER NOT NULL REFERENCES history_items(id),
+                visit_time REAL NOT NULL,
+                title TEXT NULL,
+                load_successful BOOLEAN NOT NULL DEFAULT 1,
+                http_non_get BOOLEAN NOT NULL DEFAULT 0,
+                synthesized BOOLEAN NOT NULL DEFAULT 0,
+                redirect_source INTEGER NULL UNIQUE REFERENCES history_visits(id),
+                redirect_destination INTEGER NULL UNIQUE REFERENCES history_visits(id),
+                origin INTEGER NOT NULL DEFAULT 0
+            );",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_safari_history() -> Result<()> {
+        let _ = env_logger::try_init();
+        let tmpdir = tempfile::tempdir().unwrap();
+        let safari_path = tmpdir.path().join("History.db");
+        create_safari_db(&safari_path)?;
+        let safari = rusqlite::Connection::open(&safari_path)?;
+        let visit_date = Timestamp::now().as_millis() - 1000;
+        safari.execute_batch(&format!(
+            "INSERT INTO history_items(id, url, visit_count) VALUES
+                (1, 'http://example.com/', 3),
+                (2, 'https://example.com/', 1),
+                (3, 'file:///tmp/page.html', 1);
+            INSERT INTO history_visits(id, history_item, visit_time, title, load_successful, redirect_destination, redirect_source, origin) VALUES
+                (1, 1, {time}, 'Old title', 1, 3, NULL, 0),
+                (2, 1, {time} + 1, 'Example', 1, NULL, NULL, 1),
+                (3, 2, {time}, 'Example', 1, NULL, 1, 0),
+                (4, 1, {time}, NULL, 0, NULL, NULL, 0),
+                (5, 3, {time}, 'Local file', 1, NULL, NULL, 0);",
+            time = visit_date as f64 / 1000.0 - SAFARI_EPOCH_OFFSET,
+        ))?;
+        drop(safari);
+
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let result = import_history(&conn, &safari_path)?;
+        // The failed load isn't counted, and the visit to the file fails.
+        assert_eq!(
+            (result.num_total, result.num_succeeded, result.num_failed),
+            (4, 3, 1)
+        );
+
+        let (info, visits) = fetch_visits(&conn, &Url::parse("http://example.com/")?, 10)?
+            .expect("should have imported the page");
+        // The title of the latest visit wins.
+        assert_eq!(info.title, "Example");
+        let mut visits: Vec<_> = visits
+            .into_iter()
+            .map(|v| (v.visit_date, v.visit_type, v.is_local))
+            .collect();
+        visits.sort_by_key(|(visit_date, _, _)| *visit_date);
+        assert_eq!(
+            visits,
+            vec![
+                (Timestamp(visit_date), Some(VisitType::Link), true),
+                (Timestamp(visit_date + 1000), Some(VisitType::Link), false),
+            ]
+        );
+
+        let (_, visits) = fetch_visits(&conn, &Url::parse("https://example.com/")?, 10)?
+            .expect("should have imported the page");
+        assert_eq!(visits.len(), 1);
+        assert_eq!(visits[0].visit_type, Some(VisitType::RedirectTemporary));
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use super::plist::{self, Value};
+use crate::error::{Error, Result};
+use crate::import::common::{import_bookmark_items, BookmarksMigrationResult, ImportParent};
+use crate::storage::bookmarks::{
+    insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark, InsertableFolder,
+    InsertableItem,
+};
+use crate::PlacesDb;
+use sync_guid::Guid as SyncGuid;
+use url::Url;
+
+// The values of `WebBookmarkType` we import. Proxies, like Safari's
+// "History" item, aren't real bookmarks.
+const SAFARI_TYPE_LIST: &str = "WebBookmarkTypeList";
+const SAFARI_TYPE_LEAF: &str = "WebBookmarkTypeLeaf";
+
+/// Maps the titles of Safari's top-level lists to our roots. The reading
+/// list isn't imported, and anything else at the top level goes in the menu.
+fn root_for_safari_title(title: &str) -> Option<BookmarkRootGuid> {
+    match title {
+        "BookmarksBar" => Some(BookmarkRootGuid::Toolbar),
+        "BookmarksMenu" => Some(BookmarkRootGuid::Menu),
+        _ => None,
+    }
+}
+
+const SAFARI_READING_LIST_TITLE: &str = "com.apple.ReadingList";
+
+#[derive(Debug)]
+enum SafariItem {
+    Folder { title: Option<String> },
+    Bookmark { title: Option<String>, url: String },
+}
+
+/// This import is used for users switching from Safari, and reads the
+/// `Bookmarks.plist` file from their Safari profile.
+///
+/// Like the Fennec import, bookmarks are inserted one at a time, so that an
+/// invalid bookmark only fails itself (and, for a folder, its children)
+/// instead of the whole import.
+///
+/// ### Basic process
+///
+/// - Read the property list.
+/// - Walk the trees under Safari's favorites bar and bookmarks menu, and any
+///   other top-level folders and bookmarks, parents before children, to find
+///   the items to import. The reading list isn't imported.
+/// - Insert each item into the matching folder. Safari doesn't record when
+///   bookmarks were added, so they're all added now.
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<BookmarksMigrationResult> {
+    let bytes = std::fs::read(path)?;
+    let root = plist::parse(&bytes)?;
+    do_import(conn, &root)
+}
+
+fn do_import(conn: &PlacesDb, root: &Value) -> Result<BookmarksMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    let import_start = Instant::now();
+
+    let items = find_items_to_import(root)?;
+    let num_total = items.len() as u32;
+    log::info!("The number of bookmarks is: {:?}", num_total);
+
+    let num_succeeded = import_bookmark_items(
+        &scope,
+        items,
+        |item, parent_guid| import_item(conn, item, parent_guid).map(Some),
+        |_| (),
+    )?;
+    log::info!("Successfully imported {} bookmarks!", num_succeeded);
+    Ok(BookmarksMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total - num_succeeded,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+fn children(value: &Value) -> &[Value] {
+    value
+        .get("Children")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+}
+
+fn title(value: &Value) -> Option<String> {
+    value
+        .get("Title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.is_empty())
+        .map(str::to_owned)
+}
+
+/// Returns the items to import in the order to insert them, along with where
+/// to insert them.
+fn find_items_to_import(root: &Value) -> Result<Vec<(SafariItem, ImportParent)>> {
+    if root.get("WebBookmarkType").and_then(Value::as_str) != Some(SAFARI_TYPE_LIST) {
+        return Err(Error::InvalidPropertyList("not a Safari bookmarks file"));
+    }
+    let mut items = Vec::new();
+    for child in children(root) {
+        let title = child.get("Title").and_then(Value::as_str);
+        match title.and_then(root_for_safari_title) {
+            Some(root) => add_children(child, ImportParent::Root(root), &mut items),
+            None if title == Some(SAFARI_READING_LIST_TITLE) => continue,
+            None => add_item(
+                child,
+                ImportParent::Root(BookmarkRootGuid::Menu),
+                &mut items,
+            ),
+        }
+    }
+    Ok(items)
+}
+
+fn add_children(folder: &Value, parent: ImportParent, items: &mut Vec<(SafariItem, ImportParent)>) {
+    for child in children(folder) {
+        add_item(child, parent, items);
+    }
+}
+
+/// Adds `value` and, if it's a folder, its descendants. Safari's files are
+/// parsed into a tree, so they can't contain cycles.
+fn add_item(value: &Value, parent: ImportParent, items: &mut Vec<(SafariItem, ImportParent)>) {
+    match value.get("WebBookmarkType").and_then(Value::as_str) {
+        Some(SAFARI_TYPE_LIST) => {
+            let index = items.len();
+            items.push((
+                SafariItem::Folder {
+                    title: title(value),
+                },
+                parent,
+            ));
+            add_children(value, ImportParent::Folder(index), items);
+        }
+        Some(SAFARI_TYPE_LEAF) => {
+            let title = value
+                .get("URIDictionary")
+                .and_then(|uri_dictionary| uri_dictionary.get("title"))
+                .and_then(Value::as_str)
+                .filter(|title| !title.is_empty())
+                .map(str::to_owned);
+            let url = value
+                .get("URLString")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            items.push((SafariItem::Bookmark { title, url }, parent));
+        }
+        kind => log::warn!("Skipping Safari bookmark of unsupported type {:?}", kind),
+    }
+}
+
+/// Inserts a single Safari item, returning its new GUID.
+fn import_item(conn: &PlacesDb, item: SafariItem, parent_guid: SyncGuid) -> Result<SyncGuid> {
+    let insertable: InsertableItem = match item {
+        SafariItem::Folder { title } => InsertableFolder {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added: None,
+            last_modified: None,
+            guid: None,
+            title,
+            children: Vec::new(),
+        }
+        .into(),
+        SafariItem::Bookmark { title, url } => InsertableBookmark {
+            parent_guid,
+            position: BookmarkPosition::Append,
+            date_added: None,
+            last_modified: None,
+            guid: None,
+            url: Url::parse(&url)?,
+            title,
+        }
+        .into(),
+    };
+    insert_bookmark(conn, insertable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::ConnectionType;
+    use crate::tests::assert_json_tree;
+    use serde_json::json;
+
+    fn dictionary(entries: Vec<(&str, Value)>) -> Value {
+        Value::Dictionary(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    fn list(title: &str, children: Vec<Value>) -> Value {
+        dictionary(vec![
+            ("WebBookmarkType", Value::String(SAFARI_TYPE_LIST.into())),
+            ("Title", Value::String(title.into())),
+            ("Children", Value::Array(children)),
+        ])
+    }
+
+    fn leaf(title: &str, url: &str) -> Value {
+        dictionary(vec![
+            ("WebBookmarkType", Value::String(SAFARI_TYPE_LEAF.into())),
+            ("URLString", Value::String(url.into())),
+            (
+                "URIDictionary",
+                dictionary(vec![("title", Value::String(title.into()))]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let root = list(
+            "",
+            vec![
+                dictionary(vec![
+                    (
+                        "WebBookmarkType",
+                        Value::String("WebBookmarkTypeProxy".into()),
+                    ),
+                    ("Title", Value::String("History".into())),
+                ]),
+                list(
+                    "BookmarksBar",
+                    vec![
+                        leaf("Example", "https://example.com/"),
+                        list(
+                            "Folder",
+                            vec![
+                                leaf("Nested", "https://example.org/"),
+                                leaf("Invalid", "not a url"),
+                            ],
+                        ),
+                    ],
+                ),
+                list("BookmarksMenu", vec![leaf("Menu", "https://example.net/")]),
+                list(
+                    SAFARI_READING_LIST_TITLE,
+                    vec![leaf("Reading", "https://example.com/read")],
+                ),
+                leaf("Top", "https://example.com/top"),
+            ],
+        );
+        let bytes = plist::to_bytes(&root);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Bookmarks.plist");
+        std::fs::write(&path, bytes)?;
+
+        let result = import(&conn, &path)?;
+        assert_eq!(result.num_total, 6);
+        assert_eq!(result.num_succeeded, 5);
+        assert_eq!(result.num_failed, 1);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Example",
+                        "url": "https://example.com/"
+                    },
+                    {
+                        "title": "Folder",
+                        "children": [
+                            {
+                                "title": "Nested",
+                                "url": "https://example.org/"
+                            }
+                        ]
+                    }
+                ]
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "children": [
+                    {
+                        "title": "Menu",
+                        "url": "https://example.net/"
+                    },
+                    {
+                        "title": "Top",
+                        "url": "https://example.com/top"
+                    }
+                ]
+            }),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_invalid() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Bookmarks.plist");
+        std::fs::write(&path, plist::to_bytes(&Value::Array(vec![])))?;
+        assert!(matches!(
+            import(&conn, &path),
+            Err(Error::InvalidPropertyList(_))
+        ));
+        Ok(())
+    }
+}
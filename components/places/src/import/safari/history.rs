@@ -0,0 +1,171 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, select_count, HistoryMigrationResult,
+};
+use crate::storage::update_all_frecencies_at_once;
+use crate::PlacesDb;
+use types::Timestamp;
+use url::Url;
+
+/// This import is used for users switching from Safari, and reads the
+/// `History.db` SQLite database from their Safari profile. Safari keeps the
+/// database open while it's running, so apps should pass a copy.
+///
+/// ### Basic process
+///
+/// - Attach the Safari database.
+/// - Slurp http(s) pages into a temp table "safariHistoryStaging" from the
+///   Safari database, normalizing (and specifically, punycoding) their URLs.
+///   Safari stores titles with each visit, so we use the latest one.
+/// - Add any entries to moz_places that are needed.
+/// - Insert the visits, converting Safari's timestamps, which are in seconds
+///   since 2001. Visits to pages that failed to load aren't imported.
+/// - Update frecency for new items.
+/// - Cleanup (detach Safari database, etc).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url)
+}
+
+fn do_import(conn: &PlacesDb, safari_db_file_url: Url) -> Result<HistoryMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", safari_db_file_url);
+    let auto_detach = attached_database(conn, &safari_db_file_url, "safari")?;
+    let tx = conn.begin_transaction()?;
+    let num_total = select_count(conn, &COUNT_SAFARI_HISTORY_VISITS)?;
+    log::info!("The number of visits is: {:?}", num_total);
+
+    log::info!("Creating and populating staging table");
+    tx.execute_batch(&CREATE_STAGING_TABLE)?;
+    tx.execute_batch(&FILL_STAGING)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Updating old titles that may be missing, but now are available");
+    tx.execute_batch(&UPDATE_PLACES_TITLES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Populating missing entries in moz_places");
+    tx.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Inserting the history visits");
+    let num_succeeded = tx.execute(&INSERT_HISTORY_VISITS, [])? as u32;
+    scope.err_if_interrupted()?;
+
+    log::info!("Insert all new entries into stale frecencies");
+    let now = Timestamp::now().as_millis();
+    tx.execute(&ADD_TO_STALE_FRECENCIES, &[(":now", &now)])?;
+    scope.err_if_interrupted()?;
+
+    tx.commit()?;
+    log::info!("Successfully imported history visits!");
+
+    log::info!("Updating all frecencies");
+    update_all_frecencies_at_once(conn, &scope)?;
+    log::info!("Frecencies updated!");
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed: num_total.saturating_sub(num_succeeded),
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+lazy_static::lazy_static! {
+    // Count Safari history visits to pages that loaded.
+    static ref COUNT_SAFARI_HISTORY_VISITS: &'static str =
+        "SELECT COUNT(*) FROM safari.history_visits
+         WHERE load_successful"
+    ;
+
+    // We use a staging table purely so that we can normalize URLs (and
+    // specifically, punycode them)
+    static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE IF NOT EXISTS temp.safariHistoryStaging(
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        ) WITHOUT ROWID;"
+    ;
+
+    static ref FILL_STAGING: &'static str = "
+        INSERT OR IGNORE INTO temp.safariHistoryStaging(id, url, url_hash, title)
+            SELECT
+                h.id,
+                validate_url(h.url),
+                hash(validate_url(h.url)),
+                (SELECT NULLIF(sanitize_utf8(v.title), '')
+                 FROM safari.history_visits v
+                 WHERE v.history_item = h.id AND v.title IS NOT NULL
+                 ORDER BY v.visit_time DESC
+                 LIMIT 1)
+            FROM safari.history_items h
+            WHERE (h.url LIKE 'http://%' OR h.url LIKE 'https://%')
+            AND validate_url(h.url) IS NOT NULL"
+    ;
+
+    // UPDATE FROM isn't available until sqlite 3.33, so we use a subquery.
+    static ref UPDATE_PLACES_TITLES: &'static str =
+    "UPDATE main.moz_places
+        SET title = IFNULL((SELECT t.title
+                            FROM temp.safariHistoryStaging t
+                            WHERE t.url_hash = main.moz_places.url_hash AND t.url = main.moz_places.url), title)"
+    ;
+
+    // Insert any missing entries into moz_places that we'll need for this.
+    static ref FILL_MOZ_PLACES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+        SELECT
+            IFNULL(
+                (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                generate_guid()
+            ),
+            t.url,
+            t.url_hash,
+            t.title,
+            -1,
+            1
+        FROM temp.safariHistoryStaging t"
+    ;
+
+    // Insert history visits. Safari doesn't record how the user got to a
+    // page, other than whether it was a redirect, or whether the page was
+    // visited on another device and synced through iCloud, in `origin`.
+    static ref INSERT_HISTORY_VISITS: &'static str =
+    "INSERT OR IGNORE INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+        SELECT
+            NULL, -- Safari's redirect chains refer to its own visit IDs.
+            (SELECT p.id FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+            sanitize_float_timestamp((v.visit_time + 978307200) * 1000),
+            CASE WHEN v.redirect_source IS NOT NULL THEN 6 ELSE 1 END, -- RedirectTemporary or Link
+            v.origin = 0
+        FROM safari.history_visits v
+        JOIN temp.safariHistoryStaging t ON t.id = v.history_item
+        WHERE v.load_successful"
+    ;
+
+    // Adds newly modified places entries into the stale frecencies table
+    static ref ADD_TO_STALE_FRECENCIES: &'static str =
+    "INSERT OR IGNORE INTO main.moz_places_stale_frecencies(place_id, stale_at)
+    SELECT
+        p.id,
+        :now
+    FROM main.moz_places p
+    WHERE p.frecency = -1"
+    ;
+}
@@ -0,0 +1,430 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A minimal reader for Apple's binary property list format ("bplist00"),
+//! which is how Safari stores `Bookmarks.plist`. It reads the whole file into
+//! a tree of `Value`s; we don't need to write property lists, or read the XML
+//! format.
+//!
+//! The format is described in CoreFoundation's `CFBinaryPList.c`: a header,
+//! the objects, a table of object offsets, and a trailer that says where to
+//! find the table and the top-level object. Containers refer to their
+//! children by index into the offset table.
+
+use crate::error::{Error, Result};
+
+/// The deepest we'll nest containers, so that a malicious file with a cycle
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Real(f64),
+    /// Seconds since 2001-01-01 00:00:00 UTC.
+    Date(f64),
+    Data(Vec<u8>),
+    String(String),
+    Uid(u64),
+    Array(Vec<Value>),
+    /// A dictionary, with its entries in the order they're stored.
+    Dictionary(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the value for `key` if this is a dictionary that has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dictionary(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidPropertyList(reason)
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes.
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+/// Parses a binary property list.
+pub fn parse(bytes: &[u8]) -> Result<Value> {
+    const HEADER: &[u8] = b"bplist00";
+    const TRAILER_LEN: usize = 32;
+    if bytes.len() < HEADER.len() + TRAILER_LEN || !bytes.starts_with(HEADER) {
+        return Err(invalid("not a binary property list"));
+    }
+    let trailer = &bytes[bytes.len() - TRAILER_LEN..];
+    let offset_size = usize::from(trailer[6]);
+    let ref_size = usize::from(trailer[7]);
+    let num_objects = read_uint(&trailer[8..16]);
+    let top_object = read_uint(&trailer[16..24]);
+    let offset_table_offset = read_uint(&trailer[24..32]);
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&ref_size) {
+        return Err(invalid("invalid trailer"));
+    }
+
+    let parser = Parser { bytes, ref_size };
+    let offset_table = usize::try_from(num_objects)
+        .ok()
+        .and_then(|num_objects| num_objects.checked_mul(offset_size))
+        .and_then(|len| parser.slice(offset_table_offset, len).ok())
+        .ok_or_else(|| invalid("invalid offset table"))?;
+    let offsets: Vec<u64> = offset_table.chunks(offset_size).map(read_uint).collect();
+    parser.object(&offsets, top_object, 0)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    ref_size: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn slice(&self, start: u64, len: usize) -> Result<&'a [u8]> {
+        usize::try_from(start)
+            .ok()
+            .and_then(|start| Some((start, start.checked_add(len)?)))
+            .and_then(|(start, end)| self.bytes.get(start..end))
+            .ok_or_else(|| invalid("object out of bounds"))
+    }
+
+    /// Reads the length of a variable-length object whose marker is at
+    /// `offset`, returning the length and the offset of the object's contents.
+    fn length(&self, offset: u64, marker: u8) -> Result<(usize, u64)> {
+        let info = marker & 0x0F;
+        if info != 0x0F {
+            return Ok((usize::from(info), offset + 1));
+        }
+        // The length is stored as an integer object after the marker.
+        let int_marker = self.slice(offset + 1, 1)?[0];
+        if int_marker & 0xF0 != 0x10 || int_marker & 0x0F > 3 {
+            return Err(invalid("invalid length"));
+        }
+        let int_size = 1usize << (int_marker & 0x0F);
+        let len = read_uint(self.slice(offset + 2, int_size)?);
+        let len = usize::try_from(len).map_err(|_| invalid("invalid length"))?;
+        Ok((len, offset + 2 + int_size as u64))
+    }
+
+    /// Reads the object references of a container.
+    fn refs(&self, offset: u64, count: usize) -> Result<Vec<u64>> {
+        let len = count
+            .checked_mul(self.ref_size)
+            .ok_or_else(|| invalid("invalid container"))?;
+        Ok(self
+            .slice(offset, len)?
+            .chunks(self.ref_size)
+            .map(read_uint)
+            .collect())
+    }
+
+    fn object(&self, offsets: &[u64], index: u64, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("too deeply nested"));
+        }
+        let offset = usize::try_from(index)
+            .ok()
+            .and_then(|index| offsets.get(index))
+            .copied()
+            .ok_or_else(|| invalid("invalid object reference"))?;
+        let marker = self.slice(offset, 1)?[0];
+        Ok(match marker >> 4 {
+            0x0 => match marker {
+                0x00 => Value::Null,
+                0x08 => Value::Bool(false),
+                0x09 => Value::Bool(true),
+                _ => return Err(invalid("unknown object type")),
+            },
+            0x1 => {
+                let size = 1usize << (marker & 0x0F);
+                let bytes = self.slice(offset + 1, size)?;
+                // 16-byte integers are only used for values that don't fit in
+                // an i64, so we keep the low 8 bytes.
+                Value::Integer(read_uint(&bytes[size.saturating_sub(8)..]) as i64)
+            }
+            0x2 | 0x3 => {
+                let bytes = self.slice(offset + 1, 1usize << (marker & 0x0F))?;
+                let real = match bytes.len() {
+                    4 => f64::from(f32::from_bits(read_uint(bytes) as u32)),
+                    8 => f64::from_bits(read_uint(bytes)),
+                    _ => return Err(invalid("invalid real")),
+                };
+                if marker >> 4 == 0x3 {
+                    Value::Date(real)
+                } else {
+                    Value::Real(real)
+                }
+            }
+            0x4 => {
+                let (len, start) = self.length(offset, marker)?;
+                Value::Data(self.slice(start, len)?.to_vec())
+            }
+            0x5 => {
+                let (len, start) = self.length(offset, marker)?;
+                Value::String(String::from_utf8_lossy(self.slice(start, len)?).into_owned())
+            }
+            0x6 => {
+                let (len, start) = self.length(offset, marker)?;
+                let len = len
+                    .checked_mul(2)
+                    .ok_or_else(|| invalid("invalid string"))?;
+                let units: Vec<u16> = self
+                    .slice(start, len)?
+                    .chunks(2)
+                    .map(|unit| read_uint(unit) as u16)
+                    .collect();
+                Value::String(String::from_utf16_lossy(&units))
+            }
+            0x8 => {
+                let size = usize::from(marker & 0x0F) + 1;
+                Value::Uid(read_uint(self.slice(offset + 1, size.min(8))?))
+            }
+            // Sets (0xC) are read as arrays.
+            0xA | 0xC => {
+                let (len, start) = self.length(offset, marker)?;
+                let values = self
+                    .refs(start, len)?
+                    .into_iter()
+                    .map(|child| self.object(offsets, child, depth + 1))
+                    .collect::<Result<_>>()?;
+                Value::Array(values)
+            }
+            0xD => {
+                let (len, start) = self.length(offset, marker)?;
+                let keys = self.refs(start, len)?;
+                let values = self.refs(start + (len * self.ref_size) as u64, len)?;
+                let entries = keys
+                    .into_iter()
+                    .zip(values)
+                    .map(|(key, value)| {
+                        let key = match self.object(offsets, key, depth + 1)? {
+                            Value::String(key) => key,
+                            _ => return Err(invalid("dictionary key isn't a string")),
+                        };
+                        Ok((key, self.object(offsets, value, depth + 1)?))
+                    })
+                    .collect::<Result<_>>()?;
+                Value::Dictionary(entries)
+            }
+            _ => return Err(invalid("unknown object type")),
+        })
+    }
+}
+
+/// Writes a binary property list, for tests. Each object is written
+/// separately, without sharing identical ones like CoreFoundation does.
+#[cfg(test)]
+pub fn to_bytes(value: &Value) -> Vec<u8> {
+    fn write_marker(out: &mut Vec<u8>, kind: u8, len: usize) {
+        if len < 0x0F {
+            out.push(kind << 4 | len as u8);
+        } else {
+            out.push(kind << 4 | 0x0F);
+            out.push(0x13);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    fn add_object(value: &Value, objects: &mut Vec<Vec<u8>>) -> u16 {
+        let index = objects.len();
+        objects.push(Vec::new());
+        let mut out = Vec::new();
+        match value {
+            Value::Null => out.push(0x00),
+            Value::Bool(b) => out.push(if *b { 0x09 } else { 0x08 }),
+            Value::Integer(i) => {
+                out.push(0x13);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+            Value::Real(r) | Value::Date(r) => {
+                out.push(if matches!(value, Value::Date(_)) {
+                    0x33
+                } else {
+                    0x23
+                });
+                out.extend_from_slice(&r.to_bits().to_be_bytes());
+            }
+            Value::Data(data) => {
+                write_marker(&mut out, 0x4, data.len());
+                out.extend_from_slice(data);
+            }
+            Value::String(s) if s.is_ascii() => {
+                write_marker(&mut out, 0x5, s.len());
+                out.extend_from_slice(s.as_bytes());
+            }
+            Value::String(s) => {
+                let units: Vec<u16> = s.encode_utf16().collect();
+                write_marker(&mut out, 0x6, units.len());
+                for unit in units {
+                    out.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            Value::Uid(uid) => {
+                out.push(0x87);
+                out.extend_from_slice(&uid.to_be_bytes());
+            }
+            Value::Array(values) => {
+                let refs: Vec<u16> = values.iter().map(|v| add_object(v, objects)).collect();
+                write_marker(&mut out, 0xA, refs.len());
+                for r in refs {
+                    out.extend_from_slice(&r.to_be_bytes());
+                }
+            }
+            Value::Dictionary(entries) => {
+                let keys: Vec<u16> = entries
+                    .iter()
+                    .map(|(k, _)| add_object(&Value::String(k.clone()), objects))
+                    .collect();
+                let values: Vec<u16> = entries
+                    .iter()
+                    .map(|(_, v)| add_object(v, objects))
+                    .collect();
+                write_marker(&mut out, 0xD, keys.len());
+                for r in keys.into_iter().chain(values) {
+                    out.extend_from_slice(&r.to_be_bytes());
+                }
+            }
+        }
+        objects[index] = out;
+        index as u16
+    }
+
+    let mut objects = Vec::new();
+    add_object(value, &mut objects);
+    let mut out = b"bplist00".to_vec();
+    let mut offsets = Vec::new();
+    for object in &objects {
+        offsets.push(out.len() as u64);
+        out.extend_from_slice(object);
+    }
+    let offset_table_offset = out.len() as u64;
+    for offset in offsets {
+        out.extend_from_slice(&offset.to_be_bytes());
+    }
+    out.extend_from_slice(&[0; 6]);
+    out.push(8); // offset size
+    out.push(2); // object reference size
+    out.extend_from_slice(&(objects.len() as u64).to_be_bytes());
+    out.extend_from_slice(&0u64.to_be_bytes()); // top object
+    out.extend_from_slice(&offset_table_offset.to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_foundation_plist() -> Result<()> {
+        // Written by Python's `plistlib`, which uses the smallest integer
+        // sizes it can, like Foundation:
+        // `{"a": [1, "é", True, 2.5], "b": b"xy", "a long key name": -1}`
+        let bytes = [
+            0x62, 0x70, 0x6c, 0x69, 0x73, 0x74, 0x30, 0x30, 0xd3, 0x01, 0x02, 0x03, 0x04, 0x09,
+            0x0a, 0x51, 0x61, 0x51, 0x62, 0x5f, 0x10, 0x0f, 0x61, 0x20, 0x6c, 0x6f, 0x6e, 0x67,
+            0x20, 0x6b, 0x65, 0x79, 0x20, 0x6e, 0x61, 0x6d, 0x65, 0xa4, 0x05, 0x06, 0x07, 0x08,
+            0x10, 0x01, 0x61, 0x00, 0xe9, 0x09, 0x23, 0x40, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x42, 0x78, 0x79, 0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x08,
+            0x0f, 0x11, 0x13, 0x25, 0x2a, 0x2c, 0x2f, 0x30, 0x39, 0x3c, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x45,
+        ];
+        assert_eq!(
+            parse(&bytes)?,
+            Value::Dictionary(vec![
+                (
+                    "a".into(),
+                    Value::Array(vec![
+                        Value::Integer(1),
+                        Value::String("é".into()),
+                        Value::Bool(true),
+                        Value::Real(2.5),
+                    ])
+                ),
+                ("b".into(), Value::Data(b"xy".to_vec())),
+                ("a long key name".into(), Value::Integer(-1)),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let value = Value::Dictionary(vec![
+            ("Title".into(), Value::String("x".repeat(100))),
+            ("Unicode".into(), Value::String("日本語".into())),
+            ("Date".into(), Value::Date(1.5)),
+            ("Uid".into(), Value::Uid(7)),
+            ("Null".into(), Value::Null),
+            (
+                "Children".into(),
+                Value::Array((0..20).map(Value::Integer).collect()),
+            ),
+        ]);
+        let parsed = parse(&to_bytes(&value))?;
+        assert_eq!(parsed, value);
+        assert_eq!(
+            parsed.get("Title").and_then(Value::as_str),
+            Some(&*"x".repeat(100))
+        );
+        assert_eq!(
+            parsed
+                .get("Children")
+                .and_then(Value::as_array)
+                .map(<[_]>::len),
+            Some(20)
+        );
+        assert_eq!(parsed.get("Missing"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(parse(b""), Err(Error::InvalidPropertyList(_))));
+        assert!(matches!(
+            parse(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><plist></plist>"),
+            Err(Error::InvalidPropertyList(_))
+        ));
+        // Truncate a valid plist, so its trailer points past the end.
+        let bytes = to_bytes(&Value::Array(vec![Value::Integer(1)]));
+        let mut truncated = bytes[..8].to_vec();
+        truncated.extend_from_slice(&bytes[bytes.len() - 32..]);
+        assert!(matches!(
+            parse(&truncated),
+            Err(Error::InvalidPropertyList(_))
+        ));
+        // An array that contains itself.
+        let mut cyclic = b"bplist00".to_vec();
+        cyclic.extend_from_slice(&[0xa1, 0x00, 0x08]);
+        cyclic.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        cyclic.extend_from_slice(&1u64.to_be_bytes());
+        cyclic.extend_from_slice(&0u64.to_be_bytes());
+        cyclic.extend_from_slice(&10u64.to_be_bytes());
+        assert!(matches!(parse(&cyclic), Err(Error::InvalidPropertyList(_))));
+    }
+}
@@ -9,6 +9,7 @@ pub mod api;
 pub mod error;
 pub mod types;
 // Making these all pub for now while we flesh out the API.
+pub mod bookmark_files;
 pub mod bookmark_sync;
 pub mod db;
 pub mod ffi;
@@ -18,6 +19,7 @@ pub mod history_sync;
 // match_impl is pub mostly for benchmarks (which have to run as a separate pseudo-crate).
 pub mod import;
 pub mod match_impl;
+pub mod metrics;
 pub mod observation;
 pub mod storage;
 #[cfg(test)]
@@ -31,6 +33,7 @@ pub use crate::api::places_api::{get_registered_sync_engine, ConnectionType, Pla
 
 pub use crate::db::PlacesDb;
 pub use crate::error::*;
+pub use crate::metrics::{set_metrics_handler, PlacesMetricsHandler, PlacesOperation};
 pub use crate::observation::*;
 pub use crate::storage::PageInfo;
 pub use crate::storage::RowId;
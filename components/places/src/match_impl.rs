@@ -304,6 +304,47 @@ pub fn find_in_string(token: &str, src: &str, only_boundary: bool) -> bool {
     false
 }
 
+/// Returns the byte length of the prefix of `source` that matches `token` ignoring case, or
+/// `None` if `source` doesn't start with `token`. Unlike `string_match`, this folds `source` a
+/// codepoint at a time, so we know where the match ends even when folding changes the length.
+fn case_fold_match_len(folded_token: &[char], source: &str) -> Option<usize> {
+    let mut matched = 0;
+    for (index, c) in source.char_indices() {
+        for folded in std::iter::once(c).default_case_fold() {
+            if folded_token.get(matched) != Some(&folded) {
+                return None;
+            }
+            matched += 1;
+        }
+        if matched == folded_token.len() {
+            return Some(index + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// Finds all the non-overlapping places `token` occurs in `src` ignoring case, returning the
+/// byte offsets of the start and end of each.
+pub fn find_all_in_string(token: &str, src: &str) -> Vec<(usize, usize)> {
+    let folded_token = token.chars().default_case_fold().collect::<Vec<_>>();
+    let mut ranges = Vec::new();
+    if folded_token.is_empty() {
+        return ranges;
+    }
+    let mut cur_offset = 0;
+    while cur_offset < src.len() {
+        let src_cur = &src[cur_offset..];
+        match case_fold_match_len(&folded_token, src_cur) {
+            Some(len) => {
+                ranges.push((cur_offset, cur_offset + len));
+                cur_offset += len;
+            }
+            None => cur_offset += src_cur.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    ranges
+}
+
 // Search functions used as function pointers by AutocompleteMatch::Invoke
 
 fn find_anywhere(token: &str, source: &str) -> bool {
@@ -518,4 +559,19 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_find_all_in_string() {
+        assert_eq!(find_all_in_string("moz", "Mozilla"), vec![(0, 3)]);
+        assert_eq!(
+            find_all_in_string("a", "banana"),
+            vec![(1, 2), (3, 4), (5, 6)]
+        );
+        assert_eq!(find_all_in_string("aa", "aaa"), vec![(0, 2)]);
+        assert_eq!(find_all_in_string("xyz", "Mozilla"), vec![]);
+        assert_eq!(find_all_in_string("", "Mozilla"), vec![]);
+        // Offsets are in bytes, and account for case folding changing the length.
+        assert_eq!(find_all_in_string("ex", "ÄEXAMPLE"), vec![(2, 4)]);
+        assert_eq!(find_all_in_string("strasse", "Die Straße"), vec![(4, 11)]);
+    }
 }
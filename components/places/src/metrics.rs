@@ -0,0 +1,127 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Telemetry for places operations.
+//!
+//! We can't depend on Glean from here, so apps register a
+//! `PlacesMetricsHandler` that records these into their own metrics. Timings
+//! are measured here, rather than by the platform wrappers, so that every
+//! platform times the same work. Calls that fail before they reach us, like
+//! those with invalid URL arguments, aren't measured, so the wrappers still
+//! count operations and their errors.
+
+use crate::error::{Error, PlacesApiError, Result};
+use error_support::GetErrorHandling;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use std::time::Instant;
+
+/// The kind of operation being measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacesOperation {
+    /// A query made through a read-only or read-write connection.
+    Read,
+    /// A change made through a read-write connection.
+    Write,
+    /// A step of a history or bookmarks sync.
+    Sync,
+}
+
+/// Implemented by the app to record places metrics.
+pub trait PlacesMetricsHandler: Send + Sync {
+    /// Records that an operation finished after `duration_ms` milliseconds.
+    /// `error` is the label of the error it failed with, like
+    /// `url_parse_failed` or `operation_interrupted`, or `None` if it
+    /// succeeded.
+    fn record_operation(&self, operation: PlacesOperation, duration_ms: u64, error: Option<String>);
+}
+
+lazy_static! {
+    static ref METRICS_HANDLER: RwLock<Option<Box<dyn PlacesMetricsHandler>>> = RwLock::new(None);
+}
+
+/// Sets the handler used to record metrics for all places connections,
+/// replacing any previous handler.
+pub fn set_metrics_handler(handler: Box<dyn PlacesMetricsHandler>) {
+    *METRICS_HANDLER.write() = Some(handler);
+}
+
+/// Runs `f`, and records how long it took and whether it failed with the
+/// metrics handler, if there is one.
+pub(crate) fn measure<T>(operation: PlacesOperation, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = Instant::now();
+    let result = f();
+    if let Some(handler) = METRICS_HANDLER.read().as_ref() {
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let error = result.as_ref().err().map(error_label);
+        handler.record_operation(operation, duration_ms, error);
+    }
+    result
+}
+
+// Returns the label for the error that the FFI will throw for `e`.
+fn error_label(e: &Error) -> String {
+    match e.get_error_handling().err {
+        PlacesApiError::UnexpectedPlacesException { .. } => "unexpected_places_exception",
+        PlacesApiError::UrlParseFailed { .. } => "url_parse_failed",
+        PlacesApiError::PlacesConnectionBusy { .. } => "places_connection_busy",
+        PlacesApiError::OperationInterrupted { .. } => "operation_interrupted",
+        PlacesApiError::UnknownBookmarkItem { .. } => "unknown_bookmark_item",
+        PlacesApiError::InvalidBookmarkOperation { .. } => "invalid_bookmark_operation",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::InvalidPlaceInfo;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    type Recorded = Arc<Mutex<Vec<(PlacesOperation, Option<String>)>>>;
+
+    struct RecordingHandler(Recorded);
+
+    impl PlacesMetricsHandler for RecordingHandler {
+        fn record_operation(
+            &self,
+            operation: PlacesOperation,
+            _duration_ms: u64,
+            error: Option<String>,
+        ) {
+            self.0.lock().push((operation, error));
+        }
+    }
+
+    #[test]
+    fn test_measure() {
+        let recorded = Recorded::default();
+        set_metrics_handler(Box::new(RecordingHandler(recorded.clone())));
+
+        assert_eq!(measure(PlacesOperation::Read, || Ok(1)).unwrap(), 1);
+        measure(PlacesOperation::Write, || -> Result<()> {
+            Err(InvalidPlaceInfo::NoSuchGuid("bookmarkAAAA".into()).into())
+        })
+        .expect_err("should fail");
+        measure(PlacesOperation::Sync, || -> Result<()> {
+            Err(url::Url::parse("4").unwrap_err().into())
+        })
+        .expect_err("should fail");
+
+        // Other tests may be measuring operations at the same time, so only
+        // look for ours.
+        let recorded = recorded.lock();
+        for expected in [
+            (PlacesOperation::Read, None),
+            (
+                PlacesOperation::Write,
+                Some("unknown_bookmark_item".to_string()),
+            ),
+            (PlacesOperation::Sync, Some("url_parse_failed".to_string())),
+        ] {
+            assert!(recorded.contains(&expected), "{:?}", expected);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Page annotations: named, typed values that features attach to pages, like
+//! a page's reader view state or its detected language, without needing a
+//! schema change for each one.
+//!
+//! Annotations are keyed by the page's GUID, but stored against its row, so
+//! they follow the page if Sync changes its GUID, and are removed along with
+//! it. They're local to this device, and aren't synced.
+
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use crate::ffi::{AnnotationExpiration, AnnotationValue, PageAnnotation};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::Row;
+use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+impl AnnotationExpiration {
+    /// How long an annotation with this policy is kept after it was last set,
+    /// or `None` if it isn't removed after a fixed time.
+    fn lifetime_ms(self) -> Option<u64> {
+        match self {
+            AnnotationExpiration::Never | AnnotationExpiration::WithHistory => None,
+            AnnotationExpiration::Days => Some(7 * DAY_MS),
+            AnnotationExpiration::Weeks => Some(30 * DAY_MS),
+            AnnotationExpiration::Months => Some(180 * DAY_MS),
+        }
+    }
+}
+
+impl FromSql for AnnotationExpiration {
+    #[inline]
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(match value.as_i64()? {
+            0 => AnnotationExpiration::Never,
+            // `create_shared_triggers.sql` relies on this being 1.
+            1 => AnnotationExpiration::WithHistory,
+            2 => AnnotationExpiration::Days,
+            3 => AnnotationExpiration::Weeks,
+            4 => AnnotationExpiration::Months,
+            other => return Err(FromSqlError::OutOfRange(other)),
+        })
+    }
+}
+
+impl ToSql for AnnotationExpiration {
+    #[inline]
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as u32))
+    }
+}
+
+// The `type` stored with each annotation's content.
+const TYPE_INTEGER: u8 = 1;
+const TYPE_REAL: u8 = 2;
+const TYPE_TEXT: u8 = 3;
+const TYPE_BOOL: u8 = 4;
+
+impl AnnotationValue {
+    fn type_and_content(&self) -> (u8, &dyn ToSql) {
+        match self {
+            AnnotationValue::Integer { value } => (TYPE_INTEGER, value),
+            AnnotationValue::Real { value } => (TYPE_REAL, value),
+            AnnotationValue::Text { value } => (TYPE_TEXT, value),
+            AnnotationValue::Bool { value } => (TYPE_BOOL, value),
+        }
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(match row.get::<_, u8>("type")? {
+            TYPE_INTEGER => AnnotationValue::Integer {
+                value: row.get("content")?,
+            },
+            TYPE_REAL => AnnotationValue::Real {
+                value: row.get("content")?,
+            },
+            TYPE_TEXT => AnnotationValue::Text {
+                value: row.get("content")?,
+            },
+            TYPE_BOOL => AnnotationValue::Bool {
+                value: row.get("content")?,
+            },
+            other => {
+                return Err(rusqlite::Error::IntegralValueOutOfRange(
+                    0,
+                    i64::from(other),
+                ))
+            }
+        })
+    }
+}
+
+impl PageAnnotation {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            name: row.get("name")?,
+            value: AnnotationValue::from_row(row)?,
+            expiration: row.get("expiration")?,
+            date_added: row.get("date_added")?,
+            last_modified: row.get("last_modified")?,
+        })
+    }
+}
+
+/// Returns the GUID of the page with the specified URL, or `None` if the page
+/// isn't in history or bookmarked.
+pub fn get_page_guid(db: &PlacesDb, page_url: &Url) -> Result<Option<SyncGuid>> {
+    Ok(db.try_query_row(
+        "SELECT guid FROM moz_places
+         WHERE url_hash = hash(:page_url) AND url = :page_url",
+        &[(":page_url", &page_url.as_str())],
+        |row| row.get::<_, SyncGuid>(0),
+        true,
+    )?)
+}
+
+/// Sets an annotation on a page, replacing any value it had with the same
+/// name. Setting an annotation again also resets its expiration.
+///
+/// # Arguments
+///
+/// * `db` - A database connection on which to operate.
+///
+/// * `page_guid` - The GUID of the page to annotate.
+///
+/// * `name` - The name of the annotation. Features should namespace their
+///   names, like `reader/scrollPosition`, so they don't clash.
+///
+/// * `value` - The value of the annotation.
+///
+/// * `expiration` - When the annotation should be removed.
+///
+/// # Returns
+///
+/// There is no success return value. Fails with `InvalidPlaceInfo::NoSuchGuid`
+/// if there's no page with the GUID.
+pub fn set_page_annotation(
+    db: &PlacesDb,
+    page_guid: &SyncGuid,
+    name: &str,
+    value: &AnnotationValue,
+    expiration: AnnotationExpiration,
+) -> Result<()> {
+    let (type_, content) = value.type_and_content();
+    let changed = db.execute_cached(
+        "INSERT INTO moz_annos(place_id, name, type, content, expiration,
+                               date_added, last_modified)
+         SELECT id, :name, :type, :content, :expiration, :now, :now
+         FROM moz_places
+         WHERE guid = :page_guid
+         ON CONFLICT(place_id, name) DO UPDATE SET
+           type = excluded.type,
+           content = excluded.content,
+           expiration = excluded.expiration,
+           last_modified = excluded.last_modified",
+        &[
+            (":page_guid", page_guid as &dyn ToSql),
+            (":name", &name),
+            (":type", &type_),
+            (":content", content),
+            (":expiration", &expiration),
+            (":now", &Timestamp::now()),
+        ],
+    )?;
+    if changed == 0 {
+        return Err(InvalidPlaceInfo::NoSuchGuid(page_guid.to_string()).into());
+    }
+    Ok(())
+}
+
+/// Returns the value of a page's annotation, or `None` if the page doesn't
+/// have one with the name.
+pub fn get_page_annotation(
+    db: &PlacesDb,
+    page_guid: &SyncGuid,
+    name: &str,
+) -> Result<Option<AnnotationValue>> {
+    Ok(db.try_query_row(
+        "SELECT a.type, a.content
+         FROM moz_annos a
+         JOIN moz_places h ON h.id = a.place_id
+         WHERE h.guid = :page_guid AND a.name = :name",
+        &[(":page_guid", page_guid as &dyn ToSql), (":name", &name)],
+        AnnotationValue::from_row,
+        true,
+    )?)
+}
+
+/// Returns all of a page's annotations, ordered by name.
+pub fn get_page_annotations(db: &PlacesDb, page_guid: &SyncGuid) -> Result<Vec<PageAnnotation>> {
+    Ok(db.query_rows_and_then_cached(
+        "SELECT a.name, a.type, a.content, a.expiration,
+                a.date_added, a.last_modified
+         FROM moz_annos a
+         JOIN moz_places h ON h.id = a.place_id
+         WHERE h.guid = :page_guid
+         ORDER BY a.name",
+        &[(":page_guid", page_guid)],
+        PageAnnotation::from_row,
+    )?)
+}
+
+/// Returns the GUIDs of all the pages with an annotation with the specified
+/// name, most recently set first.
+pub fn get_pages_with_annotation(db: &PlacesDb, name: &str) -> Result<Vec<SyncGuid>> {
+    Ok(db.query_rows_and_then_cached(
+        "SELECT h.guid
+         FROM moz_annos a
+         JOIN moz_places h ON h.id = a.place_id
+         WHERE a.name = :name
+         ORDER BY a.last_modified DESC",
+        &[(":name", &name)],
+        |row| row.get::<_, SyncGuid>(0),
+    )?)
+}
+
+/// Removes a page's annotation. Does nothing if the page doesn't have one with
+/// the name.
+pub fn remove_page_annotation(db: &PlacesDb, page_guid: &SyncGuid, name: &str) -> Result<()> {
+    db.execute_cached(
+        "DELETE FROM moz_annos
+         WHERE name = :name AND
+               place_id = (SELECT id FROM moz_places WHERE guid = :page_guid)",
+        &[(":page_guid", page_guid as &dyn ToSql), (":name", &name)],
+    )?;
+    Ok(())
+}
+
+/// Removes the annotations that have outlived their expiration policy as of
+/// `now`, returning how many were removed. This is one of the steps of
+/// `run_maintenance`; annotations that expire with history are removed by a
+/// trigger instead.
+pub fn expire_annotations(db: &PlacesDb, now: Timestamp) -> Result<usize> {
+    let mut removed = 0;
+    for expiration in [
+        AnnotationExpiration::Days,
+        AnnotationExpiration::Weeks,
+        AnnotationExpiration::Months,
+    ] {
+        let lifetime_ms = expiration.lifetime_ms().expect("should have a lifetime");
+        let cutoff = Timestamp(now.as_millis().saturating_sub(lifetime_ms));
+        removed += db.execute_cached(
+            "DELETE FROM moz_annos
+             WHERE expiration = :expiration AND last_modified < :cutoff",
+            &[
+                (":expiration", &expiration as &dyn ToSql),
+                (":cutoff", &cutoff),
+            ],
+        )?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{
+        insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark,
+    };
+    use crate::storage::history::{apply_observation, delete_visits_for};
+    use crate::types::VisitType;
+
+    fn visit(db: &PlacesDb, url: &str) -> SyncGuid {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            db,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )
+        .expect("should apply visit");
+        get_page_guid(db, &url)
+            .expect("should fetch guid")
+            .expect("should have a page")
+    }
+
+    fn text(value: &str) -> AnnotationValue {
+        AnnotationValue::Text {
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_annotation_values() -> Result<()> {
+        let conn = new_mem_connection();
+        let guid = visit(&conn, "https://www.example.com/page");
+
+        assert_eq!(get_page_annotation(&conn, &guid, "lang")?, None);
+        assert_eq!(get_page_annotations(&conn, &guid)?, vec![]);
+
+        let values = [
+            ("lang", text("de")),
+            ("reader/scroll", AnnotationValue::Real { value: 0.25 }),
+            ("reader/visits", AnnotationValue::Integer { value: 1 << 40 }),
+            ("saved", AnnotationValue::Bool { value: true }),
+        ];
+        for (name, value) in &values {
+            set_page_annotation(&conn, &guid, name, value, AnnotationExpiration::Never)?;
+        }
+        for (name, value) in &values {
+            assert_eq!(
+                get_page_annotation(&conn, &guid, name)?.as_ref(),
+                Some(value)
+            );
+        }
+        let annotations = get_page_annotations(&conn, &guid)?;
+        assert_eq!(
+            annotations
+                .into_iter()
+                .map(|anno| (anno.name, anno.value))
+                .collect::<Vec<_>>(),
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<Vec<_>>(),
+        );
+
+        // Setting an annotation again replaces its value, even with a
+        // different type.
+        set_page_annotation(
+            &conn,
+            &guid,
+            "lang",
+            &AnnotationValue::Integer { value: 7 },
+            AnnotationExpiration::Weeks,
+        )?;
+        let anno = get_page_annotations(&conn, &guid)?
+            .into_iter()
+            .find(|anno| anno.name == "lang")
+            .expect("should have the annotation");
+        assert_eq!(anno.value, AnnotationValue::Integer { value: 7 });
+        assert_eq!(anno.expiration, AnnotationExpiration::Weeks);
+        assert!(anno.last_modified >= anno.date_added);
+
+        remove_page_annotation(&conn, &guid, "lang")?;
+        assert_eq!(get_page_annotation(&conn, &guid, "lang")?, None);
+        assert_eq!(get_page_annotations(&conn, &guid)?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotation_unknown_page() -> Result<()> {
+        let conn = new_mem_connection();
+        let guid = SyncGuid::random();
+        let err = set_page_annotation(
+            &conn,
+            &guid,
+            "lang",
+            &text("de"),
+            AnnotationExpiration::Never,
+        )
+        .expect_err("should fail for an unknown page");
+        assert!(
+            matches!(
+                err,
+                crate::Error::InvalidPlaceInfo(InvalidPlaceInfo::NoSuchGuid(_))
+            ),
+            "{:?}",
+            err
+        );
+        assert_eq!(get_page_annotation(&conn, &guid, "lang")?, None);
+        remove_page_annotation(&conn, &guid, "lang")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pages_with_annotation() -> Result<()> {
+        let conn = new_mem_connection();
+        let guid1 = visit(&conn, "https://www.example.com/1");
+        let guid2 = visit(&conn, "https://www.example.com/2");
+        visit(&conn, "https://www.example.com/3");
+
+        set_page_annotation(
+            &conn,
+            &guid1,
+            "saved",
+            &text("a"),
+            AnnotationExpiration::Never,
+        )?;
+        set_page_annotation(
+            &conn,
+            &guid2,
+            "saved",
+            &text("b"),
+            AnnotationExpiration::Never,
+        )?;
+        set_page_annotation(
+            &conn,
+            &guid2,
+            "other",
+            &text("c"),
+            AnnotationExpiration::Never,
+        )?;
+        let mut pages = get_pages_with_annotation(&conn, "saved")?;
+        pages.sort();
+        let mut expected = vec![guid1, guid2];
+        expected.sort();
+        assert_eq!(pages, expected);
+        assert_eq!(get_pages_with_annotation(&conn, "unknown")?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_annotations() -> Result<()> {
+        let conn = new_mem_connection();
+        let guid = visit(&conn, "https://www.example.com/page");
+        for (name, expiration) in [
+            ("never", AnnotationExpiration::Never),
+            ("history", AnnotationExpiration::WithHistory),
+            ("days", AnnotationExpiration::Days),
+            ("weeks", AnnotationExpiration::Weeks),
+            ("months", AnnotationExpiration::Months),
+        ] {
+            set_page_annotation(&conn, &guid, name, &text(name), expiration)?;
+        }
+        let names = || -> Result<Vec<String>> {
+            Ok(get_page_annotations(&conn, &guid)?
+                .into_iter()
+                .map(|anno| anno.name)
+                .collect())
+        };
+
+        let now = Timestamp::now();
+        assert_eq!(expire_annotations(&conn, now)?, 0);
+        let in_days = |days: u64| Timestamp(now.as_millis() + days * DAY_MS);
+        assert_eq!(expire_annotations(&conn, in_days(8))?, 1);
+        assert_eq!(names()?, ["history", "months", "never", "weeks"]);
+        assert_eq!(expire_annotations(&conn, in_days(31))?, 1);
+        assert_eq!(expire_annotations(&conn, in_days(181))?, 1);
+        assert_eq!(names()?, ["history", "never"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotations_removed_with_history() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://www.example.com/bookmarked")?;
+        let bookmarked_guid = visit(&conn, url.as_str());
+        insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: url.clone(),
+                title: None,
+            }
+            .into(),
+        )?;
+        let unbookmarked_url = Url::parse("https://www.example.com/unbookmarked")?;
+        let unbookmarked_guid = visit(&conn, unbookmarked_url.as_str());
+        for guid in [&bookmarked_guid, &unbookmarked_guid] {
+            set_page_annotation(
+                &conn,
+                guid,
+                "never",
+                &text("a"),
+                AnnotationExpiration::Never,
+            )?;
+            set_page_annotation(
+                &conn,
+                guid,
+                "history",
+                &text("b"),
+                AnnotationExpiration::WithHistory,
+            )?;
+        }
+
+        // The bookmarked page is kept without visits, with only the
+        // annotation that doesn't expire with history...
+        delete_visits_for(&conn, &bookmarked_guid)?;
+        assert_eq!(get_page_guid(&conn, &url)?, Some(bookmarked_guid.clone()));
+        assert_eq!(
+            get_page_annotations(&conn, &bookmarked_guid)?
+                .into_iter()
+                .map(|anno| anno.name)
+                .collect::<Vec<_>>(),
+            ["never"]
+        );
+
+        // ...And the other page is removed along with all its annotations.
+        delete_visits_for(&conn, &unbookmarked_guid)?;
+        assert_eq!(get_page_guid(&conn, &unbookmarked_url)?, None);
+        assert_eq!(conn.query_one::<i64>("SELECT COUNT(*) FROM moz_annos")?, 1);
+        Ok(())
+    }
+}
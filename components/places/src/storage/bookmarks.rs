@@ -16,6 +16,7 @@ use rusqlite::{self, Connection, Row};
 use serde_json::{self, json};
 use sql_support::{self, repeat_sql_vars, ConnExt};
 use std::cmp::{max, min};
+use std::collections::HashMap;
 use sync15::engine::EngineSyncAssociation;
 use sync_guid::Guid as SyncGuid;
 use types::Timestamp;
@@ -26,6 +27,7 @@ pub use root_guid::{BookmarkRootGuid, USER_CONTENT_ROOTS};
 mod conversions;
 pub mod fetch;
 pub mod json_tree;
+pub mod keywords;
 mod root_guid;
 
 fn create_root(
@@ -449,6 +451,7 @@ fn delete_bookmark_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
         "DELETE from moz_bookmarks WHERE id = :id",
         &[(":id", &record.row_id)],
     )?;
+    keywords::remove_orphan_keywords(db)?;
     super::delete_pending_temp_tables(db)?;
     Ok(true)
 }
@@ -738,6 +741,86 @@ fn update_bookmark_in_tx(
     Ok(())
 }
 
+/// Reorders the children of a folder in a single transaction, instead of
+/// moving them one at a time with `update_bookmark`. Children are moved to the
+/// positions of their GUIDs in `ordered_child_guids`. Like desktop, GUIDs that
+/// aren't children of the folder are ignored, and children that aren't listed
+/// keep their order, after the listed ones.
+///
+/// Only the folder's sync change counter is bumped, because the order of its
+/// children is part of the folder's record.
+pub fn set_bookmark_children_order(
+    db: &PlacesDb,
+    parent_guid: &SyncGuid,
+    ordered_child_guids: &[SyncGuid],
+) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    set_bookmark_children_order_in_tx(db, parent_guid, ordered_child_guids)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn set_bookmark_children_order_in_tx(
+    db: &PlacesDb,
+    parent_guid: &SyncGuid,
+    ordered_child_guids: &[SyncGuid],
+) -> Result<()> {
+    if parent_guid == BookmarkRootGuid::Root {
+        return Err(InvalidPlaceInfo::CannotUpdateRoot(BookmarkRootGuid::Root).into());
+    }
+    let parent = get_raw_bookmark(db, parent_guid)?
+        .ok_or_else(|| InvalidPlaceInfo::NoSuchGuid(parent_guid.to_string()))?;
+    if parent.bookmark_type != BookmarkType::Folder {
+        return Err(InvalidPlaceInfo::InvalidParent(parent_guid.to_string()).into());
+    }
+    let children = db.query_rows_and_then_cached(
+        "SELECT id, guid, position FROM moz_bookmarks
+         WHERE parent = :parent
+         ORDER BY position",
+        &[(":parent", &parent.row_id)],
+        |row| -> rusqlite::Result<(RowId, SyncGuid, u32)> {
+            Ok((
+                row.get("id")?,
+                row.get::<_, String>("guid")?.into(),
+                row.get("position")?,
+            ))
+        },
+    )?;
+    // If a GUID is listed more than once, its first position wins.
+    let mut requested = HashMap::with_capacity(ordered_child_guids.len());
+    for (index, guid) in ordered_child_guids.iter().enumerate() {
+        requested.entry(guid).or_insert(index);
+    }
+    let mut reordered = children.iter().collect::<Vec<_>>();
+    // The sort is stable, so unlisted children keep their order.
+    reordered.sort_by_key(|(_, guid, _)| requested.get(guid).copied().unwrap_or(usize::MAX));
+
+    let mut changed = false;
+    for (position, (id, _, old_position)) in reordered.into_iter().enumerate() {
+        let position = position as u32;
+        if position == *old_position {
+            continue;
+        }
+        db.execute_cached(
+            "UPDATE moz_bookmarks SET position = :position WHERE id = :id",
+            &[
+                (":position", &position as &dyn rusqlite::ToSql),
+                (":id", id),
+            ],
+        )?;
+        changed = true;
+    }
+    if changed {
+        set_ancestors_last_modified(db, parent.row_id, Timestamp::now())?;
+        db.execute_cached(
+            "UPDATE moz_bookmarks SET syncChangeCounter = syncChangeCounter + 1
+             WHERE id = :parent_id",
+            &[(":parent_id", &parent.row_id)],
+        )?;
+    }
+    Ok(())
+}
+
 fn set_ancestors_last_modified(db: &PlacesDb, parent_id: RowId, time: Timestamp) -> Result<()> {
     let sql = "
         WITH RECURSIVE
@@ -762,8 +845,10 @@ fn set_ancestors_last_modified(db: &PlacesDb, parent_id: RowId, time: Timestamp)
     Ok(())
 }
 
-/// Get the URL of the bookmark matching a keyword
+/// Get the URL of the bookmark matching a keyword. Like desktop, keywords are
+/// matched case-insensitively.
 pub fn bookmarks_get_url_for_keyword(db: &PlacesDb, keyword: &str) -> Result<Option<Url>> {
+    let keyword = keyword.trim().to_lowercase();
     let bookmark_url = db.try_query_row(
         "SELECT h.url FROM moz_keywords k
          JOIN moz_places h ON h.id = k.place_id
@@ -824,6 +909,7 @@ pub fn delete_everything(db: &PlacesDb) -> Result<()> {
         BookmarkRootGuid::Toolbar.as_str(),
         BookmarkRootGuid::Unfiled.as_str(),
     ))?;
+    keywords::remove_orphan_keywords(db)?;
     reset_in_tx(db, &EngineSyncAssociation::Disconnected)?;
     tx.commit()?;
     Ok(())
@@ -1401,6 +1487,100 @@ mod tests {
         ]));
     }
 
+    #[test]
+    fn test_set_bookmark_children_order() -> Result<()> {
+        let conn = new_mem_connection();
+        let unfiled = &BookmarkRootGuid::Unfiled.as_guid();
+
+        insert_json_tree(
+            &conn,
+            json!({
+                "guid": unfiled,
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/"
+                    },
+                    {
+                        "guid": "bookmark2___",
+                        "url": "https://www.example2.com/"
+                    },
+                    {
+                        "guid": "bookmark3___",
+                        "url": "https://www.example3.com/"
+                    },
+                    {
+                        "guid": "bookmark4___",
+                        "url": "https://www.example4.com/"
+                    },
+                ]
+            }),
+        );
+        let change_counter = |guid: &str| {
+            get_raw_bookmark(&conn, &guid.into())
+                .expect("should work")
+                .expect("should exist")
+                ._sync_change_counter
+        };
+        let unfiled_counter = change_counter(unfiled.as_str());
+        let bookmark_counter = change_counter("bookmark3___");
+
+        // Unknown GUIDs are ignored, and unlisted children go at the end, in
+        // their existing order.
+        let global_change_tracker = conn.global_bookmark_change_tracker();
+        set_bookmark_children_order(
+            &conn,
+            unfiled,
+            &[
+                "bookmark3___".into(),
+                "unknown_____".into(),
+                "bookmark1___".into(),
+                "bookmark3___".into(),
+            ],
+        )?;
+        assert!(global_change_tracker.changed(), "should be tracked");
+        assert_json_tree(
+            &conn,
+            unfiled,
+            json!({
+                "guid": unfiled,
+                "children": [
+                    {"url": "https://www.example3.com/"},
+                    {"url": "https://www.example1.com/"},
+                    {"url": "https://www.example2.com/"},
+                    {"url": "https://www.example4.com/"},
+                ]
+            }),
+        );
+        // Only the folder is changed.
+        assert_eq!(change_counter(unfiled.as_str()), unfiled_counter + 1);
+        assert_eq!(change_counter("bookmark3___"), bookmark_counter);
+
+        // Setting the existing order doesn't change anything.
+        set_bookmark_children_order(
+            &conn,
+            unfiled,
+            &["bookmark3___".into(), "bookmark1___".into()],
+        )?;
+        assert_eq!(change_counter(unfiled.as_str()), unfiled_counter + 1);
+
+        assert!(matches!(
+            set_bookmark_children_order(&conn, &"unknown_____".into(), &[]),
+            Err(Error::InvalidPlaceInfo(InvalidPlaceInfo::NoSuchGuid(_)))
+        ));
+        assert!(matches!(
+            set_bookmark_children_order(&conn, &"bookmark1___".into(), &[]),
+            Err(Error::InvalidPlaceInfo(InvalidPlaceInfo::InvalidParent(_)))
+        ));
+        assert!(matches!(
+            set_bookmark_children_order(&conn, &BookmarkRootGuid::Root.as_guid(), &[]),
+            Err(Error::InvalidPlaceInfo(InvalidPlaceInfo::CannotUpdateRoot(
+                _
+            )))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_update() -> Result<()> {
         let conn = new_mem_connection();
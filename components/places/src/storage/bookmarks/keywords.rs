@@ -0,0 +1,361 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Bookmark keywords. Like desktop, a keyword belongs to a bookmarked URL,
+//! rather than to a bookmark, and each URL has at most one keyword. Sync
+//! associates keywords with bookmarks, so changing a keyword flags all the
+//! bookmarks for the affected URLs for upload.
+
+use super::fetch::{fetch_bookmarks_by_url, BookmarkData};
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use crate::storage::{fetch_page_info, RowId};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sql_support::ConnExt;
+use std::collections::HashMap;
+use url::Url;
+
+/// The characters escaped by JavaScript's `encodeURIComponent`, which desktop
+/// uses to substitute search terms into keyword URLs.
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// Normalizes a keyword the same way as desktop, by trimming whitespace and
+/// lowercasing it. Keywords can't be empty, or contain whitespace, because
+/// the first word of the input is the keyword, and the rest is the search
+/// terms.
+pub fn validate_keyword(keyword: &str) -> Result<String> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+        return Err(InvalidPlaceInfo::InvalidKeyword.into());
+    }
+    Ok(keyword.to_lowercase())
+}
+
+/// Flags all bookmarks for the pages with the specified keyword, or for the
+/// specified page, for upload.
+fn bump_change_counters(db: &PlacesDb, keyword: &str, place_id: RowId) -> Result<()> {
+    db.execute_cached(
+        "UPDATE moz_bookmarks SET
+           syncChangeCounter = syncChangeCounter + 1
+         WHERE fk = :place_id
+            OR fk = (SELECT place_id FROM moz_keywords
+                     WHERE keyword = :keyword)",
+        &[
+            (":place_id", &place_id as &dyn rusqlite::ToSql),
+            (":keyword", &keyword),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sets the keyword for a bookmarked URL, replacing its existing keyword. If
+/// another URL already has the keyword, it's removed from that URL.
+///
+/// # Returns
+///
+/// There is no success return value. Returns an `InvalidKeyword` error if the
+/// keyword is empty or contains whitespace, and a `NoSuchUrl` error if the URL
+/// isn't bookmarked.
+pub fn set_keyword_for_url(db: &PlacesDb, url: &Url, keyword: &str) -> Result<()> {
+    let keyword = validate_keyword(keyword)?;
+    let tx = db.begin_transaction()?;
+
+    let place_id = match fetch_page_info(db, url)? {
+        Some(info) => info.page.row_id,
+        None => return Err(InvalidPlaceInfo::NoSuchUrl.into()),
+    };
+    let is_bookmarked = db.exists(
+        "SELECT 1 FROM moz_bookmarks WHERE fk = :place_id",
+        &[(":place_id", &place_id)],
+    )?;
+    if !is_bookmarked {
+        return Err(InvalidPlaceInfo::NoSuchUrl.into());
+    }
+
+    // Flag the bookmarks for the URL that had the keyword before removing it,
+    // as well as the bookmarks for this URL.
+    bump_change_counters(db, &keyword, place_id)?;
+    db.execute_cached(
+        "DELETE FROM moz_keywords
+         WHERE place_id = :place_id OR keyword = :keyword",
+        &[
+            (":place_id", &place_id as &dyn rusqlite::ToSql),
+            (":keyword", &keyword),
+        ],
+    )?;
+    db.execute_cached(
+        "INSERT INTO moz_keywords(keyword, place_id)
+         VALUES(:keyword, :place_id)",
+        &[
+            (":keyword", &keyword as &dyn rusqlite::ToSql),
+            (":place_id", &place_id),
+        ],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Removes a keyword.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if no URL has
+/// the keyword.
+pub fn remove_keyword(db: &PlacesDb, keyword: &str) -> Result<()> {
+    let keyword = validate_keyword(keyword)?;
+    let tx = db.begin_transaction()?;
+    db.execute_cached(
+        "UPDATE moz_bookmarks SET
+           syncChangeCounter = syncChangeCounter + 1
+         WHERE fk = (SELECT place_id FROM moz_keywords
+                     WHERE keyword = :keyword)",
+        &[(":keyword", &keyword)],
+    )?;
+    db.execute_cached(
+        "DELETE FROM moz_keywords WHERE keyword = :keyword",
+        &[(":keyword", &keyword)],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Returns the keyword for a URL, if it has one.
+pub fn get_keyword_for_url(db: &PlacesDb, url: &Url) -> Result<Option<String>> {
+    Ok(db.try_query_one(
+        "SELECT k.keyword FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id
+         WHERE h.url_hash = hash(:url) AND h.url = :url",
+        &[(":url", &url.as_str())],
+        true,
+    )?)
+}
+
+/// Returns every keyword, keyed by the URL it belongs to.
+pub(crate) fn get_keywords_by_url(db: &PlacesDb) -> Result<HashMap<String, String>> {
+    db.query_rows_into(
+        "SELECT h.url, k.keyword FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id",
+        [],
+        |row| -> Result<_> { Ok((row.get("url")?, row.get("keyword")?)) },
+    )
+}
+
+/// Returns the bookmarks for the URL with the specified keyword. Invalid
+/// keywords don't match any bookmarks.
+pub fn fetch_bookmarks_by_keyword(db: &PlacesDb, keyword: &str) -> Result<Vec<BookmarkData>> {
+    let keyword = match validate_keyword(keyword) {
+        Ok(keyword) => keyword,
+        Err(_) => return Ok(Vec::new()),
+    };
+    match super::bookmarks_get_url_for_keyword(db, &keyword)? {
+        Some(url) => fetch_bookmarks_by_url(db, &url),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Substitutes `param` into a keyword URL, like desktop: `%s` is replaced
+/// with the escaped param, and `%S` with the param as-is.
+///
+/// Returns `None` if there are search terms, but the URL doesn't have a
+/// placeholder for them, since the input then isn't a keyword search.
+pub fn substitute_keyword_param(url: &str, param: &str) -> Option<String> {
+    let has_placeholder = url.contains("%s") || url.contains("%S");
+    if !has_placeholder {
+        return if param.is_empty() {
+            Some(url.to_owned())
+        } else {
+            None
+        };
+    }
+    let encoded_param = utf8_percent_encode(param, URI_COMPONENT).to_string();
+    Some(url.replace("%s", &encoded_param).replace("%S", param))
+}
+
+/// Splits input like "wiki firefox" into the keyword and the search terms.
+pub(crate) fn split_keyword_input(input: &str) -> (&str, &str) {
+    let input = input.trim();
+    match input.split_once(' ') {
+        Some((keyword, param)) => (keyword, param),
+        None => (input, ""),
+    }
+}
+
+/// Returns the URL to load for URL bar input that starts with a keyword,
+/// like "wiki firefox" for a bookmark with the keyword "wiki" and the URL
+/// `https://en.wikipedia.org/wiki/%s`. Returns `None` if the input doesn't
+/// start with a keyword, or has search terms that can't be substituted.
+pub fn get_keyword_search_url(db: &PlacesDb, input: &str) -> Result<Option<Url>> {
+    let (keyword, param) = split_keyword_input(input);
+    let keyword = match validate_keyword(keyword) {
+        Ok(keyword) => keyword,
+        Err(_) => return Ok(None),
+    };
+    let url = match super::bookmarks_get_url_for_keyword(db, &keyword)? {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    Ok(match substitute_keyword_param(url.as_str(), param) {
+        Some(href) => Some(Url::parse(&href)?),
+        None => None,
+    })
+}
+
+/// Removes keywords for URLs that aren't bookmarked anymore.
+pub(crate) fn remove_orphan_keywords(db: &PlacesDb) -> Result<()> {
+    db.execute_cached(
+        "DELETE FROM moz_keywords
+         WHERE NOT EXISTS(SELECT 1 FROM moz_bookmarks
+                          WHERE fk = moz_keywords.place_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::bookmarks::{
+        delete_bookmark, insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark,
+    };
+    use sync_guid::Guid as SyncGuid;
+
+    fn bookmark(db: &PlacesDb, url: &str) -> SyncGuid {
+        insert_bookmark(
+            db,
+            InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: Url::parse(url).unwrap(),
+                title: None,
+            }
+            .into(),
+        )
+        .expect("should insert bookmark")
+    }
+
+    fn change_counter(db: &PlacesDb, guid: &SyncGuid) -> u32 {
+        db.try_query_one(
+            "SELECT syncChangeCounter FROM moz_bookmarks WHERE guid = :guid",
+            &[(":guid", guid)],
+            false,
+        )
+        .unwrap()
+        .expect("bookmark should exist")
+    }
+
+    #[test]
+    fn test_validate_keyword() {
+        assert_eq!(validate_keyword(" Wiki ").unwrap(), "wiki");
+        assert!(validate_keyword("").is_err());
+        assert!(validate_keyword("two words").is_err());
+    }
+
+    #[test]
+    fn test_set_keyword() -> Result<()> {
+        let conn = new_mem_connection();
+        let wiki_url = Url::parse("https://en.wikipedia.org/wiki/%s")?;
+        let wiki_guid = bookmark(&conn, wiki_url.as_str());
+        let other_url = Url::parse("https://example.com/")?;
+        let other_guid = bookmark(&conn, other_url.as_str());
+
+        set_keyword_for_url(&conn, &wiki_url, "Wiki")?;
+        assert_eq!(get_keyword_for_url(&conn, &wiki_url)?, Some("wiki".into()));
+        assert_eq!(
+            fetch_bookmarks_by_keyword(&conn, "WIKI")?
+                .into_iter()
+                .map(|b| b.guid)
+                .collect::<Vec<_>>(),
+            vec![wiki_guid.clone()]
+        );
+
+        // Moving the keyword to another URL removes it from the first one,
+        // and flags both for upload.
+        let wiki_counter = change_counter(&conn, &wiki_guid);
+        let other_counter = change_counter(&conn, &other_guid);
+        set_keyword_for_url(&conn, &other_url, "wiki")?;
+        assert_eq!(get_keyword_for_url(&conn, &wiki_url)?, None);
+        assert_eq!(get_keyword_for_url(&conn, &other_url)?, Some("wiki".into()));
+        assert_eq!(change_counter(&conn, &wiki_guid), wiki_counter + 1);
+        assert_eq!(change_counter(&conn, &other_guid), other_counter + 1);
+
+        // A URL only has one keyword.
+        set_keyword_for_url(&conn, &other_url, "ex")?;
+        assert!(fetch_bookmarks_by_keyword(&conn, "wiki")?.is_empty());
+
+        remove_keyword(&conn, "ex")?;
+        assert_eq!(get_keyword_for_url(&conn, &other_url)?, None);
+
+        // Unbookmarked URLs can't have keywords.
+        let unknown_url = Url::parse("https://example.org/")?;
+        assert!(set_keyword_for_url(&conn, &unknown_url, "org").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyword_removed_with_bookmark() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://example.com/")?;
+        let guid = bookmark(&conn, url.as_str());
+        let other_guid = bookmark(&conn, url.as_str());
+        set_keyword_for_url(&conn, &url, "ex")?;
+
+        delete_bookmark(&conn, &guid)?;
+        assert_eq!(get_keyword_for_url(&conn, &url)?, Some("ex".into()));
+        delete_bookmark(&conn, &other_guid)?;
+        assert_eq!(get_keyword_for_url(&conn, &url)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_keyword_param() {
+        assert_eq!(
+            substitute_keyword_param("https://example.com/?q=%s", "a b&c/é").as_deref(),
+            Some("https://example.com/?q=a%20b%26c%2F%C3%A9")
+        );
+        assert_eq!(
+            substitute_keyword_param("https://example.com/%S", "a/b").as_deref(),
+            Some("https://example.com/a/b")
+        );
+        assert_eq!(
+            substitute_keyword_param("https://example.com/?q=%s", "").as_deref(),
+            Some("https://example.com/?q=")
+        );
+        assert_eq!(
+            substitute_keyword_param("https://example.com/", "").as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(substitute_keyword_param("https://example.com/", "a"), None);
+    }
+
+    #[test]
+    fn test_keyword_search_url() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://en.wikipedia.org/wiki/Special:Search?search=%s")?;
+        bookmark(&conn, url.as_str());
+        set_keyword_for_url(&conn, &url, "wiki")?;
+
+        assert_eq!(
+            get_keyword_search_url(&conn, " WIKI mozilla firefox")?,
+            Some(Url::parse(
+                "https://en.wikipedia.org/wiki/Special:Search?search=mozilla%20firefox"
+            )?)
+        );
+        assert_eq!(get_keyword_search_url(&conn, "wikipedia firefox")?, None);
+        assert_eq!(get_keyword_search_url(&conn, "")?, None);
+        Ok(())
+    }
+}
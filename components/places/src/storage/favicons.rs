@@ -0,0 +1,278 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::fetch_page_info;
+use crate::db::PlacesDb;
+use crate::error::Result;
+use crate::ffi::Favicon;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+impl Favicon {
+    pub(crate) fn from_row(row: &Row<'_>) -> Result<Self> {
+        let icon_url: String = row.get("icon_url")?;
+        Ok(Self {
+            icon_url: Url::parse(&icon_url)?,
+            width: row.get("width")?,
+            data: row.get("data")?,
+            expires_at: row.get("expire_ms")?,
+        })
+    }
+}
+
+/// Returns `true` if `icon_url` is the root icon of its origin, which is used
+/// for pages on that origin that don't have icons of their own.
+fn is_root_icon(icon_url: &Url) -> bool {
+    icon_url.path() == "/favicon.ico" && icon_url.query().is_none()
+}
+
+/// Sets an icon for the specified page, replacing any icons it had at other
+/// URLs. Each size of an icon is stored separately, so calling this again with
+/// the same icon URL and a different width adds that size.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `page_url` - The URL of the page that uses the icon.
+///
+/// * `favicon` - The icon. Its data may be `None` if the icon hasn't been
+///   fetched yet.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if the page
+/// isn't in history or bookmarked.
+pub fn set_favicon_for_page(db: &PlacesDb, page_url: &Url, favicon: &Favicon) -> Result<()> {
+    let tx = db.begin_transaction()?;
+
+    // Like tags, this won't create a new place.
+    let place_id = match fetch_page_info(db, page_url)? {
+        Some(info) => info.page.row_id,
+        None => {
+            log::debug!("Ignoring icon for unknown page");
+            return Ok(());
+        }
+    };
+
+    db.execute_cached(
+        "DELETE FROM moz_icons_to_pages
+         WHERE page_id = :page_id
+           AND icon_id IN (SELECT id FROM moz_icons
+                           WHERE icon_url <> :icon_url)",
+        &[
+            (":page_id", &place_id as &dyn rusqlite::ToSql),
+            (":icon_url", &favicon.icon_url.as_str()),
+        ],
+    )?;
+
+    db.execute_cached(
+        "INSERT INTO moz_icons(icon_url, width, root, expire_ms, data)
+         VALUES(:icon_url, :width, :root, :expire_ms, :data)
+         ON CONFLICT(icon_url, width) DO UPDATE SET
+           expire_ms = excluded.expire_ms,
+           data = IFNULL(excluded.data, data)",
+        &[
+            (
+                ":icon_url",
+                &favicon.icon_url.as_str() as &dyn rusqlite::ToSql,
+            ),
+            (":width", &favicon.width),
+            (":root", &is_root_icon(&favicon.icon_url)),
+            (":expire_ms", &favicon.expires_at),
+            (":data", &favicon.data),
+        ],
+    )?;
+
+    db.execute_cached(
+        "INSERT OR IGNORE INTO moz_icons_to_pages(page_id, icon_id)
+         VALUES(:page_id, (SELECT id FROM moz_icons
+                           WHERE icon_url = :icon_url AND width = :width))",
+        &[
+            (":page_id", &place_id as &dyn rusqlite::ToSql),
+            (":icon_url", &favicon.icon_url.as_str()),
+            (":width", &favicon.width),
+        ],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Returns the best icon for the specified page: the smallest one that's at
+/// least `preferred_width` wide, or the largest one if they're all smaller.
+/// If the page doesn't have any icons of its own, its origin's root icon is
+/// used instead.
+///
+/// Expired icons are still returned, so that there's something to show until
+/// the embedder fetches the icon again and sets it with
+/// `set_favicon_for_page`.
+pub fn get_favicon_for_page(
+    db: &PlacesDb,
+    page_url: &Url,
+    preferred_width: u32,
+) -> Result<Option<Favicon>> {
+    let page_icon = db.try_query_row(
+        "SELECT i.icon_url, i.width, i.data, i.expire_ms
+         FROM moz_icons i
+         JOIN moz_icons_to_pages ip ON ip.icon_id = i.id
+         JOIN moz_places h ON h.id = ip.page_id
+         WHERE h.url_hash = hash(:page_url) AND h.url = :page_url
+         ORDER BY i.width < :width,
+                  CASE WHEN i.width >= :width THEN i.width ELSE -i.width END
+         LIMIT 1",
+        &[
+            (":page_url", &page_url.as_str() as &dyn rusqlite::ToSql),
+            (":width", &preferred_width),
+        ],
+        Favicon::from_row,
+        true,
+    )?;
+    if page_icon.is_some() {
+        return Ok(page_icon);
+    }
+
+    let root_icon_url = match page_url.join("/favicon.ico") {
+        Ok(url) if page_url.has_host() => url,
+        _ => return Ok(None),
+    };
+    db.try_query_row(
+        "SELECT icon_url, width, data, expire_ms
+         FROM moz_icons
+         WHERE root AND icon_url = :icon_url
+         ORDER BY width < :width,
+                  CASE WHEN width >= :width THEN width ELSE -width END
+         LIMIT 1",
+        &[
+            (":icon_url", &root_icon_url.as_str() as &dyn rusqlite::ToSql),
+            (":width", &preferred_width),
+        ],
+        Favicon::from_row,
+        true,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{apply_observation, delete_everything};
+    use crate::types::VisitType;
+    use types::Timestamp;
+
+    fn visit(db: &PlacesDb, url: &str) -> Url {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            db,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )
+        .expect("should apply visit");
+        url
+    }
+
+    fn favicon(icon_url: &str, width: u32, data: &[u8]) -> Favicon {
+        Favicon {
+            icon_url: Url::parse(icon_url).unwrap(),
+            width,
+            data: Some(data.to_vec()),
+            expires_at: Timestamp(Timestamp::now().as_millis() + 60_000),
+        }
+    }
+
+    #[test]
+    fn test_favicon_size_selection() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/page");
+
+        assert_eq!(get_favicon_for_page(&conn, &page_url, 32)?, None);
+
+        for width in [16, 32, 64] {
+            set_favicon_for_page(
+                &conn,
+                &page_url,
+                &favicon("https://www.example.com/icon.png", width, &[width as u8]),
+            )?;
+        }
+        let width_for = |preferred_width| -> Result<Option<u32>> {
+            Ok(get_favicon_for_page(&conn, &page_url, preferred_width)?.map(|icon| icon.width))
+        };
+        assert_eq!(width_for(16)?, Some(16));
+        assert_eq!(width_for(24)?, Some(32));
+        assert_eq!(width_for(32)?, Some(32));
+        assert_eq!(width_for(128)?, Some(64));
+        assert_eq!(
+            get_favicon_for_page(&conn, &page_url, 32)?.and_then(|icon| icon.data),
+            Some(vec![32])
+        );
+
+        // Setting an icon at a new URL replaces the old one.
+        set_favicon_for_page(
+            &conn,
+            &page_url,
+            &favicon("https://www.example.com/new-icon.png", 16, &[1]),
+        )?;
+        assert_eq!(width_for(32)?, Some(16));
+        assert_eq!(
+            conn.query_one::<i64>("SELECT COUNT(*) FROM moz_icons")?,
+            1,
+            "Icons no pages use should be deleted"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_favicon_without_data() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/page");
+
+        let mut icon = favicon("https://www.example.com/icon.png", 16, &[1, 2, 3]);
+        icon.data = None;
+        set_favicon_for_page(&conn, &page_url, &icon)?;
+        let fetched = get_favicon_for_page(&conn, &page_url, 16)?.expect("should have an icon");
+        assert_eq!(fetched.data, None);
+
+        // Once it's fetched, the data is stored...
+        icon.data = Some(vec![1, 2, 3]);
+        set_favicon_for_page(&conn, &page_url, &icon)?;
+        // ...and kept when only the expiration changes.
+        icon.data = None;
+        icon.expires_at = Timestamp(0);
+        set_favicon_for_page(&conn, &page_url, &icon)?;
+        let fetched = get_favicon_for_page(&conn, &page_url, 16)?.expect("should have an icon");
+        assert_eq!(fetched.data, Some(vec![1, 2, 3]));
+        assert_eq!(fetched.expires_at, Timestamp(0));
+
+        // Icons for unknown pages are ignored.
+        let unknown_url = Url::parse("https://www.example.com/unknown")?;
+        set_favicon_for_page(&conn, &unknown_url, &icon)?;
+        assert!(fetch_page_info(&conn, &unknown_url)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_favicon() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/");
+        let other_page_url = visit(&conn, "https://www.example.com/other");
+        let other_origin_url = visit(&conn, "https://example.org/");
+
+        set_favicon_for_page(
+            &conn,
+            &page_url,
+            &favicon("https://www.example.com/favicon.ico", 16, &[1]),
+        )?;
+        assert_eq!(
+            get_favicon_for_page(&conn, &other_page_url, 16)?.map(|icon| icon.icon_url),
+            Some(Url::parse("https://www.example.com/favicon.ico")?),
+            "Pages on the same origin should use the root icon"
+        );
+        assert_eq!(get_favicon_for_page(&conn, &other_origin_url, 16)?, None);
+
+        // Removing the pages and their origin removes the root icon.
+        delete_everything(&conn)?;
+        assert_eq!(conn.query_one::<i64>("SELECT COUNT(*) FROM moz_icons")?, 0);
+        Ok(())
+    }
+}
@@ -5,9 +5,13 @@
 mod actions;
 
 use super::{fetch_page_info, new_page_info, PageInfo, RowId};
+use crate::api::matcher::{find_highlights, MatchReason};
 use crate::db::PlacesDb;
-use crate::error::Result;
-use crate::ffi::{HistoryVisitInfo, HistoryVisitInfosWithBound, TopFrecentSiteInfo};
+use crate::error::{Error, Result};
+use crate::ffi::{
+    HistoryVisitInfo, HistoryVisitInfosPage, HistoryVisitInfosWithBound, PageVisitCounts,
+    SearchResult, TopFrecentSiteInfo, TopSitesExclusions,
+};
 use crate::frecency;
 use crate::hash;
 use crate::history_sync::engine::{
@@ -18,14 +22,14 @@ use crate::storage::{
     delete_meta, delete_pending_temp_tables, get_meta, history_metadata, put_meta,
 };
 use crate::types::{
-    serialize_unknown_fields, SyncStatus, UnknownFields, VisitTransitionSet, VisitType,
+    serialize_unknown_fields, SyncStatus, UnknownFields, VisitSource, VisitTransitionSet, VisitType,
 };
 use actions::*;
 use rusqlite::types::ToSql;
 use rusqlite::Result as RusqliteResult;
 use rusqlite::Row;
 use sql_support::{self, ConnExt};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use sync15::bso::OutgoingBso;
 use sync15::engine::EngineSyncAssociation;
@@ -177,6 +181,20 @@ pub fn update_frecency(db: &PlacesDb, id: RowId, redirect_boost: Option<bool>) -
     Ok(())
 }
 
+/// Flags a page's frecency as stale, to be recalculated later by
+/// `run_maintenance_frecency`, instead of recalculating it now.
+pub fn mark_frecency_stale(db: &PlacesDb, id: RowId) -> Result<()> {
+    db.execute_cached(
+        "REPLACE INTO moz_places_stale_frecencies(place_id, stale_at)
+         VALUES(:page_id, :now)",
+        &[
+            (":page_id", &id.0 as &dyn rusqlite::ToSql),
+            (":now", &Timestamp::now()),
+        ],
+    )?;
+    Ok(())
+}
+
 /// Indicates if and when a URL's frecency was marked as stale.
 pub fn frecency_stale_at(db: &PlacesDb, url: &Url) -> Result<Option<Timestamp>> {
     let result = db.try_query_row(
@@ -378,6 +396,29 @@ pub fn delete_visits_for(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
     result
 }
 
+/// Delete all visits to pages on the specified host, along with the pages
+/// themselves unless they're bookmarked. Like `delete_visits_for`, this writes
+/// tombstones for pages and visits that have already been synced.
+///
+/// The host must match exactly, so deleting `example.com` doesn't delete
+/// visits to `www.example.com`.
+pub fn delete_visits_for_host(db: &PlacesDb, host: &str) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let guids = db.query_rows_and_then(
+        "SELECT h.guid
+         FROM moz_places h
+         JOIN moz_origins o ON o.id = h.origin_id
+         WHERE o.host = :host",
+        &[(":host", &host.to_ascii_lowercase())],
+        |row| row.get::<_, SyncGuid>(0),
+    )?;
+    for guid in &guids {
+        delete_visits_for_in_tx(db, guid)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
 /// Delete all visits in a date range.
 pub fn delete_visits_between(db: &PlacesDb, start: Timestamp, end: Timestamp) -> Result<()> {
     let tx = db.begin_transaction()?;
@@ -969,9 +1010,9 @@ pub mod history_sync {
                 visits_to_skip.insert(timestamp);
             }
         }
-        // XXX - we really need a better story for frecency-boost than
-        // Option<bool> - None vs Some(false) is confusing. We should use an enum.
-        update_frecency(db, page_info.row_id, None)?;
+        // Recalculating frecencies as we apply incoming visits slows down
+        // syncs with lots of history, so leave it to `run_maintenance_frecency`.
+        mark_frecency_stale(db, page_info.row_id)?;
 
         // and the place itself if necessary.
         let new_title = title.as_ref().unwrap_or(&page_info.title);
@@ -1098,6 +1139,9 @@ pub mod history_sync {
                      change_delta INTEGER NOT NULL)",
             [],
         )?;
+        // If an earlier sync failed to upload everything, it left its rows
+        // behind, but we're about to plan everything that's still unsynced.
+        db.execute("DELETE FROM temp_sync_updated_meta", [])?;
 
         let insert_meta_sql = "
             INSERT INTO temp_sync_updated_meta VALUES (:row_id, :change_delta)";
@@ -1189,6 +1233,37 @@ pub mod history_sync {
         Ok(result)
     }
 
+    /// Marks the pages and tombstones in a batch the server committed as synced,
+    /// so we don't upload them again if a later batch fails.
+    pub fn mark_uploaded(db: &PlacesDb, ids: &[SyncGuid]) -> Result<()> {
+        sql_support::each_chunk(ids, |chunk, _| -> Result<()> {
+            let vars = sql_support::repeat_sql_vars(chunk.len());
+            db.conn().execute(
+                &format!(
+                    "UPDATE moz_places
+                        SET sync_change_counter = sync_change_counter -
+                        (SELECT change_delta FROM temp_sync_updated_meta m WHERE moz_places.id = m.id)
+                    WHERE guid IN ({vars}) AND id IN (SELECT id FROM temp_sync_updated_meta)"
+                ),
+                rusqlite::params_from_iter(chunk),
+            )?;
+            // `finish_outgoing` subtracts the deltas of everything we uploaded
+            // again, so make sure it doesn't count these twice.
+            db.conn().execute(
+                &format!(
+                    "UPDATE temp_sync_updated_meta SET change_delta = 0
+                    WHERE id IN (SELECT id FROM moz_places WHERE guid IN ({vars}))"
+                ),
+                rusqlite::params_from_iter(chunk),
+            )?;
+            db.conn().execute(
+                &format!("DELETE FROM moz_places_tombstones WHERE guid IN ({vars})"),
+                rusqlite::params_from_iter(chunk),
+            )?;
+            Ok(())
+        })
+    }
+
     pub fn finish_outgoing(db: &PlacesDb) -> Result<()> {
         // So all items *other* than those above must be set to "not dirty"
         // (ie, status=SyncStatus::Normal, change_counter=0). Otherwise every
@@ -1366,14 +1441,141 @@ pub fn get_top_frecent_site_infos(
     Ok(infos)
 }
 
+/// Returns the most frecent origins, for showing as top sites. Origins whose
+/// hosts only differ by a leading "www." are combined into one site, using the
+/// https URL if the site has been visited over https. Sites with the same host
+/// as any of the `exclusions`, like those the app already shows as pinned, or
+/// that the user has removed, are skipped.
+pub fn get_top_frecent_sites(
+    db: &PlacesDb,
+    limit: i32,
+    exclusions: &TopSitesExclusions,
+) -> Result<Vec<TopFrecentSiteInfo>> {
+    let excluded_hosts = exclusions
+        .pinned
+        .iter()
+        .chain(exclusions.blocked.iter())
+        .filter_map(|url| url.host_str().map(|host| site_host(host).to_string()))
+        .collect::<HashSet<_>>();
+
+    // Origins are ordered so that the first one for each site is the one
+    // we'll use for its URL.
+    let origins = db.query_rows_and_then_cached(
+        "SELECT prefix, host, frecency
+         FROM moz_origins
+         WHERE prefix IN ('https://', 'http://') AND frecency > 0
+         ORDER BY prefix = 'https://' DESC, frecency DESC",
+        [],
+        |row| -> RusqliteResult<(String, String, i64)> {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        },
+    )?;
+    let mut sites: Vec<(String, i64)> = Vec::new();
+    let mut site_indices = HashMap::new();
+    for (prefix, host, frecency) in origins {
+        let site = site_host(&host);
+        if excluded_hosts.contains(site) {
+            continue;
+        }
+        match site_indices.get(site) {
+            Some(&index) => sites[index].1 += frecency,
+            None => {
+                site_indices.insert(site.to_string(), sites.len());
+                sites.push((format!("{}{}/", prefix, host), frecency));
+            }
+        }
+    }
+    sites.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    sites
+        .into_iter()
+        .take(limit.max(0) as usize)
+        .map(|(url, _)| -> Result<TopFrecentSiteInfo> {
+            let title = db.try_query_row(
+                "SELECT title FROM moz_places
+                 WHERE url_hash = hash(:url) AND url = :url",
+                &[(":url", &url)],
+                |row| row.get::<_, Option<String>>(0),
+                true,
+            )?;
+            Ok(TopFrecentSiteInfo {
+                url: Url::parse(&url)?,
+                title: title.flatten(),
+            })
+        })
+        .collect()
+}
+
+// The host used to identify a site for top sites, so that "www." and bare
+// hosts count as the same site.
+fn site_host(host: &str) -> &str {
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+/// Searches the titles and URLs of visited pages for all the words in `query`, each of which
+/// can match the start of a word, e.g. "moz fire" matches "Mozilla Firefox". Results are
+/// ranked by how well they match, with matches in titles counting for more, then by frecency.
+pub fn search_history(db: &PlacesDb, query: &str, limit: u32) -> Result<Vec<SearchResult>> {
+    let fts_query = to_fts_query(query);
+    if fts_query.is_empty() {
+        return Ok(Vec::new());
+    }
+    db.query_rows_and_then_cached(
+        "SELECT h.url, IFNULL(NULLIF(h.title, ''), h.url) AS title, h.frecency
+         FROM moz_places_fts
+         JOIN moz_places h ON h.id = moz_places_fts.rowid
+         WHERE moz_places_fts MATCH :query
+           AND NOT h.hidden
+           AND (h.last_visit_date_local + h.last_visit_date_remote) != 0
+         ORDER BY bm25(moz_places_fts, 2.0, 1.0), h.frecency DESC
+         LIMIT :limit",
+        rusqlite::named_params! {
+            ":query": fts_query,
+            ":limit": limit,
+        },
+        |row| -> Result<SearchResult> {
+            let url = Url::parse(&row.get::<_, String>("url")?)?;
+            let title = row.get::<_, String>("title")?;
+            let title_highlights = find_highlights(query, &title);
+            let url_highlights = find_highlights(query, url.as_str());
+            let mut reasons = Vec::new();
+            if !url_highlights.is_empty() {
+                reasons.push(MatchReason::Url);
+            }
+            if !title_highlights.is_empty() {
+                reasons.push(MatchReason::Title);
+            }
+            Ok(SearchResult {
+                url,
+                title,
+                frecency: row.get("frecency")?,
+                reasons,
+                title_highlights,
+                url_highlights,
+            })
+        },
+    )
+}
+
+// Quotes each word of the query, so FTS5 operators and punctuation in it are matched as
+// text, and makes each a prefix match. The words are implicitly ANDed.
+fn to_fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub fn get_visit_infos(
     db: &PlacesDb,
     start: Timestamp,
     end: Timestamp,
     exclude_types: VisitTransitionSet,
+    source: VisitSource,
 ) -> Result<Vec<HistoryVisitInfo>> {
     let allowed_types = exclude_types.complement();
-    let infos = db.query_rows_and_then_cached(
+    let sql = format!(
         "SELECT h.url, h.title, v.visit_date, v.visit_type, h.hidden, h.preview_image_url,
                 v.is_local
          FROM moz_places h
@@ -1381,8 +1583,13 @@ pub fn get_visit_infos(
            ON h.id = v.place_id
          WHERE v.visit_date BETWEEN :start AND :end
            AND ((1 << visit_type) & :allowed_types) != 0 AND
+           {source_filter} AND
            NOT h.hidden
          ORDER BY v.visit_date",
+        source_filter = source.sql_filter("v.is_local"),
+    );
+    let infos = db.query_rows_and_then_cached(
+        &sql,
         rusqlite::named_params! {
             ":start": start,
             ":end": end,
@@ -1393,15 +1600,24 @@ pub fn get_visit_infos(
     Ok(infos)
 }
 
-pub fn get_visit_count(db: &PlacesDb, exclude_types: VisitTransitionSet) -> Result<i64> {
-    let count = if exclude_types.is_empty() {
+pub fn get_visit_count(
+    db: &PlacesDb,
+    exclude_types: VisitTransitionSet,
+    source: VisitSource,
+) -> Result<i64> {
+    let count = if exclude_types.is_empty() && source == VisitSource::All {
         db.query_one::<i64>("SELECT COUNT(*) FROM moz_historyvisits")?
     } else {
         let allowed_types = exclude_types.complement();
-        db.query_row_and_then_cachable(
+        let sql = format!(
             "SELECT COUNT(*)
              FROM moz_historyvisits
-             WHERE ((1 << visit_type) & :allowed_types) != 0",
+             WHERE ((1 << visit_type) & :allowed_types) != 0 AND
+                   {source_filter}",
+            source_filter = source.sql_filter("is_local"),
+        );
+        db.query_row_and_then_cachable(
+            &sql,
             rusqlite::named_params! {
                 ":allowed_types": allowed_types,
             },
@@ -1412,6 +1628,53 @@ pub fn get_visit_count(db: &PlacesDb, exclude_types: VisitTransitionSet) -> Resu
     Ok(count)
 }
 
+impl PageVisitCounts {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        let url: String = row.get("url")?;
+        Ok(Self {
+            url: Url::parse(&url)?,
+            local: row.get("visit_count_local")?,
+            remote: row.get("visit_count_remote")?,
+            last_visit_date_local: row.get("last_visit_date_local")?,
+            last_visit_date_remote: row.get("last_visit_date_remote")?,
+        })
+    }
+}
+
+/// Returns how many times each of the specified pages was visited on this
+/// device, and on other devices, in the same order. Pages that aren't known
+/// are left out.
+pub fn get_page_visit_counts(db: &PlacesDb, page_urls: &[Url]) -> Result<Vec<PageVisitCounts>> {
+    let mut counts = Vec::with_capacity(page_urls.len());
+    sql_support::each_chunk_mapped(
+        page_urls,
+        |url| url.as_str(),
+        |chunk, offset| -> Result<()> {
+            let values_with_idx = sql_support::repeat_display(chunk.len(), ",", |i, f| {
+                write!(f, "({},?)", i + offset)
+            });
+            let sql = format!(
+                "WITH to_fetch(fetch_url_index, url) AS (VALUES {})
+                 SELECT f.fetch_url_index, h.url, h.visit_count_local, h.visit_count_remote,
+                        h.last_visit_date_local, h.last_visit_date_remote
+                 FROM moz_places h
+                 JOIN to_fetch f ON h.url_hash = hash(f.url)
+                   AND h.url = f.url",
+                values_with_idx
+            );
+            let mut stmt = db.prepare(&sql)?;
+            let mut rows = stmt.query(rusqlite::params_from_iter(chunk))?;
+            while let Some(row) = rows.next()? {
+                let idx: i64 = row.get("fetch_url_index")?;
+                counts.push((idx, PageVisitCounts::from_row(row)?));
+            }
+            Ok(())
+        },
+    )?;
+    counts.sort_by_key(|(idx, _)| *idx);
+    Ok(counts.into_iter().map(|(_, counts)| counts).collect())
+}
+
 pub fn get_visit_page(
     db: &PlacesDb,
     offset: i64,
@@ -1501,6 +1764,107 @@ pub fn get_visit_page_with_bound(
     }
 }
 
+/// The position of a page of visits in `get_visit_infos_page`: it starts with
+/// the visit after the one with this date and ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VisitCursor {
+    visit_date: i64,
+    visit_id: i64,
+}
+
+impl VisitCursor {
+    /// The cursor for the first page, which is before every visit.
+    const START: VisitCursor = VisitCursor {
+        visit_date: i64::MAX,
+        visit_id: i64::MAX,
+    };
+
+    fn from_continuation(continuation: &str) -> Result<Self> {
+        let (visit_date, visit_id) = continuation
+            .split_once(':')
+            .ok_or(Error::InvalidContinuation)?;
+        Ok(VisitCursor {
+            visit_date: visit_date.parse().map_err(|_| Error::InvalidContinuation)?,
+            visit_id: visit_id.parse().map_err(|_| Error::InvalidContinuation)?,
+        })
+    }
+
+    fn to_continuation(self) -> String {
+        format!("{}:{}", self.visit_date, self.visit_id)
+    }
+}
+
+/// Returns a page of the visits between `start` and `end`, most recent
+/// first, for history UIs that load more as the user scrolls.
+///
+/// Pass `None` as the `continuation` for the first page, and the returned
+/// `continuation` for each page after that, until it's `None`. Unlike an
+/// offset, the continuation is the position of the last visit returned, so
+/// pages don't skip or repeat visits when new ones are added or old ones are
+/// removed between calls.
+pub fn get_visit_infos_page(
+    db: &PlacesDb,
+    start: Timestamp,
+    end: Timestamp,
+    exclude_types: VisitTransitionSet,
+    source: VisitSource,
+    count: u32,
+    continuation: Option<&str>,
+) -> Result<HistoryVisitInfosPage> {
+    let cursor = match continuation {
+        Some(continuation) => VisitCursor::from_continuation(continuation)?,
+        None => VisitCursor::START,
+    };
+    let allowed_types = exclude_types.complement();
+    // Ask for one more visit than we need, to find out if there's another page.
+    let sql = format!(
+        "SELECT h.url, h.title, v.id, v.visit_date, v.visit_type, h.hidden,
+                h.preview_image_url, v.is_local
+         FROM moz_places h
+         JOIN moz_historyvisits v
+           ON h.id = v.place_id
+         WHERE v.visit_date BETWEEN :start AND :end
+           AND (v.visit_date < :cursor_date OR
+                (v.visit_date = :cursor_date AND v.id < :cursor_id))
+           AND ((1 << v.visit_type) & :allowed_types) != 0 AND
+           {source_filter} AND
+           NOT h.hidden
+         ORDER BY v.visit_date DESC, v.id DESC
+         LIMIT :count + 1",
+        source_filter = source.sql_filter("v.is_local"),
+    );
+    let mut rows = db.query_rows_and_then_cached(
+        &sql,
+        rusqlite::named_params! {
+            ":start": start,
+            ":end": end,
+            ":cursor_date": cursor.visit_date,
+            ":cursor_id": cursor.visit_id,
+            ":allowed_types": allowed_types,
+            ":count": count,
+        },
+        |row| -> Result<_> {
+            let cursor = VisitCursor {
+                visit_date: row.get("visit_date")?,
+                visit_id: row.get("id")?,
+            };
+            Ok((cursor, HistoryVisitInfo::from_row(row)?))
+        },
+    )?;
+
+    let continuation = if rows.len() > count as usize {
+        rows.truncate(count as usize);
+        let last_cursor = rows.last().map_or(cursor, |(cursor, _)| *cursor);
+        Some(last_cursor.to_continuation())
+    } else {
+        None
+    };
+    Ok(HistoryVisitInfosPage {
+        infos: rows.into_iter().map(|(_, info)| info).collect(),
+        continuation,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::history_sync::*;
@@ -1513,6 +1877,129 @@ mod tests {
     use sync15::engine::CollSyncIds;
     use types::Timestamp;
 
+    #[test]
+    fn test_get_top_frecent_sites() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let now = Timestamp::now();
+        for (url, title, visit_type, count) in [
+            ("http://www.example.com/", "Example", VisitType::Link, 1),
+            (
+                "https://example.com/page",
+                "Example Page",
+                VisitType::Link,
+                1,
+            ),
+            ("https://mozilla.org/", "Mozilla", VisitType::Typed, 3),
+            ("https://pinned.com/", "Pinned", VisitType::Typed, 5),
+            ("https://blocked.com/about", "Blocked", VisitType::Typed, 5),
+        ] {
+            for i in 0..count {
+                apply_observation(
+                    &conn,
+                    VisitObservation::new(Url::parse(url)?)
+                        .with_title(Some(title.into()))
+                        .with_visit_type(visit_type)
+                        .with_at(Timestamp(now.0 - i)),
+                )?;
+            }
+        }
+        let exclusions = TopSitesExclusions {
+            pinned: vec![Url::parse("https://www.pinned.com/")?],
+            blocked: vec![Url::parse("http://blocked.com/")?],
+        };
+        let top_sites = |limit: i32| -> Result<Vec<(String, Option<String>)>> {
+            Ok(get_top_frecent_sites(&conn, limit, &exclusions)?
+                .into_iter()
+                .map(|info| (info.url.to_string(), info.title))
+                .collect())
+        };
+
+        // The "www." and bare example.com origins are one site, which
+        // prefers https, and only has a title if its root page was visited.
+        assert_eq!(
+            top_sites(10)?,
+            vec![
+                (
+                    "https://mozilla.org/".to_string(),
+                    Some("Mozilla".to_string())
+                ),
+                ("https://example.com/".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            top_sites(1)?,
+            vec![(
+                "https://mozilla.org/".to_string(),
+                Some("Mozilla".to_string())
+            )]
+        );
+        assert_eq!(top_sites(0)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_history() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        for (url, title) in [
+            ("https://www.mozilla.org/firefox/", "Get Firefox Browser"),
+            ("https://support.mozilla.org/", "Mozilla Support"),
+            ("https://www.example.com/firefox-review", "A Review"),
+        ] {
+            apply_observation(
+                &conn,
+                VisitObservation::new(Url::parse(url)?)
+                    .with_title(Some(title.into()))
+                    .with_visit_type(VisitType::Link),
+            )?;
+        }
+        let search = |query: &str| -> Result<Vec<String>> {
+            Ok(search_history(&conn, query, 10)?
+                .into_iter()
+                .map(|result| result.url.to_string())
+                .collect())
+        };
+
+        // Matches in titles rank above those in URLs.
+        assert_eq!(
+            search("fire")?,
+            vec![
+                "https://www.mozilla.org/firefox/",
+                "https://www.example.com/firefox-review"
+            ]
+        );
+        // Every word has to match, in either the title or the URL.
+        assert_eq!(
+            search("moz fire")?,
+            vec!["https://www.mozilla.org/firefox/"]
+        );
+        assert_eq!(search("  ")?, Vec::<String>::new());
+        // Operators and quotes are matched as text, rather than being errors.
+        assert_eq!(
+            search("\"support OR")?,
+            vec!["https://support.mozilla.org/"]
+        );
+
+        // Changes to titles are indexed.
+        apply_observation(
+            &conn,
+            VisitObservation::new(Url::parse("https://support.mozilla.org/")?)
+                .with_title(Some("Help".into())),
+        )?;
+        assert_eq!(
+            search("support help")?,
+            vec!["https://support.mozilla.org/"]
+        );
+        assert_eq!(
+            search("mozilla support")?,
+            vec!["https://support.mozilla.org/"]
+        );
+
+        // Deleted pages are removed from the index.
+        delete_everything(&conn)?;
+        assert_eq!(search("fire")?, Vec::<String>::new());
+        Ok(())
+    }
+
     #[test]
     fn test_get_visited_urls() {
         use std::collections::HashSet;
@@ -2279,6 +2766,52 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_delete_visits_for_host() -> Result<()> {
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let urls = [
+            "https://example.com/new",
+            "http://example.com/synced",
+            "https://www.example.com/",
+            "https://example.org/",
+        ]
+        .iter()
+        .map(|url| Url::parse(url))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+        for url in &urls {
+            apply_observation(
+                &db,
+                VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+            )?;
+        }
+        db.execute_cached(
+            &format!(
+                "UPDATE moz_places
+                    SET sync_status = {}
+                 WHERE url = :url",
+                (SyncStatus::Normal as u8)
+            ),
+            &[(":url", &urls[1].as_str())],
+        )?;
+        let synced_guid = url_to_guid(&db, &urls[1])?.expect("should exist");
+
+        delete_visits_for_host(&db, "EXAMPLE.com")?;
+
+        // Both schemes are removed, but not other hosts.
+        assert!(fetch_page_info(&db, &urls[0])?.is_none());
+        assert!(fetch_page_info(&db, &urls[1])?.is_none());
+        assert!(fetch_page_info(&db, &urls[2])?.is_some());
+        assert!(fetch_page_info(&db, &urls[3])?.is_some());
+        // Only the synced page needs a tombstone.
+        let tombstones: Vec<SyncGuid> =
+            db.query_rows_into("SELECT guid FROM moz_places_tombstones", [], |row| {
+                row.get::<_, SyncGuid>(0)
+            })?;
+        assert_eq!(tombstones, vec![synced_guid]);
+        Ok(())
+    }
+
     #[test]
     fn test_reset() -> Result<()> {
         fn mark_all_as_synced(db: &PlacesDb) -> Result<()> {
@@ -3177,6 +3710,199 @@ mod tests {
         assert_eq!(infos_with_bound.offset, 1);
     }
 
+    #[test]
+    fn test_get_visit_infos_page() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("no memory db");
+        let now: Timestamp = SystemTime::now().into();
+        // Several visits at the same time, so pages split them.
+        let to_add = [
+            ("https://www.example.com/0", now.0 - 3_000),
+            ("https://www.example.com/1", now.0 - 2_000),
+            ("https://www.example.com/2", now.0 - 2_000),
+            ("https://www.example.com/3", now.0 - 2_000),
+            ("https://www.example.com/4", now.0 - 1_000),
+        ];
+        for &(url, when) in &to_add {
+            apply_observation(
+                &conn,
+                VisitObservation::new(Url::parse(url).unwrap())
+                    .with_at(Timestamp(when))
+                    .with_visit_type(VisitType::Link),
+            )?;
+        }
+        let all = get_visit_infos_page(
+            &conn,
+            Timestamp(0),
+            now,
+            VisitTransitionSet::empty(),
+            VisitSource::All,
+            10,
+            None,
+        )?;
+        assert_eq!(all.infos.len(), to_add.len());
+        assert_eq!(all.continuation, None);
+        assert!(all
+            .infos
+            .windows(2)
+            .all(|pair| pair[0].timestamp >= pair[1].timestamp));
+
+        // Paging through two at a time returns the same visits, in the same
+        // order, even when a visit is added in the middle.
+        let mut paged = Vec::new();
+        let mut continuation = None;
+        loop {
+            let page = get_visit_infos_page(
+                &conn,
+                Timestamp(0),
+                now,
+                VisitTransitionSet::empty(),
+                VisitSource::All,
+                2,
+                continuation.as_deref(),
+            )?;
+            assert!(page.infos.len() <= 2);
+            paged.extend(page.infos);
+            if paged.len() == 2 {
+                apply_observation(
+                    &conn,
+                    VisitObservation::new(Url::parse("https://www.example.com/new")?)
+                        .with_at(Timestamp(now.0 - 500))
+                        .with_visit_type(VisitType::Link),
+                )?;
+            }
+            continuation = match page.continuation {
+                Some(continuation) => Some(continuation),
+                None => break,
+            };
+        }
+        assert!(paged == all.infos, "Pages should match all the visits");
+
+        // Asking for nothing doesn't move the continuation.
+        let first = get_visit_infos_page(
+            &conn,
+            Timestamp(0),
+            now,
+            VisitTransitionSet::empty(),
+            VisitSource::All,
+            1,
+            None,
+        )?;
+        let empty = get_visit_infos_page(
+            &conn,
+            Timestamp(0),
+            now,
+            VisitTransitionSet::empty(),
+            VisitSource::All,
+            0,
+            first.continuation.as_deref(),
+        )?;
+        assert!(empty.infos.is_empty());
+        assert_eq!(empty.continuation, first.continuation);
+
+        for invalid in ["", "123", "abc:1", "1:2:3"] {
+            assert!(
+                matches!(
+                    get_visit_infos_page(
+                        &conn,
+                        Timestamp(0),
+                        now,
+                        VisitTransitionSet::empty(),
+                        VisitSource::All,
+                        2,
+                        Some(invalid),
+                    ),
+                    Err(Error::InvalidContinuation)
+                ),
+                "{:?} should be invalid",
+                invalid
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_visit_source() -> Result<()> {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("no memory db");
+        let now: Timestamp = SystemTime::now().into();
+        let both = Url::parse("https://www.example.com/both")?;
+        let remote = Url::parse("https://www.example.com/remote")?;
+        let to_add = [
+            (&both, now.0 - 3_000, false),
+            (&both, now.0 - 2_000, true),
+            (&both, now.0 - 1_000, false),
+            (&remote, now.0 - 1_500, true),
+        ];
+        for &(url, when, is_remote) in &to_add {
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone())
+                    .with_at(Timestamp(when))
+                    .with_visit_type(VisitType::Link)
+                    .with_is_remote(is_remote),
+            )?;
+        }
+
+        for (source, expected) in [
+            (VisitSource::All, 4),
+            (VisitSource::Local, 2),
+            (VisitSource::Remote, 2),
+        ] {
+            assert_eq!(
+                get_visit_count(&conn, VisitTransitionSet::empty(), source)?,
+                expected
+            );
+            let infos = get_visit_infos(
+                &conn,
+                Timestamp(0),
+                now,
+                VisitTransitionSet::empty(),
+                source,
+            )?;
+            assert_eq!(infos.len() as i64, expected);
+            let page = get_visit_infos_page(
+                &conn,
+                Timestamp(0),
+                now,
+                VisitTransitionSet::empty(),
+                source,
+                10,
+                None,
+            )?;
+            assert!(page.infos == infos.into_iter().rev().collect::<Vec<_>>());
+        }
+        let local = get_visit_infos(
+            &conn,
+            Timestamp(0),
+            now,
+            VisitTransitionSet::empty(),
+            VisitSource::Local,
+        )?;
+        assert!(local.iter().all(|info| !info.is_remote && info.url == both));
+
+        let unknown = Url::parse("https://www.example.com/unknown")?;
+        let counts = get_page_visit_counts(&conn, &[remote.clone(), unknown, both.clone()])?;
+        assert_eq!(
+            counts,
+            vec![
+                PageVisitCounts {
+                    url: remote,
+                    local: 0,
+                    remote: 1,
+                    last_visit_date_local: Timestamp(0),
+                    last_visit_date_remote: Timestamp(now.0 - 1_500),
+                },
+                PageVisitCounts {
+                    url: both,
+                    local: 2,
+                    remote: 1,
+                    last_visit_date_local: Timestamp(now.0 - 1_000),
+                    last_visit_date_remote: Timestamp(now.0 - 2_000),
+                },
+            ]
+        );
+        Ok(())
+    }
+
     /// Test find_normal_visits_to_prune
     #[test]
     fn test_normal_visit_pruning() {
@@ -425,6 +425,21 @@ lazy_static! {
         LIMIT :limit",
         common_select_sql = COMMON_METADATA_SELECT
     );
+    // SQLite takes the bare `id` column from the row with the `max(updated_at)`, so this
+    // selects the latest entry for each search term.
+    static ref RECENT_SEARCHES_SQL: String = format!(
+        "{common_select_sql}
+        WHERE m.id IN (
+            SELECT id FROM (
+                SELECT id, max(updated_at) FROM moz_places_metadata
+                WHERE search_query_id IS NOT NULL
+                GROUP BY search_query_id
+            )
+        )
+        ORDER BY updated_at DESC
+        LIMIT :limit",
+        common_select_sql = COMMON_METADATA_SELECT
+    );
 }
 
 pub fn get_latest_for_url(db: &PlacesDb, url: &Url) -> Result<Option<HistoryMetadata>> {
@@ -485,6 +500,17 @@ pub fn query(db: &PlacesDb, query: &str, limit: i32) -> Result<Vec<HistoryMetada
     )
 }
 
+/// Returns the latest entry for each search term, most recent first, for "recent searches".
+pub fn get_recent_searches(db: &PlacesDb, limit: i32) -> Result<Vec<HistoryMetadata>> {
+    db.query_rows_and_then_cached(
+        RECENT_SEARCHES_SQL.as_str(),
+        rusqlite::named_params! {
+            ":limit": limit
+        },
+        HistoryMetadata::from_row,
+    )
+}
+
 pub fn delete_older_than(db: &PlacesDb, older_than: i64) -> Result<()> {
     db.execute_cached(
         "DELETE FROM moz_places_metadata
@@ -754,7 +780,7 @@ mod tests {
         apply_observation, delete_everything, delete_visits_between, delete_visits_for,
         get_visit_count, url_to_guid,
     };
-    use crate::types::VisitType;
+    use crate::types::{VisitSource, VisitType};
     use crate::VisitTransitionSet;
     use pretty_assertions::assert_eq;
     use std::{thread, time};
@@ -1241,6 +1267,72 @@ mod tests {
         assert_eq!(0, get_since(&conn, after_meta2).unwrap().len());
     }
 
+    #[test]
+    fn test_get_recent_searches() {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("memory db");
+
+        assert_eq!(0, get_recent_searches(&conn, 10).unwrap().len());
+
+        note_observation!(&conn,
+            url "https://www.mozilla.org/firefox",
+            view_time Some(1000),
+            search_term Some("firefox"),
+            document_type Some(DocumentType::Regular),
+            referrer_url None,
+            title None
+        );
+        thread::sleep(time::Duration::from_millis(10));
+        note_observation!(&conn,
+            url "https://www.rust-lang.org/",
+            view_time Some(1000),
+            search_term Some("rust"),
+            document_type Some(DocumentType::Regular),
+            referrer_url None,
+            title None
+        );
+        thread::sleep(time::Duration::from_millis(10));
+        note_observation!(&conn,
+            url "https://support.mozilla.org/firefox",
+            view_time Some(1000),
+            search_term Some("firefox"),
+            document_type Some(DocumentType::Regular),
+            referrer_url None,
+            title None
+        );
+        // Entries without a search term aren't searches.
+        note_observation!(&conn,
+            url "https://news.website/tech",
+            view_time Some(1000),
+            search_term None,
+            document_type Some(DocumentType::Regular),
+            referrer_url None,
+            title None
+        );
+
+        let searches = get_recent_searches(&conn, 10).unwrap();
+        assert_eq!(2, searches.len());
+        assert_history_metadata_record!(searches[0],
+            url "https://support.mozilla.org/firefox",
+            total_time 1000,
+            search_term Some("firefox"),
+            document_type DocumentType::Regular,
+            referrer_url None,
+            title None,
+            preview_image_url None
+        );
+        assert_history_metadata_record!(searches[1],
+            url "https://www.rust-lang.org/",
+            total_time 1000,
+            search_term Some("rust"),
+            document_type DocumentType::Regular,
+            referrer_url None,
+            title None,
+            preview_image_url None
+        );
+
+        assert_eq!(1, get_recent_searches(&conn, 1).unwrap().len());
+    }
+
     #[test]
     fn test_get_highlights() {
         let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("memory db");
@@ -1797,7 +1889,7 @@ mod tests {
 
         // Check the DB is what we expect before deleting.
         assert_eq!(
-            get_visit_count(&conn, VisitTransitionSet::empty()).unwrap(),
+            get_visit_count(&conn, VisitTransitionSet::empty(), VisitSource::All).unwrap(),
             1
         );
         let place_guid = url_to_guid(&conn, &url)
@@ -1837,7 +1929,7 @@ mod tests {
 
         // Check the DB is what we expect before deleting.
         assert_eq!(
-            get_visit_count(&conn, VisitTransitionSet::empty()).unwrap(),
+            get_visit_count(&conn, VisitTransitionSet::empty(), VisitSource::All).unwrap(),
             1
         );
         let place_guid = url_to_guid(&conn, &url)
@@ -1869,7 +1961,7 @@ mod tests {
 
         // Check the DB is what we expect before deleting.
         assert_eq!(
-            get_visit_count(&conn, VisitTransitionSet::empty()).unwrap(),
+            get_visit_count(&conn, VisitTransitionSet::empty(), VisitSource::All).unwrap(),
             0
         );
         let place_guid = url_to_guid(&conn, &url)
@@ -5,9 +5,12 @@
 // A "storage" module - this module is intended to be the layer between the
 // API and the database.
 
+pub mod annotations;
 pub mod bookmarks;
+pub mod favicons;
 pub mod history;
 pub mod history_metadata;
+pub mod previews;
 pub mod tags;
 
 use crate::db::PlacesDb;
@@ -31,7 +34,7 @@ use url::Url;
 pub const URL_LENGTH_MAX: usize = 65536;
 pub const TITLE_LENGTH_MAX: usize = 4096;
 pub const TAG_LENGTH_MAX: usize = 100;
-// pub const DESCRIPTION_LENGTH_MAX: usize = 256;
+pub const DESCRIPTION_LENGTH_MAX: usize = 256;
 
 // Typesafe way to manage RowIds. Does it make sense? A better way?
 #[derive(
@@ -309,6 +312,115 @@ pub fn run_maintenance_checkpoint(conn: &PlacesDb) -> Result<()> {
     Ok(())
 }
 
+#[derive(Debug)]
+pub struct RunMaintenanceFrecencyMetrics {
+    pub recalculated: u32,
+    pub remaining: u32,
+}
+
+/// Run maintenance on the places DB (frecency step)
+///
+/// The `run_maintenance_*()` functions are intended to be run during idle time and will take steps
+/// to clean up / shrink the database.  They're split up so that we can time each one in the
+/// Kotlin wrapper code (This is needed because we only have access to the Glean API in Kotlin and
+/// it supports a stop-watch style API, not recording specific values).
+///
+/// Some writes, like applying incoming synced history, flag pages as having stale frecencies
+/// instead of recalculating them as they go. This recalculates the frecencies of up to `budget`
+/// of those pages, most recently flagged first, and updates their origins. Callers can keep
+/// calling this until `remaining` is 0.
+pub fn run_maintenance_frecency(
+    conn: &PlacesDb,
+    budget: u32,
+) -> Result<RunMaintenanceFrecencyMetrics> {
+    let scope = conn.begin_interrupt_scope()?;
+    let tx = conn.begin_transaction()?;
+
+    let stale_ids = tx.query_rows_and_then(
+        "SELECT place_id FROM moz_places_stale_frecencies
+         ORDER BY stale_at DESC
+         LIMIT :budget",
+        &[(":budget", &budget)],
+        |r| r.get::<_, i64>(0),
+    )?;
+    let frecencies = stale_ids
+        .iter()
+        .map(|place_id| {
+            // Frecency recalculation runs several statements, so check to
+            // make sure we aren't interrupted before each calculation.
+            scope.err_if_interrupted()?;
+            Ok((
+                *place_id,
+                calculate_frecency(conn, &DEFAULT_FRECENCY_SETTINGS, *place_id, Some(false))?,
+            ))
+        })
+        .collect::<Result<Vec<(i64, i32)>>>()?;
+
+    if !frecencies.is_empty() {
+        tx.execute_batch(&format!(
+            "WITH frecencies(id, frecency) AS (
+                VALUES {}
+                )
+                UPDATE moz_places SET
+                frecency = (SELECT frecency FROM frecencies f
+                            WHERE f.id = id)
+                WHERE id IN (SELECT f.id FROM frecencies f)",
+            sql_support::repeat_display(frecencies.len(), ",", |index, f| {
+                let (id, frecency) = frecencies[index];
+                write!(f, "({}, {})", id, frecency)
+            })
+        ))?;
+        scope.err_if_interrupted()?;
+
+        tx.execute_batch(&format!(
+            "DELETE FROM moz_places_stale_frecencies
+             WHERE place_id IN ({})",
+            sql_support::repeat_display(frecencies.len(), ",", |index, f| {
+                let (id, _) = frecencies[index];
+                write!(f, "{}", id)
+            })
+        ))?;
+        // Flush the origin frecency updates triggered by the above.
+        delete_pending_temp_tables(conn)?;
+    }
+    let remaining = tx.query_one::<u32>("SELECT COUNT(*) FROM moz_places_stale_frecencies")?;
+    tx.commit()?;
+
+    Ok(RunMaintenanceFrecencyMetrics {
+        recalculated: frecencies.len() as u32,
+        remaining,
+    })
+}
+
+/// The maximum number of visits `run_maintenance` prunes in one call. This is
+/// deliberately low, so that each call is quick.
+const MAINTENANCE_PRUNE_LIMIT: u32 = 6;
+
+/// The maximum number of stale frecencies `run_maintenance` recalculates in
+/// one call.
+const MAINTENANCE_FRECENCY_BUDGET: u32 = 400;
+
+/// Run all the maintenance steps on the places DB
+///
+/// This is a single entry point for consumers that run maintenance from a background task
+/// scheduler, and don't need to time each step. It prunes a few of the oldest visits if the
+/// database is over `db_size_limit` bytes, recalculates stale frecencies, removes expired page
+/// annotations, runs an incremental vacuum, optimizes, and checkpoints the WAL. Each step does a
+/// small amount of work, so this should be called regularly while the app is idle. Pass in a
+/// `db_size_limit` of 0 to skip pruning.
+pub fn run_maintenance(conn: &PlacesDb, db_size_limit: u32) -> Result<RunMaintenanceMetrics> {
+    let prune_metrics = run_maintenance_prune(conn, db_size_limit, MAINTENANCE_PRUNE_LIMIT)?;
+    run_maintenance_frecency(conn, MAINTENANCE_FRECENCY_BUDGET)?;
+    annotations::expire_annotations(conn, Timestamp::now())?;
+    run_maintenance_vacuum(conn)?;
+    run_maintenance_optimize(conn)?;
+    run_maintenance_checkpoint(conn)?;
+    Ok(RunMaintenanceMetrics {
+        db_size_after: conn.get_db_size()?,
+        ..prune_metrics
+    })
+}
+
 pub fn update_all_frecencies_at_once(db: &PlacesDb, scope: &SqlInterruptScope) -> Result<()> {
     let tx = db.begin_transaction()?;
 
@@ -428,6 +540,114 @@ mod tests {
         delete_meta(&conn, "foo").expect("delete non-existing should work");
     }
 
+    #[test]
+    fn test_run_maintenance_frecency() -> Result<()> {
+        use crate::history_sync::record::HistoryRecordVisit;
+        use history::history_sync::apply_synced_visits;
+
+        let conn = new_mem_connection();
+        let urls = [
+            Url::parse("http://example.com/1")?,
+            Url::parse("http://example.com/2")?,
+        ];
+        for url in &urls {
+            apply_synced_visits(
+                &conn,
+                &SyncGuid::random(),
+                url,
+                &None,
+                &[HistoryRecordVisit {
+                    date: Timestamp::now().into(),
+                    transition: VisitType::Link as u8,
+                    unknown_fields: UnknownFields::new(),
+                }],
+                &UnknownFields::new(),
+            )?;
+        }
+        let frecency_for = |url: &Url| -> Result<i64> {
+            Ok(conn.query_row_and_then_cachable(
+                "SELECT frecency FROM moz_places WHERE url = :url",
+                &[(":url", &url.as_str())],
+                |row| row.get(0),
+                false,
+            )?)
+        };
+        let origin_frecency = || -> Result<i64> {
+            Ok(conn.query_one("SELECT frecency FROM moz_origins WHERE host = 'example.com'")?)
+        };
+
+        // Incoming visits don't recalculate frecency straight away.
+        for url in &urls {
+            assert!(history::frecency_stale_at(&conn, url)?.is_some());
+            assert_eq!(frecency_for(url)?, -1);
+        }
+        assert_eq!(origin_frecency()?, 0);
+
+        let metrics = run_maintenance_frecency(&conn, 1)?;
+        assert_eq!(metrics.recalculated, 1);
+        assert_eq!(metrics.remaining, 1);
+
+        let metrics = run_maintenance_frecency(&conn, 10)?;
+        assert_eq!(metrics.recalculated, 1);
+        assert_eq!(metrics.remaining, 0);
+        for url in &urls {
+            assert!(history::frecency_stale_at(&conn, url)?.is_none());
+            assert!(frecency_for(url)? > 0);
+        }
+        assert_eq!(
+            origin_frecency()?,
+            frecency_for(&urls[0])? + frecency_for(&urls[1])?
+        );
+
+        let metrics = run_maintenance_frecency(&conn, 10)?;
+        assert_eq!(metrics.recalculated, 0);
+        assert_eq!(metrics.remaining, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_maintenance() -> Result<()> {
+        use crate::api::places_api::ConnectionType;
+
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        // 30 days ago, so the visits are old enough to prune.
+        let visit_date = Timestamp(Timestamp::now().as_millis() - 30 * 24 * 60 * 60 * 1000);
+        let mut page_ids = vec![];
+        for i in 0..10 {
+            let url = Url::parse(&format!("https://example.com/{i}"))?;
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone())
+                    .with_at(visit_date)
+                    .with_visit_type(VisitType::Link),
+            )?;
+            page_ids.push(
+                fetch_page_info(&conn, &url)?
+                    .expect("should exist")
+                    .page
+                    .row_id,
+            );
+        }
+        history::mark_frecency_stale(&conn, page_ids[0])?;
+        let visit_count = || conn.query_one::<u32>("SELECT COUNT(*) FROM moz_historyvisits");
+
+        // A limit of 0 doesn't prune anything.
+        let metrics = run_maintenance(&conn, 0)?;
+        assert!(!metrics.pruned_visits);
+        assert_eq!(visit_count()?, 10);
+        assert_eq!(
+            conn.query_one::<u32>("SELECT COUNT(*) FROM moz_places_stale_frecencies")?,
+            0
+        );
+        assert_eq!(conn.query_one::<u32>("PRAGMA auto_vacuum")?, 2);
+
+        // The database is always bigger than 1 byte, so this prunes a few visits.
+        let metrics = run_maintenance(&conn, 1)?;
+        assert!(metrics.pruned_visits);
+        assert_eq!(visit_count()?, 10 - MAINTENANCE_PRUNE_LIMIT);
+        Ok(())
+    }
+
     // Here we try and test that we replicate desktop behaviour, which isn't that obvious.
     // * create a bookmark
     // * remove the bookmark - this doesn't remove the place or origin - probably because in
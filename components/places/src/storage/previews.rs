@@ -0,0 +1,212 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::DESCRIPTION_LENGTH_MAX;
+use crate::db::PlacesDb;
+use crate::error::Result;
+use crate::ffi::PagePreview;
+use crate::util::slice_up_to;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use url::Url;
+
+impl PagePreview {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        let url: String = row.get("url")?;
+        // Image URLs were only ever stored after being parsed, but be lenient
+        // in case they weren't.
+        let preview_image_url = row
+            .get::<_, Option<String>>("preview_image_url")?
+            .and_then(|image_url| Url::parse(&image_url).ok());
+        Ok(Self {
+            url: Url::parse(&url)?,
+            preview_image_url,
+            description: row.get("description")?,
+        })
+    }
+}
+
+/// Sets the preview image URL and description for a page, usually from its
+/// `og:image` and `og:description` metadata. Both are replaced, so passing
+/// `None` for either clears it. Descriptions longer than
+/// `DESCRIPTION_LENGTH_MAX` bytes are truncated.
+///
+/// Previews aren't synced, so this doesn't change the page's sync status.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if the page
+/// isn't in history or bookmarked.
+pub fn set_page_preview(db: &PlacesDb, preview: &PagePreview) -> Result<()> {
+    let description = preview
+        .description
+        .as_deref()
+        .map(|description| slice_up_to(description, DESCRIPTION_LENGTH_MAX))
+        .filter(|description| !description.is_empty());
+    // Like favicons, this won't create a new place.
+    let changed = db.execute_cached(
+        "UPDATE moz_places
+         SET preview_image_url = :preview_image_url,
+             description = :description
+         WHERE url_hash = hash(:page_url) AND url = :page_url",
+        &[
+            (":page_url", &preview.url.as_str() as &dyn rusqlite::ToSql),
+            (
+                ":preview_image_url",
+                &preview.preview_image_url.as_ref().map(Url::as_str),
+            ),
+            (":description", &description),
+        ],
+    )?;
+    if changed == 0 {
+        log::debug!("Ignoring preview for unknown page");
+    }
+    Ok(())
+}
+
+/// Returns the previews for the specified pages, in the same order. Pages
+/// that aren't known, or don't have a preview image or a description, are
+/// left out.
+pub fn get_page_previews(db: &PlacesDb, page_urls: &[Url]) -> Result<Vec<PagePreview>> {
+    let mut previews = Vec::with_capacity(page_urls.len());
+    sql_support::each_chunk_mapped(
+        page_urls,
+        |url| url.as_str(),
+        |chunk, offset| -> Result<()> {
+            let values_with_idx = sql_support::repeat_display(chunk.len(), ",", |i, f| {
+                write!(f, "({},?)", i + offset)
+            });
+            let sql = format!(
+                "WITH to_fetch(fetch_url_index, url) AS (VALUES {})
+                 SELECT f.fetch_url_index, h.url, h.preview_image_url, h.description
+                 FROM moz_places h
+                 JOIN to_fetch f ON h.url_hash = hash(f.url)
+                   AND h.url = f.url
+                 WHERE h.preview_image_url IS NOT NULL
+                    OR h.description IS NOT NULL",
+                values_with_idx
+            );
+            let mut stmt = db.prepare(&sql)?;
+            let mut rows = stmt.query(rusqlite::params_from_iter(chunk))?;
+            while let Some(row) = rows.next()? {
+                let idx: i64 = row.get("fetch_url_index")?;
+                previews.push((idx, PagePreview::from_row(row)?));
+            }
+            Ok(())
+        },
+    )?;
+    previews.sort_by_key(|(idx, _)| *idx);
+    Ok(previews.into_iter().map(|(_, preview)| preview).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::fetch_page_info;
+    use crate::storage::history::apply_observation;
+    use crate::types::VisitType;
+
+    fn visit(db: &PlacesDb, url: &str) -> Url {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            db,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )
+        .expect("should apply visit");
+        url
+    }
+
+    #[test]
+    fn test_page_previews() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/page");
+        let other_url = visit(&conn, "https://www.example.com/other");
+        let unvisited_url = Url::parse("https://www.example.com/unvisited")?;
+
+        assert_eq!(get_page_previews(&conn, &[page_url.clone()])?, vec![]);
+
+        let preview = PagePreview {
+            url: page_url.clone(),
+            preview_image_url: Some(Url::parse("https://www.example.com/image.png")?),
+            description: Some("A page".to_string()),
+        };
+        set_page_preview(&conn, &preview)?;
+        let other_preview = PagePreview {
+            url: other_url.clone(),
+            preview_image_url: None,
+            description: Some("Another page".to_string()),
+        };
+        set_page_preview(&conn, &other_preview)?;
+
+        // Previews come back in the order they were asked for.
+        assert_eq!(
+            get_page_previews(
+                &conn,
+                &[other_url.clone(), unvisited_url.clone(), page_url.clone()]
+            )?,
+            vec![other_preview.clone(), preview.clone()]
+        );
+
+        // Setting a preview replaces both fields, and removing both removes
+        // the preview.
+        set_page_preview(
+            &conn,
+            &PagePreview {
+                url: page_url.clone(),
+                preview_image_url: None,
+                description: Some(String::new()),
+            },
+        )?;
+        assert_eq!(get_page_previews(&conn, &[page_url])?, vec![]);
+
+        // Previews for unknown pages are ignored.
+        set_page_preview(
+            &conn,
+            &PagePreview {
+                url: unvisited_url.clone(),
+                ..other_preview
+            },
+        )?;
+        assert!(fetch_page_info(&conn, &unvisited_url)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_page_preview_description_truncated() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/");
+        set_page_preview(
+            &conn,
+            &PagePreview {
+                url: page_url.clone(),
+                preview_image_url: None,
+                description: Some("é".repeat(DESCRIPTION_LENGTH_MAX)),
+            },
+        )?;
+        let previews = get_page_previews(&conn, &[page_url])?;
+        assert_eq!(
+            previews[0].description.as_deref().map(str::len),
+            Some(DESCRIPTION_LENGTH_MAX)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_observation_keeps_preview() -> Result<()> {
+        let conn = new_mem_connection();
+        let page_url = visit(&conn, "https://www.example.com/");
+        let preview = PagePreview {
+            url: page_url.clone(),
+            preview_image_url: Some(Url::parse("https://www.example.com/image.png")?),
+            description: Some("A page".to_string()),
+        };
+        set_page_preview(&conn, &preview)?;
+        // Visiting the page again shouldn't clear its preview.
+        visit(&conn, "https://www.example.com/");
+        assert_eq!(get_page_previews(&conn, &[page_url])?, vec![preview]);
+        Ok(())
+    }
+}
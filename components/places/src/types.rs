@@ -120,6 +120,27 @@ impl<'de> serde::Deserialize<'de> for VisitType {
     }
 }
 
+/// Which visits a history query includes: all of them, or only those made on
+/// this device, or only those made on other devices and downloaded by Sync.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VisitSource {
+    All,
+    Local,
+    Remote,
+}
+
+impl VisitSource {
+    /// Returns an SQL expression that's true for the visits to include, given
+    /// the name of their `is_local` column.
+    pub(crate) fn sql_filter(self, is_local: &str) -> String {
+        match self {
+            VisitSource::All => "1".to_string(),
+            VisitSource::Local => is_local.to_string(),
+            VisitSource::Remote => format!("NOT {}", is_local),
+        }
+    }
+}
+
 /// Bookmark types.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[repr(u8)]
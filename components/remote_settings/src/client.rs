@@ -6,16 +6,19 @@ use crate::config::RemoteSettingsConfig;
 use crate::error::{RemoteSettingsError, Result};
 use crate::UniffiCustomTypeConverter;
 use parking_lot::Mutex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     time::{Duration, Instant},
 };
 use url::Url;
-use viaduct::{Request, Response};
+use viaduct::{status_codes, Request, Response};
 
 const HEADER_BACKOFF: &str = "Backoff";
 const HEADER_ETAG: &str = "ETag";
+const HEADER_IF_MODIFIED_SINCE: &str = "If-Modified-Since";
+const HEADER_IF_NONE_MATCH: &str = "If-None-Match";
+const HEADER_LAST_MODIFIED: &str = "Last-Modified";
 const HEADER_RETRY_AFTER: &str = "Retry-After";
 
 /// A simple HTTP client that can retrieve Remote Settings data using the properties by [ClientConfig].
@@ -75,28 +78,96 @@ impl Client {
     ) -> Result<RemoteSettingsResponse> {
         let resp = self.get_records_raw_with_options(options)?;
         let records = resp.json::<RecordsResponse>()?.data;
-        let etag = resp
-            .headers
-            .get(HEADER_ETAG)
-            .ok_or_else(|| RemoteSettingsError::ResponseError("no etag header".into()))?;
-        // Per https://docs.kinto-storage.org/en/stable/api/1.x/timestamps.html,
-        // the `ETag` header value is a quoted integer. Trim the quotes before
-        // parsing.
-        let last_modified = etag.trim_matches('"').parse().map_err(|_| {
-            RemoteSettingsError::ResponseError(format!(
-                "expected quoted integer in etag header; got `{}`",
-                etag
-            ))
-        })?;
+        let last_modified = last_modified_from_etag(&resp)?;
         Ok(RemoteSettingsResponse {
             records,
             last_modified,
         })
     }
 
+    /// Fetches all records for the collection, as [Client::get_records_raw] does, but
+    /// only returns them once the collection's content signature has been verified.
+    ///
+    /// The signature is checked against the certificate chain the collection's metadata
+    /// points to, which must chain up to the root certificate whose SHA-256 hash is
+    /// `root_hash`, e.g. [crate::signatures::PROD_ROOT_HASH]. Returns a
+    /// [RemoteSettingsError::SignatureError] if the records have been tampered with, or
+    /// if the collection changed between fetching the records and the signature; fetching
+    /// again will succeed in the latter case.
+    #[cfg(feature = "signatures")]
+    pub fn get_records_raw_verified(&self, root_hash: &str) -> Result<Response> {
+        let resp = self.get_records_raw()?;
+        self.verify_records(&resp, root_hash)?;
+        Ok(resp)
+    }
+
+    /// Fetches all records for the collection, as [Client::get_records_raw] does, unless
+    /// they haven't changed since the response `validators` were taken from, in which
+    /// case `None` is returned.
+    pub fn get_records_raw_if_modified(
+        &self,
+        validators: &CacheValidators,
+    ) -> Result<Option<Response>> {
+        let mut req = Request::get(self.records_url(&GetItemsOptions::new())?);
+        if let Some(etag) = &validators.etag {
+            req = req.header(HEADER_IF_NONE_MATCH, etag.as_str())?;
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            req = req.header(HEADER_IF_MODIFIED_SINCE, last_modified.as_str())?;
+        }
+        let resp = self.send_request(req)?;
+        Ok((resp.status != status_codes::NOT_MODIFIED).then_some(resp))
+    }
+
+    /// As [Client::get_records_raw_if_modified], but verifies the collection's content
+    /// signature, as [Client::get_records_raw_verified] does, if the records have changed.
+    #[cfg(feature = "signatures")]
+    pub fn get_records_raw_verified_if_modified(
+        &self,
+        root_hash: &str,
+        validators: &CacheValidators,
+    ) -> Result<Option<Response>> {
+        let resp = self.get_records_raw_if_modified(validators)?;
+        if let Some(resp) = &resp {
+            self.verify_records(resp, root_hash)?;
+        }
+        Ok(resp)
+    }
+
+    #[cfg(feature = "signatures")]
+    fn verify_records(&self, resp: &Response, root_hash: &str) -> Result<()> {
+        let records = resp.json::<RawRecordsResponse>()?.data;
+        let last_modified = last_modified_from_etag(resp)?;
+
+        let path = format!(
+            "v1/buckets/{}/collections/{}",
+            &self.bucket_name, &self.collection_name
+        );
+        let metadata = self
+            .make_request(self.base_url.join(&path)?)?
+            .json::<CollectionResponse>()?
+            .data;
+        let signature = metadata.signature.ok_or_else(|| {
+            RemoteSettingsError::SignatureError("the collection is not signed".into())
+        })?;
+        let certificate_chain = self.make_request(Url::parse(&signature.x5u)?)?.body;
+
+        crate::signatures::verify(
+            &records,
+            last_modified,
+            &signature.signature,
+            &certificate_chain,
+            root_hash,
+        )
+    }
+
     /// Fetches a raw network [Response] for records from this client's
     /// collection with the given options.
     pub fn get_records_raw_with_options(&self, options: &GetItemsOptions) -> Result<Response> {
+        self.make_request(self.records_url(options)?)
+    }
+
+    fn records_url(&self, options: &GetItemsOptions) -> Result<Url> {
         let path = format!(
             "v1/buckets/{}/collections/{}/records",
             &self.bucket_name, &self.collection_name
@@ -105,7 +176,7 @@ impl Client {
         for (name, value) in options.iter_query_pairs() {
             url.query_pairs_mut().append_pair(&name, &value);
         }
-        self.make_request(url)
+        Ok(url)
     }
 
     /// Downloads an attachment from [attachment_location]. NOTE: there are no
@@ -142,17 +213,24 @@ impl Client {
     }
 
     fn make_request(&self, url: Url) -> Result<Response> {
+        self.send_request(Request::get(url))
+    }
+
+    fn send_request(&self, req: Request) -> Result<Response> {
         let mut current_remote_state = self.remote_state.lock();
         self.ensure_no_backoff(&mut current_remote_state.backoff)?;
         drop(current_remote_state);
 
-        let req = Request::get(url);
+        // Only conditional requests can get a `304 Not Modified`; for any other request,
+        // it would be taken as an empty collection.
+        let is_conditional = req.headers.get(HEADER_IF_NONE_MATCH).is_some()
+            || req.headers.get(HEADER_IF_MODIFIED_SINCE).is_some();
         let resp = req.send()?;
 
         let mut current_remote_state = self.remote_state.lock();
         self.handle_backoff_hint(&resp, &mut current_remote_state.backoff)?;
 
-        if resp.is_success() {
+        if resp.is_success() || (is_conditional && resp.status == status_codes::NOT_MODIFIED) {
             Ok(resp)
         } else {
             Err(RemoteSettingsError::ResponseError(resp.text().to_string()))
@@ -212,11 +290,74 @@ pub struct RemoteSettingsResponse {
     pub last_modified: u64,
 }
 
+/// The validators of a response for a collection's records, which make a later request
+/// conditional on the records having changed since. See
+/// [Client::get_records_raw_if_modified].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    pub fn from_response(resp: &Response) -> Self {
+        Self {
+            etag: resp.headers.get(HEADER_ETAG).map(str::to_string),
+            last_modified: resp.headers.get(HEADER_LAST_MODIFIED).map(str::to_string),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct RecordsResponse {
     data: Vec<RemoteSettingsRecord>,
 }
 
+/// The records as they were sent, since every field is covered by the signature.
+#[cfg(feature = "signatures")]
+#[derive(Deserialize)]
+struct RawRecordsResponse {
+    data: Vec<serde_json::Value>,
+}
+
+#[cfg(feature = "signatures")]
+#[derive(Deserialize)]
+struct CollectionResponse {
+    data: CollectionMetadata,
+}
+
+#[cfg(feature = "signatures")]
+#[derive(Deserialize)]
+struct CollectionMetadata {
+    signature: Option<CollectionSignature>,
+}
+
+#[cfg(feature = "signatures")]
+#[derive(Deserialize)]
+struct CollectionSignature {
+    /// The signature, base64 url-safe encoded.
+    signature: String,
+    /// The URL of the certificate chain, in PEM format.
+    x5u: String,
+}
+
+/// Extracts the collection's timestamp from the `ETag` header of a response.
+fn last_modified_from_etag(resp: &Response) -> Result<u64> {
+    let etag = resp
+        .headers
+        .get(HEADER_ETAG)
+        .ok_or_else(|| RemoteSettingsError::ResponseError("no etag header".into()))?;
+    // Per https://docs.kinto-storage.org/en/stable/api/1.x/timestamps.html,
+    // the `ETag` header value is a quoted integer. Trim the quotes before
+    // parsing.
+    etag.trim_matches('"').parse().map_err(|_| {
+        RemoteSettingsError::ResponseError(format!(
+            "expected quoted integer in etag header; got `{}`",
+            etag
+        ))
+    })
+}
+
 /// A parsed Remote Settings record. Records can contain arbitrary fields, so clients
 /// are required to further extract expected values from the [fields] member.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
@@ -961,6 +1102,76 @@ mod test {
         m.expect(1).assert();
     }
 
+    #[test]
+    fn test_get_records_raw_if_modified() {
+        viaduct_reqwest::use_reqwest_backend();
+        let path = "/v1/buckets/the-bucket/collections/the-collection/records";
+        let modified_m = mock("GET", path)
+            .match_header("if-none-match", Matcher::Missing)
+            .with_body(response_body())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("etag", "\"1000\"")
+            .with_header("last-modified", "Mon, 22 Jan 2024 10:00:00 GMT")
+            .create();
+        let not_modified_m = mock("GET", path)
+            .match_header("if-none-match", "\"1000\"")
+            .match_header("if-modified-since", "Mon, 22 Jan 2024 10:00:00 GMT")
+            .with_status(304)
+            .create();
+
+        let config = RemoteSettingsConfig {
+            server_url: Some(mockito::server_url()),
+            bucket_name: Some(String::from("the-bucket")),
+            collection_name: String::from("the-collection"),
+        };
+        let client = Client::new(config).unwrap();
+
+        let resp = client
+            .get_records_raw_if_modified(&CacheValidators::default())
+            .unwrap()
+            .expect("no validators, so the records should be returned");
+        let validators = CacheValidators::from_response(&resp);
+        assert_eq!(validators.etag.as_deref(), Some("\"1000\""));
+        assert_eq!(
+            validators.last_modified.as_deref(),
+            Some("Mon, 22 Jan 2024 10:00:00 GMT")
+        );
+
+        let resp = client.get_records_raw_if_modified(&validators).unwrap();
+        assert!(resp.is_none());
+        modified_m.expect(1).assert();
+        not_modified_m.expect(1).assert();
+    }
+
+    #[test]
+    fn test_unconditional_not_modified_is_an_error() {
+        viaduct_reqwest::use_reqwest_backend();
+        let m = mock(
+            "GET",
+            "/v1/buckets/the-bucket/collections/the-collection/records",
+        )
+        .with_status(304)
+        .create();
+
+        let config = RemoteSettingsConfig {
+            server_url: Some(mockito::server_url()),
+            bucket_name: Some(String::from("the-bucket")),
+            collection_name: String::from("the-collection"),
+        };
+        let client = Client::new(config).unwrap();
+
+        let err = client
+            .get_records_raw_if_modified(&CacheValidators::default())
+            .unwrap_err();
+        assert!(
+            matches!(err, RemoteSettingsError::ResponseError(_)),
+            "Want response error for an unrequested 304; got {}",
+            err
+        );
+        m.expect(1).assert();
+    }
+
     #[test]
     fn test_invalid_etag() {
         viaduct_reqwest::use_reqwest_backend();
@@ -22,6 +22,9 @@ pub enum RemoteSettingsError {
     ResponseError(String),
     #[error("This server doesn't support attachments")]
     AttachmentsUnsupportedError,
+    /// The content signature of the collection could not be verified.
+    #[error("Error verifying the content signature: {0}")]
+    SignatureError(String),
 }
 
 pub type Result<T, E = RemoteSettingsError> = std::result::Result<T, E>;
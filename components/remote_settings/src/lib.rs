@@ -7,11 +7,13 @@ pub use error::{RemoteSettingsError, Result};
 use std::{fs::File, io::prelude::Write};
 pub mod client;
 pub use client::{
-    Attachment, Client, GetItemsOptions, RemoteSettingsRecord, RemoteSettingsResponse,
-    RsJsonObject, SortOrder,
+    Attachment, CacheValidators, Client, GetItemsOptions, RemoteSettingsRecord,
+    RemoteSettingsResponse, RsJsonObject, SortOrder,
 };
 pub mod config;
 pub use config::RemoteSettingsConfig;
+#[cfg(feature = "signatures")]
+pub mod signatures;
 
 uniffi::include_scaffolding!("remote_settings");
 
@@ -0,0 +1,190 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Verification of the content signatures of Remote Settings collections.
+//!
+//! The server signs the collection's records and timestamp, serialized as canonical JSON,
+//! with a certificate which chains up to a Mozilla root. See
+//! <https://remote-settings.readthedocs.io/en/latest/signature.html>.
+
+use crate::error::{RemoteSettingsError, Result};
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The SHA-256 hash of the root certificate which signs the collections on the production
+/// server.
+pub const PROD_ROOT_HASH: &str = "97:E8:BA:9C:F1:2F:B3:DE:53:CC:42:A4:E6:57:7E:D6:4D:F4:93:C2:47:B4:14:FE:A0:36:81:8D:38:23:56:0E";
+
+/// The subject of the certificates which sign Remote Settings collections.
+const SIGNER_HOSTNAME: &str = "remote-settings.content-signature.mozilla.org";
+
+const SIGNATURE_PREFIX: &[u8] = b"Content-Signature:\x00";
+
+/// Verifies `signature` over the `records` of a collection as of `last_modified`.
+/// `certificate_chain` is in PEM format, and must chain up to the root certificate whose
+/// SHA-256 hash is `root_hash`.
+pub(crate) fn verify(
+    records: &[Value],
+    last_modified: u64,
+    signature: &str,
+    certificate_chain: &[u8],
+    root_hash: &str,
+) -> Result<()> {
+    rc_crypto::ensure_initialized();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    rc_crypto::contentsignature::verify(
+        &signed_message(records, last_modified),
+        signature.as_bytes(),
+        certificate_chain,
+        now,
+        root_hash,
+        SIGNER_HOSTNAME,
+    )
+    .map_err(|e| RemoteSettingsError::SignatureError(e.to_string()))
+}
+
+/// The message the server signs: the records, without tombstones and sorted by id, and
+/// the timestamp of the collection, as canonical JSON.
+fn signed_message(records: &[Value], last_modified: u64) -> Vec<u8> {
+    let mut records: Vec<&Value> = records
+        .iter()
+        .filter(|r| !r["deleted"].as_bool().unwrap_or(false))
+        .collect();
+    records.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    let payload = json!({
+        "data": records,
+        "last_modified": last_modified.to_string(),
+    });
+
+    let mut message = SIGNATURE_PREFIX.to_vec();
+    message.extend(canonical_json(&payload).as_bytes());
+    message
+}
+
+/// Serializes `value` as the server does: with sorted keys, no whitespace, non-ASCII
+/// characters escaped, and numbers formatted as Python does.
+fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(&mut out, value);
+    out
+}
+
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Null | Value::Bool(_) => out.push_str(&value.to_string()),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if !n.is_i64() && !n.is_u64() => out.push_str(&python_float(f)),
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::String(s) => write_string(out, s),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, v);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, k);
+                out.push(':');
+                write_canonical(out, v);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    write!(out, "\\u{:04x}", unit).unwrap();
+                }
+            }
+        }
+    }
+    out.push('"');
+}
+
+/// Formats a float as Python's `repr` does, which uses an exponent for very large and very
+/// small numbers, and always has a fractional part otherwise.
+fn python_float(f: f64) -> String {
+    // `{:e}` gives the shortest digits which round-trip, e.g. `1.5e-5`.
+    let formatted = format!("{:e}", f);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..16).contains(&exponent) {
+        let s = f.to_string();
+        if s.contains('.') {
+            s
+        } else {
+            format!("{s}.0")
+        }
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}e{sign}{:02}", exponent.abs())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({
+            "b": [1, 2.5, true, null],
+            "a": { "z": "Ünicode 🦊", "y": "quote\" and \\ and\nnewline" },
+            "c": 1e16,
+            "d": 0.00001,
+            "e": 3.0,
+        });
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"a":{"y":"quote\" and \\ and\nnewline","z":"\u00dcnicode \ud83e\udd8a"},"b":[1,2.5,true,null],"c":1e+16,"d":1e-05,"e":3.0}"#
+        );
+    }
+
+    #[test]
+    fn test_signed_message() {
+        let records = vec![
+            json!({ "id": "b", "last_modified": 2 }),
+            json!({ "id": "c", "last_modified": 3, "deleted": true }),
+            json!({ "id": "a", "last_modified": 1 }),
+        ];
+        assert_eq!(
+            signed_message(&records, 3),
+            b"Content-Signature:\x00{\"data\":[{\"id\":\"a\",\"last_modified\":1},{\"id\":\"b\",\"last_modified\":2}],\"last_modified\":\"3\"}"
+        );
+        assert_eq!(
+            signed_message(&[], 1603992731957),
+            b"Content-Signature:\x00{\"data\":[],\"last_modified\":\"1603992731957\"}"
+        );
+    }
+}
@@ -22,53 +22,95 @@ License, v. 2.0. If a copy of the MPL was not distributed with this
 ///
 ///  See `PlacesDb::begin_interrupt_scope()` and `PlacesApi::new_connection()` for an example of
 ///  how this works.
+///
+/// Shutdown can also be entered in phases, so that, say, syncs and queries are interrupted as
+/// soon as the app starts shutting down, while writes that save its state are left until last.
+/// Interrupts are registered for the `ShutdownPhase` in which they should be interrupted, with
+/// `register_interrupt_for_phase()`, and `SqlInterruptHandle::new_for_phase()` creates handles
+/// whose scopes fail once that phase has started.
 use crate::Interruptee;
 use parking_lot::Mutex;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Weak;
 
 use crate::SqlInterruptHandle;
 
-// Bool that tracks if we're in shutdown mode or not.  We use Ordering::Relaxed to read/write to
-// variable.  It's just a flag so we don't need stronger synchronization guarentees.
-static IN_SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// The phases of shutdown, in the order they start. Starting a phase also starts all the
+/// phases before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownPhase {
+    /// Background work, like syncing, which can be abandoned as soon as shutdown starts.
+    Background = 1,
+    /// Reads, whose results won't be used once the app is shutting down.
+    Read = 2,
+    /// Writes, which are interrupted last. This is the phase of interrupts registered with
+    /// `register_interrupt()`, and starting it enters shutdown mode.
+    Write = 3,
+}
+
+// The last shutdown phase that was started, or 0 if we're not shutting down.  We use
+// Ordering::Relaxed to read/write to variable.  It's just a flag so we don't need stronger
+// synchronization guarentees.
+static SHUTDOWN_PHASE: AtomicU8 = AtomicU8::new(0);
 
-// `SqlInterruptHandle` instances to interrupt when we shutdown
+type RegisteredInterrupt = Weak<dyn AsRef<SqlInterruptHandle> + Send + Sync>;
+
+// `SqlInterruptHandle` instances to interrupt when we shutdown, tagged with their phase
 lazy_static::lazy_static! {
-   static ref REGISTERED_INTERRUPTS: Mutex<Vec<Weak<dyn AsRef<SqlInterruptHandle> + Send + Sync>>> = Mutex::new(Vec::new());
+   static ref REGISTERED_INTERRUPTS: Mutex<Vec<(ShutdownPhase, RegisteredInterrupt)>> = Mutex::new(Vec::new());
 }
 
 /// Initiate shutdown mode
 pub fn shutdown() {
-    IN_SHUTDOWN.store(true, Ordering::Relaxed);
-    for weak in REGISTERED_INTERRUPTS.lock().iter() {
-        if let Some(interrupt) = weak.upgrade() {
-            interrupt.as_ref().as_ref().interrupt()
+    shutdown_phase(ShutdownPhase::Write)
+}
+
+/// Start a shutdown phase, and any phases before it
+///
+/// This interrupts the interrupts registered for those phases, and makes scopes for them fail
+/// from now on.
+pub fn shutdown_phase(phase: ShutdownPhase) {
+    SHUTDOWN_PHASE.fetch_max(phase as u8, Ordering::Relaxed);
+    for (interrupt_phase, weak) in REGISTERED_INTERRUPTS.lock().iter() {
+        if *interrupt_phase <= phase {
+            if let Some(interrupt) = weak.upgrade() {
+                interrupt.as_ref().as_ref().interrupt()
+            }
         }
     }
 }
 
 /// Check if we're currently in shutdown mode
 pub fn in_shutdown() -> bool {
-    IN_SHUTDOWN.load(Ordering::Relaxed)
+    in_shutdown_phase(ShutdownPhase::Write)
+}
+
+/// Check if the given shutdown phase has started
+pub fn in_shutdown_phase(phase: ShutdownPhase) -> bool {
+    SHUTDOWN_PHASE.load(Ordering::Relaxed) >= phase as u8
 }
 
 /// Register a ShutdownInterrupt implementation
 ///
 /// Call this function to ensure that the `SqlInterruptHandle::interrupt()` method will be called
 /// at shutdown.
-pub fn register_interrupt(interrupt: Weak<dyn AsRef<SqlInterruptHandle> + Send + Sync>) {
+pub fn register_interrupt(interrupt: RegisteredInterrupt) {
+    register_interrupt_for_phase(interrupt, ShutdownPhase::Write)
+}
+
+/// Register a ShutdownInterrupt implementation to be interrupted when the given phase starts
+pub fn register_interrupt_for_phase(interrupt: RegisteredInterrupt, phase: ShutdownPhase) {
     // Try to find an existing entry that's been dropped to replace.  This keeps the vector growth
     // in check
     let mut interrupts = REGISTERED_INTERRUPTS.lock();
-    for weak in interrupts.iter_mut() {
-        if weak.strong_count() == 0 {
-            *weak = interrupt;
+    for entry in interrupts.iter_mut() {
+        if entry.1.strong_count() == 0 {
+            *entry = (phase, interrupt);
             return;
         }
     }
     // No empty slots, push the new value
-    interrupts.push(interrupt);
+    interrupts.push((phase, interrupt));
 }
 
 // Implements Interruptee by checking if we've entered shutdown mode
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::{in_shutdown, Interrupted, Interruptee};
+use crate::{in_shutdown_phase, Interrupted, Interruptee, ShutdownPhase};
 use rusqlite::{Connection, InterruptHandle};
 use std::fmt;
 use std::sync::{
@@ -29,23 +29,36 @@ pub struct SqlInterruptHandle {
     // We use Ordering::Relaxed to read/write to this variable.  This is safe because we're
     // basically using it as a flag and don't need stronger synchronization guarentees.
     interrupt_counter: Arc<AtomicUsize>,
+    // The shutdown phase after which we refuse to begin new scopes.
+    shutdown_phase: ShutdownPhase,
 }
 
 impl SqlInterruptHandle {
     #[inline]
     pub fn new(conn: &Connection) -> Self {
+        Self::new_for_phase(conn, ShutdownPhase::Write)
+    }
+
+    /// Create a handle whose scopes fail once the given shutdown phase has started
+    ///
+    /// The handle still needs to be registered with `register_interrupt_for_phase()` for its
+    /// running scopes to be interrupted when the phase starts.
+    #[inline]
+    pub fn new_for_phase(conn: &Connection, shutdown_phase: ShutdownPhase) -> Self {
         Self {
             db_handle: conn.get_interrupt_handle(),
             interrupt_counter: Arc::new(AtomicUsize::new(0)),
+            shutdown_phase,
         }
     }
 
     /// Begin an interrupt scope that will be interrupted by this handle
     ///
-    /// Returns Err(Interrupted) if we're in shutdown mode
+    /// Returns Err(Interrupted) if we're in shutdown mode, or the handle's shutdown phase has
+    /// started
     #[inline]
     pub fn begin_interrupt_scope(&self) -> Result<SqlInterruptScope, Interrupted> {
-        if in_shutdown() {
+        if in_shutdown_phase(self.shutdown_phase) {
             Err(Interrupted)
         } else {
             Ok(SqlInterruptScope::new(Arc::clone(&self.interrupt_counter)))
@@ -60,6 +73,14 @@ impl SqlInterruptHandle {
     }
 }
 
+// Lets a handle be registered for shutdown by itself, rather than through the connection
+// wrapper that owns it.
+impl AsRef<SqlInterruptHandle> for SqlInterruptHandle {
+    fn as_ref(&self) -> &SqlInterruptHandle {
+        self
+    }
+}
+
 impl fmt::Debug for SqlInterruptHandle {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SqlInterruptHandle")
@@ -67,6 +88,7 @@ impl fmt::Debug for SqlInterruptHandle {
                 "interrupt_counter",
                 &self.interrupt_counter.load(Ordering::Relaxed),
             )
+            .field("shutdown_phase", &self.shutdown_phase)
             .finish()
     }
 }
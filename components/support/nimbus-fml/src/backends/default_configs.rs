@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::Value;
+
+use crate::{backends::GeneratedFile, error::Result, intermediate_representation::FeatureManifest};
+
+/// The default configuration of each feature, keyed by feature id, then channel.
+pub(crate) type DefaultConfigs = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Collects the fully resolved defaults of every feature, including imported features,
+/// from the manifest parsed for each channel.
+pub(crate) fn collect_default_configs(
+    manifests: &BTreeMap<String, FeatureManifest>,
+) -> DefaultConfigs {
+    let mut configs = DefaultConfigs::new();
+    for (channel, fm) in manifests {
+        for (_, feature) in fm.iter_all_feature_defs() {
+            configs
+                .entry(feature.name())
+                .or_default()
+                .insert(channel.clone(), feature.default_json());
+        }
+    }
+    configs
+}
+
+/// Renders one JSON file per feature into the `output` directory, e.g. `homescreen.json`,
+/// containing the feature's defaults for each channel.
+///
+/// These are intended as golden fixtures for QA and server-side tooling.
+pub(crate) fn generate_default_configs(
+    manifests: &BTreeMap<String, FeatureManifest>,
+    output: &Path,
+) -> Result<Vec<GeneratedFile>> {
+    collect_default_configs(manifests)
+        .into_iter()
+        .map(|(feature, channels)| {
+            let mut contents = serde_json::to_string_pretty(&channels)?;
+            contents.push('\n');
+            Ok(GeneratedFile::new(
+                output.join(format!("{feature}.json")),
+                contents,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        fixtures::intermediate_representation::get_feature_manifest,
+        intermediate_representation::{FeatureDef, PropDef, TypeRef},
+    };
+
+    fn manifest(title: &str) -> FeatureManifest {
+        get_feature_manifest(
+            vec![],
+            vec![],
+            vec![FeatureDef::new(
+                "homescreen",
+                "",
+                vec![
+                    PropDef::new("title", &TypeRef::String, &json!(title)),
+                    PropDef::new("enabled", &TypeRef::Boolean, &json!(true)),
+                ],
+                false,
+            )],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_default_configs_per_channel() -> Result<()> {
+        let manifests = BTreeMap::from([
+            ("release".to_string(), manifest("Home")),
+            ("nightly".to_string(), manifest("Home (Nightly)")),
+        ]);
+
+        let files = generate_default_configs(&manifests, Path::new("build/configs"))?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("build/configs/homescreen.json"));
+
+        let contents: Value = serde_json::from_str(&files[0].contents)?;
+        assert_eq!(
+            contents,
+            json!({
+                "nightly": { "title": "Home (Nightly)", "enabled": true },
+                "release": { "title": "Home", "enabled": true },
+            })
+        );
+        Ok(())
+    }
+}
@@ -51,7 +51,7 @@ impl FeatureManifest {
     fn create_experimenter_feature(&self, feature: &FeatureDef) -> Result<ExperimenterFeature> {
         Ok(ExperimenterFeature {
             description: feature.doc(),
-            has_exposure: true,
+            has_exposure: feature.has_exposure,
             is_early_startup: None,
             // TODO: Add exposure description to the IR so
             // we can use it here if it's needed
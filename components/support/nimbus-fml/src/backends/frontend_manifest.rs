@@ -4,6 +4,8 @@
 
 use std::collections::BTreeMap;
 
+use indexmap::IndexMap;
+
 use crate::frontend::{
     EnumBody, EnumVariantBody, ExampleBlock, FeatureBody, FeatureFieldBody, FieldBody,
     InlineExampleBlock, ManifestFrontEnd, ObjectBody, Types,
@@ -25,6 +27,7 @@ impl From<FeatureManifest> for ManifestFrontEnd {
             about: Some(about),
             version: "1.0.0".to_string(),
             channels,
+            aliases: Default::default(),
             includes: Default::default(),
             imports: Default::default(),
             features,
@@ -75,6 +78,8 @@ impl From<FeatureDef> for FeatureBody {
             default: None,
             allow_coenrollment: value.allow_coenrollment,
             examples,
+            has_exposure: value.has_exposure,
+            min_version: value.min_version,
         }
     }
 }
@@ -110,7 +115,7 @@ impl From<ObjectDef> for ObjectBody {
 
 impl From<EnumDef> for EnumBody {
     fn from(value: EnumDef) -> Self {
-        let mut variants = BTreeMap::new();
+        let mut variants = IndexMap::new();
         for v in value.variants {
             variants.insert(v.name.clone(), v.into());
         }
@@ -144,6 +149,7 @@ impl From<PropDef> for FeatureFieldBody {
         Self {
             pref_key: value.pref_key.clone(),
             string_alias: value.string_alias.as_ref().map(TypeRef::to_string),
+            required: value.required,
             field: value.into(),
         }
     }
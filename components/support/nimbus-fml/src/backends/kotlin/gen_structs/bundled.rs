@@ -7,8 +7,8 @@ use std::fmt::Display;
 use super::common::{code_type, quoted};
 use crate::backends::{CodeOracle, CodeType, LiteralRenderer, TypeIdentifier, VariablesType};
 use crate::intermediate_representation::{Literal, TypeRef};
+use crate::util::is_resource_id;
 use heck::SnakeCase;
-use unicode_segmentation::UnicodeSegmentation;
 
 pub(crate) struct TextCodeType;
 
@@ -112,17 +112,6 @@ impl CodeType for TextCodeType {
     }
 }
 
-fn is_resource_id(string: &str) -> bool {
-    // In Android apps, resource identifiers are [a-z_][a-z0-9_]*
-    // We don't use the regex crate, so we need some code.
-    let start = "abcdefghijklmnopqrstuvwxyz_";
-    let rest = "abcdefghijklmnopqrstuvwxyz_0123456789";
-    !string.is_empty()
-        && string
-            .grapheme_indices(true)
-            .all(|(i, c)| -> bool { (i > 0 && rest.contains(c)) || start.contains(c) })
-}
-
 pub(crate) struct ImageCodeType;
 
 impl CodeType for ImageCodeType {
@@ -232,6 +221,7 @@ mod unit_tests {
         assert!(!is_resource_id("ok then"));
         assert!(!is_resource_id("ok!"));
         assert!(!is_resource_id("1ok"));
+        assert!(!is_resource_id(""));
 
         Ok(())
     }
@@ -88,9 +88,15 @@ pub fn comment(txt: impl fmt::Display, spaces: &str) -> Result<String, askama::E
 
     let options = Options::new(80)
         .initial_indent(&indent_mid)
-        .subsequent_indent(&indent_mid);
+        .subsequent_indent(&indent_mid)
+        .break_words(false);
 
-    let lines = fill(txt.to_string().as_str(), options);
+    // Blank lines separate paragraphs, but shouldn't leave trailing whitespace.
+    let lines = fill(txt.to_string().as_str(), options)
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
     Ok(format!(
         "{start}\n{lines}\n{indent}",
         start = indent_start,
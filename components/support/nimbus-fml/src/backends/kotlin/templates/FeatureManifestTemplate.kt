@@ -37,7 +37,7 @@ object {{ nimbus_object }} : FeatureManifestInterface<{{ nimbus_object }}.Featur
         {%- for f in self.iter_feature_defs() %}
         {%- let raw_name = f.name() %}
         {%- let class_name = raw_name|class_name %}
-        {{ f.doc()|comment("        ") }}
+        {{ f.doc_with_metadata()|comment("        ") }}
         val {{raw_name|var_name}}: FeatureHolder<{{class_name}}> by lazy {
             FeatureHolder({{ nimbus_object }}.getSdk, {{ raw_name|quoted }}) { variables, prefs ->
                 {{ class_name }}(variables, prefs)
@@ -104,6 +104,17 @@ object {{ nimbus_object }} : FeatureManifestInterface<{{ nimbus_object }}.Featur
             {{- f|quoted }}
             {%- if !loop.last %}, {% endif %}
             {%- endfor %})
+
+    /**
+     * Get the earliest app version supporting each feature, for features which have one.
+     */
+    override fun getFeatureMinVersions(): Map<String, String> =
+        mapOf(
+            {%- for (f, v) in self.fm.get_feature_min_versions() %}
+            {{- f|quoted }} to {{ v|quoted }}
+            {%- if !loop.last %}, {% endif %}
+            {%- endfor %})
+
     /**
      * Accessor object for generated configuration classes extracted from Nimbus, with built-in
      * default values.
@@ -1,7 +1,7 @@
 {%- import "macros.kt" as kt %}
 {%- let inner = self.inner() %}
 
-{{ inner.doc()|comment("") }}
+{{ inner.doc_with_metadata()|comment("") }}
 public class {{ inner.name()|class_name }}  {% call kt::render_constructor() %} : FMLFeatureInterface {
     {% call kt::render_class_body(inner) %}
 
@@ -17,4 +17,16 @@ public class {{ inner.name()|class_name }}  {% call kt::render_constructor() %}
             ).any { prefs.contains(it) }
         } ?: false
     {%- endif %}
+
+    {%- if inner.has_exposure() %}
+
+    /**
+     * Records an exposure event for this feature with the Nimbus SDK.
+     *
+     * Call this when the user is shown the part of the app configured by this feature.
+     */
+    fun recordExposure() {
+        {{ self.fm.about.nimbus_object_name_kt() }}.features.{{ inner.name()|var_name }}.recordExposure()
+    }
+    {%- endif %}
 }
@@ -28,12 +28,21 @@
 //!
 //! Each backend will have its own `filter` module, which is used by the askama templates used in all `CodeType`s and `CodeDeclaration`s.
 //! This filter provides methods to generate expressions and identifiers in the target language. These are all forwarded to the oracle.
+//!
+//! Generators which don't fit this model— or which live outside of this crate— can implement [CodegenBackend] instead,
+//! and be made available to the `generate` command with [register_backend].
 
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
+use crate::error::Result;
+use crate::intermediate_representation::FeatureManifest;
 use crate::intermediate_representation::Literal;
 use crate::intermediate_representation::TypeRef;
 
+pub(crate) use registry::find_backend;
+pub use registry::register_backend;
+
 pub type TypeIdentifier = TypeRef;
 
 /// An object to look up a foreign language code specific renderer for a given type used.
@@ -271,8 +280,61 @@ impl Display for VariablesType {
     }
 }
 
+/// A code generator: takes a validated [FeatureManifest] for a given channel, and renders one or more files.
+///
+/// Implementations are registered with [register_backend] before calling [crate::command_line::do_main], and are
+/// selected with `--language`, or by the extension of the output file.
+///
+/// ```ignore
+/// nimbus_fml::register_backend(ComposePreviewBackend);
+/// nimbus_fml::command_line::do_main(std::env::args_os(), &std::env::current_dir()?)
+/// ```
+pub trait CodegenBackend: Send + Sync {
+    /// The name used to select this backend from the command line, e.g. `compose`.
+    fn language(&self) -> &str;
+
+    /// The file extension of the generated files, e.g. `preview.kt`.
+    ///
+    /// This is used to select this backend when `--language` is not given.
+    fn extension(&self) -> &str;
+
+    /// Renders the files for this manifest.
+    ///
+    /// `output` is the file or directory given on the command line. Backends which produce
+    /// a single file should write to `output` if it is not a directory.
+    ///
+    /// The returned files are written by the caller.
+    fn generate(&self, fm: &FeatureManifest, output: &Path) -> Result<Vec<GeneratedFile>>;
+}
+
+/// A file rendered by a [CodegenBackend].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl GeneratedFile {
+    pub fn new<P: Into<PathBuf>>(path: P, contents: String) -> Self {
+        Self {
+            path: path.into(),
+            contents,
+        }
+    }
+
+    pub(crate) fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, &self.contents)?;
+        Ok(())
+    }
+}
+
+pub(crate) mod default_configs;
 pub(crate) mod experimenter_manifest;
 pub(crate) mod frontend_manifest;
 pub(crate) mod info;
 pub(crate) mod kotlin;
+mod registry;
 pub(crate) mod swift;
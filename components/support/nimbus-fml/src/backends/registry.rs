@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::sync::{Arc, RwLock};
+
+use super::CodegenBackend;
+
+lazy_static::lazy_static! {
+    static ref BACKENDS: RwLock<Vec<Arc<dyn CodegenBackend>>> = RwLock::new(Vec::new());
+}
+
+/// Makes a [CodegenBackend] available to the `generate` command.
+///
+/// Backends registered later take precedence over earlier ones with the same
+/// language or extension. The built-in languages (`kotlin`, `swift`) cannot be
+/// replaced.
+pub fn register_backend<B: CodegenBackend + 'static>(backend: B) {
+    let mut backends = BACKENDS
+        .write()
+        .expect("Backend registry lock has been poisoned");
+    backends.insert(0, Arc::new(backend));
+}
+
+/// Finds a registered backend by its language name or its extension.
+pub(crate) fn find_backend(name: &str) -> Option<Arc<dyn CodegenBackend>> {
+    let name = name.to_ascii_lowercase();
+    let backends = BACKENDS
+        .read()
+        .expect("Backend registry lock has been poisoned");
+    backends
+        .iter()
+        .find(|b| b.language().to_ascii_lowercase() == name || b.extension() == name)
+        .cloned()
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        backends::GeneratedFile, error::Result, intermediate_representation::FeatureManifest,
+    };
+
+    struct FeatureListBackend;
+
+    impl CodegenBackend for FeatureListBackend {
+        fn language(&self) -> &str {
+            "feature-list"
+        }
+
+        fn extension(&self) -> &str {
+            "features.txt"
+        }
+
+        fn generate(&self, fm: &FeatureManifest, output: &Path) -> Result<Vec<GeneratedFile>> {
+            let names = fm
+                .iter_all_feature_defs()
+                .map(|(_, f)| f.name())
+                .collect::<Vec<_>>();
+            Ok(vec![GeneratedFile::new(output, names.join("\n"))])
+        }
+    }
+
+    #[test]
+    fn test_find_registered_backend() -> Result<()> {
+        assert!(find_backend("feature-list").is_none());
+
+        register_backend(FeatureListBackend);
+
+        let backend = find_backend("Feature-List").expect("found by language");
+        assert_eq!(backend.language(), "feature-list");
+
+        let backend = find_backend("features.txt").expect("found by extension");
+        assert_eq!(backend.language(), "feature-list");
+
+        assert!(find_backend("kotlin").is_none());
+        Ok(())
+    }
+}
@@ -73,9 +73,15 @@ pub fn comment(txt: impl fmt::Display, spaces: &str) -> Result<String, askama::E
 
     let options = Options::new(80)
         .initial_indent(&indent1)
-        .subsequent_indent(&indent2);
+        .subsequent_indent(&indent2)
+        .break_words(false);
 
-    let lines = fill(txt.to_string().as_str(), options);
+    // Blank lines separate paragraphs, but shouldn't leave trailing whitespace.
+    let lines = fill(txt.to_string().as_str(), options)
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
     Ok(lines)
 }
 
@@ -2,6 +2,7 @@
 * License, v. 2.0. If a copy of the MPL was not distributed with this
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 use askama::Template;
+use rayon::prelude::*;
 use std::collections::HashSet;
 
 use crate::{
@@ -37,23 +38,25 @@ impl<'a> FeatureManifestDeclaration<'a> {
         }
     }
 
-    pub fn members(&self) -> Vec<Box<dyn CodeDeclaration + 'a>> {
+    pub fn members(&self) -> Vec<Box<dyn CodeDeclaration + Send + 'a>> {
         let fm = self.fm;
 
         fm.iter_feature_defs()
             .map(|inner| {
                 Box::new(feature::FeatureCodeDeclaration::new(fm, inner))
-                    as Box<dyn CodeDeclaration>
+                    as Box<dyn CodeDeclaration + Send>
             })
             .chain(fm.iter_enum_defs().map(|inner| {
-                Box::new(enum_::EnumCodeDeclaration::new(fm, inner)) as Box<dyn CodeDeclaration>
+                Box::new(enum_::EnumCodeDeclaration::new(fm, inner))
+                    as Box<dyn CodeDeclaration + Send>
             }))
             .chain(fm.iter_object_defs().map(|inner| {
-                Box::new(object::ObjectCodeDeclaration::new(fm, inner)) as Box<dyn CodeDeclaration>
+                Box::new(object::ObjectCodeDeclaration::new(fm, inner))
+                    as Box<dyn CodeDeclaration + Send>
             }))
             .chain(fm.iter_imported_files().iter().map(|inner| {
                 Box::new(imports::ImportedModuleInitialization::new(inner))
-                    as Box<dyn CodeDeclaration>
+                    as Box<dyn CodeDeclaration + Send>
             }))
             .collect()
     }
@@ -70,10 +73,12 @@ impl<'a> FeatureManifestDeclaration<'a> {
             .collect()
     }
 
+    /// Each declaration is rendered independently, so they are rendered in parallel, but
+    /// collected in order.
     pub fn declaration_code(&self) -> Vec<String> {
         let oracle = &self.oracle;
         self.members()
-            .into_iter()
+            .into_par_iter()
             .filter_map(|member| member.definition_code(oracle))
             .collect()
     }
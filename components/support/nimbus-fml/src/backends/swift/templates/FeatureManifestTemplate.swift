@@ -55,6 +55,14 @@ public class {{ nimbus_object }} : FeatureManifestInterface {
             {%- endfor %}]
     }
 
+    /// Get the earliest app version supporting each feature, for features which have one.
+    public func getFeatureMinVersions() -> [String: String] {
+        [{%- for (f, v) in self.fm.get_feature_min_versions() %}
+            {{- f|quoted }}: {{ v|quoted }}
+            {%- if !loop.last %}, {% endif %}
+            {%- else %}:{% endfor %}]
+    }
+
     /// Introspection utility method.
     {%- let features = self.iter_feature_defs() %}
     {%- if features.is_empty() %}
@@ -116,7 +124,7 @@ public class {{ features_object }} {
     {%- for f in self.iter_feature_defs() %}
     {%- let raw_name = f.name() %}
     {%- let class_name = raw_name|class_name %}
-    {{ f.doc()|comment("        ") }}
+    {{ f.doc_with_metadata()|comment("        ") }}
     public lazy var {{raw_name|var_name}}: FeatureHolder<{{class_name}}> = {
         FeatureHolder({{ nimbus_object }}.shared.getSdk, featureId: {{ raw_name|quoted }}) { variables, prefs in
             {{ class_name }}(variables, prefs)
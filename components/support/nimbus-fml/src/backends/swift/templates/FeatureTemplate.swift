@@ -1,7 +1,7 @@
 {%- import "macros.swift" as swift %}
 {%- let inner = self.inner() %}
 {%- let class_name = inner.name()|class_name -%}
-{% call swift::render_class(inner) %}
+{% call swift::render_class(inner, inner.doc_with_metadata()) %}
 
 {%- if inner.has_prefs() %}
 
@@ -26,3 +26,15 @@ extension {{ class_name }}: FMLFeatureInterface {
 {%- else %}
 extension {{ class_name }}: FMLFeatureInterface {}
 {%- endif %}
+
+{%- if inner.has_exposure() %}
+
+public extension {{ class_name }} {
+    /// Records an exposure event for this feature with the Nimbus SDK.
+    ///
+    /// Call this when the user is shown the part of the app configured by this feature.
+    func recordExposure() {
+        {{ self.fm.about.nimbus_object_name_swift() }}.shared.features.{{ inner.name()|var_name }}.recordExposure()
+    }
+}
+{%- endif %}
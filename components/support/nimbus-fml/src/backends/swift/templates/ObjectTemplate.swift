@@ -1,6 +1,6 @@
 {%- import "macros.swift" as swift %}
 {%- let inner = self.inner() %}
-{% call swift::render_class(inner) -%}
+{% call swift::render_class(inner, inner.doc()) -%}
 {%- let class_name = inner.name()|class_name %}
 
 public extension {{class_name}} {
@@ -8,11 +8,11 @@
     and rendering literals for Objects.
 -#}
 
-{%- macro render_class(inner) %}
+{%- macro render_class(inner, doc) %}
 {%- let raw_name = inner.name() %}
 {% let class_name = inner.name()|class_name -%}
 
-{{ inner.doc()|comment("") }}
+{{ doc|comment("") }}
 public class {{class_name}}: FMLObjectInterface {
     private let _variables: Variables
     private let _defaults: Defaults
@@ -22,6 +22,7 @@ impl From<FmlLoaderConfig> for LoaderConfig {
             refs: value.refs.into_iter().collect(),
             repo_files: value.ref_files,
             cache_dir: cache,
+            offline: false,
         }
     }
 }
@@ -2,7 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use crate::diagnostics::ReportFormat;
 use crate::intermediate_representation::TargetLanguage;
+use crate::lint::LintConfig;
 use crate::util::loaders::LoaderConfig;
 use anyhow::{bail, Error, Result};
 use std::path::Path;
@@ -10,8 +12,11 @@ use std::path::PathBuf;
 
 pub(crate) enum CliCmd {
     Generate(GenerateStructCmd),
+    GenerateFromConfig(Vec<GenerateStructCmd>),
     GenerateExperimenter(GenerateExperimenterManifestCmd),
     GenerateSingleFileManifest(GenerateSingleFileManifestCmd),
+    GenerateDefaultConfigs(GenerateDefaultConfigsCmd),
+    ImportDesktop(ImportDesktopCmd),
     FetchFile(LoaderConfig, String),
     Validate(ValidateCmd),
     PrintChannels(PrintChannelsCmd),
@@ -43,9 +48,26 @@ pub(crate) struct GenerateSingleFileManifestCmd {
     pub(crate) loader: LoaderConfig,
 }
 
+pub(crate) struct GenerateDefaultConfigsCmd {
+    pub(crate) manifest: String,
+    pub(crate) output: PathBuf,
+    pub(crate) loader: LoaderConfig,
+}
+
+pub(crate) struct ImportDesktopCmd {
+    pub(crate) manifest: String,
+    pub(crate) output: PathBuf,
+    pub(crate) loader: LoaderConfig,
+}
+
 pub(crate) struct ValidateCmd {
     pub(crate) manifest: String,
     pub(crate) loader: LoaderConfig,
+    pub(crate) report: bool,
+    pub(crate) format: ReportFormat,
+    pub(crate) android_res: Option<PathBuf>,
+    pub(crate) ios_assets: Option<PathBuf>,
+    pub(crate) lint: LintConfig,
 }
 
 pub(crate) struct PrintChannelsCmd {
@@ -76,11 +98,26 @@ impl TryFrom<&std::ffi::OsStr> for TargetLanguage {
 impl TryFrom<&Path> for TargetLanguage {
     type Error = Error;
     fn try_from(value: &Path) -> Result<Self> {
-        TryFrom::try_from(
-            value
-                .extension()
-                .ok_or_else(|| anyhow::anyhow!("No extension available to determine language"))?,
-        )
+        let name = value
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        // Extensions may have more than one part, e.g. `fml.json` or a backend's `preview.kt`,
+        // so try the longest one first, falling back to the last extension.
+        let mut extensions = name
+            .match_indices('.')
+            .filter(|(i, _)| *i > 0)
+            .map(|(i, _)| &name[i + 1..])
+            .peekable();
+        while let Some(extension) = extensions.next() {
+            if extensions.peek().is_none() {
+                return TryFrom::try_from(extension);
+            }
+            if let Ok(language) = TargetLanguage::try_from(extension) {
+                return Ok(language);
+            }
+        }
+        bail!("No extension available to determine language")
     }
 }
 
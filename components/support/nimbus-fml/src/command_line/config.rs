@@ -0,0 +1,139 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use super::{commands::GenerateStructCmd, RELEASE_CHANNEL};
+use crate::{
+    intermediate_representation::TargetLanguage, lint::LintConfig, util::loaders::LoaderConfig,
+};
+
+/// A project configuration file, usually `nimbus-fml.yaml`, describing everything that
+/// should be generated from a manifest, so a single `generate --config` can generate
+/// the code for every platform.
+///
+/// ```yaml
+/// manifest: app.fml.yaml
+/// channel: release
+/// cache-dir: build/fml-cache
+/// targets:
+///   - language: kotlin
+///     output: android/src/main/java/generated/FxNimbus.kt
+///   - language: swift
+///     channel: beta
+///     output: ios/Generated/FxNimbus.swift
+/// ```
+///
+/// A file with a `.toml` extension is read as TOML instead, with the same keys:
+///
+/// ```toml
+/// manifest = "app.fml.yaml"
+/// channel = "release"
+///
+/// [[targets]]
+/// language = "kotlin"
+/// output = "android/src/main/java/generated/FxNimbus.kt"
+/// ```
+///
+/// Paths are relative to the directory containing the configuration file.
+///
+/// The same file configures the lint rules used by `validate --config`, in a `lint` section;
+/// see [LintConfig].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ProjectConfig {
+    pub(crate) manifest: String,
+    /// The channel used by targets which don't specify their own.
+    #[serde(default)]
+    pub(crate) channel: Option<String>,
+    #[serde(default)]
+    pub(crate) cache_dir: Option<String>,
+    #[serde(default)]
+    pub(crate) repo_files: Vec<String>,
+    #[serde(default)]
+    pub(crate) refs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub(crate) targets: Vec<TargetConfig>,
+    #[serde(default)]
+    pub(crate) lint: LintConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TargetConfig {
+    pub(crate) language: String,
+    pub(crate) output: String,
+    #[serde(default)]
+    pub(crate) channel: Option<String>,
+}
+
+impl ProjectConfig {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Can't read the config file {}", path.display()))?;
+        let is_toml = path.extension().map_or(false, |ext| ext == "toml");
+        if is_toml {
+            toml::from_str(&contents)
+                .with_context(|| format!("Can't parse the config file {}", path.display()))
+        } else {
+            serde_yaml::from_str(&contents)
+                .with_context(|| format!("Can't parse the config file {}", path.display()))
+        }
+    }
+
+    /// The manifest, relative to `dir`.
+    pub(crate) fn manifest(&self, dir: &Path) -> String {
+        dir.join(&self.manifest).display().to_string()
+    }
+
+    pub(crate) fn loader(&self, dir: &Path, offline: bool) -> LoaderConfig {
+        LoaderConfig {
+            cwd: dir.to_path_buf(),
+            cache_dir: self.cache_dir.as_ref().map(|d| dir.join(d)),
+            offline,
+            repo_files: self.repo_files.clone(),
+            refs: self.refs.clone(),
+        }
+    }
+
+    /// Creates a generate command for each target, resolving paths relative to `dir`.
+    pub(crate) fn generate_commands(
+        &self,
+        dir: &Path,
+        offline: bool,
+    ) -> Result<Vec<GenerateStructCmd>> {
+        if self.targets.is_empty() {
+            bail!("The config file has no targets to generate");
+        }
+        let loader = self.loader(dir, offline);
+        let manifest = self.manifest(dir);
+        let load_from_ir = matches!(
+            TargetLanguage::from_extension(&manifest),
+            Ok(TargetLanguage::ExperimenterJSON)
+        );
+
+        self.targets
+            .iter()
+            .map(|target| {
+                let channel = target
+                    .channel
+                    .as_ref()
+                    .or(self.channel.as_ref())
+                    .map_or(RELEASE_CHANNEL, String::as_str);
+                Ok(GenerateStructCmd {
+                    manifest: manifest.clone(),
+                    output: dir.join(&target.output),
+                    language: target.language.as_str().try_into()?,
+                    load_from_ir,
+                    channel: channel.to_string(),
+                    loader: loader.clone(),
+                })
+            })
+            .collect()
+    }
+}
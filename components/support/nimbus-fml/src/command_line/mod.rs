@@ -3,15 +3,18 @@
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 pub(crate) mod commands;
+mod config;
 mod workflows;
 
+use crate::diagnostics::ReportFormat;
 use crate::intermediate_representation::TargetLanguage;
-use crate::util::loaders::LoaderConfig;
+use crate::util::{loaders::LoaderConfig, par_try_map};
 use anyhow::{bail, Result};
 use clap::{App, ArgMatches};
 use commands::{
-    CliCmd, GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd, GenerateStructCmd,
-    PrintChannelsCmd, ValidateCmd,
+    CliCmd, GenerateDefaultConfigsCmd, GenerateExperimenterManifestCmd,
+    GenerateSingleFileManifestCmd, GenerateStructCmd, ImportDesktopCmd, PrintChannelsCmd,
+    ValidateCmd,
 };
 
 use std::{
@@ -21,6 +24,7 @@ use std::{
 };
 
 use self::commands::PrintInfoCmd;
+use self::config::ProjectConfig;
 
 const RELEASE_CHANNEL: &str = "release";
 
@@ -36,10 +40,16 @@ where
 fn process_command(cmd: &CliCmd) -> Result<()> {
     match cmd {
         CliCmd::Generate(params) => workflows::generate_struct(params)?,
+        CliCmd::GenerateFromConfig(cmds) => {
+            // Each target writes its own output, so they can be generated in parallel.
+            par_try_map(cmds, workflows::generate_struct)?;
+        }
         CliCmd::GenerateExperimenter(params) => workflows::generate_experimenter_manifest(params)?,
         CliCmd::GenerateSingleFileManifest(params) => {
             workflows::generate_single_file_manifest(params)?
         }
+        CliCmd::GenerateDefaultConfigs(params) => workflows::generate_default_configs(params)?,
+        CliCmd::ImportDesktop(params) => workflows::import_desktop(params)?,
         CliCmd::FetchFile(files, nm) => workflows::fetch_file(files, nm)?,
         CliCmd::Validate(params) => workflows::validate(params)?,
         CliCmd::PrintChannels(params) => workflows::print_channels(params)?,
@@ -57,12 +67,21 @@ where
     let matches = App::from_yaml(yaml).get_matches_from(args);
 
     Ok(match matches.subcommand() {
+        ("generate", Some(matches)) if matches.is_present("config") => {
+            CliCmd::GenerateFromConfig(create_generate_commands_from_config(matches, cwd)?)
+        }
         ("generate", Some(matches)) => {
             CliCmd::Generate(create_generate_command_from_cli(matches, cwd)?)
         }
         ("generate-experimenter", Some(matches)) => CliCmd::GenerateExperimenter(
             create_generate_command_experimenter_from_cli(matches, cwd)?,
         ),
+        ("default-configs", Some(matches)) => {
+            CliCmd::GenerateDefaultConfigs(create_default_configs_from_cli(matches, cwd)?)
+        }
+        ("import-desktop", Some(matches)) => {
+            CliCmd::ImportDesktop(create_import_desktop_from_cli(matches, cwd)?)
+        }
         ("fetch", Some(matches)) => {
             CliCmd::FetchFile(create_loader(matches, cwd)?, input_file(matches)?)
         }
@@ -100,6 +119,31 @@ fn create_single_file_from_cli(
     })
 }
 
+fn create_default_configs_from_cli(
+    matches: &ArgMatches,
+    cwd: &Path,
+) -> Result<GenerateDefaultConfigsCmd> {
+    let manifest = input_file(matches)?;
+    let output = file_path("OUTPUT", matches, cwd)?;
+    let loader = create_loader(matches, cwd)?;
+    Ok(GenerateDefaultConfigsCmd {
+        manifest,
+        output,
+        loader,
+    })
+}
+
+fn create_import_desktop_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<ImportDesktopCmd> {
+    let manifest = input_file(matches)?;
+    let output = file_path("OUTPUT", matches, cwd)?;
+    let loader = create_loader(matches, cwd)?;
+    Ok(ImportDesktopCmd {
+        manifest,
+        output,
+        loader,
+    })
+}
+
 fn create_generate_command_experimenter_from_cli(
     matches: &ArgMatches,
     cwd: &Path,
@@ -122,6 +166,16 @@ fn create_generate_command_experimenter_from_cli(
     Ok(cmd)
 }
 
+fn create_generate_commands_from_config(
+    matches: &ArgMatches,
+    cwd: &Path,
+) -> Result<Vec<GenerateStructCmd>> {
+    let path = file_path("config", matches, cwd)?;
+    let dir = path.parent().unwrap_or(cwd);
+    let offline = matches.is_present("offline");
+    ProjectConfig::load(&path)?.generate_commands(dir, offline)
+}
+
 fn create_generate_command_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<GenerateStructCmd> {
     let manifest = input_file(matches)?;
     let load_from_ir = matches!(
@@ -131,7 +185,7 @@ fn create_generate_command_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<
     let output =
         file_path("output", matches, cwd).or_else(|_| file_path("OUTPUT", matches, cwd))?;
     let language = match matches.value_of("language") {
-        Some(s) => TargetLanguage::try_from(s)?, // the language from the cli will always be recognized
+        Some(s) => TargetLanguage::try_from(s)?,
         None => output.as_path().try_into().map_err(|_| anyhow::anyhow!("Can't infer a target language from the file or directory, so specify a --language flag explicitly"))?,
     };
     let channel = matches
@@ -155,6 +209,7 @@ fn create_loader(matches: &ArgMatches, cwd: &Path) -> Result<LoaderConfig> {
         .value_of("cache-dir")
         .map(|f| Some(cwd.join(f)))
         .unwrap_or_default();
+    let offline = matches.is_present("offline");
 
     let files = matches.values_of("repo-file").unwrap_or_default();
     let repo_files = files.into_iter().map(|s| s.to_string()).collect();
@@ -171,6 +226,7 @@ fn create_loader(matches: &ArgMatches, cwd: &Path) -> Result<LoaderConfig> {
 
     Ok(LoaderConfig {
         cache_dir,
+        offline,
         repo_files,
         cwd,
         refs,
@@ -178,9 +234,39 @@ fn create_loader(matches: &ArgMatches, cwd: &Path) -> Result<LoaderConfig> {
 }
 
 fn create_validate_command_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<ValidateCmd> {
-    let manifest = input_file(matches)?;
-    let loader = create_loader(matches, cwd)?;
-    Ok(ValidateCmd { manifest, loader })
+    let (manifest, loader, lint) = if matches.is_present("config") {
+        let path = file_path("config", matches, cwd)?;
+        let dir = path.parent().unwrap_or(cwd);
+        let config = ProjectConfig::load(&path)?;
+        let loader = config.loader(dir, matches.is_present("offline"));
+        (config.manifest(dir), loader, config.lint)
+    } else {
+        let manifest = input_file(matches)?;
+        (manifest, create_loader(matches, cwd)?, Default::default())
+    };
+    let format = match matches.value_of("format") {
+        Some(f) => f.try_into()?,
+        None => Default::default(),
+    };
+    // The machine-readable formats always report every error.
+    let report = matches.is_present("report") || format != ReportFormat::Text;
+    let android_res = matches
+        .is_present("android-res")
+        .then(|| file_path("android-res", matches, cwd))
+        .transpose()?;
+    let ios_assets = matches
+        .is_present("ios-assets")
+        .then(|| file_path("ios-assets", matches, cwd))
+        .transpose()?;
+    Ok(ValidateCmd {
+        manifest,
+        loader,
+        report,
+        format,
+        android_res,
+        ios_assets,
+        lint,
+    })
 }
 
 fn create_print_channels_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<PrintChannelsCmd> {
@@ -233,6 +319,7 @@ mod cli_tests {
     use std::env;
 
     use super::*;
+    use crate::lint::LintLevel;
 
     const FML_BIN: &str = "nimbus-fml";
     const TEST_FILE: &str = "fixtures/fe/importing/simple/app.yaml";
@@ -400,6 +487,103 @@ mod cli_tests {
         Ok(())
     }
 
+    ///////////////////////////////////////////////////////////////////////////
+    #[test]
+    fn test_cli_generate_from_config() -> Result<()> {
+        let cwd = package_dir()?;
+        let cmd = get_command_from_cli(
+            [
+                FML_BIN,
+                "generate",
+                "--config",
+                "fixtures/config/nimbus-fml.yaml",
+                "--offline",
+            ],
+            &cwd,
+        )?;
+
+        assert!(matches!(cmd, CliCmd::GenerateFromConfig(_)));
+
+        if let CliCmd::GenerateFromConfig(cmds) = cmd {
+            assert_eq!(cmds.len(), 2);
+            let (kotlin, swift) = (&cmds[0], &cmds[1]);
+
+            assert_eq!(kotlin.language, TargetLanguage::Kotlin);
+            assert_eq!(kotlin.channel, "debug");
+            assert!(kotlin.output.ends_with("build/config-test/AppNimbus.kt"));
+
+            assert_eq!(swift.language, TargetLanguage::Swift);
+            assert_eq!(swift.channel, "release");
+            assert!(swift.output.ends_with("build/config-test/AppNimbus.swift"));
+
+            for cmd in &cmds {
+                assert!(cmd.manifest.ends_with("fe/importing/simple/app.yaml"));
+                assert!(cmd.loader.offline);
+                assert!(cmd
+                    .loader
+                    .cache_dir
+                    .as_ref()
+                    .unwrap()
+                    .ends_with("build/cache"));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_generate_from_toml_config() -> Result<()> {
+        let cwd = package_dir()?;
+        let cmd = get_command_from_cli(
+            [
+                FML_BIN,
+                "generate",
+                "--config",
+                "fixtures/config/nimbus-fml.toml",
+            ],
+            &cwd,
+        )?;
+
+        if let CliCmd::GenerateFromConfig(cmds) = cmd {
+            assert_eq!(cmds.len(), 2);
+            assert_eq!(cmds[0].language, TargetLanguage::Kotlin);
+            assert_eq!(cmds[0].channel, "debug");
+            assert_eq!(cmds[1].language, TargetLanguage::Swift);
+            assert_eq!(cmds[1].channel, "release");
+            assert!(cmds[1]
+                .output
+                .ends_with("build/config-test/AppNimbus.swift"));
+        } else {
+            panic!("Expected commands generated from the config");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_validate_from_config() -> Result<()> {
+        let cwd = package_dir()?;
+        let cmd = get_command_from_cli(
+            [
+                FML_BIN,
+                "validate",
+                "--config",
+                "fixtures/config/nimbus-fml.yaml",
+            ],
+            &cwd,
+        )?;
+
+        assert!(matches!(cmd, CliCmd::Validate(_)));
+
+        if let CliCmd::Validate(cmd) = cmd {
+            assert!(cmd.manifest.ends_with("fe/importing/simple/app.yaml"));
+            assert!(cmd.loader.cache_dir.unwrap().ends_with("build/cache"));
+            assert_eq!(cmd.lint.feature_names, LintLevel::Error);
+            assert_eq!(cmd.lint.variable_names, LintLevel::Warn);
+            assert_eq!(cmd.lint.descriptions, LintLevel::Warn);
+            assert_eq!(cmd.lint.max_nesting_depth, 3);
+        }
+        Ok(())
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     #[test]
     fn test_cli_generate_experimenter_android() -> Result<()> {
@@ -628,4 +812,21 @@ mod cli_tests {
         );
         Ok(())
     }
+
+    ///////////////////////////////////////////////////////////////////////////
+    #[test]
+    fn test_language_from_compound_extension() -> Result<()> {
+        let language: TargetLanguage = Path::new("build/app.fml.json").try_into()?;
+        assert_eq!(language, TargetLanguage::IR);
+
+        let language: TargetLanguage = Path::new("build/app.experimenter.yaml").try_into()?;
+        assert_eq!(language, TargetLanguage::ExperimenterYAML);
+
+        let language: TargetLanguage = Path::new("build/Features.kt").try_into()?;
+        assert_eq!(language, TargetLanguage::Kotlin);
+
+        let language: Result<TargetLanguage> = Path::new("build/generated").try_into();
+        assert!(language.is_err());
+        Ok(())
+    }
 }
@@ -3,19 +3,27 @@
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use glob::MatchOptions;
-use std::collections::HashSet;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
 
 use super::commands::{
-    GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd, GenerateStructCmd,
-    PrintChannelsCmd, PrintInfoCmd, ValidateCmd,
+    GenerateDefaultConfigsCmd, GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd,
+    GenerateStructCmd, ImportDesktopCmd, PrintChannelsCmd, PrintInfoCmd, ValidateCmd,
 };
 use crate::backends::info::ManifestInfo;
+use crate::defaults::BundledResources;
+use crate::diagnostics::{
+    ReportFormat, Severity, ValidationReport, LOAD_RULE, MANIFEST_GROUP, METADATA_RULE,
+    RESOURCES_RULE,
+};
 use crate::error::FMLError::CliError;
 use crate::frontend::ManifestFrontEnd;
+use crate::importers::desktop::{import_desktop_manifest, DesktopManifest};
+use crate::util::par_try_map;
 use crate::{
     backends,
     error::{FMLError, Result},
-    intermediate_representation::{FeatureManifest, TargetLanguage},
+    intermediate_representation::{FeatureDef, FeatureManifest, TargetLanguage},
     parser::Parser,
     util::loaders::{FileLoader, FilePath, LoaderConfig},
 };
@@ -91,15 +99,25 @@ fn generate_struct_from_ir(ir: &FeatureManifest, cmd: &GenerateStructCmd) -> Res
     ir.validate_manifest_for_lang(language)?;
     match language {
         TargetLanguage::IR => {
-            let contents = serde_json::to_string_pretty(&ir)?;
+            let contents = serde_json::to_string_pretty(&ir.to_versioned_json()?)?;
             std::fs::write(&cmd.output, contents)?;
         }
         TargetLanguage::Kotlin => backends::kotlin::generate_struct(ir, cmd)?,
         TargetLanguage::Swift => backends::swift::generate_struct(ir, cmd)?,
-        _ => unimplemented!(
-            "Unsupported output language for structs: {}",
-            language.extension()
-        ),
+        TargetLanguage::Custom { language, .. } => {
+            let backend = backends::find_backend(language).ok_or_else(|| {
+                FMLError::CliError(format!("No backend registered for {language}"))
+            })?;
+            for file in backend.generate(ir, &cmd.output)? {
+                file.write()?;
+            }
+        }
+        _ => {
+            return Err(CliError(format!(
+                "Unsupported output language for structs: {}",
+                language.extension()
+            )))
+        }
     };
     Ok(())
 }
@@ -121,6 +139,47 @@ pub(crate) fn generate_single_file_manifest(cmd: &GenerateSingleFileManifestCmd)
     Ok(())
 }
 
+pub(crate) fn generate_default_configs(cmd: &GenerateDefaultConfigsCmd) -> Result<()> {
+    let files: FileLoader = TryFrom::try_from(&cmd.loader)?;
+    let path = files.file_path(&cmd.manifest)?;
+    let channels = Parser::load_frontend(files.clone(), &cmd.manifest)?.channels();
+    if channels.is_empty() {
+        return Err(CliError(format!(
+            "{path} has no channels to generate default configurations for"
+        )));
+    }
+
+    let manifests = par_try_map(channels, |channel| -> Result<_> {
+        let fm = load_feature_manifest(files.clone(), path.clone(), false, Some(&channel))?;
+        Ok((channel, fm))
+    })?;
+    let manifests: BTreeMap<_, _> = manifests.into_iter().collect();
+
+    for file in backends::default_configs::generate_default_configs(&manifests, &cmd.output)? {
+        file.write()?;
+    }
+    Ok(())
+}
+
+pub(crate) fn import_desktop(cmd: &ImportDesktopCmd) -> Result<()> {
+    let files: FileLoader = TryFrom::try_from(&cmd.loader)?;
+    let path = files.file_path(&cmd.manifest)?;
+    let desktop: DesktopManifest = files.read(&path)?;
+    let (manifest, warnings) = import_desktop_manifest(&desktop);
+
+    let term = Term::stderr();
+    for warning in &warnings {
+        output_warn(&term, "Not imported", warning)?;
+    }
+    // Check that what we've generated is a valid manifest before writing it.
+    manifest
+        .get_intermediate_representation(&Default::default(), None)?
+        .validate_manifest()?;
+
+    std::fs::write(&cmd.output, serde_yaml::to_string(&manifest)?)?;
+    Ok(())
+}
+
 fn load_feature_manifest(
     files: FileLoader,
     path: FilePath,
@@ -131,7 +190,7 @@ fn load_feature_manifest(
         let parser: Parser = Parser::new(files, path)?;
         parser.get_intermediate_representation(channel)?
     } else {
-        files.read::<FeatureManifest>(&path)?
+        FeatureManifest::from_versioned_json(files.read(&path)?)?
     };
     ir.validate_manifest()?;
     Ok(ir)
@@ -175,10 +234,13 @@ pub(crate) fn validate(cmd: &ValidateCmd) -> Result<()> {
     let term = Term::stdout();
 
     let files: FileLoader = TryFrom::try_from(&cmd.loader)?;
+    if cmd.report {
+        return validate_with_report(&term, &files, cmd);
+    }
 
     let filename = &cmd.manifest;
     let file_path = files.file_path(filename)?;
-    let parser: Parser = Parser::new(files, file_path.clone())?;
+    let parser: Parser = Parser::new(files.clone(), file_path.clone())?;
     let mut loading = HashSet::new();
     let manifest_front_end = parser.load_manifest(&file_path, &mut loading)?;
 
@@ -201,6 +263,7 @@ pub(crate) fn validate(cmd: &ValidateCmd) -> Result<()> {
         ))?;
         return Ok(());
     }
+    let resources = load_bundled_resources(cmd)?;
     let intermediate_representation =
         parser.get_intermediate_representation(None).map_err(|e| {
             output_err(&term, "Manifest is invalid", &e.to_string()).unwrap();
@@ -226,17 +289,7 @@ pub(crate) fn validate(cmd: &ValidateCmd) -> Result<()> {
     term.write_line("Validating feature metadata:")?;
     let mut features_with_warnings = 0;
     for (_, f) in intermediate_representation.iter_all_feature_defs() {
-        let fm = &f.metadata;
-        let mut missing = vec![];
-        if fm.meta_bug.is_none() {
-            missing.push("'meta-bug'");
-        }
-        if fm.documentation.is_empty() {
-            missing.push("'documentation'");
-        }
-        if fm.contacts.is_empty() {
-            missing.push("'contacts'");
-        }
+        let missing = missing_metadata(f);
         if !missing.is_empty() {
             output_warn(
                 &term,
@@ -267,14 +320,36 @@ pub(crate) fn validate(cmd: &ValidateCmd) -> Result<()> {
         ))?;
     }
 
+    term.write_line("Linting manifest:")?;
+    let lints = intermediate_representation.lint(&cmd.lint);
+    let mut lint_errors = 0;
+    for l in &lints {
+        match l.severity {
+            Severity::Error => {
+                lint_errors += 1;
+                output_err(&term, &l.group, &l.message)?
+            }
+            Severity::Warning => output_warn(&term, &l.group, &l.message)?,
+        }
+    }
+    if lints.is_empty() {
+        output_ok(&term, "No lint warnings\n")?;
+    } else {
+        term.write_line("")?;
+    }
+
     term.write_line("Validating manifest for different channels:")?;
 
     let results = channels
-        .iter()
+        .par_iter()
         .map(|c| {
             let intermediate_representation = parser.get_intermediate_representation(Some(c));
             match intermediate_representation {
-                Ok(ir) => (c, ir.validate_manifest()),
+                Ok(ir) => (
+                    c,
+                    ir.validate_manifest()
+                        .and_then(|_| validate_bundled_resources(&ir, &resources)),
+                ),
                 Err(e) => (c, Err(e)),
             }
         })
@@ -301,6 +376,183 @@ pub(crate) fn validate(cmd: &ValidateCmd) -> Result<()> {
         )));
     }
 
+    if lint_errors > 0 {
+        return Err(CliError(format!(
+            "Manifest contains {lint_errors} lint error(s)"
+        )));
+    }
+
+    Ok(())
+}
+
+fn load_bundled_resources(cmd: &ValidateCmd) -> Result<Vec<BundledResources>> {
+    let mut resources = Vec::new();
+    if let Some(dir) = &cmd.android_res {
+        resources.push(BundledResources::from_android_res(dir)?);
+    }
+    if let Some(dir) = &cmd.ios_assets {
+        resources.push(BundledResources::from_ios_assets(dir)?);
+    }
+    Ok(resources)
+}
+
+fn validate_bundled_resources(ir: &FeatureManifest, resources: &[BundledResources]) -> Result<()> {
+    for r in resources {
+        if let Some((_, e)) = ir.get_resource_errors(r).into_iter().next() {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+fn missing_metadata(f: &FeatureDef) -> Vec<&'static str> {
+    let fm = &f.metadata;
+    let mut missing = vec![];
+    if fm.meta_bug.is_none() {
+        missing.push("'meta-bug'");
+    }
+    if fm.documentation.is_empty() {
+        missing.push("'documentation'");
+    }
+    if fm.contacts.is_empty() {
+        missing.push("'contacts'");
+    }
+    missing
+}
+
+/// Collects every problem found in the manifest for one channel.
+fn validate_channel(
+    parser: &Parser,
+    channel: &str,
+    resources: &[BundledResources],
+    cmd: &ValidateCmd,
+) -> ValidationReport {
+    let mut channel_report = ValidationReport::default();
+    match parser.get_intermediate_representation(Some(channel)) {
+        Ok(ir) => {
+            channel_report = ir.validation_report();
+            for r in resources {
+                for (group, e) in ir.get_resource_errors(r) {
+                    channel_report.add(RESOURCES_RULE, &group, e);
+                }
+            }
+            for (_, f) in ir.iter_all_feature_defs() {
+                let missing = missing_metadata(f);
+                if !missing.is_empty() {
+                    channel_report.add_warning(
+                        METADATA_RULE,
+                        &format!("features/{}", f.name),
+                        &format!("Missing metadata: {}", missing.join(", ")),
+                    );
+                }
+            }
+            channel_report.add_lints(&ir.id, ir.lint(&cmd.lint));
+        }
+        Err(e) => channel_report.add(LOAD_RULE, MANIFEST_GROUP, e),
+    }
+    channel_report
+}
+
+/// Validates every channel, collecting all the errors rather than stopping at the first,
+/// then prints them grouped by the feature or object they were found in.
+///
+/// Problems loading the manifest or the bundled resources are included in the report too.
+/// For the JSON and SARIF formats, only the report is printed to stdout.
+fn validate_with_report(term: &Term, files: &FileLoader, cmd: &ValidateCmd) -> Result<()> {
+    let format = cmd.format;
+    let is_text = format == ReportFormat::Text;
+
+    let mut report = ValidationReport::default();
+    let loaded = files.file_path(&cmd.manifest).and_then(|file_path| {
+        let parser = Parser::new(files.clone(), file_path.clone())?;
+        let channels = parser
+            .load_manifest(&file_path, &mut HashSet::new())?
+            .channels();
+        Ok((parser, channels))
+    });
+    let (parser, channels) = match loaded {
+        Ok((parser, channels)) => (Some(parser), channels),
+        Err(e) => {
+            report.add(LOAD_RULE, MANIFEST_GROUP, e);
+            (None, Default::default())
+        }
+    };
+    let resources = load_bundled_resources(cmd).unwrap_or_else(|e| {
+        report.add(LOAD_RULE, MANIFEST_GROUP, e);
+        Default::default()
+    });
+
+    if is_text && parser.is_some() {
+        if channels.is_empty() {
+            output_ok(term, "The manifest is valid for including in other files")?;
+        } else {
+            term.write_line("Validating manifest for different channels:")?;
+        }
+    }
+
+    // The channels are validated in parallel, then reported in order.
+    let channel_reports = match &parser {
+        Some(parser) => channels
+            .par_iter()
+            .map(|channel| (channel, validate_channel(parser, channel, &resources, cmd)))
+            .collect::<Vec<_>>(),
+        None => Default::default(),
+    };
+
+    for (channel, channel_report) in channel_reports {
+        if is_text {
+            match channel_report.error_count() {
+                0 => output_ok(term, &format!("{channel:.<20}valid"))?,
+                n => output_err(
+                    term,
+                    &format!("{channel:.<20}invalid"),
+                    &format!("{n} error(s)"),
+                )?,
+            }
+        }
+        report.merge(channel, channel_report);
+    }
+    report.find_lines(files);
+
+    match format {
+        ReportFormat::Text => {
+            for (group, diagnostics) in report.grouped() {
+                term.write_line(&format!("\n{group}:"))?;
+                for d in diagnostics {
+                    let mut title = if d.channels.len() == channels.len() {
+                        "all channels".to_string()
+                    } else {
+                        d.channels.iter().cloned().collect::<Vec<_>>().join(", ")
+                    };
+                    if let (Some(file), Some(line)) = (&d.file, d.line) {
+                        title = format!("{file}:{line} ({title})");
+                    }
+                    match d.severity {
+                        Severity::Error => output_err(term, &title, &d.message)?,
+                        Severity::Warning => output_warn(term, &title, &d.message)?,
+                    }
+                }
+            }
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        ReportFormat::Sarif => println!("{}", serde_json::to_string_pretty(&report.to_sarif())?),
+    }
+
+    let error_count = report.error_count();
+    if error_count > 0 {
+        let groups = report
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| &d.group)
+            .collect::<HashSet<_>>();
+        return Err(CliError(format!(
+            "Manifest contains {} error(s) in {} feature(s) or object(s)",
+            error_count,
+            groups.len()
+        )));
+    }
+
     Ok(())
 }
 
@@ -346,6 +598,7 @@ mod test {
     use crate::backends::experimenter_manifest::ExperimenterManifest;
     use crate::backends::{kotlin, swift};
     use crate::frontend::AboutBlock;
+    use crate::lint::{LintConfig, LintLevel};
     use crate::util::{generated_src_dir, join, pkg_dir};
 
     const MANIFEST_PATHS: &[&str] = &[
@@ -494,6 +747,57 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_generate_default_configs() -> Result<()> {
+        let output = join(generated_src_dir(), "default-configs");
+        let cmd = GenerateDefaultConfigsCmd {
+            manifest: join(pkg_dir(), "fixtures/fe/browser.yaml"),
+            output: output.clone().into(),
+            loader: Default::default(),
+        };
+        generate_default_configs(&cmd)?;
+
+        let homescreen: serde_json::Value = serde_json::from_str(&fs::read_to_string(join(
+            output.clone(),
+            "homescreen.json",
+        ))?)?;
+        let channels = homescreen.as_object().expect("keyed by channel");
+        assert_eq!(channels.len(), 4);
+        assert_eq!(homescreen["nightly"]["sections-enabled"]["pocket"], true);
+        assert_eq!(homescreen["release"]["sections-enabled"]["pocket"], false);
+        assert!(Path::new(&join(output, "nimbus-validation.json")).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_desktop() -> Result<()> {
+        let output = join(generated_src_dir(), "desktop.fml.yaml");
+        fs::create_dir_all(generated_src_dir())?;
+        let cmd = ImportDesktopCmd {
+            manifest: join(pkg_dir(), "fixtures/desktop/FeatureManifest.yaml"),
+            output: output.clone().into(),
+            loader: Default::default(),
+        };
+        import_desktop(&cmd)?;
+
+        let files = FileLoader::default()?;
+        let path = files.file_path(&output)?;
+        let fm = load_feature_manifest(files, path, false, None)?;
+
+        let urlbar = fm.get_feature("urlbar").expect("urlbar is imported");
+        assert_eq!(urlbar.props.len(), 3);
+        assert!(fm.find_enum("UrlbarResultMenu").is_some());
+
+        let search = fm.get_feature("search").expect("search is imported");
+        assert!(!search.has_exposure);
+        assert_eq!(
+            search.props[0].pref_key.as_deref(),
+            Some("browser.search.newSearchConfig.enabled")
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_importing_simple_experimenter_manifest() -> Result<()> {
         // Both the app and lib files declare features, so we should have an experimenter manifest file with two features.
@@ -509,6 +813,19 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_generate_struct_unsupported_language() -> Result<()> {
+        let mut cmd = create_command_from_test(
+            "test/simple_nimbus_validation.kts",
+            "fixtures/ir/simple_nimbus_validation.json",
+            "release",
+            true,
+        )?;
+        cmd.language = TargetLanguage::ExperimenterJSON;
+        assert!(matches!(generate_struct(&cmd), Err(CliError(_))));
+        Ok(())
+    }
+
     fn validate_against_experimenter_schema<P: AsRef<Path>>(
         schema_path: P,
         generated_yaml: &serde_yaml::Value,
@@ -558,6 +875,11 @@ mod test {
             let cmd = ValidateCmd {
                 loader: Default::default(),
                 manifest,
+                report: false,
+                format: Default::default(),
+                android_res: None,
+                ios_assets: None,
+                lint: Default::default(),
             };
             validate(&cmd)?;
         }
@@ -571,6 +893,11 @@ mod test {
         let cmd = ValidateCmd {
             loader: Default::default(),
             manifest,
+            report: false,
+            format: Default::default(),
+            android_res: None,
+            ios_assets: None,
+            lint: Default::default(),
         };
         let result = validate(&cmd);
 
@@ -586,6 +913,94 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_validate_command_fails_on_lint_errors() -> Result<()> {
+        let manifest = join(pkg_dir(), "fixtures/fe/browser.yaml");
+        let cmd = ValidateCmd {
+            loader: Default::default(),
+            manifest,
+            report: false,
+            format: Default::default(),
+            android_res: None,
+            ios_assets: None,
+            lint: LintConfig {
+                variable_names: LintLevel::Error,
+                ..Default::default()
+            },
+        };
+
+        match validate(&cmd) {
+            Err(CliError(error)) => assert!(error.ends_with("lint error(s)"), "{error}"),
+            result => panic!("Expected lint errors, got {result:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_command_with_report() -> Result<()> {
+        let path = "fixtures/fe/invalid/invalid_default_value_for_one_channel.fml.yaml";
+        let manifest = join(pkg_dir(), path);
+        let cmd = ValidateCmd {
+            loader: Default::default(),
+            manifest,
+            report: true,
+            format: Default::default(),
+            android_res: None,
+            ios_assets: None,
+            lint: Default::default(),
+        };
+        let result = validate(&cmd);
+
+        match result.err().unwrap() {
+            CliError(error) => {
+                assert_eq!(
+                    error,
+                    "Manifest contains 1 error(s) in 1 feature(s) or object(s)"
+                );
+            }
+            _ => panic!("Error is not a CliError"),
+        };
+
+        for path in MANIFEST_PATHS.iter().filter(|p| p.ends_with(".yaml")) {
+            let manifest = join(pkg_dir(), path);
+            let cmd = ValidateCmd {
+                loader: Default::default(),
+                manifest,
+                report: true,
+                format: Default::default(),
+                android_res: None,
+                ios_assets: None,
+                lint: Default::default(),
+            };
+            validate(&cmd)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_command_with_report_includes_load_errors() -> Result<()> {
+        let manifest = join(pkg_dir(), "fixtures/fe/does-not-exist.yaml");
+        let cmd = ValidateCmd {
+            loader: Default::default(),
+            manifest,
+            report: true,
+            format: Default::default(),
+            android_res: None,
+            ios_assets: None,
+            lint: Default::default(),
+        };
+
+        match validate(&cmd) {
+            Err(CliError(error)) => assert_eq!(
+                error,
+                "Manifest contains 1 error(s) in 1 feature(s) or object(s)"
+            ),
+            result => panic!("Expected a report of the load error, got {result:?}"),
+        }
+        Ok(())
+    }
+
     fn create_experimenter_manifest_cmd(path: &str) -> Result<GenerateExperimenterManifestCmd> {
         let manifest = join(pkg_dir(), path);
         let file = Path::new(&manifest);
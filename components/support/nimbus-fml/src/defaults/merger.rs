@@ -8,7 +8,7 @@ use serde_json::{json, Value};
 
 use crate::{
     error::{FMLError, Result},
-    frontend::DefaultBlock,
+    frontend::{DefaultBlock, MergeMode},
     intermediate_representation::{FeatureDef, ObjectDef, PropDef, TypeRef},
 };
 
@@ -46,12 +46,11 @@ impl<'object> DefaultsMerger<'object> {
     }
 
     fn collect_object_defaults(&self, name: &str) -> serde_json::Value {
-        let obj = self
-            .objects
-            .get(name)
-            .unwrap_or_else(|| panic!("Object named {} is not defined", name));
-
-        self.collect_props_defaults(&obj.props)
+        // If the object isn't defined, we leave it for the SchemaValidator to report.
+        match self.objects.get(name) {
+            Some(obj) => self.collect_props_defaults(&obj.props),
+            None => json!({}),
+        }
     }
 
     fn collect_props_defaults(&self, props: &Vec<PropDef>) -> Value {
@@ -72,9 +71,10 @@ impl<'object> DefaultsMerger<'object> {
     }
 
     fn collect_map_defaults(&self, v_type: &TypeRef, obj: &serde_json::Value) -> serde_json::Value {
-        let map = obj
-            .as_object()
-            .unwrap_or_else(|| panic!("Expected a JSON object as a default"));
+        // If the default isn't a map, we leave it for the DefaultsValidator to report.
+        let Some(map) = obj.as_object() else {
+            return obj.clone();
+        };
         let mut res = serde_json::value::Map::new();
         for (k, v) in map {
             let collected = self.collect_prop_defaults(v_type, v);
@@ -153,8 +153,7 @@ impl<'object> DefaultsMerger<'object> {
         defaults: &Option<Vec<DefaultBlock>>,
     ) -> Result<(), FMLError> {
         let variable_defaults = self.collect_feature_defaults(feature_def);
-        let defaults_to_merge = self.channel_specific_defaults(defaults)?;
-        let merged = merge_two_defaults(&variable_defaults, &defaults_to_merge);
+        let merged = self.channel_specific_defaults(defaults, &variable_defaults)?;
 
         self.overwrite_defaults(feature_def, &merged);
         Ok(())
@@ -180,7 +179,16 @@ impl<'object> DefaultsMerger<'object> {
         }
     }
 
-    fn channel_specific_defaults(&self, defaults: &Option<Vec<DefaultBlock>>) -> Result<Value> {
+    /// Merges the default blocks for the channel onto the `variable_defaults`, in order.
+    ///
+    /// The blocks are merged directly onto the variable defaults, rather than each other,
+    /// so that lists can be appended to, and maps replaced, regardless of where the earlier
+    /// default was declared.
+    fn channel_specific_defaults(
+        &self,
+        defaults: &Option<Vec<DefaultBlock>>,
+        variable_defaults: &Value,
+    ) -> Result<Value> {
         let supported_channels = self.supported_channels.as_slice();
         let channel = &self.channel;
         if let Some(channel) = channel {
@@ -191,17 +199,20 @@ impl<'object> DefaultsMerger<'object> {
                 ));
             }
         }
-        let empty_object = json!({});
         if let Some(defaults) = defaults {
             // No channel is represented by an unlikely string.
             let no_channel = "NO CHANNEL SPECIFIED".to_string();
-            let merged_defaults =
-                collect_channel_defaults(defaults, supported_channels, &no_channel)?;
+            let merged_defaults = collect_channel_defaults_onto(
+                defaults,
+                supported_channels,
+                &no_channel,
+                variable_defaults,
+            )?;
             let channel = self.channel.as_ref().unwrap_or(&no_channel);
             let merged = merged_defaults[channel].clone();
             Ok(merged)
         } else {
-            Ok(empty_object)
+            Ok(variable_defaults.clone())
         }
     }
 
@@ -248,6 +259,42 @@ fn merge_two_defaults(
     }
 }
 
+/// Merges a default block onto the defaults before it, according to the block's `merge` mode.
+fn merge_default_block(old_default: &Value, block: &DefaultBlock) -> Value {
+    match (block.merge, old_default, &block.value) {
+        (Some(MergeMode::Replace), Value::Object(old), Value::Object(new)) => {
+            let mut merged = old.clone();
+            merged.extend(new.clone());
+            Value::Object(merged)
+        }
+        (Some(MergeMode::Append), _, new) => append_two_defaults(old_default, new),
+        (_, _, new) => merge_two_defaults(old_default, new),
+    }
+}
+
+/// Merges two [`serde_json::Value`]s in the same way as [`merge_two_defaults`], except that
+/// lists in the `new_default` are appended to the lists in the `old_default`, rather than
+/// replacing them.
+fn append_two_defaults(old_default: &Value, new_default: &Value) -> Value {
+    match (old_default, new_default) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut merged = old.clone();
+            for (key, val) in new {
+                let val = match old.get(key) {
+                    Some(old_val) => append_two_defaults(old_val, val),
+                    None => val.clone(),
+                };
+                merged.insert(key.clone(), val);
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            Value::Array(old.iter().chain(new).cloned().collect())
+        }
+        (_, new) => new.clone(),
+    }
+}
+
 /// Collects the channel defaults of the feature manifest
 /// and merges them by channel
 ///
@@ -266,24 +313,36 @@ fn merge_two_defaults(
 /// - The `defaults` argument is not an array
 /// - There is a `channel` in the `defaults` argument that doesn't
 ///     exist in the `channels` argument
+#[cfg(test)]
 fn collect_channel_defaults(
     defaults: &[DefaultBlock],
     channels: &[String],
     no_channel: &str,
+) -> Result<HashMap<String, serde_json::Value>> {
+    collect_channel_defaults_onto(defaults, channels, no_channel, &json!({}))
+}
+
+/// Like [`collect_channel_defaults`], but merges the defaults for each channel onto the
+/// `base` value, rather than an empty object.
+fn collect_channel_defaults_onto(
+    defaults: &[DefaultBlock],
+    channels: &[String],
+    no_channel: &str,
+    base: &Value,
 ) -> Result<HashMap<String, serde_json::Value>> {
     // We initialize the map to have an entry for every valid channel
     let mut channel_map = channels
         .iter()
-        .map(|channel_name| (channel_name.clone(), json!({})))
+        .map(|channel_name| (channel_name.clone(), base.clone()))
         .collect::<HashMap<_, _>>();
-    channel_map.insert(no_channel.to_string(), json!({}));
+    channel_map.insert(no_channel.to_string(), base.clone());
     for default in defaults {
         if let Some(channels_for_default) = &default.merge_channels() {
             for channel in channels_for_default {
                 if let Some(old_default) = channel_map.get(channel).cloned() {
                     if default.targeting.is_none() {
                         // TODO: we currently ignore any defaults with targeting involved
-                        let merged = merge_two_defaults(&old_default, &default.value);
+                        let merged = merge_default_block(&old_default, default);
                         channel_map.insert(channel.clone(), merged);
                     }
                 } else {
@@ -297,9 +356,7 @@ fn collect_channel_defaults(
         } else {
             channel_map = channel_map
                 .into_iter()
-                .map(|(channel, old_default)| {
-                    (channel, merge_two_defaults(&old_default, &default.value))
-                })
+                .map(|(channel, old_default)| (channel, merge_default_block(&old_default, default)))
                 .collect();
         }
     }
@@ -1244,4 +1301,114 @@ mod unit_tests {
         );
         Ok(())
     }
+
+    fn feature_with_list_and_map() -> FeatureDef {
+        FeatureDef {
+            props: vec![
+                PropDef::new(
+                    "sections",
+                    &TypeRef::List(Box::new(TypeRef::String)),
+                    &json!(["top-sites", "jump-back-in"]),
+                ),
+                PropDef::new(
+                    "colors",
+                    &TypeRef::StringMap(Box::new(TypeRef::String)),
+                    &json!({ "background": "white", "text": "black" }),
+                ),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_feature_default_append() -> Result<()> {
+        let default_blocks = serde_json::from_value(json!([
+            {
+                "merge": "append",
+                "value": {
+                    "sections": ["pocket"],
+                    "colors": { "link": "blue" }
+                }
+            },
+            {
+                "channel": "nightly",
+                "merge": "append",
+                "value": {
+                    "sections": ["experimental"]
+                }
+            },
+            {
+                "channel": "beta",
+                "value": {
+                    "sections": ["recent-tabs"]
+                }
+            },
+        ]))?;
+        let objects = Default::default();
+        let channels = vec!["release".to_string(), "beta".into(), "nightly".into()];
+
+        let expected = [
+            ("release", json!(["top-sites", "jump-back-in", "pocket"])),
+            ("beta", json!(["recent-tabs"])),
+            (
+                "nightly",
+                json!(["top-sites", "jump-back-in", "pocket", "experimental"]),
+            ),
+        ];
+        for (channel, sections) in expected {
+            let mut feature_def = feature_with_list_and_map();
+            let merger =
+                DefaultsMerger::new_with_channel(&objects, channels.clone(), channel.into());
+            merger.merge_feature_defaults(&mut feature_def, &default_blocks)?;
+            assert_eq!(feature_def.props[0].default, sections, "{channel}");
+            // Maps are merged, as without a merge mode.
+            assert_eq!(
+                feature_def.props[1].default,
+                json!({ "background": "white", "text": "black", "link": "blue" })
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_feature_default_replace() -> Result<()> {
+        let default_blocks = serde_json::from_value(json!([
+            {
+                "channel": "release",
+                "merge": "replace",
+                "value": {
+                    "colors": { "link": "blue" }
+                }
+            },
+            {
+                "channel": "beta",
+                "value": {
+                    "colors": { "link": "blue" }
+                }
+            },
+        ]))?;
+        let objects = Default::default();
+        let channels = vec!["release".to_string(), "beta".into()];
+
+        let expected = [
+            ("release", json!({ "link": "blue" })),
+            (
+                "beta",
+                json!({ "background": "white", "text": "black", "link": "blue" }),
+            ),
+        ];
+        for (channel, colors) in expected {
+            let mut feature_def = feature_with_list_and_map();
+            let merger =
+                DefaultsMerger::new_with_channel(&objects, channels.clone(), channel.into());
+            merger.merge_feature_defaults(&mut feature_def, &default_blocks)?;
+            assert_eq!(feature_def.props[1].default, colors, "{channel}");
+            // Variables not in the block keep their defaults.
+            assert_eq!(
+                feature_def.props[0].default,
+                json!(["top-sites", "jump-back-in"])
+            );
+        }
+        Ok(())
+    }
 }
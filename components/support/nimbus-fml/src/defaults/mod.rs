@@ -4,8 +4,10 @@
 
 mod hasher;
 mod merger;
+mod resources;
 mod validator;
 
 pub(crate) use hasher::DefaultsHasher;
 pub(crate) use merger::DefaultsMerger;
+pub(crate) use resources::BundledResources;
 pub(crate) use validator::DefaultsValidator;
@@ -0,0 +1,354 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use heck::SnakeCase;
+use serde_json::Value;
+
+use crate::{
+    editing::ErrorPath,
+    error::{FMLError, Result},
+    intermediate_representation::{FeatureManifest, PropDef, TypeRef},
+    util::is_resource_id,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    Android,
+    Ios,
+}
+
+/// The images and strings bundled with an app, used to check that every `Image` and `Text`
+/// default in the manifest refers to a resource that actually exists.
+#[derive(Debug)]
+pub(crate) struct BundledResources {
+    platform: Platform,
+    dir: PathBuf,
+    images: HashSet<String>,
+    strings: HashSet<String>,
+}
+
+impl BundledResources {
+    /// Indexes an Android `res` directory: drawables from the `drawable*` directories, and
+    /// strings from the `values*` directories.
+    pub(crate) fn from_android_res(dir: &Path) -> Result<Self> {
+        let mut images = HashSet::new();
+        let mut strings = HashSet::new();
+        for entry in read_dir(dir)? {
+            let dir_name = file_name(&entry);
+            if dir_name.starts_with("drawable") {
+                for file in read_dir(&entry)? {
+                    // Nine-patch images are named `foo.9.png`, but referred to as `foo`.
+                    let name = file_name(&file);
+                    let id = name.split('.').next().unwrap_or_default();
+                    images.insert(id.to_string());
+                }
+            } else if dir_name.starts_with("values") {
+                for file in read_dir(&entry)? {
+                    if file.extension().map_or(false, |ext| ext == "xml") {
+                        collect_android_strings(&fs::read_to_string(&file)?, &mut strings);
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            platform: Platform::Android,
+            dir: dir.to_path_buf(),
+            images,
+            strings,
+        })
+    }
+
+    /// Indexes an iOS assets directory, recursively: images from `.imageset` directories in
+    /// asset catalogs and loose image files, and strings from `.strings` files.
+    pub(crate) fn from_ios_assets(dir: &Path) -> Result<Self> {
+        let mut resources = Self {
+            platform: Platform::Ios,
+            dir: dir.to_path_buf(),
+            images: Default::default(),
+            strings: Default::default(),
+        };
+        resources.collect_ios_assets(dir)?;
+        Ok(resources)
+    }
+
+    fn collect_ios_assets(&mut self, dir: &Path) -> Result<()> {
+        for entry in read_dir(dir)? {
+            let name = file_name(&entry);
+            let (stem, ext) = name.rsplit_once('.').unwrap_or((&name, ""));
+            match ext {
+                "imageset" => {
+                    self.images.insert(stem.to_string());
+                }
+                "png" | "jpg" | "jpeg" | "pdf" | "svg" | "heic" => {
+                    // Loose images can have scale suffixes, e.g. `foo@2x.png`.
+                    let id = stem.split('@').next().unwrap_or_default();
+                    self.images.insert(id.to_string());
+                }
+                "strings" => collect_ios_strings(&fs::read_to_string(&entry)?, &mut self.strings),
+                _ if entry.is_dir() => self.collect_ios_assets(&entry)?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn has_image(&self, id: &str) -> bool {
+        match self.platform {
+            Platform::Android => self.images.contains(&id.to_snake_case()),
+            Platform::Ios => self.images.contains(id),
+        }
+    }
+
+    fn has_text(&self, id: &str) -> bool {
+        match self.platform {
+            Platform::Android => self.strings.contains(&id.to_snake_case()),
+            Platform::Ios => self.strings.contains(id),
+        }
+    }
+
+    fn description(&self) -> String {
+        let platform = match self.platform {
+            Platform::Android => "Android resources",
+            Platform::Ios => "iOS assets",
+        };
+        format!("{platform} at {}", self.dir.display())
+    }
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| FMLError::InvalidPath(format!("{}: {e}", dir.display())))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    Ok(entries)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Finds the names in `<string name="foo">` elements.
+fn collect_android_strings(xml: &str, strings: &mut HashSet<String>) {
+    for element in xml.split("<string ").skip(1) {
+        let Some((_, rest)) = element.split_once("name=\"") else {
+            continue;
+        };
+        if let Some((name, _)) = rest.split_once('"') {
+            strings.insert(name.to_string());
+        }
+    }
+}
+
+/// Finds the keys in `"foo" = "Foo";` lines.
+fn collect_ios_strings(source: &str, strings: &mut HashSet<String>) {
+    for line in source.lines() {
+        let Some(rest) = line.trim_start().strip_prefix('"') else {
+            continue;
+        };
+        if let Some((key, _)) = rest.split_once('"') {
+            strings.insert(key.to_string());
+        }
+    }
+}
+
+impl FeatureManifest {
+    /// Checks that the bundled images and text in the defaults of every feature and object,
+    /// including those imported, exist in the app's resources.
+    ///
+    /// Each error is returned with the feature or object it was found in, e.g. `features/homescreen`.
+    pub(crate) fn get_resource_errors(
+        &self,
+        resources: &BundledResources,
+    ) -> Vec<(String, FMLError)> {
+        let mut errors = Default::default();
+        for (fm, object) in self.iter_all_object_defs() {
+            let path = ErrorPath::object(&object.name);
+            fm.check_props(resources, &path, &object.props, &mut errors);
+        }
+        for (fm, feature) in self.iter_all_feature_defs() {
+            let path = ErrorPath::feature(&feature.name);
+            fm.check_props(resources, &path, &feature.props, &mut errors);
+        }
+        errors
+    }
+
+    fn check_props(
+        &self,
+        resources: &BundledResources,
+        path: &ErrorPath,
+        props: &[PropDef],
+        errors: &mut Vec<(String, FMLError)>,
+    ) {
+        let mut prop_errors = Default::default();
+        for prop in props {
+            self.check_value(
+                resources,
+                &path.property(&prop.name),
+                &prop.typ,
+                &prop.default,
+                &mut prop_errors,
+            );
+        }
+        errors.extend(prop_errors.into_iter().map(|e| (path.path.clone(), e)));
+    }
+
+    fn check_value(
+        &self,
+        resources: &BundledResources,
+        path: &ErrorPath,
+        typ: &TypeRef,
+        value: &Value,
+        errors: &mut Vec<FMLError>,
+    ) {
+        match (typ, value) {
+            (TypeRef::BundleImage, Value::String(id)) if !resources.has_image(id) => {
+                errors.push(FMLError::ValidationError(
+                    path.path.clone(),
+                    format!("Image `{id}` not found in {}", resources.description()),
+                ));
+            }
+            (TypeRef::BundleText, Value::String(id))
+                if is_resource_id(id) && !resources.has_text(id) =>
+            {
+                errors.push(FMLError::ValidationError(
+                    path.path.clone(),
+                    format!("Text `{id}` not found in {}", resources.description()),
+                ));
+            }
+            (TypeRef::Option(inner), v) => self.check_value(resources, path, inner, v, errors),
+            (TypeRef::List(inner), Value::Array(list)) => {
+                for (index, v) in list.iter().enumerate() {
+                    self.check_value(resources, &path.array_index(index), inner, v, errors);
+                }
+            }
+            (TypeRef::StringMap(inner) | TypeRef::EnumMap(_, inner), Value::Object(map)) => {
+                for (key, v) in map {
+                    self.check_value(resources, &path.map_key(key), inner, v, errors);
+                }
+            }
+            (TypeRef::Object(name), Value::Object(map)) => {
+                let Some(object) = self.find_object(name) else {
+                    return;
+                };
+                let path = path.object_value(name);
+                for prop in &object.props {
+                    if let Some(v) = map.get(&prop.name) {
+                        self.check_value(
+                            resources,
+                            &path.property(&prop.name),
+                            &prop.typ,
+                            v,
+                            errors,
+                        );
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        fixtures::intermediate_representation::get_feature_manifest,
+        intermediate_representation::{FeatureDef, ObjectDef},
+    };
+
+    fn write(dir: &Path, file: &str, contents: &str) -> Result<()> {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    fn manifest() -> FeatureManifest {
+        get_feature_manifest(
+            vec![ObjectDef::new(
+                "Card",
+                &[
+                    PropDef::new("icon", &TypeRef::BundleImage, &json!("card_icon")),
+                    PropDef::new("title", &TypeRef::BundleText, &json!("Just some text")),
+                ],
+            )],
+            vec![],
+            vec![FeatureDef::new(
+                "homescreen",
+                "",
+                vec![
+                    PropDef::new("logo", &TypeRef::BundleImage, &json!("logo")),
+                    PropDef::new("title", &TypeRef::BundleText, &json!("home_title")),
+                    PropDef::new(
+                        "cards",
+                        &TypeRef::List(Box::new(TypeRef::Object("Card".into()))),
+                        &json!([{ "icon": "missing_icon" }]),
+                    ),
+                ],
+                false,
+            )],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_android_resources() -> Result<()> {
+        let tmp = TempDir::new()?;
+        let res = tmp.path();
+        write(res, "drawable/logo.xml", "")?;
+        write(res, "drawable-hdpi/card_icon.9.png", "")?;
+        write(
+            res,
+            "values/strings.xml",
+            r#"<resources><string name="home_title">Home</string></resources>"#,
+        )?;
+
+        let resources = BundledResources::from_android_res(res)?;
+        let errors = manifest().get_resource_errors(&resources);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "features/homescreen");
+        assert!(errors[0].1.to_string().contains("Image `missing_icon`"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ios_assets() -> Result<()> {
+        let tmp = TempDir::new()?;
+        let assets = tmp.path();
+        write(assets, "Assets.xcassets/logo.imageset/Contents.json", "{}")?;
+        write(assets, "Images/missing_icon@2x.png", "")?;
+        write(
+            assets,
+            "en.lproj/Localizable.strings",
+            "/* A comment */\n\"another_title\" = \"Home\";\n",
+        )?;
+
+        let resources = BundledResources::from_ios_assets(assets)?;
+        let errors = manifest().get_resource_errors(&resources);
+
+        let messages = errors
+            .iter()
+            .map(|(group, e)| format!("{group}: {e}"))
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(messages[0].starts_with("objects/Card: "));
+        assert!(messages[0].contains("Image `card_icon`"));
+        assert!(messages[1].starts_with("features/homescreen: "));
+        assert!(messages[1].contains("Text `home_title`"));
+        Ok(())
+    }
+}
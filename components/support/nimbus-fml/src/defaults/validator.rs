@@ -78,6 +78,54 @@ impl<'a> DefaultsValidator<'a> {
         Ok(())
     }
 
+    /// Collects all the errors in the defaults of this object, rather than stopping at the first.
+    pub(crate) fn get_object_def_errors(&self, object_def: &ObjectDef) -> Vec<FMLError> {
+        let mut errors = Default::default();
+        let path = ErrorPath::object(&object_def.name);
+        for prop in &object_def.props {
+            self.validate_types(
+                &path.property(&prop.name),
+                &prop.typ,
+                &prop.default,
+                &mut errors,
+            );
+        }
+        let converter = ErrorConverter::new(self.enum_defs, self.object_defs);
+        errors
+            .into_iter()
+            .map(|e| converter.convert_object_error(e))
+            .collect()
+    }
+
+    /// Collects all the errors in the defaults and examples of this feature, rather than stopping at the first.
+    ///
+    /// Like `validate_feature_def`, this assumes that the feature has passed schema validation.
+    pub(crate) fn get_feature_def_errors(&self, feature_def: &FeatureDef) -> Vec<FMLError> {
+        let defaults = feature_def.default_json();
+        let converter = ErrorConverter::new(self.enum_defs, self.object_defs);
+        let mut errors: Vec<_> = self
+            .get_errors(feature_def, &defaults, &defaults)
+            .into_iter()
+            .map(|e| converter.convert_feature_error(feature_def, &defaults, e))
+            .collect();
+
+        if let Err(e) = self.validate_feature_enum_maps(feature_def) {
+            errors.push(e);
+        }
+
+        let path = ErrorPath::feature(&feature_def.name);
+        for ex in &feature_def.examples {
+            let path = path.example(&ex.metadata.name);
+            errors.extend(
+                self.get_errors_with_path(&path, feature_def, &defaults, &ex.value)
+                    .into_iter()
+                    .map(|e| converter.convert_feature_error(feature_def, &defaults, e)),
+            );
+        }
+
+        errors
+    }
+
     pub(crate) fn guard_errors(
         &self,
         feature_def: &FeatureDef,
@@ -120,6 +168,18 @@ impl<'a> DefaultsValidator<'a> {
             return errors;
         }
 
+        for prop in feature_def.props.iter().filter(|p| p.required) {
+            if !unmerged_map.contains_key(&prop.name) {
+                errors.push(FeatureValidationError {
+                    path: path.open_brace(),
+                    kind: ErrorKind::invalid_nested_value(&prop.name, &prop.typ),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return errors;
+        }
+
         let string_aliases = feature_def.get_string_aliases();
         for prop in &feature_def.props {
             if let Some(value) = unmerged_map.get(&prop.name) {
@@ -0,0 +1,584 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    defaults::DefaultsValidator,
+    error::{FMLError, Result},
+    intermediate_representation::{FeatureManifest, ModuleId, TypeFinder, TypeRef},
+    lint::Lint,
+    schema::SchemaValidator,
+    util::loaders::{FileLoader, FilePath},
+};
+
+/// The group used for problems which stop the manifest from being loaded at all.
+pub(crate) const MANIFEST_GROUP: &str = "manifest";
+
+// The checks which find problems. Lints use the name of their rule instead.
+pub(crate) const LOAD_RULE: &str = "load";
+pub(crate) const SCHEMA_RULE: &str = "schema";
+pub(crate) const DEFAULTS_RULE: &str = "defaults";
+pub(crate) const RESOURCES_RULE: &str = "bundled-resources";
+pub(crate) const METADATA_RULE: &str = "missing-metadata";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+/// How a [ValidationReport] should be printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+    Sarif,
+}
+
+impl TryFrom<&str> for ReportFormat {
+    type Error = FMLError;
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "text" => Self::Text,
+            "json" => Self::Json,
+            "sarif" => Self::Sarif,
+            _ => {
+                return Err(FMLError::CliError(format!(
+                    "Unknown report format: {value}"
+                )))
+            }
+        })
+    }
+}
+
+/// A single problem found while validating a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    /// The check which found the problem, e.g. `defaults`, or the name of a lint rule.
+    pub(crate) rule: String,
+    /// The feature or object the problem was found in, e.g. `features/homescreen`.
+    pub(crate) group: String,
+    pub(crate) message: String,
+    /// The channels for which this problem was found. Empty if not validating per channel.
+    pub(crate) channels: BTreeSet<String>,
+    /// The manifest file the feature or object was defined in, if known.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_module_id"
+    )]
+    pub(crate) file: Option<ModuleId>,
+    /// The 1-based line in `file` where the feature or object is defined, if found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) line: Option<usize>,
+}
+
+/// Every problem found in a manifest, rather than just the first.
+///
+/// This is produced by [FeatureManifest::validation_report], and can be merged across
+/// channels with [ValidationReport::merge].
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct ValidationReport {
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub(crate) fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    pub(crate) fn add(&mut self, rule: &str, group: &str, error: FMLError) {
+        for e in error.into_all() {
+            self.push(Severity::Error, rule, group, e.to_string(), None);
+        }
+    }
+
+    pub(crate) fn add_warning(&mut self, rule: &str, group: &str, message: &str) {
+        self.push(Severity::Warning, rule, group, message.to_string(), None);
+    }
+
+    pub(crate) fn add_lints(&mut self, file: &ModuleId, lints: Vec<Lint>) {
+        for l in lints {
+            self.push(l.severity, &l.rule, &l.group, l.message, Some(file.clone()));
+        }
+    }
+
+    fn add_all(&mut self, rule: &str, group: &str, file: &ModuleId, errors: Vec<FMLError>) {
+        for e in errors {
+            self.push(
+                Severity::Error,
+                rule,
+                group,
+                e.to_string(),
+                Some(file.clone()),
+            );
+        }
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        rule: &str,
+        group: &str,
+        message: String,
+        file: Option<ModuleId>,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            rule: rule.to_string(),
+            group: group.to_string(),
+            message,
+            channels: Default::default(),
+            file,
+            line: None,
+        });
+    }
+
+    /// Adds the diagnostics from a report for a single channel.
+    ///
+    /// Problems which are the same in several channels are reported once.
+    pub(crate) fn merge(&mut self, channel: &str, other: ValidationReport) {
+        for d in other.diagnostics {
+            match self.diagnostics.iter_mut().find(|existing| {
+                existing.severity == d.severity
+                    && existing.rule == d.rule
+                    && existing.group == d.group
+                    && existing.message == d.message
+            }) {
+                Some(existing) => {
+                    existing.channels.insert(channel.to_string());
+                }
+                None => self.diagnostics.push(Diagnostic {
+                    channels: BTreeSet::from([channel.to_string()]),
+                    ..d
+                }),
+            }
+        }
+    }
+
+    /// The diagnostics, grouped by feature or object, in a stable order.
+    pub(crate) fn grouped(&self) -> BTreeMap<&str, Vec<&Diagnostic>> {
+        let mut groups: BTreeMap<_, Vec<_>> = Default::default();
+        for d in &self.diagnostics {
+            groups.entry(d.group.as_str()).or_default().push(d);
+        }
+        groups
+    }
+
+    /// Finds the line each diagnostic's feature or object is defined on, by reading
+    /// the manifest files they came from.
+    ///
+    /// Files which can't be read are skipped, leaving the line unknown.
+    pub(crate) fn find_lines(&mut self, files: &FileLoader) {
+        let mut sources: HashMap<ModuleId, Option<String>> = Default::default();
+        for d in self.diagnostics.iter_mut() {
+            let Some(file) = &d.file else {
+                continue;
+            };
+            let source = sources
+                .entry(file.clone())
+                .or_insert_with(|| read_module(files, file).ok());
+            if let Some(source) = source {
+                d.line = find_line(source, &d.group);
+            }
+        }
+    }
+
+    /// The report as a [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) log.
+    ///
+    /// Each check is a rule, and the feature or object is a logical location, so the same
+    /// problem in different features has the same rule id.
+    pub(crate) fn to_sarif(&self) -> Value {
+        let results = self
+            .diagnostics
+            .iter()
+            .map(|d| {
+                let mut location = json!({
+                    "logicalLocations": [{ "fullyQualifiedName": d.group }],
+                });
+                if let Some(uri) = d.file.as_ref().and_then(module_uri) {
+                    let mut physical = json!({ "artifactLocation": { "uri": uri } });
+                    if let Some(line) = d.line {
+                        physical["region"] = json!({ "startLine": line });
+                    }
+                    location["physicalLocation"] = physical;
+                }
+                json!({
+                    "ruleId": d.rule,
+                    "level": d.severity,
+                    "message": { "text": d.message },
+                    "locations": [location],
+                    "properties": { "channels": d.channels },
+                })
+            })
+            .collect::<Vec<_>>();
+        let rules = self
+            .diagnostics
+            .iter()
+            .map(|d| d.rule.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|rule| json!({ "id": rule }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "nimbus-fml",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }]
+        })
+    }
+}
+
+fn serialize_module_id<S: Serializer>(
+    id: &Option<ModuleId>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match id {
+        Some(id) => serializer.collect_str(id),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn module_path(id: &ModuleId) -> Result<FilePath> {
+    Ok(match id {
+        ModuleId::Local(p) => FilePath::Local(PathBuf::from(p)),
+        ModuleId::Remote(u) => FilePath::Remote(Url::parse(u)?),
+    })
+}
+
+fn module_uri(id: &ModuleId) -> Option<String> {
+    match id {
+        ModuleId::Local(p) => Url::from_file_path(p).ok().map(|u| u.to_string()),
+        ModuleId::Remote(u) => Some(u.clone()),
+    }
+}
+
+fn read_module(files: &FileLoader, id: &ModuleId) -> Result<String> {
+    files.read_to_string(&module_path(id)?)
+}
+
+/// Finds the line that a group like `features/homescreen` is defined on, by looking
+/// for the key `homescreen:` directly under the top level `features:` key.
+fn find_line(source: &str, group: &str) -> Option<usize> {
+    let (section, name) = group.split_once('/')?;
+    let key = format!("{name}:");
+    let mut in_section = false;
+    let mut entry_indent = None;
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent == 0 {
+            in_section = trimmed.trim_end() == format!("{section}:");
+            entry_indent = None;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let entry_indent = *entry_indent.get_or_insert(indent);
+        if indent == entry_indent && trimmed.starts_with(&key) {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+impl FeatureManifest {
+    /// Validates the whole manifest, including imported manifests, collecting all errors
+    /// rather than stopping at the first.
+    ///
+    /// Defaults are only validated for features and objects whose types are all
+    /// correctly defined, since the `DefaultsValidator` relies on the schema being valid.
+    pub(crate) fn validation_report(&self) -> ValidationReport {
+        let mut report = Default::default();
+        self.add_to_report(&mut report);
+        for child in self.all_imports.values() {
+            child.add_to_report(&mut report);
+        }
+        report
+    }
+
+    fn add_to_report(&self, report: &mut ValidationReport) {
+        let schema = SchemaValidator::new(&self.enum_defs, &self.obj_defs);
+        let defaults = DefaultsValidator::new(&self.enum_defs, &self.obj_defs);
+
+        let mut invalid_objects = HashSet::new();
+        for object in self.iter_object_defs() {
+            let errors = schema.get_object_def_errors(object);
+            if !errors.is_empty() {
+                invalid_objects.insert(object.name());
+            }
+            report.add_all(
+                SCHEMA_RULE,
+                &format!("objects/{}", object.name),
+                &self.id,
+                errors,
+            );
+        }
+
+        for object in self.iter_object_defs() {
+            if self.are_types_valid(&object.all_types(), &invalid_objects) {
+                report.add_all(
+                    DEFAULTS_RULE,
+                    &format!("objects/{}", object.name),
+                    &self.id,
+                    defaults.get_object_def_errors(object),
+                );
+            }
+        }
+
+        // Features are validated in parallel, but added to the report in order.
+        let feature_errors = self
+            .feature_defs
+            .par_iter()
+            .map(|(_, feature)| {
+                let mut rule = SCHEMA_RULE;
+                let mut errors = schema.get_feature_def_errors(feature);
+                if errors.is_empty() && self.are_types_valid(&feature.all_types(), &invalid_objects)
+                {
+                    rule = DEFAULTS_RULE;
+                    errors = defaults.get_feature_def_errors(feature);
+                }
+                (rule, format!("features/{}", feature.name), errors)
+            })
+            .collect::<Vec<_>>();
+        for (rule, group, errors) in feature_errors {
+            report.add_all(rule, &group, &self.id, errors);
+        }
+    }
+
+    /// Checks that the types, and the types of any objects they refer to, are all defined
+    /// and valid.
+    fn are_types_valid(&self, types: &HashSet<TypeRef>, invalid_objects: &HashSet<String>) -> bool {
+        let mut seen = HashSet::new();
+        let mut unseen: Vec<_> = types.iter().cloned().collect();
+        while let Some(t) = unseen.pop() {
+            if !seen.insert(t.clone()) {
+                continue;
+            }
+            match &t {
+                TypeRef::Enum(nm) if self.find_enum(nm).is_none() => return false,
+                TypeRef::Object(nm) => match self.find_object(nm) {
+                    Some(obj) if !invalid_objects.contains(nm) => {
+                        unseen.extend(obj.all_types());
+                    }
+                    _ => return false,
+                },
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        error::Result,
+        fixtures::intermediate_representation::get_feature_manifest,
+        intermediate_representation::{FeatureDef, ObjectDef, PropDef},
+    };
+
+    #[test]
+    fn test_report_collects_all_errors() -> Result<()> {
+        let fm = get_feature_manifest(
+            vec![ObjectDef::new(
+                "MyObject",
+                &[PropDef::new(
+                    "my-enum",
+                    &TypeRef::Enum("MissingEnum".into()),
+                    &json!("a-variant"),
+                )],
+            )],
+            vec![],
+            vec![
+                FeatureDef::new(
+                    "feature-1",
+                    "",
+                    vec![
+                        PropDef::new("a-string", &TypeRef::String, &json!(1)),
+                        PropDef::new("an-int", &TypeRef::Int, &json!(null)),
+                    ],
+                    false,
+                ),
+                FeatureDef::new(
+                    "feature-2",
+                    "",
+                    vec![PropDef::new(
+                        "an-object",
+                        &TypeRef::Object("MyObject".into()),
+                        &json!({}),
+                    )],
+                    false,
+                ),
+                FeatureDef::new(
+                    "feature-3",
+                    "",
+                    vec![PropDef::new(
+                        "missing-object",
+                        &TypeRef::Object("MissingObject".into()),
+                        &json!({}),
+                    )],
+                    false,
+                ),
+            ],
+            Default::default(),
+        );
+
+        let report = fm.validation_report();
+        let groups = report.grouped();
+
+        assert_eq!(
+            groups.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                "features/feature-1",
+                "features/feature-3",
+                "objects/MyObject"
+            ]
+        );
+        // Both bad defaults are reported, not just the first.
+        assert_eq!(groups["features/feature-1"].len(), 2);
+        assert_eq!(groups["features/feature-3"].len(), 1);
+        assert_eq!(groups["objects/MyObject"].len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_line() {
+        let source = r#"---
+about:
+  description: features:
+features:
+  # A comment
+  homescreen:
+    description: The homescreen
+    variables:
+      sections:
+        type: Boolean
+  sections:
+    description: Not the variable
+objects:
+  sections:
+    fields: {}
+"#;
+        assert_eq!(find_line(source, "features/homescreen"), Some(6));
+        assert_eq!(find_line(source, "features/sections"), Some(11));
+        assert_eq!(find_line(source, "objects/sections"), Some(14));
+        assert_eq!(find_line(source, "features/missing"), None);
+        assert_eq!(find_line(source, MANIFEST_GROUP), None);
+    }
+
+    #[test]
+    fn test_sarif_output() {
+        let mut report = ValidationReport::default();
+        report.push(
+            Severity::Error,
+            DEFAULTS_RULE,
+            "features/a",
+            "Bad default".to_string(),
+            Some(ModuleId::Local("/tmp/app.fml.yaml".to_string())),
+        );
+        report.diagnostics[0].line = Some(12);
+        report.add_warning(METADATA_RULE, "features/b", "Missing metadata");
+        report.add_warning(METADATA_RULE, "features/c", "Missing metadata");
+
+        let sarif = report.to_sarif();
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"],
+            json!([{ "id": DEFAULTS_RULE }, { "id": METADATA_RULE }])
+        );
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleId"], DEFAULTS_RULE);
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["message"]["text"], "Bad default");
+        assert_eq!(
+            results[0]["locations"][0],
+            json!({
+                "logicalLocations": [{ "fullyQualifiedName": "features/a" }],
+                "physicalLocation": {
+                    "artifactLocation": { "uri": "file:///tmp/app.fml.yaml" },
+                    "region": { "startLine": 12 },
+                },
+            })
+        );
+        // The same check in different features has the same rule id.
+        for (result, group) in results[1..].iter().zip(["features/b", "features/c"]) {
+            assert_eq!(result["ruleId"], METADATA_RULE);
+            assert_eq!(result["level"], "warning");
+            assert_eq!(
+                result["locations"][0],
+                json!({ "logicalLocations": [{ "fullyQualifiedName": group }] })
+            );
+        }
+    }
+
+    #[test]
+    fn test_merging_reports_across_channels() -> Result<()> {
+        let mut report: ValidationReport = Default::default();
+
+        let mut release = ValidationReport::default();
+        release.add(
+            SCHEMA_RULE,
+            "features/a",
+            FMLError::InternalError("Everywhere"),
+        );
+        release.add(
+            SCHEMA_RULE,
+            "features/a",
+            FMLError::InternalError("Only release"),
+        );
+        report.merge("release", release);
+
+        let mut beta = ValidationReport::default();
+        beta.add(
+            SCHEMA_RULE,
+            "features/a",
+            FMLError::InternalError("Everywhere"),
+        );
+        report.merge("beta", beta);
+
+        assert_eq!(report.diagnostics.len(), 2);
+        assert_eq!(
+            report.diagnostics[0].channels,
+            BTreeSet::from(["beta".to_string(), "release".to_string()])
+        );
+        assert_eq!(
+            report.diagnostics[1].channels,
+            BTreeSet::from(["release".to_string()])
+        );
+        Ok(())
+    }
+}
@@ -22,6 +22,8 @@ pub enum FMLError {
     FetchError(#[from] reqwest::Error),
     #[error("Can't find file: {0}")]
     InvalidPath(String),
+    #[error("Can't download {0} while offline, and it isn't in the cache at {1}")]
+    NotCachedError(String, String),
 
     #[error("Unexpected template problem: {0}")]
     TemplateProblem(#[from] askama::Error),
@@ -53,6 +55,33 @@ pub enum FMLError {
 
     #[error("Invalid API token GITHUB_BEARER_TOKEN")]
     InvalidApiToken,
+
+    #[error("IR version {0} is newer than this nimbus-fml supports, which is up to {1}")]
+    UnsupportedIRVersion(u64, u64),
+
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    MultipleErrors(Vec<FMLError>),
+}
+
+impl FMLError {
+    /// Returns all the `errors` as one error, or `Ok` if there are none.
+    pub(crate) fn from_all(mut errors: Vec<FMLError>) -> Result<()> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(FMLError::MultipleErrors(errors)),
+        }
+    }
+
+    /// The individual errors contained in this one.
+    pub(crate) fn into_all(self) -> Vec<FMLError> {
+        match self {
+            FMLError::MultipleErrors(errors) => {
+                errors.into_iter().flat_map(FMLError::into_all).collect()
+            }
+            e => vec![e],
+        }
+    }
 }
 
 #[cfg(feature = "client-lib")]
@@ -54,6 +54,7 @@ impl PropDef {
             doc: format!("{nm} property of type {typ}"),
             pref_key: None,
             string_alias: None,
+            required: false,
         }
     }
 
@@ -65,6 +66,7 @@ impl PropDef {
             doc: nm.to_string(),
             pref_key: None,
             string_alias: Some(sa.clone()),
+            required: false,
         }
     }
 
@@ -76,6 +78,7 @@ impl PropDef {
             default: default.clone(),
             pref_key: None,
             string_alias: None,
+            required: false,
         }
     }
 }
@@ -5,18 +5,20 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use email_address::EmailAddress;
-use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::json;
 use url::Url;
 
 use crate::{
     defaults::DefaultsMerger,
-    error::Result,
+    error::{FMLError, Result},
     intermediate_representation::{
         EnumDef, FeatureDef, FeatureManifest, ModuleId, ObjectDef, PropDef, TargetLanguage,
         TypeRef, VariantDef,
     },
     parser::get_typeref_from_string,
+    util::par_try_map_all,
 };
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -29,7 +31,8 @@ pub(crate) struct EnumVariantBody {
 #[serde(deny_unknown_fields)]
 pub(crate) struct EnumBody {
     pub(crate) description: String,
-    pub(crate) variants: BTreeMap<String, EnumVariantBody>,
+    /// The variants, in the order they are declared in the manifest.
+    pub(crate) variants: IndexMap<String, EnumVariantBody>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -46,6 +49,12 @@ pub(crate) struct FeatureFieldBody {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) string_alias: Option<String>,
+
+    /// Required variables must be provided by every experiment or rollout, so they don't
+    /// need a default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) required: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -54,9 +63,37 @@ pub(crate) struct FieldBody {
     pub(crate) description: String,
     #[serde(rename = "type")]
     pub(crate) variable_type: String,
+    // An explicit `default: null` is `Some(Value::Null)`, so we can tell it apart from
+    // a missing default.
+    #[serde(default, deserialize_with = "deserialize_present")]
     pub(crate) default: Option<serde_json::Value>,
 }
 
+fn deserialize_present<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<serde_json::Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+impl FieldBody {
+    /// Every feature variable and object field must have a default. Optional types
+    /// need an explicit `null` if they have no value.
+    fn check_has_default(&self, path: String) -> Result<()> {
+        if self.default.is_some() {
+            return Ok(());
+        }
+        let msg = if self.variable_type.starts_with("Option<") {
+            "A default is required; use `null` if there is no value"
+        } else {
+            "A default is required for non-optional types"
+        };
+        Err(FMLError::ValidationError(path, msg.to_string()))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct ObjectBody {
@@ -103,6 +140,7 @@ impl AboutBlock {
             TargetLanguage::IR => true,
             TargetLanguage::ExperimenterYAML => true,
             TargetLanguage::ExperimenterJSON => true,
+            TargetLanguage::Custom { .. } => true,
         }
     }
 
@@ -226,6 +264,24 @@ pub(crate) struct FeatureBody {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub(crate) examples: Vec<ExampleBlock>,
+
+    /// The earliest version of the app which supports this feature.
+    #[serde(default, alias = "min_version")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min_version: Option<String>,
+
+    /// Set to `false` for features which never record exposure events.
+    #[serde(default = "has_exposure_default")]
+    #[serde(skip_serializing_if = "is_has_exposure_default")]
+    pub(crate) has_exposure: bool,
+}
+
+fn has_exposure_default() -> bool {
+    true
+}
+
+fn is_has_exposure_default(v: &bool) -> bool {
+    *v == has_exposure_default()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -279,6 +335,12 @@ pub struct ManifestFrontEnd {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub(crate) channels: Vec<String>,
 
+    /// Other names for the channels, e.g. `developer: nightly`, for build flavors which
+    /// don't match the name of a channel.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) aliases: BTreeMap<String, String>,
+
     #[serde(default)]
     #[serde(alias = "include")]
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -312,6 +374,33 @@ impl ManifestFrontEnd {
         self.channels.clone()
     }
 
+    /// Resolves a channel alias to the channel it stands for. Any other channel is returned
+    /// as is.
+    pub(crate) fn resolve_channel<'a>(&'a self, channel: &'a str) -> &'a str {
+        self.aliases.get(channel).map_or(channel, String::as_str)
+    }
+
+    fn check_aliases(&self) -> Result<()> {
+        for (alias, channel) in &self.aliases {
+            if self.channels.contains(alias) {
+                return Err(FMLError::ValidationError(
+                    format!("aliases/{alias}"),
+                    format!("`{alias}` is already a channel, so can't be an alias"),
+                ));
+            }
+            if !self.channels.contains(channel) {
+                return Err(FMLError::ValidationError(
+                    format!("aliases/{alias}"),
+                    format!(
+                        "`{alias}` is an alias for `{channel}`, which isn't one of the channels: {:?}",
+                        self.channels
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn includes(&self) -> Vec<String> {
         self.includes.clone()
     }
@@ -343,13 +432,35 @@ impl ManifestFrontEnd {
         res
     }
 
-    fn get_prop_def_from_feature_field(&self, nm: &str, body: &FeatureFieldBody) -> PropDef {
-        let mut prop = self.get_prop_def_from_field(nm, &body.field);
+    fn get_prop_def_from_feature_field(
+        &self,
+        nm: &str,
+        body: &FeatureFieldBody,
+    ) -> Result<PropDef, FMLError> {
+        let mut prop = self.get_prop_def_from_field(nm, &body.field)?;
         prop.pref_key = body.pref_key.clone();
         if let Some(s) = &body.string_alias {
             prop.string_alias = Some(TypeRef::StringAlias(s.clone()));
         }
-        prop
+        prop.required = body.required;
+        Ok(prop)
+    }
+
+    /// The value to use for a required variable without a default, if there is one for the type.
+    fn zero_value(&self, typ: &TypeRef) -> Option<serde_json::Value> {
+        Some(match typ {
+            TypeRef::Boolean => json!(false),
+            TypeRef::Int => json!(0),
+            TypeRef::String | TypeRef::BundleText => json!(""),
+            TypeRef::List(_) => json!([]),
+            TypeRef::StringMap(_) | TypeRef::Object(_) => json!({}),
+            TypeRef::Enum(nm) => {
+                // The first variant declared in the manifest, rather than the first by name.
+                let types = self.legacy_types.as_ref().unwrap_or(&self.types);
+                json!(types.enums.get(nm)?.variants.keys().next()?)
+            }
+            _ => return None,
+        })
     }
 
     /// Transforms a front-end field definition, a tuple of [`String`] and [`FieldBody`],
@@ -359,42 +470,52 @@ impl ManifestFrontEnd {
     /// - `field`: The [`(&String, &FieldBody)`] tuple to get the propdef from
     ///
     /// # Returns
-    /// return the IR [`PropDef`]
-    fn get_prop_def_from_field(&self, nm: &str, body: &FieldBody) -> PropDef {
+    /// return the IR [`PropDef`], or an error if the type is not an FML type or a user defined type.
+    fn get_prop_def_from_field(&self, nm: &str, body: &FieldBody) -> Result<PropDef, FMLError> {
         let types = self.get_types();
-        PropDef {
-            name: nm.into(),
-            doc: body.description.clone(),
-            typ: match get_typeref_from_string(body.variable_type.to_owned(), &types) {
-                Ok(type_ref) => type_ref,
-                Err(e) => {
-                    // Try matching against the user defined types
-                    match types.get(&body.variable_type) {
-                        Some(type_ref) => type_ref.to_owned(),
-                        None => panic!(
-                            "{}\n{} is not a valid FML type or user defined type",
-                            e, body.variable_type
-                        ),
+        let typ = match get_typeref_from_string(body.variable_type.to_owned(), &types) {
+            Ok(type_ref) => type_ref,
+            Err(e) => {
+                // Try matching against the user defined types
+                match types.get(&body.variable_type) {
+                    Some(type_ref) => type_ref.to_owned(),
+                    None => {
+                        return Err(FMLError::TypeParsingError(format!(
+                            "{}\n{} is not a valid FML type or user defined type, used by {}",
+                            e, body.variable_type, nm
+                        )))
                     }
                 }
-            },
+            }
+        };
+        Ok(PropDef {
+            name: nm.into(),
+            doc: body.description.clone(),
+            typ,
             default: json!(body.default),
             pref_key: None,
             string_alias: None,
-        }
+            required: false,
+        })
     }
 
     /// Retrieves all the feature definitions represented in the manifest
     ///
     /// # Returns
     /// Returns a [`std::collections::BTreeMap<String, FeatureDef>`]
+    /// Features are independent of each other, so for large manifests they are converted
+    /// in parallel.
     fn get_feature_defs(&self, merger: &DefaultsMerger) -> Result<BTreeMap<String, FeatureDef>> {
-        let mut features: BTreeMap<_, _> = Default::default();
-        for (nm, body) in &self.features {
+        let features = par_try_map_all(&self.features, |(nm, body)| -> Result<_> {
             let mut fields: Vec<_> = Default::default();
+            let mut errors: Vec<_> = Default::default();
             for (fnm, field) in &body.variables {
-                fields.push(self.get_prop_def_from_feature_field(fnm, field));
+                match self.get_prop_def_from_feature_variable(nm, fnm, field) {
+                    Ok(prop) => fields.push(prop),
+                    Err(e) => errors.push(e),
+                }
             }
+            FMLError::from_all(errors)?;
             let examples = body.examples.iter().map(Into::into).collect();
 
             let mut def = FeatureDef {
@@ -402,25 +523,58 @@ impl ManifestFrontEnd {
                 metadata: body.metadata.clone(),
                 props: fields,
                 allow_coenrollment: body.allow_coenrollment,
+                has_exposure: body.has_exposure,
+                min_version: body.min_version.clone(),
                 examples,
             };
             merger.merge_feature_defaults(&mut def, &body.default)?;
-            features.insert(nm.to_owned(), def);
+            Ok((nm.to_owned(), def))
+        })?;
+        Ok(features.into_iter().collect())
+    }
+
+    fn get_prop_def_from_feature_variable(
+        &self,
+        nm: &str,
+        fnm: &str,
+        field: &FeatureFieldBody,
+    ) -> Result<PropDef> {
+        let path = format!("features/{nm}.{fnm}");
+        if !field.required {
+            field.field.check_has_default(path.clone())?;
+        }
+        let mut prop = self.get_prop_def_from_feature_field(fnm, field)?;
+        if field.field.default.is_none() {
+            // Required variables don't need a default, but the generated code still
+            // needs a value to fall back on, even if it should never be used.
+            prop.default = self.zero_value(&prop.typ).ok_or_else(|| {
+                FMLError::ValidationError(
+                    path,
+                    format!("A default is required for variables of type {}", prop.typ),
+                )
+            })?;
         }
-        Ok(features)
+        Ok(prop)
     }
 
     /// Retrieves all the Object type definitions represented in the manifest
     ///
     /// # Returns
-    /// Returns a [`std::collections::BTreeMap<String. ObjectDef>`]
-    fn get_objects(&self) -> BTreeMap<String, ObjectDef> {
+    /// Returns a [`std::collections::BTreeMap<String. ObjectDef>`]. Fields which can't be
+    /// converted are left out, and their errors added to `errors`.
+    fn get_objects(&self, errors: &mut Vec<FMLError>) -> BTreeMap<String, ObjectDef> {
         let types = self.legacy_types.as_ref().unwrap_or(&self.types);
         let mut objs: BTreeMap<_, _> = Default::default();
         for (nm, body) in &types.objects {
             let mut fields: Vec<_> = Default::default();
             for (fnm, field) in &body.fields {
-                fields.push(self.get_prop_def_from_field(fnm, field));
+                let prop = field
+                    .check_has_default(format!("objects/{nm}.{fnm}"))
+                    .and_then(|_| self.get_prop_def_from_field(fnm, field));
+                match prop {
+                    Ok(prop) => fields.push(prop),
+                    Err(e) => errors.push(e),
+                }
             }
             objs.insert(
                 nm.to_owned(),
@@ -449,6 +603,8 @@ impl ManifestFrontEnd {
                     doc: v_body.description.clone(),
                 });
             }
+            // The generated code lists the variants by name, whatever order they're declared in.
+            variants.sort_by(|a, b| a.name.cmp(&b.name));
             enums.insert(
                 name.to_owned(),
                 EnumDef {
@@ -466,12 +622,23 @@ impl ManifestFrontEnd {
         id: &ModuleId,
         channel: Option<&str>,
     ) -> Result<FeatureManifest> {
+        self.check_aliases()?;
+        let channel = channel.map(|c| self.resolve_channel(c));
         let enums = self.get_enums();
-        let objects = self.get_objects();
+        let mut errors = Default::default();
+        let objects = self.get_objects(&mut errors);
         let merger =
             DefaultsMerger::new(&objects, self.channels.clone(), channel.map(str::to_string));
 
-        let features = self.get_feature_defs(&merger)?;
+        // Report the problems with every object and feature, not just the first.
+        let features = match self.get_feature_defs(&merger) {
+            Ok(features) => features,
+            Err(e) => {
+                errors.push(e);
+                Default::default()
+            }
+        };
+        FMLError::from_all(errors)?;
 
         let about = match &self.about {
             Some(a) => a.clone(),
@@ -543,6 +710,23 @@ pub struct DefaultBlock {
     pub(crate) value: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) targeting: Option<String>,
+    /// How the variables in this block are combined with the defaults before it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) merge: Option<MergeMode>,
+}
+
+/// How a default block overrides the defaults before it.
+///
+/// Without a `merge`, maps are merged key by key, and everything else, including lists,
+/// is replaced.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    /// Lists are appended to the lists before them; maps are merged as usual.
+    Append,
+    /// Each variable in the block replaces the earlier default outright, without merging.
+    Replace,
 }
 
 impl DefaultBlock {
@@ -579,6 +763,7 @@ impl From<serde_json::Value> for DefaultBlock {
             channels: None,
             channel: None,
             targeting: None,
+            merge: None,
         }
     }
 }
@@ -996,3 +1181,444 @@ mod feature_additions {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod field_defaults {
+    use super::*;
+
+    fn get_ir(yaml: &str) -> Result<FeatureManifest> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(yaml)?;
+        frontend.get_intermediate_representation(&Default::default(), None)
+    }
+
+    #[test]
+    fn test_explicit_null_default() -> Result<()> {
+        let fm = get_ir(
+            r#"
+            features:
+              my-feature:
+                description: A feature
+                variables:
+                  my-optional:
+                    description: An optional variable
+                    type: Option<String>
+                    default: null
+            "#,
+        )?;
+        let feature = fm.get_feature("my-feature").unwrap();
+        assert_eq!(feature.props[0].default, serde_json::Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_default_for_feature_variable() -> Result<()> {
+        for typ in ["String", "Option<String>"] {
+            let result = get_ir(&format!(
+                r#"
+                features:
+                  my-feature:
+                    description: A feature
+                    variables:
+                      my-variable:
+                        description: A variable without a default
+                        type: {typ}
+                "#,
+            ));
+            assert!(matches!(
+                result,
+                Err(FMLError::ValidationError(path, _)) if path == "features/my-feature.my-variable"
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_default_for_object_field() -> Result<()> {
+        let result = get_ir(
+            r#"
+            objects:
+              MyObject:
+                description: An object
+                fields:
+                  my-field:
+                    description: A field without a default
+                    type: Int
+            "#,
+        );
+        assert!(matches!(
+            result,
+            Err(FMLError::ValidationError(path, _)) if path == "objects/MyObject.my-field"
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod required_variables {
+    use serde_json::json;
+
+    use super::*;
+
+    fn get_ir(variables: &str) -> Result<FeatureManifest> {
+        let yaml = format!(
+            r#"
+            types:
+              enums:
+                Position:
+                  description: An enum
+                  variants:
+                    top:
+                      description: The top
+                    bottom:
+                      description: The bottom
+            features:
+              my-feature:
+                description: A feature
+                variables:
+            {variables}
+            "#
+        );
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(&yaml)?;
+        frontend.get_intermediate_representation(&Default::default(), None)
+    }
+
+    #[test]
+    fn test_required_variables_without_defaults() -> Result<()> {
+        let fm = get_ir(
+            r#"
+                  my-string:
+                    description: A required string
+                    type: String
+                    required: true
+                  my-position:
+                    description: A required enum
+                    type: Position
+                    required: true
+                  my-int:
+                    description: A required int, with a default
+                    type: Int
+                    required: true
+                    default: 42
+            "#,
+        )?;
+        fm.validate_manifest()?;
+
+        let feature = fm.get_feature("my-feature").unwrap();
+        assert!(feature.props.iter().all(PropDef::required));
+        assert_eq!(
+            feature.default_json(),
+            json!({ "my-int": 42, "my-position": "top", "my-string": "" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_required_variables_must_be_provided() -> Result<()> {
+        let fm = get_ir(
+            r#"
+                  my-string:
+                    description: A required string
+                    type: String
+                    required: true
+                  my-int:
+                    description: An int
+                    type: Int
+                    default: 42
+            "#,
+        )?;
+
+        assert!(fm
+            .validate_feature_config("my-feature", json!({ "my-int": 1 }))
+            .is_err());
+
+        let feature =
+            fm.validate_feature_config("my-feature", json!({ "my-string": "provided" }))?;
+        assert_eq!(
+            feature.default_json(),
+            json!({ "my-int": 42, "my-string": "provided" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_required_variables() -> Result<()> {
+        let fm = get_ir(
+            r#"
+                  my-optional:
+                    description: A required optional
+                    type: Option<String>
+                    required: true
+                    default: null
+            "#,
+        )?;
+        assert!(fm.validate_manifest().is_err());
+
+        // There's no sensible value for a missing image.
+        let result = get_ir(
+            r#"
+                  my-image:
+                    description: A required image
+                    type: Image
+                    required: true
+            "#,
+        );
+        assert!(matches!(
+            result,
+            Err(FMLError::ValidationError(path, _)) if path == "features/my-feature.my-image"
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod feature_exposure {
+    use super::*;
+
+    #[test]
+    fn test_has_exposure() -> Result<()> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(
+            r#"
+            features:
+              seen-feature:
+                description: A feature the user sees
+                variables: {}
+              unseen-feature:
+                description: A feature the user never sees
+                has-exposure: false
+                variables: {}
+            "#,
+        )?;
+        let fm = frontend.get_intermediate_representation(&Default::default(), None)?;
+
+        assert!(fm.get_feature("seen-feature").unwrap().has_exposure());
+        assert!(!fm.get_feature("unseen-feature").unwrap().has_exposure());
+
+        let yaml = serde_yaml::to_string(&frontend)?;
+        // Only the opted out feature needs to say so.
+        assert_eq!(yaml.matches("has-exposure").count(), 1);
+        assert!(yaml.contains("has-exposure: false"));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod collecting_errors {
+    use super::*;
+
+    #[test]
+    fn test_all_type_errors_are_returned() -> Result<()> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(
+            r#"
+            objects:
+              Button:
+                description: A button
+                fields:
+                  color:
+                    description: The color
+                    type: Colour
+                    default: red
+            features:
+              homescreen:
+                description: The homescreen
+                variables:
+                  title:
+                    description: The title
+                    type: Strin
+                    default: Home
+                  count:
+                    description: The count
+                    type: Integer
+                    default: 1
+              settings:
+                description: The settings
+                variables:
+                  enabled:
+                    description: Whether enabled
+                    type: Bool
+                    default: true
+            "#,
+        )?;
+        let err = frontend
+            .get_intermediate_representation(&Default::default(), None)
+            .expect_err("Unknown types should be errors");
+
+        let errors = err.into_all();
+        assert_eq!(errors.len(), 4);
+        for ty in ["Colour", "Strin", "Integer", "Bool"] {
+            assert!(
+                errors.iter().any(|e| e.to_string().contains(ty)),
+                "Missing error for {ty}"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod feature_min_version {
+    use super::*;
+
+    #[test]
+    fn test_min_version() -> Result<()> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(
+            r#"
+            features:
+              old-feature:
+                description: A feature every version supports
+                variables: {}
+              new-feature:
+                description: A feature only newer versions support
+                min-version: "118.0"
+                variables: {}
+            "#,
+        )?;
+        let fm = frontend.get_intermediate_representation(&Default::default(), None)?;
+        fm.validate_manifest()?;
+
+        assert_eq!(fm.get_feature("old-feature").unwrap().min_version(), None);
+        assert_eq!(
+            fm.get_feature("new-feature").unwrap().min_version(),
+            Some("118.0".to_string())
+        );
+        assert_eq!(
+            fm.get_feature_min_versions(),
+            BTreeMap::from([("new-feature".to_string(), "118.0".to_string())])
+        );
+
+        let yaml = serde_yaml::to_string(&frontend)?;
+        assert_eq!(yaml.matches("min-version").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_min_version() -> Result<()> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(
+            r#"
+            features:
+              new-feature:
+                description: A feature only newer versions support
+                min_version: soon
+                variables: {}
+            "#,
+        )?;
+        let fm = frontend.get_intermediate_representation(&Default::default(), None)?;
+        let err = fm.validate_manifest().expect_err("soon is not a version");
+        assert!(err.to_string().contains("Invalid min-version soon"));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod channel_aliases {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        channels:
+          - release
+          - nightly
+        aliases:
+          developer: nightly
+        features:
+          homescreen:
+            description: The homescreen
+            variables:
+              title:
+                description: The title
+                type: String
+                default: Home
+            defaults:
+              - channel: nightly
+                value:
+                  title: Home (Nightly)
+        "#;
+
+    #[test]
+    fn test_alias_resolves_to_channel() -> Result<()> {
+        let frontend: ManifestFrontEnd = serde_yaml::from_str(MANIFEST)?;
+        assert_eq!(frontend.resolve_channel("developer"), "nightly");
+        assert_eq!(frontend.resolve_channel("release"), "release");
+
+        let title = |channel| -> Result<serde_json::Value> {
+            let fm =
+                frontend.get_intermediate_representation(&Default::default(), Some(channel))?;
+            Ok(fm.get_feature("homescreen").unwrap().props[0]
+                .default
+                .clone())
+        };
+        assert_eq!(title("developer")?, "Home (Nightly)");
+        assert_eq!(title("nightly")?, "Home (Nightly)");
+        assert_eq!(title("release")?, "Home");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_aliases() -> Result<()> {
+        let mut frontend: ManifestFrontEnd = serde_yaml::from_str(MANIFEST)?;
+        frontend.aliases = BTreeMap::from([("developer".into(), "beta".into())]);
+        let err = frontend
+            .get_intermediate_representation(&Default::default(), Some("developer"))
+            .expect_err("beta is not a channel");
+        assert!(err.to_string().contains("isn't one of the channels"));
+
+        frontend.aliases = BTreeMap::from([("release".into(), "nightly".into())]);
+        let err = frontend
+            .get_intermediate_representation(&Default::default(), Some("release"))
+            .expect_err("release is already a channel");
+        assert!(err.to_string().contains("already a channel"));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod parallel_features {
+    use super::*;
+
+    fn manifest(n: usize, invalid: &[usize]) -> Result<ManifestFrontEnd> {
+        let mut features = BTreeMap::new();
+        for i in 0..n {
+            let default = if invalid.contains(&i) {
+                json!("not a number")
+            } else {
+                json!(i)
+            };
+            features.insert(
+                format!("feature-{i:03}"),
+                json!({
+                    "description": "A feature",
+                    "variables": {
+                        "count": { "description": "A count", "type": "Int", "default": default }
+                    }
+                }),
+            );
+        }
+        Ok(serde_json::from_value(json!({ "features": features }))?)
+    }
+
+    #[test]
+    fn test_features_are_in_order() -> Result<()> {
+        let fm = manifest(200, &[])?.get_intermediate_representation(&Default::default(), None)?;
+        let names = fm.iter_feature_defs().map(|f| f.name()).collect::<Vec<_>>();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names.len(), 200);
+        assert_eq!(names, sorted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_error_is_reported() -> Result<()> {
+        let fm = manifest(200, &[150, 42, 7])?
+            .get_intermediate_representation(&Default::default(), None)?;
+        for _ in 0..10 {
+            let err = fm.validate_manifest().expect_err("defaults are invalid");
+            assert!(err.to_string().contains("feature-007"), "{err}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Imports the feature definitions from Firefox desktop's `FeatureManifest.yaml`, so that
+//! mobile and desktop can share feature schemas.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use email_address::EmailAddress;
+use heck::CamelCase;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::frontend::{
+    AboutBlock, EnumBody, EnumVariantBody, FeatureBody, FeatureFieldBody, FeatureMetadata,
+    FieldBody, ManifestFrontEnd,
+};
+
+/// The features in a desktop `FeatureManifest.yaml`, keyed by feature id.
+pub(crate) type DesktopManifest = BTreeMap<String, DesktopFeature>;
+
+/// A desktop feature. Properties which have no equivalent in FML, e.g. `isEarlyStartup` or
+/// `applications`, are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DesktopFeature {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    has_exposure: bool,
+    #[serde(default)]
+    allow_coenrollment: bool,
+    #[serde(default)]
+    variables: BTreeMap<String, DesktopVariable>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DesktopVariable {
+    #[serde(rename = "type")]
+    variable_type: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    fallback_pref: Option<String>,
+    #[serde(default)]
+    set_pref: Option<SetPref>,
+    #[serde(default, rename = "enum")]
+    variants: Vec<Value>,
+}
+
+/// `setPref` is either the name of the pref, or the pref and the branch it is set on.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SetPref {
+    Pref(String),
+    Branch { pref: String },
+}
+
+impl DesktopVariable {
+    fn pref(&self) -> Option<&str> {
+        match &self.set_pref {
+            Some(SetPref::Pref(pref) | SetPref::Branch { pref }) => Some(pref),
+            None => self.fallback_pref.as_deref(),
+        }
+    }
+}
+
+/// Converts the desktop features into an FML manifest.
+///
+/// Variables are given the zero value of their type as their default, since desktop
+/// defaults live in prefs rather than in the manifest. Desktop's `json` variables have no
+/// schema to convert, so are left out; a warning is returned for each of them.
+pub(crate) fn import_desktop_manifest(
+    desktop: &DesktopManifest,
+) -> (ManifestFrontEnd, Vec<String>) {
+    let mut manifest = ManifestFrontEnd {
+        version: "1.0.0".to_string(),
+        about: Some(AboutBlock {
+            description: "Features imported from Firefox desktop".to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut warnings = Vec::new();
+
+    for (feature_id, feature) in desktop {
+        let mut variables = BTreeMap::new();
+        for (name, variable) in &feature.variables {
+            let path = format!("{feature_id}.{name}");
+            let (variable_type, default) = match variable.variable_type.as_str() {
+                "boolean" => ("Boolean".to_string(), json!(false)),
+                "int" => ("Int".to_string(), json!(0)),
+                "string" if !variable.variants.is_empty() => {
+                    let enum_name = format!("{feature_id}-{name}").to_camel_case();
+                    let variants = variable
+                        .variants
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(|v| {
+                            let body = EnumVariantBody {
+                                description: format!("`{v}`"),
+                            };
+                            (v.to_string(), body)
+                        })
+                        .collect();
+                    let Some(first) = variable.variants.iter().find_map(Value::as_str) else {
+                        warnings.push(format!("{path}: enum has no string values, skipped"));
+                        continue;
+                    };
+                    let default = json!(first);
+                    manifest.types.enums.insert(
+                        enum_name.clone(),
+                        EnumBody {
+                            description: format!("The values of `{name}` in `{feature_id}`."),
+                            variants,
+                        },
+                    );
+                    (enum_name, default)
+                }
+                "string" => ("String".to_string(), json!("")),
+                t => {
+                    warnings.push(format!(
+                        "{path}: `{t}` variables can't be imported, skipped"
+                    ));
+                    continue;
+                }
+            };
+            // Prefs are only supported for the primitive types.
+            let pref_key = match variable_type.as_str() {
+                "Boolean" | "Int" | "String" => variable.pref().map(str::to_string),
+                _ => None,
+            };
+            variables.insert(
+                name.clone(),
+                FeatureFieldBody {
+                    field: FieldBody {
+                        description: variable.description.clone(),
+                        variable_type,
+                        default: Some(default),
+                    },
+                    pref_key,
+                    string_alias: None,
+                    required: false,
+                },
+            );
+        }
+
+        let contacts = feature
+            .owner
+            .iter()
+            .filter_map(|owner| EmailAddress::from_str(owner).ok())
+            .collect();
+        manifest.features.insert(
+            feature_id.clone(),
+            FeatureBody {
+                metadata: FeatureMetadata {
+                    description: feature.description.clone(),
+                    contacts,
+                    ..Default::default()
+                },
+                variables,
+                default: None,
+                allow_coenrollment: feature.allow_coenrollment,
+                examples: Default::default(),
+                min_version: None,
+                has_exposure: feature.has_exposure,
+            },
+        );
+    }
+
+    (manifest, warnings)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{error::Result, intermediate_representation::TypeRef};
+
+    const DESKTOP_MANIFEST: &str = r#"
+urlbar:
+  description: The address bar
+  owner: jdoe@example.com
+  hasExposure: true
+  exposureDescription: Exposure is sent when the urlbar is used
+  isEarlyStartup: false
+  variables:
+    enabled:
+      type: boolean
+      description: Whether the feature is enabled
+      fallbackPref: browser.urlbar.enabled
+    maxResults:
+      type: int
+      description: The maximum number of results
+      setPref:
+        branch: user
+        pref: browser.urlbar.maxRichResults
+    layout:
+      type: string
+      description: The layout of the results
+      enum:
+        - compact
+        - expanded
+    extras:
+      type: json
+      description: Anything else
+"#;
+
+    #[test]
+    fn test_import_desktop_manifest() -> Result<()> {
+        let desktop: DesktopManifest = serde_yaml::from_str(DESKTOP_MANIFEST)?;
+        let (manifest, warnings) = import_desktop_manifest(&desktop);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("urlbar.extras"));
+
+        let feature = &manifest.features["urlbar"];
+        assert!(feature.has_exposure);
+        assert_eq!(feature.metadata.contacts.len(), 1);
+
+        let max_results = &feature.variables["maxResults"];
+        assert_eq!(
+            max_results.pref_key.as_deref(),
+            Some("browser.urlbar.maxRichResults")
+        );
+
+        let fm = manifest.get_intermediate_representation(&Default::default(), None)?;
+        fm.validate_manifest()?;
+
+        let feature = fm.get_feature("urlbar").unwrap();
+        let layout = feature.props.iter().find(|p| p.name == "layout").unwrap();
+        assert_eq!(layout.typ, TypeRef::Enum("UrlbarLayout".to_string()));
+        assert_eq!(layout.default, json!("compact"));
+        assert_eq!(fm.find_enum("UrlbarLayout").unwrap().variants.len(), 2);
+
+        let yaml = serde_yaml::to_string(&manifest)?;
+        let reparsed: ManifestFrontEnd = serde_yaml::from_str(&yaml)?;
+        assert_eq!(reparsed.features["urlbar"].variables.len(), 3);
+        Ok(())
+    }
+}
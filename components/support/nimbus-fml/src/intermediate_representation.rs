@@ -1,6 +1,7 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
 * License, v. 2.0. If a copy of the MPL was not distributed with this
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use crate::backends::find_backend;
 use crate::defaults::{DefaultsHasher, DefaultsMerger, DefaultsValidator};
 use crate::error::FMLError::InvalidFeatureError;
 use crate::error::{FMLError, Result};
@@ -9,6 +10,7 @@ use crate::frontend::{
 };
 use crate::schema::{SchemaHasher, SchemaValidator, TypeQuery};
 use crate::util::loaders::FilePath;
+use crate::util::par_try_map;
 use anyhow::{bail, Error, Result as AnyhowResult};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
@@ -22,6 +24,11 @@ pub enum TargetLanguage {
     IR,
     ExperimenterYAML,
     ExperimenterJSON,
+    /// A backend registered with [crate::backends::register_backend].
+    Custom {
+        language: String,
+        extension: String,
+    },
 }
 
 impl TargetLanguage {
@@ -32,6 +39,7 @@ impl TargetLanguage {
             TargetLanguage::IR => "fml.json",
             TargetLanguage::ExperimenterJSON => "json",
             TargetLanguage::ExperimenterYAML => "yaml",
+            TargetLanguage::Custom { extension, .. } => extension,
         }
     }
 
@@ -53,7 +61,13 @@ impl TryFrom<&str> for TargetLanguage {
             "fml.json" => TargetLanguage::IR,
             "yaml" => TargetLanguage::ExperimenterYAML,
             "json" => TargetLanguage::ExperimenterJSON,
-            _ => bail!("Unknown or unsupported target language: \"{}\"", value),
+            _ => match find_backend(value) {
+                Some(backend) => TargetLanguage::Custom {
+                    language: backend.language().to_string(),
+                    extension: backend.extension().to_string(),
+                },
+                None => bail!("Unknown or unsupported target language: \"{}\"", value),
+            },
         })
     }
 }
@@ -319,23 +333,23 @@ impl FeatureManifest {
 
     fn validate_schema(&self) -> Result<(), FMLError> {
         let validator = SchemaValidator::new(&self.enum_defs, &self.obj_defs);
-        for object in self.iter_object_defs() {
-            validator.validate_object_def(object)?;
-        }
-        for feature_def in self.iter_feature_defs() {
-            validator.validate_feature_def(feature_def)?;
-        }
+        par_try_map(&self.obj_defs, |(_, object)| {
+            validator.validate_object_def(object)
+        })?;
+        par_try_map(&self.feature_defs, |(_, feature_def)| {
+            validator.validate_feature_def(feature_def)
+        })?;
         Ok(())
     }
 
     fn validate_defaults(&self) -> Result<()> {
         let validator = DefaultsValidator::new(&self.enum_defs, &self.obj_defs);
-        for object in self.iter_object_defs() {
-            validator.validate_object_def(object)?;
-        }
-        for feature in self.iter_feature_defs() {
-            validator.validate_feature_def(feature)?;
-        }
+        par_try_map(&self.obj_defs, |(_, object)| {
+            validator.validate_object_def(object)
+        })?;
+        par_try_map(&self.feature_defs, |(_, feature)| {
+            validator.validate_feature_def(feature)
+        })?;
         Ok(())
     }
 
@@ -413,6 +427,12 @@ impl FeatureManifest {
             .collect()
     }
 
+    pub fn get_feature_min_versions(&self) -> BTreeMap<String, String> {
+        self.iter_all_feature_defs()
+            .filter_map(|(_, f)| Some((f.name(), f.min_version()?)))
+            .collect()
+    }
+
     pub fn find_feature(&self, nm: &str) -> Option<(&FeatureManifest, &FeatureDef)> {
         if let Some(f) = self.get_feature(nm) {
             Some((self, f))
@@ -495,7 +515,7 @@ impl FeatureManifest {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct FeatureDef {
     pub(crate) name: String,
     #[serde(flatten)]
@@ -505,6 +525,32 @@ pub struct FeatureDef {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub(crate) examples: Vec<FeatureExample>,
+    /// Features which opt out of exposure events don't get a `recordExposure()` method.
+    #[serde(default = "default_has_exposure")]
+    pub(crate) has_exposure: bool,
+    /// The earliest version of the app which supports this feature. Configurations for this
+    /// feature shouldn't be applied to older versions.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min_version: Option<String>,
+}
+
+fn default_has_exposure() -> bool {
+    true
+}
+
+impl Default for FeatureDef {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            metadata: Default::default(),
+            props: Default::default(),
+            allow_coenrollment: false,
+            examples: Default::default(),
+            has_exposure: default_has_exposure(),
+            min_version: None,
+        }
+    }
 }
 
 impl FeatureDef {
@@ -526,6 +572,40 @@ impl FeatureDef {
     pub fn doc(&self) -> String {
         self.metadata.description.clone()
     }
+    /// The description, followed by a list of the contacts, bugs and links from the feature's
+    /// metadata. This is used for the doc comments in generated code.
+    pub fn doc_with_metadata(&self) -> String {
+        let metadata = &self.metadata;
+        let mut items = Vec::new();
+        if !metadata.contacts.is_empty() {
+            let contacts = metadata.contacts.iter().map(|c| c.to_string());
+            items.push(format!(
+                "Contacts: {}",
+                contacts.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if let Some(url) = &metadata.meta_bug {
+            items.push(format!("Bug: {url}"));
+        }
+        for link in &metadata.documentation {
+            items.push(format!("Documentation: [{}]({})", link.name, link.url));
+        }
+        for url in &metadata.events {
+            items.push(format!("Events: {url}"));
+        }
+        if let Some(url) = &metadata.configurator {
+            items.push(format!("Configurator: {url}"));
+        }
+
+        let mut doc = self.doc();
+        if !items.is_empty() {
+            doc.push('\n');
+            for item in items {
+                doc.push_str(&format!("\n- {item}"));
+            }
+        }
+        doc
+    }
     pub fn props(&self) -> Vec<PropDef> {
         self.props.clone()
     }
@@ -533,6 +613,14 @@ impl FeatureDef {
         self.allow_coenrollment
     }
 
+    pub fn has_exposure(&self) -> bool {
+        self.has_exposure
+    }
+
+    pub fn min_version(&self) -> Option<String> {
+        self.min_version.clone()
+    }
+
     pub fn default_json(&self) -> Value {
         let mut props = Map::new();
 
@@ -662,6 +750,10 @@ pub struct PropDef {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) string_alias: Option<TypeRef>,
+    /// Required variables must be provided by every remote configuration of the feature.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) required: bool,
 }
 
 impl PropDef {
@@ -683,6 +775,9 @@ impl PropDef {
     pub fn pref_key(&self) -> Option<String> {
         self.pref_key.clone()
     }
+    pub fn required(&self) -> bool {
+        self.required
+    }
 }
 
 impl TypeFinder for PropDef {
@@ -781,6 +876,30 @@ pub mod unit_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_doc_with_metadata() -> Result<()> {
+        let mut feature = FeatureDef::new("some_def", "A lovely feature.", vec![], false);
+        assert_eq!(feature.doc_with_metadata(), "A lovely feature.");
+
+        feature.metadata = serde_json::from_value(json!({
+            "description": "A lovely feature.",
+            "owners": ["jdoe@example.com", "jroe@example.com"],
+            "meta-bug": "https://example.com/bug/1",
+            "documentation": [{ "name": "User guide", "url": "https://example.com/guide" }],
+        }))?;
+        assert_eq!(
+            feature.doc_with_metadata(),
+            "A lovely feature.\n\
+            \n- Contacts: jdoe@example.com, jroe@example.com\
+            \n- Bug: https://example.com/bug/1\
+            \n- Documentation: [User guide](https://example.com/guide)"
+        );
+        // The plain description is still used outside of generated code.
+        assert_eq!(feature.doc(), "A lovely feature.");
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
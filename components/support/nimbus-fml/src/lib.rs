@@ -5,14 +5,20 @@
 mod backends;
 pub mod command_line;
 pub(crate) mod defaults;
+pub(crate) mod diagnostics;
 mod editing;
 pub mod error;
 pub(crate) mod frontend;
+pub(crate) mod importers;
 pub mod intermediate_representation;
+pub(crate) mod lint;
+pub(crate) mod migrations;
 pub mod parser;
 pub(crate) mod schema;
 pub mod util;
 
+pub use crate::backends::{register_backend, CodegenBackend, GeneratedFile};
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "client-lib")] {
         pub mod client;
@@ -0,0 +1,319 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Style checks for manifests, beyond what is needed for the manifest to be valid.
+//!
+//! Each rule can be turned off, or made a warning or an error, in the `lint` section of the
+//! project config file:
+//!
+//! ```yaml
+//! lint:
+//!   feature-names: error
+//!   variable-names: warn
+//!   descriptions: warn
+//!   nesting-depth: warn
+//!   max-nesting-depth: 3
+//! ```
+
+use std::collections::HashSet;
+
+use heck::{KebabCase, MixedCase};
+use serde::Deserialize;
+
+use crate::{
+    diagnostics::Severity,
+    intermediate_representation::{FeatureManifest, PropDef, TypeRef},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LintLevel {
+    Off,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+impl LintLevel {
+    fn severity(&self) -> Option<Severity> {
+        match self {
+            Self::Off => None,
+            Self::Warn => Some(Severity::Warning),
+            Self::Error => Some(Severity::Error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LintConfig {
+    /// Feature names should be kebab-case, e.g. `new-tab-page`.
+    #[serde(default = "LintConfig::warn")]
+    pub(crate) feature_names: LintLevel,
+    /// Variable names should be camelCase, e.g. `sectionsEnabled`.
+    #[serde(default = "LintConfig::off")]
+    pub(crate) variable_names: LintLevel,
+    /// Features, variables, objects, fields, enums and variants should all have descriptions.
+    #[serde(default = "LintConfig::warn")]
+    pub(crate) descriptions: LintLevel,
+    /// Variables should not nest objects, lists and maps more than `max_nesting_depth` deep.
+    #[serde(default = "LintConfig::warn")]
+    pub(crate) nesting_depth: LintLevel,
+    #[serde(default = "LintConfig::default_max_nesting_depth")]
+    pub(crate) max_nesting_depth: usize,
+}
+
+impl LintConfig {
+    fn off() -> LintLevel {
+        LintLevel::Off
+    }
+
+    fn warn() -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn default_max_nesting_depth() -> usize {
+        4
+    }
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            feature_names: Self::warn(),
+            variable_names: Self::off(),
+            descriptions: Self::warn(),
+            nesting_depth: Self::warn(),
+            max_nesting_depth: Self::default_max_nesting_depth(),
+        }
+    }
+}
+
+/// A problem found by one of the lint rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lint {
+    pub(crate) severity: Severity,
+    /// The name of the rule, e.g. `max-nesting-depth`.
+    pub(crate) rule: String,
+    /// The feature, object or enum the problem was found in, e.g. `features/homescreen`.
+    pub(crate) group: String,
+    pub(crate) message: String,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    manifest: &'a FeatureManifest,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, level: LintLevel, rule: &str, group: &str, message: String) {
+        if let Some(severity) = level.severity() {
+            self.lints.push(Lint {
+                severity,
+                rule: rule.to_string(),
+                group: group.to_string(),
+                message: format!("{message} [{rule}]"),
+            });
+        }
+    }
+
+    fn check_description(&mut self, group: &str, what: &str, description: &str) {
+        if description.trim().is_empty() {
+            self.report(
+                self.config.descriptions,
+                "descriptions",
+                group,
+                format!("{what} has no description"),
+            );
+        }
+    }
+
+    fn check_props(&mut self, group: &str, props: &[PropDef], is_feature: bool) {
+        for prop in props {
+            let what = format!("`{}`", prop.name);
+            self.check_description(group, &what, &prop.doc);
+            if is_feature && prop.name.to_mixed_case() != prop.name {
+                self.report(
+                    self.config.variable_names,
+                    "variable-names",
+                    group,
+                    format!("Variable {what} should be camelCase"),
+                );
+            }
+            let depth = self.depth(&prop.typ, &mut HashSet::new());
+            if depth > self.config.max_nesting_depth {
+                self.report(
+                    self.config.nesting_depth,
+                    "nesting-depth",
+                    group,
+                    format!(
+                        "{what} is nested {depth} deep, more than the maximum of {}",
+                        self.config.max_nesting_depth
+                    ),
+                );
+            }
+        }
+    }
+
+    /// How many objects, lists and maps deep a value of this type can be. Recursive
+    /// objects are only counted once.
+    fn depth(&self, typ: &TypeRef, seen: &mut HashSet<String>) -> usize {
+        match typ {
+            TypeRef::Option(inner) => self.depth(inner, seen),
+            TypeRef::List(inner) | TypeRef::StringMap(inner) | TypeRef::EnumMap(_, inner) => {
+                1 + self.depth(inner, seen)
+            }
+            TypeRef::Object(name) => {
+                if !seen.insert(name.clone()) {
+                    return 1;
+                }
+                let depth = self
+                    .manifest
+                    .find_object(name)
+                    .map(|object| {
+                        object
+                            .props
+                            .iter()
+                            .map(|p| self.depth(&p.typ, seen))
+                            .max()
+                            .unwrap_or_default()
+                    })
+                    .unwrap_or_default();
+                seen.remove(name);
+                1 + depth
+            }
+            _ => 0,
+        }
+    }
+}
+
+impl FeatureManifest {
+    /// Checks the features, objects and enums defined in this manifest against the lint
+    /// rules. Imported manifests aren't checked, since they are maintained elsewhere.
+    pub(crate) fn lint(&self, config: &LintConfig) -> Vec<Lint> {
+        let mut linter = Linter {
+            config,
+            manifest: self,
+            lints: Default::default(),
+        };
+
+        for feature in self.iter_feature_defs() {
+            let group = format!("features/{}", feature.name);
+            if feature.name.to_kebab_case() != feature.name {
+                linter.report(
+                    config.feature_names,
+                    "feature-names",
+                    &group,
+                    format!("Feature `{}` should be kebab-case", feature.name),
+                );
+            }
+            linter.check_description(&group, "The feature", &feature.metadata.description);
+            linter.check_props(&group, &feature.props, true);
+        }
+
+        for object in self.iter_object_defs() {
+            let group = format!("objects/{}", object.name);
+            linter.check_description(&group, "The object", &object.doc);
+            linter.check_props(&group, &object.props, false);
+        }
+
+        for e in self.iter_enum_defs() {
+            let group = format!("enums/{}", e.name);
+            linter.check_description(&group, "The enum", &e.doc);
+            for variant in &e.variants {
+                linter.check_description(&group, &format!("`{}`", variant.name), &variant.doc);
+            }
+        }
+
+        linter.lints
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        fixtures::intermediate_representation::get_feature_manifest,
+        intermediate_representation::{FeatureDef, ObjectDef},
+    };
+
+    fn manifest() -> FeatureManifest {
+        let nested = TypeRef::List(Box::new(TypeRef::StringMap(Box::new(TypeRef::Object(
+            "Card".into(),
+        )))));
+        get_feature_manifest(
+            vec![ObjectDef {
+                doc: " ".into(),
+                ..ObjectDef::new(
+                    "Card",
+                    &[PropDef::new(
+                        "tags",
+                        &TypeRef::List(Box::new(TypeRef::String)),
+                        &json!([]),
+                    )],
+                )
+            }],
+            vec![],
+            vec![FeatureDef::new(
+                "home_screen",
+                "The home screen",
+                vec![
+                    PropDef::new("sections-enabled", &TypeRef::Boolean, &json!(true)),
+                    PropDef {
+                        doc: "".into(),
+                        ..PropDef::new("cards", &nested, &json!([]))
+                    },
+                ],
+                false,
+            )],
+            Default::default(),
+        )
+    }
+
+    fn messages(lints: &[Lint]) -> Vec<String> {
+        lints
+            .iter()
+            .map(|l| format!("{:?} {}: {}", l.severity, l.group, l.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let lints = manifest().lint(&Default::default());
+        assert_eq!(
+            messages(&lints),
+            vec![
+                "Warning features/home_screen: Feature `home_screen` should be kebab-case [feature-names]",
+                "Warning features/home_screen: `cards` has no description [descriptions]",
+                "Warning objects/Card: The object has no description [descriptions]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_configured_rules() -> crate::error::Result<()> {
+        let config: LintConfig = serde_yaml::from_str(
+            "
+            feature-names: error
+            variable-names: warn
+            descriptions: off
+            max-nesting-depth: 3
+            ",
+        )?;
+        let lints = manifest().lint(&config);
+        assert_eq!(
+            messages(&lints),
+            vec![
+                "Error features/home_screen: Feature `home_screen` should be kebab-case [feature-names]",
+                "Warning features/home_screen: Variable `sections-enabled` should be camelCase [variable-names]",
+                "Warning features/home_screen: `cards` is nested 4 deep, more than the maximum of 3 [nesting-depth]",
+            ]
+        );
+        Ok(())
+    }
+}
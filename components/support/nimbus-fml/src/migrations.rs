@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Versioning of the intermediate representation.
+//!
+//! IR files are stamped with an `ir_version`, so that IR written by an older version of
+//! nimbus-fml, e.g. cached or committed to a repository, can be migrated to the current
+//! format before it is deserialized.
+//!
+//! When a change to the IR would stop older IR from deserializing as it did before, bump
+//! [IR_VERSION] and add a migration to [MIGRATIONS].
+
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{FMLError, Result},
+    intermediate_representation::FeatureManifest,
+};
+
+/// The version of the IR written by this version of nimbus-fml.
+pub(crate) const IR_VERSION: u64 = 2;
+
+/// IR written before it was versioned.
+const UNVERSIONED_IR: u64 = 1;
+
+const VERSION_KEY: &str = "ir_version";
+
+/// A migration from each version to the next, starting at [UNVERSIONED_IR].
+const MIGRATIONS: [fn(&mut Map<String, Value>); (IR_VERSION - UNVERSIONED_IR) as usize] =
+    [migrate_v1_to_v2];
+
+/// Features used to have a `doc` rather than a `description`, and didn't always have
+/// `allow_coenrollment`. This applies to the features of imported manifests too.
+fn migrate_v1_to_v2(manifest: &mut Map<String, Value>) {
+    if let Some(Value::Object(features)) = manifest.get_mut("features") {
+        for feature in features.values_mut().filter_map(Value::as_object_mut) {
+            if let Some(doc) = feature.remove("doc") {
+                feature.entry("description").or_insert(doc);
+            }
+            feature
+                .entry("allow_coenrollment")
+                .or_insert(Value::Bool(false));
+        }
+    }
+    if let Some(Value::Object(imports)) = manifest.get_mut("all_imports") {
+        for import in imports.values_mut().filter_map(Value::as_object_mut) {
+            migrate_v1_to_v2(import);
+        }
+    }
+}
+
+impl FeatureManifest {
+    /// Serializes the manifest as IR, stamped with the current [IR_VERSION].
+    pub(crate) fn to_versioned_json(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Value::Object(manifest) = &mut value {
+            manifest.insert(VERSION_KEY.to_string(), IR_VERSION.into());
+        }
+        Ok(value)
+    }
+
+    /// Deserializes IR written by this or an older version of nimbus-fml, migrating it to the
+    /// current [IR_VERSION] first.
+    pub(crate) fn from_versioned_json(mut value: Value) -> Result<Self> {
+        if let Value::Object(manifest) = &mut value {
+            let version = match manifest.remove(VERSION_KEY) {
+                Some(v) => v.as_u64().ok_or_else(|| {
+                    FMLError::ValidationError(
+                        VERSION_KEY.to_string(),
+                        format!("{v} is not a version"),
+                    )
+                })?,
+                None => UNVERSIONED_IR,
+            };
+            if version > IR_VERSION {
+                return Err(FMLError::UnsupportedIRVersion(version, IR_VERSION));
+            }
+            let first = version.max(UNVERSIONED_IR) - UNVERSIONED_IR;
+            for migration in &MIGRATIONS[first as usize..] {
+                migration(manifest);
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::intermediate_representation::get_simple_homescreen_feature;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let fm = get_simple_homescreen_feature();
+        let value = fm.to_versioned_json()?;
+        assert_eq!(value[VERSION_KEY], IR_VERSION);
+        assert_eq!(FeatureManifest::from_versioned_json(value)?, fm);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_unversioned_ir() -> Result<()> {
+        let value = json!({
+            "features": {
+                "homescreen": {
+                    "name": "homescreen",
+                    "doc": "Represents the homescreen feature",
+                    "props": [],
+                }
+            },
+        });
+        let fm = FeatureManifest::from_versioned_json(value)?;
+
+        let feature = fm.get_feature("homescreen").unwrap();
+        assert_eq!(feature.doc(), "Represents the homescreen feature");
+        assert!(!feature.allow_coenrollment);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2_migrates_imports() {
+        let mut manifest = json!({
+            "features": {},
+            "all_imports": {
+                "lib": {
+                    "features": {
+                        "library": {
+                            "name": "library",
+                            "doc": "A feature from an imported manifest",
+                            "props": [],
+                        }
+                    },
+                }
+            },
+        });
+        migrate_v1_to_v2(manifest.as_object_mut().unwrap());
+
+        let feature = &manifest["all_imports"]["lib"]["features"]["library"];
+        assert_eq!(
+            feature["description"],
+            "A feature from an imported manifest"
+        );
+        assert!(feature.get("doc").is_none());
+        assert_eq!(feature["allow_coenrollment"], false);
+    }
+
+    #[test]
+    fn test_newer_ir_is_an_error() {
+        let value = json!({ "ir_version": IR_VERSION + 1, "features": {} });
+        let err = FeatureManifest::from_versioned_json(value).expect_err("too new");
+        assert!(matches!(err, FMLError::UnsupportedIRVersion(..)));
+    }
+}
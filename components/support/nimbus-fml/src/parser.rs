@@ -243,7 +243,7 @@ impl Parser {
         let channel = if frontend.channels.len() == 1 {
             frontend.channels.first().map(String::as_str)
         } else {
-            channel
+            channel.map(|c| frontend.resolve_channel(c))
         };
 
         let mut manifest = frontend.get_intermediate_representation(&id, channel)?;
@@ -334,9 +334,11 @@ impl Parser {
             .remove(&id)
             .expect("Top level manifest should always be present");
 
-        for child in manifests.values() {
-            check_can_import_manifest(&fm, child)?;
-        }
+        let errors = manifests
+            .values()
+            .filter_map(|child| check_can_import_manifest(&fm, child).err())
+            .collect();
+        FMLError::from_all(errors)?;
 
         fm.all_imports = manifests;
 
@@ -368,6 +370,18 @@ impl Parser {
             }
         }
 
+        for (alias, channel) in &child.aliases {
+            if parent.aliases.get(alias) != Some(channel) {
+                return Err(FMLError::ValidationError(
+                    "aliases".to_string(),
+                    format!(
+                        "Included manifest should not define its own channel aliases: {}",
+                        child_path
+                    ),
+                ));
+            }
+        }
+
         if let Some(about) = &child.about {
             if !about.is_includable() {
                 return Err(FMLError::ValidationError(
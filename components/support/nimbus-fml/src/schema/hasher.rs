@@ -98,6 +98,10 @@ impl SchemaHash for PropDef {
         self.name.hash(state);
         self.typ.hash(state);
         self.string_alias.hash(state);
+        // Only hashed when set, so existing schema hashes don't change.
+        if self.required {
+            self.required.hash(state);
+        }
     }
 }
 
@@ -35,21 +35,44 @@ impl<'a> SchemaValidator<'a> {
     }
 
     pub(crate) fn validate_object_def(&self, object_def: &ObjectDef) -> Result<()> {
+        first_error(self.get_object_def_errors(object_def))
+    }
+
+    /// Collects all the schema errors in this object, rather than stopping at the first.
+    pub(crate) fn get_object_def_errors(&self, object_def: &ObjectDef) -> Vec<FMLError> {
         let obj_nm = &object_def.name;
+        let mut errors = Vec::new();
         for prop in &object_def.props {
             let prop_nm = &prop.name;
 
             // Check the types exist for this property.
             let path = format!("objects/{obj_nm}/{prop_nm}");
-            self.validate_type_ref(&path, &prop.typ)?;
+            if let Err(e) = self.validate_type_ref(&path, &prop.typ) {
+                errors.push(e);
+            }
         }
 
-        Ok(())
+        errors
     }
 
     pub(crate) fn validate_feature_def(&self, feature_def: &FeatureDef) -> Result<()> {
+        first_error(self.get_feature_def_errors(feature_def))
+    }
+
+    /// Collects all the schema errors in this feature, rather than stopping at the first.
+    pub(crate) fn get_feature_def_errors(&self, feature_def: &FeatureDef) -> Vec<FMLError> {
         let feat_nm = &feature_def.name;
         let mut string_aliases: HashSet<_> = Default::default();
+        let mut errors = Vec::new();
+
+        if let Some(v) = &feature_def.min_version {
+            if !is_valid_version(v) {
+                errors.push(FMLError::ValidationError(
+                    format!("features/{feat_nm}"),
+                    format!("Invalid min-version {v}: expected a version like 118.0"),
+                ));
+            }
+        }
 
         for prop in &feature_def.props {
             let prop_nm = &prop.name;
@@ -58,12 +81,22 @@ impl<'a> SchemaValidator<'a> {
             let path = format!("features/{feat_nm}/{prop_nm}");
 
             // Check the types exist for this property.
-            self.validate_type_ref(&path, prop_t)?;
+            if let Err(e) = self.validate_type_ref(&path, prop_t) {
+                errors.push(e);
+            }
+
+            // A required variable is always provided, so can't be optional.
+            if prop.required && matches!(prop_t, TypeRef::Option(_)) {
+                errors.push(FMLError::ValidationError(
+                    path.clone(),
+                    "Required variables cannot have an optional type".to_string(),
+                ));
+            }
 
             // Check pref support for this type.
             if prop.pref_key.is_some() && !prop.typ.supports_prefs() {
-                return Err(FMLError::ValidationError(
-                    path,
+                errors.push(FMLError::ValidationError(
+                    path.clone(),
                     "Pref keys can only be used with Boolean, String, Int and Text variables"
                         .to_string(),
                 ));
@@ -73,16 +106,17 @@ impl<'a> SchemaValidator<'a> {
             if let Some(sa) = &prop.string_alias {
                 // Check that the string-alias has only been defined once in this feature.
                 if !string_aliases.insert(sa) {
-                    return Err(FMLError::ValidationError(
+                    errors.push(FMLError::ValidationError(
                         path,
                         format!("The string-alias {sa} should only be declared once per feature"),
                     ));
+                    continue;
                 }
 
                 // Check that the string-alias is actually used in this property type.
                 let types = prop_t.all_types();
                 if !types.contains(sa) {
-                    return Err(FMLError::ValidationError(
+                    errors.push(FMLError::ValidationError(
                         path,
                         format!(
                             "The string-alias {sa} must be part of the {} type declaration",
@@ -96,14 +130,16 @@ impl<'a> SchemaValidator<'a> {
         // Now check that that there is a path from this feature to any objects using the
         // string-aliases defined in this feature.
         let types = feature_def.all_types();
-        self.validate_string_alias_declarations(
+        if let Err(e) = self.validate_string_alias_declarations(
             &format!("features/{feat_nm}"),
             feat_nm,
             &types,
             &string_aliases,
-        )?;
+        ) {
+            errors.push(e);
+        }
 
-        Ok(())
+        errors
     }
 
     fn validate_string_alias_declarations(
@@ -192,6 +228,21 @@ impl<'a> SchemaValidator<'a> {
     }
 }
 
+/// Versions are dot separated, and each part starts with a number, e.g. `118`, `118.0.1`
+/// or `118.0a1`.
+fn is_valid_version(version: &str) -> bool {
+    version
+        .split('.')
+        .all(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn first_error(errors: Vec<FMLError>) -> Result<()> {
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod manifest_schema {
     use serde_json::json;
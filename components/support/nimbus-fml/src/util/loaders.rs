@@ -25,6 +25,8 @@ pub struct LoaderConfig {
     pub cwd: PathBuf,
     pub repo_files: Vec<String>,
     pub cache_dir: Option<PathBuf>,
+    /// Only read remote files from the cache, rather than fetching them.
+    pub offline: bool,
     pub refs: BTreeMap<String, String>,
 }
 
@@ -47,6 +49,7 @@ impl Default for LoaderConfig {
         Self {
             repo_files: Default::default(),
             cache_dir: None,
+            offline: false,
             cwd: env::current_dir().expect("Current Working Directory is not set"),
             refs: Default::default(),
         }
@@ -265,6 +268,10 @@ pub struct FileLoader {
     cache_dir: Option<PathBuf>,
     fetch_client: Client,
 
+    /// When set, files from the network are only read from the cache, so builds don't
+    /// depend on the network being available.
+    offline: bool,
+
     /// A mapping of repository IDs (without the leading @) to the git refs that
     /// should be used to download files.
     repo_refs: BTreeMap<String, FilePath>,
@@ -280,8 +287,14 @@ impl TryFrom<&LoaderConfig> for FileLoader {
     fn try_from(loader_config: &LoaderConfig) -> Result<Self, Self::Error> {
         let cache_dir = loader_config.cache_dir.clone();
         let cwd = loader_config.cwd.clone();
+        if loader_config.offline && cache_dir.is_none() {
+            return Err(FMLError::CliError(
+                "Working offline needs a cache directory to read from".to_string(),
+            ));
+        }
 
         let mut file_loader = Self::new(cwd, cache_dir, Default::default())?;
+        file_loader.offline = loader_config.offline;
 
         for (repo_id, git_ref) in &loader_config.refs {
             file_loader.add_repo(repo_id, git_ref)?;
@@ -310,6 +323,7 @@ impl FileLoader {
         Ok(Self {
             cache_dir,
             fetch_client: http_client,
+            offline: false,
             cwd,
             repo_refs,
         })
@@ -412,8 +426,10 @@ impl FileLoader {
     pub fn read_to_string(&self, file: &FilePath) -> Result<String> {
         Ok(match file {
             FilePath::Local(path) => std::fs::read_to_string(path)?,
-            FilePath::Remote(url) => self.fetch_and_cache(url)?,
-            FilePath::GitHub(p) => {
+            FilePath::Remote(url) => self.fetch_and_cache(url, || Ok(url.clone()))?,
+            // GitHub files are cached by repo, ref and path, so we can find them in the cache
+            // without asking the GitHub API where to download them from.
+            FilePath::GitHub(p) => self.fetch_and_cache(&p.default_download_url()?, || {
                 // If there is a GITHUB_BEARER_TOKEN environment variable
                 // present, we will use that to get the download URL from the
                 // GitHub contents API.
@@ -423,7 +439,7 @@ impl FileLoader {
                     Err(env::VarError::NotUnicode(_)) => Err(FMLError::InvalidApiToken)?,
                 };
 
-                let download_url = if let Some(api_key) = api_key {
+                Ok(if let Some(api_key) = api_key {
                     let contents_api_url = p.contents_api_url()?;
 
                     // The response format is documented here:
@@ -447,10 +463,8 @@ impl FileLoader {
                         .and_then(|u| Url::parse(u).map_err(Into::into))?
                 } else {
                     p.default_download_url()?
-                };
-
-                self.fetch_and_cache(&download_url)?
-            }
+                })
+            })?,
         })
     }
 
@@ -462,16 +476,28 @@ impl FileLoader {
         Ok(serde_yaml::from_str(&string)?)
     }
 
-    fn fetch_and_cache(&self, url: &Url) -> Result<String> {
+    /// Reads the file cached for the `url`, or if it isn't in the cache, downloads it from
+    /// the URL returned by `download_url` and caches it.
+    fn fetch_and_cache(
+        &self,
+        url: &Url,
+        download_url: impl FnOnce() -> Result<Url>,
+    ) -> Result<String> {
         if !SUPPORT_URL_LOADING {
             unimplemented!("Loading manifests from URLs is not yet supported ({})", url);
         }
         let path_buf = self.create_cache_path_buf(url);
         Ok(if path_buf.exists() {
             std::fs::read_to_string(path_buf)?
+        } else if self.offline {
+            return Err(FMLError::NotCachedError(
+                url.to_string(),
+                self.cache_dir().display().to_string(),
+            ));
         } else {
-            let res = self.fetch_client.get(url.clone()).send()?;
-            let text = res.text()?;
+            let res = self.fetch_client.get(download_url()?).send()?;
+            // Don't cache error pages as if they were the file.
+            let text = res.error_for_status()?.text()?;
 
             let parent = path_buf.parent().expect("Cache directory is specified");
             if !parent.exists() {
@@ -779,6 +805,7 @@ mod unit_tests {
         let config = &LoaderConfig {
             cwd,
             cache_dir: None,
+            offline: false,
             repo_files: vec![
                 "fixtures/loaders/config_files/remote.json".to_string(),
                 "fixtures/loaders/config_files/local.yaml".to_string(),
@@ -841,6 +868,7 @@ mod unit_tests {
         let config = &LoaderConfig {
             cwd,
             cache_dir: None,
+            offline: false,
             repo_files: Default::default(),
             refs: BTreeMap::from([("@my-remote/repo".to_string(), "cli-branch".to_string())]),
         };
@@ -859,12 +887,56 @@ mod unit_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_offline_reads_from_cache() -> Result<()> {
+        let tmp = tempfile::TempDir::new()?;
+        let config = &LoaderConfig {
+            cwd: PathBuf::from(pkg_dir()),
+            cache_dir: Some(tmp.path().to_path_buf()),
+            offline: true,
+            repo_files: Default::default(),
+            refs: BTreeMap::from([("@my-remote/repo".to_string(), "v1.0".to_string())]),
+        };
+        let files: FileLoader = config.try_into()?;
+
+        let remote = files.file_path("https://example.com/path/to/file.txt")?;
+        let github = files.file_path("@my-remote/repo/path/to/file.txt")?;
+        let FilePath::GitHub(p) = &github else {
+            unreachable!("Expected {github} to be a GitHub file")
+        };
+
+        // Neither file has been downloaded, and we can't download them.
+        for f in [&remote, &github] {
+            let err = files.read_to_string(f).expect_err("Nothing is cached");
+            assert!(matches!(err, FMLError::NotCachedError(..)));
+        }
+
+        let remote_url = Url::parse("https://example.com/path/to/file.txt")?;
+        fs::write(files.create_cache_path_buf(&remote_url), "remote")?;
+        fs::write(
+            files.create_cache_path_buf(&p.default_download_url()?),
+            "github",
+        )?;
+
+        assert_eq!(files.read_to_string(&remote)?, "remote");
+        assert_eq!(files.read_to_string(&github)?, "github");
+
+        // The same file at a different ref isn't in the cache.
+        let other_ref = FilePath::GitHub(
+            GitHubRepoFilePath::new("my-remote/repo", "v2.0").join("path/to/file.txt")?,
+        );
+        assert!(files.read_to_string(&other_ref).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_dropping_tmp_cache_dir() -> Result<()> {
         let cwd = PathBuf::from(pkg_dir());
         let config = &LoaderConfig {
             cwd,
             cache_dir: None,
+            offline: false,
             repo_files: Default::default(),
             refs: Default::default(),
         };
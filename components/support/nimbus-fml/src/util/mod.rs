@@ -4,8 +4,56 @@
 
 use std::{env, path::PathBuf};
 
+use rayon::prelude::*;
+
+use crate::error::FMLError;
+
 pub mod loaders;
 
+/// Maps the items in parallel, returning the results in the same order as the items.
+///
+/// If more than one item fails, the error is the one from the first to fail in that order,
+/// so the outcome doesn't depend on how the work was scheduled across threads.
+pub(crate) fn par_try_map<I, T, E, F>(items: I, f: F) -> Result<Vec<T>, E>
+where
+    I: IntoParallelIterator,
+    T: Send,
+    E: Send,
+    F: Fn(I::Item) -> Result<T, E> + Sync + Send,
+{
+    let results: Vec<_> = items.into_par_iter().map(f).collect();
+    results.into_iter().collect()
+}
+
+/// Like [par_try_map], but returns every error rather than just the first.
+pub(crate) fn par_try_map_all<I, T, F>(items: I, f: F) -> Result<Vec<T>, FMLError>
+where
+    I: IntoParallelIterator,
+    T: Send,
+    F: Fn(I::Item) -> Result<T, FMLError> + Sync + Send,
+{
+    let results: Vec<_> = items.into_par_iter().map(f).collect();
+    let mut values = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for r in results {
+        match r {
+            Ok(v) => values.push(v),
+            Err(e) => errors.push(e),
+        }
+    }
+    FMLError::from_all(errors)?;
+    Ok(values)
+}
+
+/// Whether the string looks like an Android resource identifier, i.e. `[a-z_][a-z0-9_]*`.
+///
+/// Bundled text which doesn't look like a resource identifier is used as is.
+pub(crate) fn is_resource_id(string: &str) -> bool {
+    let mut chars = string.chars();
+    matches!(chars.next(), Some('a'..='z' | '_'))
+        && chars.all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+}
+
 pub(crate) fn pkg_dir() -> String {
     env::var("CARGO_MANIFEST_DIR")
         .expect("Missing $CARGO_MANIFEST_DIR, cannot build tests for generated bindings")
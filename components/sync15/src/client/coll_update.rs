@@ -7,39 +7,86 @@ use super::{
     CollState, Sync15ClientResponse, Sync15StorageClient,
 };
 use crate::bso::{IncomingBso, OutgoingBso, OutgoingEncryptedBso};
-use crate::engine::CollectionRequest;
-use crate::error::{self, Error, Result};
+use crate::engine::{CollectionRequest, RequestOrder};
+use crate::error::{self, Error, ErrorResponse, Result};
 use crate::{CollectionName, KeyBundle, ServerTimestamp};
 
+/// How many records we ask the server for at a time, when the engine doesn't set a limit.
+const FETCH_BATCH_SIZE: usize = 1000;
+
 fn encrypt_outgoing(o: Vec<OutgoingBso>, key: &KeyBundle) -> Result<Vec<OutgoingEncryptedBso>> {
     o.into_iter()
         .map(|change| change.into_encrypted(key))
         .collect()
 }
 
+/// Fetches the records `collection_request` asks for, a batch at a time, and passes each batch
+/// to `on_batch` before fetching the next. Returns the number of records fetched.
+///
+/// If the request is for records `newer` than the last sync and there aren't any, the server
+/// says so without sending the collection. If the collection changes while we're part way
+/// through, the server fails the next batch, and so do we - the records already passed to
+/// `on_batch` will be fetched again next sync.
 pub fn fetch_incoming(
     client: &Sync15StorageClient,
     state: &CollState,
     collection_request: CollectionRequest,
-) -> Result<Vec<IncomingBso>> {
-    let (records, _timestamp) = match client.get_encrypted_records(collection_request)? {
-        Sync15ClientResponse::Success {
-            record,
-            last_modified,
-            ..
-        } => (record, last_modified),
-        other => return Err(other.create_storage_error()),
+    on_batch: &mut dyn FnMut(Vec<IncomingBso>) -> Result<()>,
+) -> Result<usize> {
+    // If the engine set its own limit, it only wants that many, so we don't fetch more pages.
+    let (collection_request, paged) = match collection_request.limit {
+        Some(_) => (collection_request, false),
+        None => (
+            collection_request.limit(FETCH_BATCH_SIZE, RequestOrder::Oldest),
+            true,
+        ),
     };
-    let mut result = Vec::with_capacity(records.len());
-    for record in records {
-        // if we see a HMAC error, we've made an explicit decision to
-        // NOT handle it here, but restart the global state machine.
-        // That should cause us to re-read crypto/keys and things should
-        // work (although if for some reason crypto/keys was updated but
-        // not all storage was wiped we are probably screwed.)
-        result.push(record.into_decrypted(&state.key)?);
+    let mut count = 0;
+    // The offset of the next page, and the timestamp of the first.
+    let mut next: Option<(String, ServerTimestamp)> = None;
+    loop {
+        let page = next.as_ref().map(|(offset, xius)| (offset.as_str(), *xius));
+        let (records, last_modified, next_offset) =
+            match client.get_encrypted_records_page(&collection_request, page)? {
+                (
+                    Sync15ClientResponse::Success {
+                        record,
+                        last_modified,
+                        ..
+                    },
+                    next_offset,
+                ) => (record, last_modified, next_offset),
+                (Sync15ClientResponse::Error(ErrorResponse::NotModified { .. }), _)
+                    if next.is_none() =>
+                {
+                    log::info!(
+                        "{} hasn't changed since {:?}",
+                        collection_request.collection,
+                        collection_request.newer
+                    );
+                    return Ok(0);
+                }
+                (other, _) => return Err(other.create_storage_error()),
+            };
+        count += records.len();
+        let mut batch = Vec::with_capacity(records.len());
+        for record in records {
+            // if we see a HMAC error, we've made an explicit decision to
+            // NOT handle it here, but restart the global state machine.
+            // That should cause us to re-read crypto/keys and things should
+            // work (although if for some reason crypto/keys was updated but
+            // not all storage was wiped we are probably screwed.)
+            batch.push(record.into_decrypted(&state.key)?);
+        }
+        on_batch(batch)?;
+        match next_offset {
+            Some(offset) if paged => {
+                let xius = next.map_or(last_modified, |(_, xius)| xius);
+                next = Some((offset, xius));
+            }
+            _ => return Ok(count),
+        }
     }
-    Ok(result)
 }
 
 pub struct CollectionUpdate<'a> {
@@ -88,34 +135,54 @@ impl<'a> CollectionUpdate<'a> {
         ))
     }
 
-    /// Returns a list of the IDs that failed if allowed_dropped_records is true, otherwise
-    /// returns an empty vec.
-    pub fn upload(self) -> error::Result<UploadInfo> {
-        let mut failed = vec![];
+    /// Uploads the records in as many batches as the server's limits allow. Each batch is
+    /// committed atomically, and `on_commit` is called with the records in it as soon as the
+    /// server commits it, so the engine can record them as uploaded even if a later batch
+    /// fails. It's always called at least once, with the timestamp the collection has after the
+    /// upload. Returns the records from all the batches, where failed records are only allowed
+    /// if we aren't `fully_atomic`.
+    pub fn upload(
+        self,
+        on_commit: &mut dyn FnMut(UploadInfo) -> Result<()>,
+    ) -> error::Result<UploadInfo> {
         let mut q = self.client.new_post_queue(
             &self.collection,
             &self.state.config,
             self.xius,
             NormalResponseHandler::new(!self.fully_atomic),
         )?;
+        let mut info = UploadInfo {
+            successful_ids: vec![],
+            failed_ids: vec![],
+            modified_timestamp: self.xius,
+        };
+        let mut report = |batch: UploadInfo| -> Result<()> {
+            if self.fully_atomic {
+                assert_eq!(
+                    batch.failed_ids.len(),
+                    0,
+                    "Bug: Should have failed by now if we aren't allowing dropped records"
+                );
+            }
+            info.successful_ids.extend_from_slice(&batch.successful_ids);
+            info.failed_ids.extend_from_slice(&batch.failed_ids);
+            info.modified_timestamp = batch.modified_timestamp;
+            on_commit(batch)
+        };
 
         for record in self.to_update.into_iter() {
             let enqueued = q.enqueue(&record)?;
             if !enqueued && self.fully_atomic {
                 return Err(Error::RecordTooLargeError);
             }
+            // Enqueuing a record which doesn't fit in the current batch commits it.
+            if let Some(batch) = q.take_committed() {
+                report(batch)?;
+            }
         }
 
         q.flush(true)?;
-        let mut info = q.completed_upload_info();
-        info.failed_ids.append(&mut failed);
-        if self.fully_atomic {
-            assert_eq!(
-                info.failed_ids.len(),
-                0,
-                "Bug: Should have failed by now if we aren't allowing dropped records"
-            );
-        }
+        report(q.completed_upload_info())?;
         Ok(info)
     }
 }
@@ -457,6 +457,20 @@ pub struct UploadInfo {
 }
 
 impl<Poster> PostQueue<Poster, NormalResponseHandler> {
+    /// Takes the IDs of the records in batches the server has committed since we were last
+    /// called, or None if there aren't any. Unlike `completed_upload_info`, records in a batch
+    /// which is still in progress are left alone.
+    pub fn take_committed(&mut self) -> Option<UploadInfo> {
+        if self.on_response.successful_ids.is_empty() && self.on_response.failed_ids.is_empty() {
+            return None;
+        }
+        Some(UploadInfo {
+            successful_ids: std::mem::take(&mut self.on_response.successful_ids),
+            failed_ids: std::mem::take(&mut self.on_response.failed_ids),
+            modified_timestamp: self.last_modified,
+        })
+    }
+
     // TODO: should take by move
     pub fn completed_upload_info(&mut self) -> UploadInfo {
         let mut result = UploadInfo {
@@ -1190,6 +1204,66 @@ mod test {
         );
     }
 
+    fn fake_success_response<'a, T: Into<Option<&'a str>>>(
+        status: u16,
+        lm: i64,
+        batch: T,
+        ids: &[&str],
+    ) -> PostResponse {
+        let mut resp = fake_response(status, lm, batch);
+        if let Sync15ClientResponse::Success { record, .. } = &mut resp {
+            record.success = ids.iter().map(|&id| id.into()).collect();
+        }
+        resp
+    }
+
+    #[test]
+    fn test_pq_take_committed() {
+        let cfg = InfoConfiguration {
+            max_post_records: 1,
+            max_total_records: 2,
+            ..InfoConfiguration::default()
+        };
+        let time = 11_111_111_000;
+        let tester = TestPoster::new(
+            &cfg,
+            vec![
+                fake_success_response(status_codes::ACCEPTED, time, "1234", &["a"]),
+                fake_success_response(status_codes::OK, time + 100_000, None, &["b"]),
+                fake_success_response(status_codes::OK, time + 200_000, None, &["c"]),
+            ],
+        );
+        let mut pq = PostQueue::new(
+            &cfg,
+            ServerTimestamp(time),
+            tester.clone(),
+            NormalResponseHandler::new(false),
+        );
+
+        pq.enqueue(&make_record(100)).unwrap();
+        // POST
+        pq.enqueue(&make_record(100)).unwrap();
+        // The batch is still in progress, so nothing is committed yet.
+        assert!(pq.take_committed().is_none());
+        // POST + COMMIT
+        pq.enqueue(&make_record(100)).unwrap();
+        let committed = pq.take_committed().unwrap();
+        assert_eq!(committed.successful_ids, vec!["a", "b"]);
+        assert!(committed.failed_ids.is_empty());
+        assert_eq!(committed.modified_timestamp.0, time + 100_000);
+        assert!(pq.take_committed().is_none());
+
+        pq.flush(true).unwrap(); // COMMIT
+        let committed = pq.take_committed().unwrap();
+        assert_eq!(committed.successful_ids, vec!["c"]);
+        assert_eq!(committed.modified_timestamp.0, time + 200_000);
+
+        let info = pq.completed_upload_info();
+        assert!(info.successful_ids.is_empty());
+        assert!(info.failed_ids.is_empty());
+        assert_eq!(tester.borrow().batches.len(), 2);
+    }
+
     // TODO: Test
     //
     // - error cases!!! We don't test our handling of server errors at all!
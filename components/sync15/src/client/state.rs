@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::request::{InfoCollections, InfoConfiguration};
 use super::storage_client::{SetupStorageClient, Sync15ClientResponse};
@@ -39,6 +40,9 @@ const DEFAULT_ENGINES: &[(&str, usize)] = &[
 // Declined engines to include in a fresh `meta/global` record.
 const DEFAULT_DECLINED: &[&str] = &[];
 
+// The longest persisted backoff we respect.
+const MAX_PERSISTED_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// State that we require the app to persist to storage for us.
 /// It's a little unfortunate we need this, because it's only tracking
 /// "declined engines", and even then, only needed in practice when there's
@@ -58,13 +62,27 @@ pub enum PersistedGlobalState {
     /// V2 is just tracking the globally declined list.
     /// None means "I've no idea" and theoretically should only happen on the
     /// very first sync for an app.
-    V2 { declined: Option<Vec<String>> },
+    /// It later gained the backoff requested by the server, so we respect it
+    /// even if the app restarts. These are in milliseconds since the epoch;
+    /// `retry_after_until` is the part which came from `Retry-After`, which we
+    /// respect even when the user asked for the sync.
+    V2 {
+        declined: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backoff_until: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_until: Option<u64>,
+    },
 }
 
 impl Default for PersistedGlobalState {
     #[inline]
     fn default() -> PersistedGlobalState {
-        PersistedGlobalState::V2 { declined: None }
+        PersistedGlobalState::V2 {
+            declined: None,
+            backoff_until: None,
+            retry_after_until: None,
+        }
     }
 }
 
@@ -145,17 +163,81 @@ fn compute_engine_states(input: EngineStateInput) -> EngineStateOutput {
 impl PersistedGlobalState {
     fn set_declined(&mut self, new_declined: Vec<String>) {
         match self {
-            Self::V2 { ref mut declined } => *declined = Some(new_declined),
+            Self::V2 {
+                ref mut declined, ..
+            } => *declined = Some(new_declined),
         }
     }
     pub(crate) fn get_declined(&self) -> &[String] {
         match self {
-            Self::V2 { declined: Some(d) } => d,
-            Self::V2 { declined: None } => &[],
+            Self::V2 {
+                declined: Some(d), ..
+            } => d,
+            Self::V2 { declined: None, .. } => &[],
+        }
+    }
+    pub(crate) fn set_backoff(
+        &mut self,
+        until: Option<SystemTime>,
+        retry_after: Option<SystemTime>,
+    ) {
+        match self {
+            Self::V2 {
+                backoff_until,
+                retry_after_until,
+                ..
+            } => {
+                *backoff_until = until.map(to_millis);
+                *retry_after_until = retry_after.map(to_millis);
+            }
+        }
+    }
+    /// Returns when we may next sync, if the server asked us to back off and
+    /// that hasn't yet expired.
+    ///
+    /// A deadline too far in the future is more likely a clock change than
+    /// the server, and respecting it might stop us syncing for good, so it's
+    /// clamped to `MAX_PERSISTED_BACKOFF` from now. The clamped deadline is
+    /// saved, so that later calls don't keep pushing it back.
+    pub(crate) fn get_backoff_until(&mut self, ignore_soft_backoff: bool) -> Option<SystemTime> {
+        self.get_backoff_until_at(ignore_soft_backoff, SystemTime::now())
+    }
+
+    fn get_backoff_until_at(
+        &mut self,
+        ignore_soft_backoff: bool,
+        now: SystemTime,
+    ) -> Option<SystemTime> {
+        let until = match self {
+            Self::V2 {
+                backoff_until,
+                retry_after_until,
+                ..
+            } => {
+                if ignore_soft_backoff {
+                    retry_after_until
+                } else {
+                    backoff_until
+                }
+            }
+        };
+        let max = to_millis(now + MAX_PERSISTED_BACKOFF);
+        let clamped = (*until)?.min(max);
+        *until = Some(clamped);
+        let clamped = UNIX_EPOCH + Duration::from_millis(clamped);
+        if clamped > now {
+            Some(clamped)
+        } else {
+            None
         }
     }
 }
 
+fn to_millis(time: SystemTime) -> u64 {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+}
+
 /// Holds global Sync state, including server upload limits, the
 /// last-fetched collection modified times, `meta/global` record, and
 /// an encrypted copy of the crypto/keys resource (avoids keeping them
@@ -190,7 +272,9 @@ fn new_global(pgs: &PersistedGlobalState) -> MetaGlobalRecord {
     // we previously saw a meta/global then we would have updated it with what
     // it was at the time.
     let declined = match pgs {
-        PersistedGlobalState::V2 { declined: Some(d) } => d.clone(),
+        PersistedGlobalState::V2 {
+            declined: Some(d), ..
+        } => d.clone(),
         _ => DEFAULT_DECLINED.iter().map(ToString::to_string).collect(),
     };
 
@@ -436,6 +520,19 @@ impl<'a> SetupStateMachine<'a> {
                         // json body also carries the timestamp. If they aren't
                         // identical something has screwed up and we should die.
                         assert_eq!(last_modified, record.envelope.modified);
+                        // If we can't decrypt the keys, our sync key is probably stale - eg,
+                        // the user reset their password on another device - so we fail, and
+                        // the app fetches the current one before the next sync. We never
+                        // start over because of it, as that would wipe the server for every
+                        // client that still has the right key.
+                        if let Err(e) = CollectionKeys::from_encrypted_payload(
+                            record.payload.clone(),
+                            last_modified,
+                            self.root_key,
+                        ) {
+                            log::warn!("Can't decrypt crypto/keys with our sync key");
+                            return Err(e);
+                        }
                         let state = GlobalState {
                             config,
                             collections,
@@ -463,9 +560,16 @@ impl<'a> SetupStateMachine<'a> {
                     record: collections,
                     ..
                 } => Ok(
+                    // The keys we have are no use if the sync key changed since.
                     if self.engine_updates.is_none()
                         && is_same_timestamp(old_state.global_timestamp, &collections, "meta")
                         && is_same_timestamp(old_state.keys_timestamp, &collections, "crypto")
+                        && CollectionKeys::from_encrypted_payload(
+                            old_state.keys.clone(),
+                            old_state.keys_timestamp,
+                            self.root_key,
+                        )
+                        .is_ok()
                     {
                         Ready {
                             state: GlobalState {
@@ -791,7 +895,7 @@ mod tests {
             meta_global: mocked_success_ts(mg, 999_000),
             crypto_keys: mocked_success_keys(keys, &root_key),
         };
-        let mut pgs = PersistedGlobalState::V2 { declined: None };
+        let mut pgs = PersistedGlobalState::default();
 
         let mut state_machine =
             SetupStateMachine::for_full_sync(&client, &root_key, &mut pgs, None, &NeverInterrupts);
@@ -890,7 +994,7 @@ mod tests {
 
         // First a test where the "previous" global state is OK to reuse.
         {
-            let mut pgs = PersistedGlobalState::V2 { declined: None };
+            let mut pgs = PersistedGlobalState::default();
             // A "previous" global state.
             let old_state = GlobalState {
                 config: InfoConfiguration::default(),
@@ -914,7 +1018,7 @@ mod tests {
 
         // Now where the meta/global record on the server is later.
         {
-            let mut pgs = PersistedGlobalState::V2 { declined: None };
+            let mut pgs = PersistedGlobalState::default();
             // A "previous" global state.
             let old_state = GlobalState {
                 config: InfoConfiguration::default(),
@@ -938,7 +1042,7 @@ mod tests {
 
         // Where keys on the server is later.
         {
-            let mut pgs = PersistedGlobalState::V2 { declined: None };
+            let mut pgs = PersistedGlobalState::default();
             // A "previous" global state.
             let old_state = GlobalState {
                 config: InfoConfiguration::default(),
@@ -962,7 +1066,7 @@ mod tests {
 
         // Where there are engine-state changes.
         {
-            let mut pgs = PersistedGlobalState::V2 { declined: None };
+            let mut pgs = PersistedGlobalState::default();
             // A "previous" global state.
             let old_state = GlobalState {
                 config: InfoConfiguration::default(),
@@ -985,13 +1089,95 @@ mod tests {
                 &sm_seq_restarted,
             );
             let declined = match pgs {
-                PersistedGlobalState::V2 { declined: d } => d,
+                PersistedGlobalState::V2 { declined: d, .. } => d,
             };
             // and check we now consider logins as declined.
             assert_eq!(declined, Some(vec!["logins".to_string()]));
         }
     }
 
+    #[test]
+    fn test_sync_key_changed() {
+        let _ = env_logger::try_init();
+        let old_root_key = KeyBundle::new_random().unwrap();
+        let root_key = KeyBundle::new_random().unwrap();
+        let ts_metaglobal = 123_456;
+        let ts_keys = 145_000;
+        let keys = CollectionKeys {
+            timestamp: ServerTimestamp(ts_keys),
+            default: KeyBundle::new_random().unwrap(),
+            collections: HashMap::new(),
+        };
+        let mg = MetaGlobalRecord {
+            sync_id: "syncIDAAAAAA".into(),
+            storage_version: 5usize,
+            engines: vec![(
+                "bookmarks",
+                MetaGlobalEngine {
+                    version: 1usize,
+                    sync_id: "syncIDBBBBBB".into(),
+                },
+            )]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect(),
+            declined: vec!["logins".to_string()],
+        };
+        let collections = InfoCollections::new(
+            vec![("meta", ts_metaglobal), ("crypto", ts_keys)]
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), ServerTimestamp(value)))
+                .collect(),
+        );
+        // The keys on the server are still encrypted with the sync key we had before.
+        let client = InMemoryClient {
+            info_configuration: mocked_success(InfoConfiguration::default()),
+            info_collections: mocked_success(collections.clone()),
+            meta_global: mocked_success_ts(mg.clone(), ts_metaglobal),
+            crypto_keys: mocked_success_keys(keys.clone(), &old_root_key),
+        };
+        let mut pgs = PersistedGlobalState::default();
+        let mut state_machine =
+            SetupStateMachine::for_full_sync(&client, &root_key, &mut pgs, None, &NeverInterrupts);
+
+        // Even though nothing changed on the server, we can't reuse the keys we had.
+        let old_state = GlobalState {
+            config: InfoConfiguration::default(),
+            collections: collections.clone(),
+            global: mg.clone(),
+            global_timestamp: ServerTimestamp(ts_metaglobal),
+            keys: keys.to_encrypted_payload(&old_root_key).unwrap(),
+            keys_timestamp: ServerTimestamp(ts_keys),
+        };
+        let next = state_machine
+            .advance(WithPreviousState { old_state })
+            .unwrap();
+        assert_eq!(next.label(), "InitialWithConfig");
+
+        // And if the keys we fetch again still aren't encrypted with our sync key, we fail
+        // rather than starting over, as it's more likely our key is out of date.
+        let initial_with_meta_global = || InitialWithMetaGlobal {
+            config: InfoConfiguration::default(),
+            collections: collections.clone(),
+            global: mg.clone(),
+            global_timestamp: ServerTimestamp(ts_metaglobal),
+        };
+        assert!(matches!(
+            state_machine.advance(initial_with_meta_global()),
+            Err(ErrorKind::HmacMismatch)
+        ));
+
+        // Once another client has replaced the keys, we use them.
+        let client = InMemoryClient {
+            crypto_keys: mocked_success_keys(keys.clone(), &root_key),
+            ..client
+        };
+        let mut state_machine =
+            SetupStateMachine::for_full_sync(&client, &root_key, &mut pgs, None, &NeverInterrupts);
+        let next = state_machine.advance(initial_with_meta_global()).unwrap();
+        assert_eq!(next.label(), "Ready");
+    }
+
     fn string_set(s: &[&str]) -> HashSet<String> {
         s.iter().map(ToString::to_string).collect()
     }
@@ -1086,4 +1272,59 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_persisted_backoff() {
+        // State persisted before we tracked backoff should still parse.
+        let mut pgs: PersistedGlobalState =
+            serde_json::from_str(r#"{"schema_version":"V2","declined":["tabs"]}"#).unwrap();
+        assert_eq!(pgs.get_declined(), &["tabs".to_string()]);
+        assert_eq!(pgs.get_backoff_until(false), None);
+
+        let now = SystemTime::now();
+        let soon = now + Duration::from_secs(60);
+        let later = now + Duration::from_secs(3600);
+        pgs.set_backoff(Some(later), Some(soon));
+        let mut pgs: PersistedGlobalState =
+            serde_json::from_str(&serde_json::to_string(&pgs).unwrap()).unwrap();
+        assert_eq!(pgs.get_declined(), &["tabs".to_string()]);
+        // We only keep millisecond precision.
+        let within_a_ms = |until: Option<SystemTime>, expected: SystemTime| {
+            let until = until.expect("should be backed off");
+            expected.duration_since(until).unwrap() < Duration::from_millis(1)
+        };
+        assert!(within_a_ms(pgs.get_backoff_until(false), later));
+        assert!(within_a_ms(pgs.get_backoff_until(true), soon));
+
+        // A backoff which has expired is ignored, and one suspiciously far in
+        // the future is clamped.
+        let mut pgs = PersistedGlobalState::default();
+        pgs.set_backoff(
+            Some(now - Duration::from_secs(1)),
+            Some(now + Duration::from_secs(7 * 24 * 60 * 60)),
+        );
+        assert_eq!(pgs.get_backoff_until_at(false, now), None);
+        let clamped = now + MAX_PERSISTED_BACKOFF;
+        assert!(within_a_ms(pgs.get_backoff_until_at(true, now), clamped));
+
+        // The clamped deadline is saved, so it doesn't move as time passes,
+        // and we sync again once it's expired.
+        let mut pgs: PersistedGlobalState =
+            serde_json::from_str(&serde_json::to_string(&pgs).unwrap()).unwrap();
+        let an_hour_later = now + Duration::from_secs(3600);
+        assert!(within_a_ms(
+            pgs.get_backoff_until_at(true, an_hour_later),
+            clamped
+        ));
+        assert_eq!(
+            pgs.get_backoff_until_at(true, clamped + Duration::from_secs(1)),
+            None
+        );
+
+        pgs.set_backoff(None, None);
+        assert_eq!(
+            serde_json::to_string(&pgs).unwrap(),
+            r#"{"schema_version":"V2","declined":null}"#
+        );
+    }
 }
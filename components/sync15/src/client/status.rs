@@ -60,6 +60,17 @@ impl ServiceStatus {
     }
 }
 
+/// Why the keys we encrypt records with changed since we last saw them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeysChanged {
+    /// The sync key changed, eg, because the user reset their password, and another client
+    /// replaced crypto/keys with keys encrypted with the new one. Every engine was reset.
+    SyncKey,
+    /// Another client changed the keys for some collections in crypto/keys. The engines for
+    /// those collections were reset, so they download everything again.
+    CollectionKeys { reset_engines: Vec<String> },
+}
+
 /// The result of a sync request. This too is from the "sync manager", but only
 /// has a fraction of the things it will have when we actually build that.
 #[derive(Debug)]
@@ -80,6 +91,9 @@ pub struct SyncResult {
     pub telemetry: SyncTelemetryPing,
 
     pub next_sync_after: Option<std::time::SystemTime>,
+
+    /// Set if the keys changed since the last sync, in which case engines may have been reset.
+    pub keys_changed: Option<KeysChanged>,
 }
 
 // If `r` has a BackoffError, then returns the later backoff value.
@@ -94,13 +108,19 @@ fn advance_backoff(cur_best: SystemTime, r: &Result<(), Error>) -> SystemTime {
 
 impl SyncResult {
     pub(crate) fn set_sync_after(&mut self, backoff_duration: Duration) {
+        self.next_sync_after = self.get_sync_after(backoff_duration);
+    }
+
+    // Returns the time we should wait until before syncing again, given the backoff requested
+    // via headers and any backoff errors in our results.
+    pub(crate) fn get_sync_after(&self, backoff_duration: Duration) -> Option<SystemTime> {
         let now = SystemTime::now();
         let toplevel = advance_backoff(now + backoff_duration, &self.result);
         let sync_after = self.engine_results.values().fold(toplevel, advance_backoff);
         if sync_after <= now {
-            self.next_sync_after = None;
+            None
         } else {
-            self.next_sync_after = Some(sync_after);
+            Some(sync_after)
         }
     }
 }
@@ -58,10 +58,11 @@ impl<T> Sync15ClientResponse<T> {
             .get(header_names::RETRY_AFTER)
             .and_then(parse_seconds);
 
-        let backoff = resp
-            .headers
-            .get(header_names::X_WEAVE_BACKOFF)
-            .and_then(parse_seconds);
+        // Servers may use either name for a backoff.
+        let backoff = [header_names::X_WEAVE_BACKOFF, header_names::X_BACKOFF]
+            .into_iter()
+            .filter_map(|name| resp.headers.get(name).and_then(parse_seconds))
+            .max();
 
         if let Some(b) = backoff {
             backoff_listener.note_backoff(b);
@@ -93,6 +94,7 @@ impl<T> Sync15ClientResponse<T> {
             let status = resp.status;
             log::info!("Request \"{}\" was an error (status={})", route, status);
             match status {
+                304 => Sync15ClientResponse::Error(ErrorResponse::NotModified { route }),
                 404 => Sync15ClientResponse::Error(ErrorResponse::NotFound { route }),
                 401 => Sync15ClientResponse::Error(ErrorResponse::Unauthorized { route }),
                 412 => Sync15ClientResponse::Error(ErrorResponse::PreconditionFailed { route }),
@@ -295,7 +297,50 @@ impl Sync15StorageClient {
         &self,
         collection_request: CollectionRequest,
     ) -> error::Result<Sync15ClientResponse<Vec<IncomingEncryptedBso>>> {
-        self.collection_request(Method::Get, collection_request)
+        let (resp, _) = self.get_encrypted_records_page(&collection_request, None)?;
+        Ok(resp)
+    }
+
+    /// Fetches a page of the records `collection_request` asks for, along with the
+    /// `X-Weave-Next-Offset` of the next page, if there is one.
+    ///
+    /// For the first page, `next` is None, and if the request is for records `newer` than some
+    /// timestamp, the server responds with a 304 if there aren't any. For later pages, `next` is
+    /// the offset and the `X-Last-Modified` of the first page, so that the server responds with
+    /// a 412 if the collection changed since we started.
+    pub(crate) fn get_encrypted_records_page(
+        &self,
+        collection_request: &CollectionRequest,
+        next: Option<(&str, ServerTimestamp)>,
+    ) -> error::Result<(
+        Sync15ClientResponse<Vec<IncomingEncryptedBso>>,
+        Option<String>,
+    )> {
+        let url = build_collection_request_url(
+            Url::parse(&self.tsc.api_endpoint()?)?,
+            collection_request,
+            next.map(|(offset, _)| offset),
+        )?;
+        let req = self.build_request(Method::Get, url)?;
+        let req = match (next, collection_request.newer) {
+            (Some((_, xius)), _) => {
+                req.header(header_names::X_IF_UNMODIFIED_SINCE, format!("{}", xius))?
+            }
+            (None, Some(newer)) => {
+                req.header(header_names::X_IF_MODIFIED_SINCE, format!("{}", newer))?
+            }
+            (None, None) => req,
+        };
+        log::trace!("request: GET {} ({:?})", req.url.path(), req.url.query());
+        let resp = req.send()?;
+        let next_offset = resp
+            .headers
+            .get(header_names::X_WEAVE_NEXT_OFFSET)
+            .map(str::to_string);
+        Ok((
+            Sync15ClientResponse::from_response(resp, &self.backoff)?,
+            next_offset,
+        ))
     }
 
     #[inline]
@@ -353,18 +398,6 @@ impl Sync15StorageClient {
         }
     }
 
-    fn collection_request<T>(
-        &self,
-        method: Method,
-        r: CollectionRequest,
-    ) -> error::Result<Sync15ClientResponse<T>>
-    where
-        for<'a> T: serde::de::Deserialize<'a>,
-    {
-        let url = build_collection_request_url(Url::parse(&self.tsc.api_endpoint()?)?, r)?;
-        self.exec_request(self.build_request(method, url)?, false)
-    }
-
     pub fn new_post_queue<'a, F: PostResponseHandler>(
         &'a self,
         coll: &'a CollectionName,
@@ -466,7 +499,11 @@ fn build_collection_url(mut base_url: Url, collection: CollectionName) -> error:
     Ok(base_url)
 }
 
-fn build_collection_request_url(mut base_url: Url, r: CollectionRequest) -> error::Result<Url> {
+fn build_collection_request_url(
+    mut base_url: Url,
+    r: &CollectionRequest,
+    offset: Option<&str>,
+) -> error::Result<Url> {
     let mut pairs = base_url.query_pairs_mut();
     if r.full {
         pairs.append_pair("full", "1");
@@ -492,9 +529,12 @@ fn build_collection_request_url(mut base_url: Url, r: CollectionRequest) -> erro
         pairs.append_pair("sort", l.order.as_str());
         pairs.append_pair("limit", &l.num.to_string());
     }
+    if let Some(offset) = offset {
+        pairs.append_pair("offset", offset);
+    }
     pairs.finish();
     drop(pairs);
-    build_collection_url(base_url, r.collection)
+    build_collection_url(base_url, r.collection.clone())
 }
 
 #[cfg(feature = "sync-client")]
@@ -542,15 +582,16 @@ mod test {
         let base = Url::parse("https://example.com/sync").unwrap();
 
         let empty =
-            build_collection_request_url(base.clone(), CollectionRequest::new("foo".into()))
+            build_collection_request_url(base.clone(), &CollectionRequest::new("foo".into()), None)
                 .unwrap();
         assert_eq!(empty.as_str(), "https://example.com/sync/storage/foo");
 
         let idreq = build_collection_request_url(
             base.clone(),
-            CollectionRequest::new("wutang".into())
+            &CollectionRequest::new("wutang".into())
                 .full()
                 .ids(&["rza", "gza"]),
+            None,
         )
         .unwrap();
         assert_eq!(
@@ -559,16 +600,30 @@ mod test {
         );
 
         let complex = build_collection_request_url(
-            base,
-            CollectionRequest::new("specific".into())
+            base.clone(),
+            &CollectionRequest::new("specific".into())
                 .full()
                 .limit(10, RequestOrder::Oldest)
                 .older_than(ServerTimestamp(9_876_540))
                 .newer_than(ServerTimestamp(1_234_560)),
+            None,
         )
         .unwrap();
         assert_eq!(complex.as_str(),
             "https://example.com/sync/storage/specific?full=1&older=9876.54&newer=1234.56&sort=oldest&limit=10");
+
+        let paged = build_collection_request_url(
+            base,
+            &CollectionRequest::new("paged".into())
+                .full()
+                .limit(1000, RequestOrder::Oldest),
+            Some("1000"),
+        )
+        .unwrap();
+        assert_eq!(
+            paged.as_str(),
+            "https://example.com/sync/storage/paged?full=1&sort=oldest&limit=1000&offset=1000"
+        );
     }
 
     #[cfg(feature = "sync-client")]
@@ -3,11 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use super::{CollectionUpdate, GlobalState, LocalCollStateMachine, Sync15StorageClient};
-use crate::clients_engine;
 use crate::engine::SyncEngine;
 use crate::error::Error;
 use crate::telemetry;
-use crate::KeyBundle;
+use crate::{ClientData, KeyBundle};
 use interrupt_support::Interruptee;
 
 #[allow(clippy::too_many_arguments)]
@@ -15,7 +14,9 @@ pub fn synchronize_with_clients_engine(
     client: &Sync15StorageClient,
     global_state: &GlobalState,
     root_sync_key: &KeyBundle,
-    clients: Option<&clients_engine::Engine<'_>>,
+    // From the clients engine, if we synced it. We take a copy, rather than the engine itself, so
+    // that engines can be synced on other threads.
+    client_data: Option<&ClientData>,
     engine: &dyn SyncEngine,
     fully_atomic: bool,
     telem_engine: &mut telemetry::Engine,
@@ -37,8 +38,8 @@ pub fn synchronize_with_clients_engine(
         }
     };
 
-    if let Some(clients) = clients {
-        engine.prepare_for_sync(&|| clients.get_client_data())?;
+    if let Some(client_data) = client_data {
+        engine.prepare_for_sync(&|| client_data.clone())?;
     }
     interruptee.err_if_interrupted()?;
     // We assume an "engine" manages exactly one "collection" with the engine's name.
@@ -47,26 +48,20 @@ pub fn synchronize_with_clients_engine(
             log::info!("skipping incoming for {} - not needed.", collection);
         }
         Some(collection_request) => {
-            // Ideally we would "batch" incoming records (eg, fetch just 1000 at a time)
-            // and ask the engine to "stage" them as they come in - but currently we just read
-            // them all in one request.
-
-            // Doing this batching will involve specifying a "limit=" param and
-            // "x-if-unmodified-since" for each request, looking for an
-            // "X-Weave-Next-Offset header in the response and using that in subsequent
-            // requests.
-            // See https://mozilla-services.readthedocs.io/en/latest/storage/apis-1.5.html#syncstorage-paging
-            //
-            // But even if we had that, we need to deal with a 412 response on a subsequent batch,
-            // so we can't know if we've staged *every* record for that timestamp; the next
+            // We stage each batch of records as it arrives. If we get a 412 on a later batch,
+            // we can't know if we've staged *every* record for that timestamp, so the next
             // sync must use an earlier one.
             //
             // For this reason, an engine can't really trust a server timestamp until the
             // very end when we know we've staged them all.
-            let incoming = super::fetch_incoming(client, &coll_state, collection_request)?;
-            log::info!("Downloaded {} remote changes", incoming.len());
-            engine.stage_incoming(incoming, telem_engine)?;
-            interruptee.err_if_interrupted()?;
+            // See https://mozilla-services.readthedocs.io/en/latest/storage/apis-1.5.html#syncstorage-paging
+            let count =
+                super::fetch_incoming(client, &coll_state, collection_request, &mut |incoming| {
+                    engine.stage_incoming(incoming, telem_engine)?;
+                    interruptee.err_if_interrupted()?;
+                    Ok(())
+                })?;
+            log::info!("Downloaded {} remote changes", count);
         }
     };
 
@@ -78,11 +73,6 @@ pub fn synchronize_with_clients_engine(
     let outgoing = engine.apply(coll_state.last_modified, telem_engine)?;
     interruptee.err_if_interrupted()?;
 
-    // XXX - this upload strategy is buggy due to batching. With enough records, we will commit
-    // 2 batches on the server. If the second fails, we get an Err<> here, so can't tell the
-    // engine about the successful server batch commit.
-    // Most stuff below should be called per-batch rather than at the successful end of all
-    // batches, but that's not trivial.
     log::info!("Uploading {} outgoing changes", outgoing.len());
     let upload_info = CollectionUpdate::new_from_changeset(
         client,
@@ -91,22 +81,23 @@ pub fn synchronize_with_clients_engine(
         outgoing,
         fully_atomic,
     )?
-    .upload()?;
+    .upload(&mut |batch| {
+        // Each batch the server commits is recorded as it happens, so that if a later one
+        // fails, we don't upload these records again next time.
+        if !batch.successful_ids.is_empty() || !batch.failed_ids.is_empty() {
+            let mut telem_outgoing = telemetry::EngineOutgoing::new();
+            telem_outgoing.sent(batch.successful_ids.len() + batch.failed_ids.len());
+            telem_outgoing.failed(batch.failed_ids.len());
+            telem_engine.outgoing(telem_outgoing);
+        }
+        Ok(engine.set_uploaded(batch.modified_timestamp, batch.successful_ids)?)
+    })?;
     log::info!(
         "Upload success ({} records success, {} records failed)",
         upload_info.successful_ids.len(),
         upload_info.failed_ids.len()
     );
 
-    let mut telem_outgoing = telemetry::EngineOutgoing::new();
-    telem_outgoing.sent(upload_info.successful_ids.len() + upload_info.failed_ids.len());
-    telem_outgoing.failed(upload_info.failed_ids.len());
-    telem_engine.outgoing(telem_outgoing);
-
-    engine.set_uploaded(upload_info.modified_timestamp, upload_info.successful_ids)?;
-
-    // The above should all be per-batch :(
-
     engine.sync_finished()?;
 
     log::info!("Sync finished!");
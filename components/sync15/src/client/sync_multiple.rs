@@ -6,18 +6,26 @@
 // global and local state between syncs.
 
 use super::state::{EngineChangesNeeded, GlobalState, PersistedGlobalState, SetupStateMachine};
-use super::status::{ServiceStatus, SyncResult};
+use super::status::{KeysChanged, ServiceStatus, SyncResult};
 use super::storage_client::{BackoffListener, Sync15StorageClient, Sync15StorageClientInit};
+use super::CollectionKeys;
 use crate::clients_engine::{self, CommandProcessor, CLIENTS_TTL_REFRESH};
 use crate::engine::{EngineSyncAssociation, SyncEngine};
 use crate::error::Error;
 use crate::telemetry;
-use crate::KeyBundle;
+use crate::{ClientData, EncryptedPayload, KeyBundle, ServerTimestamp};
 use interrupt_support::Interruptee;
 use std::collections::HashMap;
 use std::result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
 use std::time::{Duration, SystemTime};
 
+// How often we check the interruptee while waiting for engines being synced in parallel.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Info about the client to use. We reuse the client unless
 /// we discover the client_init has changed, in which case we re-create one.
 #[derive(Debug)]
@@ -78,7 +86,9 @@ impl MemoryCachedState {
 /// * `persisted_global_state` - The global state to use, or None if never
 ///   before provided. At the end of the sync, and even when the sync fails,
 ///   the value in this cell should be persisted to permanent storage and
-///   provided next time the sync is called.
+///   provided next time the sync is called. It includes any backoff the
+///   server asked for - until that expires, we return a result with a
+///   `BackedOff` status and a `BackoffError` without syncing.
 /// * `last_client_info` - The client state to use, or None if never before
 ///   provided. At the end of the sync, the value should be persisted
 ///   *in memory only* - it should not be persisted to disk.
@@ -133,9 +143,21 @@ pub fn sync_multiple_with_command_processor(
         next_sync_after: None,
         engine_results: HashMap::with_capacity(engines.len()),
         telemetry: telemetry::SyncTelemetryPing::new(),
+        keys_changed: None,
     };
-    let backoff = super::storage_client::new_backoff_listener();
     let req_info = req_info.unwrap_or_default();
+    if let Some(until) = persisted_backoff_until(persisted_global_state, req_info.is_user_action) {
+        log::info!(
+            "The server asked us to back off until {:?}, not syncing",
+            until
+        );
+        sync_result.service_status = ServiceStatus::BackedOff;
+        sync_result.result = Err(Error::BackoffError(until));
+        sync_result.next_sync_after = Some(until);
+        mem_cached_state.next_sync_after = Some(until);
+        return sync_result;
+    }
+    let backoff = super::storage_client::new_backoff_listener();
     let driver = SyncMultipleDriver {
         command_processor,
         engines,
@@ -149,6 +171,7 @@ pub fn sync_multiple_with_command_processor(
         mem_cached_state,
         saw_auth_error: false,
         ignore_soft_backoff: req_info.is_user_action,
+        max_parallel_engines: req_info.max_parallel_engines,
     };
     match driver.sync() {
         Ok(()) => {
@@ -170,10 +193,61 @@ pub fn sync_multiple_with_command_processor(
     // ignoring it during the sync
     sync_result.set_sync_after(backoff.get_required_wait(false).unwrap_or_default());
     mem_cached_state.next_sync_after = sync_result.next_sync_after;
+    let retry_after =
+        sync_result.get_sync_after(backoff.get_required_wait(true).unwrap_or_default());
+    persist_backoff(
+        persisted_global_state,
+        sync_result.next_sync_after,
+        retry_after,
+    );
     log::trace!("Sync result: {:?}", sync_result);
     sync_result
 }
 
+// Returns when we may next sync, if a backoff from a previous sync is still in effect. This
+// saves the backoff if it had to be clamped, so that the clamped deadline is kept.
+fn persisted_backoff_until(
+    persisted_global_state: &mut Option<String>,
+    ignore_soft_backoff: bool,
+) -> Option<SystemTime> {
+    let persisted = persisted_global_state.as_deref()?;
+    // If this doesn't parse, the driver will report it.
+    let mut pgs = serde_json::from_str::<PersistedGlobalState>(persisted).ok()?;
+    let until = pgs.get_backoff_until(ignore_soft_backoff);
+    match serde_json::to_string(&pgs) {
+        Ok(s) if s != persisted => *persisted_global_state = Some(s),
+        Ok(_) => (),
+        Err(e) => log::warn!("Failed to persist the backoff: {}", e),
+    }
+    until
+}
+
+// Records the backoff the server asked for in the persisted state, so we respect it even if
+// the app restarts before the next sync.
+fn persist_backoff(
+    persisted_global_state: &mut Option<String>,
+    until: Option<SystemTime>,
+    retry_after: Option<SystemTime>,
+) {
+    let mut pgs = match persisted_global_state.as_deref() {
+        Some(persisted) if !persisted.is_empty() => {
+            match serde_json::from_str::<PersistedGlobalState>(persisted) {
+                Ok(pgs) => pgs,
+                // Already reported by the driver, and we don't want to replace it.
+                Err(_) => return,
+            }
+        }
+        // No need to start persisting state just to say there's no backoff.
+        _ if until.is_none() => return,
+        _ => PersistedGlobalState::default(),
+    };
+    pgs.set_backoff(until, retry_after);
+    match serde_json::to_string(&pgs) {
+        Ok(s) => *persisted_global_state = Some(s),
+        Err(e) => log::warn!("Failed to persist the backoff: {}", e),
+    }
+}
+
 /// This is essentially a bag of information that the sync manager knows, but
 /// otherwise we won't. It should probably be rethought if it gains many more
 /// fields.
@@ -181,6 +255,9 @@ pub fn sync_multiple_with_command_processor(
 pub struct SyncRequestInfo<'a> {
     pub engines_to_state_change: Option<&'a HashMap<String, bool>>,
     pub is_user_action: bool,
+    /// The maximum number of engines to sync at the same time. Only engines which opt in via
+    /// `SyncEngine::as_parallel` are synced in parallel; 0 or 1 syncs everything serially.
+    pub max_parallel_engines: usize,
 }
 
 // The sync multiple driver
@@ -197,11 +274,27 @@ struct SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
     mem_cached_state: &'mcs mut MemoryCachedState,
     ignore_soft_backoff: bool,
     saw_auth_error: bool,
+    max_parallel_engines: usize,
 }
 
 impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
     /// The actual worker for sync_multiple.
     fn sync(mut self) -> result::Result<(), Error> {
+        // We always record the sync, even if it failed before we got to the engines, so the
+        // telemetry ping is complete without callers needing to add the failure themselves.
+        let mut telem_sync = telemetry::SyncTelemetry::new();
+        let result = self.sync_with_telemetry(&mut telem_sync);
+        if let Err(ref e) = result {
+            telem_sync.failure(e.into());
+        }
+        self.result.telemetry.sync(telem_sync);
+        result
+    }
+
+    fn sync_with_telemetry(
+        &mut self,
+        telem_sync: &mut telemetry::SyncTelemetry,
+    ) -> result::Result<(), Error> {
         log::info!("Loading/initializing persisted state");
         let mut pgs = self.prepare_persisted_state();
 
@@ -236,7 +329,6 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
                 should_refresh,
             ) {
                 // Record telemetry with the error just in case...
-                let mut telem_sync = telemetry::SyncTelemetry::new();
                 let mut telem_engine = telemetry::Engine::new("clients");
                 telem_engine.failure(&e);
                 telem_sync.engine(telem_engine);
@@ -260,9 +352,12 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
 
         log::info!("Synchronizing engines");
 
-        let telem_sync =
-            self.sync_engines(&client_info, &mut global_state, clients_engine.as_ref());
-        self.result.telemetry.sync(telem_sync);
+        self.sync_engines(
+            &client_info,
+            &mut global_state,
+            clients_engine.as_ref(),
+            telem_sync,
+        );
 
         log::info!("Finished syncing engines.");
 
@@ -290,63 +385,154 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
         client_info: &ClientInfo,
         global_state: &mut GlobalState,
         clients: Option<&clients_engine::Engine<'_>>,
-    ) -> telemetry::SyncTelemetry {
-        let mut telem_sync = telemetry::SyncTelemetry::new();
+        telem_sync: &mut telemetry::SyncTelemetry,
+    ) {
+        let ctx = EngineSyncContext {
+            client: &client_info.client,
+            global_state,
+            root_sync_key: self.root_sync_key,
+            client_data: clients.map(|c| c.get_client_data()),
+            backoff: self.backoff.clone(),
+            ignore_soft_backoff: self.ignore_soft_backoff,
+        };
+        self.sync_engines_with(&ctx, telem_sync);
+    }
+
+    // Syncs each engine with `syncer`, in parallel where we can. This is separate from
+    // `sync_engines()` so that the tests can sync mock engines, without a server.
+    fn sync_engines_with(
+        &mut self,
+        syncer: &dyn EngineSyncer,
+        telem_sync: &mut telemetry::SyncTelemetry,
+    ) {
+        let mut parallel = Vec::new();
+        let mut serial = Vec::new();
         for engine in self.engines {
-            let name = engine.collection_name();
-            if self
-                .backoff
-                .get_required_wait(self.ignore_soft_backoff)
-                .is_some()
-            {
-                log::warn!("Got backoff, bailing out of sync early");
-                break;
+            match engine.as_parallel() {
+                Some(engine) if self.max_parallel_engines > 1 => parallel.push(engine),
+                _ => serial.push(*engine),
             }
-            if global_state.global.declined.iter().any(|e| e == &*name) {
-                log::info!("The {} engine is declined. Skipping", name);
-                continue;
+        }
+        if parallel.is_empty() {
+            for engine in serial {
+                let outcome = syncer.sync_engine(engine, self.interruptee);
+                if !self.record_outcome(outcome, telem_sync) {
+                    break;
+                }
             }
-            log::info!("Syncing {} engine!", name);
+            return;
+        }
 
-            let mut telem_engine = telemetry::Engine::new(&*name);
-            let result = super::sync::synchronize_with_clients_engine(
-                &client_info.client,
-                global_state,
-                self.root_sync_key,
-                clients,
-                *engine,
-                true,
-                &mut telem_engine,
-                self.interruptee,
-            );
+        let num_workers = self.max_parallel_engines.min(parallel.len());
+        log::info!(
+            "Syncing {} engines on {} workers",
+            parallel.len(),
+            num_workers
+        );
+        let queue = Mutex::new(parallel.into_iter());
+        let flags = StopFlags::default();
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..num_workers {
+                let (queue, flags, tx) = (&queue, &flags, tx.clone());
+                scope.spawn(move || {
+                    while !flags.stopping.load(Ordering::SeqCst) {
+                        // Bind the engine first so the queue isn't locked while we sync it.
+                        let next = queue.lock().unwrap().next();
+                        let Some(engine) = next else {
+                            break;
+                        };
+                        let outcome = syncer.sync_engine(engine, flags);
+                        // Stop taking engines from the queue straight away, rather than when
+                        // the outcome is recorded, by which time we might have started another.
+                        if outcome.stops_sync() {
+                            flags.stop(false);
+                        }
+                        if tx.send(outcome).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
 
-            match result {
-                Ok(()) => log::info!("Sync of {} was successful!", name),
-                Err(ref e) => {
-                    log::warn!("Sync of {} failed! {:?}", name, e);
-                    let this_status = ServiceStatus::from_err(e);
-                    // The only error which forces us to discard our state is an
-                    // auth error.
-                    self.saw_auth_error =
-                        self.saw_auth_error || this_status == ServiceStatus::AuthenticationError;
-                    telem_engine.failure(e);
-                    // If the failure from the engine looks like anything other than
-                    // a "engine error" we don't bother trying the others.
-                    if this_status != ServiceStatus::OtherError {
-                        telem_sync.engine(telem_engine);
-                        self.result.engine_results.insert(name.into(), result);
-                        self.result.service_status = this_status;
-                        break;
+            // Our interruptee can't be shared with the workers, so they check `flags`, which
+            // we set whenever we notice we've been interrupted. Engines which can't be synced
+            // in parallel are synced on this thread while the workers run.
+            for engine in serial {
+                if flags.stopping.load(Ordering::SeqCst) {
+                    break;
+                }
+                let outcome = syncer.sync_engine(engine, self.interruptee);
+                self.record_parallel_outcome(outcome, telem_sync, &flags);
+                while let Ok(outcome) = rx.try_recv() {
+                    self.record_parallel_outcome(outcome, telem_sync, &flags);
+                }
+            }
+            loop {
+                match rx.recv_timeout(INTERRUPT_POLL_INTERVAL) {
+                    Ok(outcome) => self.record_parallel_outcome(outcome, telem_sync, &flags),
+                    Err(RecvTimeoutError::Timeout) => {
+                        if self.was_interrupted() {
+                            flags.stop(true);
+                        }
                     }
+                    Err(RecvTimeoutError::Disconnected) => break,
                 }
             }
-            telem_sync.engine(telem_engine);
-            self.result.engine_results.insert(name.into(), result);
-            if self.was_interrupted() {
-                break;
+        });
+    }
+
+    // Records the outcome of syncing an engine, returning false if we shouldn't sync any more.
+    fn record_outcome(
+        &mut self,
+        outcome: EngineOutcome,
+        telem_sync: &mut telemetry::SyncTelemetry,
+    ) -> bool {
+        let (name, result, mut telem_engine) = match outcome {
+            EngineOutcome::BackedOff => return false,
+            EngineOutcome::Declined => return true,
+            EngineOutcome::Synced {
+                name,
+                result,
+                telem,
+            } => (name, result, *telem),
+        };
+        match result {
+            Ok(()) => log::info!("Sync of {} was successful!", name),
+            Err(ref e) => {
+                log::warn!("Sync of {} failed! {:?}", name, e);
+                let this_status = ServiceStatus::from_err(e);
+                // The only error which forces us to discard our state is an
+                // auth error.
+                self.saw_auth_error =
+                    self.saw_auth_error || this_status == ServiceStatus::AuthenticationError;
+                telem_engine.failure(e);
+                // If the failure from the engine looks like anything other than
+                // a "engine error" we don't bother trying the others.
+                if this_status != ServiceStatus::OtherError {
+                    telem_sync.engine(telem_engine);
+                    self.result.engine_results.insert(name, result);
+                    self.result.service_status = this_status;
+                    return false;
+                }
             }
         }
-        telem_sync
+        telem_sync.engine(telem_engine);
+        self.result.engine_results.insert(name, result);
+        !self.was_interrupted()
+    }
+
+    fn record_parallel_outcome(
+        &mut self,
+        outcome: EngineOutcome,
+        telem_sync: &mut telemetry::SyncTelemetry,
+        flags: &StopFlags,
+    ) {
+        if !self.record_outcome(outcome, telem_sync) {
+            // Engines already being synced are left to finish unless we were interrupted.
+            flags.stop(self.interruptee.was_interrupted());
+        }
     }
 
     fn run_state_machine(
@@ -355,6 +541,10 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
         pgs: &mut PersistedGlobalState,
     ) -> result::Result<GlobalState, Error> {
         let last_state = self.mem_cached_state.last_global_state.take();
+        // The keys we used last time, so we can tell if they've changed.
+        let last_keys = last_state
+            .as_ref()
+            .map(|state| (state.keys.clone(), state.keys_timestamp));
 
         let mut state_machine = SetupStateMachine::for_full_sync(
             &client_info.client,
@@ -391,6 +581,7 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
             }
             Ok(state) => state,
         };
+        self.reset_engines_for_new_keys(last_keys, &state)?;
         self.result.telemetry.uid(client_info.client.hashed_uid()?);
         // As for client_info, put None back now so we start from scratch on error.
         self.mem_cached_state.last_global_state = None;
@@ -421,6 +612,59 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
         Ok(())
     }
 
+    // Resets the engines whose records we can no longer decrypt because the keys changed. The
+    // keys themselves are re-derived from the new crypto/keys as each engine is synced.
+    fn reset_engines_for_new_keys(
+        &mut self,
+        last_keys: Option<(EncryptedPayload, ServerTimestamp)>,
+        state: &GlobalState,
+    ) -> result::Result<(), Error> {
+        let changed = match last_keys {
+            Some((keys, timestamp)) if timestamp != state.keys_timestamp => {
+                let new_keys = CollectionKeys::from_encrypted_payload(
+                    state.keys.clone(),
+                    state.keys_timestamp,
+                    self.root_sync_key,
+                )?;
+                match CollectionKeys::from_encrypted_payload(keys, timestamp, self.root_sync_key) {
+                    // Another client already replaced the keys for the new sync key.
+                    Err(_) => KeysChanged::SyncKey,
+                    Ok(old_keys) => {
+                        let reset_engines: Vec<String> = self
+                            .engines
+                            .iter()
+                            .map(|engine| engine.collection_name().to_string())
+                            .filter(|name| {
+                                old_keys.key_for_collection(name)
+                                    != new_keys.key_for_collection(name)
+                            })
+                            .collect();
+                        if reset_engines.is_empty() {
+                            return Ok(());
+                        }
+                        KeysChanged::CollectionKeys { reset_engines }
+                    }
+                }
+            }
+            _ => return Ok(()),
+        };
+        for engine in self.engines {
+            let name = engine.collection_name();
+            let affected = match &changed {
+                KeysChanged::SyncKey => true,
+                KeysChanged::CollectionKeys { reset_engines } => {
+                    reset_engines.iter().any(|e| *e == *name)
+                }
+            };
+            if affected {
+                log::info!("Resetting engine {}, as its key changed", name);
+                engine.reset(&EngineSyncAssociation::Disconnected)?;
+            }
+        }
+        self.result.keys_changed = Some(changed);
+        Ok(())
+    }
+
     fn prepare_client_info(&mut self) -> result::Result<ClientInfo, Error> {
         let mut client_info = match self.mem_cached_state.last_client_info.take() {
             Some(client_info) => {
@@ -489,3 +733,547 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
         }
     }
 }
+
+/// Syncs a single engine. Shared by the threads syncing engines in parallel.
+trait EngineSyncer: Sync {
+    fn sync_engine(&self, engine: &dyn SyncEngine, interruptee: &dyn Interruptee) -> EngineOutcome;
+}
+
+/// Everything needed to sync a single engine, shared by the threads syncing them.
+struct EngineSyncContext<'a> {
+    client: &'a Sync15StorageClient,
+    global_state: &'a GlobalState,
+    root_sync_key: &'a KeyBundle,
+    client_data: Option<ClientData>,
+    backoff: BackoffListener,
+    ignore_soft_backoff: bool,
+}
+
+impl EngineSyncer for EngineSyncContext<'_> {
+    fn sync_engine(&self, engine: &dyn SyncEngine, interruptee: &dyn Interruptee) -> EngineOutcome {
+        let name = engine.collection_name();
+        if self
+            .backoff
+            .get_required_wait(self.ignore_soft_backoff)
+            .is_some()
+        {
+            log::warn!("Got backoff, bailing out of sync early");
+            return EngineOutcome::BackedOff;
+        }
+        if self
+            .global_state
+            .global
+            .declined
+            .iter()
+            .any(|e| e == &*name)
+        {
+            log::info!("The {} engine is declined. Skipping", name);
+            return EngineOutcome::Declined;
+        }
+        log::info!("Syncing {} engine!", name);
+
+        let mut telem = telemetry::Engine::new(&*name);
+        let result = super::sync::synchronize_with_clients_engine(
+            self.client,
+            self.global_state,
+            self.root_sync_key,
+            self.client_data.as_ref(),
+            engine,
+            true,
+            &mut telem,
+            interruptee,
+        );
+        EngineOutcome::Synced {
+            name: name.into(),
+            result,
+            telem: Box::new(telem),
+        }
+    }
+}
+
+enum EngineOutcome {
+    BackedOff,
+    Declined,
+    Synced {
+        name: String,
+        result: result::Result<(), Error>,
+        // Boxed as it's much larger than the other variants.
+        telem: Box<telemetry::Engine>,
+    },
+}
+
+impl EngineOutcome {
+    // Whether `record_outcome()` will stop us syncing any more engines.
+    fn stops_sync(&self) -> bool {
+        match self {
+            EngineOutcome::BackedOff => true,
+            EngineOutcome::Declined => false,
+            EngineOutcome::Synced { result, .. } => result
+                .as_ref()
+                .is_err_and(|e| ServiceStatus::from_err(e) != ServiceStatus::OtherError),
+        }
+    }
+}
+
+/// How the thread coordinating a parallel sync tells the workers to stop.
+#[derive(Default)]
+struct StopFlags {
+    // Don't start syncing any more engines.
+    stopping: AtomicBool,
+    // Also abandon the engines currently being synced.
+    interrupted: AtomicBool,
+}
+
+impl StopFlags {
+    fn stop(&self, interrupt: bool) {
+        self.stopping.store(true, Ordering::SeqCst);
+        if interrupt {
+            self.interrupted.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Interruptee for StopFlags {
+    fn was_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::request::{InfoCollections, InfoConfiguration};
+    use super::*;
+    use crate::bso::{IncomingBso, OutgoingBso};
+    use crate::engine::CollectionRequest;
+    use crate::record_types::MetaGlobalRecord;
+    use crate::{CollectionName, Guid};
+    use anyhow::Result;
+    use interrupt_support::{Interrupted, NeverInterrupts};
+    use std::sync::atomic::AtomicUsize;
+    use std::thread::ThreadId;
+    use std::time::Instant;
+    use url::Url;
+
+    struct TestEngine {
+        collection_name: &'static str,
+        parallel: bool,
+        num_resets: AtomicUsize,
+    }
+
+    impl TestEngine {
+        fn serial(collection_name: &'static str) -> Self {
+            Self {
+                collection_name,
+                parallel: false,
+                num_resets: AtomicUsize::new(0),
+            }
+        }
+
+        fn parallel(collection_name: &'static str) -> Self {
+            Self {
+                collection_name,
+                parallel: true,
+                num_resets: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl SyncEngine for TestEngine {
+        fn collection_name(&self) -> CollectionName {
+            self.collection_name.into()
+        }
+
+        fn stage_incoming(
+            &self,
+            _inbound: Vec<IncomingBso>,
+            _telem: &mut telemetry::Engine,
+        ) -> Result<()> {
+            unreachable!("these tests shouldn't call these");
+        }
+
+        fn apply(
+            &self,
+            _timestamp: ServerTimestamp,
+            _telem: &mut telemetry::Engine,
+        ) -> Result<Vec<OutgoingBso>> {
+            unreachable!("these tests shouldn't call these");
+        }
+
+        fn set_uploaded(&self, _new_timestamp: ServerTimestamp, _ids: Vec<Guid>) -> Result<()> {
+            unreachable!("these tests shouldn't call these");
+        }
+
+        fn get_collection_request(
+            &self,
+            _server_timestamp: ServerTimestamp,
+        ) -> Result<Option<CollectionRequest>> {
+            unreachable!("these tests shouldn't call these");
+        }
+
+        fn get_sync_assoc(&self) -> Result<EngineSyncAssociation> {
+            unreachable!("these tests shouldn't call these");
+        }
+
+        fn reset(&self, assoc: &EngineSyncAssociation) -> Result<()> {
+            assert!(matches!(assoc, EngineSyncAssociation::Disconnected));
+            self.num_resets.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn as_parallel(&self) -> Option<&(dyn SyncEngine + Sync)> {
+            if self.parallel {
+                Some(self)
+            } else {
+                None
+            }
+        }
+    }
+
+    // Syncs each engine by calling a function with its name, instead of talking to a server.
+    struct TestSyncer<F>(F);
+
+    impl<F> TestSyncer<F>
+    where
+        F: Fn(&str, &dyn Interruptee) -> result::Result<(), Error> + Sync,
+    {
+        fn new(sync: F) -> Self {
+            Self(sync)
+        }
+    }
+
+    impl<F> EngineSyncer for TestSyncer<F>
+    where
+        F: Fn(&str, &dyn Interruptee) -> result::Result<(), Error> + Sync,
+    {
+        fn sync_engine(
+            &self,
+            engine: &dyn SyncEngine,
+            interruptee: &dyn Interruptee,
+        ) -> EngineOutcome {
+            let name = engine.collection_name();
+            EngineOutcome::Synced {
+                name: name.to_string(),
+                result: (self.0)(&name, interruptee),
+                telem: Box::new(telemetry::Engine::new(&*name)),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct TestInterruptee(AtomicBool);
+
+    impl Interruptee for TestInterruptee {
+        fn was_interrupted(&self) -> bool {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    // Waits for something another engine's thread does, failing the test if it never happens.
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    // Runs `f` with a driver for `engines`, returning the result it recorded.
+    fn with_driver(
+        engines: &[&dyn SyncEngine],
+        root_sync_key: &KeyBundle,
+        max_parallel_engines: usize,
+        interruptee: &dyn Interruptee,
+        f: impl FnOnce(&mut SyncMultipleDriver<'_, '_, '_, '_>),
+    ) -> SyncResult {
+        let storage_init = Sync15StorageClientInit {
+            key_id: "key-id".into(),
+            access_token: "access-token".into(),
+            tokenserver_url: Url::parse("https://token.example.com").unwrap(),
+        };
+        let mut persisted_global_state = None;
+        let mut mem_cached_state = MemoryCachedState::default();
+        let mut result = SyncResult {
+            service_status: ServiceStatus::Ok,
+            result: Ok(()),
+            declined: None,
+            next_sync_after: None,
+            engine_results: HashMap::new(),
+            telemetry: telemetry::SyncTelemetryPing::new(),
+            keys_changed: None,
+        };
+        f(&mut SyncMultipleDriver {
+            command_processor: None,
+            engines,
+            storage_init: &storage_init,
+            root_sync_key,
+            interruptee,
+            backoff: super::super::storage_client::new_backoff_listener(),
+            engines_to_state_change: None,
+            result: &mut result,
+            persisted_global_state: &mut persisted_global_state,
+            mem_cached_state: &mut mem_cached_state,
+            ignore_soft_backoff: false,
+            saw_auth_error: false,
+            max_parallel_engines,
+        });
+        result
+    }
+
+    fn sync_engines(
+        engines: &[&dyn SyncEngine],
+        max_parallel_engines: usize,
+        interruptee: &dyn Interruptee,
+        syncer: &dyn EngineSyncer,
+    ) -> SyncResult {
+        let root_sync_key = KeyBundle::new_random().unwrap();
+        with_driver(
+            engines,
+            &root_sync_key,
+            max_parallel_engines,
+            interruptee,
+            |driver| driver.sync_engines_with(syncer, &mut telemetry::SyncTelemetry::new()),
+        )
+    }
+
+    #[test]
+    fn test_sync_engines_in_parallel() {
+        let (tabs, passwords, addresses) = (
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+            TestEngine::parallel("addresses"),
+        );
+        let started = AtomicUsize::new(0);
+        // Each engine waits for the others to start, which only happens if they're synced at the
+        // same time.
+        let syncer = TestSyncer::new(|_, _| {
+            started.fetch_add(1, Ordering::SeqCst);
+            wait_for(|| started.load(Ordering::SeqCst) == 3);
+            Ok(())
+        });
+        let result = sync_engines(
+            &[&tabs, &passwords, &addresses],
+            3,
+            &NeverInterrupts,
+            &syncer,
+        );
+        assert_eq!(result.service_status, ServiceStatus::Ok);
+        let mut synced: Vec<_> = result.engine_results.keys().cloned().collect();
+        synced.sort();
+        assert_eq!(synced, ["addresses", "passwords", "tabs"]);
+        assert!(result.engine_results.values().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_sync_engines_in_parallel_stops_after_failure() {
+        let (tabs, passwords, addresses, creditcards) = (
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+            TestEngine::parallel("addresses"),
+            TestEngine::parallel("creditcards"),
+        );
+        let started = Mutex::new(Vec::new());
+        let tabs_failed = AtomicBool::new(false);
+        let syncer = TestSyncer::new(|name, _| {
+            started.lock().unwrap().push(name.to_string());
+            match name {
+                "tabs" => {
+                    wait_for(|| started.lock().unwrap().len() == 2);
+                    tabs_failed.store(true, Ordering::SeqCst);
+                    Err(Error::TokenserverHttpError(401))
+                }
+                _ => {
+                    // The engine which was already being synced is allowed to finish.
+                    wait_for(|| tabs_failed.load(Ordering::SeqCst));
+                    thread::sleep(Duration::from_millis(50));
+                    Ok(())
+                }
+            }
+        });
+        let result = sync_engines(
+            &[&tabs, &passwords, &addresses, &creditcards],
+            2,
+            &NeverInterrupts,
+            &syncer,
+        );
+        assert_eq!(result.service_status, ServiceStatus::AuthenticationError);
+        let mut started = started.lock().unwrap().clone();
+        started.sort();
+        assert_eq!(started, ["passwords", "tabs"]);
+        assert!(result.engine_results["tabs"].is_err());
+        assert!(result.engine_results["passwords"].is_ok());
+        assert_eq!(result.engine_results.len(), 2);
+    }
+
+    #[test]
+    fn test_sync_engines_in_parallel_interrupted() {
+        let (tabs, passwords, addresses) = (
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+            TestEngine::parallel("addresses"),
+        );
+        let interruptee = TestInterruptee::default();
+        let started = AtomicUsize::new(0);
+        // Both engines being synced keep going until the interrupt reaches them, which only
+        // happens through the workers' own interruptee.
+        let syncer = TestSyncer::new(|_, worker_interruptee| {
+            if started.fetch_add(1, Ordering::SeqCst) == 1 {
+                interruptee.0.store(true, Ordering::SeqCst);
+            }
+            wait_for(|| worker_interruptee.was_interrupted());
+            Err(Interrupted.into())
+        });
+        let result = sync_engines(&[&tabs, &passwords, &addresses], 2, &interruptee, &syncer);
+        assert_eq!(result.service_status, ServiceStatus::Interrupted);
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+        assert!(!result.engine_results.contains_key("addresses"));
+    }
+
+    #[test]
+    fn test_sync_engines_keeps_places_serial() {
+        // History and bookmarks share the places database, so they don't opt in to parallel
+        // syncs, and must be synced one at a time on the calling thread.
+        let (history, bookmarks, tabs, passwords) = (
+            TestEngine::serial("history"),
+            TestEngine::serial("bookmarks"),
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+        );
+        let threads: Mutex<HashMap<String, ThreadId>> = Mutex::default();
+        let num_serial_syncing = AtomicUsize::new(0);
+        let syncer = TestSyncer::new(|name, _| {
+            threads
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), thread::current().id());
+            if ["history", "bookmarks"].contains(&name) {
+                assert_eq!(num_serial_syncing.fetch_add(1, Ordering::SeqCst), 0);
+                thread::sleep(Duration::from_millis(20));
+                num_serial_syncing.fetch_sub(1, Ordering::SeqCst);
+            }
+            Ok(())
+        });
+        let result = sync_engines(
+            &[&history, &bookmarks, &tabs, &passwords],
+            2,
+            &NeverInterrupts,
+            &syncer,
+        );
+        assert_eq!(result.service_status, ServiceStatus::Ok);
+        assert_eq!(result.engine_results.len(), 4);
+        let threads = threads.lock().unwrap();
+        let this_thread = thread::current().id();
+        assert_eq!(threads["history"], this_thread);
+        assert_eq!(threads["bookmarks"], this_thread);
+        assert_ne!(threads["tabs"], this_thread);
+        assert_ne!(threads["passwords"], this_thread);
+    }
+
+    fn global_state(keys: &CollectionKeys, root_sync_key: &KeyBundle) -> GlobalState {
+        GlobalState {
+            config: InfoConfiguration::default(),
+            collections: InfoCollections::new(HashMap::new()),
+            global: MetaGlobalRecord {
+                sync_id: "syncIDAAAAAA".into(),
+                storage_version: 5,
+                engines: HashMap::new(),
+                declined: Vec::new(),
+            },
+            global_timestamp: ServerTimestamp(1000),
+            keys: keys.to_encrypted_payload(root_sync_key).unwrap(),
+            keys_timestamp: keys.timestamp,
+        }
+    }
+
+    #[test]
+    fn test_reset_engines_for_new_collection_keys() {
+        let (tabs, passwords, addresses) = (
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+            TestEngine::parallel("addresses"),
+        );
+        let engines: &[&dyn SyncEngine] = &[&tabs, &passwords, &addresses];
+        let root_sync_key = KeyBundle::new_random().unwrap();
+        let passwords_key = KeyBundle::new_random().unwrap();
+        let old_keys = CollectionKeys {
+            timestamp: ServerTimestamp(1000),
+            default: KeyBundle::new_random().unwrap(),
+            collections: [("passwords".to_string(), passwords_key.clone())].into(),
+        };
+        let last_keys = Some((
+            old_keys.to_encrypted_payload(&root_sync_key).unwrap(),
+            old_keys.timestamp,
+        ));
+
+        // Another client gave tabs its own key, and kept the others.
+        let new_keys = CollectionKeys {
+            timestamp: ServerTimestamp(2000),
+            collections: [
+                ("passwords".to_string(), passwords_key),
+                ("tabs".to_string(), KeyBundle::new_random().unwrap()),
+            ]
+            .into(),
+            ..old_keys
+        };
+        let state = global_state(&new_keys, &root_sync_key);
+        let result = with_driver(engines, &root_sync_key, 1, &NeverInterrupts, |driver| {
+            driver
+                .reset_engines_for_new_keys(last_keys, &state)
+                .unwrap()
+        });
+        assert_eq!(
+            result.keys_changed,
+            Some(KeysChanged::CollectionKeys {
+                reset_engines: vec!["tabs".to_string()]
+            })
+        );
+        assert_eq!(tabs.num_resets.load(Ordering::SeqCst), 1);
+        assert_eq!(passwords.num_resets.load(Ordering::SeqCst), 0);
+        assert_eq!(addresses.num_resets.load(Ordering::SeqCst), 0);
+
+        // If the keys didn't change, nothing is reset.
+        let state = global_state(&new_keys, &root_sync_key);
+        let last_keys = Some((state.keys.clone(), state.keys_timestamp));
+        let result = with_driver(engines, &root_sync_key, 1, &NeverInterrupts, |driver| {
+            driver
+                .reset_engines_for_new_keys(last_keys, &state)
+                .unwrap()
+        });
+        assert_eq!(result.keys_changed, None);
+        assert_eq!(tabs.num_resets.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_reset_engines_for_new_sync_key() {
+        let (tabs, passwords) = (
+            TestEngine::parallel("tabs"),
+            TestEngine::parallel("passwords"),
+        );
+        let engines: &[&dyn SyncEngine] = &[&tabs, &passwords];
+        let old_root_sync_key = KeyBundle::new_random().unwrap();
+        let root_sync_key = KeyBundle::new_random().unwrap();
+        let old_keys = CollectionKeys {
+            timestamp: ServerTimestamp(1000),
+            default: KeyBundle::new_random().unwrap(),
+            collections: HashMap::new(),
+        };
+        let last_keys = Some((
+            old_keys.to_encrypted_payload(&old_root_sync_key).unwrap(),
+            old_keys.timestamp,
+        ));
+        // Another client already replaced the keys after the sync key changed.
+        let new_keys = CollectionKeys {
+            timestamp: ServerTimestamp(2000),
+            default: KeyBundle::new_random().unwrap(),
+            collections: HashMap::new(),
+        };
+        let state = global_state(&new_keys, &root_sync_key);
+        let result = with_driver(engines, &root_sync_key, 1, &NeverInterrupts, |driver| {
+            driver
+                .reset_engines_for_new_keys(last_keys, &state)
+                .unwrap()
+        });
+        assert_eq!(result.keys_changed, Some(KeysChanged::SyncKey));
+        assert_eq!(tabs.num_resets.load(Ordering::SeqCst), 1);
+        assert_eq!(passwords.num_resets.load(Ordering::SeqCst), 1);
+    }
+}
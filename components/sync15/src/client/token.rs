@@ -7,8 +7,8 @@ use crate::ServerTimestamp;
 use rc_crypto::hawk;
 use serde_derive::*;
 use std::borrow::{Borrow, Cow};
-use std::cell::RefCell;
 use std::fmt;
+use std::sync::Mutex;
 use std::time::{Duration, SystemTime};
 use url::Url;
 use viaduct::{header_names, Request};
@@ -121,6 +121,11 @@ impl TokenFetcher for TokenServerFetcher {
                 let when = self.now() + Duration::from_millis(ms);
                 return Err(ErrorKind::BackoffError(when));
             }
+            // It might instead ask us to back off with X-Backoff.
+            if let Some(secs) = resp.headers.try_get::<f64, _>(header_names::X_BACKOFF) {
+                let when = self.now() + Duration::from_millis((secs * 1000f64) as u64);
+                return Err(ErrorKind::BackoffError(when));
+            }
             let status = resp.status;
             return Err(ErrorKind::TokenserverHttpError(status));
         }
@@ -240,8 +245,9 @@ enum TokenState {
 #[derive(Debug)]
 struct TokenProviderImpl<TF: TokenFetcher> {
     fetcher: TF,
-    // Our token state (ie, whether we have a token, and if not, why not)
-    current_state: RefCell<TokenState>,
+    // Our token state (ie, whether we have a token, and if not, why not). A mutex rather than
+    // a RefCell because engines may be synced in parallel, sharing a single provider.
+    current_state: Mutex<TokenState>,
 }
 
 impl<TF: TokenFetcher> TokenProviderImpl<TF> {
@@ -251,7 +257,7 @@ impl<TF: TokenFetcher> TokenProviderImpl<TF> {
         rc_crypto::ensure_initialized();
         TokenProviderImpl {
             fetcher,
-            current_state: RefCell::new(TokenState::NoToken),
+            current_state: Mutex::new(TokenState::NoToken),
         }
     }
 
@@ -351,7 +357,7 @@ impl<TF: TokenFetcher> TokenProviderImpl<TF> {
     {
         // first get a mutable ref to our existing state, advance to the
         // state we will use, then re-stash that state for next time.
-        let state: &mut TokenState = &mut self.current_state.borrow_mut();
+        let state: &mut TokenState = &mut self.current_state.lock().unwrap();
         if let Some(new_state) = self.advance_state(state) {
             *state = new_state;
         }
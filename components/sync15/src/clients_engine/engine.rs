@@ -312,7 +312,7 @@ impl<'a> Engine<'a> {
             outgoing,
             true,
         )?
-        .upload()?;
+        .upload(&mut |_| Ok(()))?;
 
         log::info!(
             "Upload success ({} records success, {} records failed)",
@@ -335,7 +335,11 @@ impl<'a> Engine<'a> {
         let coll_request = CollectionRequest::new(COLLECTION_NAME.into()).full();
 
         self.interruptee.err_if_interrupted()?;
-        let inbound = crate::client::fetch_incoming(storage_client, coll_state, coll_request)?;
+        let mut inbound = Vec::new();
+        crate::client::fetch_incoming(storage_client, coll_state, coll_request, &mut |batch| {
+            inbound.extend(batch);
+            Ok(())
+        })?;
 
         Ok(inbound)
     }
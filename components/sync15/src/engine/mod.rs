@@ -13,9 +13,10 @@
 //! implemented in the [client] module (or in some cases, implemented externally)
 //!
 //! There are currently 2 types of engine:
-//! * Code which implements the [crate::engine::sync_engine::SyncEngine]
-//!   trait. These are the "original" Rust engines, designed to be used with
-//!   the [crate::client](sync client)
+//! * Code which implements the [SyncEngine] trait. These are the "original"
+//!   Rust engines, designed to be used with the [sync client](crate::client).
+//!   This is also the trait for crates outside this repo to implement - see its
+//!   docs for how, and an example.
 //! * Code which implements the [crate::engine::bridged_engine::BridgedEngine]
 //!   trait. These engines are a "bridge" between the Desktop JS Sync world and
 //!   this rust code.
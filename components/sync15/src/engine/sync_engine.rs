@@ -120,7 +120,144 @@ impl TryFrom<&str> for SyncEngineId {
 ///
 /// Different engines will produce errors of different types.  To accommodate
 /// this, we force them all to return anyhow::Error.
+///
+/// # Implementing an engine
+///
+/// This trait is how crates outside this repo sync their own data, so we treat any breaking
+/// change to it as a breaking change to this crate. Everything an engine needs is public - the
+/// records are in [crate::bso], and the telemetry in [crate::telemetry].
+///
+/// For each sync, the client calls:
+/// 1. `get_sync_assoc()`, then `reset()` if the engine's sync IDs don't match the server's - eg,
+///    on the first sync, or after another device reset the collection. The engine should persist
+///    the association it's given, so it can return it next time.
+/// 2. `get_collection_request()`, to find out which records to download.
+/// 3. `stage_incoming()`, once for each batch of records downloaded.
+/// 4. `apply()`, which reconciles the staged records with the local data, and returns the
+///    records to upload.
+/// 5. `set_uploaded()`, once for each batch the server accepts, then `sync_finished()`.
+///
+/// The engine is responsible for persisting its own state - the sync association and the
+/// timestamp of the last sync - in the same storage as its data.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Mutex;
+/// use serde::{Deserialize, Serialize};
+/// use sync15::bso::{IncomingBso, OutgoingBso};
+/// use sync15::engine::{CollectionRequest, EngineSyncAssociation, SyncEngine};
+/// use sync15::{telemetry, CollectionName, Guid, ServerTimestamp};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct FormEntry {
+///     id: Guid,
+///     name: String,
+///     value: String,
+/// }
+///
+/// // A real engine would keep all of this in its database.
+/// struct FormsEngine {
+///     assoc: Mutex<EngineSyncAssociation>,
+///     last_sync: Mutex<ServerTimestamp>,
+///     staged: Mutex<Vec<IncomingBso>>,
+///     entries: Mutex<Vec<FormEntry>>,
+/// }
+///
+/// impl SyncEngine for FormsEngine {
+///     fn collection_name(&self) -> CollectionName {
+///         "forms".into()
+///     }
+///
+///     fn stage_incoming(
+///         &self,
+///         inbound: Vec<IncomingBso>,
+///         _telem: &mut telemetry::Engine,
+///     ) -> anyhow::Result<()> {
+///         self.staged.lock().unwrap().extend(inbound);
+///         Ok(())
+///     }
+///
+///     fn apply(
+///         &self,
+///         timestamp: ServerTimestamp,
+///         _telem: &mut telemetry::Engine,
+///     ) -> anyhow::Result<Vec<OutgoingBso>> {
+///         let mut entries = self.entries.lock().unwrap();
+///         for bso in self.staged.lock().unwrap().drain(..) {
+///             // Tombstones, and records we can't parse, have no content.
+///             if let Some(entry) = bso.into_content::<FormEntry>().content() {
+///                 entries.retain(|e| e.id != entry.id);
+///                 entries.push(entry);
+///             }
+///         }
+///         *self.last_sync.lock().unwrap() = timestamp;
+///         // This is where we'd return the entries changed since the last sync.
+///         Ok(Vec::new())
+///     }
+///
+///     fn set_uploaded(&self, new_timestamp: ServerTimestamp, _ids: Vec<Guid>) -> anyhow::Result<()> {
+///         *self.last_sync.lock().unwrap() = new_timestamp;
+///         Ok(())
+///     }
+///
+///     fn get_collection_request(
+///         &self,
+///         server_timestamp: ServerTimestamp,
+///     ) -> anyhow::Result<Option<CollectionRequest>> {
+///         let since = *self.last_sync.lock().unwrap();
+///         Ok(if since >= server_timestamp {
+///             None
+///         } else {
+///             Some(CollectionRequest::new(self.collection_name()).full().newer_than(since))
+///         })
+///     }
+///
+///     fn get_sync_assoc(&self) -> anyhow::Result<EngineSyncAssociation> {
+///         Ok(self.assoc.lock().unwrap().clone())
+///     }
+///
+///     fn reset(&self, assoc: &EngineSyncAssociation) -> anyhow::Result<()> {
+///         *self.assoc.lock().unwrap() = assoc.clone();
+///         *self.last_sync.lock().unwrap() = ServerTimestamp::default();
+///         Ok(())
+///     }
+///
+///     fn wipe(&self) -> anyhow::Result<()> {
+///         self.entries.lock().unwrap().clear();
+///         Ok(())
+///     }
+/// }
+///
+/// let engine = FormsEngine {
+///     assoc: Mutex::new(EngineSyncAssociation::Disconnected),
+///     last_sync: Mutex::new(ServerTimestamp::default()),
+///     staged: Mutex::new(Vec::new()),
+///     entries: Mutex::new(Vec::new()),
+/// };
+/// let mut telem = telemetry::Engine::new("forms");
+/// engine
+///     .stage_incoming(
+///         vec![IncomingBso::from_test_content(serde_json::json!({
+///             "id": "formentry001",
+///             "name": "email",
+///             "value": "me@example.com",
+///         }))],
+///         &mut telem,
+///     )
+///     .unwrap();
+/// engine.apply(ServerTimestamp(1000), &mut telem).unwrap();
+/// assert_eq!(engine.entries.lock().unwrap().len(), 1);
+/// // Nothing has changed on the server since.
+/// assert!(engine
+///     .get_collection_request(ServerTimestamp(1000))
+///     .unwrap()
+///     .is_none());
+/// ```
 pub trait SyncEngine {
+    /// The name of the collection on the server, such as "passwords". The client only syncs
+    /// collections which are listed in `meta/global`, which are the collections Firefox knows
+    /// about, so an engine for any other collection won't be synced.
     fn collection_name(&self) -> CollectionName;
 
     /// Prepares the engine for syncing. The tabs engine currently uses this to
@@ -190,8 +327,9 @@ pub trait SyncEngine {
     ) -> Result<Vec<OutgoingBso>>;
 
     /// Indicates that the given record IDs were uploaded successfully to the server.
-    /// This may be called multiple times per sync, once for each batch. Batching is determined
-    /// dynamically based on payload sizes and counts via the server's advertised limits.
+    /// This may be called multiple times per sync, once for each batch the server commits, as
+    /// soon as it does. Batching is determined dynamically based on payload sizes and counts via
+    /// the server's advertised limits.
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> Result<()>;
 
     /// Called once the sync is finished. Not currently called if uploads fail (which
@@ -207,6 +345,8 @@ pub trait SyncEngine {
     /// request saying "give me full records since that date" - however, other
     /// engines might do something fancier. It can return None if the server timestamp
     /// has not advanced since the last sync.
+    /// The records are fetched in batches unless the request sets its own `limit`, and a
+    /// request with `newer_than()` only fetches anything if the collection has changed since.
     /// This could even later be extended to handle "backfills", and we might end up
     /// wanting one engine to use multiple collections (eg, as a "foreign key" via guid), etc.
     fn get_collection_request(
@@ -232,6 +372,17 @@ pub trait SyncEngine {
     fn wipe(&self) -> Result<()> {
         unimplemented!("The engine does not implement wipe, no wipe should be requested")
     }
+
+    /// Returns this engine if it can be synced on another thread, at the same time as other
+    /// engines. `sync_multiple` runs such engines concurrently when the caller asks for it
+    /// via `SyncRequestInfo::max_parallel_engines`; all others are synced one at a time.
+    ///
+    /// Engines whose storage is independent of every other engine (eg, tabs or logins) can
+    /// implement this as `Some(self)`. Engines which share storage with another engine (eg,
+    /// history and bookmarks, which share the places database) must not.
+    fn as_parallel(&self) -> Option<&(dyn SyncEngine + Sync)> {
+        None
+    }
 }
 
 #[cfg(test)]
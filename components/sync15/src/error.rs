@@ -8,6 +8,8 @@ use interrupt_support::Interrupted;
 #[cfg(feature = "sync-client")]
 #[derive(Debug, Clone)]
 pub enum ErrorResponse {
+    // 304, for a request which only wanted changes, when there weren't any.
+    NotModified { route: String },
     NotFound { route: String },
     // 401
     Unauthorized { route: String },
@@ -28,12 +28,24 @@ where
 
 /// What we record for 'when' and 'took' in a telemetry record.
 #[derive(Debug, Serialize)]
-struct WhenTook {
+pub struct WhenTook {
     when: f64,
     #[serde(skip_serializing_if = "crate::skip_if_default")]
     took: u64,
 }
 
+impl WhenTook {
+    /// Get the time this started, to the second.
+    pub fn get_started_at(&self) -> time::SystemTime {
+        time::UNIX_EPOCH + time::Duration::from_secs_f64(self.when)
+    }
+
+    /// Get the time this finished.
+    pub fn get_finished_at(&self) -> time::SystemTime {
+        self.get_started_at() + time::Duration::from_millis(self.took)
+    }
+}
+
 /// What we track while recording 'when' and 'took. It serializes as a WhenTook,
 /// except when .finished() hasn't been called, in which case it panics.
 #[derive(Debug)]
@@ -75,6 +87,13 @@ impl Stopwatch {
             }
         }
     }
+
+    fn get_when_took(&self) -> Option<&WhenTook> {
+        match self {
+            Stopwatch::Started(_, _) => None,
+            Stopwatch::Finished(wt) => Some(wt),
+        }
+    }
 }
 
 impl Serialize for Stopwatch {
@@ -124,6 +143,22 @@ mod stopwatch_tests {
             serde_json::json!({"when": 1.0}),
         );
     }
+
+    #[test]
+    fn test_times() {
+        let wt = WhenTook {
+            when: 10.0,
+            took: 1500,
+        };
+        assert_eq!(
+            wt.get_started_at(),
+            time::UNIX_EPOCH + time::Duration::from_secs(10)
+        );
+        assert_eq!(
+            wt.get_finished_at(),
+            time::UNIX_EPOCH + time::Duration::from_millis(11_500)
+        );
+    }
 }
 
 /// A generic "Event" - suitable for all kinds of pings (although this module
@@ -398,6 +433,18 @@ impl EngineOutgoing {
     pub fn failed(&mut self, n: usize) {
         self.failed += n;
     }
+
+    /// Get the value of `sent`.
+    #[inline]
+    pub fn get_sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Get the value of `failed`.
+    #[inline]
+    pub fn get_failed(&self) -> usize {
+        self.failed
+    }
 }
 
 /// One engine's sync.
@@ -473,6 +520,28 @@ impl Engine {
     fn finished(&mut self) {
         self.when_took = self.when_took.finished();
     }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get when this engine's sync started and how long it took, once it has finished.
+    pub fn get_when_took(&self) -> Option<&WhenTook> {
+        self.when_took.get_when_took()
+    }
+
+    /// Get the outgoing records, one for each batch posted.
+    pub fn get_outgoing(&self) -> &[EngineOutgoing] {
+        &self.outgoing
+    }
+
+    pub fn get_failure(&self) -> Option<&SyncFailure> {
+        self.failure.as_ref()
+    }
+
+    pub fn get_validation(&self) -> Option<&Validation> {
+        self.validation.as_ref()
+    }
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -501,6 +570,18 @@ impl Validation {
         }
         self
     }
+
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn get_problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    pub fn get_failure(&self) -> Option<&SyncFailure> {
+        self.failure.as_ref()
+    }
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -510,6 +591,16 @@ pub struct Problem {
     count: usize,
 }
 
+impl Problem {
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+}
+
 #[cfg(test)]
 mod engine_tests {
     use super::*;
@@ -643,6 +734,20 @@ impl SyncTelemetry {
     pub fn finished(&mut self) {
         self.when_took = self.when_took.finished();
     }
+
+    /// Get when this sync started and how long it took, once it has finished.
+    pub fn get_when_took(&self) -> Option<&WhenTook> {
+        self.when_took.get_when_took()
+    }
+
+    pub fn get_engines(&self) -> &[Engine] {
+        &self.engines
+    }
+
+    /// Get the failure of the sync as a whole, as opposed to that of an engine.
+    pub fn get_failure(&self) -> Option<&SyncFailure> {
+        self.failure.as_ref()
+    }
 }
 
 #[cfg(test)]
@@ -779,6 +884,14 @@ impl SyncTelemetryPing {
     pub fn event(&mut self, e: Event) {
         self.events.push(e);
     }
+
+    pub fn get_uid(&self) -> Option<&str> {
+        self.uid.as_deref()
+    }
+
+    pub fn get_syncs(&self) -> &[SyncTelemetry] {
+        &self.syncs
+    }
 }
 
 ffi_support::implement_into_ffi_by_json!(SyncTelemetryPing);
@@ -832,6 +945,7 @@ impl<'a> From<&'a Error> for SyncFailure {
             Error::BackoffError(_) => SyncFailure::Http { code: 503 },
             #[cfg(feature = "sync-client")]
             Error::StorageHttpError(ref e) => match e {
+                ErrorResponse::NotModified { .. } => SyncFailure::Http { code: 304 },
                 ErrorResponse::NotFound { .. } => SyncFailure::Http { code: 404 },
                 ErrorResponse::Unauthorized { .. } => SyncFailure::Auth { from: "storage" },
                 ErrorResponse::PreconditionFailed { .. } => SyncFailure::Http { code: 412 },
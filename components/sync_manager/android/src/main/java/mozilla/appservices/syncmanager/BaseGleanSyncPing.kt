@@ -5,14 +5,18 @@
 package mozilla.appservices.syncmanager
 
 import mozilla.appservices.sync15.EngineInfo
+import mozilla.appservices.sync15.FailureName
 import mozilla.appservices.sync15.FailureReason
+import mozilla.appservices.sync15.ProblemInfo
 import java.util.Date
 
 /**
  * Holds fields common to all Glean sync engine pings.
  */
 internal data class BaseGleanSyncPing(
-    val uid: String,
+    val name: String,
+    // Null if the sync failed before we knew the hashed FxA uid.
+    val uid: String?,
     val startedAt: Date,
     val finishedAt: Date,
     val applied: Int,
@@ -22,6 +26,7 @@ internal data class BaseGleanSyncPing(
     val failedToUpload: Int,
     val outgoingBatches: Int,
     val failureReason: FailureReason?,
+    val validationProblems: List<ProblemInfo> = emptyList(),
 ) {
     companion object {
         const val MILLIS_PER_SEC = 1000L
@@ -35,6 +40,7 @@ internal data class BaseGleanSyncPing(
                 Pair(uploaded + batch.sent, failedToUpload + batch.failed)
             }
             return BaseGleanSyncPing(
+                name = info.name,
                 uid = uid,
                 startedAt = Date(info.at * MILLIS_PER_SEC),
                 // Glean intentionally doesn't support recording arbitrary
@@ -51,7 +57,40 @@ internal data class BaseGleanSyncPing(
                 failedToUpload = failedToUpload,
                 outgoingBatches = info.outgoing.size,
                 failureReason = info.failureReason,
+                validationProblems = info.validation?.problems.orEmpty(),
             )
         }
+
+        fun fromEngineSyncPing(uid: String?, engine: EngineSyncPing): BaseGleanSyncPing {
+            return BaseGleanSyncPing(
+                name = engine.name,
+                uid = uid,
+                startedAt = Date.from(engine.startedAt),
+                finishedAt = Date.from(engine.finishedAt),
+                applied = engine.applied.toClampedInt(),
+                failedToApply = engine.failedToApply.toClampedInt(),
+                reconciled = engine.reconciled.toClampedInt(),
+                uploaded = engine.uploaded.toClampedInt(),
+                failedToUpload = engine.failedToUpload.toClampedInt(),
+                outgoingBatches = engine.outgoingBatches.toClampedInt(),
+                failureReason = engine.failureReason?.let { failureReasonFrom(it) },
+                validationProblems = engine.validation?.problems.orEmpty().map {
+                    ProblemInfo(it.name, it.count.toClampedInt())
+                },
+            )
+        }
+
+        fun failureReasonFrom(reason: SyncFailureReason): FailureReason {
+            val name = when (reason.kind) {
+                SyncFailureKind.SHUTDOWN -> FailureName.Shutdown
+                SyncFailureKind.OTHER -> FailureName.Other
+                SyncFailureKind.UNEXPECTED -> FailureName.Unexpected
+                SyncFailureKind.AUTH -> FailureName.Auth
+                SyncFailureKind.HTTP -> FailureName.Http
+            }
+            return FailureReason(name, reason.message, reason.code?.toInt() ?: -1)
+        }
+
+        private fun UInt.toClampedInt(): Int = minOf(this, Int.MAX_VALUE.toUInt()).toInt()
     }
 }
@@ -4,7 +4,6 @@
 
 package mozilla.appservices.syncmanager
 
-import mozilla.appservices.sync15.EngineInfo
 import mozilla.appservices.sync15.FailureName
 import mozilla.appservices.sync15.FailureReason
 import mozilla.appservices.sync15.SyncTelemetryPing
@@ -59,57 +58,110 @@ object SyncTelemetry {
         submitTabsPing: () -> Unit = { Pings.tabsSync.submit() },
     ) {
         syncTelemetry.syncs.forEach { syncInfo ->
-            // Note that `syncUuid` is configured to be submitted in all of the sync pings (it's set
-            // once, and will be attached by glean to history-sync, bookmarks-sync, and logins-sync pings).
-            // However, this only happens if sync telemetry is being submitted via [processSyncTelemetry].
-            // That is, if different data types were synchronized together, as happens when using a sync manager.
-            // We can then use 'syncUuid' to associate together all of the individual syncs that happened together.
-            // If a data type is synchronized individually via the legacy 'sync' API on specific storage layers,
-            // then the corresponding ping will not have 'syncUuid' set.
-            Sync.syncUuid.generateAndSet()
+            recordSync(
+                syncInfo.failureReason,
+                syncInfo.engines.map { BaseGleanSyncPing.fromEngineInfo(syncTelemetry.uid, it) },
+                submitGlobalPing,
+                submitHistoryPing,
+                submitBookmarksPing,
+                submitLoginsPing,
+                submitCreditCardsPing,
+                submitAddressesPing,
+                submitTabsPing,
+            )
+        }
+    }
 
-            // It's possible for us to sync some engines, and then get a hard error that fails the
-            // entire sync. Examples of such errors are an HTTP server error, token authentication
-            // error, or other kind of network error.
-            // We can have some engines that succeed (and others that fail, with different reasons)
-            // and still have a global failure_reason.
-            syncInfo.failureReason?.let { failureReason ->
-                recordFailureReason(failureReason, Sync.failureReason)
-            }
+    /**
+     * Process the [SyncPing] in a [SyncResult], which has the totals for each engine already
+     * assembled, so there's no need to parse [SyncResult.telemetryJson].
+     */
+    @Suppress("LongParameterList")
+    fun processSyncTelemetry(
+        syncPing: SyncPing,
+        submitGlobalPing: () -> Unit = { Pings.sync.submit() },
+        submitHistoryPing: () -> Unit = { Pings.historySync.submit() },
+        submitBookmarksPing: () -> Unit = { Pings.bookmarksSync.submit() },
+        submitLoginsPing: () -> Unit = { Pings.loginsSync.submit() },
+        submitCreditCardsPing: () -> Unit = { Pings.creditcardsSync.submit() },
+        submitAddressesPing: () -> Unit = { Pings.addressesSync.submit() },
+        submitTabsPing: () -> Unit = { Pings.tabsSync.submit() },
+    ) {
+        recordSync(
+            syncPing.failureReason?.let { BaseGleanSyncPing.failureReasonFrom(it) },
+            syncPing.engines.map { BaseGleanSyncPing.fromEngineSyncPing(syncPing.uid, it) },
+            submitGlobalPing,
+            submitHistoryPing,
+            submitBookmarksPing,
+            submitLoginsPing,
+            submitCreditCardsPing,
+            submitAddressesPing,
+            submitTabsPing,
+        )
+    }
+
+    @Suppress("LongParameterList")
+    private fun recordSync(
+        failureReason: FailureReason?,
+        engines: List<BaseGleanSyncPing>,
+        submitGlobalPing: () -> Unit,
+        submitHistoryPing: () -> Unit,
+        submitBookmarksPing: () -> Unit,
+        submitLoginsPing: () -> Unit,
+        submitCreditCardsPing: () -> Unit,
+        submitAddressesPing: () -> Unit,
+        submitTabsPing: () -> Unit,
+    ) {
+        // Note that `syncUuid` is configured to be submitted in all of the sync pings (it's set
+        // once, and will be attached by glean to history-sync, bookmarks-sync, and logins-sync pings).
+        // However, this only happens if sync telemetry is being submitted via [processSyncTelemetry].
+        // That is, if different data types were synchronized together, as happens when using a sync manager.
+        // We can then use 'syncUuid' to associate together all of the individual syncs that happened together.
+        // If a data type is synchronized individually via the legacy 'sync' API on specific storage layers,
+        // then the corresponding ping will not have 'syncUuid' set.
+        Sync.syncUuid.generateAndSet()
 
-            syncInfo.engines.forEach { engineInfo ->
-                when (engineInfo.name) {
-                    "bookmarks" -> {
-                        individualBookmarksSync(syncTelemetry.uid, engineInfo)
-                        submitBookmarksPing()
-                    }
-                    "history" -> {
-                        individualHistorySync(syncTelemetry.uid, engineInfo)
-                        submitHistoryPing()
-                    }
-                    "passwords" -> {
-                        individualLoginsSync(syncTelemetry.uid, engineInfo)
-                        submitLoginsPing()
-                    }
-                    "creditcards" -> {
-                        individualCreditCardsSync(syncTelemetry.uid, engineInfo)
-                        submitCreditCardsPing()
-                    }
-                    "addresses" -> {
-                        individualAddressesSync(syncTelemetry.uid, engineInfo)
-                        submitAddressesPing()
-                    }
-                    "tabs" -> {
-                        individualTabsSync(syncTelemetry.uid, engineInfo)
-                        submitTabsPing()
-                    }
-                    // TODO: fix
-                    // else -> logger.warn("Ignoring telemetry for engine ${engineInfo.name}")
+        // It's possible for us to sync some engines, and then get a hard error that fails the
+        // entire sync. Examples of such errors are an HTTP server error, token authentication
+        // error, or other kind of network error.
+        // We can have some engines that succeed (and others that fail, with different reasons)
+        // and still have a global failure_reason.
+        failureReason?.let {
+            recordFailureReason(it, Sync.failureReason)
+        }
+
+        engines.forEach { base ->
+            when (base.name) {
+                "bookmarks" -> {
+                    individualBookmarksSync(base)
+                    submitBookmarksPing()
+                }
+                "history" -> {
+                    individualHistorySync(base)
+                    submitHistoryPing()
                 }
+                "passwords" -> {
+                    individualLoginsSync(base)
+                    submitLoginsPing()
+                }
+                "creditcards" -> {
+                    individualCreditCardsSync(base)
+                    submitCreditCardsPing()
+                }
+                "addresses" -> {
+                    individualAddressesSync(base)
+                    submitAddressesPing()
+                }
+                "tabs" -> {
+                    individualTabsSync(base)
+                    submitTabsPing()
+                }
+                // TODO: fix
+                // else -> logger.warn("Ignoring telemetry for engine ${base.name}")
             }
-
-            submitGlobalPing()
         }
+
+        submitGlobalPing()
     }
 
     /**
@@ -131,7 +183,7 @@ object SyncTelemetry {
                 if (engine.name != "history") {
                     return@eachEngine
                 }
-                individualHistorySync(ping.uid, engine)
+                individualHistorySync(BaseGleanSyncPing.fromEngineInfo(ping.uid, engine))
                 sendPing()
             }
         }
@@ -157,7 +209,7 @@ object SyncTelemetry {
                 if (engine.name != "passwords") {
                     return@eachEngine
                 }
-                individualLoginsSync(ping.uid, engine)
+                individualLoginsSync(BaseGleanSyncPing.fromEngineInfo(ping.uid, engine))
                 sendPing()
             }
         }
@@ -189,7 +241,7 @@ object SyncTelemetry {
                 if (engine.name != "bookmarks") {
                     return@eachEngine
                 }
-                individualBookmarksSync(ping.uid, engine)
+                individualBookmarksSync(BaseGleanSyncPing.fromEngineInfo(ping.uid, engine))
                 sendPing()
             }
         }
@@ -197,12 +249,11 @@ object SyncTelemetry {
     }
 
     @Suppress("ComplexMethod")
-    private fun individualLoginsSync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "passwords") { "Expected 'passwords', got ${engineInfo.name}" }
+    private fun individualLoginsSync(base: BaseGleanSyncPing) {
+        require(base.name == "passwords") { "Expected 'passwords', got ${base.name}" }
 
         LoginsSync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -233,12 +284,11 @@ object SyncTelemetry {
     }
 
     @Suppress("ComplexMethod")
-    private fun individualBookmarksSync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "bookmarks") { "Expected 'bookmarks', got ${engineInfo.name}" }
+    private fun individualBookmarksSync(base: BaseGleanSyncPing) {
+        require(base.name == "bookmarks") { "Expected 'bookmarks', got ${base.name}" }
 
         BookmarksSync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -262,21 +312,18 @@ object SyncTelemetry {
             base.failureReason?.let {
                 recordFailureReason(it, failureReason)
             }
-            engineInfo.validation?.let {
-                it.problems.forEach { problemInfo ->
-                    remoteTreeProblems[problemInfo.name].add(problemInfo.count)
-                }
+            base.validationProblems.forEach { problemInfo ->
+                remoteTreeProblems[problemInfo.name].add(problemInfo.count)
             }
         }
     }
 
     @Suppress("ComplexMethod")
-    private fun individualHistorySync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "history") { "Expected 'history', got ${engineInfo.name}" }
+    private fun individualHistorySync(base: BaseGleanSyncPing) {
+        require(base.name == "history") { "Expected 'history', got ${base.name}" }
 
         HistorySync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -307,12 +354,11 @@ object SyncTelemetry {
     }
 
     @Suppress("ComplexMethod")
-    private fun individualCreditCardsSync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "creditcards") { "Expected 'creditcards', got ${engineInfo.name}" }
+    private fun individualCreditCardsSync(base: BaseGleanSyncPing) {
+        require(base.name == "creditcards") { "Expected 'creditcards', got ${base.name}" }
 
         CreditcardsSync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -343,12 +389,11 @@ object SyncTelemetry {
     }
 
     @Suppress("ComplexMethod")
-    private fun individualAddressesSync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "addresses") { "Expected 'addresses', got ${engineInfo.name}" }
+    private fun individualAddressesSync(base: BaseGleanSyncPing) {
+        require(base.name == "addresses") { "Expected 'addresses', got ${base.name}" }
 
         AddressesSync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -379,12 +424,11 @@ object SyncTelemetry {
     }
 
     @Suppress("ComplexMethod")
-    private fun individualTabsSync(hashedFxaUid: String, engineInfo: EngineInfo) {
-        require(engineInfo.name == "tabs") { "Expected 'tabs', got ${engineInfo.name}" }
+    private fun individualTabsSync(base: BaseGleanSyncPing) {
+        require(base.name == "tabs") { "Expected 'tabs', got ${base.name}" }
 
         TabsSync.apply {
-            val base = BaseGleanSyncPing.fromEngineInfo(hashedFxaUid, engineInfo)
-            uid.set(base.uid)
+            base.uid?.let { uid.set(it) }
             startedAt.set(base.startedAt)
             finishedAt.set(base.finishedAt)
             if (base.applied > 0) {
@@ -27,6 +27,7 @@ import org.junit.Rule
 import org.junit.Test
 import org.junit.runner.RunWith
 import org.mozilla.appservices.syncmanager.GleanMetrics.Pings
+import java.time.Instant
 import java.util.Date
 import java.util.UUID
 import org.mozilla.appservices.syncmanager.GleanMetrics.BookmarksSyncV2 as BookmarksSync
@@ -1149,6 +1150,89 @@ class SyncTelemetryTest {
         )
     }
 
+    @Test
+    fun `records telemetry from a sync ping`() {
+        val startedAt = Instant.ofEpochSecond(now)
+        SyncTelemetry.processSyncTelemetry(
+            SyncPing(
+                uid = "abc123",
+                startedAt = startedAt,
+                finishedAt = startedAt.plusSeconds(10),
+                failureReason = SyncFailureReason(SyncFailureKind.HTTP, null, 503u),
+                engines = listOf(
+                    EngineSyncPing(
+                        name = "bookmarks",
+                        startedAt = startedAt.plusSeconds(1),
+                        finishedAt = startedAt.plusSeconds(3),
+                        applied = 5u,
+                        failedToApply = 0u,
+                        reconciled = 1u,
+                        uploaded = 10u,
+                        failedToUpload = 2u,
+                        outgoingBatches = 1u,
+                        failureReason = null,
+                        validation = EngineValidation(
+                            version = 2u,
+                            problems = listOf(ValidationProblem("missingParents", 4u)),
+                            failureReason = null,
+                        ),
+                    ),
+                    EngineSyncPing(
+                        name = "history",
+                        startedAt = startedAt.plusSeconds(3),
+                        finishedAt = startedAt.plusSeconds(4),
+                        applied = 0u,
+                        failedToApply = 0u,
+                        reconciled = 0u,
+                        uploaded = 0u,
+                        failedToUpload = 0u,
+                        outgoingBatches = 0u,
+                        failureReason = SyncFailureReason(SyncFailureKind.OTHER, "Synergies not aligned", null),
+                        validation = null,
+                    ),
+                ),
+            ),
+            submitGlobalPing = {
+                Sync.apply {
+                    assertEquals("Unexpected error: 503", failureReason["unexpected"].testGetValue())
+                    assertNull(failureReason["other"].testGetValue())
+                }
+                Pings.sync.submit()
+                pingCount++
+            },
+            submitBookmarksPing = {
+                BookmarksSync.apply {
+                    assertEquals("abc123", uid.testGetValue())
+                    assertEquals(now + 1, startedAt.testGetValue()!!.asSeconds())
+                    assertEquals(now + 3, finishedAt.testGetValue()!!.asSeconds())
+                    assertEquals(5, incoming["applied"].testGetValue())
+                    assertNull(incoming["failed_to_apply"].testGetValue())
+                    assertEquals(1, incoming["reconciled"].testGetValue())
+                    assertEquals(10, outgoing["uploaded"].testGetValue())
+                    assertEquals(2, outgoing["failed_to_upload"].testGetValue())
+                    assertEquals(1, outgoingBatches.testGetValue())
+                    assertEquals(4, remoteTreeProblems["missingParents"].testGetValue())
+                    assertNotNull(Sync.syncUuid.testGetValue("bookmarks-sync"))
+                }
+                Pings.bookmarksSync.submit()
+                pingCount++
+            },
+            submitHistoryPing = {
+                HistorySync.apply {
+                    assertEquals("abc123", uid.testGetValue())
+                    assertNull(incoming["applied"].testGetValue())
+                    assertNull(outgoingBatches.testGetValue())
+                    assertEquals("Synergies not aligned", failureReason["other"].testGetValue())
+                    assertNotNull(Sync.syncUuid.testGetValue("history-sync"))
+                }
+                Pings.historySync.submit()
+                pingCount++
+            },
+        )
+
+        assertEquals(3, pingCount)
+    }
+
     @Test
     fun `checks sent tab telemetry records what it should`() {
         val json = """
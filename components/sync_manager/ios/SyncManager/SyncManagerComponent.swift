@@ -24,9 +24,8 @@ open class SyncManagerComponent {
     }
 
     public static func reportSyncTelemetry(syncResult: SyncResult) throws {
-        if let json = syncResult.telemetryJson {
-            let telemetry = try RustSyncTelemetryPing.fromJSONString(jsonObjectText: json)
-            try processSyncTelemetry(syncTelemetry: telemetry)
+        if let telemetry = syncResult.telemetry {
+            try processSyncTelemetry(syncPing: telemetry)
         }
     }
 }
@@ -7,11 +7,13 @@
 
 pub mod error;
 pub mod manager;
+mod telemetry;
 mod types;
 
 pub use sync15::DeviceType;
 
 pub use error::{Result, SyncManagerError};
+pub use telemetry::*;
 pub use types::*;
 
 use manager::SyncManager;
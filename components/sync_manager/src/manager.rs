@@ -3,7 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::error::*;
-use crate::types::{ServiceStatus, SyncEngineSelection, SyncParams, SyncReason, SyncResult};
+use crate::telemetry::SyncPing;
+use crate::types::{
+    KeysChanged, ServiceStatus, SyncEngineSelection, SyncParams, SyncReason, SyncResult,
+};
 use crate::{reset, reset_all, wipe};
 use error_support::breadcrumb;
 use parking_lot::Mutex;
@@ -17,6 +20,10 @@ use sync15::client::{
 use sync15::clients_engine::{Command, CommandProcessor, CommandStatus, Settings};
 use sync15::engine::{EngineSyncAssociation, SyncEngine, SyncEngineId};
 
+// The most engines we sync at once. Only engines with independent storage (eg, tabs, logins,
+// autofill) are synced in parallel; places engines are always synced one after the other.
+const MAX_PARALLEL_ENGINES: usize = 3;
+
 #[derive(Default)]
 pub struct SyncManager {
     mem_cached_state: Mutex<Option<MemoryCachedState>>,
@@ -105,6 +112,8 @@ impl SyncManager {
                 persisted_state: params.persisted_state.unwrap_or_default(),
                 // It would be nice to record telemetry here.
                 telemetry_json: None,
+                telemetry: None,
+                keys_changed: None,
             })
         };
         breadcrumb!("SyncManager sync ended");
@@ -160,6 +169,7 @@ impl SyncManager {
             Some(SyncRequestInfo {
                 engines_to_state_change: engines_to_change,
                 is_user_action: matches!(params.reason, SyncReason::User),
+                max_parallel_engines: MAX_PARALLEL_ENGINES,
             }),
         );
         *state = Some(mem_cached_state);
@@ -182,6 +192,7 @@ impl SyncManager {
             }
         }
         let telemetry_json = serde_json::to_string(&result.telemetry).unwrap();
+        let telemetry = SyncPing::from_telemetry(&result.telemetry);
 
         Ok(SyncResult {
             status,
@@ -191,6 +202,8 @@ impl SyncManager {
             next_sync_allowed_at: result.next_sync_after,
             persisted_state: disk_cached_state.unwrap_or_default(),
             telemetry_json: Some(telemetry_json),
+            telemetry,
+            keys_changed: result.keys_changed.map(Into::into),
         })
     }
 
@@ -280,6 +293,17 @@ impl From<sync15::client::ServiceStatus> for ServiceStatus {
     }
 }
 
+impl From<sync15::client::KeysChanged> for KeysChanged {
+    fn from(changed: sync15::client::KeysChanged) -> Self {
+        match changed {
+            sync15::client::KeysChanged::SyncKey => KeysChanged::SyncKey,
+            sync15::client::KeysChanged::CollectionKeys { reset_engines } => {
+                KeysChanged::CollectionKeys { reset_engines }
+            }
+        }
+    }
+}
+
 struct SyncClient(Settings);
 
 impl SyncClient {
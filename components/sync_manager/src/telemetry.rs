@@ -0,0 +1,220 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The sync telemetry ping, as we hand it to the app. sync15 records what happened in a
+//! `SyncTelemetryPing`; here we turn that into the totals and failure reasons which end up
+//! in the app's telemetry, so that each app doesn't need to do it.
+
+use std::time::SystemTime;
+use sync15::telemetry::{self, SyncFailure, SyncTelemetryPing};
+
+// The telemetry for a single sync.
+#[derive(Debug)]
+pub struct SyncPing {
+    // The hashed FxA uid, or None if the sync failed before we knew it.
+    pub uid: Option<String>,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    // Why the sync as a whole failed. Engines which failed record their own reason.
+    pub failure_reason: Option<SyncFailureReason>,
+    pub engines: Vec<EngineSyncPing>,
+}
+
+// The telemetry for one engine in a sync.
+#[derive(Debug)]
+pub struct EngineSyncPing {
+    pub name: String,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    // Incoming records which were applied to the local store.
+    pub applied: u32,
+    // Incoming records we failed to apply, including those which failed in previous syncs.
+    pub failed_to_apply: u32,
+    // Incoming records which were merged with a local change.
+    pub reconciled: u32,
+    pub uploaded: u32,
+    pub failed_to_upload: u32,
+    pub outgoing_batches: u32,
+    pub failure_reason: Option<SyncFailureReason>,
+    pub validation: Option<EngineValidation>,
+}
+
+#[derive(Debug)]
+pub struct EngineValidation {
+    pub version: u32,
+    pub problems: Vec<ValidationProblem>,
+    pub failure_reason: Option<SyncFailureReason>,
+}
+
+#[derive(Debug)]
+pub struct ValidationProblem {
+    pub name: String,
+    pub count: u32,
+}
+
+#[derive(Debug)]
+pub struct SyncFailureReason {
+    pub kind: SyncFailureKind,
+    // The error for `Other` and `Unexpected` failures, or where an `Auth` failure came from.
+    pub message: Option<String>,
+    // The HTTP status for `Http` failures.
+    pub code: Option<u16>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncFailureKind {
+    Shutdown,
+    Other,
+    Unexpected,
+    Auth,
+    Http,
+}
+
+impl SyncPing {
+    // Assemble the ping for the sync recorded in `ping`. `sync_multiple` records exactly one
+    // sync, but we take the last to be safe.
+    pub(crate) fn from_telemetry(ping: &SyncTelemetryPing) -> Option<Self> {
+        let sync = ping.get_syncs().last()?;
+        let when_took = sync.get_when_took()?;
+        Some(Self {
+            uid: ping.get_uid().map(str::to_string),
+            started_at: when_took.get_started_at(),
+            finished_at: when_took.get_finished_at(),
+            failure_reason: sync.get_failure().map(Into::into),
+            engines: sync
+                .get_engines()
+                .iter()
+                .filter_map(EngineSyncPing::from_telemetry)
+                .collect(),
+        })
+    }
+}
+
+impl EngineSyncPing {
+    fn from_telemetry(engine: &telemetry::Engine) -> Option<Self> {
+        let when_took = engine.get_when_took()?;
+        let incoming = engine.get_incoming().as_ref();
+        let outgoing = engine.get_outgoing();
+        Some(Self {
+            name: engine.get_name().to_string(),
+            started_at: when_took.get_started_at(),
+            finished_at: when_took.get_finished_at(),
+            applied: incoming.map_or(0, |i| i.get_applied()),
+            failed_to_apply: incoming.map_or(0, |i| i.get_failed() + i.get_new_failed()),
+            reconciled: incoming.map_or(0, |i| i.get_reconciled()),
+            uploaded: to_u32(outgoing.iter().map(|o| o.get_sent()).sum()),
+            failed_to_upload: to_u32(outgoing.iter().map(|o| o.get_failed()).sum()),
+            outgoing_batches: to_u32(outgoing.len()),
+            failure_reason: engine.get_failure().map(Into::into),
+            validation: engine.get_validation().map(|v| EngineValidation {
+                version: v.get_version(),
+                problems: v
+                    .get_problems()
+                    .iter()
+                    .map(|p| ValidationProblem {
+                        name: p.get_name().to_string(),
+                        count: to_u32(p.get_count()),
+                    })
+                    .collect(),
+                failure_reason: v.get_failure().map(Into::into),
+            }),
+        })
+    }
+}
+
+impl From<&SyncFailure> for SyncFailureReason {
+    fn from(failure: &SyncFailure) -> Self {
+        let (kind, message, code) = match failure {
+            SyncFailure::Shutdown => (SyncFailureKind::Shutdown, None, None),
+            SyncFailure::Other { error } => (SyncFailureKind::Other, Some(error.clone()), None),
+            SyncFailure::Unexpected { error } => {
+                (SyncFailureKind::Unexpected, Some(error.clone()), None)
+            }
+            SyncFailure::Auth { from } => (SyncFailureKind::Auth, Some(from.to_string()), None),
+            SyncFailure::Http { code } => (SyncFailureKind::Http, None, Some(*code)),
+        };
+        Self {
+            kind,
+            message,
+            code,
+        }
+    }
+}
+
+fn to_u32(n: usize) -> u32 {
+    u32::try_from(n).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sync15::telemetry::{EngineIncoming, EngineOutgoing, SyncTelemetry, Validation};
+
+    #[test]
+    fn test_sync_ping() {
+        let mut passwords = telemetry::Engine::new("passwords");
+        let mut incoming = EngineIncoming::new();
+        incoming.applied(3);
+        incoming.failed(1);
+        incoming.new_failed(2);
+        incoming.reconciled(1);
+        passwords.incoming(incoming);
+        for (sent, failed) in [(10, 0), (5, 1)] {
+            let mut outgoing = EngineOutgoing::new();
+            outgoing.sent(sent);
+            outgoing.failed(failed);
+            passwords.outgoing(outgoing);
+        }
+
+        let mut bookmarks = telemetry::Engine::new("bookmarks");
+        bookmarks.failure(SyncFailure::Http { code: 500 });
+        let mut validation = Validation::with_version(2);
+        validation.problem("orphans", 4);
+        bookmarks.validation(validation);
+
+        let mut sync = SyncTelemetry::new();
+        sync.engine(passwords);
+        sync.engine(bookmarks);
+        sync.failure(SyncFailure::Auth { from: "storage" });
+        let mut ping = SyncTelemetryPing::new();
+        ping.uid("hashed-uid".into());
+        ping.sync(sync);
+
+        let sync_ping = SyncPing::from_telemetry(&ping).unwrap();
+        assert_eq!(sync_ping.uid.as_deref(), Some("hashed-uid"));
+        assert!(sync_ping.finished_at >= sync_ping.started_at);
+        let failure = sync_ping.failure_reason.unwrap();
+        assert_eq!(failure.kind, SyncFailureKind::Auth);
+        assert_eq!(failure.message.as_deref(), Some("storage"));
+        assert_eq!(sync_ping.engines.len(), 2);
+
+        let passwords = &sync_ping.engines[0];
+        assert_eq!(passwords.name, "passwords");
+        assert_eq!(passwords.applied, 3);
+        assert_eq!(passwords.failed_to_apply, 3);
+        assert_eq!(passwords.reconciled, 1);
+        assert_eq!(passwords.uploaded, 15);
+        assert_eq!(passwords.failed_to_upload, 1);
+        assert_eq!(passwords.outgoing_batches, 2);
+        assert!(passwords.failure_reason.is_none());
+        assert!(passwords.validation.is_none());
+
+        let bookmarks = &sync_ping.engines[1];
+        assert_eq!(bookmarks.applied, 0);
+        assert_eq!(bookmarks.outgoing_batches, 0);
+        let failure = bookmarks.failure_reason.as_ref().unwrap();
+        assert_eq!(failure.kind, SyncFailureKind::Http);
+        assert_eq!(failure.code, Some(500));
+        let validation = bookmarks.validation.as_ref().unwrap();
+        assert_eq!(validation.version, 2);
+        assert_eq!(validation.problems.len(), 1);
+        assert_eq!(validation.problems[0].name, "orphans");
+        assert_eq!(validation.problems[0].count, 4);
+    }
+
+    #[test]
+    fn test_no_sync() {
+        assert!(SyncPing::from_telemetry(&SyncTelemetryPing::new()).is_none());
+    }
+}
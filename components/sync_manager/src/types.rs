@@ -1,3 +1,4 @@
+use crate::SyncPing;
 use std::collections::HashMap;
 use std::time::SystemTime;
 use sync15::DeviceType;
@@ -81,6 +82,21 @@ pub struct SyncResult {
     pub next_sync_allowed_at: Option<SystemTime>,
     // JSON string encoding a `SyncTelemetryPing` object
     pub telemetry_json: Option<String>,
+    // The telemetry for this sync, ready to be recorded, or None if we didn't sync.
+    pub telemetry: Option<SyncPing>,
+    // Set if the keys used to encrypt records changed since the last sync.
+    pub keys_changed: Option<KeysChanged>,
+}
+
+// Why the keys used to encrypt records changed since the last sync.
+#[derive(Debug)]
+pub enum KeysChanged {
+    // The sync key changed, eg, because the user reset their password. Every
+    // engine was reset.
+    SyncKey,
+    // Another device changed the keys for some collections. The engines for
+    // those collections were reset.
+    CollectionKeys { reset_engines: Vec<String> },
 }
 
 #[derive(Debug)]
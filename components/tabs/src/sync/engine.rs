@@ -307,6 +307,10 @@ impl SyncEngine for TabsEngine {
             EngineSyncAssociation::Disconnected
         })
     }
+
+    fn as_parallel(&self) -> Option<&(dyn SyncEngine + Sync)> {
+        Some(self)
+    }
 }
 
 impl crate::TabsStore {
@@ -385,6 +385,8 @@ pub mod consts {
         (USER_AGENT, "user-agent"),
         // non-standard, but it's convenient to have these.
         (RETRY_AFTER, "retry-after"),
+        (X_BACKOFF, "x-backoff"),
+        (X_IF_MODIFIED_SINCE, "x-if-modified-since"),
         (X_IF_UNMODIFIED_SINCE, "x-if-unmodified-since"),
         (X_KEYID, "x-keyid"),
         (X_LAST_MODIFIED, "x-last-modified"),
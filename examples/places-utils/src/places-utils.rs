@@ -171,12 +171,9 @@ fn run_native_export(db: &PlacesDb, filename: String) -> Result<()> {
 
 fn run_maintenance(conn: &PlacesDb, db_size_limit: u32, count: u32) -> Result<()> {
     for _ in 0..count {
-        let prune_metrics = places::storage::run_maintenance_prune(conn, db_size_limit, 6)?;
-        places::storage::run_maintenance_vacuum(conn)?;
-        places::storage::run_maintenance_optimize(conn)?;
-        places::storage::run_maintenance_checkpoint(conn)?;
+        let metrics = places::storage::run_maintenance(conn, db_size_limit)?;
         println!("Maintenance complete");
-        println!("Prune metrics: {prune_metrics:?}");
+        println!("Metrics: {metrics:?}");
     }
     Ok(())
 }
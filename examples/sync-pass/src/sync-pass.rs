@@ -48,6 +48,7 @@ fn read_login() -> LoginEntry {
 fn read_form_based_login() -> LoginEntry {
     let username = prompt_string("username").unwrap_or_default();
     let password = prompt_string("password").unwrap_or_default();
+    let notes = prompt_string("notes");
     let form_action_origin = prompt_string("form_action_origin (example: https://www.example.com)");
     let origin = prompt_string("origin (example: https://www.example.com)").unwrap_or_default();
     let username_field = prompt_string("username_field").unwrap_or_default();
@@ -60,13 +61,18 @@ fn read_form_based_login() -> LoginEntry {
             http_realm: None,
             origin,
         },
-        sec_fields: SecureLoginFields { username, password },
+        sec_fields: SecureLoginFields {
+            username,
+            password,
+            notes,
+        },
     }
 }
 
 fn read_auth_based_login() -> LoginEntry {
     let username = prompt_string("username").unwrap_or_default();
     let password = prompt_string("password").unwrap_or_default();
+    let notes = prompt_string("notes");
     let origin = prompt_string("origin (example: https://www.example.com)").unwrap_or_default();
     let http_realm = prompt_string("http_realm (example: My Auth Realm)");
     let username_field = prompt_string("username_field").unwrap_or_default();
@@ -79,7 +85,11 @@ fn read_auth_based_login() -> LoginEntry {
             http_realm,
             origin,
         },
-        sec_fields: SecureLoginFields { username, password },
+        sec_fields: SecureLoginFields {
+            username,
+            password,
+            notes,
+        },
     }
 }
 
@@ -100,6 +110,13 @@ fn update_encrypted_fields(fields: &mut SecureLoginFields, extra: &str) {
     if let Some(v) = prompt_string(format!("new password [now {}{}]", fields.password, extra)) {
         fields.password = v;
     };
+    if let Some(v) = prompt_string(format!(
+        "new notes [now {}{}]",
+        fields.notes.as_deref().unwrap_or_default(),
+        extra
+    )) {
+        fields.notes = Some(v);
+    };
 }
 
 fn string_opt(o: &Option<String>) -> Option<&str> {
@@ -14,44 +14,68 @@ class SyncManagerTelemetryTests: XCTestCase {
         super.setUp()
         Glean.shared.resetGlean(clearStores: true)
         Glean.shared.enableTestingMode()
-        now = Int64(Date().timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC
+        now = Int64(Date().timeIntervalSince1970)
+    }
+
+    // The pings only record whole seconds in these tests, so they're easy to compare.
+    private func date(_ seconds: Int64) -> Date {
+        return Date(timeIntervalSince1970: TimeInterval(seconds))
+    }
+
+    private func seconds(_ date: Date?) -> Int64? {
+        return date.map { Int64($0.timeIntervalSince1970) }
+    }
+
+    private func engineSyncPing(name: String,
+                                startedAt: Int64,
+                                took: Int64,
+                                applied: UInt32 = 0,
+                                failedToApply: UInt32 = 0,
+                                reconciled: UInt32 = 0,
+                                uploaded: UInt32 = 0,
+                                failedToUpload: UInt32 = 0,
+                                outgoingBatches: UInt32 = 0,
+                                validation: EngineValidation? = nil) -> EngineSyncPing
+    {
+        return EngineSyncPing(name: name,
+                              startedAt: date(startedAt),
+                              finishedAt: date(startedAt + took),
+                              applied: applied,
+                              failedToApply: failedToApply,
+                              reconciled: reconciled,
+                              uploaded: uploaded,
+                              failedToUpload: failedToUpload,
+                              outgoingBatches: outgoingBatches,
+                              failureReason: nil,
+                              validation: validation)
     }
 
     func testSendsLoginsHistoryAndGlobalPings() {
         var globalSyncUuid = UUID()
-        let syncTelemetry = RustSyncTelemetryPing(version: 1,
-                                                  uid: "abc123",
-                                                  events: [],
-                                                  syncs: [SyncInfo(at: now,
-                                                                   took: 10000,
-                                                                   engines: [EngineInfo(name: "passwords",
-                                                                                        at: now,
-                                                                                        took: 5000,
-                                                                                        incoming: IncomingInfo(applied: 5,
-                                                                                                               failed: 4,
-                                                                                                               newFailed: 3,
-                                                                                                               reconciled: 2),
-                                                                                        outgoing: [OutgoingInfo(sent: 10,
-                                                                                                                failed: 5),
-                                                                                                   OutgoingInfo(sent: 4,
-                                                                                                                failed: 2)],
-                                                                                        failureReason: nil,
-                                                                                        validation: nil),
-                                                                             EngineInfo(name: "history",
-                                                                                        at: now,
-                                                                                        took: 5000,
-                                                                                        incoming: IncomingInfo(applied: 5,
-                                                                                                               failed: 4,
-                                                                                                               newFailed: 3,
-                                                                                                               reconciled: 2),
-                                                                                        outgoing: [OutgoingInfo(sent: 10,
-                                                                                                                failed: 5),
-                                                                                                   OutgoingInfo(sent: 4,
-                                                                                                                failed: 2)],
-                                                                                        failureReason: nil,
-                                                                                        validation: nil)],
-                                                                   failureReason: FailureReason(name: FailureName.unknown,
-                                                                                                message: "Synergies not aligned"))])
+        let syncPing = SyncPing(uid: "abc123",
+                                startedAt: date(now),
+                                finishedAt: date(now + 10),
+                                failureReason: SyncFailureReason(kind: .other,
+                                                                 message: "Synergies not aligned",
+                                                                 code: nil),
+                                engines: [engineSyncPing(name: "passwords",
+                                                         startedAt: now,
+                                                         took: 5,
+                                                         applied: 5,
+                                                         failedToApply: 7,
+                                                         reconciled: 2,
+                                                         uploaded: 14,
+                                                         failedToUpload: 7,
+                                                         outgoingBatches: 2),
+                                          engineSyncPing(name: "history",
+                                                         startedAt: now,
+                                                         took: 5,
+                                                         applied: 5,
+                                                         failedToApply: 7,
+                                                         reconciled: 2,
+                                                         uploaded: 14,
+                                                         failedToUpload: 7,
+                                                         outgoingBatches: 2)])
 
         func submitGlobalPing(_: NoReasonCodes?) {
             XCTAssertEqual("Synergies not aligned", SyncMetrics.failureReason["other"].testGetValue())
@@ -63,10 +87,8 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("history-sync")!
             XCTAssertEqual("abc123", HistoryMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(HistoryMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(HistoryMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now, Int64(HistoryMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 5, Int64(HistoryMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now, seconds(HistoryMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 5, seconds(HistoryMetrics.finishedAt.testGetValue()))
 
             XCTAssertEqual(5, HistoryMetrics.incoming["applied"].testGetValue())
             XCTAssertEqual(7, HistoryMetrics.incoming["failed_to_apply"].testGetValue())
@@ -80,10 +102,8 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("logins-sync")!
             XCTAssertEqual("abc123", LoginsMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(LoginsMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(LoginsMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now, Int64(LoginsMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 5, Int64(LoginsMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now, seconds(LoginsMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 5, seconds(LoginsMetrics.finishedAt.testGetValue()))
 
             XCTAssertEqual(5, LoginsMetrics.incoming["applied"].testGetValue())
             XCTAssertEqual(7, LoginsMetrics.incoming["failed_to_apply"].testGetValue())
@@ -93,7 +113,7 @@ class SyncManagerTelemetryTests: XCTestCase {
             XCTAssertEqual(2, LoginsMetrics.outgoingBatches.testGetValue())
         }
 
-        try! processSyncTelemetry(syncTelemetry: syncTelemetry,
+        try! processSyncTelemetry(syncPing: syncPing,
                                   submitGlobalPing: submitGlobalPing,
                                   submitHistoryPing: submitHistoryPing,
                                   submitLoginsPing: submitLoginsPing)
@@ -101,19 +121,13 @@ class SyncManagerTelemetryTests: XCTestCase {
 
     func testSendsHistoryAndGlobalPings() {
         var globalSyncUuid = UUID()
-        let syncTelemetry = RustSyncTelemetryPing(version: 1,
-                                                  uid: "abc123",
-                                                  events: [],
-                                                  syncs: [SyncInfo(at: now + 10,
-                                                                   took: 5000,
-                                                                   engines: [EngineInfo(name: "history",
-                                                                                        at: now + 10,
-                                                                                        took: 5000,
-                                                                                        incoming: nil,
-                                                                                        outgoing: [],
-                                                                                        failureReason: nil,
-                                                                                        validation: nil)],
-                                                                   failureReason: nil)])
+        let syncPing = SyncPing(uid: "abc123",
+                                startedAt: date(now + 10),
+                                finishedAt: date(now + 15),
+                                failureReason: nil,
+                                engines: [engineSyncPing(name: "history",
+                                                         startedAt: now + 10,
+                                                         took: 5)])
 
         func submitGlobalPing(_: NoReasonCodes?) {
             XCTAssertNil(SyncMetrics.failureReason["other"].testGetValue())
@@ -125,10 +139,8 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("history-sync")!
             XCTAssertEqual("abc123", HistoryMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(HistoryMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(HistoryMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now + 10, Int64(HistoryMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 15, Int64(HistoryMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now + 10, seconds(HistoryMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 15, seconds(HistoryMetrics.finishedAt.testGetValue()))
 
             XCTAssertNil(HistoryMetrics.incoming["applied"].testGetValue())
             XCTAssertNil(HistoryMetrics.incoming["failed_to_apply"].testGetValue())
@@ -138,31 +150,28 @@ class SyncManagerTelemetryTests: XCTestCase {
             XCTAssertNil(HistoryMetrics.outgoingBatches.testGetValue())
         }
 
-        try! processSyncTelemetry(syncTelemetry: syncTelemetry,
+        try! processSyncTelemetry(syncPing: syncPing,
                                   submitGlobalPing: submitGlobalPing,
                                   submitHistoryPing: submitHistoryPing)
     }
 
     func testSendsBookmarksAndGlobalPings() {
         var globalSyncUuid = UUID()
-        let syncTelemetry = RustSyncTelemetryPing(version: 1,
-                                                  uid: "abc123",
-                                                  events: [],
-                                                  syncs: [SyncInfo(at: now + 20,
-                                                                   took: 8000,
-                                                                   engines: [EngineInfo(name: "bookmarks",
-                                                                                        at: now + 25,
-                                                                                        took: 6000,
-                                                                                        incoming: nil,
-                                                                                        outgoing: [OutgoingInfo(sent: 10, failed: 5)],
-                                                                                        failureReason: nil,
-                                                                                        validation: ValidationInfo(version: 2,
-                                                                                                                   problems: [ProblemInfo(name: "missingParents",
-                                                                                                                                          count: 5),
-                                                                                                                              ProblemInfo(name: "missingChildren",
-                                                                                                                                          count: 7)],
-                                                                                                                   failureReason: nil))],
-                                                                   failureReason: nil)])
+        let validation = EngineValidation(version: 2,
+                                          problems: [ValidationProblem(name: "missingParents", count: 5),
+                                                     ValidationProblem(name: "missingChildren", count: 7)],
+                                          failureReason: nil)
+        let syncPing = SyncPing(uid: "abc123",
+                                startedAt: date(now + 20),
+                                finishedAt: date(now + 28),
+                                failureReason: nil,
+                                engines: [engineSyncPing(name: "bookmarks",
+                                                         startedAt: now + 25,
+                                                         took: 6,
+                                                         uploaded: 10,
+                                                         failedToUpload: 5,
+                                                         outgoingBatches: 1,
+                                                         validation: validation)])
 
         func submitGlobalPing(_: NoReasonCodes?) {
             XCTAssertNil(SyncMetrics.failureReason["other"].testGetValue())
@@ -174,10 +183,8 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("bookmarks-sync")!
             XCTAssertEqual("abc123", BookmarksMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(BookmarksMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(BookmarksMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now + 25, Int64(BookmarksMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 31, Int64(BookmarksMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now + 25, seconds(BookmarksMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 31, seconds(BookmarksMetrics.finishedAt.testGetValue()))
 
             XCTAssertNil(BookmarksMetrics.incoming["applied"].testGetValue())
             XCTAssertNil(BookmarksMetrics.incoming["failed_to_apply"].testGetValue())
@@ -185,38 +192,32 @@ class SyncManagerTelemetryTests: XCTestCase {
             XCTAssertEqual(10, BookmarksMetrics.outgoing["uploaded"].testGetValue())
             XCTAssertEqual(5, BookmarksMetrics.outgoing["failed_to_upload"].testGetValue())
             XCTAssertEqual(1, BookmarksMetrics.outgoingBatches.testGetValue())
+            XCTAssertEqual(5, BookmarksMetrics.remoteTreeProblems["missingParents"].testGetValue())
+            XCTAssertEqual(7, BookmarksMetrics.remoteTreeProblems["missingChildren"].testGetValue())
         }
 
-        try! processSyncTelemetry(syncTelemetry: syncTelemetry,
+        try! processSyncTelemetry(syncPing: syncPing,
                                   submitGlobalPing: submitGlobalPing,
                                   submitBookmarksPing: submitBookmarksPing)
     }
 
     func testSendsTabsCreditCardsAndGlobalPings() {
         var globalSyncUuid = UUID()
-        let syncTelemetry = RustSyncTelemetryPing(version: 1,
-                                                  uid: "abc123",
-                                                  events: [],
-                                                  syncs: [SyncInfo(at: now + 30,
-                                                                   took: 10000,
-                                                                   engines: [EngineInfo(name: "tabs",
-                                                                                        at: now + 10,
-                                                                                        took: 6000,
-                                                                                        incoming: nil,
-                                                                                        outgoing: [OutgoingInfo(sent: 8, failed: 2)],
-                                                                                        failureReason: nil,
-                                                                                        validation: nil),
-                                                                             EngineInfo(name: "creditcards",
-                                                                                        at: now + 15,
-                                                                                        took: 4000,
-                                                                                        incoming: IncomingInfo(applied: 3,
-                                                                                                               failed: 1,
-                                                                                                               newFailed: 1,
-                                                                                                               reconciled: 0),
-                                                                                        outgoing: [],
-                                                                                        failureReason: nil,
-                                                                                        validation: nil)],
-                                                                   failureReason: nil)])
+        let syncPing = SyncPing(uid: "abc123",
+                                startedAt: date(now + 30),
+                                finishedAt: date(now + 40),
+                                failureReason: nil,
+                                engines: [engineSyncPing(name: "tabs",
+                                                         startedAt: now + 10,
+                                                         took: 6,
+                                                         uploaded: 8,
+                                                         failedToUpload: 2,
+                                                         outgoingBatches: 1),
+                                          engineSyncPing(name: "creditcards",
+                                                         startedAt: now + 15,
+                                                         took: 4,
+                                                         applied: 3,
+                                                         failedToApply: 2)])
 
         func submitGlobalPing(_: NoReasonCodes?) {
             XCTAssertNil(SyncMetrics.failureReason["other"].testGetValue())
@@ -228,16 +229,14 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("creditcards-sync")!
             XCTAssertEqual("abc123", CreditcardsMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(CreditcardsMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(CreditcardsMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now + 15, Int64(CreditcardsMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 19, Int64(CreditcardsMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now + 15, seconds(CreditcardsMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 19, seconds(CreditcardsMetrics.finishedAt.testGetValue()))
 
             XCTAssertEqual(3, CreditcardsMetrics.incoming["applied"].testGetValue())
             XCTAssertEqual(2, CreditcardsMetrics.incoming["failed_to_apply"].testGetValue())
             XCTAssertNil(CreditcardsMetrics.incoming["reconciled"].testGetValue())
-            XCTAssertNil(HistoryMetrics.outgoing["uploaded"].testGetValue())
-            XCTAssertNil(HistoryMetrics.outgoing["failed_to_upload"].testGetValue())
+            XCTAssertNil(CreditcardsMetrics.outgoing["uploaded"].testGetValue())
+            XCTAssertNil(CreditcardsMetrics.outgoing["failed_to_upload"].testGetValue())
             XCTAssertNil(CreditcardsMetrics.outgoingBatches.testGetValue())
         }
 
@@ -245,10 +244,8 @@ class SyncManagerTelemetryTests: XCTestCase {
             globalSyncUuid = SyncMetrics.syncUuid.testGetValue("tabs-sync")!
             XCTAssertEqual("abc123", TabsMetrics.uid.testGetValue())
 
-            XCTAssertNotNil(TabsMetrics.startedAt.testGetValue())
-            XCTAssertNotNil(TabsMetrics.finishedAt.testGetValue())
-            XCTAssertEqual(now + 10, Int64(TabsMetrics.startedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
-            XCTAssertEqual(now + 16, Int64(TabsMetrics.finishedAt.testGetValue()!.timeIntervalSince1970) / BaseGleanSyncPing.MILLIS_PER_SEC)
+            XCTAssertEqual(now + 10, seconds(TabsMetrics.startedAt.testGetValue()))
+            XCTAssertEqual(now + 16, seconds(TabsMetrics.finishedAt.testGetValue()))
 
             XCTAssertNil(TabsMetrics.incoming["applied"].testGetValue())
             XCTAssertNil(TabsMetrics.incoming["failed_to_apply"].testGetValue())
@@ -257,7 +254,7 @@ class SyncManagerTelemetryTests: XCTestCase {
             XCTAssertEqual(2, TabsMetrics.outgoing["failed_to_upload"].testGetValue())
         }
 
-        try! processSyncTelemetry(syncTelemetry: syncTelemetry,
+        try! processSyncTelemetry(syncPing: syncPing,
                                   submitGlobalPing: submitGlobalPing,
                                   submitCreditCardsPing: submitCreditCardsPing,
                                   submitTabsPing: submitTabsPing)
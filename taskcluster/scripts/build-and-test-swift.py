@@ -146,7 +146,7 @@ def generate_glean_metrics(args):
     out_dir = args.out_dir / 'all' / 'Generated' / 'Metrics'
     focus_out_dir = args.out_dir / 'focus' / 'Generated' / 'Metrics'
     focus_glean_files = map(str, [ROOT_DIR / "components/nimbus/metrics.yaml"])
-    firefox_glean_files = map(str, [ROOT_DIR / "components/nimbus/metrics.yaml", ROOT_DIR / "components/sync_manager/metrics.yaml", ROOT_DIR / "components/sync_manager/pings.yaml"])
+    firefox_glean_files = map(str, [ROOT_DIR / "components/nimbus/metrics.yaml", ROOT_DIR / "components/places/metrics.yaml", ROOT_DIR / "components/sync_manager/metrics.yaml", ROOT_DIR / "components/sync_manager/pings.yaml"])
     generate_glean_metrics_for_target(env, glean_script, out_dir, firefox_glean_files)
     generate_glean_metrics_for_target(env, glean_script, focus_out_dir, focus_glean_files)
 
@@ -0,0 +1,59 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Shutdown is process-wide, so these tests get a binary of their own, rather
+// than running alongside the other integration tests.
+
+use places::api::places_api::{places_shutdown, ConnectionType, ShutdownPhase};
+use places::{Error, PlacesApi};
+
+#[test]
+fn test_shutdown_phases() {
+    let _ = env_logger::try_init();
+    let api = PlacesApi::new_memory("test-shutdown-phases").expect("should get an API");
+    let writer = api
+        .open_connection(ConnectionType::ReadWrite)
+        .expect("should get writer");
+    let reader = api
+        .open_connection(ConnectionType::ReadOnly)
+        .expect("should get reader");
+    let sync_conn = api
+        .get_sync_connection()
+        .expect("should get sync connection");
+    let writer_scope = writer.begin_interrupt_scope().unwrap();
+    let reader_scope = reader.begin_interrupt_scope().unwrap();
+    let sync_scope = sync_conn.begin_interrupt_scope().unwrap();
+
+    // Syncs are abandoned first.
+    places_shutdown(ShutdownPhase::Background);
+    assert!(sync_scope.was_interrupted());
+    assert!(!reader_scope.was_interrupted());
+    assert!(matches!(
+        sync_conn.begin_interrupt_scope(),
+        Err(Error::InterruptedError(_))
+    ));
+    assert!(matches!(
+        api.get_sync_connection(),
+        Err(Error::InterruptedError(_))
+    ));
+    assert!(reader.begin_interrupt_scope().is_ok());
+
+    // Then queries.
+    places_shutdown(ShutdownPhase::Read);
+    assert!(reader_scope.was_interrupted());
+    assert!(!writer_scope.was_interrupted());
+    assert!(matches!(
+        api.open_connection(ConnectionType::ReadOnly),
+        Err(Error::InterruptedError(_))
+    ));
+    assert!(writer.begin_interrupt_scope().is_ok());
+
+    // And writes last.
+    places_shutdown(ShutdownPhase::Write);
+    assert!(writer_scope.was_interrupted());
+    assert!(matches!(
+        writer.begin_interrupt_scope(),
+        Err(Error::InterruptedError(_))
+    ));
+}